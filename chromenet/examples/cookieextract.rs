@@ -9,6 +9,7 @@
 //! if the browser is running.
 
 use chromenet::cookies::browser::{Browser, BrowserCookieReader};
+use chromenet::cookies::error::CookieExtractionError;
 
 fn main() {
     println!("=== Browser Cookie Extraction Example ===\n");
@@ -55,8 +56,8 @@ fn print_browser_cookies(browser: Browser, domain_filter: Option<&str>) {
         }
     }
 
-    // Try to read cookies using the v2 API (better error handling)
-    match reader.read_cookies_v2() {
+    // Try to read cookies
+    match reader.read_cookies() {
         Ok(cookies) => {
             println!("  Found {} cookies", cookies.len());
 
@@ -79,9 +80,12 @@ fn print_browser_cookies(browser: Browser, domain_filter: Option<&str>) {
                 println!("    ... and {} more", cookies.len() - 5);
             }
         }
-        Err(e) => {
-            println!("  Error: {:?}", e);
-        }
+        Err(e) => match CookieExtractionError::from(e) {
+            CookieExtractionError::DatabaseLocked => {
+                println!("  Cookie database locked, close the browser and retry");
+            }
+            e => println!("  Error ({}): {}", e.code(), e),
+        },
     }
 
     println!();
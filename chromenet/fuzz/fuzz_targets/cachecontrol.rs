@@ -0,0 +1,9 @@
+//! `Cache-Control` response header values.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    chromenet::fuzzing::parse_cache_control(data);
+});
@@ -0,0 +1,12 @@
+//! Chunked transfer-coded HTTP/1.1 response bodies.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let _ = rt.block_on(chromenet::fuzzing::read_chunked_body(data));
+});
@@ -0,0 +1,16 @@
+//! `WWW-Authenticate: Digest` challenges, then answering them.
+
+#![no_main]
+
+use chromenet::http::digestauth::DigestAuthHandler;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(challenge) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(mut handler) = DigestAuthHandler::parse_challenge(challenge) {
+        let _ = handler.generate_auth_token("GET", "/path?q=1", "user", "pass");
+        let _ = handler.is_stale();
+    }
+});
@@ -0,0 +1,13 @@
+//! Netscape `cookies.txt` files, imported and exported again.
+
+#![no_main]
+
+use chromenet::cookies::monster::CookieMonster;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let content = String::from_utf8_lossy(data);
+    let jar = CookieMonster::new();
+    jar.import_netscape(&content);
+    let _ = jar.export_netscape(None);
+});
@@ -0,0 +1,10 @@
+//! TLS-encoded SignedCertificateTimestampList from a server.
+
+#![no_main]
+
+use chromenet::tls::decode_sct_list;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_sct_list(data);
+});
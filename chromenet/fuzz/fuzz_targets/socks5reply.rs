@@ -0,0 +1,12 @@
+//! Bytes a SOCKS5 proxy sends back during the handshake.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let _ = rt.block_on(chromenet::fuzzing::socks5_handshake(data));
+});
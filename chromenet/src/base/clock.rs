@@ -0,0 +1,104 @@
+//! Wall clock used for expiry.
+//!
+//! Chromium equivalent: `base::Clock`, `base::DefaultClock` and
+//! `base::SimpleTestClock`
+//!
+//! Cache freshness, cookie expiry, HSTS max-age and SCT timestamps are
+//! checked against a [`Clock`] instead of the system time, so a test can
+//! hand [`HttpCache`](crate::http::HttpCache),
+//! [`CookieMonster`](crate::cookies::monster::CookieMonster),
+//! [`HstsStore`](crate::tls::HstsStore) and
+//! [`MultiLogCtVerifier`](crate::tls::ctverifier::MultiLogCtVerifier) a
+//! [`MockClock`] and move time forward instead of sleeping.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Source of the current time.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The system's wall clock, the default everywhere.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Shared handle to the system clock.
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Clock that only moves when told to.
+///
+/// Clones share the time, so a test keeps one and hands the other to the
+/// store under test.
+///
+/// ```rust,ignore
+/// use chromenet::base::clock::MockClock;
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new(std::time::SystemTime::now());
+/// let hsts = HstsStore::new().with_clock(Arc::new(clock.clone()));
+/// hsts.add_from_header("example.com", "max-age=60");
+/// clock.advance(Duration::from_secs(61));
+/// assert!(!hsts.should_upgrade("example.com"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl MockClock {
+    /// A clock stopped at `start`.
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    /// Move the clock to `now`, backwards included.
+    pub fn set(&self, now: SystemTime) {
+        *self.lock() = now;
+    }
+
+    /// Move the clock forward by `delta`.
+    pub fn advance(&self, delta: Duration) {
+        *self.lock() += delta;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SystemTime> {
+        self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_shared_between_clones() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let clock = MockClock::new(start);
+        let shared: Arc<dyn Clock> = Arc::new(clock.clone());
+        assert_eq!(shared.now(), start);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(shared.now(), start + Duration::from_secs(5));
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+}
@@ -0,0 +1,493 @@
+//! Stable table of [`NetError`] codes.
+//!
+//! Chromium mapping: `net_error_list.h`, `net::ErrorToShortString`
+//!
+//! FFI callers and log pipelines only see the `i32` from
+//! [`NetError::as_i32`]. [`lookup`] turns one back into a name, category
+//! and whether the request may be retried, without relying on the enum's
+//! `Debug` output:
+//!
+//! ```
+//! use chromenet::base::errorcodes::{self, ErrorCategory};
+//!
+//! let code = errorcodes::lookup(-102).unwrap();
+//! assert_eq!(code.name, "CONNECTION_REFUSED");
+//! assert_eq!(code.category, ErrorCategory::Connection);
+//! assert!(!code.retryable);
+//! ```
+//!
+//! Names are the variant name in upper snake case, which for Chromium's
+//! codes is their `net_error_list.h` name in almost every case. Names and
+//! codes never change once listed. Context variants such as
+//! `ConnectionFailedTo` share the code of their plain variant and are not
+//! listed separately.
+//!
+//! The table, [`NetError::as_i32`] and `From<i32> for NetError` are
+//! generated from the one list in this file.
+//!
+//! `retryable` matches [`RetryReason::from_error`]: the connection failed
+//! in a way that retrying on a fresh connection may fix.
+//!
+//! [`RetryReason::from_error`]: crate::http::retry::RetryReason::from_error
+
+use crate::base::neterror::NetError;
+use crate::cookies::error::CookieExtractionError;
+
+/// Range of codes a [`NetErrorCode`] belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// -1 to -99.
+    Generic,
+    /// -100 to -199, including TLS handshake errors.
+    Connection,
+    /// -300 to -399, including HTTP/2 and QUIC.
+    Http,
+    /// -800 to -899.
+    Dns,
+    /// -10020 to -10029, extracting cookies from a browser. Cookies
+    /// rejected when set are [`Client`](Self::Client) errors.
+    Cookie,
+    /// Other codes from -10000, chromenet's own errors.
+    Client,
+}
+
+impl ErrorCategory {
+    const fn of(code: i32) -> Self {
+        match code {
+            -99..=-1 => Self::Generic,
+            -199..=-100 => Self::Connection,
+            -399..=-300 => Self::Http,
+            -899..=-800 => Self::Dns,
+            -10029..=-10020 => Self::Cookie,
+            _ => Self::Client,
+        }
+    }
+}
+
+/// One entry of [`NET_ERROR_CODES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetErrorCode {
+    /// The value of [`NetError::as_i32`].
+    pub code: i32,
+    /// Stable name, e.g. `CONNECTION_REFUSED`.
+    pub name: &'static str,
+    pub category: ErrorCategory,
+    /// Whether the request may succeed if retried on a new connection.
+    pub retryable: bool,
+}
+
+const fn entry(code: i32, name: &'static str, retryable: bool) -> NetErrorCode {
+    NetErrorCode {
+        code,
+        name,
+        category: ErrorCategory::of(code),
+        retryable,
+    }
+}
+
+/// Generates [`NET_ERROR_CODES`], [`NetError::as_i32`] and
+/// `From<i32> for NetError` from one list, so they cannot drift apart.
+///
+/// `codes` has a row `(code, name, retryable, Variant)` per code, from the
+/// closest to zero, leaving out `Variant` for codes only variants with
+/// context carry. `context` maps those variants to their codes, and
+/// `from_code` rebuilds the ones that can be rebuilt from a code alone.
+/// `as_i32` matches every variant, so a new one fails to compile until it
+/// is listed.
+macro_rules! net_error_codes {
+    (
+        codes: [$(($code:literal, $name:literal, $retryable:literal $(, $variant:ident)?)),* $(,)?],
+        context: {$($pattern:pat => $context_code:expr,)*},
+        from_code: {$($from_code:literal => $error:expr,)*},
+    ) => {
+        /// Every code [`NetError::as_i32`] returns, apart from
+        /// [`NetError::Unknown`], from the closest to zero.
+        pub const NET_ERROR_CODES: &[NetErrorCode] = &[$(entry($code, $name, $retryable)),*];
+
+        impl NetError {
+            /// The error's code, Chromium's `net_error_list.h` value where
+            /// Chromium has the error.
+            #[allow(deprecated)]
+            pub fn as_i32(&self) -> i32 {
+                match self {
+                    $($(NetError::$variant => $code,)?)*
+                    $($pattern => $context_code,)*
+                }
+            }
+        }
+
+        impl From<i32> for NetError {
+            fn from(code: i32) -> Self {
+                match code {
+                    $($($code => NetError::$variant,)?)*
+                    $($from_code => $error,)*
+                    _ => NetError::Unknown(code),
+                }
+            }
+        }
+    };
+}
+
+net_error_codes! {
+    codes: [
+        (-3, "ABORTED", false, Aborted),
+        (-7, "TIMED_OUT", false, TimedOut),
+        (-20, "BLOCKED_BY_CLIENT", false, BlockedByClient),
+        (-25, "UPLOAD_STREAM_REWIND_NOT_SUPPORTED", false, UploadStreamRewindNotSupported),
+        (-26, "CONTEXT_SHUT_DOWN", false, ContextShutDown),
+        (-100, "CONNECTION_CLOSED", true, ConnectionClosed),
+        (-101, "CONNECTION_RESET", true, ConnectionReset),
+        (-102, "CONNECTION_REFUSED", false, ConnectionRefused),
+        (-103, "CONNECTION_ABORTED", true, ConnectionAborted),
+        (-104, "CONNECTION_FAILED", false, ConnectionFailed),
+        (-105, "NAME_NOT_RESOLVED", false, NameNotResolved),
+        (-106, "INTERNET_DISCONNECTED", false, InternetDisconnected),
+        (-107, "SSL_PROTOCOL_ERROR", false, SslProtocolError),
+        (-108, "ADDRESS_INVALID", false, AddressInvalid),
+        (-109, "ADDRESS_UNREACHABLE", false, AddressUnreachable),
+        (-110, "SSL_CLIENT_AUTH_CERT_NEEDED", false, SslClientAuthCertNeeded),
+        (-111, "TUNNEL_CONNECTION_FAILED", false, TunnelConnectionFailed),
+        (-112, "SOCKET_NOT_CONNECTED", true, SocketNotConnected),
+        (-113, "SSL_VERSION_OR_CIPHER_MISMATCH", false, SslVersionOrCipherMismatch),
+        (-114, "SSL_RENEGOTIATION_REQUESTED", false, SslRenegotiationRequested),
+        (-115, "PROXY_AUTH_UNSUPPORTED", false, ProxyAuthUnsupported),
+        (-117, "BAD_SSL_CLIENT_AUTH_CERT", false, BadSslClientAuthCert),
+        (-118, "CONNECTION_TIMED_OUT", true, ConnectionTimedOut),
+        (-119, "HOST_RESOLVER_QUEUE_TOO_LARGE", false, HostResolverQueueTooLarge),
+        (-120, "SOCKS_CONNECTION_FAILED", false, SocksConnectionFailed),
+        (-121, "SOCKS_CONNECTION_HOST_UNREACHABLE", false, SocksConnectionHostUnreachable),
+        (-122, "ALPN_NEGOTIATION_FAILED", false, AlpnNegotiationFailed),
+        (-123, "SSL_NO_RENEGOTIATION", false, SslNoRenegotiation),
+        (-124, "WINSOCK_UNEXPECTED_WRITTEN_BYTES", false, WinsockUnexpectedWrittenBytes),
+        (-125, "SSL_DECOMPRESSION_FAILURE_ALERT", false, SslDecompressionFailureAlert),
+        (-126, "SSL_BAD_RECORD_MAC_ALERT", false, SslBadRecordMacAlert),
+        (-127, "PROXY_AUTH_REQUESTED", false, ProxyAuthRequested),
+        (-130, "PROXY_CONNECTION_FAILED", false, ProxyConnectionFailed),
+        (-131, "MANDATORY_PROXY_CONFIGURATION_FAILED", false, MandatoryProxyConfigurationFailed),
+        (-133, "PRECONNECT_MAX_SOCKET_LIMIT", false, PreconnectMaxSocketLimit),
+        (-134, "SSL_CLIENT_AUTH_PRIVATE_KEY_ACCESS_DENIED", false, SslClientAuthPrivateKeyAccessDenied),
+        (-135, "SSL_CLIENT_AUTH_CERT_NO_PRIVATE_KEY", false, SslClientAuthCertNoPrivateKey),
+        (-136, "PROXY_CERTIFICATE_INVALID", false, ProxyCertificateInvalid),
+        (-137, "NAME_RESOLUTION_FAILED", false, NameResolutionFailed),
+        (-138, "NETWORK_ACCESS_DENIED", false, NetworkAccessDenied),
+        (-139, "TEMPORARILY_THROTTLED", false, TemporarilyThrottled),
+        (-141, "SSL_CLIENT_AUTH_SIGNATURE_FAILED", false, SslClientAuthSignatureFailed),
+        (-142, "MSG_TOO_BIG", false, MsgTooBig),
+        (-145, "WS_PROTOCOL_ERROR", false, WsProtocolError),
+        (-147, "ADDRESS_IN_USE", false, AddressInUse),
+        (-150, "SSL_PINNED_KEY_NOT_IN_CERT_CHAIN", false, SslPinnedKeyNotInCertChain),
+        (-151, "CLIENT_AUTH_CERT_TYPE_UNSUPPORTED", false, ClientAuthCertTypeUnsupported),
+        (-153, "SSL_DECRYPT_ERROR_ALERT", false, SslDecryptErrorAlert),
+        (-154, "WS_THROTTLE_QUEUE_TOO_LARGE", false, WsThrottleQueueTooLarge),
+        (-156, "SSL_SERVER_CERT_CHANGED", false, SslServerCertChanged),
+        (-159, "SSL_UNRECOGNIZED_NAME_ALERT", false, SslUnrecognizedNameAlert),
+        (-160, "SOCKET_SET_RECEIVE_BUFFER_SIZE_ERROR", false, SocketSetReceiveBufferSizeError),
+        (-161, "SOCKET_SET_SEND_BUFFER_SIZE_ERROR", false, SocketSetSendBufferSizeError),
+        (-162, "SOCKET_RECEIVE_BUFFER_SIZE_UNCHANGEABLE", false, SocketReceiveBufferSizeUnchangeable),
+        (-163, "SOCKET_SEND_BUFFER_SIZE_UNCHANGEABLE", false, SocketSendBufferSizeUnchangeable),
+        (-164, "SSL_CLIENT_AUTH_CERT_BAD_FORMAT", false, SslClientAuthCertBadFormat),
+        (-166, "ICANN_NAME_COLLISION", false, IcannNameCollision),
+        (-167, "SSL_SERVER_CERT_BAD_FORMAT", false, SslServerCertBadFormat),
+        (-168, "CT_STH_PARSING_FAILED", false, CtSthParsingFailed),
+        (-169, "CT_STH_INCOMPLETE", false, CtSthIncomplete),
+        (-170, "UNABLE_TO_REUSE_CONNECTION_FOR_PROXY_AUTH", false, UnableToReuseConnectionForProxyAuth),
+        (-171, "CT_CONSISTENCY_PROOF_PARSING_FAILED", false, CtConsistencyProofParsingFailed),
+        (-172, "SSL_OBSOLETE_CIPHER", false, SslObsoleteCipher),
+        (-173, "WS_UPGRADE", false, WsUpgrade),
+        (-174, "READ_IF_READY_NOT_IMPLEMENTED", false, ReadIfReadyNotImplemented),
+        (-176, "NO_BUFFER_SPACE", false, NoBufferSpace),
+        (-177, "SSL_CLIENT_AUTH_NO_COMMON_ALGORITHMS", false, SslClientAuthNoCommonAlgorithms),
+        (-178, "EARLY_DATA_REJECTED", false, EarlyDataRejected),
+        (-179, "WRONG_VERSION_ON_EARLY_DATA", false, WrongVersionOnEarlyData),
+        (-180, "TLS13_DOWNGRADE_DETECTED", false, Tls13DowngradeDetected),
+        (-181, "SSL_KEY_USAGE_INCOMPATIBLE", false, SslKeyUsageIncompatible),
+        (-182, "INVALID_ECH_CONFIG_LIST", false, InvalidEchConfigList),
+        (-183, "ECH_NOT_NEGOTIATED", false, EchNotNegotiated),
+        (-184, "ECH_FALLBACK_CERTIFICATE_INVALID", false, EchFallbackCertificateInvalid),
+        (-186, "PROXY_UNABLE_TO_CONNECT_TO_DESTINATION", false, ProxyUnableToConnectToDestination),
+        (-187, "PROXY_DELEGATE_CANCELED_CONNECT_REQUEST", false, ProxyDelegateCanceledConnectRequest),
+        (-188, "PROXY_DELEGATE_CANCELED_CONNECT_RESPONSE", false, ProxyDelegateCanceledConnectResponse),
+        (-300, "INVALID_URL", false, InvalidUrl),
+        (-301, "DISALLOWED_URL_SCHEME", false, DisallowedUrlScheme),
+        (-302, "UNKNOWN_URL_SCHEME", false, UnknownUrlScheme),
+        (-303, "INVALID_REDIRECT", false, InvalidRedirect),
+        (-310, "TOO_MANY_REDIRECTS", false, TooManyRedirects),
+        (-311, "UNSAFE_REDIRECT", false, UnsafeRedirect),
+        (-312, "UNSAFE_PORT", false, UnsafePort),
+        (-320, "INVALID_RESPONSE", false, InvalidResponse),
+        (-321, "INVALID_CHUNKED_ENCODING", false, InvalidChunkedEncoding),
+        (-322, "METHOD_NOT_SUPPORTED", false, MethodNotSupported),
+        (-323, "UNEXPECTED_PROXY_AUTH", false, UnexpectedProxyAuth),
+        (-324, "EMPTY_RESPONSE", true, EmptyResponse),
+        (-325, "RESPONSE_HEADERS_TOO_BIG", false, ResponseHeadersTooBig),
+        (-327, "PAC_SCRIPT_FAILED", false, PacScriptFailed),
+        (-328, "REQUEST_RANGE_NOT_SATISFIABLE", false, RequestRangeNotSatisfiable),
+        (-329, "MALFORMED_IDENTITY", false, MalformedIdentity),
+        (-330, "CONTENT_DECODING_FAILED", false, ContentDecodingFailed),
+        (-331, "NETWORK_IO_SUSPENDED", false, NetworkIoSuspended),
+        (-336, "NO_SUPPORTED_PROXIES", false, NoSupportedProxies),
+        (-337, "HTTP2_PROTOCOL_ERROR", false, Http2ProtocolError),
+        (-338, "INVALID_AUTH_CREDENTIALS", false, InvalidAuthCredentials),
+        (-339, "UNSUPPORTED_AUTH_SCHEME", false, UnsupportedAuthScheme),
+        (-340, "ENCODING_DETECTION_FAILED", false, EncodingDetectionFailed),
+        (-341, "MISSING_AUTH_CREDENTIALS", false, MissingAuthCredentials),
+        (-342, "UNEXPECTED_SECURITY_LIBRARY_STATUS", false, UnexpectedSecurityLibraryStatus),
+        (-343, "MISCONFIGURED_AUTH_ENVIRONMENT", false, MisconfiguredAuthEnvironment),
+        (-344, "UNDOCUMENTED_SECURITY_LIBRARY_STATUS", false, UndocumentedSecurityLibraryStatus),
+        (-345, "RESPONSE_BODY_TOO_BIG_TO_DRAIN", false, ResponseBodyTooBigToDrain),
+        (-346, "RESPONSE_HEADERS_MULTIPLE_CONTENT_LENGTH", false, ResponseHeadersMultipleContentLength),
+        (-347, "INCOMPLETE_HTTP2_HEADERS", false, IncompleteHttp2Headers),
+        (-348, "PAC_NOT_IN_DHCP", false, PacNotInDhcp),
+        (-349, "RESPONSE_HEADERS_MULTIPLE_CONTENT_DISPOSITION", false, ResponseHeadersMultipleContentDisposition),
+        (-350, "RESPONSE_HEADERS_MULTIPLE_LOCATION", false, ResponseHeadersMultipleLocation),
+        (-351, "HTTP2_SERVER_REFUSED_STREAM", true, Http2ServerRefusedStream),
+        (-352, "HTTP2_PING_FAILED", false, Http2PingFailed),
+        (-354, "CONTENT_LENGTH_MISMATCH", false, ContentLengthMismatch),
+        (-355, "INCOMPLETE_CHUNKED_ENCODING", false, IncompleteChunkedEncoding),
+        (-356, "QUIC_PROTOCOL_ERROR", false, QuicProtocolError),
+        (-357, "RESPONSE_HEADERS_TRUNCATED", false, ResponseHeadersTruncated),
+        (-358, "QUIC_HANDSHAKE_FAILED", false, QuicHandshakeFailed),
+        (-360, "HTTP2_INADEQUATE_TRANSPORT_SECURITY", false, Http2InadequateTransportSecurity),
+        (-361, "HTTP2_FLOW_CONTROL_ERROR", false, Http2FlowControlError),
+        (-362, "HTTP2_FRAME_SIZE_ERROR", false, Http2FrameSizeError),
+        (-363, "HTTP2_COMPRESSION_ERROR", false, Http2CompressionError),
+        (-364, "PROXY_AUTH_REQUESTED_WITH_NO_CONNECTION", false, ProxyAuthRequestedWithNoConnection),
+        (-365, "HTTP11_REQUIRED", false, Http11Required),
+        (-366, "PROXY_HTTP11_REQUIRED", false, ProxyHttp11Required),
+        (-367, "PAC_SCRIPT_TERMINATED", false, PacScriptTerminated),
+        (-368, "PROXY_REQUIRED", false, ProxyRequired),
+        (-370, "INVALID_HTTP_RESPONSE", false, InvalidHttpResponse),
+        (-371, "CONTENT_DECODING_INIT_FAILED", false, ContentDecodingInitFailed),
+        (-372, "HTTP2_RST_STREAM_NO_ERROR_RECEIVED", false, Http2RstStreamNoErrorReceived),
+        (-373, "HTTP2_PUSHED_STREAM_NOT_AVAILABLE", false, Http2PushedStreamNotAvailable),
+        (-374, "HTTP2_CLAIMED_PUSHED_STREAM_RESET_BY_SERVER", false, Http2ClaimedPushedStreamResetByServer),
+        (-375, "TOO_MANY_RETRIES", false, TooManyRetries),
+        (-376, "HTTP2_STREAM_CLOSED", false, Http2StreamClosed),
+        (-377, "HTTP2_CLIENT_REFUSED_STREAM", false, Http2ClientRefusedStream),
+        (-378, "HTTP2_PUSHED_RESPONSE_DOES_NOT_MATCH", false, Http2PushedResponseDoesNotMatch),
+        (-803, "DNS_TIMED_OUT", false, DnsTimedOut),
+        (-10000, "REDIRECT_CYCLE_DETECTED", false, RedirectCycleDetected),
+        (-10001, "SOCKET_REMOTE_CLOSED", false, SocketRemoteClosed),
+        (-10002, "DATA_RECEIVED_UNEXPECTEDLY", false, DataReceivedUnexpectedly),
+        (-10003, "COOKIE_INVALID_PREFIX", false, CookieInvalidPrefix),
+        (-10004, "COOKIE_PUBLIC_SUFFIX", false, CookiePublicSuffix),
+        (-10005, "INVALID_HEADER", false, InvalidHeader),
+        (-10006, "HTTP_BODY_ERROR", false, HttpBodyError),
+        (-10007, "INVALID_UTF8", false, InvalidUtf8),
+        (-10008, "JSON_PARSE_ERROR", false, JsonParseError),
+        (-10009, "CERT_PINNING_FAILED", false, CertPinningFailed),
+        (-10010, "CERTIFICATE_TRANSPARENCY_REQUIRED", false, CertificateTransparencyRequired),
+        (-10011, "NOT_IMPLEMENTED", false, NotImplemented),
+        (-10012, "FILE_NOT_FOUND", false, FileNotFound),
+        (-10020, "BROWSER_NOT_FOUND", false),
+        (-10021, "COOKIE_DB_NOT_FOUND", false),
+        (-10022, "COOKIE_DECRYPTION_FAILED", false),
+        (-10023, "COOKIE_DATABASE_LOCKED", false),
+        (-10024, "COOKIE_UNSUPPORTED_VERSION", false),
+        (-10025, "COOKIE_PLATFORM_NOT_SUPPORTED", false),
+        (-10026, "COOKIE_PROFILE_NOT_FOUND", false),
+        (-10027, "COOKIE_KEYRING_UNAVAILABLE", false),
+        (-10028, "COOKIE_INVALID_DATA", false),
+        (-10029, "COOKIE_DATABASE_ERROR", false),
+        (-10030, "INCONSISTENT_IDENTITY", false),
+        (-10031, "RESPONSE_BODY_TOO_BIG", false),
+        (-10032, "SNI_OVERRIDE_NOT_ALLOWED", false, SniOverrideNotAllowed),
+        (-10033, "HTTP_STATUS", false),
+        (-10034, "REQUEST_HEADERS_TOO_BIG", false),
+        (-10035, "URL_TOO_LONG", false),
+        (-10036, "REQUEST_LINE_TOO_LONG", false),
+    ],
+    context: {
+        // Same code as the plain variant
+        NetError::ConnectionFailedTo { .. } => -104,
+        NetError::NameNotResolvedFor { .. } => -105,
+        NetError::SslHandshakeFailedWith { .. } => -107,
+        NetError::TunnelConnectionTimedOut { .. } => -111,
+        // Reports the last attempt's error, like Chromium's connect jobs
+        NetError::ConnectionAttemptsFailed { attempts, .. } => {
+            attempts.last().map_or(-104, |a| a.error.as_i32())
+        },
+        // Cookie extraction errors, -10020 to -10029
+        NetError::CookieExtraction(e) => e.code(),
+        NetError::BrowserNotFound { .. } => -10020,
+        NetError::CookieDbNotFound { .. } => -10021,
+        NetError::CookieDecryptionFailed { .. } => -10022,
+        NetError::CookieDatabaseLocked => -10023,
+        NetError::CookieUnsupportedVersion { .. } => -10024,
+        NetError::CookiePlatformNotSupported { .. } => -10025,
+        NetError::CookieProfileNotFound { .. } => -10026,
+        NetError::CookieKeyringUnavailable => -10027,
+        NetError::CookieInvalidData { .. } => -10028,
+        NetError::CookieDatabaseError { .. } => -10029,
+        // chromenet's own errors with context
+        NetError::InconsistentIdentity { .. } => -10030,
+        NetError::ResponseBodyTooBig { .. } => -10031,
+        NetError::HttpStatus(_) => -10033,
+        NetError::RequestHeadersTooBig { .. } => -10034,
+        NetError::UrlTooLong { .. } => -10035,
+        NetError::RequestLineTooLong { .. } => -10036,
+        NetError::Unknown(code) => *code,
+    },
+    from_code: {
+        -10023 => CookieExtractionError::DatabaseLocked.into(),
+        -10027 => CookieExtractionError::KeyringUnavailable.into(),
+    },
+}
+
+/// The codes of [`NET_ERROR_CODES`].
+pub fn codes() -> impl Iterator<Item = &'static NetErrorCode> {
+    NET_ERROR_CODES.iter()
+}
+
+/// The entry for `code`, if it is a known code.
+pub fn lookup(code: i32) -> Option<&'static NetErrorCode> {
+    NET_ERROR_CODES
+        .binary_search_by(|entry| code.cmp(&entry.code))
+        .ok()
+        .map(|i| &NET_ERROR_CODES[i])
+}
+
+/// The entry named `name`, e.g. `CONNECTION_REFUSED`.
+pub fn lookup_name(name: &str) -> Option<&'static NetErrorCode> {
+    codes().find(|entry| entry.name == name)
+}
+
+impl NetError {
+    /// Name, category and retryability of this error's code; `None` for an
+    /// [`Unknown`](NetError::Unknown) code.
+    pub fn code_info(&self) -> Option<&'static NetErrorCode> {
+        lookup(self.as_i32())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::retry::RetryReason;
+    use std::io;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Variant name, with cookie extraction errors named like their
+    /// deprecated `NetError` variants.
+    fn variant_name(error: &NetError) -> String {
+        let debug = match error {
+            NetError::CookieExtraction(e) => format!("Cookie{e:?}"),
+            e => format!("{e:?}"),
+        };
+        let name = debug.split(['(', ' ', '{']).next().unwrap();
+        let mut snake = String::new();
+        for (i, c) in name.char_indices() {
+            let prev = name[..i].chars().last();
+            if c.is_ascii_uppercase() && prev.is_some_and(|p| !p.is_ascii_uppercase()) {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_uppercase());
+        }
+        snake
+    }
+
+    #[test]
+    fn test_table_matches_net_error() {
+        for entry in codes() {
+            let error = NetError::from(entry.code);
+            assert_eq!(error.as_i32(), entry.code, "{}", entry.name);
+            if let NetError::Unknown(_) = error {
+                // Only carried by variants with context
+                assert!(!entry.retryable, "{}", entry.name);
+            } else {
+                assert_eq!(variant_name(&error), entry.name);
+                assert_eq!(
+                    RetryReason::from_error(&error).is_some(),
+                    entry.retryable,
+                    "{}",
+                    entry.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_context_variants_are_listed() {
+        let io = || Arc::new(io::Error::other("test"));
+        let text = || "test".to_string();
+        let errors = [
+            NetError::ConnectionFailedTo {
+                host: text(),
+                port: 443,
+                source: io(),
+            },
+            NetError::NameNotResolvedFor {
+                domain: text(),
+                source: io(),
+            },
+            NetError::SslHandshakeFailedWith {
+                host: text(),
+                reason: text(),
+            },
+            NetError::TunnelConnectionTimedOut {
+                proxy: text(),
+                timeout: Duration::ZERO,
+            },
+            NetError::ConnectionAttemptsFailed {
+                host: text(),
+                port: 443,
+                attempts: Vec::new(),
+            },
+            NetError::CookieExtraction(CookieExtractionError::Database(text())),
+            NetError::BrowserNotFound { browser: text() },
+            NetError::CookieDbNotFound { path: text() },
+            NetError::CookieDecryptionFailed {
+                browser: text(),
+                reason: text(),
+            },
+            NetError::CookieDatabaseLocked,
+            NetError::CookieUnsupportedVersion { version: text() },
+            NetError::CookiePlatformNotSupported { platform: text() },
+            NetError::CookieProfileNotFound { profile: text() },
+            NetError::CookieKeyringUnavailable,
+            NetError::CookieInvalidData { reason: text() },
+            NetError::CookieDatabaseError { message: text() },
+            NetError::InconsistentIdentity { reason: text() },
+            NetError::ResponseBodyTooBig { limit: 1 },
+            NetError::RequestHeadersTooBig { size: 2, limit: 1 },
+            NetError::UrlTooLong {
+                length: 2,
+                limit: 1,
+            },
+            NetError::RequestLineTooLong {
+                length: 2,
+                limit: 1,
+            },
+        ];
+        for error in errors {
+            assert!(error.code_info().is_some(), "{error:?}");
+        }
+    }
+
+    #[test]
+    fn test_table_sorted_and_unique() {
+        assert!(NET_ERROR_CODES.windows(2).all(|w| w[0].code > w[1].code));
+        for entry in codes() {
+            assert_eq!(lookup(entry.code), Some(entry));
+            assert_eq!(lookup_name(entry.name), Some(entry));
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let info = NetError::ConnectionReset.code_info().unwrap();
+        assert_eq!((info.code, info.name), (-101, "CONNECTION_RESET"));
+        assert!(info.retryable);
+        assert_eq!(
+            lookup(-10032).map(|e| e.category),
+            Some(ErrorCategory::Client)
+        );
+        assert_eq!(lookup(-10023).unwrap().category, ErrorCategory::Cookie);
+        assert_eq!(lookup(-803).unwrap().name, "DNS_TIMED_OUT");
+        assert!(lookup(-9999).is_none());
+        assert!(NetError::Unknown(-9999).code_info().is_none());
+    }
+}
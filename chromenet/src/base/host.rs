@@ -0,0 +1,116 @@
+//! Canonical host names.
+//!
+//! Chromium mapping: `url::CanonicalizeHost` and `net::CanonicalizeHost`
+//!
+//! DNS lookups, cookie domain matching, HSTS and pin lookups and pool keys
+//! all compare host names, so they must agree on one spelling. The
+//! canonical form is the one [`Url::host_str`] produces, minus a trailing
+//! dot:
+//! - domains are mapped with IDNA (UTS #46) to lowercase ASCII, with
+//!   Unicode labels in punycode: `Bücher.Example.` is `xn--bcher-kva.example`
+//! - IPv4 addresses are in dotted-decimal form: `0x7f.1` is `127.0.0.1`
+//! - IPv6 addresses are compressed and in brackets: `[0:0::1]` is `[::1]`
+//!
+//! IP literals are not domains: they have no parent domains or registrable
+//! domain, get no SNI, only host-only cookies and no HSTS, and need no DNS
+//! lookup. [`ip_literal`] tells them apart.
+
+use std::net::{IpAddr, Ipv6Addr};
+use url::{Host, Url};
+
+/// Canonical form of `host`, `None` if it is not a valid host name or IP
+/// literal.
+///
+/// IPv6 literals are accepted with or without brackets.
+pub fn canonicalize_host(host: &str) -> Option<String> {
+    let host = host.trim();
+    if let Ok(ip) = host.parse::<Ipv6Addr>() {
+        return Some(Host::<String>::Ipv6(ip).to_string());
+    }
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() || host.ends_with('.') {
+        return None;
+    }
+    Host::parse(host).ok().map(|host| host.to_string())
+}
+
+/// Canonical form of `host` for use as a lookup key.
+///
+/// Falls back to lowercasing when `host` is not a valid host name, so
+/// invalid names still only match themselves.
+pub fn host_key(host: &str) -> String {
+    canonicalize_host(host).unwrap_or_else(|| host.trim().to_ascii_lowercase())
+}
+
+/// The address of an IP-literal `host`, `None` for domains.
+///
+/// Accepts canonical hosts and IPv6 addresses with or without brackets.
+pub fn ip_literal(host: &str) -> Option<IpAddr> {
+    let host = host.trim();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    host.parse().ok()
+}
+
+/// Canonical host of `url`, `None` if it has none.
+pub fn url_host(url: &Url) -> Option<String> {
+    url.host_str().map(host_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_domains() {
+        assert_eq!(
+            canonicalize_host("WWW.Example.COM.").as_deref(),
+            Some("www.example.com")
+        );
+        assert_eq!(
+            canonicalize_host("Bücher.example").as_deref(),
+            Some("xn--bcher-kva.example")
+        );
+        assert_eq!(
+            canonicalize_host("xn--bcher-kva.example").as_deref(),
+            Some("xn--bcher-kva.example")
+        );
+        // UTS #46 mapping: fullwidth letters and ideographic full stops
+        assert_eq!(
+            canonicalize_host("ｅｘａｍｐｌｅ。com").as_deref(),
+            Some("example.com")
+        );
+        for invalid in ["", ".", "example..", "exa mple.com", "a\u{0}b"] {
+            assert_eq!(canonicalize_host(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_ip_literals() {
+        assert_eq!(canonicalize_host("0x7f.1").as_deref(), Some("127.0.0.1"));
+        assert_eq!(canonicalize_host("0:0::1").as_deref(), Some("[::1]"));
+        assert_eq!(canonicalize_host("[0:0::1]").as_deref(), Some("[::1]"));
+    }
+
+    #[test]
+    fn test_ip_literal() {
+        assert_eq!(ip_literal("10.0.0.5"), Some(IpAddr::from([10, 0, 0, 5])));
+        assert_eq!(ip_literal("[::1]"), Some(Ipv6Addr::LOCALHOST.into()));
+        assert_eq!(ip_literal("::1"), Some(Ipv6Addr::LOCALHOST.into()));
+        for domain in ["example.com", "10.0.0.5.example", "[example.com]", "0x7f.1"] {
+            assert_eq!(ip_literal(domain), None, "{:?}", domain);
+        }
+        // Non-canonical spellings are canonicalized first
+        let host = canonicalize_host("0x7f.1").unwrap();
+        assert_eq!(ip_literal(&host), Some(IpAddr::from([127, 0, 0, 1])));
+    }
+
+    #[test]
+    fn test_url_host_matches_canonical_form() {
+        let url = Url::parse("https://Bücher.Example./path").unwrap();
+        assert_eq!(url_host(&url).as_deref(), Some("xn--bcher-kva.example"));
+        assert_eq!(host_key("bad host"), "bad host");
+    }
+}
@@ -0,0 +1,266 @@
+//! Log files with a bound on disk use.
+//!
+//! Chromium equivalent: the bounded mode of `FileNetLogObserver` in
+//! `net/log/file_net_log_observer.h`
+//!
+//! Always-on logging in a long-running process, e.g. a tracing subscriber
+//! writing JSON events or one HAR entry per line, must not fill the disk.
+//! A [`RotatingFile`] finishes the current file once the next write would
+//! take it past [`LogRotation::max_file_size`], gzips it and deletes the
+//! oldest finished files until they fit in
+//! [`LogRotation::max_total_size`].
+//!
+//! The current file is `path`, finished ones are `path.1.gz`, `path.2.gz`
+//! and so on, newest first (without `.gz` when not compressing). A file
+//! is only finished between writes, so a record written with one
+//! `write_all` is never split across files, and the disk holds at most
+//! `max_file_size + max_total_size` bytes unless a single record is larger
+//! than `max_file_size`.
+//!
+//! ```rust,ignore
+//! use chromenet::base::logfile::{LogRotation, RotatingFile};
+//! use std::sync::Mutex;
+//!
+//! let file = RotatingFile::open("chromenet.jsonl", LogRotation::default())?;
+//! tracing_subscriber::fmt()
+//!     .json()
+//!     .with_writer(Mutex::new(file))
+//!     .init();
+//! ```
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// When a [`RotatingFile`] starts a new file and how many it keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRotation {
+    /// Size past which the current file is finished and a new one started
+    pub max_file_size: u64,
+    /// Bound on the size of all finished files together, as stored
+    pub max_total_size: u64,
+    /// Gzip finished files
+    pub compress: bool,
+}
+
+impl Default for LogRotation {
+    /// 10 MiB files, 100 MiB of finished ones, gzipped.
+    fn default() -> Self {
+        Self {
+            max_file_size: 10 * 1024 * 1024,
+            max_total_size: 100 * 1024 * 1024,
+            compress: true,
+        }
+    }
+}
+
+/// A file written through [`Write`] that rotates by size.
+///
+/// Finishing a file compresses it on the writing thread; wrap the file in
+/// a non-blocking writer where that matters.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    len: u64,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, continuing a file left by an earlier run.
+    pub fn open(path: impl Into<PathBuf>, rotation: LogRotation) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            len,
+        })
+    }
+
+    /// Path of the current file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Finished files still on disk, newest first.
+    pub fn finished_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for n in 1.. {
+            match [true, false]
+                .map(|gz| self.numbered(n, gz))
+                .into_iter()
+                .find(|path| path.exists())
+            {
+                Some(path) => files.push(path),
+                None => break,
+            }
+        }
+        files
+    }
+
+    /// Finish the current file now, unless it is empty, and start a new
+    /// one.
+    pub fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.len == 0 {
+            return Ok(());
+        }
+        // Make room for the newest: .1 becomes .2 and so on
+        let finished = self.finished_files();
+        for (i, path) in finished.iter().enumerate().rev() {
+            let gz = path.extension().is_some_and(|ext| ext == "gz");
+            fs::rename(path, self.numbered(i + 2, gz))?;
+        }
+
+        let newest = self.numbered(1, self.rotation.compress);
+        if self.rotation.compress {
+            let mut encoder = flate2::write::GzEncoder::new(
+                File::create(&newest)?,
+                flate2::Compression::default(),
+            );
+            io::copy(&mut File::open(&self.path)?, &mut encoder)?;
+            encoder.finish()?.sync_all()?;
+            fs::remove_file(&self.path)?;
+        } else {
+            fs::rename(&self.path, &newest)?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.len = 0;
+        self.prune()
+    }
+
+    /// Delete the oldest finished files past the total size bound.
+    fn prune(&self) -> io::Result<()> {
+        let mut total = 0;
+        for path in self.finished_files() {
+            total += fs::metadata(&path)?.len();
+            if total > self.rotation.max_total_size {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `path.n`, or `path.n.gz` with `gz`.
+    fn numbered(&self, n: usize, gz: bool) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{}", n));
+        if gz {
+            name.push(".gz");
+        }
+        name.into()
+    }
+}
+
+impl Write for RotatingFile {
+    /// Write all of `buf` to the current file, finishing it first if `buf`
+    /// would take it past the size limit.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len > 0 && self.len + buf.len() as u64 > self.rotation.max_file_size {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.len += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn gunzip(path: &Path) -> String {
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(File::open(path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_rotates_and_compresses_between_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("net.jsonl");
+        let rotation = LogRotation {
+            max_file_size: 16,
+            ..LogRotation::default()
+        };
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+
+        // Larger than a file, but a file is never empty
+        file.write_all(b"a first long record\n").unwrap();
+        file.write_all(b"second\n").unwrap();
+        file.write_all(b"third\n").unwrap();
+        file.write_all(b"fourth\n").unwrap();
+
+        let finished = file.finished_files();
+        assert_eq!(
+            finished,
+            [
+                dir.path().join("net.jsonl.1.gz"),
+                dir.path().join("net.jsonl.2.gz")
+            ]
+        );
+        assert_eq!(gunzip(&finished[0]), "second\nthird\n");
+        assert_eq!(gunzip(&finished[1]), "a first long record\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+    }
+
+    #[test]
+    fn test_oldest_files_deleted_past_total_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("har.jsonl");
+        let rotation = LogRotation {
+            max_file_size: 8,
+            max_total_size: 16,
+            compress: false,
+        };
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+        for record in [
+            "entry 1\n",
+            "entry 2\n",
+            "entry 3\n",
+            "entry 4\n",
+            "entry 5\n",
+        ] {
+            file.write_all(record.as_bytes()).unwrap();
+        }
+
+        let finished = file.finished_files();
+        assert_eq!(finished.len(), 2);
+        assert_eq!(fs::read_to_string(&finished[0]).unwrap(), "entry 4\n");
+        assert_eq!(fs::read_to_string(&finished[1]).unwrap(), "entry 3\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "entry 5\n");
+    }
+
+    #[test]
+    fn test_reopened_file_continues() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("net.jsonl");
+        let rotation = LogRotation {
+            max_file_size: 10,
+            ..LogRotation::default()
+        };
+        RotatingFile::open(&path, rotation)
+            .unwrap()
+            .write_all(b"earlier\n")
+            .unwrap();
+
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+        file.write_all(b"later\n").unwrap();
+        assert_eq!(gunzip(&file.finished_files()[0]), "earlier\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "later\n");
+    }
+}
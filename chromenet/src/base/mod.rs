@@ -2,11 +2,25 @@
 //!
 //! Provides foundational types mirroring Chromium's `net/base/`:
 //! - [`NetError`]: Network error codes matching `net_error_list.h`
+//! - [`errorcodes`]: Stable table of error codes, names and categories
 //! - [`LoadState`]: Request loading states from `load_states_list.h`
+//! - [`NetworkIsolationKey`]: Partitioning of sockets by top-frame site
+//! - [`host`]: Canonical host names shared by DNS, cookies, TLS and pooling
+//! - [`RequestId`](requestid::RequestId): Identifiers correlating a request's log events
+//! - [`secret`]: Passwords and header values redacted from `Debug` output
+//! - [`clock`]: Wall clock behind expiry checks, mockable in tests
+//! - [`logfile`]: Log files rotated and gzipped within a bound on disk use
 
+pub mod clock;
 pub mod context;
+pub mod errorcodes;
+pub mod host;
 pub mod loadstate;
+pub mod logfile;
 pub mod neterror;
+pub mod networkisolationkey;
+pub mod requestid;
+pub mod secret;
 
 #[cfg(test)]
 mod tests;
@@ -1,13 +1,41 @@
+use crate::cookies::error::CookieExtractionError;
+use crate::http::response::StatusError;
 use std::io;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
+/// One TCP connect attempt to a resolved address.
+///
+/// Chromium equivalent: `ConnectionAttempt` in `net/socket/connection_attempts.h`
+#[derive(Debug, Clone)]
+pub struct ConnectionAttempt {
+    /// Address that was tried.
+    pub address: SocketAddr,
+    /// Why the attempt failed.
+    pub error: NetError,
+    /// Time from starting the attempt until it failed.
+    pub duration: Duration,
+}
+
 /// Network error type mirroring Chromium's net/base/net_error_list.h.
 ///
 /// This enum covers all network-level errors including connection, SSL/TLS,
 /// HTTP, and cookie extraction errors.
 #[derive(Debug, Error, Clone)]
 pub enum NetError {
+    // Generic Errors
+    #[error("Operation aborted")]
+    Aborted,
+    #[error("Operation timed out")]
+    TimedOut,
+    #[error("Request blocked by client")]
+    BlockedByClient,
+    /// The client was shut down; see [`Client::shutdown`](crate::Client::shutdown).
+    #[error("Client shut down")]
+    ContextShutDown,
+
     // Connection Errors
     #[error("Connection closed (TCP FIN)")]
     ConnectionClosed,
@@ -154,6 +182,10 @@ pub enum NetError {
     #[error("Proxy delegate canceled connect response")]
     ProxyDelegateCanceledConnectResponse,
 
+    // Upload Errors
+    #[error("Upload stream rewind not supported")]
+    UploadStreamRewindNotSupported,
+
     // HTTP Errors
     #[error("Invalid URL")]
     InvalidUrl,
@@ -298,6 +330,10 @@ pub enum NetError {
     #[error("HTTP/2 pushed response does not match")]
     Http2PushedResponseDoesNotMatch,
 
+    // DNS Errors
+    #[error("DNS lookup timed out")]
+    DnsTimedOut,
+
     // Context-rich connection errors
     #[error("Connection to {host}:{port} failed")]
     ConnectionFailedTo {
@@ -314,200 +350,97 @@ pub enum NetError {
     },
     #[error("SSL handshake with {host} failed: {reason}")]
     SslHandshakeFailedWith { host: String, reason: String },
+    /// The proxy did not finish its CONNECT or SOCKS5 handshake within the
+    /// attempt timeout.
+    #[error("Handshake with proxy {proxy} timed out after {timeout:?}")]
+    TunnelConnectionTimedOut { proxy: String, timeout: Duration },
+    #[error("Connection to {host}:{port} failed after {} attempts", .attempts.len())]
+    ConnectionAttemptsFailed {
+        host: String,
+        port: u16,
+        /// Every address tried, IPv6 and IPv4 attempts grouped by family.
+        attempts: Vec<ConnectionAttempt>,
+    },
 
-    // Cookie extraction errors (unified from CookieExtractionError)
+    // Cookie extraction errors, see `CookieExtractionError` for the structured form
+    #[error(transparent)]
+    CookieExtraction(CookieExtractionError),
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Browser {browser} not found")]
     BrowserNotFound { browser: String },
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Cookie database not found: {path}")]
     CookieDbNotFound { path: String },
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Failed to decrypt {browser} cookies: {reason}")]
     CookieDecryptionFailed { browser: String, reason: String },
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Cookie database locked (close browser)")]
     CookieDatabaseLocked,
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Browser version not supported: {version}")]
     CookieUnsupportedVersion { version: String },
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Platform not supported: {platform}")]
     CookiePlatformNotSupported { platform: String },
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Profile not found: {profile}")]
     CookieProfileNotFound { profile: String },
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Keyring unavailable")]
     CookieKeyringUnavailable,
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Invalid cookie data: {reason}")]
     CookieInvalidData { reason: String },
+    #[deprecated(since = "0.2.0", note = "Use NetError::CookieExtraction instead")]
     #[error("Cookie database error: {message}")]
     CookieDatabaseError { message: String },
 
+    /// User-Agent, client hints and TLS fingerprint disagree; see
+    /// [`UaConsistency::Strict`](crate::emulation::UaConsistency::Strict).
+    #[error("Inconsistent browser identity: {reason}")]
+    InconsistentIdentity { reason: String },
+
+    /// Response body exceeds the limit set with
+    /// [`HttpResponse::with_body_limit`](crate::http::HttpResponse::with_body_limit).
+    #[error("Response body exceeds {limit} bytes")]
+    ResponseBodyTooBig { limit: usize },
+
+    /// A request set a [`ServerName`](crate::socket::tls::ServerName) on a
+    /// client without
+    /// [`ClientBuilder::allow_sni_override`](crate::ClientBuilder::allow_sni_override).
+    #[error("TLS server name override not allowed")]
+    SniOverrideNotAllowed,
+
+    /// The request's headers exceed the `SETTINGS_MAX_HEADER_LIST_SIZE` the
+    /// HTTP/2 server announced, counted as
+    /// [`h2_header_list_size`](crate::http::headerlimits::h2_header_list_size)
+    /// does. The request was not sent.
+    #[error("Request headers of {size} bytes exceed the server's limit of {limit} bytes")]
+    RequestHeadersTooBig { size: usize, limit: usize },
+
+    /// The request's URL is longer than
+    /// [`UrlLimits::max_url_length`](crate::urlrequest::urllimits::UrlLimits::max_url_length).
+    /// The request was not sent.
+    #[error("URL of {length} bytes exceeds the limit of {limit} bytes")]
+    UrlTooLong { length: usize, limit: usize },
+
+    /// The request line is longer than
+    /// [`UrlLimits::max_request_line`](crate::urlrequest::urllimits::UrlLimits::max_request_line).
+    /// The request was not sent.
+    #[error("Request line of {length} bytes exceeds the limit of {limit} bytes")]
+    RequestLineTooLong { length: usize, limit: usize },
+
+    /// The server answered with a 4xx or 5xx status; see
+    /// [`HttpResponse::error_for_status`](crate::http::HttpResponse::error_for_status).
+    #[error(transparent)]
+    HttpStatus(Box<StatusError>),
+
     #[error("Unknown error: {0}")]
     Unknown(i32),
 }
 
 impl NetError {
-    pub fn as_i32(&self) -> i32 {
-        match self {
-            NetError::ConnectionClosed => -100,
-            NetError::ConnectionReset => -101,
-            NetError::ConnectionRefused => -102,
-            NetError::ConnectionAborted => -103,
-            NetError::ConnectionFailed => -104,
-            NetError::NameNotResolved => -105,
-            NetError::InternetDisconnected => -106,
-            NetError::SocketNotConnected => -112,
-            NetError::SslProtocolError => -107,
-            NetError::AddressInvalid => -108,
-            NetError::AddressUnreachable => -109,
-            NetError::SslClientAuthCertNeeded => -110,
-            NetError::TunnelConnectionFailed => -111,
-            NetError::SslVersionOrCipherMismatch => -113,
-            NetError::SslRenegotiationRequested => -114,
-            NetError::ProxyAuthUnsupported => -115,
-            NetError::BadSslClientAuthCert => -117,
-            NetError::ConnectionTimedOut => -118,
-            NetError::HostResolverQueueTooLarge => -119,
-            NetError::SocksConnectionFailed => -120,
-            NetError::SocksConnectionHostUnreachable => -121,
-            NetError::AlpnNegotiationFailed => -122,
-            NetError::SslNoRenegotiation => -123,
-            NetError::WinsockUnexpectedWrittenBytes => -124,
-            NetError::SslDecompressionFailureAlert => -125,
-            NetError::SslBadRecordMacAlert => -126,
-            NetError::ProxyAuthRequested => -127,
-            NetError::ProxyConnectionFailed => -130,
-            NetError::MandatoryProxyConfigurationFailed => -131,
-            NetError::PreconnectMaxSocketLimit => -133,
-            NetError::SslClientAuthPrivateKeyAccessDenied => -134,
-            NetError::SslClientAuthCertNoPrivateKey => -135,
-            NetError::ProxyCertificateInvalid => -136,
-            NetError::NameResolutionFailed => -137,
-            NetError::NetworkAccessDenied => -138,
-            NetError::TemporarilyThrottled => -139,
-            NetError::SslClientAuthSignatureFailed => -141,
-            NetError::MsgTooBig => -142,
-            NetError::WsProtocolError => -145,
-            NetError::AddressInUse => -147,
-            NetError::SslPinnedKeyNotInCertChain => -150,
-            NetError::ClientAuthCertTypeUnsupported => -151,
-            NetError::SslDecryptErrorAlert => -153,
-            NetError::WsThrottleQueueTooLarge => -154,
-            NetError::SslServerCertChanged => -156,
-            NetError::SslUnrecognizedNameAlert => -159,
-            NetError::SocketSetReceiveBufferSizeError => -160,
-            NetError::SocketSetSendBufferSizeError => -161,
-            NetError::SocketReceiveBufferSizeUnchangeable => -162,
-            NetError::SocketSendBufferSizeUnchangeable => -163,
-            NetError::SslClientAuthCertBadFormat => -164,
-            NetError::IcannNameCollision => -166,
-            NetError::SslServerCertBadFormat => -167,
-            NetError::CtSthParsingFailed => -168,
-            NetError::CtSthIncomplete => -169,
-            NetError::UnableToReuseConnectionForProxyAuth => -170,
-            NetError::CtConsistencyProofParsingFailed => -171,
-            NetError::SslObsoleteCipher => -172,
-            NetError::WsUpgrade => -173,
-            NetError::ReadIfReadyNotImplemented => -174,
-            NetError::NoBufferSpace => -176,
-            NetError::SslClientAuthNoCommonAlgorithms => -177,
-            NetError::EarlyDataRejected => -178,
-            NetError::WrongVersionOnEarlyData => -179,
-            NetError::Tls13DowngradeDetected => -180,
-            NetError::SslKeyUsageIncompatible => -181,
-            NetError::InvalidEchConfigList => -182,
-            NetError::EchNotNegotiated => -183,
-            NetError::EchFallbackCertificateInvalid => -184,
-            NetError::ProxyUnableToConnectToDestination => -186,
-            NetError::ProxyDelegateCanceledConnectRequest => -187,
-            NetError::ProxyDelegateCanceledConnectResponse => -188,
-
-            NetError::InvalidUrl => -300,
-            NetError::DisallowedUrlScheme => -301,
-            NetError::UnknownUrlScheme => -302,
-            NetError::InvalidRedirect => -303,
-            NetError::TooManyRedirects => -310,
-            NetError::UnsafeRedirect => -311,
-            NetError::UnsafePort => -312,
-            NetError::InvalidResponse => -320,
-            NetError::InvalidChunkedEncoding => -321,
-            NetError::MethodNotSupported => -322,
-            NetError::UnexpectedProxyAuth => -323,
-            NetError::EmptyResponse => -324,
-            NetError::ResponseHeadersTooBig => -325,
-            NetError::PacScriptFailed => -327,
-            NetError::RequestRangeNotSatisfiable => -328,
-            NetError::MalformedIdentity => -329,
-            NetError::ContentDecodingFailed => -330,
-            NetError::NetworkIoSuspended => -331,
-            NetError::NoSupportedProxies => -336,
-            NetError::Http2ProtocolError => -337,
-            NetError::InvalidAuthCredentials => -338,
-            NetError::UnsupportedAuthScheme => -339,
-            NetError::EncodingDetectionFailed => -340,
-            NetError::MissingAuthCredentials => -341,
-            NetError::UnexpectedSecurityLibraryStatus => -342,
-            NetError::MisconfiguredAuthEnvironment => -343,
-            NetError::UndocumentedSecurityLibraryStatus => -344,
-            NetError::ResponseBodyTooBigToDrain => -345,
-            NetError::ResponseHeadersMultipleContentLength => -346,
-            NetError::IncompleteHttp2Headers => -347,
-            NetError::PacNotInDhcp => -348,
-            NetError::ResponseHeadersMultipleContentDisposition => -349,
-            NetError::ResponseHeadersMultipleLocation => -350,
-            NetError::Http2ServerRefusedStream => -351,
-            NetError::Http2PingFailed => -352,
-            NetError::ContentLengthMismatch => -354,
-            NetError::IncompleteChunkedEncoding => -355,
-            NetError::QuicProtocolError => -356,
-            NetError::ResponseHeadersTruncated => -357,
-            NetError::QuicHandshakeFailed => -358,
-            NetError::Http2InadequateTransportSecurity => -360,
-            NetError::Http2FlowControlError => -361,
-            NetError::Http2FrameSizeError => -362,
-            NetError::Http2CompressionError => -363,
-            NetError::ProxyAuthRequestedWithNoConnection => -364,
-            NetError::Http11Required => -365,
-            NetError::ProxyHttp11Required => -366,
-            NetError::PacScriptTerminated => -367,
-            NetError::ProxyRequired => -368,
-            NetError::InvalidHttpResponse => -370,
-            NetError::ContentDecodingInitFailed => -371,
-            NetError::Http2RstStreamNoErrorReceived => -372,
-            NetError::Http2PushedStreamNotAvailable => -373,
-            NetError::Http2ClaimedPushedStreamResetByServer => -374,
-            NetError::TooManyRetries => -375,
-            NetError::Http2StreamClosed => -376,
-            NetError::Http2ClientRefusedStream => -377,
-            NetError::Http2PushedResponseDoesNotMatch => -378,
-            // Edge case errors (custom codes starting at -10000 to avoid collision with Chromium Blob errors)
-            NetError::RedirectCycleDetected => -10000,
-            NetError::SocketRemoteClosed => -10001,
-            NetError::DataReceivedUnexpectedly => -10002,
-            NetError::CookieInvalidPrefix => -10003,
-            NetError::CookiePublicSuffix => -10004,
-            NetError::InvalidHeader => -10005,
-            NetError::HttpBodyError => -10006,
-            NetError::InvalidUtf8 => -10007,
-            NetError::JsonParseError => -10008,
-            NetError::CertPinningFailed => -10009,
-            NetError::CertificateTransparencyRequired => -10010,
-            NetError::NotImplemented => -10011,
-            NetError::FileNotFound => -10012,
-            // Context variants (same code as simple variant)
-            NetError::ConnectionFailedTo { .. } => -104,
-            NetError::NameNotResolvedFor { .. } => -105,
-            NetError::SslHandshakeFailedWith { .. } => -107,
-            // Cookie extraction errors
-            NetError::BrowserNotFound { .. } => -10020,
-            NetError::CookieDbNotFound { .. } => -10021,
-            NetError::CookieDecryptionFailed { .. } => -10022,
-            NetError::CookieDatabaseLocked => -10023,
-            NetError::CookieUnsupportedVersion { .. } => -10024,
-            NetError::CookiePlatformNotSupported { .. } => -10025,
-            NetError::CookieProfileNotFound { .. } => -10026,
-            NetError::CookieKeyringUnavailable => -10027,
-            NetError::CookieInvalidData { .. } => -10028,
-            NetError::CookieDatabaseError { .. } => -10029,
-            NetError::Unknown(code) => *code,
-        }
-    }
-
     // Helper constructors for context-rich errors
 
     /// Create connection failed error with context.
@@ -535,31 +468,46 @@ impl NetError {
         }
     }
 
+    /// Create proxy handshake timeout error with context.
+    pub fn tunnel_timed_out(proxy: impl Into<String>, timeout: Duration) -> Self {
+        Self::TunnelConnectionTimedOut {
+            proxy: proxy.into(),
+            timeout,
+        }
+    }
+
     /// Create browser not found error.
     pub fn browser_not_found(browser: impl Into<String>) -> Self {
-        Self::BrowserNotFound {
-            browser: browser.into(),
-        }
+        CookieExtractionError::BrowserNotFound(browser.into()).into()
     }
 
     /// Create cookie decryption failed error.
     pub fn cookie_decryption_failed(browser: impl Into<String>, reason: impl Into<String>) -> Self {
-        Self::CookieDecryptionFailed {
+        CookieExtractionError::DecryptionFailed {
             browser: browser.into(),
             reason: reason.into(),
         }
+        .into()
     }
 
     /// Create cookie database not found error.
     pub fn cookie_db_not_found(path: impl Into<String>) -> Self {
-        Self::CookieDbNotFound { path: path.into() }
+        CookieExtractionError::DatabaseNotFound(path.into()).into()
     }
 
     /// Create cookie invalid data error.
     pub fn cookie_invalid_data(reason: impl Into<String>) -> Self {
-        Self::CookieInvalidData {
-            reason: reason.into(),
-        }
+        CookieExtractionError::InvalidData(reason.into()).into()
+    }
+
+    /// Create cookie keyring unavailable error.
+    pub fn cookie_keyring_unavailable() -> Self {
+        CookieExtractionError::KeyringUnavailable.into()
+    }
+
+    /// Create cookie platform not supported error.
+    pub fn cookie_platform_not_supported(platform: impl Into<String>) -> Self {
+        CookieExtractionError::PlatformNotSupported(platform.into()).into()
     }
 }
 
@@ -584,164 +532,3 @@ impl From<url::ParseError> for NetError {
         Self::InvalidUrl
     }
 }
-
-impl From<i32> for NetError {
-    fn from(code: i32) -> Self {
-        match code {
-            -100 => NetError::ConnectionClosed,
-            -101 => NetError::ConnectionReset,
-            -102 => NetError::ConnectionRefused,
-            -103 => NetError::ConnectionAborted,
-            -104 => NetError::ConnectionFailed,
-            -105 => NetError::NameNotResolved,
-            -106 => NetError::InternetDisconnected,
-            -112 => NetError::SocketNotConnected,
-            -107 => NetError::SslProtocolError,
-            -108 => NetError::AddressInvalid,
-            -109 => NetError::AddressUnreachable,
-            -110 => NetError::SslClientAuthCertNeeded,
-            -111 => NetError::TunnelConnectionFailed,
-            -113 => NetError::SslVersionOrCipherMismatch,
-            -114 => NetError::SslRenegotiationRequested,
-            -115 => NetError::ProxyAuthUnsupported,
-            -117 => NetError::BadSslClientAuthCert,
-            -118 => NetError::ConnectionTimedOut,
-            -119 => NetError::HostResolverQueueTooLarge,
-            -120 => NetError::SocksConnectionFailed,
-            -121 => NetError::SocksConnectionHostUnreachable,
-            -122 => NetError::AlpnNegotiationFailed,
-            -123 => NetError::SslNoRenegotiation,
-            -124 => NetError::WinsockUnexpectedWrittenBytes,
-            -125 => NetError::SslDecompressionFailureAlert,
-            -126 => NetError::SslBadRecordMacAlert,
-            -127 => NetError::ProxyAuthRequested,
-            -130 => NetError::ProxyConnectionFailed,
-            -131 => NetError::MandatoryProxyConfigurationFailed,
-            -133 => NetError::PreconnectMaxSocketLimit,
-            -134 => NetError::SslClientAuthPrivateKeyAccessDenied,
-            -135 => NetError::SslClientAuthCertNoPrivateKey,
-            -136 => NetError::ProxyCertificateInvalid,
-            -137 => NetError::NameResolutionFailed,
-            -138 => NetError::NetworkAccessDenied,
-            -139 => NetError::TemporarilyThrottled,
-            -141 => NetError::SslClientAuthSignatureFailed,
-            -142 => NetError::MsgTooBig,
-            -145 => NetError::WsProtocolError,
-            -147 => NetError::AddressInUse,
-            -150 => NetError::SslPinnedKeyNotInCertChain,
-            -151 => NetError::ClientAuthCertTypeUnsupported,
-            -153 => NetError::SslDecryptErrorAlert,
-            -154 => NetError::WsThrottleQueueTooLarge,
-            -156 => NetError::SslServerCertChanged,
-            -159 => NetError::SslUnrecognizedNameAlert,
-            -160 => NetError::SocketSetReceiveBufferSizeError,
-            -161 => NetError::SocketSetSendBufferSizeError,
-            -162 => NetError::SocketReceiveBufferSizeUnchangeable,
-            -163 => NetError::SocketSendBufferSizeUnchangeable,
-            -164 => NetError::SslClientAuthCertBadFormat,
-            -166 => NetError::IcannNameCollision,
-            -167 => NetError::SslServerCertBadFormat,
-            -168 => NetError::CtSthParsingFailed,
-            -169 => NetError::CtSthIncomplete,
-            -170 => NetError::UnableToReuseConnectionForProxyAuth,
-            -171 => NetError::CtConsistencyProofParsingFailed,
-            -172 => NetError::SslObsoleteCipher,
-            -173 => NetError::WsUpgrade,
-            -174 => NetError::ReadIfReadyNotImplemented,
-            -176 => NetError::NoBufferSpace,
-            -177 => NetError::SslClientAuthNoCommonAlgorithms,
-            -178 => NetError::EarlyDataRejected,
-            -179 => NetError::WrongVersionOnEarlyData,
-            -180 => NetError::Tls13DowngradeDetected,
-            -181 => NetError::SslKeyUsageIncompatible,
-            -182 => NetError::InvalidEchConfigList,
-            -183 => NetError::EchNotNegotiated,
-            -184 => NetError::EchFallbackCertificateInvalid,
-            -186 => NetError::ProxyUnableToConnectToDestination,
-            -187 => NetError::ProxyDelegateCanceledConnectRequest,
-            -188 => NetError::ProxyDelegateCanceledConnectResponse,
-
-            -300 => NetError::InvalidUrl,
-            -301 => NetError::DisallowedUrlScheme,
-            -302 => NetError::UnknownUrlScheme,
-            -303 => NetError::InvalidRedirect,
-            -310 => NetError::TooManyRedirects,
-            -311 => NetError::UnsafeRedirect,
-            -312 => NetError::UnsafePort,
-            -320 => NetError::InvalidResponse,
-            -321 => NetError::InvalidChunkedEncoding,
-            -322 => NetError::MethodNotSupported,
-            -323 => NetError::UnexpectedProxyAuth,
-            -324 => NetError::EmptyResponse,
-            -325 => NetError::ResponseHeadersTooBig,
-            -327 => NetError::PacScriptFailed,
-            -328 => NetError::RequestRangeNotSatisfiable,
-            -329 => NetError::MalformedIdentity,
-            -330 => NetError::ContentDecodingFailed,
-            -331 => NetError::NetworkIoSuspended,
-            -336 => NetError::NoSupportedProxies,
-            -337 => NetError::Http2ProtocolError,
-            -338 => NetError::InvalidAuthCredentials,
-            -339 => NetError::UnsupportedAuthScheme,
-            -340 => NetError::EncodingDetectionFailed,
-            -341 => NetError::MissingAuthCredentials,
-            -342 => NetError::UnexpectedSecurityLibraryStatus,
-            -343 => NetError::MisconfiguredAuthEnvironment,
-            -344 => NetError::UndocumentedSecurityLibraryStatus,
-            -345 => NetError::ResponseBodyTooBigToDrain,
-            -346 => NetError::ResponseHeadersMultipleContentLength,
-            -347 => NetError::IncompleteHttp2Headers,
-            -348 => NetError::PacNotInDhcp,
-            -349 => NetError::ResponseHeadersMultipleContentDisposition,
-            -350 => NetError::ResponseHeadersMultipleLocation,
-            -351 => NetError::Http2ServerRefusedStream,
-            -352 => NetError::Http2PingFailed,
-            -354 => NetError::ContentLengthMismatch,
-            -355 => NetError::IncompleteChunkedEncoding,
-            -356 => NetError::QuicProtocolError,
-            -357 => NetError::ResponseHeadersTruncated,
-            -358 => NetError::QuicHandshakeFailed,
-            -360 => NetError::Http2InadequateTransportSecurity,
-            -361 => NetError::Http2FlowControlError,
-            -362 => NetError::Http2FrameSizeError,
-            -363 => NetError::Http2CompressionError,
-            -364 => NetError::ProxyAuthRequestedWithNoConnection,
-            -365 => NetError::Http11Required,
-            -366 => NetError::ProxyHttp11Required,
-            -367 => NetError::PacScriptTerminated,
-            -368 => NetError::ProxyRequired,
-            -370 => NetError::InvalidHttpResponse,
-            -371 => NetError::ContentDecodingInitFailed,
-            -372 => NetError::Http2RstStreamNoErrorReceived,
-            -373 => NetError::Http2PushedStreamNotAvailable,
-            -374 => NetError::Http2ClaimedPushedStreamResetByServer,
-            -375 => NetError::TooManyRetries,
-            -376 => NetError::Http2StreamClosed,
-            -377 => NetError::Http2ClientRefusedStream,
-            -378 => NetError::Http2PushedResponseDoesNotMatch,
-            // Fix critical error code collisions in neterror.rs where custom chromenet errors
-            // overlap with Chromium's Blob error range (-900 to -906), and update loadstate.rs
-            // to include missing load states found in Chromium's load_states_list.h.
-            //
-            // User Review Required
-            // WARNING
-            //
-            // Breaking Change: NetError codes for custom errors (like RedirectCycleDetected)
-            // will change from the -900 range to the -10000 range to avoid future conflicts
-            // with Chromium's Blob errors.
-            -10000 => NetError::RedirectCycleDetected,
-            -10001 => NetError::SocketRemoteClosed,
-            -10002 => NetError::DataReceivedUnexpectedly,
-            -10003 => NetError::CookieInvalidPrefix,
-            -10004 => NetError::CookiePublicSuffix,
-            -10005 => NetError::InvalidHeader,
-            -10006 => NetError::HttpBodyError,
-            -10007 => NetError::InvalidUtf8,
-            -10008 => NetError::JsonParseError,
-            -10009 => NetError::CertPinningFailed,
-            -10010 => NetError::NotImplemented,
-            -10011 => NetError::FileNotFound,
-            _ => NetError::Unknown(code),
-        }
-    }
-}
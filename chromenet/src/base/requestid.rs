@@ -0,0 +1,39 @@
+//! Identifiers of URL requests.
+//!
+//! Chromium equivalent: the identifier from `GenerateURLRequestIdentifier()`
+//! in `net/url_request/url_request.cc`
+//!
+//! Each request gets the next identifier when its job is created and keeps
+//! it across redirects. The job runs in a `URLRequest` span with a
+//! `request.id` field, so the events of its transactions, socket pool
+//! requests, connect jobs and cache lookups all carry it, and
+//! [`HttpResponse::request_id`](crate::http::HttpResponse::request_id)
+//! ties a response back to them.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Process-wide identifier of a URL request, increasing in the order
+/// requests are created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestId(u64);
+
+impl RequestId {
+    /// The identifier for a new request.
+    pub fn next() -> Self {
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The identifier as a number, as logged in the `request.id` field.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,100 @@
+//! Secrets kept out of logs.
+//!
+//! Passwords, `Authorization` values and cookie values end up in structs
+//! that are logged with `{:?}`. [`SecretString`] holds such a value,
+//! prints as `[redacted]` and is zeroized on drop; the value itself is
+//! only reached with [`SecretString::expose`]. Header maps print the
+//! values of [sensitive headers](is_sensitive_header) the same way.
+
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// What a redacted value prints as.
+pub const REDACTED: &str = "[redacted]";
+
+/// A string that prints as `[redacted]` and is zeroized on drop.
+///
+/// ```
+/// use chromenet::base::secret::SecretString;
+///
+/// let password = SecretString::new("hunter2");
+/// assert_eq!(format!("{password:?} {password}"), "[redacted] [redacted]");
+/// assert_eq!(password.expose(), "hunter2");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(Zeroizing::new(secret.into()))
+    }
+
+    /// The secret itself.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl PartialEq<str> for SecretString {
+    fn eq(&self, other: &str) -> bool {
+        self.expose() == other
+    }
+}
+
+impl PartialEq<&str> for SecretString {
+    fn eq(&self, other: &&str) -> bool {
+        self.expose() == *other
+    }
+}
+
+/// Whether the values of header `name` carry credentials or cookies:
+/// `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie`.
+pub fn is_sensitive_header(name: &str) -> bool {
+    [
+        "authorization",
+        "proxy-authorization",
+        "cookie",
+        "set-cookie",
+    ]
+    .iter()
+    .any(|sensitive| name.eq_ignore_ascii_case(sensitive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_redacted() {
+        let secret = SecretString::from("s3cret".to_string());
+        assert_eq!(format!("{secret:?}"), REDACTED);
+        assert_eq!(format!("{:?}", Some(&secret)), "Some([redacted])");
+        assert_eq!(secret, "s3cret");
+        assert!(is_sensitive_header("Proxy-Authorization"));
+        assert!(!is_sensitive_header("Accept"));
+    }
+}
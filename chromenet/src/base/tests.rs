@@ -32,3 +32,45 @@ fn test_collision_avoidance() {
     let redirect_error = NetError::RedirectCycleDetected;
     assert!(!blob_range.contains(&redirect_error.as_i32()));
 }
+
+#[test]
+fn test_connection_attempts_code_follows_last_attempt() {
+    use crate::base::neterror::ConnectionAttempt;
+    use std::time::Duration;
+
+    let attempt = |addr: &str, error| ConnectionAttempt {
+        address: addr.parse().unwrap(),
+        error,
+        duration: Duration::from_millis(5),
+    };
+    let err = NetError::ConnectionAttemptsFailed {
+        host: "example.com".into(),
+        port: 443,
+        attempts: vec![
+            attempt("[::1]:443", NetError::ConnectionTimedOut),
+            attempt("127.0.0.1:443", NetError::ConnectionRefused),
+        ],
+    };
+    assert_eq!(err.as_i32(), -102);
+    assert_eq!(
+        err.to_string(),
+        "Connection to example.com:443 failed after 2 attempts"
+    );
+
+    let empty = NetError::ConnectionAttemptsFailed {
+        host: "example.com".into(),
+        port: 443,
+        attempts: Vec::new(),
+    };
+    assert_eq!(empty.as_i32(), -104);
+}
+
+#[test]
+fn test_request_ids_increase() {
+    use crate::base::requestid::RequestId;
+
+    let first = RequestId::next();
+    let second = RequestId::next();
+    assert!(second > first);
+    assert_eq!(first.to_string(), first.get().to_string());
+}
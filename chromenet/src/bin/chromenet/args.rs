@@ -0,0 +1,359 @@
+//! Command line parsing.
+//!
+//! Follows curl where the flags overlap: short flags take their value as
+//! the next argument or attached (`-XPOST`), long flags as the next
+//! argument or after `=`.
+
+use chromenet::base::secret::SecretString;
+use chromenet::socket::connectto::ConnectTo;
+use chromenet::socket::tls::ImpersonateTarget;
+use http::{HeaderName, HeaderValue, Method};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(feature = "browser-cookies")]
+use chromenet::cookies::browser::Browser;
+
+pub const USAGE: &str = "\
+Usage: chromenet [options] <url>
+
+Options:
+  -X, --request <method>        Request method (default GET, POST with --data)
+  -H, --header <name: value>    Add a request header, repeatable
+  -d, --data <data>             Send a form body; @file reads it from a file
+  -A, --user-agent <ua>         User-Agent to send
+  -b, --cookie <name=value>     Cookie header to send
+  -e, --referer <url>           Referer to send
+  -u, --user <user:password>    Credentials for Basic or Digest challenges
+  -x, --proxy <url>             HTTP, HTTPS or SOCKS5 proxy
+      --connect-to <host:port:target:tport>
+                                Connect to target:tport for host:port, repeatable
+  -m, --max-time <seconds>      Time limit for the whole request
+  -i, --include                 Print the response head before the body
+  -I, --head                    Send a HEAD request and print the head
+  -o, --output <file>           Write the body to a file
+  -s, --silent                  Do not report errors
+  -0, --http1.0                 Send an HTTP/1.0 request
+      --http1.1                 Only use HTTP/1.1
+      --http2-prior-knowledge   Use HTTP/2 without negotiating it
+      --impersonate <target>    Impersonate a browser, e.g. chrome124
+      --browser-cookies <browser[:domain]>
+                                Send cookies of an installed browser
+      --ja3-print               Print the JA3 fingerprint of each ClientHello
+      --har <file>              Write a HAR log of the request, - for stdout;
+                                gzipped if the name ends in .gz
+  -h, --help                    Print this help
+  -V, --version                 Print the version
+
+Targets: chrome124 chrome128 firefox128 firefox129 safari17 safari18
+         okhttp4 okhttp5
+";
+
+/// What the command line asks for.
+#[derive(Debug)]
+pub enum Command {
+    Help,
+    Version,
+    Fetch(Box<Args>),
+}
+
+/// HTTP version restriction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpVersion {
+    Http10,
+    Http1,
+    Http2PriorKnowledge,
+}
+
+/// Options of a request.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub url: String,
+    pub method: Option<Method>,
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub data: Option<Vec<u8>>,
+    pub user: Option<(String, SecretString)>,
+    pub proxy: Option<String>,
+    pub connect_to: Vec<ConnectTo>,
+    pub max_time: Option<Duration>,
+    pub include: bool,
+    pub head: bool,
+    pub output: Option<PathBuf>,
+    pub silent: bool,
+    pub http_version: Option<HttpVersion>,
+    pub impersonate: Option<ImpersonateTarget>,
+    #[cfg(feature = "browser-cookies")]
+    pub browser_cookies: Vec<(Browser, Option<String>)>,
+    pub ja3_print: bool,
+    pub har: Option<PathBuf>,
+}
+
+impl Args {
+    /// The method to send.
+    pub fn method(&self) -> Method {
+        match &self.method {
+            Some(method) => method.clone(),
+            None if self.head => Method::HEAD,
+            None if self.data.is_some() => Method::POST,
+            None => Method::GET,
+        }
+    }
+}
+
+/// Parse the arguments after the program name.
+pub fn parse<I>(args: I) -> Result<Command, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    let mut parsed = Args::default();
+    let mut url = None;
+
+    while let Some(arg) = args.next() {
+        let (flag, attached) = split_flag(&arg);
+        let mut value = || {
+            attached
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("option {} needs a value", flag))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-X" | "--request" => {
+                let method = value()?;
+                let method = Method::from_bytes(method.as_bytes())
+                    .map_err(|_| format!("invalid method {:?}", method))?;
+                parsed.method = Some(method);
+            }
+            "-H" | "--header" => parsed.headers.push(header(&value()?)?),
+            "-d" | "--data" => {
+                let data = data(&value()?)?;
+                // Repeated data is joined like form fields
+                match &mut parsed.data {
+                    Some(body) => {
+                        body.push(b'&');
+                        body.extend(data);
+                    }
+                    None => parsed.data = Some(data),
+                }
+            }
+            "-A" | "--user-agent" => parsed.headers.push(named("user-agent", &value()?)?),
+            "-b" | "--cookie" => parsed.headers.push(named("cookie", &value()?)?),
+            "-e" | "--referer" => parsed.headers.push(named("referer", &value()?)?),
+            "-u" | "--user" => {
+                let user = value()?;
+                let (name, password) = user.split_once(':').unwrap_or((&user, ""));
+                parsed.user = Some((name.to_string(), SecretString::new(password)));
+            }
+            "-x" | "--proxy" => parsed.proxy = Some(value()?),
+            "--connect-to" => {
+                let value = value()?;
+                let rule = ConnectTo::parse(&value)
+                    .ok_or_else(|| format!("invalid --connect-to {:?}", value))?;
+                parsed.connect_to.push(rule);
+            }
+            "-m" | "--max-time" => {
+                let seconds = value()?;
+                let seconds: f64 = seconds
+                    .parse()
+                    .ok()
+                    .filter(|s: &f64| s.is_finite() && *s > 0.0)
+                    .ok_or_else(|| format!("invalid time {:?}", seconds))?;
+                parsed.max_time = Some(Duration::from_secs_f64(seconds));
+            }
+            "-i" | "--include" => parsed.include = true,
+            "-I" | "--head" => parsed.head = true,
+            "-o" | "--output" => parsed.output = Some(value()?.into()),
+            "-s" | "--silent" => parsed.silent = true,
+            "-0" | "--http1.0" => parsed.http_version = Some(HttpVersion::Http10),
+            "--http1.1" => parsed.http_version = Some(HttpVersion::Http1),
+            "--http2-prior-knowledge" => {
+                parsed.http_version = Some(HttpVersion::Http2PriorKnowledge)
+            }
+            "--impersonate" => parsed.impersonate = Some(target(&value()?)?),
+            "--browser-cookies" => browser_cookies(&mut parsed, &value()?)?,
+            "--ja3-print" => parsed.ja3_print = true,
+            "--har" => parsed.har = Some(value()?.into()),
+            _ if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option {}", flag));
+            }
+            _ if url.is_none() => url = Some(arg),
+            _ => return Err(format!("unexpected argument {:?}", arg)),
+        }
+    }
+
+    parsed.url = url.ok_or("no URL given")?;
+    Ok(Command::Fetch(Box::new(parsed)))
+}
+
+/// Split `--flag=value` and `-Xvalue` into the flag and its value.
+fn split_flag(arg: &str) -> (String, Option<String>) {
+    if let Some(long) = arg.strip_prefix("--") {
+        return match long.split_once('=') {
+            Some((flag, value)) => (format!("--{}", flag), Some(value.to_string())),
+            None => (arg.to_string(), None),
+        };
+    }
+    match arg.char_indices().nth(2) {
+        Some((i, _)) if arg.starts_with('-') => (arg[..i].to_string(), Some(arg[i..].to_string())),
+        _ => (arg.to_string(), None),
+    }
+}
+
+/// A `Name: value` header.
+fn header(line: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = line
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header {:?}", line))?;
+    named(name.trim(), value.trim())
+}
+
+fn named(name: &str, value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("invalid header name {:?}", name))?;
+    let value = HeaderValue::from_str(value).map_err(|_| format!("invalid value for {}", name))?;
+    Ok((name, value))
+}
+
+/// Body data, read from a file for `@path`.
+fn data(value: &str) -> Result<Vec<u8>, String> {
+    match value.strip_prefix('@') {
+        Some(path) => std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e)),
+        None => Ok(value.as_bytes().to_vec()),
+    }
+}
+
+fn target(name: &str) -> Result<ImpersonateTarget, String> {
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "chrome124" => ImpersonateTarget::Chrome124,
+        "chrome128" => ImpersonateTarget::Chrome128,
+        "firefox128" => ImpersonateTarget::Firefox128,
+        "firefox129" => ImpersonateTarget::Firefox129,
+        "safari17" => ImpersonateTarget::Safari17,
+        "safari18" => ImpersonateTarget::Safari18,
+        "okhttp4" => ImpersonateTarget::OkHttp4,
+        "okhttp5" => ImpersonateTarget::OkHttp5,
+        _ => return Err(format!("unknown impersonation target {:?}", name)),
+    })
+}
+
+#[cfg(feature = "browser-cookies")]
+fn browser_cookies(parsed: &mut Args, value: &str) -> Result<(), String> {
+    let (name, domain) = match value.split_once(':') {
+        Some((name, domain)) => (name, Some(domain.to_string())),
+        None => (value, None),
+    };
+    let browser = match name.to_ascii_lowercase().as_str() {
+        "chrome" => Browser::Chrome,
+        "chromium" => Browser::Chromium,
+        "edge" => Browser::Edge,
+        "brave" => Browser::Brave,
+        "opera" => Browser::Opera,
+        "opera-gx" | "operagx" => Browser::OperaGx,
+        "vivaldi" => Browser::Vivaldi,
+        "arc" => Browser::Arc,
+        "firefox" => Browser::Firefox,
+        "safari" => Browser::Safari,
+        _ => return Err(format!("unknown browser {:?}", name)),
+    };
+    parsed.browser_cookies.push((browser, domain));
+    Ok(())
+}
+
+#[cfg(not(feature = "browser-cookies"))]
+fn browser_cookies(_parsed: &mut Args, _value: &str) -> Result<(), String> {
+    Err("--browser-cookies needs the browser-cookies feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch(args: &[&str]) -> Args {
+        match parse(args.iter().map(|a| a.to_string())).unwrap() {
+            Command::Fetch(args) => *args,
+            command => panic!("{:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_curl_like_flags() {
+        let args = fetch(&[
+            "-XPUT",
+            "-H",
+            "X-Test: 1",
+            "--header=Accept: */*",
+            "-d",
+            "a=1",
+            "--data",
+            "b=2",
+            "-m",
+            "2.5",
+            "-i",
+            "https://example.com/",
+        ]);
+        assert_eq!(args.url, "https://example.com/");
+        assert_eq!(args.method(), Method::PUT);
+        assert_eq!(args.headers.len(), 2);
+        assert_eq!(args.headers[1].1, "*/*");
+        assert_eq!(args.data.as_deref(), Some(&b"a=1&b=2"[..]));
+        assert_eq!(args.max_time, Some(Duration::from_millis(2500)));
+        assert!(args.include);
+    }
+
+    #[test]
+    fn test_default_methods() {
+        assert_eq!(fetch(&["http://a.test"]).method(), Method::GET);
+        assert_eq!(fetch(&["-I", "http://a.test"]).method(), Method::HEAD);
+        assert_eq!(fetch(&["-d", "x", "http://a.test"]).method(), Method::POST);
+    }
+
+    #[test]
+    fn test_impersonate_and_extras() {
+        let args = fetch(&[
+            "--impersonate",
+            "Chrome124",
+            "--ja3-print",
+            "--har",
+            "-",
+            "-u",
+            "user:pa:ss",
+            "--connect-to",
+            "a.test:80:127.0.0.1:8080",
+            "-0",
+            "http://a.test",
+        ]);
+        assert_eq!(args.impersonate, Some(ImpersonateTarget::Chrome124));
+        assert!(args.ja3_print);
+        assert_eq!(args.har, Some(PathBuf::from("-")));
+        assert_eq!(args.user, Some(("user".into(), "pa:ss".into())));
+        assert_eq!(args.http_version, Some(HttpVersion::Http10));
+        assert_eq!(
+            args.connect_to,
+            [ConnectTo::new("a.test", 80, "127.0.0.1", 8080).unwrap()]
+        );
+    }
+
+    #[cfg(feature = "browser-cookies")]
+    #[test]
+    fn test_browser_cookies() {
+        let args = fetch(&["--browser-cookies", "chrome:example.com", "http://a.test"]);
+        assert_eq!(
+            args.browser_cookies,
+            [(Browser::Chrome, Some("example.com".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let try_parse = |args: &[&str]| parse(args.iter().map(|a| a.to_string()));
+        assert!(try_parse(&[]).is_err());
+        assert!(try_parse(&["-H"]).is_err());
+        assert!(try_parse(&["--nope", "http://a.test"]).is_err());
+        assert!(try_parse(&["--impersonate", "netscape4", "http://a.test"]).is_err());
+        assert!(try_parse(&["--connect-to", "a.test:80", "http://a.test"]).is_err());
+        assert!(try_parse(&["http://a.test", "http://b.test"]).is_err());
+        assert!(matches!(try_parse(&["-h"]), Ok(Command::Help)));
+    }
+}
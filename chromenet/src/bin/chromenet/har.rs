@@ -0,0 +1,199 @@
+//! HAR 1.2 logs of a request.
+//!
+//! The request headers are those given on the command line; what an
+//! emulation profile adds is not visible to the CLI.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Version};
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// What was sent.
+pub struct HarRequest<'a> {
+    pub method: &'a Method,
+    pub url: &'a str,
+    pub headers: &'a [(HeaderName, HeaderValue)],
+    pub body: Option<&'a [u8]>,
+}
+
+/// What came back.
+pub struct HarResponse<'a> {
+    pub url: &'a str,
+    pub status: StatusCode,
+    pub version: Version,
+    pub headers: &'a HeaderMap,
+    pub body: &'a [u8],
+}
+
+/// When the request started and how long its parts took.
+pub struct HarTimings {
+    pub started: SystemTime,
+    /// Until the response head arrived
+    pub wait: Duration,
+    /// Reading the body
+    pub receive: Duration,
+}
+
+/// A HAR log with a single entry.
+pub fn log(request: &HarRequest<'_>, response: &HarResponse<'_>, timings: &HarTimings) -> Value {
+    let started = OffsetDateTime::from(timings.started)
+        .format(&Rfc3339)
+        .unwrap_or_default();
+    let wait = millis(timings.wait);
+    let receive = millis(timings.receive);
+
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {"name": "chromenet", "version": env!("CARGO_PKG_VERSION")},
+            "pages": [],
+            "entries": [{
+                "startedDateTime": started,
+                "time": wait + receive,
+                "request": request_json(request),
+                "response": response_json(response),
+                "cache": {},
+                "timings": {"send": 0, "wait": wait, "receive": receive},
+            }],
+        }
+    })
+}
+
+fn request_json(request: &HarRequest<'_>) -> Value {
+    let query: Vec<Value> = url::Url::parse(request.url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| json!({"name": name, "value": value}))
+                .collect()
+        })
+        .unwrap_or_default();
+    let headers: Vec<Value> = request
+        .headers
+        .iter()
+        .map(|(name, value)| header_json(name, value))
+        .collect();
+    let mut json = json!({
+        "method": request.method.as_str(),
+        "url": request.url,
+        "httpVersion": "",
+        "cookies": [],
+        "headers": headers,
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": request.body.map_or(0, |body| body.len() as i64),
+    });
+    if let Some(body) = request.body {
+        let mime_type = request
+            .headers
+            .iter()
+            .find(|(name, _)| name == http::header::CONTENT_TYPE)
+            .and_then(|(_, value)| value.to_str().ok())
+            .unwrap_or("application/x-www-form-urlencoded");
+        json["postData"] = json!({
+            "mimeType": mime_type,
+            "text": String::from_utf8_lossy(body),
+        });
+    }
+    json
+}
+
+fn response_json(response: &HarResponse<'_>) -> Value {
+    let headers: Vec<Value> = response
+        .headers
+        .iter()
+        .map(|(name, value)| header_json(name, value))
+        .collect();
+    let mime_type = response
+        .headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let mut content = json!({
+        "size": response.body.len(),
+        "mimeType": mime_type,
+    });
+    match std::str::from_utf8(response.body) {
+        Ok(text) => content["text"] = json!(text),
+        Err(_) => {
+            content["text"] = json!(STANDARD.encode(response.body));
+            content["encoding"] = json!("base64");
+        }
+    }
+    let redirect_url = response
+        .headers
+        .get(http::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    json!({
+        "url": response.url,
+        "status": response.status.as_u16(),
+        "statusText": response.status.canonical_reason().unwrap_or(""),
+        "httpVersion": format!("{:?}", response.version),
+        "cookies": [],
+        "headers": headers,
+        "content": content,
+        "redirectURL": redirect_url,
+        "headersSize": -1,
+        "bodySize": -1,
+    })
+}
+
+fn header_json(name: &HeaderName, value: &HeaderValue) -> Value {
+    json!({
+        "name": name.as_str(),
+        "value": String::from_utf8_lossy(value.as_bytes()),
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_micros() as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log() {
+        let headers = [(
+            HeaderName::from_static("content-type"),
+            HeaderValue::from_static("text/plain"),
+        )];
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert("content-type", HeaderValue::from_static("image/png"));
+        let log = log(
+            &HarRequest {
+                method: &Method::POST,
+                url: "https://example.com/upload?a=1",
+                headers: &headers,
+                body: Some(&b"hi"[..]),
+            },
+            &HarResponse {
+                url: "https://example.com/upload?a=1",
+                status: StatusCode::CREATED,
+                version: Version::HTTP_2,
+                headers: &response_headers,
+                body: &[0x89, 0x50, 0xff],
+            },
+            &HarTimings {
+                started: SystemTime::UNIX_EPOCH,
+                wait: Duration::from_millis(20),
+                receive: Duration::from_millis(5),
+            },
+        );
+
+        let entry = &log["log"]["entries"][0];
+        assert_eq!(entry["startedDateTime"], "1970-01-01T00:00:00Z");
+        assert_eq!(entry["time"], 25.0);
+        assert_eq!(entry["request"]["queryString"][0]["name"], "a");
+        assert_eq!(entry["request"]["postData"]["mimeType"], "text/plain");
+        assert_eq!(entry["response"]["status"], 201);
+        assert_eq!(entry["response"]["httpVersion"], "HTTP/2.0");
+        assert_eq!(entry["response"]["content"]["encoding"], "base64");
+        assert_eq!(entry["response"]["content"]["text"], "iVD/");
+    }
+}
@@ -0,0 +1,127 @@
+//! JA3 fingerprints of the ClientHellos a client sends.
+//!
+//! The hellos are taken from the raw traffic of the client's connections,
+//! see [`chromenet::socket::wire`], so they are exactly what went out.
+//! JA3 is `version,ciphers,extensions,groups,point formats` with GREASE
+//! values left out; extension order is part of it, so browsers that
+//! permute extensions get a new JA3 on every connection.
+
+use chromenet::socket::tls::ClientHello;
+use chromenet::socket::wire::{Direction, WireEvent, WireLayer, WireSink};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Collects the first ClientHello of every connection.
+#[derive(Clone, Default)]
+pub struct Ja3Recorder {
+    inner: Arc<Mutex<Recorded>>,
+}
+
+#[derive(Default)]
+struct Recorded {
+    /// Bytes of hellos still arriving, by connection
+    pending: HashMap<u64, Vec<u8>>,
+    done: HashSet<u64>,
+    hellos: Vec<ClientHello>,
+}
+
+impl Ja3Recorder {
+    /// The hellos sent so far, in order.
+    pub fn hellos(&self) -> Vec<ClientHello> {
+        self.inner.lock().unwrap().hellos.clone()
+    }
+}
+
+impl WireSink for Ja3Recorder {
+    fn record(&self, event: &WireEvent<'_>) {
+        if event.layer != WireLayer::Raw || event.direction != Direction::Sent {
+            return;
+        }
+        let mut recorded = self.inner.lock().unwrap();
+        if recorded.done.contains(&event.connection) {
+            return;
+        }
+        let buf = recorded.pending.entry(event.connection).or_default();
+        // Skip what precedes the handshake, e.g. a proxy CONNECT
+        if buf.is_empty() && event.data.first() != Some(&0x16) {
+            return;
+        }
+        buf.extend_from_slice(event.data);
+        let Some(len) = buf
+            .get(3..5)
+            .map(|len| 5 + u16::from_be_bytes([len[0], len[1]]) as usize)
+        else {
+            return;
+        };
+        if buf.len() < len {
+            return;
+        }
+
+        let buf = recorded
+            .pending
+            .remove(&event.connection)
+            .unwrap_or_default();
+        recorded.done.insert(event.connection);
+        if let Some(hello) = ClientHello::parse(&buf[..len]) {
+            recorded.hellos.push(hello);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A record with a ClientHello carrying `extensions`.
+    fn record(ciphers: &[u16], extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut hello = vec![0x03, 0x03];
+        hello.extend([0; 32]);
+        hello.push(0); // Session ID
+        hello.extend(((ciphers.len() * 2) as u16).to_be_bytes());
+        for cipher in ciphers {
+            hello.extend(cipher.to_be_bytes());
+        }
+        hello.extend([1, 0]); // Null compression
+        let mut ext = Vec::new();
+        for (kind, data) in extensions {
+            ext.extend(kind.to_be_bytes());
+            ext.extend((data.len() as u16).to_be_bytes());
+            ext.extend(data);
+        }
+        hello.extend((ext.len() as u16).to_be_bytes());
+        hello.extend(ext);
+
+        let mut handshake = vec![0x01];
+        handshake.extend(&(hello.len() as u32).to_be_bytes()[1..]);
+        handshake.extend(hello);
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend((handshake.len() as u16).to_be_bytes());
+        record.extend(handshake);
+        record
+    }
+
+    #[test]
+    fn test_recorder_reassembles_hello() {
+        let record = record(&[0x1301], &[(43, vec![2, 3, 4])]);
+        let recorder = Ja3Recorder::default();
+        let send = |connection, data: &[u8]| {
+            recorder.record(&WireEvent {
+                connection,
+                layer: WireLayer::Raw,
+                direction: Direction::Sent,
+                data,
+            })
+        };
+        send(1, b"CONNECT a.test:443 HTTP/1.1\r\n\r\n");
+        send(1, &record[..10]);
+        assert!(recorder.hellos().is_empty());
+        send(1, &record[10..]);
+        // Later records of the connection are not hellos
+        send(1, &record);
+        send(2, b"GET / HTTP/1.1\r\n\r\n");
+
+        let hellos = recorder.hellos();
+        assert_eq!(hellos.len(), 1);
+        assert_eq!(hellos[0].ja3(), "771,4865,43,,");
+    }
+}
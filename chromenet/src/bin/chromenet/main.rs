@@ -0,0 +1,204 @@
+//! `chromenet`: curl-like requests through the library.
+//!
+//! Built with the `cli` feature:
+//!
+//! ```text
+//! cargo run --features cli -- --impersonate chrome124 -i https://example.com/
+//! ```
+//!
+//! Everything goes through the public API, so the binary doubles as an
+//! integration test of it.
+
+mod args;
+mod har;
+mod ja3;
+
+use args::{Args, Command, HttpVersion};
+use chromenet::cookies::monster::CookieMonster;
+use chromenet::socket::proxy::ProxySettings;
+use chromenet::Client;
+use har::{HarRequest, HarResponse, HarTimings};
+use ja3::Ja3Recorder;
+use std::error::Error;
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::{Instant, SystemTime};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(Command::Fetch(args)) => args,
+        Ok(Command::Help) => {
+            print!("{}", args::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("chromenet {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!(
+                "chromenet: {}\nTry 'chromenet --help' for more information.",
+                e
+            );
+            return ExitCode::from(2);
+        }
+    };
+
+    let silent = args.silent;
+    match run(*args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if !silent {
+                eprintln!("chromenet: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let ja3 = Ja3Recorder::default();
+    let client = client(&args, &ja3)?;
+
+    let method = args.method();
+    let mut request = client.request(method.clone(), &args.url);
+    for (name, value) in &args.headers {
+        request = request.header(name.clone(), value.clone());
+    }
+    if let Some(data) = &args.data {
+        let has_type = args
+            .headers
+            .iter()
+            .any(|(name, _)| name == http::header::CONTENT_TYPE);
+        if !has_type {
+            request = request.header(
+                http::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            );
+        }
+        request = request.body(data.clone());
+    }
+    if let Some((user, password)) = &args.user {
+        request = request.credentials(user, password.expose());
+    }
+    if let Some(max_time) = args.max_time {
+        request = request.timeout(max_time);
+    }
+    for rule in &args.connect_to {
+        request = request.connect_to(rule.clone());
+    }
+    request = match args.http_version {
+        Some(HttpVersion::Http10) => request.http1_0(),
+        Some(HttpVersion::Http1) => request.http1_only(),
+        Some(HttpVersion::Http2PriorKnowledge) => request.http2_prior_knowledge(),
+        None => request,
+    };
+
+    let started = SystemTime::now();
+    let start = Instant::now();
+    let response = request.send().await?;
+    let wait = start.elapsed();
+
+    let status = response.status();
+    let version = response.version();
+    let status_line = response.status_line();
+    let headers = response.headers().clone();
+    let url = response
+        .url()
+        .map_or_else(|| args.url.clone(), |url| url.to_string());
+    let body = response.bytes().await?;
+    let receive = start.elapsed() - wait;
+
+    let mut stdout = std::io::stdout().lock();
+    if args.include || args.head {
+        // HTTP/2 has no status line
+        let status_line = status_line.unwrap_or_else(|| {
+            let reason = status.canonical_reason().unwrap_or("");
+            format!("{:?} {} {}", version, status.as_u16(), reason)
+        });
+        write!(stdout, "{}\r\n", status_line)?;
+        for (name, value) in &headers {
+            stdout.write_all(name.as_str().as_bytes())?;
+            stdout.write_all(b": ")?;
+            stdout.write_all(value.as_bytes())?;
+            stdout.write_all(b"\r\n")?;
+        }
+        stdout.write_all(b"\r\n")?;
+    }
+    if !args.head {
+        match &args.output {
+            Some(path) => std::fs::write(path, &body)?,
+            None => stdout.write_all(&body)?,
+        }
+    }
+    stdout.flush()?;
+    drop(stdout);
+
+    if args.ja3_print {
+        for hello in ja3.hellos() {
+            eprintln!("JA3: {}", hello.ja3());
+            eprintln!("JA3 hash: {}", hello.ja3_hash());
+        }
+    }
+
+    if let Some(path) = &args.har {
+        let log = har::log(
+            &HarRequest {
+                method: &method,
+                url: &args.url,
+                headers: &args.headers,
+                body: args.data.as_deref(),
+            },
+            &HarResponse {
+                url: &url,
+                status,
+                version,
+                headers: &headers,
+                body: &body,
+            },
+            &HarTimings {
+                started,
+                wait,
+                receive,
+            },
+        );
+        let json = serde_json::to_string_pretty(&log)? + "\n";
+        if path.as_os_str() == "-" {
+            print!("{}", json);
+        } else if path.extension().is_some_and(|ext| ext == "gz") {
+            let file = std::fs::File::create(path)?;
+            let mut gz = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            gz.write_all(json.as_bytes())?;
+            gz.finish()?;
+        } else {
+            std::fs::write(path, json)?;
+        }
+    }
+    Ok(())
+}
+
+/// The client for `args`, recording ClientHellos to `ja3` if asked to.
+fn client(args: &Args, ja3: &Ja3Recorder) -> Result<Client, Box<dyn Error>> {
+    let mut builder = Client::builder();
+    if let Some(target) = args.impersonate {
+        builder = builder.impersonate(target);
+    }
+    if let Some(proxy) = &args.proxy {
+        let proxy = ProxySettings::new(proxy).ok_or_else(|| format!("invalid proxy {}", proxy))?;
+        builder = builder.proxy(proxy);
+    }
+    if args.ja3_print {
+        builder = builder.wire_capture(ja3.clone());
+    }
+
+    let jar = CookieMonster::new();
+    #[cfg(feature = "browser-cookies")]
+    for (browser, domain) in &args.browser_cookies {
+        let count = jar.import_from_browser(*browser, domain.as_deref())?;
+        if !args.silent {
+            eprintln!("chromenet: {} cookies from {:?}", count, browser);
+        }
+    }
+    Ok(builder.cookie_store(jar).build())
+}
@@ -18,16 +18,63 @@
 //! ```
 
 use crate::base::neterror::NetError;
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::base::secret::SecretString;
 use crate::cookies::monster::CookieMonster;
+use crate::dns::{PostResolve, Resolve};
+use crate::emulation::useragent::{self, UaConsistency, UserAgent};
+#[cfg(feature = "emulation-profiles")]
+use crate::emulation::Impersonate;
 use crate::emulation::{Emulation, EmulationFactory};
-use crate::http::streamfactory::HttpStreamFactory;
-use crate::socket::pool::ClientSocketPool;
+use crate::http::batch::Batch;
+use crate::http::conditional::{Conditional, EntityTag, Validators};
+use crate::http::contentdecoder::ContentDecoders;
+use crate::http::contentdigest::ContentDigest;
+use crate::http::headerlimits::HeaderLimits;
+use crate::http::httpcache::{CacheMode, HttpCache};
+use crate::http::httpdate::format_http_date;
+use crate::http::orderedheaders::OrderedHeaderMap;
+use crate::http::prefetch::Prefetcher;
+use crate::http::priority::PriorityHeader;
+use crate::http::ratelimit::RateLimiter;
+use crate::http::requestbody::RequestBody;
+use crate::http::requestparts::RequestParts;
+use crate::http::resumable::ResumableUpload;
+use crate::http::serverproperties::HttpServerProperties;
+use crate::http::shareddictionary::SharedDictionaryStore;
+use crate::http::shutdown::{Lifecycle, ShutdownHook, ShutdownReport};
+use crate::http::singleflight::{FlightKey, SingleFlight};
+use crate::http::streamfactory::{H2SessionPolicy, H2cMode, HttpStreamFactory, HttpVersionPref};
+use crate::http::tracecontext::{TraceContext, TracePropagator};
+use crate::metrics::{MetricsRecorder, RequestMetrics};
+#[cfg(feature = "quic")]
+use crate::quic::QuicOutcomes;
+use crate::socket::authcache::AuthCache;
+use crate::socket::connectjob::ConnectTimeouts;
+use crate::socket::connectto::ConnectTo;
+use crate::socket::grouplimits::GroupLimits;
+use crate::socket::hooks::ConnectHooks;
+use crate::socket::lifetime::ConnectionLifetime;
+use crate::socket::pool::{ClientSocketPool, ConnectionReuse, RequestPriority};
 use crate::socket::proxy::ProxySettings;
-use crate::socket::tls::TlsOptions;
+use crate::socket::socketfactory::SocketFactory;
+use crate::socket::systemproxy::SystemProxyConfig;
+use crate::socket::tls::{ServerName, TlsOptions, TlsOptionsBuilder, TlsOverrides};
+use crate::socket::wire::{SocketInstrumentation, WireSink};
+use crate::tls::certverify::CertPolicy;
+use crate::tls::hsts::HstsStore;
+use crate::tls::persister::TransportSecurityPersister;
+use crate::urlrequest::challenge::Challenges;
 use crate::urlrequest::job::URLRequestHttpJob;
+use crate::urlrequest::redirect::DEFAULT_MAX_REDIRECT_DRAIN;
+use crate::urlrequest::robots::Robots;
+use crate::urlrequest::rules::{ResourceType, RuleSet};
+use crate::urlrequest::urllimits::UrlLimits;
 use http::Method;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+use tracing::field::Empty;
+use tracing::Instrument;
 use url::Url;
 
 /// HTTP Client for making requests.
@@ -39,9 +86,42 @@ pub struct Client {
     pool: Arc<ClientSocketPool>,
     factory: Arc<HttpStreamFactory>,
     cookie_store: Arc<CookieMonster>,
+    auth_cache: AuthCache,
     emulation: Option<Emulation>,
     proxy: Option<ProxySettings>,
+    /// Proxies of the environment or OS, used without an explicit proxy
+    system_proxy: Option<SystemProxyConfig>,
+    network_isolation_key: Option<NetworkIsolationKey>,
     timeout: Option<Duration>,
+    version_pref: HttpVersionPref,
+    single_flight: Option<SingleFlight>,
+    trace_propagator: Option<Arc<dyn TracePropagator>>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    /// Base for relative request URLs, `Err` with the reason if invalid
+    base_url: Option<Result<Url, String>>,
+    default_headers: http::HeaderMap,
+    removed_default_headers: Vec<String>,
+    /// Whether `user_agent` was given an invalid header value
+    invalid_user_agent: bool,
+    ua_consistency: UaConsistency,
+    rate_limiter: Option<RateLimiter>,
+    allow_sni_override: bool,
+    post_resolve: Option<PostResolve>,
+    rules: Option<Arc<RuleSet>>,
+    robots: Option<Robots>,
+    hsts: Option<HstsStore>,
+    follow_refresh: bool,
+    max_redirect_drain: usize,
+    header_limits: HeaderLimits,
+    url_limits: UrlLimits,
+    cache: Option<Arc<HttpCache>>,
+    shared_dictionaries: Option<SharedDictionaryStore>,
+    content_decoders: Option<ContentDecoders>,
+    content_digest: Option<ContentDigest>,
+    challenges: Option<Challenges>,
+    cert_policy: CertPolicy,
+    priority_header: Option<PriorityHeader>,
+    lifecycle: Arc<Lifecycle>,
 }
 
 impl Default for Client {
@@ -53,15 +133,46 @@ impl Default for Client {
 impl Client {
     /// Create a new client with default settings.
     pub fn new() -> Self {
+        let pool = Arc::new(ClientSocketPool::default());
+        let lifecycle = Arc::new(Lifecycle::default());
+        start_pool_cleanup(&pool, &lifecycle);
         Self {
-            pool: Arc::new(ClientSocketPool::default()),
-            factory: Arc::new(HttpStreamFactory::new(
-                Arc::new(ClientSocketPool::default()),
-            )),
+            factory: Arc::new(HttpStreamFactory::new(pool.clone())),
+            pool,
             cookie_store: Arc::new(CookieMonster::new()),
+            auth_cache: AuthCache::new(),
             emulation: None,
             proxy: None,
+            system_proxy: SystemProxyConfig::detect(),
+            network_isolation_key: None,
             timeout: None,
+            version_pref: HttpVersionPref::default(),
+            single_flight: None,
+            trace_propagator: None,
+            metrics: None,
+            base_url: None,
+            default_headers: http::HeaderMap::new(),
+            removed_default_headers: Vec::new(),
+            invalid_user_agent: false,
+            ua_consistency: UaConsistency::Off,
+            rate_limiter: None,
+            allow_sni_override: false,
+            post_resolve: None,
+            rules: None,
+            robots: None,
+            hsts: None,
+            follow_refresh: false,
+            max_redirect_drain: DEFAULT_MAX_REDIRECT_DRAIN,
+            header_limits: HeaderLimits::default(),
+            url_limits: UrlLimits::default(),
+            cache: None,
+            shared_dictionaries: None,
+            content_decoders: None,
+            content_digest: None,
+            challenges: None,
+            cert_policy: CertPolicy::default(),
+            priority_header: None,
+            lifecycle,
         }
     }
 
@@ -70,6 +181,79 @@ impl Client {
         ClientBuilder::default()
     }
 
+    /// Create a client impersonating a browser.
+    ///
+    /// Shorthand for `Client::builder().impersonate(target).build()`.
+    #[cfg(feature = "emulation-profiles")]
+    pub fn impersonate<T: Into<Impersonate>>(target: T) -> Self {
+        Self::builder().impersonate(target).build()
+    }
+
+    /// Get the client's cookie jar.
+    pub fn cookie_store(&self) -> &Arc<CookieMonster> {
+        &self.cookie_store
+    }
+
+    /// Get what the client learned about servers, e.g. which require HTTP/1.1.
+    pub fn server_properties(&self) -> &HttpServerProperties {
+        self.factory.server_properties()
+    }
+
+    /// Get how QUIC races to each origin went, `None` unless
+    /// [`http3`](ClientBuilder::http3) is enabled.
+    #[cfg(feature = "quic")]
+    pub fn quic_outcomes(&self) -> Option<&QuicOutcomes> {
+        self.factory.quic_outcomes()
+    }
+
+    /// Get the client's HTTP auth cache.
+    pub fn auth_cache(&self) -> &AuthCache {
+        &self.auth_cache
+    }
+
+    /// Base URL relative request URLs are resolved against, if one was set
+    /// and is valid.
+    pub fn base_url(&self) -> Option<&Url> {
+        self.base_url.as_ref()?.as_ref().ok()
+    }
+
+    /// The HTTP cache set with [`ClientBuilder::cache`]. Its
+    /// [mode](HttpCache::set_mode) can be changed while the client is in
+    /// use.
+    pub fn cache(&self) -> Option<&Arc<HttpCache>> {
+        self.cache.as_ref()
+    }
+
+    /// The dictionary store set with [`ClientBuilder::shared_dictionaries`].
+    pub fn shared_dictionaries(&self) -> Option<&SharedDictionaryStore> {
+        self.shared_dictionaries.as_ref()
+    }
+
+    /// The per-origin schedule set with [`ClientBuilder::rate_limiter`].
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// The isolation key of requests that do not set their own.
+    pub(crate) fn isolation_key(&self) -> Option<&NetworkIsolationKey> {
+        self.network_isolation_key.as_ref()
+    }
+
+    /// A hyper-util connector opening connections like this client: with
+    /// its TLS settings, proxy and partition, sharing its pool limits. See
+    /// [`crate::socket::connector`].
+    #[cfg(feature = "hyper-connector")]
+    pub fn connector(&self) -> crate::socket::connector::Connector {
+        let mut connector = crate::socket::connector::Connector::from_pool(self.pool.clone());
+        if let Some(proxy) = &self.proxy {
+            connector = connector.with_proxy(proxy.clone());
+        }
+        if let Some(nik) = &self.network_isolation_key {
+            connector = connector.with_network_isolation_key(nik.clone());
+        }
+        connector
+    }
+
     /// Start building a GET request.
     pub fn get<U: AsRef<str>>(&self, url: U) -> RequestBuilder {
         self.request(Method::GET, url)
@@ -109,8 +293,182 @@ impl Client {
             headers: http::HeaderMap::new(),
             body: None,
             emulation_override: None,
+            version_pref: None,
+            http1_0: false,
+            network_isolation_key: None,
+            server_name: ServerName::Host,
+            connect_to: Vec::new(),
+            post_resolve: None,
+            header_override: None,
+            removed_headers: Vec::new(),
+            title_case_headers: false,
+            cookies: RequestCookies::Client,
+            site_for_cookies: None,
+            credentials: None,
+            trace_context: None,
+            content_digest: None,
+            extensions: http::Extensions::new(),
+            query: Ok(Vec::new()),
+            invalid_header: false,
+            timeout: None,
+            priority: RequestPriority::default(),
+            incremental: false,
+            connection_reuse: ConnectionReuse::default(),
+            resource_type: ResourceType::default(),
+            cache_mode: None,
         }
     }
+
+    /// GET `url` unless it is unchanged since `validators` were taken from
+    /// an earlier response, see [`conditional`](crate::http::conditional).
+    ///
+    /// Without validators the request is unconditional and always yields
+    /// [`Conditional::Modified`].
+    pub async fn fetch_if_modified<U: AsRef<str>>(
+        &self,
+        url: U,
+        validators: &Validators,
+    ) -> Result<Conditional, NetError> {
+        let resp = self.get(url).conditional(validators).send().await?;
+        Ok(Conditional::from(resp))
+    }
+
+    /// Send `requests` concurrently, see [`Batch`].
+    ///
+    /// ```no_run
+    /// use chromenet::Client;
+    ///
+    /// # async fn run() {
+    /// let client = Client::new();
+    /// let pages = (1..=50).map(|n| client.get(format!("https://example.com/page/{}", n)));
+    /// for result in client.batch(pages).buffered(8).send().await {
+    ///     match result {
+    ///         Ok(resp) => println!("{}", resp.status()),
+    ///         Err(e) => eprintln!("failed: {}", e),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn batch<I>(&self, requests: I) -> Batch
+    where
+        I: IntoIterator<Item = RequestBuilder>,
+    {
+        Batch::new(requests)
+    }
+
+    /// Keep the cache entries of a set of URLs warm in the background, see
+    /// [`Prefetcher`]. Needs a [cache](ClientBuilder::cache) to be of use.
+    pub fn prefetcher(&self) -> Prefetcher {
+        Prefetcher::new(self.clone())
+    }
+
+    /// Upload `total` bytes to the upload URL `url`, resuming after
+    /// connection failures. See [`crate::http::resumable`].
+    pub fn resumable_upload<U: AsRef<str>>(&self, url: U, total: u64) -> ResumableUpload {
+        ResumableUpload::new(self.clone(), url, total)
+    }
+
+    /// Shut the client and its clones down, waiting up to `timeout` for
+    /// requests in flight. See [`crate::http::shutdown`].
+    ///
+    /// Later requests fail with [`NetError::ContextShutDown`]. Background
+    /// tasks are aborted, connections are closed once their requests are
+    /// done, and the [`on_shutdown`](ClientBuilder::on_shutdown) hooks
+    /// run. Calling it again only waits for requests still in flight.
+    ///
+    /// ```no_run
+    /// # use chromenet::Client;
+    /// # use std::time::Duration;
+    /// # async fn run(client: Client) {
+    /// let report = client.shutdown(Duration::from_secs(5)).await;
+    /// if !report.is_clean() {
+    ///     eprintln!("shutdown left {} requests behind", report.abandoned);
+    /// }
+    /// # }
+    /// ```
+    pub async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        let deadline = std::time::Instant::now() + timeout;
+        if self.lifecycle.close() {
+            tracing::debug!(
+                in_flight = self.lifecycle.in_flight(),
+                "shutting down client"
+            );
+        }
+        let aborted_tasks = self.lifecycle.abort_tasks();
+        let abandoned = self.lifecycle.drain(deadline).await;
+        self.factory.close();
+        self.pool.close();
+        let failed_hooks = self.lifecycle.run_hooks().await;
+        ShutdownReport {
+            abandoned,
+            aborted_tasks,
+            failed_hooks,
+        }
+    }
+
+    /// Close the client and its clones without waiting.
+    ///
+    /// Like [`shutdown`](Self::shutdown), later requests fail with
+    /// [`NetError::ContextShutDown`], background tasks stop and connections
+    /// close once their requests are done, but requests in flight are not
+    /// waited for and the [`on_shutdown`](ClientBuilder::on_shutdown) hooks
+    /// do not run. Dropping the last clone of a client stops its background
+    /// tasks too.
+    pub fn close(&self) {
+        if self.lifecycle.close() {
+            tracing::debug!(in_flight = self.lifecycle.in_flight(), "closing client");
+        }
+        self.lifecycle.abort_tasks();
+        self.factory.close();
+        self.pool.close();
+    }
+
+    /// Stop reusing connections to `host`, on any port, e.g. after
+    /// rotating credentials or when the host misbehaves.
+    ///
+    /// Idle sockets close now, and the ones in use once their request is
+    /// done. HTTP/2 sessions take no new streams and close once their open
+    /// streams finish. The cached DNS answer for `host` is dropped. Later
+    /// requests connect afresh; the client stays open.
+    pub fn flush_host(&self, host: &str) {
+        self.factory.flush_host(host);
+    }
+
+    /// [`flush_host`](Self::flush_host) for every host, also dropping every
+    /// cached DNS answer and proxy tunnel.
+    pub fn flush_all(&self) {
+        self.factory.flush_all();
+    }
+
+    /// A [`WebSocketBuilder`](crate::ws::WebSocketBuilder) for `url` with
+    /// this client's emulation profile, TLS options and proxy, so the
+    /// handshake carries the same headers and TLS fingerprint as its
+    /// requests.
+    #[cfg(feature = "websocket")]
+    pub fn websocket(&self, url: &str) -> Result<crate::ws::WebSocketBuilder, NetError> {
+        let mut builder = crate::ws::WebSocketBuilder::new().url(url)?;
+        if let Some(emulation) = &self.emulation {
+            builder = builder.emulation(emulation.clone());
+        }
+        if let Some(tls_options) = self.pool.tls_options() {
+            builder = builder.tls_options(tls_options.clone());
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        Ok(builder)
+    }
+
+    /// Whether [`close`](Self::close) or [`shutdown`](Self::shutdown) was
+    /// called on this client or a clone.
+    pub fn is_closed(&self) -> bool {
+        self.lifecycle.is_closed()
+    }
+
+    /// Abort `task` when the client shuts down.
+    pub(crate) fn track_task(&self, task: tokio::task::AbortHandle) {
+        self.lifecycle.track(task);
+    }
 }
 
 /// Builder for creating a [`Client`].
@@ -119,10 +477,53 @@ impl Client {
 pub struct ClientBuilder {
     emulation: Option<Emulation>,
     cookie_store: Option<CookieMonster>,
+    auth_cache: Option<AuthCache>,
     proxy: Option<ProxySettings>,
+    no_system_proxy: bool,
+    network_isolation_key: Option<NetworkIsolationKey>,
     tls_options: Option<TlsOptions>,
+    tls_overrides: TlsOverrides,
+    group_limits: GroupLimits,
     timeout: Option<Duration>,
-    pool_size_per_host: Option<usize>,
+    h2c_mode: H2cMode,
+    h2_session_policy: H2SessionPolicy,
+    version_pref: HttpVersionPref,
+    http11_required_ttl: Option<Duration>,
+    #[cfg(feature = "quic")]
+    http3: bool,
+    single_flight: bool,
+    trace_propagator: Option<Arc<dyn TracePropagator>>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    base_url: Option<Result<Url, String>>,
+    default_headers: http::HeaderMap,
+    removed_default_headers: Vec<String>,
+    /// Whether `user_agent` was given an invalid header value
+    invalid_user_agent: bool,
+    ua_consistency: UaConsistency,
+    rate_limiter: Option<RateLimiter>,
+    connect_hooks: Option<Arc<dyn ConnectHooks>>,
+    socket_factory: Option<Arc<dyn SocketFactory>>,
+    dns_resolver: Option<Arc<dyn Resolve>>,
+    post_resolve: Option<PostResolve>,
+    allow_sni_override: bool,
+    connection_lifetime: ConnectionLifetime,
+    rules: Option<RuleSet>,
+    robots: Option<Robots>,
+    hsts: Option<HstsStore>,
+    follow_refresh: bool,
+    max_redirect_drain: Option<usize>,
+    header_limits: HeaderLimits,
+    url_limits: UrlLimits,
+    cache: Option<HttpCache>,
+    shared_dictionaries: Option<SharedDictionaryStore>,
+    content_decoders: Option<ContentDecoders>,
+    content_digest: Option<ContentDigest>,
+    challenges: Option<Challenges>,
+    cert_policy: CertPolicy,
+    priority_header: Option<PriorityHeader>,
+    instrumentation: SocketInstrumentation,
+    connect_timeouts: ConnectTimeouts,
+    shutdown_hooks: Vec<ShutdownHook>,
 }
 
 impl ClientBuilder {
@@ -132,85 +533,1010 @@ impl ClientBuilder {
         self
     }
 
+    /// Impersonate a browser: TLS fingerprint, HTTP/2 settings, default
+    /// headers and a matching User-Agent in one call.
+    ///
+    /// Pass a bare [`ImpersonateTarget`] for its default OS, or pick one with
+    /// [`ImpersonateTarget::with_os`].
+    ///
+    /// ```no_run
+    /// use chromenet::emulation::ImpersonateOs;
+    /// use chromenet::socket::tls::ImpersonateTarget;
+    /// use chromenet::Client;
+    ///
+    /// let client = Client::builder()
+    ///     .impersonate(ImpersonateTarget::Chrome124.with_os(ImpersonateOs::MacOS))
+    ///     .build();
+    /// ```
+    #[cfg(feature = "emulation-profiles")]
+    pub fn impersonate<T: Into<Impersonate>>(self, target: T) -> Self {
+        self.emulation(target.into())
+    }
+
+    /// Send `headers` with every request.
+    ///
+    /// They override the emulation profile's headers of the same name and
+    /// are overridden by the request's own; see
+    /// [Header layering](RequestBuilder#header-layering). Repeated calls
+    /// add to the headers set before.
+    pub fn default_headers(mut self, headers: http::HeaderMap) -> Self {
+        for (key, value) in headers {
+            if let Some(key) = key {
+                self.default_headers.insert(key, value);
+            }
+        }
+        self
+    }
+
+    /// Send the header `key` with every request, like
+    /// [`default_headers`](Self::default_headers).
+    pub fn default_header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: http::header::IntoHeaderName,
+        V: TryInto<http::HeaderValue>,
+    {
+        if let Ok(val) = value.try_into() {
+            self.default_headers.insert(key, val);
+        }
+        self
+    }
+
+    /// Do not send `name` by default, even if the emulation profile adds
+    /// it. Requests can still set it with [`RequestBuilder::header`].
+    pub fn remove_default_header(mut self, name: &str) -> Self {
+        self.removed_default_headers.push(name.to_string());
+        self
+    }
+
+    /// Send `user_agent` and the client hints that go with it with every
+    /// request, replacing the emulation profile's.
+    ///
+    /// `Sec-CH-UA`, `Sec-CH-UA-Mobile` and `Sec-CH-UA-Platform` are derived
+    /// from `user_agent`, or dropped if its browser sends none. The TLS and
+    /// HTTP/2 fingerprint stay the profile's, so pick a profile of the same
+    /// browser engine; [`ua_consistency`](Self::ua_consistency) reports
+    /// when they differ.
+    ///
+    /// If `user_agent` is not a valid header value, requests fail with
+    /// [`NetError::InvalidHeader`].
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.invalid_user_agent = !set_user_agent(
+            &mut self.default_headers,
+            &mut self.removed_default_headers,
+            user_agent,
+        );
+        self
+    }
+
+    /// Check that each request's User-Agent agrees with its client hints
+    /// and with the emulation profile's TLS and HTTP/2 fingerprint, and
+    /// what to do if not. Off by default.
+    pub fn ua_consistency(mut self, mode: UaConsistency) -> Self {
+        self.ua_consistency = mode;
+        self
+    }
+
     /// Set cookie store.
     pub fn cookie_store(mut self, store: CookieMonster) -> Self {
         self.cookie_store = Some(store);
         self
     }
 
+    /// Set the HTTP auth cache, e.g. to share credentials between clients.
+    pub fn auth_cache(mut self, cache: AuthCache) -> Self {
+        self.auth_cache = Some(cache);
+        self
+    }
+
     /// Set proxy.
+    ///
+    /// It replaces the system's proxies for all requests.
     pub fn proxy(mut self, proxy: ProxySettings) -> Self {
         self.proxy = Some(proxy);
         self
     }
 
+    /// Whether to use the proxies of the environment and OS when no
+    /// [`proxy`](Self::proxy) is set. On by default; see
+    /// [`crate::socket::systemproxy`] for where they are looked up.
+    pub fn system_proxy(mut self, enabled: bool) -> Self {
+        self.no_system_proxy = !enabled;
+        self
+    }
+
+    /// Partition this client's connections by `nik`.
+    ///
+    /// Clients sharing a connection pool only reuse each other's sockets
+    /// when their keys match. Requests can override it with
+    /// [`RequestBuilder::network_isolation_key`].
+    pub fn network_isolation_key(mut self, nik: NetworkIsolationKey) -> Self {
+        self.network_isolation_key = Some(nik);
+        self
+    }
+
+    /// Let requests announce a TLS server name other than their host with
+    /// [`RequestBuilder::server_name`], e.g. for domain fronting. Off by
+    /// default, so such requests fail with
+    /// [`NetError::SniOverrideNotAllowed`].
+    pub fn allow_sni_override(mut self, allow: bool) -> Self {
+        self.allow_sni_override = allow;
+        self
+    }
+
     /// Set TLS options (overrides emulation TLS if set).
     pub fn tls_options(mut self, opts: TlsOptions) -> Self {
         self.tls_options = Some(opts);
         self
     }
 
-    /// Set request timeout.
+    /// Adjust the TLS options for hosts matching `pattern`.
+    ///
+    /// `f` receives a builder seeded with the client's TLS options (or the
+    /// emulation profile's) and returns the adjusted one. Patterns are an
+    /// exact host or IP literal, `*.example.com` for subdomains, or `*`.
+    /// All matching overrides apply, in the order they were added.
+    ///
+    /// ```no_run
+    /// use chromenet::socket::tls::TlsVersion;
+    /// use chromenet::Client;
+    ///
+    /// let client = Client::builder()
+    ///     .tls_override("appliance.local", |tls| tls.max_tls_version(TlsVersion::TLS_1_2))
+    ///     .build();
+    /// ```
+    pub fn tls_override<F>(mut self, pattern: &str, f: F) -> Self
+    where
+        F: Fn(TlsOptionsBuilder) -> TlsOptionsBuilder + Send + Sync + 'static,
+    {
+        self.tls_overrides.add(pattern, f);
+        self
+    }
+
+    /// Allow as many connections per host as `limits` gives it, instead of
+    /// Chromium's 6 for every host. Requests over the limit wait for a
+    /// connection to come free:
+    ///
+    /// ```no_run
+    /// use chromenet::socket::grouplimits::GroupLimits;
+    /// use chromenet::Client;
+    ///
+    /// let client = Client::builder()
+    ///     .group_limits(
+    ///         GroupLimits::default()
+    ///             .with_host("api.example.com", 15)
+    ///             .with_host("*.crawl.test", 2),
+    ///     )
+    ///     .build();
+    /// ```
+    ///
+    /// See [`crate::socket::grouplimits`].
+    pub fn group_limits(mut self, limits: GroupLimits) -> Self {
+        self.group_limits = limits;
+        self
+    }
+
+    /// Limit each request, from sending it until its body is read, to
+    /// `timeout`.
+    ///
+    /// Past the deadline, the request fails or the body read by
+    /// [`HttpResponse`](crate::http::HttpResponse)'s consuming methods
+    /// fails with [`NetError::TimedOut`], and the transfer is aborted: an
+    /// HTTP/2 stream is reset with `CANCEL`, an HTTP/1.1 connection closed.
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeout = Some(timeout);
         self
     }
 
+    /// Use HTTP/2 over cleartext for `http://` URLs.
+    ///
+    /// Off by default, as in browsers. Useful for internal services and
+    /// gRPC backends that serve HTTP/2 without TLS.
+    pub fn h2c(mut self, mode: H2cMode) -> Self {
+        self.h2c_mode = mode;
+        self
+    }
+
+    /// Spread parallel requests to an origin over several HTTP/2
+    /// connections instead of multiplexing them all onto one.
+    ///
+    /// ```no_run
+    /// use chromenet::http::H2SessionPolicy;
+    ///
+    /// // Up to 4 connections, another once each carries 8 streams
+    /// let client = chromenet::Client::builder()
+    ///     .h2_session_policy(
+    ///         H2SessionPolicy::new()
+    ///             .with_max_streams_per_session(8)
+    ///             .with_max_sessions(4),
+    ///     )
+    ///     .build();
+    /// ```
+    pub fn h2_session_policy(mut self, policy: H2SessionPolicy) -> Self {
+        self.h2_session_policy = policy;
+        self
+    }
+
+    /// Only use HTTP/1.1.
+    pub fn http1_only(mut self) -> Self {
+        self.version_pref = HttpVersionPref::Http1Only;
+        self
+    }
+
+    /// Use HTTP/2 without negotiation, including h2c for `http://` URLs.
+    ///
+    /// Requests fail with [`NetError::Http11Required`] if a server picks
+    /// HTTP/1.1 during ALPN.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.version_pref = HttpVersionPref::Http2PriorKnowledge;
+        self
+    }
+
+    /// How long an origin that rejected HTTP/2 is spoken to over HTTP/1.1.
+    ///
+    /// Defaults to five minutes.
+    pub fn http11_required_ttl(mut self, ttl: Duration) -> Self {
+        self.http11_required_ttl = Some(ttl);
+        self
+    }
+
+    /// Race a QUIC handshake against TCP for new connections to
+    /// `https://` origins, as Chrome does for origins known to speak
+    /// HTTP/3.
+    ///
+    /// TCP starts after a delay learned from earlier races, and an origin
+    /// where QUIC failed is not raced for a while; see
+    /// [`quic::race`](crate::quic::race) and [`Client::quic_outcomes`].
+    /// Requests through a proxy or restricted to an HTTP version do not
+    /// race.
+    ///
+    /// Inert for now: there is no HTTP/3 transport yet, so every QUIC
+    /// handshake fails, the request goes over TCP and the origin is not
+    /// raced again for a while. Needs the `quic` feature.
+    #[cfg(feature = "quic")]
+    pub fn http3(mut self, enabled: bool) -> Self {
+        self.http3 = enabled;
+        self
+    }
+
+    /// Only use HTTP/3.
+    ///
+    /// HTTP/3 transport is not available yet, so requests fail with
+    /// [`NetError::NotImplemented`].
+    pub fn http3_only(mut self) -> Self {
+        self.version_pref = HttpVersionPref::Http3Only;
+        self
+    }
+
+    /// Coalesce identical concurrent GET and HEAD requests into one fetch.
+    ///
+    /// Requests match when method, URL, body and per-request settings are
+    /// the same. Every caller receives its own copy of the response, whose
+    /// body is read into memory only when another caller shares it.
+    /// Requests with their own credentials, cookie jar, emulation or a
+    /// streaming body always go to the network.
+    pub fn single_flight(mut self, enabled: bool) -> Self {
+        self.single_flight = enabled;
+        self
+    }
+
+    /// Send `traceparent` and `tracestate` headers taken from `propagator`
+    /// with every request.
+    ///
+    /// Headers set on the request itself take precedence.
+    pub fn trace_propagator<P: TracePropagator + 'static>(mut self, propagator: P) -> Self {
+        self.trace_propagator = Some(Arc::new(propagator));
+        self
+    }
+
+    /// Resolve relative request URLs against `base`, per RFC 3986.
+    ///
+    /// Keep the trailing slash on a base path: with
+    /// `https://api.example.com/v2/`, `users` resolves to `/v2/users`, while
+    /// `/users` and `../v1/users` leave `/v2`. Absolute request URLs are
+    /// used as they are. If `base` is not a valid `http` or `https` URL,
+    /// relative requests fail with [`NetError::InvalidUrl`].
+    pub fn base_url(mut self, base: impl AsRef<str>) -> Self {
+        let base = base.as_ref();
+        self.base_url = Some(match Url::parse(base) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(url),
+            Ok(url) => Err(format!("unsupported base URL scheme {:?}", url.scheme())),
+            Err(e) => Err(format!("invalid base URL {:?}: {}", base, e)),
+        });
+        self
+    }
+
+    /// Hold requests to origins that asked to slow down.
+    ///
+    /// After a 429 or 503 response with `Retry-After`, or a response
+    /// reporting an exhausted `RateLimit` quota, requests to that origin
+    /// wait until `limiter` allows them. Share `limiter` to query the
+    /// schedule or to apply it across clients.
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Let `hooks` fake DNS answers, TCP connect results and TLS handshake
+    /// failures of new connections, e.g. to test error handling without a
+    /// network. See [`crate::socket::hooks`].
+    pub fn connect_hooks<H: ConnectHooks + 'static>(mut self, hooks: H) -> Self {
+        self.connect_hooks = Some(Arc::new(hooks));
+        self
+    }
+
+    /// Open the transport connections of new connections with `factory`
+    /// instead of plain TCP, e.g. for TCP Fast Open or a userspace network
+    /// stack. See [`crate::socket::socketfactory`].
+    pub fn socket_factory<F: SocketFactory + 'static>(mut self, factory: F) -> Self {
+        self.socket_factory = Some(Arc::new(factory));
+        self
+    }
+
+    /// Resolve hosts with `resolver` instead of
+    /// [`default_resolver`](crate::dns::default_resolver), e.g. a
+    /// [`HostCache`](crate::dns::HostCache) whose metrics show how often
+    /// lookups are answered from memory. Connect hooks still answer first.
+    pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: R) -> Self {
+        self.dns_resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Pass the DNS answers of every request's new connections through
+    /// `hook`, which may reorder, filter or replace them. Requests can set
+    /// their own with [`RequestBuilder::post_resolve`]. See
+    /// [`PostResolve`].
+    pub fn post_resolve(mut self, hook: PostResolve) -> Self {
+        self.post_resolve = Some(hook);
+        self
+    }
+
+    /// Stop reusing connections that are too old or whose address DNS no
+    /// longer returns, e.g. behind a CDN that rotates its addresses. See
+    /// [`crate::socket::lifetime`].
+    ///
+    /// ```no_run
+    /// use chromenet::socket::lifetime::ConnectionLifetime;
+    /// use chromenet::Client;
+    /// use std::time::Duration;
+    ///
+    /// let client = Client::builder()
+    ///     .connection_lifetime(
+    ///         ConnectionLifetime::new()
+    ///             .with_max_age(Duration::from_secs(600))
+    ///             .with_dns_ttl(Duration::from_secs(60)),
+    ///     )
+    ///     .build();
+    /// ```
+    pub fn connection_lifetime(mut self, lifetime: ConnectionLifetime) -> Self {
+        self.connection_lifetime = lifetime;
+        self
+    }
+
+    /// Limit how long a TCP connect attempt and the whole connect may take,
+    /// by default 30 seconds and 4 minutes.
+    ///
+    /// ```no_run
+    /// use chromenet::socket::connectjob::ConnectTimeouts;
+    /// use chromenet::Client;
+    /// use std::time::Duration;
+    ///
+    /// let client = Client::builder()
+    ///     .connect_timeouts(
+    ///         ConnectTimeouts::new()
+    ///             .with_attempt_timeout(Duration::from_secs(5))
+    ///             .with_total_timeout(Duration::from_secs(20)),
+    ///     )
+    ///     .build();
+    /// ```
+    pub fn connect_timeouts(mut self, timeouts: ConnectTimeouts) -> Self {
+        self.connect_timeouts = timeouts;
+        self
+    }
+
+    /// Block, redirect or edit the headers of requests matching `rules`
+    /// before they are sent. See [`crate::urlrequest::rules`].
+    pub fn rules(mut self, rules: RuleSet) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Check requests against the robots.txt of their origin before they
+    /// are sent. With a [`rate_limiter`](Self::rate_limiter), `Crawl-delay`
+    /// also spaces requests to the origin. See
+    /// [`crate::urlrequest::robots`].
+    pub fn robots(mut self, robots: Robots) -> Self {
+        self.robots = Some(robots);
+        self
+    }
+
+    /// Send `http` requests and redirects to hosts in `hsts` over `https`,
+    /// and learn the `Strict-Transport-Security` headers of HTTPS
+    /// responses into it. Without a store URLs are sent as given.
+    ///
+    /// ```no_run
+    /// use chromenet::tls::HstsStore;
+    /// use chromenet::Client;
+    ///
+    /// let client = Client::builder().hsts(HstsStore::with_preload()).build();
+    /// ```
+    pub fn hsts(mut self, hsts: HstsStore) -> Self {
+        self.hsts = Some(hsts);
+        self
+    }
+
+    /// Keep the HSTS entries and pins of a started `persister` on disk for
+    /// this client: its stores become the client's [`hsts`](Self::hsts)
+    /// store and the pins of its [`cert_policy`](Self::cert_policy). The
+    /// persister lives as long as the client, and changes it has not
+    /// written yet are saved when the client [shuts down](Client::shutdown).
+    ///
+    /// ```no_run
+    /// # async fn run() -> std::io::Result<()> {
+    /// use chromenet::tls::{HstsStore, PinStore, TransportSecurityPersister};
+    /// use chromenet::Client;
+    ///
+    /// let (hsts, pins) = (HstsStore::with_preload(), PinStore::new());
+    /// let persister = TransportSecurityPersister::new("TransportSecurity", &hsts, &pins).start()?;
+    /// let client = Client::builder().transport_security_persister(persister).build();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transport_security_persister(mut self, persister: TransportSecurityPersister) -> Self {
+        self.hsts = Some(persister.hsts().clone());
+        self.cert_policy = self.cert_policy.with_pins(persister.pins().clone());
+        self.on_shutdown(move || {
+            if persister.has_pending_write() {
+                persister.commit_now()
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// Also follow `Refresh: 0; url=...` headers like a 303 redirect, for
+    /// legacy sites that redirect that way. Only immediate refreshes are
+    /// followed; `<meta http-equiv="refresh">` tags in bodies are not.
+    /// Off by default, as in modern browsers.
+    pub fn follow_refresh(mut self, follow: bool) -> Self {
+        self.follow_refresh = follow;
+        self
+    }
+
+    /// Read at most `limit` bytes of a redirect's body before following
+    /// it, 16 KiB by default. Draining lets an HTTP/1.1 connection carry
+    /// the next hop; a longer body closes it instead, and the next hop
+    /// reconnects. [`HttpResponse::redirects`](crate::http::HttpResponse::redirects)
+    /// tells what became of each hop's body. See
+    /// [`crate::urlrequest::redirect`].
+    pub fn max_redirect_drain(mut self, limit: usize) -> Self {
+        self.max_redirect_drain = Some(limit);
+        self
+    }
+
+    /// Limit the size of request headers, in all and per value. Requests
+    /// over a limit, or with a control character in a header value, fail
+    /// with [`NetError::InvalidHeader`] before they are sent. See
+    /// [`crate::http::headerlimits`].
+    pub fn header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.header_limits = limits;
+        self
+    }
+
+    /// Limit the length of request URLs and request lines. Requests and
+    /// redirects over a limit fail with [`NetError::UrlTooLong`] or
+    /// [`NetError::RequestLineTooLong`] before they are sent. URLs are
+    /// capped at 2 MiB by default, as in Chromium. See
+    /// [`crate::urlrequest::urllimits`].
+    pub fn url_limits(mut self, limits: UrlLimits) -> Self {
+        self.url_limits = limits;
+        self
+    }
+
+    /// Cache responses in `cache`: fresh entries answer GET and HEAD
+    /// requests without a round trip, and stale ones are revalidated with
+    /// `If-None-Match` and `If-Modified-Since`. Requests choose how they
+    /// use it with [`RequestBuilder::cache_mode`].
+    pub fn cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Use compression dictionaries like current Chrome: responses with
+    /// `Use-As-Dictionary` are kept in `store`, later requests they match
+    /// announce them and add `dcb` and `dcz` to `Accept-Encoding`, and
+    /// bodies compressed with them are decoded. See
+    /// [`crate::http::shareddictionary`].
+    pub fn shared_dictionaries(mut self, store: SharedDictionaryStore) -> Self {
+        self.shared_dictionaries = Some(store);
+        self
+    }
+
+    /// Decode the bodies of final responses with `decoders`, removing
+    /// `Content-Encoding`, instead of returning them as received. Bodies
+    /// are read whole to decode them, and codings without a decoder fail
+    /// with [`NetError::ContentDecodingFailed`]. Register codings beyond
+    /// the built-in `gzip`, `deflate`, `br` and `zstd` with
+    /// [`ContentDecoders::with`]; announcing them in `Accept-Encoding` is
+    /// left to [`RequestBuilder::accept_encoding`]. See
+    /// [`crate::http::contentdecoder`].
+    pub fn content_decoders(mut self, decoders: ContentDecoders) -> Self {
+        self.content_decoders = Some(decoders);
+        self
+    }
+
+    /// Send a `Content-Digest` or `Repr-Digest` header with every request
+    /// that has a body, unless the request sets its own. See
+    /// [`crate::http::contentdigest`].
+    pub fn content_digest(mut self, digest: ContentDigest) -> Self {
+        self.content_digest = Some(digest);
+        self
+    }
+
+    /// Answer bot-protection challenges that `challenges` recognizes and
+    /// send the challenged request again with the solution's cookies and
+    /// headers. See [`crate::urlrequest::challenge`].
+    pub fn challenges(mut self, challenges: Challenges) -> Self {
+        self.challenges = Some(challenges);
+        self
+    }
+
+    /// Check the pins and CT of every HTTPS connection a request is sent
+    /// on with `policy`, failing requests that violate it. What the checks
+    /// found is in
+    /// [`HttpResponse::cert_verify_result`](crate::http::HttpResponse::cert_verify_result).
+    /// See [`crate::tls::certverify`].
+    pub fn cert_policy(mut self, policy: CertPolicy) -> Self {
+        self.cert_policy = policy;
+        self
+    }
+
+    /// When requests announce their [priority](RequestBuilder::priority)
+    /// in an RFC 9218 `priority` header, overriding the emulation
+    /// profile's choice. See [`crate::http::priority`].
+    pub fn priority_header(mut self, mode: PriorityHeader) -> Self {
+        self.priority_header = Some(mode);
+        self
+    }
+
+    /// Count the bytes each request takes on the wire, reported by
+    /// [`HttpResponse::wire_bytes`](crate::http::HttpResponse::wire_bytes). See [`crate::socket::wire`].
+    pub fn count_bytes(mut self, enabled: bool) -> Self {
+        self.instrumentation = self.instrumentation.with_byte_counting(enabled);
+        self
+    }
+
+    /// Record the raw and decrypted traffic of every connection to `sink`,
+    /// e.g. to compare a handshake with a browser's. See
+    /// [`crate::socket::wire`].
+    pub fn wire_capture<S: WireSink + 'static>(mut self, sink: S) -> Self {
+        self.instrumentation = self.instrumentation.with_sink(Arc::new(sink));
+        self
+    }
+
+    /// Report request outcomes, connect timings and pool queueing to
+    /// `recorder`, e.g. a [`ClientMetrics`](crate::metrics::ClientMetrics).
+    pub fn metrics<R: MetricsRecorder + 'static>(mut self, recorder: R) -> Self {
+        self.metrics = Some(Arc::new(recorder));
+        self
+    }
+
+    /// Run `hook` when the client [shuts down](Client::shutdown), after
+    /// its requests finished, e.g. to save the cookie jar or commit a
+    /// [`TransportSecurityPersister`](crate::tls::TransportSecurityPersister).
+    /// Hooks run in the order added, on a blocking thread; a failed one is
+    /// logged and counted in the [`ShutdownReport`].
+    ///
+    /// ```no_run
+    /// use chromenet::cookies::monster::CookieMonster;
+    /// use chromenet::cookies::persistence;
+    /// use chromenet::Client;
+    /// use std::path::Path;
+    ///
+    /// // Clones share their cookies
+    /// let jar = CookieMonster::new();
+    /// let saved = jar.clone();
+    /// let client = Client::builder()
+    ///     .cookie_store(jar)
+    ///     .on_shutdown(move || persistence::save_cookies(&saved, Path::new("cookies.json")))
+    ///     .build();
+    /// ```
+    pub fn on_shutdown<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce() -> std::io::Result<()> + Send + 'static,
+    {
+        self.shutdown_hooks.push(Box::new(hook));
+        self
+    }
+
     /// Build the client.
     pub fn build(self) -> Client {
         let tls_opts = self
             .tls_options
             .or_else(|| self.emulation.as_ref().and_then(|e| e.tls_options.clone()));
 
-        let pool = Arc::new(ClientSocketPool::new(tls_opts));
-        let factory = Arc::new(HttpStreamFactory::new(pool.clone()));
+        let mut pool = ClientSocketPool::new(tls_opts)
+            .with_tls_overrides(self.tls_overrides)
+            .with_group_limits(self.group_limits)
+            .with_connection_lifetime(self.connection_lifetime)
+            .with_instrumentation(self.instrumentation)
+            .with_connect_timeouts(self.connect_timeouts);
+        if let Some(metrics) = &self.metrics {
+            pool = pool.with_metrics(metrics.clone());
+        }
+        if let Some(hooks) = self.connect_hooks {
+            pool = pool.with_connect_hooks(hooks);
+        }
+        if let Some(factory) = self.socket_factory {
+            pool = pool.with_socket_factory(factory);
+        }
+        if let Some(resolver) = self.dns_resolver {
+            pool = pool.with_resolver(resolver);
+        }
+        let pool = Arc::new(pool);
+        let server_properties = self
+            .http11_required_ttl
+            .map(HttpServerProperties::new)
+            .unwrap_or_default();
+        let factory = HttpStreamFactory::new(pool.clone())
+            .with_h2c_mode(self.h2c_mode)
+            .with_h2_session_policy(self.h2_session_policy)
+            .with_server_properties(server_properties);
+        #[cfg(feature = "quic")]
+        let factory = if self.http3 {
+            factory.with_quic_outcomes(QuicOutcomes::new())
+        } else {
+            factory
+        };
+        let factory = Arc::new(factory);
         let cookie_store = Arc::new(self.cookie_store.unwrap_or_default());
+        let lifecycle = Arc::new(Lifecycle::default());
+        for hook in self.shutdown_hooks {
+            lifecycle.add_hook(hook);
+        }
+        start_pool_cleanup(&pool, &lifecycle);
 
         Client {
             pool,
             factory,
             cookie_store,
+            auth_cache: self.auth_cache.unwrap_or_default(),
             emulation: self.emulation,
+            system_proxy: if self.proxy.is_none() && !self.no_system_proxy {
+                SystemProxyConfig::detect()
+            } else {
+                None
+            },
             proxy: self.proxy,
+            network_isolation_key: self.network_isolation_key,
             timeout: self.timeout,
+            version_pref: self.version_pref,
+            single_flight: self.single_flight.then(SingleFlight::new),
+            trace_propagator: self.trace_propagator,
+            metrics: self.metrics,
+            base_url: self.base_url,
+            default_headers: self.default_headers,
+            removed_default_headers: self.removed_default_headers,
+            invalid_user_agent: self.invalid_user_agent,
+            ua_consistency: self.ua_consistency,
+            rate_limiter: self.rate_limiter,
+            allow_sni_override: self.allow_sni_override,
+            post_resolve: self.post_resolve,
+            rules: self.rules.map(Arc::new),
+            robots: self.robots,
+            hsts: self.hsts,
+            follow_refresh: self.follow_refresh,
+            max_redirect_drain: self
+                .max_redirect_drain
+                .unwrap_or(DEFAULT_MAX_REDIRECT_DRAIN),
+            header_limits: self.header_limits,
+            url_limits: self.url_limits,
+            cache: self.cache.map(Arc::new),
+            shared_dictionaries: self.shared_dictionaries,
+            content_decoders: self.content_decoders,
+            content_digest: self.content_digest,
+            challenges: self.challenges,
+            cert_policy: self.cert_policy,
+            priority_header: self.priority_header,
+            lifecycle,
+        }
+    }
+}
+
+/// Clean up the idle sockets of `pool` until the client is closed or
+/// dropped. Clients built outside a runtime get no cleanup task.
+fn start_pool_cleanup(pool: &Arc<ClientSocketPool>, lifecycle: &Lifecycle) {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        pool.start_cleanup_task(lifecycle.cancel_token());
+    }
+}
+
+/// Low-entropy client hints, replaced together with the User-Agent.
+const CLIENT_HINTS: [&str; 3] = ["sec-ch-ua", "sec-ch-ua-mobile", "sec-ch-ua-platform"];
+
+/// Set `user_agent` and the client hints derived from it in `headers`. The
+/// hints are listed in `removed` if its browser sends none, and taken off
+/// it otherwise. Returns `false`, changing nothing, if `user_agent` is not
+/// a valid header value.
+fn set_user_agent(
+    headers: &mut http::HeaderMap,
+    removed: &mut Vec<String>,
+    user_agent: &str,
+) -> bool {
+    let Ok(value) = http::HeaderValue::from_str(user_agent) else {
+        return false;
+    };
+    headers.insert(http::header::USER_AGENT, value);
+    let hints = UserAgent::parse(user_agent).client_hints();
+    for name in CLIENT_HINTS {
+        headers.remove(name);
+        removed.retain(|n| !n.eq_ignore_ascii_case(name));
+        if hints.is_empty() {
+            removed.push(name.to_string());
+        }
+    }
+    for (name, value) in hints {
+        if let Ok(value) = http::HeaderValue::from_str(&value) {
+            headers.insert(name, value);
         }
     }
+    true
+}
+
+/// Which cookie jar a single request uses.
+enum RequestCookies {
+    /// The client's jar.
+    Client,
+    /// An explicitly provided jar.
+    Store(Arc<CookieMonster>),
+    /// A fresh jar that lives only for this request and its redirects.
+    Temporary,
+    /// No cookies are sent or saved.
+    Disabled,
 }
 
 /// Builder for a single request.
+///
+/// # Header layering
+///
+/// Request headers are merged from three layers, each overriding the one
+/// before it for headers of the same name:
+///
+/// 1. The emulation profile's headers, or the request's
+///    [`ordered_headers`](Self::ordered_headers) in their place
+/// 2. The client's [`default_headers`](ClientBuilder::default_headers)
+/// 3. Headers set on the request with [`header`](Self::header)
+///
+/// An overridden header keeps the position it had in the earliest layer
+/// that set it, so a profile's header order survives; new headers are
+/// appended. Names are matched case-insensitively. Inherited headers are
+/// dropped with [`ClientBuilder::remove_default_header`], which a request
+/// can undo by setting the header again, and
+/// [`remove_header`](Self::remove_header), which always wins.
+/// [`build_parts`](Self::build_parts) shows the result without sending
+/// the request.
 pub struct RequestBuilder {
     client: Client,
     method: Method,
     url: String,
     headers: http::HeaderMap,
-    body: Option<Vec<u8>>,
+    body: Option<RequestBody>,
     emulation_override: Option<Emulation>,
+    version_pref: Option<HttpVersionPref>,
+    http1_0: bool,
+    network_isolation_key: Option<NetworkIsolationKey>,
+    server_name: ServerName,
+    connect_to: Vec<ConnectTo>,
+    post_resolve: Option<PostResolve>,
+    header_override: Option<OrderedHeaderMap>,
+    removed_headers: Vec<String>,
+    title_case_headers: bool,
+    cookies: RequestCookies,
+    site_for_cookies: Option<Url>,
+    credentials: Option<(String, SecretString)>,
+    trace_context: Option<TraceContext>,
+    content_digest: Option<ContentDigest>,
+    extensions: http::Extensions,
+    /// Parameters added with `query`, `Err` if one failed to serialize
+    query: Result<Vec<(String, String)>, ()>,
+    /// Whether `header` was given a value that is not a valid header value
+    invalid_header: bool,
+    timeout: Option<Duration>,
+    priority: RequestPriority,
+    incremental: bool,
+    connection_reuse: ConnectionReuse,
+    resource_type: ResourceType,
+    cache_mode: Option<CacheMode>,
 }
 
 impl RequestBuilder {
     /// Add a header.
+    ///
+    /// A value that is not a valid header value, e.g. one with a line
+    /// break, fails the request with [`NetError::InvalidHeader`].
     pub fn header<K, V>(mut self, key: K, value: V) -> Self
     where
         K: http::header::IntoHeaderName,
         V: TryInto<http::HeaderValue>,
     {
-        if let Ok(val) = value.try_into() {
-            self.headers.insert(key, val);
+        match value.try_into() {
+            Ok(val) => {
+                self.headers.insert(key, val);
+            }
+            Err(_) => self.invalid_header = true,
+        }
+        self
+    }
+
+    /// Replace the emulation profile's default headers with `headers`.
+    ///
+    /// Headers are sent in exactly this order; the client's default headers
+    /// not in `headers` follow them, and headers added with
+    /// [`header`](Self::header) are still applied on top.
+    pub fn ordered_headers(mut self, headers: OrderedHeaderMap) -> Self {
+        self.header_override = Some(headers);
+        self
+    }
+
+    /// Like [`ordered_headers`](Self::ordered_headers), and write HTTP/1.1
+    /// header names in Title-Case.
+    ///
+    /// hyper writes names either lowercase or in Title-Case, so other
+    /// casings cannot be kept; HTTP/2 always sends lowercase names. Convert
+    /// [`CaseSensitiveHeaders`](crate::http::orderedheaders::CaseSensitiveHeaders)
+    /// with `OrderedHeaderMap::try_from`, which fails on a name or value
+    /// that is not valid.
+    pub fn title_case_headers(mut self, headers: OrderedHeaderMap) -> Self {
+        self.header_override = Some(headers);
+        self.title_case_headers = true;
+        self
+    }
+
+    /// Do not send `name`, even if the emulation profile or the client adds
+    /// it by default.
+    pub fn remove_header(mut self, name: &str) -> Self {
+        self.removed_headers.push(name.to_string());
+        self
+    }
+
+    /// Send `Accept-Encoding: value` instead of the profile's, e.g. `gzip`
+    /// to store compressed bodies as they come.
+    ///
+    /// The body is returned with its `Content-Encoding` still applied, see
+    /// [`HttpResponse::content_encodings`](crate::http::HttpResponse::content_encodings).
+    /// Any request that sets or removes `Accept-Encoding` itself is
+    /// treated the same: [shared dictionaries](ClientBuilder::shared_dictionaries)
+    /// are neither announced nor decoded, so the header says exactly what
+    /// the client accepts.
+    pub fn accept_encoding<V>(self, value: V) -> Self
+    where
+        V: TryInto<http::HeaderValue>,
+    {
+        self.header(http::header::ACCEPT_ENCODING, value)
+    }
+
+    /// Ask for the body without content coding (`Accept-Encoding:
+    /// identity`). See [`accept_encoding`](Self::accept_encoding).
+    pub fn identity_encoding(self) -> Self {
+        self.accept_encoding("identity")
+    }
+
+    /// Send `user_agent` and the client hints that go with it, like
+    /// [`ClientBuilder::user_agent`].
+    ///
+    /// If `user_agent` is not a valid header value, sending fails with
+    /// [`NetError::InvalidHeader`], as with [`header`](Self::header).
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        if !set_user_agent(&mut self.headers, &mut self.removed_headers, user_agent) {
+            self.invalid_header = true;
+        }
+        self
+    }
+
+    /// Use `store` instead of the client's cookie jar for this request.
+    pub fn cookie_store(mut self, store: Arc<CookieMonster>) -> Self {
+        self.cookies = RequestCookies::Store(store);
+        self
+    }
+
+    /// Use a fresh, empty cookie jar for this request.
+    ///
+    /// Cookies set along a redirect chain are sent on later hops, then
+    /// discarded. The client's jar is neither read nor written.
+    pub fn incognito(mut self) -> Self {
+        self.cookies = RequestCookies::Temporary;
+        self
+    }
+
+    /// Do not send or save any cookies for this request.
+    pub fn no_cookies(mut self) -> Self {
+        self.cookies = RequestCookies::Disabled;
+        self
+    }
+
+    /// Send the request as if made from a page on `site`, e.g. a fetch or
+    /// a subresource, rather than as a top-level navigation.
+    ///
+    /// `SameSite=Strict`, `Lax` and unspecified cookies then go only to
+    /// hops that are same-site with `site` and were reached without
+    /// leaving it. See [`crate::cookies::samesite`].
+    pub fn site_for_cookies(mut self, site: Url) -> Self {
+        self.site_for_cookies = Some(site);
+        self
+    }
+
+    /// Answer Basic or Digest challenges with these credentials.
+    ///
+    /// After a successful challenge the credentials are cached, and later
+    /// requests to the same origin send them preemptively.
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), SecretString::new(password)));
+        self
+    }
+
+    /// Send `context` in the `traceparent` and `tracestate` headers,
+    /// instead of asking the client's trace propagator.
+    pub fn trace_context(mut self, context: TraceContext) -> Self {
+        self.trace_context = Some(context);
+        self
+    }
+
+    /// Append query parameters to the URL.
+    ///
+    /// Takes a list of pairs, `&[("page", "2"), ("tag", "a")]`, or any
+    /// `Serialize` struct or map; see [`crate::http::query`] for how
+    /// sequences and nested values are encoded. Parameters already in the
+    /// URL are kept, and repeated calls append in order. If `query` cannot
+    /// be serialized, sending fails with [`NetError::InvalidUrl`].
+    pub fn query<T: serde::Serialize + ?Sized>(mut self, query: &T) -> Self {
+        if let Ok(pairs) = &mut self.query {
+            match crate::http::query::to_pairs(query) {
+                Ok(new_pairs) => pairs.extend(new_pairs),
+                Err(e) => {
+                    tracing::debug!(error = %e, "invalid query parameters");
+                    self.query = Err(());
+                }
+            }
         }
         self
     }
 
     /// Set request body.
-    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+    ///
+    /// Pass a [`RewindableBody`](crate::http::RewindableBody) to stream the
+    /// body; it is replayed on 307/308 redirects and retries.
+    pub fn body<B: Into<RequestBody>>(mut self, body: B) -> Self {
         self.body = Some(body.into());
         self
     }
 
+    /// Send the hash of the body in a `Content-Digest` or `Repr-Digest`
+    /// header, instead of the client's
+    /// [setting](ClientBuilder::content_digest). A streaming body is read
+    /// once to hash it before it is sent. A header set with
+    /// [`header`](Self::header) is sent instead.
+    pub fn content_digest(mut self, digest: ContentDigest) -> Self {
+        self.content_digest = Some(digest);
+        self
+    }
+
+    /// Attach `value` to the request, replacing one of the same type.
+    ///
+    /// Nothing is sent: the value travels with the request to
+    /// [challenge handlers](crate::urlrequest::challenge::Challenge::extensions)
+    /// and comes back in
+    /// [`HttpResponse::extensions`](crate::http::HttpResponse::extensions),
+    /// so layers wrapping the client can keep per-request state, such as an
+    /// auth token or a retry count, without a map keyed by request.
+    pub fn extension<T: Clone + Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.extensions.insert(value);
+        self
+    }
+
     /// Set JSON body.
     #[cfg(feature = "json")]
     pub fn json<T: serde::Serialize>(mut self, json: &T) -> Self {
         if let Ok(bytes) = serde_json::to_vec(json) {
-            self.body = Some(bytes);
+            self.body = Some(bytes.into());
             self.headers.insert(
                 http::header::CONTENT_TYPE,
                 http::HeaderValue::from_static("application/json"),
@@ -225,18 +1551,584 @@ impl RequestBuilder {
         self
     }
 
+    /// Only use HTTP/1.1 for this request.
+    pub fn http1_only(mut self) -> Self {
+        self.version_pref = Some(HttpVersionPref::Http1Only);
+        self
+    }
+
+    /// Use HTTP/2 without negotiation for this request.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.version_pref = Some(HttpVersionPref::Http2PriorKnowledge);
+        self
+    }
+
+    /// Only use HTTP/3 for this request.
+    pub fn http3_only(mut self) -> Self {
+        self.version_pref = Some(HttpVersionPref::Http3Only);
+        self
+    }
+
+    /// Send an HTTP/1.0 request, e.g. to test how a server treats old
+    /// clients: the request line ends in `HTTP/1.0` and only HTTP/1.x is
+    /// used, as with [`Self::http1_only`]. The connection is closed after
+    /// the response unless the server keeps it alive.
+    ///
+    /// A body of unknown length cannot be sent, as HTTP/1.0 has no chunked
+    /// encoding. The server's answer need not be HTTP/1.0; see
+    /// [`HttpResponse::status_line`](crate::http::HttpResponse::status_line).
+    pub fn http1_0(mut self) -> Self {
+        self.version_pref = Some(HttpVersionPref::Http1Only);
+        self.http1_0 = true;
+        self
+    }
+
+    /// Only reuse connections opened for the same `nik`.
+    ///
+    /// Use [`NetworkIsolationKey::from_top_frame_url`] to keep requests made
+    /// on behalf of different sites on separate connections, as a browser
+    /// does for third-party resources.
+    pub fn network_isolation_key(mut self, nik: NetworkIsolationKey) -> Self {
+        self.network_isolation_key = Some(nik);
+        self
+    }
+
+    /// Announce `server_name` in the TLS handshake instead of the URL's
+    /// host, while `Host` and `:authority` keep the host:
+    ///
+    /// ```no_run
+    /// use chromenet::socket::tls::ServerName;
+    /// use chromenet::Client;
+    ///
+    /// # async fn run() -> Result<(), chromenet::base::neterror::NetError> {
+    /// let client = Client::builder().allow_sni_override(true).build();
+    /// let resp = client
+    ///     .get("https://origin.example.com/")
+    ///     .server_name(ServerName::custom("front.example.net"))
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The certificate is verified against the name sent, or the host with
+    /// [`ServerName::Disabled`]. Requires
+    /// [`ClientBuilder::allow_sni_override`].
+    pub fn server_name(mut self, server_name: ServerName) -> Self {
+        self.server_name = server_name;
+        self
+    }
+
+    /// Connect where `rule` says instead of to the URL's host and port,
+    /// like curl's `--connect-to`, while the URL, SNI, certificate
+    /// verification, `Host` and cookies stay those of the URL:
+    ///
+    /// ```no_run
+    /// use chromenet::socket::connectto::ConnectTo;
+    /// use chromenet::Client;
+    ///
+    /// # async fn run() -> Result<(), chromenet::base::neterror::NetError> {
+    /// let client = Client::new();
+    /// let rule = ConnectTo::parse("example.com:443:staging.internal:8443").unwrap();
+    /// let resp = client
+    ///     .get("https://example.com/")
+    ///     .connect_to(rule)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Repeatable; the first rule matching applies. Redirects are matched
+    /// against the rules again.
+    pub fn connect_to(mut self, rule: ConnectTo) -> Self {
+        self.connect_to.push(rule);
+        self
+    }
+
+    /// Pass the DNS answers of this request's new connections, redirects
+    /// included, through `hook` instead of the client's
+    /// [`post_resolve`](ClientBuilder::post_resolve) hook:
+    ///
+    /// ```no_run
+    /// use chromenet::dns::PostResolve;
+    /// use chromenet::Client;
+    /// use std::net::IpAddr;
+    ///
+    /// # async fn run() -> Result<(), chromenet::base::neterror::NetError> {
+    /// let edge: IpAddr = "203.0.113.7".parse().unwrap();
+    /// let resp = Client::new()
+    ///     .get("https://example.com/")
+    ///     .post_resolve(PostResolve::new(move |_host, addrs| {
+    ///         if addrs.contains(&edge) {
+    ///             vec![edge]
+    ///         } else {
+    ///             addrs
+    ///         }
+    ///     }))
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Connections are only reused by requests with the same hook.
+    pub fn post_resolve(mut self, hook: PostResolve) -> Self {
+        self.post_resolve = Some(hook);
+        self
+    }
+
+    /// Limit this request to `timeout`, overriding the client's
+    /// [`timeout`](ClientBuilder::timeout).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Only send the body if it does not match `etag`, e.g. one from
+    /// [`HttpResponse::etag`](crate::http::HttpResponse::etag). The server
+    /// answers `304 Not Modified` otherwise. `None` leaves the request
+    /// unconditional.
+    pub fn if_none_match(self, etag: impl Into<Option<EntityTag>>) -> Self {
+        match etag.into() {
+            Some(etag) => self.header(http::header::IF_NONE_MATCH, etag.to_string()),
+            None => self,
+        }
+    }
+
+    /// Only send the body if the resource changed after `time`, e.g. one
+    /// from [`HttpResponse::last_modified`](crate::http::HttpResponse::last_modified).
+    /// The server answers `304 Not Modified` otherwise. `None` leaves the
+    /// request unconditional.
+    pub fn if_modified_since(self, time: impl Into<Option<SystemTime>>) -> Self {
+        match time.into() {
+            Some(time) => self.header(http::header::IF_MODIFIED_SINCE, format_http_date(time)),
+            None => self,
+        }
+    }
+
+    /// Make the request conditional on all of `validators`.
+    pub fn conditional(self, validators: &Validators) -> Self {
+        self.if_none_match(validators.etag.clone())
+            .if_modified_since(validators.last_modified)
+    }
+
+    /// Order this request among others waiting for a connection to the
+    /// same host once its connection limit is reached, and announce it in
+    /// the `priority` header where the client sends one (see
+    /// [`ClientBuilder::priority_header`]). Defaults to
+    /// [`RequestPriority::Medium`].
+    pub fn priority(mut self, priority: RequestPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Mark the response as useful in parts, e.g. a page rendered as it
+    /// arrives, with `i` in the `priority` header.
+    pub fn priority_incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Send the request on a new connection instead of a live HTTP/2
+    /// session or idle socket, so the server sees a full TLS and HTTP/2
+    /// handshake before it, as for a browser's first request to a site.
+    /// Redirects and later requests may reuse the connection. The request
+    /// is never [coalesced](ClientBuilder::single_flight).
+    pub fn fresh_connection(mut self) -> Self {
+        self.connection_reuse = ConnectionReuse::Fresh;
+        self
+    }
+
+    /// Send the request and its redirects on new connections that are
+    /// closed after the response, as for a load test that must not share
+    /// connections: HTTP/1.1 requests carry `Connection: close` and their
+    /// sockets never return to the pool, and an HTTP/2 session serves only
+    /// this request. The request is never
+    /// [coalesced](ClientBuilder::single_flight).
+    pub fn close_connection(mut self) -> Self {
+        self.connection_reuse = ConnectionReuse::Close;
+        self
+    }
+
+    /// What the request is for, for [rules](ClientBuilder::rules) that
+    /// match on resource types. Defaults to [`ResourceType::Other`].
+    pub fn resource_type(mut self, resource_type: ResourceType) -> Self {
+        self.resource_type = resource_type;
+        self
+    }
+
+    /// How this request uses the client's [cache](ClientBuilder::cache):
+    /// [`CacheMode::Disabled`] skips it, [`CacheMode::ReadOnly`] reads
+    /// without storing the response, [`CacheMode::ForceRefresh`] goes to
+    /// the network but stores the response and [`CacheMode::Revalidate`]
+    /// revalidates a stored entry even while it is fresh. Defaults to the
+    /// cache's [mode](HttpCache::mode) when the request is sent.
+    pub fn cache_mode(mut self, mode: CacheMode) -> Self {
+        self.cache_mode = Some(mode);
+        self
+    }
+
     /// Send the request.
+    ///
+    /// Runs in a `chromenet::http` span carrying OpenTelemetry HTTP client
+    /// attributes, so a `tracing-opentelemetry` layer exports it as-is.
     pub async fn send(self) -> Result<crate::http::HttpResponse, NetError> {
-        let url = Url::parse(&self.url).map_err(|_| NetError::InvalidUrl)?;
+        let Some(_in_flight) = self.client.lifecycle.enter() else {
+            return Err(NetError::ContextShutDown);
+        };
+        let span = self.span();
+        let start = std::time::Instant::now();
+        let metrics = self.client.metrics.clone();
+        let method = self.method.clone();
+        let deadline = self.timeout.or(self.client.timeout).map(|t| start + t);
+        let transfer = async {
+            if let Some(flights) = self.client.single_flight.clone() {
+                if let Some(key) = self.flight_key() {
+                    // A shared response carries the leader's extensions
+                    let extensions = self.extensions.clone();
+                    return flights
+                        .run(key, self.execute())
+                        .await
+                        .map(|resp| resp.with_extensions(extensions));
+                }
+            }
+            self.execute().await
+        }
+        .instrument(span.clone());
+        // Dropping the transfer at the deadline drops its streams, which
+        // resets an HTTP/2 stream and closes an HTTP/1.1 connection
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), transfer)
+                .await
+                .unwrap_or(Err(NetError::TimedOut))
+                .map(|resp| resp.with_deadline(deadline)),
+            None => transfer.await,
+        };
 
-        // Create job using existing infrastructure
-        let mut job = URLRequestHttpJob::new(
-            self.client.factory.clone(),
-            url,
-            self.client.cookie_store.clone(),
+        match &result {
+            Ok(resp) => {
+                let status = resp.status();
+                span.record("http.response.status_code", status.as_u16());
+                span.record(
+                    "network.protocol.version",
+                    match resp.version() {
+                        http::Version::HTTP_09 => "0.9",
+                        http::Version::HTTP_10 => "1.0",
+                        http::Version::HTTP_2 => "2",
+                        http::Version::HTTP_3 => "3",
+                        _ => "1.1",
+                    },
+                );
+                // Client spans treat 4xx as errors too
+                if status.is_client_error() || status.is_server_error() {
+                    span.record("error.type", status.as_str());
+                    span.record("otel.status_code", "ERROR");
+                }
+            }
+            Err(e) => {
+                span.record("error.type", tracing::field::debug(e));
+                span.record("otel.status_code", "ERROR");
+            }
+        }
+        if let Some(metrics) = metrics {
+            metrics.record_request(&RequestMetrics {
+                method: &method,
+                status: result.as_ref().ok().map(|resp| resp.status()),
+                duration: start.elapsed(),
+                error: result.as_ref().err(),
+            });
+        }
+        result
+    }
+
+    /// The request URL, resolved against the client's base URL if relative,
+    /// with the parameters added by [`query`](Self::query).
+    fn url(&self) -> Result<Url, NetError> {
+        let mut url = match Url::parse(&self.url) {
+            Ok(url) => url,
+            Err(url::ParseError::RelativeUrlWithoutBase) => match &self.client.base_url {
+                Some(Ok(base)) => base.join(&self.url).map_err(|_| NetError::InvalidUrl)?,
+                Some(Err(reason)) => {
+                    tracing::debug!(url = %self.url, reason = %reason, "cannot resolve relative URL");
+                    return Err(NetError::InvalidUrl);
+                }
+                None => {
+                    tracing::debug!(url = %self.url, "relative URL without a base_url");
+                    return Err(NetError::InvalidUrl);
+                }
+            },
+            Err(_) => return Err(NetError::InvalidUrl),
+        };
+        let pairs = self.query.as_ref().map_err(|_| NetError::InvalidUrl)?;
+        crate::http::query::append(&mut url, pairs);
+        Ok(url)
+    }
+
+    /// Span for this request, named and attributed per the OpenTelemetry
+    /// HTTP client semantic conventions.
+    fn span(&self) -> tracing::Span {
+        let span = tracing::info_span!(
+            target: "chromenet::http",
+            "HTTP request",
+            otel.name = %self.method,
+            otel.kind = "client",
+            otel.status_code = Empty,
+            http.request.method = %self.method,
+            url.full = Empty,
+            server.address = Empty,
+            server.port = Empty,
+            http.response.status_code = Empty,
+            network.protocol.version = Empty,
+            error.type = Empty,
+            request.id = Empty,
         );
+        if let Ok(mut url) = self.url() {
+            // Never export credentials
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            if let Some(host) = url.host_str() {
+                span.record("server.address", host);
+            }
+            if let Some(port) = url.port_or_known_default() {
+                span.record("server.port", port);
+            }
+            span.record("url.full", url.as_str());
+        }
+        span
+    }
+
+    /// Key for coalescing this request with identical ones, `None` if it
+    /// must not be shared.
+    fn flight_key(&self) -> Option<FlightKey> {
+        if !matches!(self.method, Method::GET | Method::HEAD)
+            || !matches!(self.cookies, RequestCookies::Client)
+            || self.credentials.is_some()
+            || self.emulation_override.is_some()
+            || self.connection_reuse != ConnectionReuse::Allowed
+        {
+            return None;
+        }
+        let body: &[u8] = match &self.body {
+            None | Some(RequestBody::Empty) => &[],
+            Some(RequestBody::Bytes(bytes)) => bytes,
+            Some(RequestBody::Rewindable(_)) => return None,
+        };
+        let url = self.url().ok()?;
+        // A forced refresh must not be answered by a flight that may come
+        // from the cache, and the other way round
+        let cache_mode = self
+            .client
+            .cache
+            .as_ref()
+            .map(|cache| self.cache_mode.unwrap_or_else(|| cache.mode()));
+        let digest = self
+            .content_digest
+            .as_ref()
+            .or(self.client.content_digest.as_ref());
+        let trace_context = self.trace_context.clone().or_else(|| {
+            self.client
+                .trace_propagator
+                .as_ref()
+                .and_then(|p| p.current())
+        });
+        let header_override: Option<Vec<(&str, &[u8])>> = self.header_override.as_ref().map(|h| {
+            h.iter()
+                .map(|(name, value)| (name.as_str(), value.as_bytes()))
+                .collect()
+        });
+
+        Some(
+            FlightKey::new(&self.method, &url, body)
+                .with_headers(&self.headers)
+                .with(header_override)
+                .with(&self.removed_headers)
+                .with(self.title_case_headers)
+                .with(self.version_pref)
+                .with(self.http1_0)
+                .with(&self.network_isolation_key)
+                .with(&self.server_name)
+                .with(&self.connect_to)
+                .with(&self.post_resolve)
+                .with(&self.site_for_cookies)
+                .with(cache_mode)
+                .with(digest)
+                .with(trace_context)
+                .with(self.timeout),
+        )
+    }
+
+    /// Whether the request sets or removes `Accept-Encoding` itself,
+    /// rather than taking the profile's and the client's.
+    fn controls_accept_encoding(&self) -> bool {
+        let name = http::header::ACCEPT_ENCODING;
+        self.headers.contains_key(&name)
+            || self
+                .header_override
+                .as_ref()
+                .is_some_and(|h| h.get(name.as_str()).is_some())
+            || self
+                .removed_headers
+                .iter()
+                .any(|removed| removed.eq_ignore_ascii_case(name.as_str()))
+    }
+
+    /// Resolve everything the request would send, without connecting or
+    /// sending anything: the method, the URL after HSTS upgrades and
+    /// rules redirects, the headers in the order they go out over
+    /// HTTP/1.1, and the body. The headers are layered as
+    /// [`send`](Self::send) layers them, with `Host`, the cookies of the
+    /// cookie store, `Authorization` from the auth cache and the
+    /// `priority` header, so callers can sign, log or diff requests.
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), chromenet::base::neterror::NetError> {
+    /// let client = chromenet::Client::new();
+    /// let request = client.get("https://example.com/api").header("x-key", "1");
+    /// let parts = request.build_parts().await?;
+    /// println!("{parts}");
+    /// let response = request.send().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// robots.txt and the cache are not consulted, and nothing is
+    /// counted against a rate limiter. A streaming body is read once to
+    /// compute a [content digest](Self::content_digest), as sending it
+    /// would. See [`crate::http::requestparts`].
+    pub async fn build_parts(&self) -> Result<RequestParts, NetError> {
+        let mut job = self.build_job().await?;
+        let headers = job.prepare()?;
+        let body = self.body.as_ref().unwrap_or(&RequestBody::Empty);
+        Ok(RequestParts::new(
+            self.method.clone(),
+            job.url().clone(),
+            headers,
+            body,
+        ))
+    }
+
+    async fn execute(self) -> Result<crate::http::HttpResponse, NetError> {
+        let mut job = self.build_job().await?;
+        let url = self.url()?;
+        if let Some(limiter) = &self.client.rate_limiter {
+            limiter.wait(&url).await;
+        }
+        let rate_limiter = self.client.rate_limiter.clone().map(|l| (l, url));
+
+        // Start the job
+        job.start().await?;
+
+        // Get response
+        let response = job.take_response().ok_or(NetError::ConnectionFailed)?;
+        if let Some((limiter, url)) = rate_limiter {
+            limiter.record(&url, response.status(), response.headers());
+            let crawl_delay = response.robots().and_then(|verdict| verdict.crawl_delay);
+            if let (Some(delay), Some(final_url)) = (crawl_delay, response.url()) {
+                limiter.record_crawl_delay(final_url, delay);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Set a job up with everything the request sends, ready to start.
+    async fn build_job(&self) -> Result<URLRequestHttpJob, NetError> {
+        let url = self.url()?;
+        if self.invalid_header || self.client.invalid_user_agent {
+            return Err(NetError::InvalidHeader);
+        }
+        if self.server_name.is_override() && !self.client.allow_sni_override {
+            return Err(NetError::SniOverrideNotAllowed);
+        }
+
+        let cookie_store = match self.cookies {
+            RequestCookies::Client | RequestCookies::Disabled => self.client.cookie_store.clone(),
+            RequestCookies::Store(ref store) => store.clone(),
+            RequestCookies::Temporary => Arc::new(CookieMonster::new()),
+        };
+
+        // Integrity field over the body, unless the caller set it
+        let digest = self
+            .content_digest
+            .as_ref()
+            .or(self.client.content_digest.as_ref());
+        let digest_header = match (digest, &self.body) {
+            (Some(digest), Some(body))
+                if !body.is_empty() && !self.headers.contains_key(digest.header_name()) =>
+            {
+                Some((digest.header_name(), digest.digest_body(body).await?))
+            }
+            _ => None,
+        };
 
-        job.set_method(self.method);
+        // Create job using existing infrastructure
+        let system_proxy = self
+            .client
+            .system_proxy
+            .as_ref()
+            .and_then(|system| system.proxy_for(&url))
+            .cloned();
+        let mut job = URLRequestHttpJob::new(self.client.factory.clone(), url, cookie_store);
+        tracing::Span::current().record("request.id", job.id().get());
+        if matches!(self.cookies, RequestCookies::Disabled) {
+            job.set_allow_cookies(false);
+        }
+        if let Some(site) = &self.site_for_cookies {
+            job.set_site_for_cookies(site.clone());
+        }
+
+        job.set_method(self.method.clone());
+        job.set_auth_cache(self.client.auth_cache.clone());
+        if let Some((username, password)) = &self.credentials {
+            job.set_credentials(username, password.expose());
+        }
+        if let Some(body) = &self.body {
+            job.set_body(body.clone());
+        }
+        job.set_version_pref(self.version_pref.unwrap_or(self.client.version_pref));
+        job.set_http1_0(self.http1_0);
+        job.set_priority(self.priority);
+        job.set_connection_reuse(self.connection_reuse);
+        job.set_server_name(self.server_name.clone());
+        job.set_connect_to(self.connect_to.clone());
+        if let Some(hook) = self
+            .post_resolve
+            .clone()
+            .or_else(|| self.client.post_resolve.clone())
+        {
+            job.set_post_resolve(hook);
+        }
+        if let Some(rules) = &self.client.rules {
+            job.set_rules(rules.clone());
+        }
+        if let Some(hsts) = &self.client.hsts {
+            job.set_hsts(hsts.clone());
+        }
+        if let Some(robots) = &self.client.robots {
+            job.set_robots(robots.clone());
+        }
+        job.set_follow_refresh(self.client.follow_refresh);
+        job.set_max_redirect_drain(self.client.max_redirect_drain);
+        job.set_header_limits(self.client.header_limits);
+        job.set_url_limits(self.client.url_limits);
+        job.set_cert_policy(self.client.cert_policy.clone());
+        job.set_resource_type(self.resource_type);
+        job.set_extensions(self.extensions.clone());
+        if let Some(cache) = &self.client.cache {
+            let mode = self.cache_mode.unwrap_or_else(|| cache.mode());
+            job.set_cache(cache.clone(), mode);
+        }
+        if let Some(decoders) = &self.client.content_decoders {
+            job.set_content_decoders(decoders.clone());
+        }
+        if let Some(challenges) = &self.client.challenges {
+            job.set_challenges(challenges.clone());
+        }
+        if let Some(store) = &self.client.shared_dictionaries {
+            if !self.controls_accept_encoding() {
+                job.set_shared_dictionaries(store.clone());
+            }
+        }
 
         // Apply headers from emulation
         let emulation = self
@@ -245,10 +2137,13 @@ impl RequestBuilder {
             .or(self.client.emulation.as_ref());
 
         if let Some(emu) = emulation {
-            for (key, value) in emu.headers.iter() {
-                if let Ok(k) = key.as_str().parse::<http::header::HeaderName>() {
-                    if let Ok(v) = value.to_str() {
-                        job.add_header(k.as_str(), v);
+            // Profile headers are skipped when the request replaces them
+            if self.header_override.is_none() {
+                for (key, value) in emu.headers.iter() {
+                    if let Ok(k) = key.as_str().parse::<http::header::HeaderName>() {
+                        if let Ok(v) = value.to_str() {
+                            job.add_header(k.as_str(), v);
+                        }
                     }
                 }
             }
@@ -257,6 +2152,80 @@ impl RequestBuilder {
             if let Some(h2_opts) = &emu.http2_options {
                 job.set_h2_fingerprint(h2_opts.to_h2_fingerprint());
             }
+            if let Some(profile) = &emu.profile {
+                job.set_emulation_profile(profile.clone());
+            }
+        }
+
+        // Apply request-level header override
+        if let Some(headers) = &self.header_override {
+            for (key, value) in headers.iter() {
+                if let Ok(v) = value.to_str() {
+                    job.add_header(key.as_str(), v);
+                }
+            }
+        }
+
+        // Client defaults replace same-named profile headers in place; the
+        // request's ordered headers are left alone by both defaults and
+        // client-level removals
+        let in_override = |name: &str| {
+            self.header_override
+                .as_ref()
+                .is_some_and(|h| h.get(name).is_some())
+        };
+        for (key, value) in self.client.default_headers.iter() {
+            if in_override(key.as_str()) {
+                continue;
+            }
+            if let Ok(v) = value.to_str() {
+                job.add_header(key.as_str(), v);
+            }
+        }
+        for name in &self.client.removed_default_headers {
+            if !in_override(name) {
+                job.remove_header(name);
+            }
+        }
+
+        // Apply HTTP/1.1 header casing
+        let mut http1_options = emulation
+            .and_then(|emu| emu.http1_options.clone())
+            .unwrap_or_default();
+        if self.title_case_headers {
+            http1_options.title_case_headers = true;
+        }
+        job.set_http1_options(http1_options);
+
+        let priority_header = self
+            .client
+            .priority_header
+            .or(emulation.map(|emu| emu.priority_header))
+            .unwrap_or_default();
+        job.set_priority_header(priority_header, self.incremental);
+
+        // Trace context, unless the caller set the headers directly
+        let trace_context = self.trace_context.clone().or_else(|| {
+            self.client
+                .trace_propagator
+                .as_ref()
+                .and_then(|p| p.current())
+        });
+        if let Some(context) = trace_context {
+            if !self.headers.contains_key("traceparent") {
+                job.add_header("traceparent", &context.traceparent());
+                if let Some(tracestate) = context.tracestate() {
+                    if !self.headers.contains_key("tracestate") {
+                        job.add_header("tracestate", tracestate);
+                    }
+                }
+            }
+        }
+
+        if let Some((name, value)) = &digest_header {
+            if let Ok(value) = value.to_str() {
+                job.add_header(name, value);
+            }
         }
 
         // Apply custom headers (override emulation headers)
@@ -266,15 +2235,106 @@ impl RequestBuilder {
             }
         }
 
-        // Apply proxy
-        if let Some(ref proxy) = self.client.proxy {
-            job.set_proxy(proxy.clone());
+        // Suppress headers last so nothing above can re-add them
+        for name in &self.removed_headers {
+            job.remove_header(name);
         }
 
-        // Start the job
-        job.start().await?;
+        if self.client.ua_consistency != UaConsistency::Off {
+            let fingerprint_ua = emulation
+                .and_then(|emu| emu.headers.get(http::header::USER_AGENT))
+                .and_then(|v| v.to_str().ok());
+            let mismatches = useragent::check_with(|name| job.header(name), fingerprint_ua);
+            match self.client.ua_consistency {
+                UaConsistency::Strict if !mismatches.is_empty() => {
+                    let reason = mismatches
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("; ");
+                    return Err(NetError::InconsistentIdentity { reason });
+                }
+                UaConsistency::Derive => {
+                    // Client hints set on the request itself are left alone
+                    let fix_hints = mismatches.iter().any(|m| m.is_client_hint())
+                        && !CLIENT_HINTS.iter().any(|n| self.headers.contains_key(*n));
+                    if fix_hints {
+                        let ua = UserAgent::parse(job.header("user-agent").unwrap_or_default());
+                        let hints = ua.client_hints();
+                        if hints.is_empty() {
+                            for name in CLIENT_HINTS {
+                                job.remove_header(name);
+                            }
+                        }
+                        // Replaced in place, keeping the profile's order
+                        for (name, value) in hints {
+                            job.add_header(name, &value);
+                        }
+                    }
+                    for mismatch in mismatches.iter().filter(|m| !m.is_client_hint()) {
+                        tracing::warn!(%mismatch, "inconsistent browser identity");
+                    }
+                }
+                _ => {
+                    for mismatch in &mismatches {
+                        tracing::warn!(%mismatch, "inconsistent browser identity");
+                    }
+                }
+            }
+        }
 
-        // Get response
-        job.take_response().ok_or(NetError::ConnectionFailed)
+        // Apply proxy, the system's unless the client has its own
+        if let Some(proxy) = self.client.proxy.clone().or(system_proxy) {
+            job.set_proxy(proxy);
+        }
+        if let Some(nik) = self
+            .network_isolation_key
+            .clone()
+            .or_else(|| self.client.network_isolation_key.clone())
+        {
+            job.set_network_isolation_key(nik);
+        }
+        Ok(job)
+    }
+}
+
+/// Sends [`http::Request`]s, so tower middleware such as timeouts, retries
+/// and load shedding can wrap the client (feature `tower`):
+///
+/// ```rust,ignore
+/// use tower::{ServiceBuilder, ServiceExt};
+///
+/// let service = ServiceBuilder::new()
+///     .timeout(Duration::from_secs(10))
+///     .service(Client::new());
+/// let request = http::Request::get("https://example.com/").body(())?;
+/// let response = service.oneshot(request).await?;
+/// ```
+///
+/// The request goes through [`Client::request`] like any other: emulation,
+/// default headers and cookies apply, and a relative URI is joined to the
+/// [base URL](ClientBuilder::base_url). The request's HTTP version is
+/// ignored; its extensions are carried over to the response, as with
+/// [`RequestBuilder::extension`].
+#[cfg(feature = "tower")]
+impl<B: Into<RequestBody>> tower_service::Service<http::Request<B>> for Client {
+    type Response = crate::http::HttpResponse;
+    type Error = NetError;
+    type Future = futures::future::BoxFuture<'static, Result<Self::Response, NetError>>;
+
+    /// Always ready: requests over the socket limits wait in the pool.
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), NetError>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let (parts, body) = request.into_parts();
+        let mut builder = self.request(parts.method, parts.uri.to_string());
+        builder.headers = parts.headers;
+        builder.extensions = parts.extensions;
+        Box::pin(builder.body(body).send())
     }
 }
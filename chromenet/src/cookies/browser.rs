@@ -1,7 +1,8 @@
 //! Browser cookie extraction from Chrome/Firefox SQLite databases.
 //!
 //! Reads cookies from local browser databases for session reuse.
-//! Supports Chrome, Chromium, Edge, Brave, Opera, Firefox, and Safari.
+//! Supports Chrome, Chromium, Edge, Brave, Opera, Opera GX, Vivaldi, Arc,
+//! Firefox, and Safari, including snap and flatpak installs on Linux.
 //!
 //! ## Encryption Support
 //! - **Linux v10**: Fully supported (hardcoded key + empty key fallback)
@@ -11,8 +12,10 @@
 
 use crate::base::neterror::NetError;
 use crate::cookies::canonicalcookie::{CanonicalCookie, CookiePriority, SameSite};
+use crate::cookies::decrypt::{KeySource, KeyringIdentity};
 use crate::cookies::oscrypt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use time::OffsetDateTime;
 
 /// Supported browsers for cookie extraction.
@@ -28,6 +31,12 @@ pub enum Browser {
     Brave,
     /// Opera Browser
     Opera,
+    /// Opera GX (macOS and Windows)
+    OperaGx,
+    /// Vivaldi
+    Vivaldi,
+    /// Arc (macOS and Windows)
+    Arc,
     /// Mozilla Firefox
     Firefox,
     /// Apple Safari (macOS only)
@@ -37,10 +46,7 @@ pub enum Browser {
 impl Browser {
     /// Returns true if this is a Chromium-based browser.
     pub fn is_chromium_based(&self) -> bool {
-        matches!(
-            self,
-            Browser::Chrome | Browser::Chromium | Browser::Edge | Browser::Brave | Browser::Opera
-        )
+        !matches!(self, Browser::Firefox | Browser::Safari)
     }
 
     /// Returns all Chromium-based browsers.
@@ -51,8 +57,117 @@ impl Browser {
             Browser::Edge,
             Browser::Brave,
             Browser::Opera,
+            Browser::OperaGx,
+            Browser::Vivaldi,
+            Browser::Arc,
         ]
     }
+
+    /// Names the browser keeps its cookie password under in the keyring.
+    ///
+    /// Edge and Opera use Chromium's entries on Linux and Vivaldi Chrome's,
+    /// but each has its own on macOS. Firefox and Safari do not encrypt
+    /// cookies; they get Chrome's.
+    pub fn keyring_identity(&self) -> KeyringIdentity {
+        match self {
+            Browser::Chrome | Browser::Firefox | Browser::Safari => {
+                KeyringIdentity::named("Chrome")
+            }
+            Browser::Chromium => KeyringIdentity::named("Chromium"),
+            Browser::Edge => KeyringIdentity::named("Microsoft Edge")
+                .with_application("chromium")
+                .with_kwallet_name("Chromium"),
+            Browser::Brave => KeyringIdentity::named("Brave"),
+            Browser::Opera | Browser::OperaGx => KeyringIdentity::named("Opera")
+                .with_application("chromium")
+                .with_kwallet_name("Chromium"),
+            Browser::Vivaldi => KeyringIdentity::named("Vivaldi")
+                .with_application("chrome")
+                .with_kwallet_name("Chrome"),
+            Browser::Arc => KeyringIdentity::named("Arc"),
+        }
+    }
+}
+
+/// Cookie database names inside a Chromium profile, newest layout first.
+const CHROMIUM_COOKIE_FILES: [&str; 2] = ["Network/Cookies", "Cookies"];
+
+/// Linux user data directories relative to `$HOME`: native, snap, flatpak.
+#[cfg(target_os = "linux")]
+fn linux_user_data_dirs(browser: Browser) -> &'static [&'static str] {
+    match browser {
+        Browser::Chrome => &[
+            ".config/google-chrome",
+            ".var/app/com.google.Chrome/config/google-chrome",
+        ],
+        Browser::Chromium => &[
+            ".config/chromium",
+            "snap/chromium/common/chromium",
+            ".var/app/org.chromium.Chromium/config/chromium",
+        ],
+        Browser::Edge => &[
+            ".config/microsoft-edge",
+            ".var/app/com.microsoft.Edge/config/microsoft-edge",
+        ],
+        Browser::Brave => &[
+            ".config/BraveSoftware/Brave-Browser",
+            "snap/brave/current/.config/BraveSoftware/Brave-Browser",
+            ".var/app/com.brave.Browser/config/BraveSoftware/Brave-Browser",
+        ],
+        Browser::Opera => &[".config/opera", "snap/opera/current/.config/opera"],
+        Browser::Vivaldi => &[
+            ".config/vivaldi",
+            ".var/app/com.vivaldi.Vivaldi/config/vivaldi",
+        ],
+        // Not available on Linux
+        Browser::OperaGx | Browser::Arc | Browser::Firefox | Browser::Safari => &[],
+    }
+}
+
+/// Linux Firefox profile directories relative to `$HOME`: native, snap,
+/// flatpak.
+#[cfg(target_os = "linux")]
+const LINUX_FIREFOX_DIRS: [&str; 3] = [
+    ".mozilla/firefox",
+    "snap/firefox/common/.mozilla/firefox",
+    ".var/app/org.mozilla.firefox/.mozilla/firefox",
+];
+
+/// First existing cookie database of `profile` in one of `dirs`, or where
+/// the first directory would keep it.
+fn find_chromium_cookies(dirs: &[PathBuf], profile: Option<&str>) -> Option<PathBuf> {
+    let profile_dirs = dirs.iter().map(|dir| match profile {
+        Some(profile) => dir.join(profile),
+        None => dir.clone(),
+    });
+    profile_dirs
+        .clone()
+        .flat_map(|dir| CHROMIUM_COOKIE_FILES.map(|file| dir.join(file)))
+        .find(|path| path.exists())
+        .or_else(|| profile_dirs.clone().next().map(|dir| dir.join("Cookies")))
+}
+
+/// Firefox container tabs ("contextual identities") to read cookies from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FirefoxContainer {
+    /// Every container and cookies outside containers (default).
+    #[default]
+    All,
+    /// Only cookies outside any container.
+    NoContainer,
+    /// The container with this `userContextId`.
+    Id(u32),
+    /// The container with this name, case-insensitive.
+    Name(String),
+}
+
+/// A container defined in a Firefox profile's `containers.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerInfo {
+    /// `userContextId` of the container's cookies.
+    pub id: u32,
+    /// Display name.
+    pub name: String,
 }
 
 /// Reader for browser cookie databases.
@@ -60,6 +175,10 @@ pub struct BrowserCookieReader {
     browser: Browser,
     profile: Option<String>,
     domain_filter: Option<String>,
+    container: FirefoxContainer,
+    keyring_identity: KeyringIdentity,
+    /// Where the key of the last read came from
+    key_source: Mutex<Option<KeySource>>,
 }
 
 impl BrowserCookieReader {
@@ -69,9 +188,29 @@ impl BrowserCookieReader {
             browser,
             profile: None,
             domain_filter: None,
+            container: FirefoxContainer::All,
+            keyring_identity: browser.keyring_identity(),
+            key_source: Mutex::new(None),
         }
     }
 
+    /// Look the cookie password up in the keyring under `identity` instead
+    /// of the browser's [own](Browser::keyring_identity).
+    pub fn keyring_identity(mut self, identity: KeyringIdentity) -> Self {
+        self.keyring_identity = identity;
+        self
+    }
+
+    /// Where the key that decrypted the cookies of the last
+    /// [`read_cookies`](Self::read_cookies) came from, `None` if it needed
+    /// no keyring key.
+    pub fn key_source(&self) -> Option<KeySource> {
+        self.key_source
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
     /// Use a specific profile (default: "Default" for Chrome, first profile for Firefox).
     pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
         self.profile = Some(profile.into());
@@ -84,54 +223,85 @@ impl BrowserCookieReader {
         self
     }
 
+    /// Read Firefox cookies from `container` only. Ignored for other
+    /// browsers.
+    pub fn firefox_container(mut self, container: FirefoxContainer) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// List the containers of the Firefox profile.
+    ///
+    /// Returns an empty list for other browsers or if the profile has no
+    /// `containers.json`.
+    pub fn list_firefox_containers(&self) -> Vec<ContainerInfo> {
+        if self.browser != Browser::Firefox {
+            return vec![];
+        }
+        self.get_db_path()
+            .and_then(|db| read_containers(db.parent()?))
+            .unwrap_or_default()
+    }
+
+    /// `userContextId` to keep for the selected container, `None` for all.
+    fn container_id(&self, db_path: &Path) -> Result<Option<u32>, NetError> {
+        match &self.container {
+            FirefoxContainer::All => Ok(None),
+            FirefoxContainer::NoContainer => Ok(Some(0)),
+            FirefoxContainer::Id(id) => Ok(Some(*id)),
+            FirefoxContainer::Name(name) => db_path
+                .parent()
+                .and_then(read_containers)
+                .unwrap_or_default()
+                .into_iter()
+                .find(|c| c.name.eq_ignore_ascii_case(name))
+                .map(|c| Some(c.id))
+                .ok_or_else(|| {
+                    NetError::cookie_invalid_data(format!("no Firefox container named {:?}", name))
+                }),
+        }
+    }
+
     /// Get the path to the browser's cookie database.
+    ///
+    /// Looks in the native install location first, then in snap and flatpak
+    /// locations on Linux. If no database exists, returns where the native
+    /// install would keep it.
     pub fn get_db_path(&self) -> Option<PathBuf> {
         match self.browser {
-            Browser::Chrome => self.chromium_cookie_path("google-chrome", "Google/Chrome"),
-            Browser::Chromium => self.chromium_cookie_path("chromium", "Chromium"),
-            Browser::Edge => self.chromium_cookie_path("microsoft-edge", "Microsoft/Edge"),
-            Browser::Brave => self
-                .chromium_cookie_path("BraveSoftware/Brave-Browser", "BraveSoftware/Brave-Browser"),
-            Browser::Opera => self.chromium_cookie_path("opera", "com.operasoftware.Opera"),
             Browser::Firefox => self.firefox_cookie_path(),
             Browser::Safari => self.safari_cookie_path(),
+            _ => self.chromium_cookie_path(),
         }
     }
 
-    fn chromium_cookie_path(&self, linux_name: &str, _macos_name: &str) -> Option<PathBuf> {
-        let profile = self.profile.as_deref().unwrap_or("Default");
-
-        #[cfg(target_os = "linux")]
-        {
-            let home = std::env::var("HOME").ok()?;
-            Some(PathBuf::from(format!(
-                "{}/.config/{}/{}/Cookies",
-                home, linux_name, profile
-            )))
+    /// Directory of the selected profile, found the same way as the cookie
+    /// database. `None` for Safari, which has no profiles.
+    pub fn profile_dir(&self) -> Option<PathBuf> {
+        if self.browser == Browser::Safari {
+            return None;
         }
-
-        #[cfg(target_os = "macos")]
-        {
-            let home = std::env::var("HOME").ok()?;
-            Some(PathBuf::from(format!(
-                "{}/Library/Application Support/{}/{}/Cookies",
-                home, macos_name, profile
-            )))
+        let db = self.get_db_path()?;
+        let dir = db.parent()?;
+        match dir.file_name() {
+            Some(name) if name == "Network" && self.browser.is_chromium_based() => {
+                dir.parent().map(Path::to_path_buf)
+            }
+            _ => Some(dir.to_path_buf()),
         }
+    }
 
-        #[cfg(target_os = "windows")]
-        {
-            let local_app_data = std::env::var("LOCALAPPDATA").ok()?;
-            Some(PathBuf::from(format!(
-                "{}/{}/User Data/{}/Network/Cookies",
-                local_app_data, macos_name, profile
-            )))
-        }
+    fn chromium_cookie_path(&self) -> Option<PathBuf> {
+        find_chromium_cookies(&self.chromium_user_data_dirs(), self.chromium_profile())
+    }
 
-        #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
-        {
-            let _ = (linux_name, macos_name, profile);
-            None
+    /// Profile directory inside the user data directory, `None` for Opera,
+    /// which keeps its single profile in the user data directory itself.
+    fn chromium_profile(&self) -> Option<&str> {
+        match (&self.profile, self.browser) {
+            (Some(profile), _) => Some(profile),
+            (None, Browser::Opera | Browser::OperaGx) => None,
+            (None, _) => Some("Default"),
         }
     }
 
@@ -152,76 +322,29 @@ impl BrowserCookieReader {
     }
 
     fn firefox_cookie_path(&self) -> Option<PathBuf> {
-        #[cfg(target_os = "linux")]
-        {
-            let home = std::env::var("HOME").ok()?;
-            let firefox_dir = PathBuf::from(format!("{}/.mozilla/firefox", home));
-
-            // Find first .default profile if no profile specified
-            if let Some(profile) = &self.profile {
-                return Some(firefox_dir.join(profile).join("cookies.sqlite"));
-            }
-
-            // Auto-detect profile
-            if let Ok(entries) = std::fs::read_dir(&firefox_dir) {
-                for entry in entries.flatten() {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    if name.ends_with(".default") || name.ends_with(".default-release") {
-                        return Some(entry.path().join("cookies.sqlite"));
-                    }
-                }
-            }
-            None
+        let dirs = self.get_firefox_profiles_dirs();
+
+        if let Some(profile) = &self.profile {
+            let candidates = dirs.iter().map(|d| d.join(profile).join("cookies.sqlite"));
+            return candidates
+                .clone()
+                .find(|p| p.exists())
+                .or_else(|| candidates.clone().next());
         }
 
-        #[cfg(target_os = "macos")]
-        {
-            let home = std::env::var("HOME").ok()?;
-            let firefox_dir = PathBuf::from(format!(
-                "{}/Library/Application Support/Firefox/Profiles",
-                home
-            ));
-
-            if let Some(profile) = &self.profile {
-                return Some(firefox_dir.join(profile).join("cookies.sqlite"));
-            }
-
-            // Auto-detect
-            if let Ok(entries) = std::fs::read_dir(&firefox_dir) {
-                for entry in entries.flatten() {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    if name.ends_with(".default") || name.ends_with(".default-release") {
-                        return Some(entry.path().join("cookies.sqlite"));
-                    }
-                }
-            }
-            None
-        }
-
-        #[cfg(target_os = "windows")]
-        {
-            let app_data = std::env::var("APPDATA").ok()?;
-            let firefox_dir = PathBuf::from(format!("{}/Mozilla/Firefox/Profiles", app_data));
-
-            if let Some(profile) = &self.profile {
-                return Some(firefox_dir.join(profile).join("cookies.sqlite"));
-            }
-
-            if let Ok(entries) = std::fs::read_dir(&firefox_dir) {
-                for entry in entries.flatten() {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    if name.ends_with(".default") || name.ends_with(".default-release") {
-                        return Some(entry.path().join("cookies.sqlite"));
-                    }
+        // Auto-detect the default profile
+        for dir in &dirs {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.ends_with(".default") || name.ends_with(".default-release") {
+                    return Some(entry.path().join("cookies.sqlite"));
                 }
             }
-            None
-        }
-
-        #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
-        {
-            None
         }
+        None
     }
 
     /// List available profiles for this browser.
@@ -229,24 +352,18 @@ impl BrowserCookieReader {
     /// Returns a list of profile names that can be passed to `with_profile()`.
     pub fn list_profiles(&self) -> Vec<String> {
         match self.browser {
-            Browser::Chrome
-            | Browser::Chromium
-            | Browser::Edge
-            | Browser::Brave
-            | Browser::Opera => self.list_chromium_profiles(),
             Browser::Firefox => self.list_firefox_profiles(),
             Browser::Safari => vec![], // Safari doesn't have profiles
+            _ => self.list_chromium_profiles(),
         }
     }
 
     fn list_chromium_profiles(&self) -> Vec<String> {
-        let user_data_dir = self.get_chromium_user_data_dir();
-        let Some(dir) = user_data_dir else {
-            return vec![];
-        };
-
         let mut profiles = Vec::new();
-        if let Ok(entries) = std::fs::read_dir(&dir) {
+        for dir in self.chromium_user_data_dirs() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
             for entry in entries.flatten() {
                 if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
                     let name = entry.file_name().to_string_lossy().to_string();
@@ -263,69 +380,81 @@ impl BrowserCookieReader {
             }
         }
         profiles.sort();
+        profiles.dedup();
         profiles
     }
 
-    fn get_chromium_user_data_dir(&self) -> Option<PathBuf> {
+    /// User data directories the browser may be installed with, native
+    /// install first.
+    fn chromium_user_data_dirs(&self) -> Vec<PathBuf> {
         #[cfg(target_os = "linux")]
         {
-            let home = std::env::var("HOME").ok()?;
-            let browser_dir = match self.browser {
-                Browser::Chrome => "google-chrome",
-                Browser::Chromium => "chromium",
-                Browser::Edge => "microsoft-edge",
-                Browser::Brave => "BraveSoftware/Brave-Browser",
-                Browser::Opera => "opera",
-                _ => return None,
+            let Some(home) = std::env::var_os("HOME") else {
+                return vec![];
             };
-            Some(PathBuf::from(format!("{}/.config/{}", home, browser_dir)))
+            linux_user_data_dirs(self.browser)
+                .iter()
+                .map(|dir| PathBuf::from(&home).join(dir))
+                .collect()
         }
 
         #[cfg(target_os = "macos")]
         {
-            let home = std::env::var("HOME").ok()?;
+            let home = std::env::var_os("HOME");
             let browser_dir = match self.browser {
                 Browser::Chrome => "Google/Chrome",
                 Browser::Chromium => "Chromium",
-                Browser::Edge => "Microsoft/Edge",
+                Browser::Edge => "Microsoft Edge",
                 Browser::Brave => "BraveSoftware/Brave-Browser",
                 Browser::Opera => "com.operasoftware.Opera",
-                _ => return None,
+                Browser::OperaGx => "com.operasoftware.OperaGX",
+                Browser::Vivaldi => "Vivaldi",
+                Browser::Arc => "Arc/User Data",
+                Browser::Firefox | Browser::Safari => return vec![],
             };
-            Some(PathBuf::from(format!(
-                "{}/Library/Application Support/{}",
-                home, browser_dir
-            )))
+            home.map(|home| {
+                PathBuf::from(home)
+                    .join("Library/Application Support")
+                    .join(browser_dir)
+            })
+            .into_iter()
+            .collect()
         }
 
         #[cfg(target_os = "windows")]
         {
-            let local_app_data = std::env::var("LOCALAPPDATA").ok()?;
-            let browser_dir = match self.browser {
-                Browser::Chrome => "Google/Chrome/User Data",
-                Browser::Chromium => "Chromium/User Data",
-                Browser::Edge => "Microsoft/Edge/User Data",
-                Browser::Brave => "BraveSoftware/Brave-Browser/User Data",
-                Browser::Opera => "Opera Software/Opera Stable",
-                _ => return None,
+            let (var, browser_dir) = match self.browser {
+                Browser::Chrome => ("LOCALAPPDATA", "Google/Chrome/User Data"),
+                Browser::Chromium => ("LOCALAPPDATA", "Chromium/User Data"),
+                Browser::Edge => ("LOCALAPPDATA", "Microsoft/Edge/User Data"),
+                Browser::Brave => ("LOCALAPPDATA", "BraveSoftware/Brave-Browser/User Data"),
+                Browser::Opera => ("APPDATA", "Opera Software/Opera Stable"),
+                Browser::OperaGx => ("APPDATA", "Opera Software/Opera GX Stable"),
+                Browser::Vivaldi => ("LOCALAPPDATA", "Vivaldi/User Data"),
+                Browser::Arc => (
+                    "LOCALAPPDATA",
+                    "Packages/TheBrowserCompany.Arc_ttt1ap7aakyb4/LocalCache/Local/Arc/User Data",
+                ),
+                Browser::Firefox | Browser::Safari => return vec![],
             };
-            Some(PathBuf::from(format!("{}/{}", local_app_data, browser_dir)))
+            std::env::var_os(var)
+                .map(|base| PathBuf::from(base).join(browser_dir))
+                .into_iter()
+                .collect()
         }
 
         #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
         {
-            None
+            vec![]
         }
     }
 
     fn list_firefox_profiles(&self) -> Vec<String> {
-        let firefox_dir = self.get_firefox_profiles_dir();
-        let Some(dir) = firefox_dir else {
-            return vec![];
-        };
-
         let mut profiles = Vec::new();
-        if let Ok(entries) = std::fs::read_dir(&dir) {
+        for dir in self.get_firefox_profiles_dirs() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
             for entry in entries.flatten() {
                 if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
                     let name = entry.file_name().to_string_lossy().to_string();
@@ -338,61 +467,59 @@ impl BrowserCookieReader {
             }
         }
         profiles.sort();
+        profiles.dedup();
         profiles
     }
 
-    fn get_firefox_profiles_dir(&self) -> Option<PathBuf> {
+    /// Firefox profile directories, native install first.
+    fn get_firefox_profiles_dirs(&self) -> Vec<PathBuf> {
         #[cfg(target_os = "linux")]
         {
-            let home = std::env::var("HOME").ok()?;
-            Some(PathBuf::from(format!("{}/.mozilla/firefox", home)))
+            let Some(home) = std::env::var_os("HOME") else {
+                return vec![];
+            };
+            LINUX_FIREFOX_DIRS
+                .iter()
+                .map(|dir| PathBuf::from(&home).join(dir))
+                .collect()
         }
 
         #[cfg(target_os = "macos")]
         {
-            let home = std::env::var("HOME").ok()?;
-            Some(PathBuf::from(format!(
-                "{}/Library/Application Support/Firefox/Profiles",
-                home
-            )))
+            std::env::var_os("HOME")
+                .map(|home| {
+                    PathBuf::from(home).join("Library/Application Support/Firefox/Profiles")
+                })
+                .into_iter()
+                .collect()
         }
 
         #[cfg(target_os = "windows")]
         {
-            let app_data = std::env::var("APPDATA").ok()?;
-            Some(PathBuf::from(format!(
-                "{}/Mozilla/Firefox/Profiles",
-                app_data
-            )))
+            std::env::var_os("APPDATA")
+                .map(|app_data| PathBuf::from(app_data).join("Mozilla/Firefox/Profiles"))
+                .into_iter()
+                .collect()
         }
 
         #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
         {
-            None
+            vec![]
         }
     }
 
     /// Read all cookies from the browser database.
+    ///
+    /// Cookie-specific failures are [`NetError::CookieExtraction`]; convert
+    /// the error into a [`CookieExtractionError`] to match on them:
+    /// - no profile directory for the browser: `BrowserNotFound`
+    /// - no cookie database in it: `DatabaseNotFound`
+    /// - the browser holds the database lock: `DatabaseLocked`
+    /// - a value that cannot be decrypted: `DecryptionFailed` or
+    ///   `KeyringUnavailable`
+    ///
+    /// [`CookieExtractionError`]: super::error::CookieExtractionError
     pub fn read_cookies(&self) -> Result<Vec<CanonicalCookie>, NetError> {
-        let db_path = self.get_db_path().ok_or(NetError::FileNotFound)?;
-
-        if !db_path.exists() {
-            return Err(NetError::FileNotFound);
-        }
-
-        match self.browser {
-            Browser::Chrome
-            | Browser::Chromium
-            | Browser::Edge
-            | Browser::Brave
-            | Browser::Opera => self.read_chromium_cookies(&db_path),
-            Browser::Firefox => self.read_firefox_cookies(&db_path),
-            Browser::Safari => self.read_safari_cookies(&db_path),
-        }
-    }
-
-    /// Read cookies with better error handling.
-    pub fn read_cookies_v2(&self) -> Result<Vec<CanonicalCookie>, NetError> {
         let db_path = self
             .get_db_path()
             .ok_or_else(|| NetError::browser_not_found(format!("{:?}", self.browser)))?;
@@ -402,22 +529,19 @@ impl BrowserCookieReader {
         }
 
         match self.browser {
-            Browser::Chrome
-            | Browser::Chromium
-            | Browser::Edge
-            | Browser::Brave
-            | Browser::Opera => self.read_chromium_cookies_v2(&db_path),
-            Browser::Firefox => self.read_firefox_cookies_v2(&db_path),
-            Browser::Safari => self.read_safari_cookies_v2(&db_path),
+            Browser::Firefox => self.read_firefox_cookies(&db_path),
+            Browser::Safari => self.read_safari_cookies(&db_path),
+            _ => self.read_chromium_cookies(&db_path),
         }
     }
 
-    fn read_safari_cookies(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
-        let data = std::fs::read(path).map_err(|_| NetError::FileNotFound)?;
-        super::safari::parse_binary_cookies(&data).map_err(|_| NetError::InvalidResponse)
+    /// Read all cookies from the browser database.
+    #[deprecated(since = "0.2.0", note = "Use read_cookies instead")]
+    pub fn read_cookies_v2(&self) -> Result<Vec<CanonicalCookie>, NetError> {
+        self.read_cookies()
     }
 
-    fn read_safari_cookies_v2(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
+    fn read_safari_cookies(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
         let data = std::fs::read(path)?;
         super::safari::parse_binary_cookies(&data)
     }
@@ -425,78 +549,6 @@ impl BrowserCookieReader {
     fn read_chromium_cookies(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
         use rusqlite::{Connection, OpenFlags};
 
-        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
-            .map_err(|_| NetError::FileNotFound)?;
-
-        let mut stmt = conn
-            .prepare(
-                "SELECT host_key, name, value, encrypted_value, path, expires_utc, is_secure, is_httponly, samesite
-                 FROM cookies",
-            )
-            .map_err(|_| NetError::InvalidResponse)?;
-
-        let mut cookies = Vec::new();
-        let now = OffsetDateTime::now_utc();
-
-        let mut rows = stmt.query([]).map_err(|_| NetError::InvalidResponse)?;
-
-        // TODO(performance): accessing all rows loads all cookies into memory.
-        // Consider returning an iterator or streaming the results for large databases.
-        while let Some(row) = rows.next().map_err(|_| NetError::InvalidResponse)? {
-            let host_key: String = row.get(0).unwrap_or_default();
-            let name: String = row.get(1).unwrap_or_default();
-            let value: String = row.get(2).unwrap_or_default();
-            let encrypted_value: Vec<u8> = row.get(3).unwrap_or_default();
-            let path: String = row.get(4).unwrap_or_default();
-            let expires_utc: i64 = row.get(5).unwrap_or(0);
-            let is_secure: i32 = row.get(6).unwrap_or(0);
-            let is_httponly: i32 = row.get(7).unwrap_or(0);
-            let samesite: i32 = row.get(8).unwrap_or(-1);
-
-            // Apply domain filter
-            if let Some(ref filter) = self.domain_filter {
-                if !host_key.ends_with(filter) && !host_key.trim_start_matches('.').eq(filter) {
-                    continue;
-                }
-            }
-
-            // Determine the cookie value
-            let cookie_value = if !value.is_empty() {
-                value
-            } else if !encrypted_value.is_empty() {
-                // Try to decrypt
-                match oscrypt::decrypt_v10(&encrypted_value) {
-                    Some(decrypted) => decrypted,
-                    None => continue, // Skip if decryption fails
-                }
-            } else {
-                continue; // Skip cookies with no value
-            };
-
-            let host_only = !host_key.starts_with('.');
-            let cookie = CanonicalCookie {
-                name,
-                value: cookie_value,
-                domain: host_key,
-                path,
-                expiration_time: chrome_time_to_offset(expires_utc),
-                secure: is_secure != 0,
-                http_only: is_httponly != 0,
-                same_site: chrome_samesite(samesite),
-                priority: CookiePriority::Medium,
-                creation_time: now,
-                last_access_time: now,
-                host_only,
-            };
-            cookies.push(cookie);
-        }
-
-        Ok(cookies)
-    }
-
-    fn read_chromium_cookies_v2(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
-        use rusqlite::{Connection, OpenFlags};
-
         let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
 
         let mut stmt = conn.prepare(
@@ -506,6 +558,9 @@ impl BrowserCookieReader {
 
         let mut cookies = Vec::new();
         let now = OffsetDateTime::now_utc();
+        let mut decryptor =
+            oscrypt::CookieDecryptor::new(&format!("{:?}", self.browser).to_lowercase())
+                .with_identity(self.keyring_identity.clone());
 
         let mut rows = stmt.query([])?;
 
@@ -531,7 +586,7 @@ impl BrowserCookieReader {
             let cookie_value = if !value.is_empty() {
                 value
             } else if !encrypted_value.is_empty() {
-                oscrypt::decrypt_cookie(&encrypted_value)?
+                decryptor.decrypt(&encrypted_value)?
             } else {
                 continue;
             };
@@ -554,68 +609,22 @@ impl BrowserCookieReader {
             cookies.push(cookie);
         }
 
-        Ok(cookies)
-    }
-
-    fn read_firefox_cookies(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
-        use rusqlite::{Connection, OpenFlags};
-
-        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
-            .map_err(|_| NetError::FileNotFound)?;
-
-        let mut stmt = conn
-            .prepare(
-                "SELECT host, name, value, path, expiry, isSecure, isHttpOnly, sameSite
-             FROM moz_cookies",
-            )
-            .map_err(|_| NetError::InvalidResponse)?;
-
-        let cookie_iter = stmt
-            .query_map([], |row| {
-                Ok(FirefoxCookieRow {
-                    host: row.get(0)?,
-                    name: row.get(1)?,
-                    value: row.get(2)?,
-                    path: row.get(3)?,
-                    expiry: row.get(4)?,
-                    is_secure: row.get(5)?,
-                    is_http_only: row.get(6)?,
-                    same_site: row.get(7)?,
-                })
-            })
-            .map_err(|_| NetError::InvalidResponse)?;
-
-        let mut cookies = Vec::new();
-        let now = OffsetDateTime::now_utc();
-
-        for row in cookie_iter.flatten() {
-            let cookie = CanonicalCookie {
-                name: row.name,
-                value: row.value,
-                domain: row.host.clone(),
-                path: row.path,
-                expiration_time: firefox_time_to_offset(row.expiry),
-                secure: row.is_secure != 0,
-                http_only: row.is_http_only != 0,
-                same_site: firefox_samesite(row.same_site),
-                priority: CookiePriority::Medium,
-                creation_time: now,
-                last_access_time: now,
-                host_only: !row.host.starts_with('.'),
-            };
-            cookies.push(cookie);
+        let key_source = decryptor.key_source();
+        if let Some(source) = &key_source {
+            tracing::debug!(browser = ?self.browser, key_source = %source, "decrypted cookies");
         }
-
+        *self.key_source.lock().unwrap_or_else(|e| e.into_inner()) = key_source;
         Ok(cookies)
     }
 
-    fn read_firefox_cookies_v2(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
+    fn read_firefox_cookies(&self, path: &PathBuf) -> Result<Vec<CanonicalCookie>, NetError> {
         use rusqlite::{Connection, OpenFlags};
 
         let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
 
+        let container = self.container_id(path)?;
         let mut stmt = conn.prepare(
-            "SELECT host, name, value, path, expiry, isSecure, isHttpOnly, sameSite
+            "SELECT host, name, value, path, expiry, isSecure, isHttpOnly, sameSite, originAttributes
              FROM moz_cookies",
         )?;
 
@@ -633,6 +642,11 @@ impl BrowserCookieReader {
             let is_secure: i32 = row.get(5).unwrap_or(0);
             let is_http_only: i32 = row.get(6).unwrap_or(0);
             let same_site: i32 = row.get(7).unwrap_or(0);
+            let origin_attributes: String = row.get(8).unwrap_or_default();
+
+            if container.is_some_and(|id| user_context_id(&origin_attributes) != id) {
+                continue;
+            }
 
             // Apply domain filter
             if let Some(ref filter) = self.domain_filter {
@@ -674,15 +688,41 @@ struct ChromeCookieRow {
     samesite: i32,
 }
 
-struct FirefoxCookieRow {
-    host: String,
-    name: String,
-    value: String,
-    path: String,
-    expiry: i64,
-    is_secure: i32,
-    is_http_only: i32,
-    same_site: i32,
+/// `userContextId` in a Firefox `originAttributes` suffix such as
+/// `^userContextId=2&firstPartyDomain=example.com`; 0 outside containers.
+fn user_context_id(origin_attributes: &str) -> u32 {
+    origin_attributes
+        .trim_start_matches('^')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("userContextId="))
+        .and_then(|id| id.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Public containers listed in `containers.json` of a Firefox profile.
+fn read_containers(profile_dir: &Path) -> Option<Vec<ContainerInfo>> {
+    let data = std::fs::read(profile_dir.join("containers.json")).ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&data).ok()?;
+    let identities = json.get("identities")?.as_array()?;
+    Some(
+        identities
+            .iter()
+            .filter(|identity| identity["public"].as_bool().unwrap_or(true))
+            .filter_map(|identity| {
+                let id = u32::try_from(identity["userContextId"].as_u64()?).ok()?;
+                // Built-in containers only carry a localization id
+                let name = match identity["name"].as_str() {
+                    Some(name) => name.to_string(),
+                    None => identity["l10nID"]
+                        .as_str()?
+                        .strip_prefix("userContext")?
+                        .strip_suffix(".label")?
+                        .to_string(),
+                };
+                Some(ContainerInfo { id, name })
+            })
+            .collect(),
+    )
 }
 
 /// Convert Chrome's WebKit timestamp to OffsetDateTime.
@@ -792,6 +832,133 @@ mod tests {
         let browsers = Browser::all_chromium();
         assert!(browsers.contains(&Browser::Chrome));
         assert!(browsers.contains(&Browser::Edge));
+        assert!(browsers.contains(&Browser::Vivaldi));
         assert!(!browsers.contains(&Browser::Firefox));
+        assert!(browsers.iter().all(Browser::is_chromium_based));
+    }
+
+    #[test]
+    fn test_user_context_id() {
+        assert_eq!(user_context_id(""), 0);
+        assert_eq!(user_context_id("^userContextId=3"), 3);
+        assert_eq!(
+            user_context_id("^firstPartyDomain=example.com&userContextId=12"),
+            12
+        );
+        assert_eq!(user_context_id("^privateBrowsingId=1"), 0);
+    }
+
+    #[test]
+    fn test_firefox_containers_are_filtered() {
+        let profile = tempfile::tempdir().unwrap();
+        std::fs::write(
+            profile.path().join("containers.json"),
+            r#"{"version":5,"identities":[
+                {"userContextId":1,"public":true,"l10nID":"userContextPersonal.label"},
+                {"userContextId":2,"public":true,"name":"Shopping Alt"},
+                {"userContextId":4294967295,"public":false,"name":"userContextIdInternal.thumbnail"}
+            ]}"#,
+        )
+        .unwrap();
+        let db = profile.path().join("cookies.sqlite");
+        let conn = rusqlite::Connection::open(&db).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_cookies (host TEXT, name TEXT, value TEXT, path TEXT, expiry INTEGER,
+                isSecure INTEGER, isHttpOnly INTEGER, sameSite INTEGER, originAttributes TEXT);
+             INSERT INTO moz_cookies VALUES ('.example.com', 'plain', 'a', '/', 0, 0, 0, 0, '');
+             INSERT INTO moz_cookies VALUES ('.example.com', 'personal', 'b', '/', 0, 0, 0, 0, '^userContextId=1');
+             INSERT INTO moz_cookies VALUES ('.example.com', 'shopping', 'c', '/', 0, 0, 0, 0, '^userContextId=2');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let names = |container| -> Vec<String> {
+            BrowserCookieReader::new(Browser::Firefox)
+                .firefox_container(container)
+                .read_firefox_cookies(&db)
+                .unwrap()
+                .into_iter()
+                .map(|c| c.name)
+                .collect()
+        };
+        assert_eq!(names(FirefoxContainer::All).len(), 3);
+        assert_eq!(names(FirefoxContainer::NoContainer), ["plain"]);
+        assert_eq!(names(FirefoxContainer::Id(2)), ["shopping"]);
+        assert_eq!(
+            names(FirefoxContainer::Name("personal".into())),
+            ["personal"]
+        );
+
+        let missing = BrowserCookieReader::new(Browser::Firefox)
+            .firefox_container(FirefoxContainer::Name("Work".into()))
+            .read_firefox_cookies(&db);
+        assert!(missing.is_err());
+
+        assert_eq!(
+            read_containers(profile.path()).unwrap(),
+            [
+                ContainerInfo {
+                    id: 1,
+                    name: "Personal".into()
+                },
+                ContainerInfo {
+                    id: 2,
+                    name: "Shopping Alt".into()
+                },
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_finds_snap_and_flatpak_installs() {
+        let home = tempfile::tempdir().unwrap();
+        let dirs = |browser| -> Vec<PathBuf> {
+            linux_user_data_dirs(browser)
+                .iter()
+                .map(|dir| home.path().join(dir))
+                .collect()
+        };
+        let touch = |path: &str| {
+            let path = home.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, b"").unwrap();
+            path
+        };
+
+        // Nothing installed: the native location
+        assert_eq!(
+            find_chromium_cookies(&dirs(Browser::Chromium), Some("Default")),
+            Some(home.path().join(".config/chromium/Default/Cookies"))
+        );
+
+        let snap = touch("snap/chromium/common/chromium/Default/Cookies");
+        assert_eq!(
+            find_chromium_cookies(&dirs(Browser::Chromium), Some("Default")),
+            Some(snap)
+        );
+
+        let flatpak =
+            touch(".var/app/com.vivaldi.Vivaldi/config/vivaldi/Profile 1/Network/Cookies");
+        assert_eq!(
+            find_chromium_cookies(&dirs(Browser::Vivaldi), Some("Profile 1")),
+            Some(flatpak)
+        );
+
+        // Native wins over snap
+        let native = touch(".config/chromium/Default/Network/Cookies");
+        assert_eq!(
+            find_chromium_cookies(&dirs(Browser::Chromium), Some("Default")),
+            Some(native)
+        );
+
+        // Opera keeps its profile in the user data directory
+        let opera = touch("snap/opera/current/.config/opera/Cookies");
+        assert_eq!(
+            find_chromium_cookies(&dirs(Browser::Opera), None),
+            Some(opera)
+        );
+
+        assert!(dirs(Browser::Arc).is_empty());
     }
 }
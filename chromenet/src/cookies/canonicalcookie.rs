@@ -1,8 +1,11 @@
+use crate::base::secret::REDACTED;
 use time::OffsetDateTime;
 
 /// Represents a cookie.
 /// Modeled after Chromium's `net::CanonicalCookie`.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// `Debug` output redacts the value.
+#[derive(Clone, PartialEq, Eq)]
 pub struct CanonicalCookie {
     pub name: String,
     pub value: String,
@@ -18,6 +21,25 @@ pub struct CanonicalCookie {
     pub priority: CookiePriority,
 }
 
+impl std::fmt::Debug for CanonicalCookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CanonicalCookie")
+            .field("name", &self.name)
+            .field("value", &REDACTED)
+            .field("domain", &self.domain)
+            .field("path", &self.path)
+            .field("creation_time", &self.creation_time)
+            .field("expiration_time", &self.expiration_time)
+            .field("last_access_time", &self.last_access_time)
+            .field("secure", &self.secure)
+            .field("http_only", &self.http_only)
+            .field("host_only", &self.host_only)
+            .field("same_site", &self.same_site)
+            .field("priority", &self.priority)
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SameSite {
     Unspecified,
@@ -0,0 +1,482 @@
+//! Cookie exchange with a browser driven over the Chrome DevTools Protocol.
+//!
+//! Chromium mapping: `protocol::Network::Cookie` and `CookieParam`, built
+//! by `NetworkHandler` in `content/browser/devtools/protocol/`
+//!
+//! Hybrid automation logs in with a headless browser, continues with
+//! plain requests, and hands the session back to the browser later. A
+//! [`CdpCookie`] has the JSON shape of the cookies `Network.getAllCookies`
+//! and `Storage.getCookies` return, and serializes to the `CookieParam`
+//! that `Network.setCookies` takes.
+//!
+//! [`CookieSync`] keeps both sides in step. It remembers the cookies as of
+//! the last sync, so on the next one it can tell which side changed each
+//! cookie since: a change on one side is copied to the other, deletions
+//! included. A cookie both sides changed differently is a conflict,
+//! settled by its [`ConflictPolicy`]. CDP reports no change times, so
+//! which side changed a cookie last cannot be told apart.
+//!
+//! ```rust,ignore
+//! use chromenet::cookies::cdp::{self, CookieSync};
+//!
+//! let mut sync = CookieSync::new();
+//! // Each time control passes between the browser and the jar
+//! let reply = cdp_call("Network.getAllCookies", json!({})).await?;
+//! let plan = sync.sync(&jar, &cdp::parse_cookies(&reply)?);
+//! cdp_call("Network.setCookies", json!({ "cookies": plan.set })).await?;
+//! for cookie in &plan.delete {
+//!     cdp_call("Network.deleteCookies", json!(cookie)).await?;
+//! }
+//! ```
+
+use crate::base::host::host_key;
+use crate::base::secret::REDACTED;
+use crate::cookies::canonicalcookie::{CanonicalCookie, CookiePriority, SameSite};
+use crate::cookies::monster::CookieMonster;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use time::OffsetDateTime;
+
+/// `expires` of a session cookie.
+const SESSION: f64 = -1.0;
+
+/// A cookie as the DevTools protocol represents it.
+///
+/// Unknown fields (`size`, `sourceScheme`, `partitionKey`, ...) are
+/// ignored when parsing. Serializing gives a `CookieParam`: `session`
+/// is left out, and so is `expires` for session cookies.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CdpCookie {
+    pub name: String,
+    pub value: String,
+    /// The host for host-only cookies, the domain with a leading dot
+    /// otherwise.
+    pub domain: String,
+    #[serde(default = "root_path")]
+    pub path: String,
+    /// Expiry in seconds since the Unix epoch, `-1` for session cookies.
+    #[serde(
+        default = "session_expires",
+        skip_serializing_if = "is_session_expires"
+    )]
+    pub expires: f64,
+    #[serde(default)]
+    pub http_only: bool,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default, skip_serializing)]
+    pub session: bool,
+    /// `Strict`, `Lax` or `None`; absent when unspecified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<String>,
+    /// `Low`, `Medium` or `High`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+}
+
+fn root_path() -> String {
+    "/".to_string()
+}
+
+fn session_expires() -> f64 {
+    SESSION
+}
+
+fn is_session_expires(expires: &f64) -> bool {
+    *expires <= 0.0
+}
+
+impl fmt::Debug for CdpCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CdpCookie")
+            .field("name", &self.name)
+            .field("value", &REDACTED)
+            .field("domain", &self.domain)
+            .field("path", &self.path)
+            .field("expires", &self.expires)
+            .field("http_only", &self.http_only)
+            .field("secure", &self.secure)
+            .field("same_site", &self.same_site)
+            .field("priority", &self.priority)
+            .finish()
+    }
+}
+
+impl CdpCookie {
+    /// Whether the cookie has no expiry.
+    pub fn is_session(&self) -> bool {
+        self.session || self.expires <= 0.0
+    }
+
+    /// Whether the cookie expired before `now`.
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        !self.is_session() && self.expires < now.unix_timestamp() as f64
+    }
+
+    /// The cookie as stored in a [`CookieMonster`], created at `now`.
+    pub fn to_canonical(&self, now: OffsetDateTime) -> CanonicalCookie {
+        let expiration_time = (!self.is_session())
+            .then(|| OffsetDateTime::from_unix_timestamp(self.expires as i64).ok())
+            .flatten();
+        CanonicalCookie {
+            name: self.name.clone(),
+            value: self.value.clone(),
+            domain: self.domain.trim_start_matches('.').to_string(),
+            path: self.path.clone(),
+            creation_time: now,
+            expiration_time,
+            last_access_time: now,
+            secure: self.secure,
+            http_only: self.http_only,
+            host_only: !self.domain.starts_with('.'),
+            same_site: match self.same_site.as_deref() {
+                Some(s) if s.eq_ignore_ascii_case("strict") => SameSite::Strict,
+                Some(s) if s.eq_ignore_ascii_case("lax") => SameSite::Lax,
+                Some(s) if s.eq_ignore_ascii_case("none") => SameSite::NoRestriction,
+                _ => SameSite::Unspecified,
+            },
+            priority: match self.priority.as_deref() {
+                Some("Low") => CookiePriority::Low,
+                Some("High") => CookiePriority::High,
+                _ => CookiePriority::Medium,
+            },
+        }
+    }
+
+    /// Name, domain and path, which identify a cookie on both sides.
+    fn key(&self) -> CookieKey {
+        CookieKey {
+            name: self.name.clone(),
+            domain: host_key(self.domain.trim_start_matches('.')),
+            path: self.path.clone(),
+        }
+    }
+
+    /// Whether `a` and `b` are the same cookie with the same contents.
+    fn same(a: Option<&Self>, b: Option<&Self>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                a.value == b.value
+                    && a.domain.starts_with('.') == b.domain.starts_with('.')
+                    && a.secure == b.secure
+                    && a.http_only == b.http_only
+                    && a.is_session() == b.is_session()
+                    && (a.is_session() || a.expires.floor() == b.expires.floor())
+                    && a.same_site_value() == b.same_site_value()
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn same_site_value(&self) -> SameSite {
+        self.to_canonical(OffsetDateTime::UNIX_EPOCH).same_site
+    }
+}
+
+impl From<&CanonicalCookie> for CdpCookie {
+    fn from(cookie: &CanonicalCookie) -> Self {
+        let domain = cookie.domain.trim_start_matches('.');
+        Self {
+            name: cookie.name.clone(),
+            value: cookie.value.clone(),
+            domain: if cookie.host_only {
+                domain.to_string()
+            } else {
+                format!(".{domain}")
+            },
+            path: cookie.path.clone(),
+            expires: cookie
+                .expiration_time
+                .map_or(SESSION, |t| t.unix_timestamp() as f64),
+            http_only: cookie.http_only,
+            secure: cookie.secure,
+            session: cookie.expiration_time.is_none(),
+            same_site: match cookie.same_site {
+                SameSite::Unspecified => None,
+                SameSite::NoRestriction => Some("None".to_string()),
+                SameSite::Lax => Some("Lax".to_string()),
+                SameSite::Strict => Some("Strict".to_string()),
+            },
+            priority: Some(
+                match cookie.priority {
+                    CookiePriority::Low => "Low",
+                    CookiePriority::Medium => "Medium",
+                    CookiePriority::High => "High",
+                }
+                .to_string(),
+            ),
+        }
+    }
+}
+
+/// Parameters of a `Network.deleteCookies` call removing one cookie.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CdpDeleteCookie {
+    pub name: String,
+    pub domain: String,
+    pub path: String,
+}
+
+/// Parse the cookies of a CDP reply: a bare array, or an object with a
+/// `cookies` array as `Network.getAllCookies` and `Storage.getCookies`
+/// return, possibly still wrapped in the message's `result`.
+pub fn parse_cookies(json: &str) -> Result<Vec<CdpCookie>, serde_json::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Reply {
+        Cookies(Vec<CdpCookie>),
+        Result { result: Box<Reply> },
+        Object { cookies: Vec<CdpCookie> },
+    }
+    let mut reply: Reply = serde_json::from_str(json)?;
+    loop {
+        reply = match reply {
+            Reply::Cookies(cookies) | Reply::Object { cookies } => return Ok(cookies),
+            Reply::Result { result } => *result,
+        };
+    }
+}
+
+/// Which side wins when the browser and the jar both changed a cookie
+/// since the last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Keep the browser's cookie, e.g. when the browser is where the user
+    /// logs in.
+    #[default]
+    PreferBrowser,
+    /// Keep the jar's cookie.
+    PreferJar,
+}
+
+/// Identity of a cookie on both sides.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CookieKey {
+    name: String,
+    /// Canonical host, without a leading dot.
+    domain: String,
+    path: String,
+}
+
+/// Changes for the browser from [`CookieSync::sync`], the jar being
+/// updated already.
+#[derive(Debug, Clone, Default)]
+pub struct SyncPlan {
+    /// Cookies to set with `Network.setCookies`.
+    pub set: Vec<CdpCookie>,
+    /// Cookies to remove with `Network.deleteCookies`.
+    pub delete: Vec<CdpDeleteCookie>,
+    /// Cookies copied into the jar.
+    pub imported: usize,
+    /// Cookies removed from the jar.
+    pub removed: usize,
+    /// Cookies both sides changed differently, settled by the policy.
+    pub conflicts: usize,
+}
+
+/// Two-way cookie sync between a [`CookieMonster`] and a browser.
+///
+/// Keep one per browser session: the first sync has nothing to compare
+/// with, so every cookie the two sides hold differently is a conflict.
+#[derive(Debug, Clone, Default)]
+pub struct CookieSync {
+    policy: ConflictPolicy,
+    /// The cookies both sides held after the last sync.
+    base: HashMap<CookieKey, CdpCookie>,
+    last_sync: Option<OffsetDateTime>,
+}
+
+impl CookieSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settle conflicts with `policy`, [`ConflictPolicy::PreferBrowser`]
+    /// by default.
+    pub fn with_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// When the last sync ran, by the jar's clock.
+    pub fn last_sync(&self) -> Option<OffsetDateTime> {
+        self.last_sync
+    }
+
+    /// Merge `browser`, the browser's current cookies, with `jar`.
+    ///
+    /// The jar is updated in place; the returned plan holds what the
+    /// browser needs, and the next sync assumes it was applied. Expired
+    /// cookies count as absent on both sides.
+    pub fn sync(&mut self, jar: &CookieMonster, browser: &[CdpCookie]) -> SyncPlan {
+        let now = jar.now();
+        let browser: HashMap<_, _> = browser
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .map(|cookie| (cookie.key(), cookie))
+            .collect();
+        let jar_cookies: Vec<CdpCookie> = jar
+            .iter_all_cookies()
+            .filter(|cookie| !cookie.is_expired(now))
+            .map(|cookie| CdpCookie::from(&cookie))
+            .collect();
+        let jar_cookies: HashMap<_, _> = jar_cookies
+            .iter()
+            .map(|cookie| (cookie.key(), cookie))
+            .collect();
+        let keys: HashSet<&CookieKey> = self
+            .base
+            .keys()
+            .chain(browser.keys())
+            .chain(jar_cookies.keys())
+            .collect();
+
+        let mut plan = SyncPlan::default();
+        let mut base = HashMap::new();
+        for key in keys {
+            let in_browser = browser.get(key).copied();
+            let in_jar = jar_cookies.get(key).copied();
+            let before = self.base.get(key);
+            let browser_changed = !CdpCookie::same(in_browser, before);
+            let jar_changed = !CdpCookie::same(in_jar, before);
+            let merged = match (browser_changed, jar_changed) {
+                (true, true) if !CdpCookie::same(in_browser, in_jar) => {
+                    plan.conflicts += 1;
+                    match self.policy {
+                        ConflictPolicy::PreferBrowser => in_browser,
+                        ConflictPolicy::PreferJar => in_jar,
+                    }
+                }
+                (true, _) => in_browser,
+                (false, _) => in_jar,
+            };
+
+            if !CdpCookie::same(in_jar, merged) {
+                match merged {
+                    Some(cookie) => {
+                        jar.set_canonical_cookie(cookie.to_canonical(now));
+                        plan.imported += 1;
+                    }
+                    None => {
+                        jar.delete_cookie(&key.name, &key.domain, &key.path);
+                        plan.removed += 1;
+                    }
+                }
+            }
+            if !CdpCookie::same(in_browser, merged) {
+                match (merged, in_browser) {
+                    (Some(cookie), _) => plan.set.push(cookie.clone()),
+                    (None, Some(cookie)) => plan.delete.push(CdpDeleteCookie {
+                        name: cookie.name.clone(),
+                        domain: cookie.domain.clone(),
+                        path: cookie.path.clone(),
+                    }),
+                    (None, None) => {}
+                }
+            }
+            if let Some(cookie) = merged {
+                base.insert(key.clone(), cookie.clone());
+            }
+        }
+        self.base = base;
+        self.last_sync = Some(now);
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::clock::MockClock;
+    use std::sync::Arc;
+    use url::Url;
+
+    const REPLY: &str = r#"{"id": 7, "result": {"cookies": [
+        {"name": "sid", "value": "abc", "domain": ".example.com", "path": "/",
+         "expires": 1900000000.5, "size": 6, "httpOnly": true, "secure": true,
+         "session": false, "sameSite": "Lax", "priority": "Medium",
+         "sourceScheme": "Secure", "sourcePort": 443},
+        {"name": "theme", "value": "dark", "domain": "www.example.com", "path": "/",
+         "expires": -1, "size": 9, "httpOnly": false, "secure": false,
+         "session": true, "priority": "Medium"}
+    ]}}"#;
+
+    fn jar() -> CookieMonster {
+        let start = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        CookieMonster::new().with_clock(Arc::new(MockClock::new(start.into())))
+    }
+
+    #[test]
+    fn test_parse_and_convert() {
+        let cookies = parse_cookies(REPLY).unwrap();
+        assert_eq!(cookies.len(), 2);
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+
+        let sid = cookies[0].to_canonical(now);
+        assert_eq!(sid.domain, "example.com");
+        assert!(!sid.host_only && sid.secure && sid.http_only);
+        assert_eq!(sid.same_site, SameSite::Lax);
+        assert_eq!(sid.expiration_time.unwrap().unix_timestamp(), 1_900_000_000);
+        let theme = cookies[1].to_canonical(now);
+        assert!(theme.host_only && theme.expiration_time.is_none());
+
+        // Back as a CookieParam, without read-only fields
+        let param = serde_json::to_value(CdpCookie::from(&theme)).unwrap();
+        assert_eq!(param["domain"], "www.example.com");
+        assert!(param.get("expires").is_none() && param.get("session").is_none());
+        assert!(CdpCookie::same(
+            Some(&CdpCookie::from(&sid)),
+            Some(&cookies[0])
+        ));
+    }
+
+    #[test]
+    fn test_sync_round_trip() {
+        let jar = jar();
+        let mut sync = CookieSync::new();
+        let browser = parse_cookies(REPLY).unwrap();
+
+        // The browser logged in: everything goes into the jar
+        let plan = sync.sync(&jar, &browser);
+        assert_eq!((plan.imported, plan.conflicts), (2, 0));
+        assert!(plan.set.is_empty() && plan.delete.is_empty());
+        assert_eq!(sync.last_sync().unwrap().unix_timestamp(), 1_700_000_000);
+
+        // Requests rotate the session and clear the theme
+        let url = Url::parse("https://www.example.com/").unwrap();
+        jar.parse_and_save_cookie(&url, "sid=def; Domain=example.com; Secure; HttpOnly");
+        jar.delete_cookie("theme", "www.example.com", "/");
+        let plan = sync.sync(&jar, &browser);
+        assert_eq!((plan.imported, plan.removed), (0, 0));
+        assert_eq!(plan.set.len(), 1);
+        assert_eq!(plan.set[0].value, "def");
+        assert_eq!(
+            plan.delete,
+            [CdpDeleteCookie {
+                name: "theme".into(),
+                domain: "www.example.com".into(),
+                path: "/".into(),
+            }]
+        );
+
+        // Nothing changed since
+        let mut browser = vec![plan.set[0].clone()];
+        let plan = sync.sync(&jar, &browser);
+        assert!(plan.set.is_empty() && plan.delete.is_empty() && plan.imported == 0);
+
+        // Both sides change the session: the policy decides
+        browser[0].value = "from-browser".into();
+        jar.parse_and_save_cookie(&url, "sid=from-jar; Domain=example.com; Secure; HttpOnly");
+        let mut prefer_jar = sync.clone().with_policy(ConflictPolicy::PreferJar);
+        let plan = prefer_jar.sync(&jar, &browser);
+        assert_eq!(plan.conflicts, 1);
+        assert_eq!(plan.set[0].value, "from-jar");
+
+        let plan = sync.sync(&jar, &browser);
+        assert_eq!((plan.conflicts, plan.imported), (1, 1));
+        assert_eq!(jar.get_cookies_for_url(&url)[0].value, "from-browser");
+    }
+}
@@ -180,6 +180,12 @@ pub mod keyring {
 
     /// macOS Keychain service for Opera
     pub const MACOS_OPERA_SERVICE: &str = "Opera Safe Storage";
+
+    /// macOS Keychain service for Vivaldi
+    pub const MACOS_VIVALDI_SERVICE: &str = "Vivaldi Safe Storage";
+
+    /// macOS Keychain service for Arc
+    pub const MACOS_ARC_SERVICE: &str = "Arc Safe Storage";
 }
 
 #[cfg(test)]
@@ -1,42 +1,120 @@
 //! Linux keyring access for Chrome v11 cookie decryption.
 //!
-//! Uses the Secret Service API (GNOME Keyring / KWallet) to retrieve
-//! Chrome's encryption password, then derives the AES key.
+//! Chrome keeps its encryption password in one of several stores, picked
+//! from the desktop environment or the `--password-store` flag:
+//! - **GNOME Keyring / libsecret**, via the Secret Service API
+//! - **KWallet**, via its own DBus interface
+//! - **basic**, no keyring: the hardcoded password "peanuts"
+//!
+//! [`v11_key_candidates`] collects the keys of every store, most likely
+//! first, so the caller can find the one that decrypts the profile.
 //!
 //! ## Chrome's Keyring Schema
 //! - Schema name: `chrome_libsecret_os_crypt_password_v2`
 //! - Attribute: `("application", "chrome")` (or browser variant)
 //! - Label: "Chrome Safe Storage" or "Chromium Safe Storage"
+//!
+//! ## KWallet
+//! - Folder: "Chrome Keys" (or "Chromium Keys", "Brave Keys")
+//! - Entry: "Chrome Safe Storage" (or browser variant)
+//!
+//! The names come from the browser's [`KeyringIdentity`].
 
+use super::KeyringIdentity;
 use crate::base::neterror::NetError;
 use std::collections::HashMap;
 
+/// Where Chrome stored the v11 encryption password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PasswordStore {
+    /// GNOME Keyring or another Secret Service provider.
+    Libsecret,
+    /// KDE Wallet.
+    KWallet,
+    /// No keyring: the hardcoded "peanuts" password, or an empty one.
+    Basic,
+}
+
+impl PasswordStore {
+    /// Name as accepted by Chrome's `--password-store` flag.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasswordStore::Libsecret => "gnome-libsecret",
+            PasswordStore::KWallet => "kwallet",
+            PasswordStore::Basic => "basic",
+        }
+    }
+}
+
+/// Keyrings to ask, most likely first for the desktop in `xdg_current_desktop`.
+///
+/// Mirrors Chrome's `GetDesktopEnvironment`: KDE sessions use KWallet, every
+/// other desktop uses libsecret.
+pub fn keyring_order(xdg_current_desktop: Option<&str>) -> [PasswordStore; 2] {
+    let is_kde = xdg_current_desktop
+        .is_some_and(|desktop| desktop.split(':').any(|d| d.eq_ignore_ascii_case("kde")));
+    if is_kde {
+        [PasswordStore::KWallet, PasswordStore::Libsecret]
+    } else {
+        [PasswordStore::Libsecret, PasswordStore::KWallet]
+    }
+}
+
+/// Every key the v11 cookies of the browser named by `identity` may be
+/// encrypted with, most likely first.
+///
+/// Keyrings that are unavailable or hold no password are skipped. The basic
+/// store's keys come last: "peanuts", then the empty password Chrome used
+/// when the keyring returned nothing (<https://crbug.com/40055416>).
+#[cfg(target_os = "linux")]
+pub fn v11_key_candidates(identity: &KeyringIdentity) -> Vec<(PasswordStore, [u8; 16])> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").ok();
+    let mut candidates = Vec::new();
+    for store in keyring_order(desktop.as_deref()) {
+        let key = match store {
+            PasswordStore::Libsecret => get_v11_key(identity),
+            _ => get_kwallet_key(identity),
+        };
+        match key {
+            Ok(Some(key)) => candidates.push((store, key)),
+            Ok(None) => tracing::debug!(store = store.as_str(), "no v11 password in keyring"),
+            Err(e) => tracing::debug!(store = store.as_str(), error = %e, "keyring unavailable"),
+        }
+    }
+    candidates.push((PasswordStore::Basic, super::derive_key(b"peanuts", 1)));
+    candidates.push((PasswordStore::Basic, super::derive_key(b"", 1)));
+    candidates
+}
+
 /// Get the v11 encryption key from GNOME Keyring/Secret Service.
 ///
 /// # Arguments
-/// * `application` - The application name (e.g., "chrome", "chromium", "brave")
+/// * `identity` - The browser's `application` attribute and schema
 ///
 /// # Returns
 /// * `Ok(Some(key))` - Successfully retrieved and derived the key
 /// * `Ok(None)` - Keyring is available but no key found for this application
 /// * `Err(...)` - Keyring is unavailable or access was denied
 #[cfg(target_os = "linux")]
-pub fn get_v11_key(application: &str) -> Result<Option<[u8; 16]>, NetError> {
+pub fn get_v11_key(identity: &KeyringIdentity) -> Result<Option<[u8; 16]>, NetError> {
     // Use the blocking API for simplicity (no async runtime needed)
     use secret_service::blocking::SecretService;
     use secret_service::EncryptionType;
 
     // Connect to Secret Service
     let ss = SecretService::connect(EncryptionType::Dh)
-        .map_err(|_| NetError::CookieKeyringUnavailable)?;
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
 
     // Search for Chrome's password using the application attribute
     let mut attributes = HashMap::new();
-    attributes.insert("application", application);
+    attributes.insert("application", identity.application.as_str());
+    if let Some(schema) = &identity.schema {
+        attributes.insert("xdg:schema", schema.as_str());
+    }
 
     let search_result = ss
         .search_items(attributes)
-        .map_err(|_| NetError::CookieKeyringUnavailable)?;
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
 
     // Check unlocked items first, then locked
     let item = search_result
@@ -51,13 +129,13 @@ pub fn get_v11_key(application: &str) -> Result<Option<[u8; 16]>, NetError> {
     // Unlock if needed
     if search_result.unlocked.is_empty() {
         item.unlock()
-            .map_err(|_| NetError::CookieKeyringUnavailable)?;
+            .map_err(|_| NetError::cookie_keyring_unavailable())?;
     }
 
     // Get the secret (password)
     let mut secret = item
         .get_secret()
-        .map_err(|_| NetError::CookieKeyringUnavailable)?;
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
 
     // Derive the AES key using PBKDF2 (1 iteration for Linux)
     let key = super::derive_key(&secret, 1);
@@ -69,16 +147,133 @@ pub fn get_v11_key(application: &str) -> Result<Option<[u8; 16]>, NetError> {
     Ok(Some(key))
 }
 
-/// Get the application name for keyring lookup based on browser type.
-pub fn browser_to_application(browser: &str) -> &'static str {
-    match browser.to_lowercase().as_str() {
-        "chrome" | "google-chrome" => "chrome",
-        "chromium" => "chromium",
-        "edge" | "microsoft-edge" => "chromium", // Edge uses chromium keyring
-        "brave" | "brave-browser" => "brave",
-        "opera" => "chromium", // Opera uses chromium keyring
-        _ => "chrome",
+/// Attributes of the Secret Service item holding storage key `name`.
+fn storage_key_attributes(name: &str) -> HashMap<&str, &str> {
+    HashMap::from([
+        ("application", super::STORAGE_KEY_APPLICATION),
+        ("name", name),
+    ])
+}
+
+/// Get a storage key saved by [`set_storage_key`] from the Secret Service.
+///
+/// Returns `Ok(None)` if there is none, or if the stored secret is not a
+/// 32-byte key.
+#[cfg(target_os = "linux")]
+pub fn get_storage_key(name: &str) -> Result<Option<[u8; 32]>, NetError> {
+    use secret_service::blocking::SecretService;
+    use secret_service::EncryptionType;
+    use zeroize::Zeroize;
+
+    let ss = SecretService::connect(EncryptionType::Dh)
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    let search_result = ss
+        .search_items(storage_key_attributes(name))
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+
+    let item = if let Some(item) = search_result.unlocked.first() {
+        item
+    } else if let Some(item) = search_result.locked.first() {
+        item.unlock()
+            .map_err(|_| NetError::cookie_keyring_unavailable())?;
+        item
+    } else {
+        return Ok(None);
+    };
+
+    let mut secret = item
+        .get_secret()
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    let key = <[u8; 32]>::try_from(secret.as_slice()).ok();
+    secret.zeroize();
+    Ok(key)
+}
+
+/// Save a storage key in the default Secret Service collection, the login
+/// keyring on most desktops.
+#[cfg(target_os = "linux")]
+pub fn set_storage_key(name: &str, key: &[u8; 32]) -> Result<(), NetError> {
+    use secret_service::blocking::SecretService;
+    use secret_service::EncryptionType;
+
+    let ss = SecretService::connect(EncryptionType::Dh)
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    let collection = ss
+        .get_default_collection()
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    collection
+        .ensure_unlocked()
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    collection
+        .create_item(
+            &format!("chromenet cookie key ({name})"),
+            storage_key_attributes(name),
+            key,
+            true,
+            "application/octet-stream",
+        )
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    Ok(())
+}
+
+/// KWallet services, newest first.
+const KWALLET_SERVICES: [(&str, &str); 3] = [
+    ("org.kde.kwalletd6", "/modules/kwalletd6"),
+    ("org.kde.kwalletd5", "/modules/kwalletd5"),
+    ("org.kde.kwalletd", "/modules/kwalletd"),
+];
+
+/// App id Chrome identifies itself to KWallet with.
+const KWALLET_APP_ID: &str = "chrome";
+
+/// Get the v11 encryption key from KWallet.
+///
+/// Returns the same as [`get_v11_key`]: `Ok(None)` if the wallet has no
+/// password under `identity`'s KWallet name, an error if no KWallet daemon
+/// answers or the wallet could not be opened.
+#[cfg(target_os = "linux")]
+pub fn get_kwallet_key(identity: &KeyringIdentity) -> Result<Option<[u8; 16]>, NetError> {
+    use zbus::blocking::{Connection, Proxy};
+    use zeroize::Zeroize;
+
+    let conn = Connection::session().map_err(|_| NetError::cookie_keyring_unavailable())?;
+    let proxy = KWALLET_SERVICES
+        .iter()
+        .filter_map(|(service, path)| Proxy::new(&conn, *service, *path, "org.kde.KWallet").ok())
+        .find(|proxy| proxy.call::<_, _, bool>("isEnabled", &()).unwrap_or(false))
+        .ok_or_else(NetError::cookie_keyring_unavailable)?;
+
+    let wallet: String = proxy
+        .call("networkWallet", &())
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    let handle: i32 = proxy
+        .call("open", &(wallet.as_str(), 0i64, KWALLET_APP_ID))
+        .map_err(|_| NetError::cookie_keyring_unavailable())?;
+    if handle < 0 {
+        return Err(NetError::cookie_keyring_unavailable());
     }
+
+    let name = &identity.kwallet_name;
+    let folder = format!("{} Keys", name);
+    let entry = format!("{} Safe Storage", name);
+    let password: Result<String, _> = proxy.call(
+        "readPassword",
+        &(handle, folder.as_str(), entry.as_str(), KWALLET_APP_ID),
+    );
+    let _ = proxy.call::<_, _, i32>("close", &(handle, false, KWALLET_APP_ID));
+
+    let mut password = password.map_err(|_| NetError::cookie_keyring_unavailable())?;
+    if password.is_empty() {
+        return Ok(None);
+    }
+    let key = super::derive_key(password.as_bytes(), 1);
+    password.zeroize();
+    Ok(Some(key))
+}
+
+/// Get the application name for keyring lookup based on browser type.
+pub fn browser_to_application(browser: &str) -> String {
+    KeyringIdentity::for_browser(browser).application
 }
 
 #[cfg(test)]
@@ -92,4 +287,39 @@ mod tests {
         assert_eq!(browser_to_application("brave"), "brave");
         assert_eq!(browser_to_application("edge"), "chromium");
     }
+
+    #[test]
+    fn test_keyring_order() {
+        use PasswordStore::*;
+        assert_eq!(keyring_order(Some("KDE")), [KWallet, Libsecret]);
+        assert_eq!(keyring_order(Some("ubuntu:KDE")), [KWallet, Libsecret]);
+        assert_eq!(keyring_order(Some("ubuntu:GNOME")), [Libsecret, KWallet]);
+        assert_eq!(keyring_order(None), [Libsecret, KWallet]);
+    }
+
+    #[test]
+    fn test_kwallet_name() {
+        let name = |browser| KeyringIdentity::for_browser(browser).kwallet_name;
+        assert_eq!(name("chrome"), "Chrome");
+        assert_eq!(name("edge"), "Chromium");
+        assert_eq!(name("brave"), "Brave");
+        assert_eq!(name("vivaldi"), "Chrome");
+    }
+
+    #[test]
+    fn test_key_source() {
+        let identity = KeyringIdentity::for_browser("brave");
+        assert_eq!(
+            identity.key_source(PasswordStore::KWallet).to_string(),
+            "kwallet (Brave Keys/Brave Safe Storage)"
+        );
+        assert_eq!(
+            identity.key_source(PasswordStore::Libsecret).to_string(),
+            "gnome-libsecret (application=brave)"
+        );
+        assert_eq!(
+            identity.key_source(PasswordStore::Basic).to_string(),
+            "basic"
+        );
+    }
 }
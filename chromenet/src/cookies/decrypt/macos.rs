@@ -7,51 +7,37 @@
 //! - Service: "Chrome Safe Storage" (or browser variant)
 //! - Account: "Chrome" (or browser variant)
 //! - Key derivation: PBKDF2-HMAC-SHA1 with 1003 iterations
+//!
+//! The service and account come from the browser's [`KeyringIdentity`].
 
+use super::KeyringIdentity;
 use crate::base::neterror::NetError;
 
 /// Keychain service names for each browser.
-pub fn browser_keychain_service(browser: &str) -> &'static str {
-    match browser.to_lowercase().as_str() {
-        "chrome" | "google-chrome" => "Chrome Safe Storage",
-        "chromium" => "Chromium Safe Storage",
-        "edge" | "microsoft-edge" => "Microsoft Edge Safe Storage",
-        "brave" | "brave-browser" => "Brave Safe Storage",
-        "opera" => "Opera Safe Storage",
-        _ => "Chrome Safe Storage",
-    }
+pub fn browser_keychain_service(browser: &str) -> String {
+    KeyringIdentity::for_browser(browser).keychain_service
 }
 
 /// Keychain account names for each browser.
-pub fn browser_keychain_account(browser: &str) -> &'static str {
-    match browser.to_lowercase().as_str() {
-        "chrome" | "google-chrome" => "Chrome",
-        "chromium" => "Chromium",
-        "edge" | "microsoft-edge" => "Microsoft Edge",
-        "brave" | "brave-browser" => "Brave",
-        "opera" => "Opera",
-        _ => "Chrome",
-    }
+pub fn browser_keychain_account(browser: &str) -> String {
+    KeyringIdentity::for_browser(browser).keychain_account
 }
 
 /// Get the encryption key from macOS Keychain.
 ///
 /// # Arguments
-/// * `application` - The application/browser name
+/// * `identity` - The browser's Keychain service and account
 ///
 /// # Returns
 /// * `Ok(Some(key))` - Successfully retrieved and derived the key
 /// * `Ok(None)` - Keychain entry not found
 /// * `Err(...)` - Keychain access denied
 #[cfg(target_os = "macos")]
-pub fn get_keychain_key(application: &str) -> Result<Option<[u8; 16]>, NetError> {
+pub fn get_keychain_key(identity: &KeyringIdentity) -> Result<Option<[u8; 16]>, NetError> {
     use security_framework::passwords::get_generic_password;
 
-    let service = browser_keychain_service(application);
-    let account = browser_keychain_account(application);
-
     // Try to get the password from Keychain
-    match get_generic_password(service, account) {
+    match get_generic_password(&identity.keychain_service, &identity.keychain_account) {
         Ok(password) => {
             // Derive key with 1003 iterations (macOS uses more iterations than Linux)
             let key = super::derive_key(&password, 1003);
@@ -61,10 +47,41 @@ pub fn get_keychain_key(application: &str) -> Result<Option<[u8; 16]>, NetError>
             // errSecItemNotFound - no password stored
             Ok(None)
         }
-        Err(_) => Err(NetError::CookieKeyringUnavailable),
+        Err(_) => Err(NetError::cookie_keyring_unavailable()),
+    }
+}
+
+/// Get a storage key saved by [`set_storage_key`] from the Keychain.
+///
+/// Returns `Ok(None)` if there is none, or if the stored password is not a
+/// 32-byte key.
+#[cfg(target_os = "macos")]
+pub fn get_storage_key(name: &str) -> Result<Option<[u8; 32]>, NetError> {
+    use security_framework::passwords::get_generic_password;
+    use zeroize::Zeroize;
+
+    match get_generic_password(name, super::STORAGE_KEY_APPLICATION) {
+        Ok(mut password) => {
+            let key = <[u8; 32]>::try_from(password.as_slice()).ok();
+            password.zeroize();
+            Ok(key)
+        }
+        // errSecItemNotFound
+        Err(e) if e.code() == -25300 => Ok(None),
+        Err(_) => Err(NetError::cookie_keyring_unavailable()),
     }
 }
 
+/// Save a storage key as a generic password: service `name`, account
+/// "chromenet".
+#[cfg(target_os = "macos")]
+pub fn set_storage_key(name: &str, key: &[u8; 32]) -> Result<(), NetError> {
+    use security_framework::passwords::set_generic_password;
+
+    set_generic_password(name, super::STORAGE_KEY_APPLICATION, key)
+        .map_err(|_| NetError::cookie_keyring_unavailable())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
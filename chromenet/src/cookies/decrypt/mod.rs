@@ -4,7 +4,8 @@
 //! which uses system keyrings/credential managers to store the encryption key.
 //!
 //! ## Platform Support
-//! - **Linux**: libsecret/GNOME Keyring via `secret-service` crate
+//! - **Linux**: libsecret/GNOME Keyring via `secret-service` crate, KWallet
+//!   over DBus, and the keyring-less "basic" store
 //! - **macOS**: Keychain via `security-framework` crate
 //! - **Windows**: DPAPI via `windows` crate
 
@@ -18,6 +19,132 @@ pub mod macos;
 pub mod windows;
 
 use crate::base::neterror::NetError;
+use crate::cookies::browser::Browser;
+use std::fmt;
+
+/// How a browser names itself to the keyring holding its cookie password.
+///
+/// Chrome, Chromium, Brave and the other forks each keep their password
+/// under their own names, and some share Chromium's on Linux.
+/// [`Browser::keyring_identity`] gives the names each browser uses; change
+/// them for a fork or a custom build that stores its password elsewhere.
+///
+/// Reference: `components/os_crypt/sync/key_storage_libsecret.cc`,
+/// `key_storage_kwallet.cc` and `keychain_password_mac.mm`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyringIdentity {
+    /// `application` attribute of the Secret Service item (Linux).
+    pub application: String,
+    /// `xdg:schema` the Secret Service item must have (Linux), `None` for
+    /// any, e.g. [`keyring::CHROME_SCHEMA`](crate::cookies::chromedb::keyring::CHROME_SCHEMA).
+    pub schema: Option<String>,
+    /// KWallet name (Linux): the password is entry `"<name> Safe Storage"`
+    /// in folder `"<name> Keys"`.
+    pub kwallet_name: String,
+    /// Keychain service of the generic password (macOS).
+    pub keychain_service: String,
+    /// Keychain account of the generic password (macOS).
+    pub keychain_account: String,
+}
+
+impl KeyringIdentity {
+    /// The same name everywhere, the way most browsers do it: for "Brave",
+    /// application `brave`, KWallet `Brave`, Keychain service
+    /// `Brave Safe Storage` and account `Brave`.
+    pub fn named(name: &str) -> Self {
+        Self {
+            application: name.to_lowercase(),
+            schema: None,
+            kwallet_name: name.to_string(),
+            keychain_service: format!("{name} Safe Storage"),
+            keychain_account: name.to_string(),
+        }
+    }
+
+    /// Identity of the browser called `browser`, e.g. "chrome",
+    /// "google-chrome" or "brave", as [`CookieDecryptor`] names it; Chrome's
+    /// for unknown names.
+    ///
+    /// [`CookieDecryptor`]: crate::cookies::oscrypt::CookieDecryptor
+    pub fn for_browser(browser: &str) -> Self {
+        let browser = match browser.to_lowercase().as_str() {
+            "chromium" => Browser::Chromium,
+            "edge" | "microsoft-edge" => Browser::Edge,
+            "brave" | "brave-browser" => Browser::Brave,
+            "opera" => Browser::Opera,
+            "operagx" | "opera-gx" => Browser::OperaGx,
+            "vivaldi" => Browser::Vivaldi,
+            "arc" => Browser::Arc,
+            _ => Browser::Chrome,
+        };
+        browser.keyring_identity()
+    }
+
+    /// Look the Secret Service item up by `application` instead.
+    pub fn with_application(mut self, application: impl Into<String>) -> Self {
+        self.application = application.into();
+        self
+    }
+
+    /// Only accept Secret Service items of `schema`.
+    pub fn with_schema(mut self, schema: impl Into<String>) -> Self {
+        self.schema = Some(schema.into());
+        self
+    }
+
+    /// Read the KWallet entry of `name` instead.
+    pub fn with_kwallet_name(mut self, name: impl Into<String>) -> Self {
+        self.kwallet_name = name.into();
+        self
+    }
+
+    /// Read the Keychain generic password of `service` and `account`
+    /// instead.
+    pub fn with_keychain(mut self, service: impl Into<String>, account: impl Into<String>) -> Self {
+        self.keychain_service = service.into();
+        self.keychain_account = account.into();
+        self
+    }
+
+    /// Where a key from `store` was found under this identity.
+    #[cfg(target_os = "linux")]
+    pub fn key_source(&self, store: linux::PasswordStore) -> KeySource {
+        match store {
+            linux::PasswordStore::Libsecret => KeySource::Libsecret {
+                application: self.application.clone(),
+            },
+            linux::PasswordStore::KWallet => KeySource::KWallet {
+                folder: format!("{} Keys", self.kwallet_name),
+                entry: format!("{} Safe Storage", self.kwallet_name),
+            },
+            linux::PasswordStore::Basic => KeySource::Basic,
+        }
+    }
+}
+
+/// Where the key that decrypted a browser's cookies came from, for
+/// diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// Secret Service item with this `application` attribute.
+    Libsecret { application: String },
+    /// KWallet entry in `folder`.
+    KWallet { folder: String, entry: String },
+    /// No keyring: Chrome's hardcoded password, or the empty one.
+    Basic,
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Libsecret { application } => {
+                write!(f, "gnome-libsecret (application={application})")
+            }
+            KeySource::KWallet { folder, entry } => write!(f, "kwallet ({folder}/{entry})"),
+            KeySource::Basic => f.write_str("basic"),
+        }
+    }
+}
 
 /// Derive a 16-byte AES key from a password using PBKDF2-HMAC-SHA1.
 ///
@@ -44,17 +171,18 @@ pub fn derive_key(password: &[u8], iterations: u32) -> [u8; 16] {
 /// Get the Chrome encryption key from the system keyring.
 ///
 /// This is a convenience function that calls the appropriate platform-specific
-/// implementation based on the current OS.
+/// implementation based on the current OS, asking the keyring for the
+/// entry of `identity`.
 #[allow(unused_variables)]
-pub fn get_chrome_key(application: &str) -> Result<Option<[u8; 16]>, NetError> {
+pub fn get_chrome_key(identity: &KeyringIdentity) -> Result<Option<[u8; 16]>, NetError> {
     #[cfg(target_os = "linux")]
     {
-        linux::get_v11_key(application)
+        linux::get_v11_key(identity)
     }
 
     #[cfg(target_os = "macos")]
     {
-        macos::get_keychain_key(application)
+        macos::get_keychain_key(identity)
     }
 
     #[cfg(target_os = "windows")]
@@ -65,8 +193,63 @@ pub fn get_chrome_key(application: &str) -> Result<Option<[u8; 16]>, NetError> {
 
     #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
     {
-        Err(CookieExtractionError::PlatformNotSupported(
-            "Keyring access not supported on this platform".into(),
+        Err(NetError::cookie_platform_not_supported(
+            "Keyring access not supported on this platform",
+        ))
+    }
+}
+
+/// Application attribute (Linux) or account (macOS) of the keyring entries
+/// chromenet creates for its own keys.
+pub const STORAGE_KEY_APPLICATION: &str = "chromenet";
+
+/// Get the key stored under `name` by [`set_storage_key`].
+///
+/// Used to encrypt saved cookies at rest, see
+/// [`persistence`](crate::cookies::persistence).
+///
+/// # Returns
+/// * `Ok(Some(key))` - The stored key
+/// * `Ok(None)` - The keyring has no key under `name`
+/// * `Err(...)` - The keyring is unavailable, or not supported on this platform
+#[allow(unused_variables)]
+pub fn get_storage_key(name: &str) -> Result<Option<[u8; 32]>, NetError> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::get_storage_key(name)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        macos::get_storage_key(name)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        Err(NetError::cookie_platform_not_supported(
+            "Storing keys in the keyring is not supported on this platform",
+        ))
+    }
+}
+
+/// Store `key` in the system keyring under `name`, replacing any key
+/// stored there before.
+#[allow(unused_variables)]
+pub fn set_storage_key(name: &str, key: &[u8; 32]) -> Result<(), NetError> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::set_storage_key(name, key)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        macos::set_storage_key(name, key)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        Err(NetError::cookie_platform_not_supported(
+            "Storing keys in the keyring is not supported on this platform",
         ))
     }
 }
@@ -75,6 +258,33 @@ pub fn get_chrome_key(application: &str) -> Result<Option<[u8; 16]>, NetError> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_keyring_identity_for_browser() {
+        let chrome = KeyringIdentity::for_browser("google-chrome");
+        assert_eq!(chrome, KeyringIdentity::named("Chrome"));
+        assert_eq!(chrome.keychain_service, "Chrome Safe Storage");
+        assert_eq!(KeyringIdentity::for_browser("unknown"), chrome);
+
+        let brave = KeyringIdentity::for_browser("Brave");
+        assert_eq!(brave.application, "brave");
+        assert_eq!(brave.kwallet_name, "Brave");
+
+        // Edge keeps its password in Chromium's entries on Linux only
+        let edge = KeyringIdentity::for_browser("edge");
+        assert_eq!(edge.application, "chromium");
+        assert_eq!(edge.kwallet_name, "Chromium");
+        assert_eq!(edge.keychain_service, "Microsoft Edge Safe Storage");
+        assert_eq!(edge.keychain_account, "Microsoft Edge");
+
+        let custom =
+            KeyringIdentity::named("Thorium").with_schema("chrome_libsecret_os_crypt_password_v2");
+        assert_eq!(custom.application, "thorium");
+        assert_eq!(
+            custom.schema.as_deref(),
+            Some("chrome_libsecret_os_crypt_password_v2")
+        );
+    }
+
     #[test]
     fn test_derive_key_v10() {
         // Test v10 key derivation (1 iteration) - same as Linux
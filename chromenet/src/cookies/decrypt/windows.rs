@@ -67,35 +67,29 @@ pub fn decrypt_v10_windows(encrypted: &[u8], key: &[u8; 32]) -> Result<String, N
     const NONCE_LEN: usize = 12;
 
     if !encrypted.starts_with(V10_PREFIX) {
-        return Err(NetError::CookieDecryptionFailed {
-            browser: "chrome".into(),
-            reason: "Not a v10 encrypted value".into(),
-        });
+        return Err(NetError::cookie_decryption_failed(
+            "chrome",
+            "Not a v10 encrypted value",
+        ));
     }
 
     let data = &encrypted[V10_PREFIX.len()..];
     if data.len() < NONCE_LEN {
-        return Err(NetError::CookieDecryptionFailed {
-            browser: "chrome".into(),
-            reason: "Data too short".into(),
-        });
+        return Err(NetError::cookie_decryption_failed(
+            "chrome",
+            "Data too short",
+        ));
     }
 
     let nonce = Nonce::from_slice(&data[..NONCE_LEN]);
     let ciphertext = &data[NONCE_LEN..];
 
-    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| NetError::CookieDecryptionFailed {
-        browser: "chrome".into(),
-        reason: "Invalid key".into(),
-    })?;
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|_| NetError::cookie_decryption_failed("chrome", "Invalid key"))?;
 
-    let plaintext =
-        cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|_| NetError::CookieDecryptionFailed {
-                browser: "chrome".into(),
-                reason: "AES-GCM decryption failed".into(),
-            })?;
+    let plaintext = cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| NetError::cookie_decryption_failed("chrome", "AES-GCM decryption failed"))?;
 
     String::from_utf8(plaintext)
         .map_err(|_| NetError::cookie_invalid_data("Invalid UTF-8 in decrypted value"))
@@ -137,18 +131,14 @@ pub fn get_dpapi_key(local_state_path: &Path) -> Result<[u8; 32], NetError> {
     let mut blob_out = CRYPT_INTEGER_BLOB::default();
 
     unsafe {
-        CryptUnprotectData(&mut blob_in, None, None, None, None, 0, &mut blob_out).map_err(
-            |_| NetError::CookieDecryptionFailed {
-                browser: "chrome".into(),
-                reason: "DPAPI decryption failed".into(),
-            },
-        )?;
+        CryptUnprotectData(&mut blob_in, None, None, None, None, 0, &mut blob_out)
+            .map_err(|_| NetError::cookie_decryption_failed("chrome", "DPAPI decryption failed"))?;
 
         if blob_out.cbData != 32 {
-            return Err(NetError::CookieDecryptionFailed {
-                browser: "chrome".into(),
-                reason: "Unexpected key length from DPAPI".into(),
-            });
+            return Err(NetError::cookie_decryption_failed(
+                "chrome",
+                "Unexpected key length from DPAPI",
+            ));
         }
 
         let mut key = [0u8; 32];
@@ -1,33 +1,125 @@
 //! Cookie extraction error types.
 //!
-//! # Deprecated
+//! Reading cookies out of a browser fails in ways specific to browsers:
+//! missing profiles, locked databases, unavailable keyrings. Those failures
+//! are [`CookieExtractionError`]s. Public APIs return
+//! [`NetError`] like the rest of the crate, carrying the cookie error in
+//! [`NetError::CookieExtraction`], and the two convert into each other
+//! without losing context:
 //!
-//! This module is deprecated. Use [`crate::base::neterror::NetError`] instead.
-//! Cookie extraction errors have been unified into `NetError`.
+//! ```
+//! use chromenet::base::neterror::NetError;
+//! use chromenet::cookies::error::CookieExtractionError;
+//!
+//! let error = NetError::from(CookieExtractionError::ProfileNotFound("Work".into()));
+//! assert_eq!(error.as_i32(), -10026);
+//! assert!(matches!(
+//!     CookieExtractionError::from(error),
+//!     CookieExtractionError::ProfileNotFound(profile) if profile == "Work"
+//! ));
+//! ```
+//!
+//! The flat `NetError::Cookie*` variants predate this type and are
+//! deprecated; converting one yields the matching structured error.
 
 use crate::base::neterror::NetError;
+use thiserror::Error;
 
-/// Cookie extraction error type.
+/// Why reading cookies from a browser failed.
 ///
-/// # Deprecated
+/// Each kind keeps the error code of the `NetError` variant it replaces,
+/// in `-10020..=-10029`:
 ///
-/// This type is deprecated. Use [`NetError`] instead.
-/// The following mappings apply:
-///
-/// | Old | New |
-/// |-----|-----|
-/// | `CookieExtractionError::BrowserNotFound` | `NetError::BrowserNotFound` |
-/// | `CookieExtractionError::DatabaseNotFound` | `NetError::CookieDbNotFound` |
-/// | `CookieExtractionError::DecryptionFailed` | `NetError::CookieDecryptionFailed` |
-/// | `CookieExtractionError::DatabaseLocked` | `NetError::CookieDatabaseLocked` |
-/// | `CookieExtractionError::UnsupportedVersion` | `NetError::CookieUnsupportedVersion` |
-/// | `CookieExtractionError::PlatformNotSupported` | `NetError::CookiePlatformNotSupported` |
-/// | `CookieExtractionError::ProfileNotFound` | `NetError::CookieProfileNotFound` |
-/// | `CookieExtractionError::KeyringUnavailable` | `NetError::CookieKeyringUnavailable` |
-/// | `CookieExtractionError::InvalidData` | `NetError::CookieInvalidData` |
-/// | `CookieExtractionError::Database` | `NetError::CookieDatabaseError` |
-#[deprecated(since = "0.2.0", note = "Use crate::base::neterror::NetError instead")]
-pub type CookieExtractionError = NetError;
+/// | Kind | Code | Deprecated `NetError` variant |
+/// |------|------|-------------------------------|
+/// | `BrowserNotFound` | -10020 | `NetError::BrowserNotFound` |
+/// | `DatabaseNotFound` | -10021 | `NetError::CookieDbNotFound` |
+/// | `DecryptionFailed` | -10022 | `NetError::CookieDecryptionFailed` |
+/// | `DatabaseLocked` | -10023 | `NetError::CookieDatabaseLocked` |
+/// | `UnsupportedVersion` | -10024 | `NetError::CookieUnsupportedVersion` |
+/// | `PlatformNotSupported` | -10025 | `NetError::CookiePlatformNotSupported` |
+/// | `ProfileNotFound` | -10026 | `NetError::CookieProfileNotFound` |
+/// | `KeyringUnavailable` | -10027 | `NetError::CookieKeyringUnavailable` |
+/// | `InvalidData` | -10028 | `NetError::CookieInvalidData` |
+/// | `Database` | -10029 | `NetError::CookieDatabaseError` |
+#[derive(Debug, Error, Clone)]
+pub enum CookieExtractionError {
+    #[error("Browser {0} not found")]
+    BrowserNotFound(String),
+    #[error("Cookie database not found: {0}")]
+    DatabaseNotFound(String),
+    #[error("Failed to decrypt {browser} cookies: {reason}")]
+    DecryptionFailed { browser: String, reason: String },
+    #[error("Cookie database locked (close browser)")]
+    DatabaseLocked,
+    #[error("Browser version not supported: {0}")]
+    UnsupportedVersion(String),
+    #[error("Platform not supported: {0}")]
+    PlatformNotSupported(String),
+    #[error("Profile not found: {0}")]
+    ProfileNotFound(String),
+    #[error("Keyring unavailable")]
+    KeyringUnavailable,
+    #[error("Invalid cookie data: {0}")]
+    InvalidData(String),
+    #[error("Cookie database error: {0}")]
+    Database(String),
+    /// A failure that is not specific to cookies, e.g. an I/O error.
+    #[error(transparent)]
+    Net(Box<NetError>),
+}
+
+impl CookieExtractionError {
+    /// Error code, the same as the converted [`NetError`]'s.
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::BrowserNotFound(_) => -10020,
+            Self::DatabaseNotFound(_) => -10021,
+            Self::DecryptionFailed { .. } => -10022,
+            Self::DatabaseLocked => -10023,
+            Self::UnsupportedVersion(_) => -10024,
+            Self::PlatformNotSupported(_) => -10025,
+            Self::ProfileNotFound(_) => -10026,
+            Self::KeyringUnavailable => -10027,
+            Self::InvalidData(_) => -10028,
+            Self::Database(_) => -10029,
+            Self::Net(error) => error.as_i32(),
+        }
+    }
+}
+
+impl From<CookieExtractionError> for NetError {
+    fn from(error: CookieExtractionError) -> Self {
+        match error {
+            CookieExtractionError::Net(error) => *error,
+            error => NetError::CookieExtraction(error),
+        }
+    }
+}
+
+impl From<NetError> for CookieExtractionError {
+    #[allow(deprecated)]
+    fn from(error: NetError) -> Self {
+        match error {
+            NetError::CookieExtraction(error) => error,
+            NetError::BrowserNotFound { browser } => Self::BrowserNotFound(browser),
+            NetError::CookieDbNotFound { path } => Self::DatabaseNotFound(path),
+            NetError::CookieDecryptionFailed { browser, reason } => {
+                Self::DecryptionFailed { browser, reason }
+            }
+            NetError::CookieDatabaseLocked => Self::DatabaseLocked,
+            NetError::CookieUnsupportedVersion { version } => Self::UnsupportedVersion(version),
+            NetError::CookiePlatformNotSupported { platform } => {
+                Self::PlatformNotSupported(platform)
+            }
+            NetError::CookieProfileNotFound { profile } => Self::ProfileNotFound(profile),
+            NetError::CookieKeyringUnavailable => Self::KeyringUnavailable,
+            NetError::CookieInvalidData { reason } => Self::InvalidData(reason),
+            NetError::CookieDatabaseError { message } => Self::Database(message),
+            error => Self::Net(Box::new(error)),
+        }
+    }
+}
 
 /// Result type alias for cookie extraction operations.
 #[deprecated(since = "0.2.0", note = "Use Result<T, NetError> instead")]
@@ -63,19 +155,19 @@ pub fn invalid_data(reason: impl Into<String>) -> NetError {
 }
 
 // Conversion from rusqlite errors
+#[cfg(feature = "browser-cookies")]
 impl From<rusqlite::Error> for NetError {
     fn from(err: rusqlite::Error) -> Self {
-        match err {
+        let error = match err {
             rusqlite::Error::SqliteFailure(e, _)
                 if e.code == rusqlite::ffi::ErrorCode::DatabaseBusy
                     || e.code == rusqlite::ffi::ErrorCode::DatabaseLocked =>
             {
-                NetError::CookieDatabaseLocked
+                CookieExtractionError::DatabaseLocked
             }
-            _ => NetError::CookieDatabaseError {
-                message: err.to_string(),
-            },
-        }
+            _ => CookieExtractionError::Database(err.to_string()),
+        };
+        error.into()
     }
 }
 
@@ -83,10 +175,55 @@ impl From<rusqlite::Error> for NetError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_round_trip_keeps_context_and_code() {
+        let errors = [
+            CookieExtractionError::BrowserNotFound("Chrome".into()),
+            CookieExtractionError::DatabaseNotFound("/tmp/Cookies".into()),
+            CookieExtractionError::DecryptionFailed {
+                browser: "chrome".into(),
+                reason: "bad tag".into(),
+            },
+            CookieExtractionError::DatabaseLocked,
+            CookieExtractionError::UnsupportedVersion("v12".into()),
+            CookieExtractionError::PlatformNotSupported("wasm".into()),
+            CookieExtractionError::ProfileNotFound("Work".into()),
+            CookieExtractionError::KeyringUnavailable,
+            CookieExtractionError::InvalidData("truncated".into()),
+            CookieExtractionError::Database("disk I/O error".into()),
+        ];
+        for (code, error) in (-10029..=-10020).rev().zip(errors) {
+            let message = error.to_string();
+            let net = NetError::from(error);
+            assert_eq!(net.as_i32(), code);
+            assert_eq!(net.to_string(), message);
+
+            let back = CookieExtractionError::from(net);
+            assert_eq!(back.code(), code);
+            assert_eq!(back.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_other_errors_pass_through() {
+        let error = CookieExtractionError::from(NetError::FileNotFound);
+        assert!(
+            matches!(&error, CookieExtractionError::Net(e) if matches!(**e, NetError::FileNotFound))
+        );
+        assert_eq!(error.code(), NetError::FileNotFound.as_i32());
+        assert!(matches!(NetError::from(error), NetError::FileNotFound));
+    }
+
     #[test]
     #[allow(deprecated)]
-    fn test_type_alias_works() {
-        let err: CookieExtractionError = NetError::CookieDatabaseLocked;
-        assert!(matches!(err, NetError::CookieDatabaseLocked));
+    fn test_deprecated_variants_convert() {
+        let error = CookieExtractionError::from(NetError::CookieProfileNotFound {
+            profile: "Work".into(),
+        });
+        assert!(matches!(error, CookieExtractionError::ProfileNotFound(p) if p == "Work"));
+        assert_eq!(
+            NetError::CookieKeyringUnavailable.as_i32(),
+            CookieExtractionError::KeyringUnavailable.code()
+        );
     }
 }
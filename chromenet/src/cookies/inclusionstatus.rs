@@ -0,0 +1,175 @@
+//! Why a cookie was or was not stored.
+//!
+//! Chromium mapping: `net::CookieInclusionStatus`
+//!
+//! [`CookieMonster::try_parse_and_save_cookie`](crate::cookies::monster::CookieMonster::try_parse_and_save_cookie)
+//! fails with a [`CookieInclusionStatus`] listing every
+//! [`ExclusionReason`] that applied, so a rejected `Set-Cookie` line can be
+//! told apart from a stored one:
+//!
+//! ```
+//! use chromenet::cookies::inclusionstatus::ExclusionReason;
+//! use chromenet::cookies::monster::CookieMonster;
+//! use url::Url;
+//!
+//! let jar = CookieMonster::new();
+//! let url = Url::parse("http://example.co.uk/").unwrap();
+//! let status = jar
+//!     .try_parse_and_save_cookie(&url, "id=1; Domain=co.uk; Secure")
+//!     .unwrap_err();
+//! assert!(status.has_exclusion_reason(ExclusionReason::InvalidDomain));
+//! ```
+
+use std::fmt;
+use thiserror::Error;
+
+/// A reason a cookie was not stored. Names follow Chromium's
+/// `CookieInclusionStatus::ExclusionReason`; more may be added as the
+/// cookie rules grow.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExclusionReason {
+    /// The cookie line could not be parsed (`EXCLUDE_FAILURE_TO_STORE`).
+    #[error("cookie line could not be parsed")]
+    FailureToStore,
+    /// The `Domain` attribute is not a host name, is a public suffix, or
+    /// does not domain-match the URL (`EXCLUDE_INVALID_DOMAIN`).
+    #[error("invalid Domain attribute")]
+    InvalidDomain,
+    /// A `Secure` cookie set from an insecure URL (`EXCLUDE_SECURE_ONLY`).
+    #[error("Secure cookie set from an insecure URL")]
+    SecureOnly,
+    /// An insecure URL would overwrite or shadow a `Secure` cookie
+    /// (`EXCLUDE_OVERWRITE_SECURE`).
+    #[error("would overwrite a Secure cookie")]
+    OverwriteSecure,
+    /// A `SameSite=Strict` cookie set in a cross-site context
+    /// (`EXCLUDE_SAMESITE_STRICT`).
+    #[error("SameSite=Strict cookie set cross-site")]
+    SameSiteStrict,
+    /// A `SameSite=Lax` cookie, or one without `SameSite`, set in a
+    /// cross-site context (`EXCLUDE_SAMESITE_LAX`).
+    #[error("SameSite=Lax cookie set cross-site")]
+    SameSiteLax,
+}
+
+impl ExclusionReason {
+    /// Every reason, in the order they are reported.
+    pub const ALL: [ExclusionReason; 6] = [
+        Self::FailureToStore,
+        Self::InvalidDomain,
+        Self::SecureOnly,
+        Self::OverwriteSecure,
+        Self::SameSiteStrict,
+        Self::SameSiteLax,
+    ];
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// The outcome of trying to store a cookie: included, or excluded for one
+/// or more [`ExclusionReason`]s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CookieInclusionStatus {
+    exclusions: u32,
+}
+
+impl CookieInclusionStatus {
+    /// A status with no exclusion reasons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A status excluded for `reason` only.
+    pub fn from_reason(reason: ExclusionReason) -> Self {
+        Self {
+            exclusions: reason.bit(),
+        }
+    }
+
+    /// Whether the cookie is included, i.e. no reason excludes it.
+    pub fn is_include(&self) -> bool {
+        self.exclusions == 0
+    }
+
+    /// Whether `reason` is one of the exclusion reasons.
+    pub fn has_exclusion_reason(&self, reason: ExclusionReason) -> bool {
+        self.exclusions & reason.bit() != 0
+    }
+
+    /// Whether `reason` is the only exclusion reason.
+    pub fn has_only_exclusion_reason(&self, reason: ExclusionReason) -> bool {
+        self.exclusions == reason.bit()
+    }
+
+    /// Exclude the cookie for `reason` too.
+    pub fn add_exclusion_reason(&mut self, reason: ExclusionReason) {
+        self.exclusions |= reason.bit();
+    }
+
+    /// The exclusion reasons, empty when included.
+    pub fn exclusion_reasons(&self) -> Vec<ExclusionReason> {
+        ExclusionReason::ALL
+            .into_iter()
+            .filter(|r| self.has_exclusion_reason(*r))
+            .collect()
+    }
+}
+
+impl From<ExclusionReason> for CookieInclusionStatus {
+    fn from(reason: ExclusionReason) -> Self {
+        Self::from_reason(reason)
+    }
+}
+
+impl fmt::Display for CookieInclusionStatus {
+    /// `included`, or the exclusion reasons separated by `; `.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_include() {
+            return f.write_str("included");
+        }
+        for (i, reason) in self.exclusion_reasons().into_iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CookieInclusionStatus {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reasons_accumulate() {
+        let mut status = CookieInclusionStatus::new();
+        assert!(status.is_include());
+        assert_eq!(status.to_string(), "included");
+
+        status.add_exclusion_reason(ExclusionReason::OverwriteSecure);
+        assert!(!status.is_include());
+        assert!(status.has_only_exclusion_reason(ExclusionReason::OverwriteSecure));
+
+        status.add_exclusion_reason(ExclusionReason::SecureOnly);
+        assert!(status.has_exclusion_reason(ExclusionReason::SecureOnly));
+        assert!(!status.has_only_exclusion_reason(ExclusionReason::SecureOnly));
+        assert!(!status.has_exclusion_reason(ExclusionReason::InvalidDomain));
+        assert_eq!(
+            status.exclusion_reasons(),
+            [
+                ExclusionReason::SecureOnly,
+                ExclusionReason::OverwriteSecure
+            ]
+        );
+        assert_eq!(
+            status.to_string(),
+            "Secure cookie set from an insecure URL; would overwrite a Secure cookie"
+        );
+    }
+}
@@ -3,10 +3,12 @@
 //! This module provides a complete cookie management system including:
 //!
 //! - **Storage**: In-memory cookie jar ([`CookieMonster`](monster::CookieMonster))
-//! - **Browser Extraction**: Read cookies from Chrome, Firefox, Safari, Edge, Brave, Opera
+//! - **Browser Extraction**: Read cookies from Chrome, Firefox, Safari, Edge, Brave, Opera,
+//!   Vivaldi, Arc, including snap and flatpak installs on Linux
 //! - **Decryption**: Platform-specific decryption (v10/v11 on Linux, Keychain on macOS, DPAPI on Windows)
 //! - **Persistence**: Save/load cookies to disk
 //! - **Import/Export**: Netscape format and browser import
+//! - **Browser Sync**: Two-way sync with a browser over CDP ([`cdp`])
 //!
 //! # Architecture
 //!
@@ -16,8 +18,10 @@
 //! |----------------|------------------|----------------|
 //! | `net::CookieMonster` | [`CookieMonster`](monster::CookieMonster) | Cookie jar with LRU eviction |
 //! | `net::CanonicalCookie` | [`CanonicalCookie`](canonical_cookie::CanonicalCookie) | Single cookie representation |
+//! | `CookieOptions::SameSiteCookieContext` | [`SameSiteContext`](samesite::SameSiteContext) | Which `SameSite` cookies a request gets |
 //! | `os_crypt::OSCrypt` | [`oscrypt`] | Cookie decryption |
 //! | `SqlitePersistentCookieStore` | [`persistence`] | Disk persistence |
+//! | DevTools `Network.Cookie` | [`cdp`] | Sync with a browser over CDP |
 //!
 //! # Browser Cookie Extraction
 //!
@@ -27,7 +31,7 @@
 //! let reader = BrowserCookieReader::new(Browser::Chrome)
 //!     .domain("example.com"); // Optional: filter by domain
 //!
-//! match reader.read_cookies_v2() {
+//! match reader.read_cookies() {
 //!     Ok(cookies) => println!("Found {} cookies", cookies.len()),
 //!     Err(e) => eprintln!("Error: {:?}", e),
 //! }
@@ -57,13 +61,17 @@
 //! # Ok::<(), std::io::Error>(())
 //! ```
 //!
+//! Browser extraction and decryption ([`browser`], [`decrypt`],
+//! [`oscrypt`]) need the `browser-cookies` feature.
+//!
 //! # Supported Browsers
 //!
 //! | Browser | Linux | macOS | Windows |
 //! |---------|-------|-------|---------|
 //! | Chrome/Chromium | v10, v11 | Keychain | DPAPI |
 //! | Firefox | ✓ (plaintext) | ✓ | ✓ |
-//! | Edge/Brave/Opera | v10, v11 | Keychain | DPAPI |
+//! | Edge/Brave/Opera/Vivaldi | v10, v11 | Keychain | DPAPI |
+//! | Opera GX/Arc | N/A | Keychain | DPAPI |
 //! | Safari | N/A | ✓ (binary) | N/A |
 //!
 //! # Chromium References
@@ -72,13 +80,19 @@
 //! - Encryption: `components/os_crypt/sync/os_crypt_linux.cc`
 //! - Cookie monster: `net/cookies/cookie_monster.cc`
 
+#[cfg(feature = "browser-cookies")]
 pub mod browser;
 pub mod canonicalcookie;
+pub mod cdp;
 pub mod chromedb;
+#[cfg(feature = "browser-cookies")]
 pub mod decrypt;
 pub mod error;
+pub mod inclusionstatus;
 pub mod monster;
+#[cfg(feature = "browser-cookies")]
 pub mod oscrypt;
 pub mod persistence;
 pub mod psl;
 pub mod safari;
+pub mod samesite;
@@ -1,4 +1,8 @@
+use crate::base::clock::{system_clock, Clock};
+use crate::base::host::{canonicalize_host, host_key, ip_literal, url_host};
 use crate::cookies::canonicalcookie::CanonicalCookie;
+use crate::cookies::inclusionstatus::{CookieInclusionStatus, ExclusionReason};
+use crate::cookies::samesite::SameSiteContext;
 use dashmap::DashMap;
 use std::sync::Arc;
 use time::OffsetDateTime;
@@ -19,6 +23,8 @@ pub struct CookieMonster {
     // Store: Map<Domain, List<Cookie>>
     // Using DashMap for high concurrency.
     store: Arc<DashMap<String, Vec<CanonicalCookie>>>,
+    strict_secure: bool,
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for CookieMonster {
@@ -31,10 +37,56 @@ impl CookieMonster {
     pub fn new() -> Self {
         Self {
             store: Arc::new(DashMap::new()),
+            strict_secure: true,
+            clock: system_clock(),
         }
     }
 
-    pub fn set_canonical_cookie(&self, cookie: CanonicalCookie) {
+    /// Stamp and expire cookies by `clock` instead of the system time,
+    /// e.g. a [`MockClock`](crate::base::clock::MockClock) in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The current time by the jar's clock.
+    pub(crate) fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::from(self.clock.now())
+    }
+
+    /// Whether insecure origins are kept away from `Secure` cookies
+    /// (RFC 6265bis section 5.7, "Leave Secure Cookies Alone"). On by
+    /// default.
+    ///
+    /// When on, a response from an `http:` or `ws:` URL can neither set a
+    /// `Secure` cookie nor set a cookie that would shadow one: same name,
+    /// domains that domain-match each other, and a path within the `Secure`
+    /// cookie's path. Turning it off restores RFC 6265 behavior, where any
+    /// origin may overwrite any cookie of its domain.
+    ///
+    /// Like all cookie matching, this ignores ports: `http://example.com:8080`
+    /// cannot touch a `Secure` cookie set by `https://example.com`.
+    pub fn with_strict_secure(mut self, strict: bool) -> Self {
+        self.strict_secure = strict;
+        self
+    }
+
+    /// Whether [`with_strict_secure`](Self::with_strict_secure) is on.
+    pub fn is_strict_secure(&self) -> bool {
+        self.strict_secure
+    }
+
+    /// Store `cookie`, replacing one with the same name, domain and path.
+    ///
+    /// The domain is stored in canonical form (see [`crate::base::host`]),
+    /// keeping a leading dot.
+    pub fn set_canonical_cookie(&self, mut cookie: CanonicalCookie) {
+        let bare = cookie.domain.strip_prefix('.');
+        let canonical = host_key(bare.unwrap_or(&cookie.domain));
+        cookie.domain = match bare {
+            Some(_) => format!(".{}", canonical),
+            None => canonical,
+        };
         let mut entry = self.store.entry(cookie.domain.clone()).or_default();
 
         // Remove existing if name/domain/path match
@@ -93,10 +145,26 @@ impl CookieMonster {
     }
 
     /// Get cookies matching the URL with proper domain suffix matching.
+    ///
+    /// Every `SameSite` cookie matches, as for a request in a
+    /// [`Strict`](SameSiteContext::Strict) context.
     pub fn get_cookies_for_url(&self, url: &Url) -> Vec<CanonicalCookie> {
+        self.get_cookies_for_url_in_context(url, SameSiteContext::Strict)
+    }
+
+    /// Get cookies matching the URL that a request in `context` sends.
+    ///
+    /// Chromium mapping: `CanonicalCookie::IncludeForRequestURL` with the
+    /// `SameSiteCookieContext` of its `CookieOptions`
+    pub fn get_cookies_for_url_in_context(
+        &self,
+        url: &Url,
+        context: SameSiteContext,
+    ) -> Vec<CanonicalCookie> {
         let mut result = Vec::new();
-        let host = url.host_str().unwrap_or("");
-        let now = OffsetDateTime::now_utc();
+        let host = url_host(url).unwrap_or_default();
+        let host = host.as_str();
+        let now = self.now();
 
         // Collect matching domains (host itself and parent domains)
         let domains_to_check = Self::get_matching_domains(host);
@@ -115,7 +183,7 @@ impl CookieMonster {
                     }
 
                     // Check secure
-                    if cookie.secure && url.scheme() != "https" {
+                    if cookie.secure && !Self::is_secure_scheme(url) {
                         continue;
                     }
 
@@ -124,6 +192,10 @@ impl CookieMonster {
                         continue;
                     }
 
+                    if !context.includes(cookie.same_site) {
+                        continue;
+                    }
+
                     result.push(cookie.clone());
                 }
             }
@@ -190,79 +262,175 @@ impl CookieMonster {
         false
     }
 
+    /// Whether `url` may see and set `Secure` cookies.
+    fn is_secure_scheme(url: &Url) -> bool {
+        matches!(url.scheme(), "https" | "wss")
+    }
+
+    /// Whether storing `cookie` from an insecure origin would overwrite or
+    /// shadow a `Secure` cookie.
+    ///
+    /// Chromium mapping: `CookieMonster::MaybeDeleteEquivalentCookieAndUpdateStatus`
+    /// with `skip_secure`
+    fn shadows_secure_cookie(&self, cookie: &CanonicalCookie) -> bool {
+        let domain = cookie.domain.trim_start_matches('.');
+        self.store.iter().any(|entry| {
+            entry.value().iter().any(|existing| {
+                let existing_domain = existing.domain.trim_start_matches('.');
+                existing.secure
+                    && existing.name == cookie.name
+                    && (Self::domain_matches(existing_domain, domain, false)
+                        || Self::domain_matches(domain, existing_domain, false))
+                    && Self::path_matches(&existing.path, &cookie.path)
+            })
+        })
+    }
+
     /// Get all domains to check for a given host.
-    /// Returns the host itself and all parent domains.
+    /// Returns the host itself and its parent domains down to the
+    /// registrable domain (eTLD+1). Public suffixes above it, like "co.uk"
+    /// or "github.io", never hold cookies for the host.
     fn get_matching_domains(host: &str) -> Vec<String> {
         let mut domains = vec![host.to_string()];
-
-        // Add parent domains (e.g., for "foo.bar.example.com", add "bar.example.com", "example.com")
-        let parts: Vec<&str> = host.split('.').collect();
-        for i in 1..parts.len().saturating_sub(1) {
-            let parent = parts[i..].join(".");
-            domains.push(parent);
+        // IP literals and public suffixes have no parent domains to check
+        let Some(registrable) = crate::cookies::psl::registrable_domain(host) else {
+            return domains;
+        };
+
+        // For "foo.bar.example.co.uk", add "bar.example.co.uk", "example.co.uk"
+        let mut parent = host;
+        while parent.len() > registrable.len() {
+            let Some((_, rest)) = parent.split_once('.') else {
+                break;
+            };
+            parent = rest;
+            domains.push(parent.to_string());
         }
 
         domains
     }
 
+    /// Parse a `Set-Cookie` line received from `url` and store the cookie,
+    /// ignoring it if it is rejected. See
+    /// [`try_parse_and_save_cookie`](Self::try_parse_and_save_cookie) to
+    /// learn why.
     pub fn parse_and_save_cookie(&self, url: &Url, cookie_line: &str) {
-        use crate::cookies::canonicalcookie::{CookiePriority, SameSite};
-        use cookie::Cookie;
-
-        if let Ok(parsed) = Cookie::parse(cookie_line) {
-            let now = time::OffsetDateTime::now_utc();
-
-            // Domain logic
-            let (domain, host_only) = if let Some(d) = parsed.domain() {
-                // If explicit domain, it's not host-only.
-                // Chromium strips leading dot.
-                let d = d.trim_start_matches('.').to_lowercase();
-
-                // PSL validation: reject cookies set on public suffixes
-                // This prevents supercookie attacks (e.g., setting cookie on ".com")
-                if !crate::cookies::psl::is_valid_cookie_domain(&d, url.host_str().unwrap_or("")) {
-                    return; // Silently reject like browsers do
-                }
-
-                (d, false)
-            } else {
-                // Host only
-                (url.host_str().unwrap_or("").to_lowercase(), true)
-            };
+        let _ = self.try_parse_and_save_cookie(url, cookie_line);
+    }
 
-            // Path logic
-            let path = parsed.path().unwrap_or("/").to_string();
+    /// Parse a `Set-Cookie` line received from `url` and store the cookie.
+    ///
+    /// Fails, storing nothing, with every reason that excluded the cookie:
+    /// a line that does not parse, a `Domain` that is not a host name or
+    /// fails the Public Suffix List check, and with
+    /// [`with_strict_secure`](Self::with_strict_secure) a `Secure` cookie
+    /// or one shadowing a `Secure` cookie from an insecure URL.
+    ///
+    /// Chromium mapping: `CookieMonster::SetCanonicalCookie` and the
+    /// `CookieInclusionStatus` of its `CookieAccessResult`
+    pub fn try_parse_and_save_cookie(
+        &self,
+        url: &Url,
+        cookie_line: &str,
+    ) -> Result<(), CookieInclusionStatus> {
+        self.try_parse_and_save_cookie_in_context(url, cookie_line, SameSiteContext::Strict)
+    }
 
-            // Expiry logic
-            let expiration_time = parsed.expires().and_then(|e| e.datetime());
+    /// Like [`try_parse_and_save_cookie`](Self::try_parse_and_save_cookie)
+    /// for a response to a request in `context`: a cross-site response
+    /// cannot set `SameSite=Strict`, `Lax` or unspecified cookies.
+    pub fn try_parse_and_save_cookie_in_context(
+        &self,
+        url: &Url,
+        cookie_line: &str,
+        context: SameSiteContext,
+    ) -> Result<(), CookieInclusionStatus> {
+        use crate::cookies::canonicalcookie::{CookiePriority, SameSite};
+        use cookie::Cookie;
 
-            // SameSite logic
-            let same_site = match parsed.same_site() {
-                Some(cookie::SameSite::Lax) => SameSite::Lax,
-                Some(cookie::SameSite::Strict) => SameSite::Strict,
-                Some(cookie::SameSite::None) => SameSite::NoRestriction,
-                None => SameSite::Unspecified,
+        let Ok(parsed) = Cookie::parse(cookie_line) else {
+            tracing::trace!(target: "chromenet::cookies", cookie = %cookie_line, "Failed to parse cookie");
+            return Err(ExclusionReason::FailureToStore.into());
+        };
+        let now = self.now();
+
+        // Domain logic
+        let (domain, host_only) = if let Some(d) = parsed.domain() {
+            // If explicit domain, it's not host-only.
+            // Chromium strips leading dot.
+            let Some(d) = canonicalize_host(d.trim_start_matches('.')) else {
+                return Err(ExclusionReason::InvalidDomain.into()); // Not a host name
             };
+            let host = url_host(url).unwrap_or_default();
 
-            let c = CanonicalCookie {
-                name: parsed.name().to_string(),
-                value: parsed.value().to_string(),
-                domain,
-                path,
-                creation_time: now,
-                expiration_time,
-                last_access_time: now,
-                secure: parsed.secure().unwrap_or(false),
-                http_only: parsed.http_only().unwrap_or(false),
-                host_only,
-                same_site,
-                priority: CookiePriority::Medium,
-            };
+            // PSL validation: reject cookies set on public suffixes
+            // This prevents supercookie attacks (e.g., setting cookie on ".com")
+            if !crate::cookies::psl::is_valid_cookie_domain(&d, &host) {
+                return Err(ExclusionReason::InvalidDomain.into());
+            }
 
-            self.set_canonical_cookie(c);
+            // An IP literal names only itself, like a host-only cookie
+            let host_only = ip_literal(&host).is_some();
+            (d, host_only)
         } else {
-            tracing::trace!(target: "chromenet::cookies", cookie = %cookie_line, "Failed to parse cookie");
+            // Host only
+            (url_host(url).unwrap_or_default(), true)
+        };
+
+        // Path logic
+        let path = parsed.path().unwrap_or("/").to_string();
+
+        // Expiry logic
+        let expiration_time = parsed.expires().and_then(|e| e.datetime());
+
+        // SameSite logic
+        let same_site = match parsed.same_site() {
+            Some(cookie::SameSite::Lax) => SameSite::Lax,
+            Some(cookie::SameSite::Strict) => SameSite::Strict,
+            Some(cookie::SameSite::None) => SameSite::NoRestriction,
+            None => SameSite::Unspecified,
+        };
+
+        let c = CanonicalCookie {
+            name: parsed.name().to_string(),
+            value: parsed.value().to_string(),
+            domain,
+            path,
+            creation_time: now,
+            expiration_time,
+            last_access_time: now,
+            secure: parsed.secure().unwrap_or(false),
+            http_only: parsed.http_only().unwrap_or(false),
+            host_only,
+            same_site,
+            priority: CookiePriority::Medium,
+        };
+
+        let mut status = CookieInclusionStatus::new();
+        if self.strict_secure && !Self::is_secure_scheme(url) {
+            if c.secure {
+                tracing::trace!(target: "chromenet::cookies", name = %c.name, "Secure cookie from insecure origin rejected");
+                status.add_exclusion_reason(ExclusionReason::SecureOnly);
+            }
+            if self.shadows_secure_cookie(&c) {
+                tracing::trace!(target: "chromenet::cookies", name = %c.name, "Cookie would overwrite a Secure cookie, rejected");
+                status.add_exclusion_reason(ExclusionReason::OverwriteSecure);
+            }
+        }
+        if !context.allows_set(c.same_site) {
+            tracing::trace!(target: "chromenet::cookies", name = %c.name, "SameSite cookie set cross-site, rejected");
+            status.add_exclusion_reason(if c.same_site == SameSite::Strict {
+                ExclusionReason::SameSiteStrict
+            } else {
+                ExclusionReason::SameSiteLax
+            });
+        }
+        if !status.is_include() {
+            return Err(status);
         }
+
+        self.set_canonical_cookie(c);
+        Ok(())
     }
 
     /// Get total cookie count.
@@ -275,6 +443,22 @@ impl CookieMonster {
         self.store.clear();
     }
 
+    /// Remove the cookie named `name` on `domain` and `path`, host-only or
+    /// not. A leading dot on `domain` is ignored. Returns whether there was
+    /// one.
+    pub fn delete_cookie(&self, name: &str, domain: &str, path: &str) -> bool {
+        let bare = host_key(domain.trim_start_matches('.'));
+        let mut deleted = false;
+        for key in [format!(".{bare}"), bare] {
+            if let Some(mut entry) = self.store.get_mut(&key) {
+                let before = entry.len();
+                entry.retain(|c| c.name != name || c.path != path);
+                deleted |= entry.len() != before;
+            }
+        }
+        deleted
+    }
+
     /// Iterate over all cookies (for persistence).
     pub fn iter_all_cookies(&self) -> impl Iterator<Item = CanonicalCookie> + '_ {
         self.store.iter().flat_map(|entry| entry.value().clone())
@@ -295,6 +479,7 @@ impl CookieMonster {
     ///     Err(e) => eprintln!("Import failed: {:?}", e),
     /// }
     /// ```
+    #[cfg(feature = "browser-cookies")]
     pub fn import_from_browser(
         &self,
         browser: crate::cookies::browser::Browser,
@@ -307,7 +492,7 @@ impl CookieMonster {
             reader = reader.domain(domain);
         }
 
-        let cookies = reader.read_cookies_v2()?;
+        let cookies = reader.read_cookies()?;
         let count = cookies.len();
 
         for cookie in cookies {
@@ -318,6 +503,7 @@ impl CookieMonster {
     }
 
     /// Import cookies from browser with a specific profile.
+    #[cfg(feature = "browser-cookies")]
     pub fn import_from_browser_profile(
         &self,
         browser: crate::cookies::browser::Browser,
@@ -331,7 +517,7 @@ impl CookieMonster {
             reader = reader.domain(domain);
         }
 
-        let cookies = reader.read_cookies_v2()?;
+        let cookies = reader.read_cookies()?;
         let count = cookies.len();
 
         for cookie in cookies {
@@ -409,10 +595,9 @@ impl CookieMonster {
     /// ```
     pub fn import_netscape(&self, content: &str) -> usize {
         use crate::cookies::canonicalcookie::{CookiePriority, SameSite};
-        use time::OffsetDateTime;
 
         let mut count = 0;
-        let now = OffsetDateTime::now_utc();
+        let now = self.now();
 
         for line in content.lines() {
             let line = line.trim();
@@ -467,6 +652,7 @@ impl CookieMonster {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::base::clock::MockClock;
     use crate::cookies::canonicalcookie::{CookiePriority, SameSite};
 
     fn make_test_cookie(name: &str, domain: &str) -> CanonicalCookie {
@@ -486,6 +672,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_matching_domains_stop_at_registrable_domain() {
+        assert_eq!(
+            CookieMonster::get_matching_domains("a.b.example.co.uk"),
+            ["a.b.example.co.uk", "b.example.co.uk", "example.co.uk"]
+        );
+        assert_eq!(
+            CookieMonster::get_matching_domains("user.github.io"),
+            ["user.github.io"]
+        );
+        assert_eq!(CookieMonster::get_matching_domains("co.uk"), ["co.uk"]);
+        assert_eq!(
+            CookieMonster::get_matching_domains("www.example.com"),
+            ["www.example.com", "example.com"]
+        );
+    }
+
+    #[test]
+    fn test_public_suffix_cookies_not_sent() {
+        // Imported cookies skip the PSL check done for Set-Cookie
+        let jar = CookieMonster::new();
+        jar.set_canonical_cookie(make_test_cookie("suffix", "github.io"));
+        jar.set_canonical_cookie(make_test_cookie("site", "user.github.io"));
+        jar.set_canonical_cookie(make_test_cookie("uk", "co.uk"));
+        jar.set_canonical_cookie(make_test_cookie("shop", "shop.co.uk"));
+
+        let names = |url: &str| -> Vec<String> {
+            let url = Url::parse(url).unwrap();
+            let mut names: Vec<_> = jar
+                .get_cookies_for_url(&url)
+                .into_iter()
+                .map(|c| c.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("https://user.github.io/"), ["site"]);
+        assert_eq!(names("https://www.shop.co.uk/"), ["shop"]);
+    }
+
+    #[test]
+    fn test_cookie_expires_by_clock() {
+        let start = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let clock = MockClock::new(start.into());
+        let jar = CookieMonster::new().with_clock(Arc::new(clock.clone()));
+        let url = Url::parse("https://example.com/").unwrap();
+        jar.parse_and_save_cookie(&url, "a=1; Expires=Tue, 14 Nov 2023 23:13:20 GMT");
+
+        let cookies = jar.get_cookies_for_url(&url);
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].creation_time, start);
+
+        clock.advance(std::time::Duration::from_secs(3_600));
+        assert_eq!(jar.get_cookies_for_url(&url).len(), 1);
+        clock.advance(std::time::Duration::from_secs(1));
+        assert!(jar.get_cookies_for_url(&url).is_empty());
+    }
+
     #[test]
     fn test_export_netscape_basic() {
         let jar = CookieMonster::new();
@@ -552,4 +796,44 @@ mod tests {
 
         assert_eq!(count, 1);
     }
+
+    #[test]
+    fn test_same_site_context() {
+        let jar = CookieMonster::new();
+        let url = Url::parse("https://example.com/").unwrap();
+        for line in [
+            "strict=1; SameSite=Strict",
+            "lax=1; SameSite=Lax",
+            "default=1",
+            "none=1; SameSite=None; Secure",
+        ] {
+            jar.parse_and_save_cookie(&url, line);
+        }
+        let names = |context| {
+            let mut names: Vec<_> = jar
+                .get_cookies_for_url_in_context(&url, context)
+                .into_iter()
+                .map(|c| c.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names(SameSiteContext::Strict),
+            ["default", "lax", "none", "strict"]
+        );
+        assert_eq!(names(SameSiteContext::Lax), ["default", "lax", "none"]);
+        assert_eq!(names(SameSiteContext::CrossSite), ["none"]);
+
+        let status = jar
+            .try_parse_and_save_cookie_in_context(&url, "x=1", SameSiteContext::CrossSite)
+            .unwrap_err();
+        assert!(status.has_only_exclusion_reason(ExclusionReason::SameSiteLax));
+        jar.try_parse_and_save_cookie_in_context(
+            &url,
+            "y=1; SameSite=Strict",
+            SameSiteContext::Lax,
+        )
+        .unwrap();
+    }
 }
@@ -10,6 +10,7 @@
 //! - **v11 (Linux)**: AES-CBC with Keyring-derived key
 
 use crate::base::neterror::NetError;
+use crate::cookies::decrypt::{KeySource, KeyringIdentity};
 
 /// v10 prefix used by Chrome for encrypted values.
 pub const V10_PREFIX: &[u8] = b"v10";
@@ -147,9 +148,7 @@ pub fn decrypt_for_browser(encrypted: &[u8], browser: &str) -> Result<String, Ne
         // v11 requires keyring access
         #[cfg(target_os = "linux")]
         {
-            use super::decrypt::linux;
-            let application = linux::browser_to_application(browser);
-            match super::decrypt::get_chrome_key(application) {
+            match super::decrypt::get_chrome_key(&KeyringIdentity::for_browser(browser)) {
                 Ok(Some(key)) => {
                     decrypt_v10_with_key(encrypted, &key).ok_or(NetError::InvalidResponse)
                 }
@@ -175,7 +174,126 @@ pub fn decrypt_cookie(encrypted: &[u8]) -> Result<String, NetError> {
     decrypt_cookie_for_browser(encrypted, "chrome")
 }
 
+/// Decrypts the cookies of one browser profile.
+///
+/// On Linux the v11 keys of every password store are looked up on the first
+/// v11 cookie, and the store whose key decrypts it is used for the rest, so
+/// profiles kept in KWallet or the basic store decrypt without the caller
+/// knowing which one Chrome picked. [`key_source`](Self::key_source) tells
+/// which one it was.
+#[derive(Debug)]
+pub struct CookieDecryptor {
+    browser: String,
+    /// Names of the browser's entries in the keyrings
+    identity: KeyringIdentity,
+    #[cfg(target_os = "linux")]
+    v11_keys: Option<Vec<(PasswordStore, [u8; 16])>>,
+    /// Index into `v11_keys` of the key that last decrypted a cookie.
+    #[cfg(target_os = "linux")]
+    v11_key: Option<usize>,
+}
+
+#[cfg(target_os = "linux")]
+use super::decrypt::linux::PasswordStore;
+
+impl CookieDecryptor {
+    /// Decryptor for `browser`'s cookies, e.g. "chrome" or "brave".
+    pub fn new(browser: &str) -> Self {
+        Self {
+            browser: browser.to_string(),
+            identity: KeyringIdentity::for_browser(browser),
+            #[cfg(target_os = "linux")]
+            v11_keys: None,
+            #[cfg(target_os = "linux")]
+            v11_key: None,
+        }
+    }
+
+    /// Look the keys up under `identity` instead of the one of the browser
+    /// named on creation.
+    pub fn with_identity(mut self, identity: KeyringIdentity) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    /// Names the keys are looked up under.
+    pub fn identity(&self) -> &KeyringIdentity {
+        &self.identity
+    }
+
+    /// Decryptor that tries only `keys`, in order, for v11 cookies.
+    #[cfg(target_os = "linux")]
+    pub fn with_v11_keys(browser: &str, keys: Vec<(PasswordStore, [u8; 16])>) -> Self {
+        Self {
+            v11_keys: Some(keys),
+            ..Self::new(browser)
+        }
+    }
+
+    /// Password store whose key decrypted the last v11 cookie.
+    #[cfg(target_os = "linux")]
+    pub fn password_store(&self) -> Option<PasswordStore> {
+        let keys = self.v11_keys.as_ref()?;
+        self.v11_key.map(|i| keys[i].0)
+    }
+
+    /// Where the key that decrypted the last keyring-encrypted cookie came
+    /// from, `None` before one was decrypted or where the platform's
+    /// cookies need no keyring key.
+    pub fn key_source(&self) -> Option<KeySource> {
+        #[cfg(target_os = "linux")]
+        {
+            self.password_store()
+                .map(|store| self.identity.key_source(store))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+
+    /// Decrypt one `encrypted_value`, as [`decrypt_cookie_for_browser`].
+    pub fn decrypt(&mut self, encrypted: &[u8]) -> Result<String, NetError> {
+        #[cfg(target_os = "linux")]
+        if encrypted.starts_with(V11_PREFIX) {
+            return self.decrypt_v11(encrypted);
+        }
+        decrypt_cookie_for_browser(encrypted, &self.browser)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn decrypt_v11(&mut self, encrypted: &[u8]) -> Result<String, NetError> {
+        use super::decrypt::linux;
+
+        let keys = self
+            .v11_keys
+            .get_or_insert_with(|| linux::v11_key_candidates(&self.identity));
+        // The last working key first, then every other one
+        let order = self.v11_key.into_iter().chain(0..keys.len());
+        for i in order {
+            if let Some(value) = decrypt_v10_with_key(encrypted, &keys[i].1) {
+                self.v11_key = Some(i);
+                return Ok(value);
+            }
+        }
+
+        let mut tried: Vec<&str> = keys.iter().map(|(store, _)| store.as_str()).collect();
+        tried.dedup();
+        Err(NetError::cookie_decryption_failed(
+            &self.browser,
+            format!(
+                "no v11 key decrypts the cookie (tried {})",
+                tried.join(", ")
+            ),
+        ))
+    }
+}
+
 /// Decrypt cookie with browser-specific keyring lookup.
+///
+/// On Linux, v11 values are tried against every password store; use a
+/// [`CookieDecryptor`] to look the keys up once for many cookies.
 pub fn decrypt_cookie_for_browser(encrypted: &[u8], browser: &str) -> Result<String, NetError> {
     if encrypted.starts_with(V10_PREFIX) {
         decrypt_v10(encrypted)
@@ -184,22 +302,14 @@ pub fn decrypt_cookie_for_browser(encrypted: &[u8], browser: &str) -> Result<Str
         // v11 requires keyring access
         #[cfg(target_os = "linux")]
         {
-            use super::decrypt::linux;
-            let application = linux::browser_to_application(browser);
-            match super::decrypt::get_chrome_key(application) {
-                Ok(Some(key)) => decrypt_v10_with_key(encrypted, &key).ok_or_else(|| {
-                    NetError::cookie_decryption_failed(browser, "v11 decryption failed")
-                }),
-                Ok(None) => Err(NetError::CookieKeyringUnavailable),
-                Err(e) => Err(e),
-            }
+            CookieDecryptor::new(browser).decrypt_v11(encrypted)
         }
         #[cfg(not(target_os = "linux"))]
         {
             let _ = browser;
-            Err(NetError::CookiePlatformNotSupported {
-                platform: "v11 keyring not available on this platform".into(),
-            })
+            Err(NetError::cookie_platform_not_supported(
+                "v11 keyring not available on this platform",
+            ))
         }
     } else if encrypted.is_empty() {
         Ok(String::new())
@@ -321,6 +431,82 @@ mod tests {
         assert!(result.is_none());
     }
 
+    /// `plaintext` encrypted the way Chrome does, under `prefix`.
+    fn encrypt(prefix: &[u8], key: &[u8; 16], plaintext: &[u8]) -> Vec<u8> {
+        use boring::symm::{Cipher, Crypter, Mode};
+        let mut crypter =
+            Crypter::new(Cipher::aes_128_cbc(), Mode::Encrypt, key, Some(&V10_IV)).unwrap();
+        let mut ciphertext = vec![0u8; plaintext.len() + 16];
+        let count = crypter.update(plaintext, &mut ciphertext).unwrap();
+        let rest = crypter.finalize(&mut ciphertext[count..]).unwrap();
+        ciphertext.truncate(count + rest);
+        [prefix, &ciphertext].concat()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_decryptor_finds_working_store() {
+        use super::super::decrypt::derive_key;
+
+        let kwallet_key = derive_key(b"kwallet password", 1);
+        let keys = vec![
+            (PasswordStore::Libsecret, derive_key(b"stale password", 1)),
+            (PasswordStore::KWallet, kwallet_key),
+            (PasswordStore::Basic, V10_KEY),
+            (PasswordStore::Basic, EMPTY_KEY),
+        ];
+        let mut decryptor = CookieDecryptor::with_v11_keys("chrome", keys.clone());
+        assert_eq!(decryptor.password_store(), None);
+        assert_eq!(decryptor.key_source(), None);
+
+        let cookie = encrypt(V11_PREFIX, &kwallet_key, b"session=abc123");
+        assert_eq!(decryptor.decrypt(&cookie).unwrap(), "session=abc123");
+        assert_eq!(decryptor.password_store(), Some(PasswordStore::KWallet));
+        assert_eq!(
+            decryptor.key_source(),
+            Some(KeySource::KWallet {
+                folder: "Chrome Keys".into(),
+                entry: "Chrome Safe Storage".into(),
+            })
+        );
+
+        // A fork keeping its password under its own name
+        let mut decryptor = CookieDecryptor::with_v11_keys("chromium", keys.clone())
+            .with_identity(KeyringIdentity::named("Thorium"));
+        assert_eq!(decryptor.decrypt(&cookie).unwrap(), "session=abc123");
+        assert_eq!(
+            decryptor.key_source().unwrap().to_string(),
+            "kwallet (Thorium Keys/Thorium Safe Storage)"
+        );
+
+        // Basic store, e.g. Chrome run with --password-store=basic
+        let mut decryptor = CookieDecryptor::with_v11_keys("chrome", keys.clone());
+        let cookie = encrypt(V11_PREFIX, &EMPTY_KEY, b"token");
+        assert_eq!(decryptor.decrypt(&cookie).unwrap(), "token");
+        assert_eq!(decryptor.password_store(), Some(PasswordStore::Basic));
+
+        // v10 values don't touch the v11 keys
+        let cookie = encrypt(V10_PREFIX, &V10_KEY, b"v10 value");
+        assert_eq!(decryptor.decrypt(&cookie).unwrap(), "v10 value");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_decryptor_reports_stores_tried() {
+        use super::super::decrypt::derive_key;
+
+        let keys = vec![
+            (PasswordStore::Libsecret, derive_key(b"a", 1)),
+            (PasswordStore::Basic, V10_KEY),
+            (PasswordStore::Basic, EMPTY_KEY),
+        ];
+        let mut decryptor = CookieDecryptor::with_v11_keys("brave", keys);
+        let cookie = encrypt(V11_PREFIX, &derive_key(b"b", 1), b"value");
+        let err = decryptor.decrypt(&cookie).unwrap_err().to_string();
+        assert!(err.contains("gnome-libsecret, basic"), "{}", err);
+        assert_eq!(decryptor.password_store(), None);
+    }
+
     #[test]
     fn test_chrome_salt_constant() {
         assert_eq!(CHROME_SALT, b"saltysalt");
@@ -1,6 +1,20 @@
 //! Cookie persistence - save and load cookies to/from disk.
 //!
 //! Provides JSON-based persistence for CookieMonster.
+//!
+//! Chromium mapping: `SqlitePersistentCookieStore` with a
+//! `CookieCryptoDelegate`, which encrypts cookie values with `os_crypt`
+//!
+//! [`save_cookies`] writes plaintext JSON. [`save_cookies_encrypted`]
+//! encrypts the same JSON at rest with AES-256-GCM, under a key the caller
+//! supplies or one kept in the system keyring ([`StorageKey`]), so a saved
+//! session is not readable from the file alone.
+//!
+//! ## Encrypted File Format
+//! - Magic: "chromenet-cookies-v1\0" (21 bytes), also authenticated
+//! - Nonce: 12 random bytes
+//! - Ciphertext: the JSON of [`save_cookies`]
+//! - Tag: 16 bytes
 
 use crate::cookies::monster::CookieMonster;
 use std::fs;
@@ -11,7 +25,7 @@ use serde::{Deserialize, Serialize};
 
 /// Serializable representation of a cookie for persistence.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-struct PersistentCookie {
+pub(crate) struct PersistentCookie {
     name: String,
     value: String,
     domain: String,
@@ -22,6 +36,68 @@ struct PersistentCookie {
     expires_unix_secs: Option<i64>,
 }
 
+/// Magic at the start of encrypted files.
+const ENCRYPTED_MAGIC: &[u8] = b"chromenet-cookies-v1\0";
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Key to encrypt saved cookies with.
+#[derive(Clone)]
+pub enum StorageKey {
+    /// A 256-bit key supplied by the caller.
+    Key([u8; 32]),
+    /// A key in the system keyring (Secret Service on Linux, Keychain on
+    /// macOS) under this name. Saving creates it on first use.
+    #[cfg(feature = "browser-cookies")]
+    Keyring(String),
+}
+
+impl std::fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageKey::Key(_) => f.write_str("Key(..)"),
+            #[cfg(feature = "browser-cookies")]
+            StorageKey::Keyring(name) => f.debug_tuple("Keyring").field(name).finish(),
+        }
+    }
+}
+
+impl StorageKey {
+    /// The key to save with, creating a keyring key if there is none yet.
+    fn for_save(&self) -> io::Result<[u8; 32]> {
+        match self {
+            StorageKey::Key(key) => Ok(*key),
+            #[cfg(feature = "browser-cookies")]
+            StorageKey::Keyring(name) => {
+                use crate::cookies::decrypt::{get_storage_key, set_storage_key};
+                if let Some(key) = get_storage_key(name).map_err(io::Error::other)? {
+                    return Ok(key);
+                }
+                let mut key = [0u8; 32];
+                boring::rand::rand_bytes(&mut key).map_err(io::Error::other)?;
+                set_storage_key(name, &key).map_err(io::Error::other)?;
+                Ok(key)
+            }
+        }
+    }
+
+    /// The key to load with.
+    fn for_load(&self) -> io::Result<[u8; 32]> {
+        match self {
+            StorageKey::Key(key) => Ok(*key),
+            #[cfg(feature = "browser-cookies")]
+            StorageKey::Keyring(name) => crate::cookies::decrypt::get_storage_key(name)
+                .map_err(io::Error::other)?
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no cookie storage key named {name:?} in the keyring"),
+                    )
+                }),
+        }
+    }
+}
+
 /// Save cookies from a CookieMonster to a file.
 ///
 /// # Example
@@ -29,13 +105,62 @@ struct PersistentCookie {
 /// persistence::save_cookies(&monster, "/path/to/cookies.json")?;
 /// ```
 pub fn save_cookies(monster: &CookieMonster, path: &Path) -> io::Result<()> {
-    let mut all_cookies = Vec::new();
+    fs::write(path, encode(monster)?)
+}
 
-    // Iterate through all cookies
-    for cookie in monster.iter_all_cookies() {
-        let expires = cookie.expiration_time.map(|t| t.unix_timestamp());
+/// Save cookies to a file encrypted with `key`.
+///
+/// # Example
+/// ```ignore
+/// let key = StorageKey::Keyring("my-app".into());
+/// persistence::save_cookies_encrypted(&monster, path, &key)?;
+/// ```
+pub fn save_cookies_encrypted(
+    monster: &CookieMonster,
+    path: &Path,
+    key: &StorageKey,
+) -> io::Result<()> {
+    use boring::symm::{encrypt_aead, Cipher};
+    use zeroize::Zeroize;
+
+    let mut key = key.for_save()?;
+    let mut json = encode(monster)?;
+    let mut nonce = [0u8; NONCE_LEN];
+    boring::rand::rand_bytes(&mut nonce).map_err(io::Error::other)?;
+    let mut tag = [0u8; TAG_LEN];
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key,
+        Some(&nonce),
+        ENCRYPTED_MAGIC,
+        &json,
+        &mut tag,
+    );
+    key.zeroize();
+    json.zeroize();
+    let ciphertext = ciphertext.map_err(io::Error::other)?;
 
-        all_cookies.push(PersistentCookie {
+    let mut data =
+        Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + ciphertext.len() + TAG_LEN);
+    data.extend_from_slice(ENCRYPTED_MAGIC);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    data.extend_from_slice(&tag);
+    fs::write(path, data)
+}
+
+/// JSON of all cookies in `monster`.
+fn encode(monster: &CookieMonster) -> io::Result<Vec<u8>> {
+    serde_json::to_vec_pretty(&export(monster))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// All cookies in `monster`, in their persisted form.
+pub(crate) fn export(monster: &CookieMonster) -> Vec<PersistentCookie> {
+    monster
+        .iter_all_cookies()
+        .map(|cookie| PersistentCookie {
+            expires_unix_secs: cookie.expiration_time.map(|t| t.unix_timestamp()),
             name: cookie.name,
             value: cookie.value,
             domain: cookie.domain,
@@ -43,14 +168,8 @@ pub fn save_cookies(monster: &CookieMonster, path: &Path) -> io::Result<()> {
             secure: cookie.secure,
             http_only: cookie.http_only,
             host_only: cookie.host_only,
-            expires_unix_secs: expires,
-        });
-    }
-
-    let json = serde_json::to_string_pretty(&all_cookies)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-    fs::write(path, json)
+        })
+        .collect()
 }
 
 /// Load cookies from a file into a new CookieMonster.
@@ -60,15 +179,72 @@ pub fn save_cookies(monster: &CookieMonster, path: &Path) -> io::Result<()> {
 /// let monster = persistence::load_cookies("/path/to/cookies.json")?;
 /// ```
 pub fn load_cookies(path: &Path) -> io::Result<CookieMonster> {
-    use crate::cookies::canonicalcookie::{CanonicalCookie, CookiePriority, SameSite};
-    use time::OffsetDateTime;
+    decode(&fs::read(path)?)
+}
+
+/// Load cookies saved by [`save_cookies_encrypted`] into a new CookieMonster.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the file is not encrypted,
+/// or was not encrypted with `key` or modified since.
+pub fn load_cookies_encrypted(path: &Path, key: &StorageKey) -> io::Result<CookieMonster> {
+    use boring::symm::{decrypt_aead, Cipher};
+    use zeroize::Zeroize;
 
-    let json = fs::read_to_string(path)?;
+    let data = fs::read(path)?;
+    let Some(body) = data.strip_prefix(ENCRYPTED_MAGIC) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an encrypted cookie file",
+        ));
+    };
+    if body.len() < NONCE_LEN + TAG_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "encrypted cookie file is truncated",
+        ));
+    }
+    let (nonce, rest) = body.split_at(NONCE_LEN);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+    let mut key = key.for_load()?;
+    let json = decrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key,
+        Some(nonce),
+        ENCRYPTED_MAGIC,
+        ciphertext,
+        tag,
+    );
+    key.zeroize();
+    let mut json = json.map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "cookie file does not decrypt with this key",
+        )
+    })?;
+    let monster = decode(&json);
+    json.zeroize();
+    monster
+}
+
+/// Cookies from their JSON, skipping expired ones.
+fn decode(json: &[u8]) -> io::Result<CookieMonster> {
     let persistent_cookies: Vec<PersistentCookie> =
-        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        serde_json::from_slice(json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
     let monster = CookieMonster::new();
+    import(&monster, persistent_cookies);
+    Ok(monster)
+}
+
+/// Add persisted cookies to `monster`, skipping expired ones. Returns how
+/// many were added.
+pub(crate) fn import(monster: &CookieMonster, persistent_cookies: Vec<PersistentCookie>) -> usize {
+    use crate::cookies::canonicalcookie::{CanonicalCookie, CookiePriority, SameSite};
+    use time::OffsetDateTime;
+
     let now = OffsetDateTime::now_utc();
+    let mut imported = 0;
 
     for pc in persistent_cookies {
         // Skip expired cookies
@@ -100,9 +276,10 @@ pub fn load_cookies(path: &Path) -> io::Result<CookieMonster> {
         };
 
         monster.set_canonical_cookie(cookie);
+        imported += 1;
     }
 
-    Ok(monster)
+    imported
 }
 
 #[cfg(test)]
@@ -110,8 +287,7 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
-    #[test]
-    fn test_save_load_roundtrip() {
+    fn session_monster() -> CookieMonster {
         use crate::cookies::canonicalcookie::{CanonicalCookie, CookiePriority, SameSite};
         use time::OffsetDateTime;
 
@@ -133,6 +309,12 @@ mod tests {
             same_site: SameSite::Lax,
             priority: CookiePriority::Medium,
         });
+        monster
+    }
+
+    #[test]
+    fn test_save_load_roundtrip() {
+        let monster = session_monster();
 
         // Save to temp file
         let dir = tempdir().unwrap();
@@ -153,4 +335,55 @@ mod tests {
         assert_eq!(cookies[0].name, "session");
         assert_eq!(cookies[0].value, "abc123");
     }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cookies.bin");
+        let key = StorageKey::Key([7; 32]);
+
+        save_cookies_encrypted(&session_monster(), &path, &key).unwrap();
+        let data = fs::read(&path).unwrap();
+        assert!(data.starts_with(ENCRYPTED_MAGIC));
+        assert!(!data.windows(6).any(|w| w == b"abc123"));
+
+        let loaded = load_cookies_encrypted(&path, &key).unwrap();
+        let url = url::Url::parse("https://example.com/").unwrap();
+        let cookies = loaded.get_cookies_for_url(&url);
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].value, "abc123");
+    }
+
+    #[test]
+    fn test_encrypted_rejects_wrong_key_and_tampering() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cookies.bin");
+        save_cookies_encrypted(&session_monster(), &path, &StorageKey::Key([7; 32])).unwrap();
+
+        let err = load_cookies_encrypted(&path, &StorageKey::Key([8; 32]))
+            .map(drop)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut data = fs::read(&path).unwrap();
+        let last = data.len() - TAG_LEN - 1;
+        data[last] ^= 1;
+        fs::write(&path, &data).unwrap();
+        let err = load_cookies_encrypted(&path, &StorageKey::Key([7; 32]))
+            .map(drop)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_encrypted_load_rejects_plaintext() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cookies.json");
+        save_cookies(&session_monster(), &path).unwrap();
+
+        let err = load_cookies_encrypted(&path, &StorageKey::Key([7; 32]))
+            .map(drop)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
 }
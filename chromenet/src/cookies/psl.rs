@@ -5,6 +5,7 @@
 //!
 //! Uses Mozilla's Public Suffix List via the `psl` crate.
 
+use crate::base::host::{host_key, ip_literal};
 use dashmap::DashMap;
 use psl::{List, Psl};
 use std::sync::LazyLock;
@@ -12,7 +13,8 @@ use std::sync::LazyLock;
 static PSL_CACHE: LazyLock<DashMap<String, bool>> = LazyLock::new(DashMap::new);
 
 /// Check if a domain is a public suffix (e.g., "com", "co.uk").
-/// Returns true if the domain itself is a public suffix.
+/// Returns true if the domain itself is a public suffix, never for IP
+/// literals.
 pub fn is_public_suffix(domain: &str) -> bool {
     // Fast path: Check cache for the exact input string
     if let Some(entry) = PSL_CACHE.get(domain) {
@@ -20,17 +22,19 @@ pub fn is_public_suffix(domain: &str) -> bool {
     }
 
     // Slow path: Calculate and cache
-    let domain_lower = domain.to_lowercase();
+    let domain_lower = host_key(domain);
     let domain_bytes = domain_lower.as_bytes();
 
-    let result = if let Some(suffix) = List.suffix(domain_bytes) {
+    let result = if ip_literal(&domain_lower).is_some() {
+        false
+    } else if let Some(suffix) = List.suffix(domain_bytes) {
         suffix.as_bytes() == domain_bytes
     } else {
         false
     };
 
     // Cache the result for next time.
-    // We cache the input string (avoiding normalization on hits) at the cost of duplicate entries for mixed-case variants.
+    // We cache the input string (avoiding normalization on hits) at the cost of duplicate entries for differently spelled variants.
     PSL_CACHE.insert(domain.to_string(), result);
     result
 }
@@ -38,9 +42,12 @@ pub fn is_public_suffix(domain: &str) -> bool {
 /// Get the registrable domain (eTLD+1) for a domain.
 /// For "sub.example.com", returns "example.com".
 /// For "example.com", returns "example.com".
-/// For "com" (public suffix), returns None.
+/// For "com" (public suffix) and IP literals, returns None.
 pub fn registrable_domain(domain: &str) -> Option<String> {
-    let domain_lower = domain.to_lowercase();
+    let domain_lower = host_key(domain);
+    if ip_literal(&domain_lower).is_some() {
+        return None;
+    }
     psl::domain(domain_lower.as_bytes())
         .and_then(|d| std::str::from_utf8(d.as_bytes()).ok())
         .map(|s| s.to_string())
@@ -48,12 +55,17 @@ pub fn registrable_domain(domain: &str) -> Option<String> {
 
 /// Check if a cookie domain is valid for a given URL.
 /// The cookie domain must be a suffix of the URL's host and
-/// must not be a public suffix.
+/// must not be a public suffix. An IP literal host only accepts itself.
 pub fn is_valid_cookie_domain(cookie_domain: &str, url_host: &str) -> bool {
     // Remove leading dot from cookie domain if present
     let cookie_domain = cookie_domain.strip_prefix('.').unwrap_or(cookie_domain);
-    let cookie_domain_lower = cookie_domain.to_lowercase();
-    let url_host_lower = url_host.to_lowercase();
+    let cookie_domain_lower = host_key(cookie_domain);
+    let url_host_lower = host_key(url_host);
+
+    // IP literals have no parent domains
+    if ip_literal(&url_host_lower).is_some() || ip_literal(&cookie_domain_lower).is_some() {
+        return url_host_lower == cookie_domain_lower;
+    }
 
     // 1. Cookie domain must not be a public suffix
     if is_public_suffix(&cookie_domain_lower) {
@@ -154,4 +166,17 @@ mod tests {
     fn test_invalid_cookie_domain_mismatch() {
         assert!(!is_valid_cookie_domain("other.com", "example.com"));
     }
+
+    #[test]
+    fn test_ip_literals() {
+        assert_eq!(registrable_domain("10.0.0.5"), None);
+        assert_eq!(registrable_domain("[::1]"), None);
+        assert!(!is_public_suffix("10.0.0.5"));
+
+        assert!(is_valid_cookie_domain("10.0.0.5", "10.0.0.5"));
+        assert!(is_valid_cookie_domain("[::1]", "[::1]"));
+        // Trailing labels of an address are not its parent domains
+        assert!(!is_valid_cookie_domain("0.0.5", "10.0.0.5"));
+        assert!(!is_valid_cookie_domain("10.0.0.5", "host.10.0.0.5"));
+    }
 }
@@ -0,0 +1,172 @@
+//! SameSite context of a request.
+//!
+//! Chromium mapping: `CookieOptions::SameSiteCookieContext`,
+//! `SiteForCookies` and `cookie_util::ComputeSameSiteContextForRequest`
+//!
+//! Whether `SameSite=Strict` and `SameSite=Lax` cookies go with a request
+//! depends on the site it is made from, its site for cookies, and on every
+//! URL of its redirect chain (RFC 6265bis 5.2). The context is computed
+//! again for each hop, so a cross-site redirect in the middle of a chain
+//! withholds `Strict` cookies from the rest of it:
+//!
+//! | Request | Chain same-site with the site for cookies | Otherwise |
+//! |---------|-------------------------------------------|-----------|
+//! | Top-level, `GET` or `HEAD` | [`Strict`](SameSiteContext::Strict) | [`Lax`](SameSiteContext::Lax) |
+//! | Top-level, other methods | `Strict` | [`CrossSite`](SameSiteContext::CrossSite) |
+//! | From a site | `Strict` | `CrossSite` |
+//!
+//! A request without a site for cookies is a top-level navigation: its
+//! site is that of the URL it is currently sent to. Sites are schemeful,
+//! so `http://example.com` and `https://example.com` are different sites.
+
+use crate::base::host::url_host;
+use crate::cookies::canonicalcookie::SameSite;
+use crate::cookies::psl::registrable_domain;
+use http::Method;
+use url::Url;
+
+/// How much of the request's chain is same-site, deciding which
+/// `SameSite` cookies are sent and stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum SameSiteContext {
+    /// Only `SameSite=None` cookies are sent and stored.
+    CrossSite,
+    /// `Lax` and unspecified cookies are sent too, and `Strict` ones
+    /// stored.
+    Lax,
+    /// Every cookie is sent and stored. The context of requests made
+    /// without a site for cookies and never redirected off their site.
+    #[default]
+    Strict,
+}
+
+impl SameSiteContext {
+    /// The context of a request to the last URL of `url_chain`, the URLs
+    /// it was sent to in order, made from `site_for_cookies` or as a
+    /// top-level navigation.
+    pub fn for_request(url_chain: &[Url], site_for_cookies: Option<&Url>, method: &Method) -> Self {
+        let Some(current) = url_chain.last() else {
+            return Self::Strict;
+        };
+        let site = site_for_cookies.unwrap_or(current);
+        if url_chain.iter().all(|url| is_same_site(url, site)) {
+            Self::Strict
+        } else if site_for_cookies.is_none() && method.is_safe() {
+            Self::Lax
+        } else {
+            Self::CrossSite
+        }
+    }
+
+    /// Whether a cookie with `same_site` is sent in this context.
+    /// Unspecified cookies are treated as `Lax`, as in Chromium.
+    pub fn includes(self, same_site: SameSite) -> bool {
+        match same_site {
+            SameSite::NoRestriction => true,
+            SameSite::Lax | SameSite::Unspecified => self >= Self::Lax,
+            SameSite::Strict => self == Self::Strict,
+        }
+    }
+
+    /// Whether a cookie with `same_site` may be stored from a response in
+    /// this context. A `Lax` context, a top-level navigation, stores
+    /// `Strict` cookies too.
+    pub fn allows_set(self, same_site: SameSite) -> bool {
+        same_site == SameSite::NoRestriction || self >= Self::Lax
+    }
+}
+
+/// Whether `a` and `b` are the same site: the same scheme, `ws` and `wss`
+/// counting as `http` and `https`, and the same registrable domain, or
+/// host for IP literals and hosts without one.
+pub fn is_same_site(a: &Url, b: &Url) -> bool {
+    fn scheme(url: &Url) -> &str {
+        match url.scheme() {
+            "ws" => "http",
+            "wss" => "https",
+            scheme => scheme,
+        }
+    }
+    fn site(url: &Url) -> Option<String> {
+        let host = url_host(url)?;
+        Some(registrable_domain(&host).unwrap_or(host))
+    }
+    scheme(a) == scheme(b) && site(a).is_some() && site(a) == site(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(urls: &[&str]) -> Vec<Url> {
+        urls.iter().map(|url| Url::parse(url).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_same_site() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert!(is_same_site(
+            &url("https://a.example.com/"),
+            &url("https://b.example.com:8443/x")
+        ));
+        assert!(is_same_site(
+            &url("wss://example.com/"),
+            &url("https://example.com/")
+        ));
+        assert!(!is_same_site(
+            &url("http://example.com/"),
+            &url("https://example.com/")
+        ));
+        assert!(!is_same_site(
+            &url("https://a.github.io/"),
+            &url("https://b.github.io/")
+        ));
+        assert!(!is_same_site(
+            &url("http://127.0.0.1/"),
+            &url("http://localhost/")
+        ));
+    }
+
+    #[test]
+    fn test_context_per_hop() {
+        let get = Method::GET;
+        let same = chain(&["https://example.com/a", "https://www.example.com/b"]);
+        assert_eq!(
+            SameSiteContext::for_request(&same, None, &get),
+            SameSiteContext::Strict
+        );
+
+        // Bounced through another site and back
+        let bounced = chain(&[
+            "https://example.com/a",
+            "https://tracker.test/r",
+            "https://example.com/b",
+        ]);
+        assert_eq!(
+            SameSiteContext::for_request(&bounced, None, &get),
+            SameSiteContext::Lax
+        );
+        assert_eq!(
+            SameSiteContext::for_request(&bounced, None, &Method::POST),
+            SameSiteContext::CrossSite
+        );
+
+        // Made from a site
+        let site = Url::parse("https://example.com/").unwrap();
+        assert_eq!(
+            SameSiteContext::for_request(&same, Some(&site), &get),
+            SameSiteContext::Strict
+        );
+        assert_eq!(
+            SameSiteContext::for_request(&bounced, Some(&site), &get),
+            SameSiteContext::CrossSite
+        );
+
+        assert!(SameSiteContext::Lax.includes(SameSite::Unspecified));
+        assert!(!SameSiteContext::Lax.includes(SameSite::Strict));
+        assert!(SameSiteContext::Lax.allows_set(SameSite::Strict));
+        assert!(!SameSiteContext::CrossSite.includes(SameSite::Lax));
+        assert!(SameSiteContext::CrossSite.includes(SameSite::NoRestriction));
+        assert!(!SameSiteContext::CrossSite.allows_set(SameSite::Unspecified));
+    }
+}
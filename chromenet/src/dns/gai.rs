@@ -9,12 +9,25 @@
 //! - When DoH/DoT is not required
 //! - As a fallback when hickory-dns is not available
 
-use super::{Addrs, Name, Resolve, Resolving};
+use super::{Addrs, DnsMetrics, Name, Resolve, Resolving};
 use crate::base::neterror::NetError;
 use std::{
     io,
     net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
+    sync::Arc,
+    time::Duration,
 };
+use tokio::sync::Semaphore;
+use tokio::time::{timeout_at, Instant};
+
+/// Lookups run at once by default (Chromium's `kDefaultMaxSystemTasks`).
+pub const DEFAULT_MAX_CONCURRENT_LOOKUPS: usize = 6;
+
+/// Lookups allowed to wait for a free thread by default.
+pub const DEFAULT_MAX_QUEUED_LOOKUPS: usize = 256;
+
+/// Default time allowed for a lookup, including time spent queued.
+pub const DEFAULT_LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// System DNS resolver using `getaddrinfo` in a thread pool.
 ///
@@ -22,42 +35,132 @@ use std::{
 /// executes resolution in `tokio::task::spawn_blocking` to avoid blocking
 /// the async runtime.
 ///
+/// # Concurrency
+///
+/// At most [`DEFAULT_MAX_CONCURRENT_LOOKUPS`] lookups occupy a blocking
+/// thread at a time; further lookups wait in a queue, and once that holds
+/// [`DEFAULT_MAX_QUEUED_LOOKUPS`] new ones fail with
+/// [`NetError::HostResolverQueueTooLarge`]. A lookup that takes longer than
+/// the timeout fails with [`NetError::DnsTimedOut`]. `getaddrinfo` cannot be
+/// cancelled, so a timed-out lookup keeps its thread until it returns.
+/// Clones share the limits and [`metrics`](Self::metrics).
+///
 /// # Performance
 ///
-/// Each resolution spawns a blocking task. For high-throughput scenarios,
-/// consider using `HickoryResolver` which is fully async.
-#[derive(Clone, Debug, Default)]
-pub struct GaiResolver;
+/// Each resolution occupies a blocking thread. For high-throughput
+/// scenarios, consider using `HickoryResolver` which is fully async.
+#[derive(Clone, Debug)]
+pub struct GaiResolver {
+    slots: Arc<Semaphore>,
+    max_queued: usize,
+    timeout: Duration,
+    metrics: DnsMetrics,
+}
+
+impl Default for GaiResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl GaiResolver {
-    /// Creates a new `GaiResolver`.
+    /// Creates a new `GaiResolver` with the default limits.
     pub fn new() -> Self {
-        Self
+        Self {
+            slots: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_LOOKUPS)),
+            max_queued: DEFAULT_MAX_QUEUED_LOOKUPS,
+            timeout: DEFAULT_LOOKUP_TIMEOUT,
+            metrics: DnsMetrics::new(),
+        }
+    }
+
+    /// Run at most `max` lookups at once.
+    pub fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.slots = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    /// Let at most `max` lookups wait for a free thread.
+    pub fn with_max_queued(mut self, max: usize) -> Self {
+        self.max_queued = max;
+        self
+    }
+
+    /// Fail lookups that take longer than `timeout`, queueing included.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Counters for this resolver and its clones.
+    pub fn metrics(&self) -> &DnsMetrics {
+        &self.metrics
     }
 }
 
 impl Resolve for GaiResolver {
     fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
         Box::pin(async move {
             let host = name.as_str().to_string();
             let domain = host.clone();
-
-            let result = tokio::task::spawn_blocking(move || {
+            let metrics = resolver.metrics.clone();
+            let deadline = Instant::now() + resolver.timeout;
+            metrics.record_lookup();
+            let _timer = metrics.time_lookup();
+
+            // Take a thread slot, queueing only while the queue has room
+            let permit = match resolver.slots.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    let Some(_queued) = metrics.try_enqueue(resolver.max_queued) else {
+                        tracing::debug!(domain = %domain, "DNS resolver queue full");
+                        metrics.record_rejected();
+                        return Err(NetError::HostResolverQueueTooLarge);
+                    };
+                    match timeout_at(deadline, resolver.slots.clone().acquire_owned()).await {
+                        Ok(Ok(permit)) => permit,
+                        Ok(Err(_)) => {
+                            metrics.record_failure();
+                            return Err(NetError::NameNotResolved);
+                        }
+                        Err(_) => {
+                            tracing::debug!(domain = %domain, "DNS lookup timed out while queued");
+                            metrics.record_timeout();
+                            return Err(NetError::DnsTimedOut);
+                        }
+                    }
+                }
+            };
+
+            metrics.record_queries(1);
+            let in_flight = metrics.in_flight();
+            let task = tokio::task::spawn_blocking(move || {
+                // Held until getaddrinfo returns, even if the caller gave up
+                let _permit = permit;
+                let _in_flight = in_flight;
                 tracing::debug!(host = %host, "resolving via getaddrinfo");
-                (host.as_str(), 0u16)
+                // Names keep IPv6 literals in brackets
+                (host.trim_start_matches('[').trim_end_matches(']'), 0u16)
                     .to_socket_addrs()
                     .map(|iter| iter.collect::<Vec<_>>())
-            })
-            .await;
+            });
+            let Ok(result) = timeout_at(deadline, task).await else {
+                tracing::debug!(domain = %domain, timeout = ?resolver.timeout, "DNS lookup timed out");
+                metrics.record_timeout();
+                return Err(NetError::DnsTimedOut);
+            };
 
             // Handle task join error (cancellation, panic)
             let addrs = result
                 .map_err(|e| {
                     tracing::error!(error = %e, "DNS resolution task failed");
+                    metrics.record_failure();
                     NetError::NameNotResolved
                 })?
                 .map_err(|e| {
                     tracing::debug!(domain = %domain, error = %e, "DNS resolution failed");
+                    metrics.record_failure();
                     NetError::NameNotResolvedFor {
                         domain: domain.clone(),
                         source: std::sync::Arc::new(e),
@@ -65,6 +168,7 @@ impl Resolve for GaiResolver {
                 })?;
 
             if addrs.is_empty() {
+                metrics.record_failure();
                 return Err(NetError::NameNotResolvedFor {
                     domain,
                     source: std::sync::Arc::new(io::Error::new(
@@ -237,6 +341,30 @@ mod tests {
         assert!(fallback.addrs[0].is_ipv6());
     }
 
+    #[tokio::test]
+    async fn test_gai_resolver_rejects_when_queue_full() {
+        let resolver = GaiResolver::new().with_max_concurrent(1).with_max_queued(0);
+        // Occupy the only slot
+        let _slot = resolver.slots.clone().try_acquire_owned().unwrap();
+
+        let result = resolver.resolve(Name::new("localhost")).await;
+        assert!(matches!(result, Err(NetError::HostResolverQueueTooLarge)));
+        assert_eq!(resolver.metrics().snapshot().rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_gai_resolver_times_out_while_queued() {
+        let resolver = GaiResolver::new()
+            .with_max_concurrent(1)
+            .with_timeout(Duration::from_millis(20));
+        let _slot = resolver.slots.clone().try_acquire_owned().unwrap();
+
+        let result = resolver.resolve(Name::new("localhost")).await;
+        assert!(matches!(result, Err(NetError::DnsTimedOut)));
+        let snapshot = resolver.metrics().snapshot();
+        assert_eq!((snapshot.timeouts, snapshot.queued), (1, 0));
+    }
+
     #[tokio::test]
     async fn test_gai_resolver_localhost() {
         let resolver = GaiResolver::new();
@@ -4,7 +4,8 @@
 //! - DNS-over-HTTPS (DoH)
 //! - DNS-over-TLS (DoT)
 //! - System DNS configuration auto-detection
-//! - Happy Eyeballs (IPv4 + IPv6 lookup)
+//! - Happy Eyeballs (A and AAAA queries issued in parallel)
+//! - Typed TXT, MX, SRV and HTTPS record lookups
 //!
 //! # Performance
 //!
@@ -12,14 +13,20 @@
 //! spawning blocking tasks. It maintains connection pools to DNS servers
 //! for better performance under load.
 
-use super::{Addrs, Name, Resolve, Resolving};
+use super::records::{HttpsRecord, MxRecord, SrvRecord, TxtRecord};
+use super::{Addrs, DnsMetrics, Name, Resolve, Resolving};
 use crate::base::neterror::NetError;
 use hickory_resolver::{
     config::{LookupIpStrategy, ResolverConfig},
     name_server::TokioConnectionProvider,
-    TokioResolver,
+    proto::rr::{RData, RecordType},
+    ResolveError, TokioResolver,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::LazyLock,
+    time::Duration,
 };
-use std::{net::SocketAddr, sync::LazyLock};
 
 /// Async DNS resolver backed by hickory-dns.
 ///
@@ -31,8 +38,12 @@ use std::{net::SocketAddr, sync::LazyLock};
 ///
 /// - Fully async (no blocking threads)
 /// - Automatic system configuration detection
-/// - IPv4 and IPv6 dual-stack resolution
+/// - IPv4 and IPv6 dual-stack resolution, with A and AAAA queries in
+///   parallel; either family answering is enough
 /// - Connection pooling to DNS servers
+/// - Optional overall timeout per lookup ([`with_timeout`](Self::with_timeout))
+/// - TXT, MX, SRV and HTTPS records ([`lookup_txt`](Self::lookup_txt) and
+///   its siblings)
 ///
 /// # Example
 ///
@@ -45,6 +56,8 @@ use std::{net::SocketAddr, sync::LazyLock};
 #[derive(Debug, Clone)]
 pub struct HickoryResolver {
     resolver: &'static LazyLock<TokioResolver>,
+    timeout: Option<Duration>,
+    metrics: DnsMetrics,
 }
 
 impl HickoryResolver {
@@ -80,6 +93,144 @@ impl HickoryResolver {
 
         Self {
             resolver: &RESOLVER,
+            timeout: None,
+            metrics: DnsMetrics::new(),
+        }
+    }
+
+    /// Fail lookups that take longer than `timeout` with
+    /// [`NetError::DnsTimedOut`].
+    ///
+    /// Without one, only hickory's per-query timeouts and retries apply.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Counters for this resolver and its clones.
+    pub fn metrics(&self) -> &DnsMetrics {
+        &self.metrics
+    }
+
+    /// TXT records of `domain`, e.g. for domain verification or SPF.
+    ///
+    /// A name without TXT records answers an empty list; one that does not
+    /// exist fails with [`NetError::NameNotResolvedFor`].
+    pub async fn lookup_txt(&self, domain: &str) -> Result<Vec<TxtRecord>, NetError> {
+        let records = self.lookup_records(domain, RecordType::TXT).await?;
+        Ok(records
+            .iter()
+            .filter_map(|rdata| match rdata {
+                RData::TXT(txt) => Some(TxtRecord::from_rdata(txt)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// MX records of `domain`, most preferred first.
+    pub async fn lookup_mx(&self, domain: &str) -> Result<Vec<MxRecord>, NetError> {
+        let records = self.lookup_records(domain, RecordType::MX).await?;
+        let mut mx: Vec<_> = records
+            .iter()
+            .filter_map(|rdata| match rdata {
+                RData::MX(mx) => Some(MxRecord::from_rdata(mx)),
+                _ => None,
+            })
+            .collect();
+        mx.sort_by_key(|record| record.preference);
+        Ok(mx)
+    }
+
+    /// SRV records of `domain`, e.g. `_imaps._tcp.example.com`, by
+    /// priority. Choosing by weight within a priority is up to the caller.
+    pub async fn lookup_srv(&self, domain: &str) -> Result<Vec<SrvRecord>, NetError> {
+        let records = self.lookup_records(domain, RecordType::SRV).await?;
+        let mut srv: Vec<_> = records
+            .iter()
+            .filter_map(|rdata| match rdata {
+                RData::SRV(srv) => Some(SrvRecord::from_rdata(srv)),
+                _ => None,
+            })
+            .collect();
+        srv.sort_by_key(|record| record.priority);
+        Ok(srv)
+    }
+
+    /// HTTPS records of `domain`, by priority, aliases first.
+    pub async fn lookup_https(&self, domain: &str) -> Result<Vec<HttpsRecord>, NetError> {
+        let records = self.lookup_records(domain, RecordType::HTTPS).await?;
+        let mut https: Vec<_> = records
+            .iter()
+            .filter_map(|rdata| match rdata {
+                RData::HTTPS(https) => Some(HttpsRecord::from_rdata(https)),
+                _ => None,
+            })
+            .collect();
+        https.sort_by_key(|record| record.priority);
+        Ok(https)
+    }
+
+    /// Query `record_type` records of `domain`, counted and timed out like
+    /// address lookups.
+    async fn lookup_records(
+        &self,
+        domain: &str,
+        record_type: RecordType,
+    ) -> Result<Vec<RData>, NetError> {
+        tracing::debug!(domain = %domain, %record_type, "querying via hickory-dns");
+        let metrics = &self.metrics;
+        metrics.record_lookup();
+        metrics.record_queries(1);
+        let _in_flight = metrics.in_flight();
+        let _timer = metrics.time_lookup();
+        let query = self.resolver.lookup(domain, record_type);
+        let lookup = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, query).await.map_err(|_| {
+                tracing::debug!(domain = %domain, %record_type, timeout = ?timeout, "hickory-dns query timed out");
+                metrics.record_timeout();
+                NetError::DnsTimedOut
+            })?,
+            None => query.await,
+        };
+        match lookup {
+            Ok(lookup) => Ok(lookup.iter().cloned().collect()),
+            // The name exists, without records of the type
+            Err(e) if e.is_no_records_found() && !e.is_nx_domain() => Ok(Vec::new()),
+            Err(e) => {
+                tracing::debug!(domain = %domain, %record_type, error = %e, "hickory-dns query failed");
+                record_error(metrics, &e);
+                Err(not_resolved(domain, e.to_string()))
+            }
+        }
+    }
+
+    /// Query A and AAAA records in parallel.
+    ///
+    /// Succeeds with whatever family answered; fails with the A error if
+    /// neither did.
+    async fn lookup_both(&self, domain: &str) -> Result<Vec<SocketAddr>, ResolveError> {
+        self.metrics.record_queries(2);
+        let (v4, v6) = tokio::join!(
+            self.resolver.ipv4_lookup(domain),
+            self.resolver.ipv6_lookup(domain)
+        );
+
+        let mut addrs: Vec<SocketAddr> = Vec::new();
+        let v4_err = match v4 {
+            Ok(lookup) => {
+                addrs.extend(lookup.iter().map(|a| SocketAddr::new(IpAddr::V4(a.0), 0)));
+                None
+            }
+            Err(e) => Some(e),
+        };
+        match v6 {
+            Ok(lookup) => addrs.extend(lookup.iter().map(|a| SocketAddr::new(IpAddr::V6(a.0), 0))),
+            Err(e) => tracing::trace!(domain = %domain, error = %e, "AAAA lookup failed"),
+        }
+
+        match v4_err {
+            Some(e) if addrs.is_empty() => Err(e),
+            _ => Ok(addrs),
         }
     }
 }
@@ -97,33 +248,67 @@ impl Resolve for HickoryResolver {
             let domain = name.as_str();
             tracing::debug!(domain = %domain, "resolving via hickory-dns");
 
-            let lookup = resolver.resolver.lookup_ip(domain).await.map_err(|e| {
+            // IP literals need no query
+            if let Ok(ip) = domain
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+            {
+                return Ok(Box::new(std::iter::once(SocketAddr::new(ip, 0))) as Addrs);
+            }
+
+            let metrics = &resolver.metrics;
+            metrics.record_lookup();
+            let _in_flight = metrics.in_flight();
+            let _timer = metrics.time_lookup();
+            let lookup = match resolver.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, resolver.lookup_both(domain))
+                    .await
+                    .map_err(|_| {
+                        tracing::debug!(domain = %domain, timeout = ?timeout, "hickory-dns lookup timed out");
+                        metrics.record_timeout();
+                        NetError::DnsTimedOut
+                    })?,
+                None => resolver.lookup_both(domain).await,
+            };
+            let addrs = lookup.map_err(|e| {
                 tracing::debug!(domain = %domain, error = %e, "hickory-dns lookup failed");
-                NetError::NameNotResolvedFor {
-                    domain: domain.to_string(),
-                    source: std::sync::Arc::new(std::io::Error::new(
-                        std::io::ErrorKind::NotFound,
-                        e.to_string(),
-                    )),
-                }
+                record_error(metrics, &e);
+                not_resolved(domain, e.to_string())
             })?;
 
-            let addrs: Vec<SocketAddr> = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
-
             if addrs.is_empty() {
-                return Err(NetError::NameNotResolvedFor {
-                    domain: domain.to_string(),
-                    source: std::sync::Arc::new(std::io::Error::new(
-                        std::io::ErrorKind::NotFound,
-                        "No addresses returned",
-                    )),
-                });
+                metrics.record_failure();
+                return Err(not_resolved(domain, "No addresses returned".to_string()));
             }
 
             tracing::debug!(domain = %domain, count = addrs.len(), "hickory-dns resolution complete");
             Ok(Box::new(addrs.into_iter()) as Addrs)
         })
     }
+
+    /// hickory's cache only clears whole, and is shared by every
+    /// `HickoryResolver`: single names are left to expire.
+    fn forget_all(&self) {
+        self.resolver.clear_cache();
+    }
+}
+
+/// Count a failed lookup, telling names that do not exist apart.
+fn record_error(metrics: &DnsMetrics, error: &ResolveError) {
+    if error.is_nx_domain() {
+        metrics.record_nxdomain();
+    } else {
+        metrics.record_failure();
+    }
+}
+
+/// [`NetError::NameNotResolvedFor`] `domain`, for `message`.
+fn not_resolved(domain: &str, message: String) -> NetError {
+    NetError::NameNotResolvedFor {
+        domain: domain.to_string(),
+        source: std::sync::Arc::new(std::io::Error::new(std::io::ErrorKind::NotFound, message)),
+    }
 }
 
 #[cfg(test)]
@@ -158,6 +343,34 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_hickory_resolver_ip_literal_skips_queries() {
+        let resolver = HickoryResolver::new();
+        let addrs: Vec<_> = resolver
+            .resolve(Name::new("[::1]"))
+            .await
+            .unwrap()
+            .collect();
+        assert_eq!(
+            addrs,
+            vec![SocketAddr::new(
+                IpAddr::from([0u16, 0, 0, 0, 0, 0, 0, 1]),
+                0
+            )]
+        );
+        assert_eq!(resolver.metrics().snapshot().queries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hickory_record_lookup_invalid_domain() {
+        let resolver = HickoryResolver::new();
+        let result = resolver
+            .lookup_txt("this-domain-definitely-does-not-exist.invalid")
+            .await;
+        assert!(matches!(result, Err(NetError::NameNotResolvedFor { .. })));
+        assert_eq!(resolver.metrics().snapshot().queries, 1);
+    }
+
     #[test]
     fn test_hickory_resolver_is_clone() {
         let r1 = HickoryResolver::new();
@@ -0,0 +1,258 @@
+//! Cache of recent host resolutions.
+//!
+//! Chromium mapping: `net::HostCache`
+//!
+//! [`HostCache`] wraps a resolver and answers repeated lookups of a name
+//! from memory. Entries live for a minute by default, what Chromium uses for
+//! system resolver results, which carry no TTL. Failures are not cached.
+//!
+//! Its [`metrics`](HostCache::metrics) count every lookup, the cache hits
+//! and the cached names, and time the misses; the wrapped resolver's own
+//! metrics count the queries those misses sent.
+
+use super::{Addrs, DnsMetrics, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long an answer is reused, like Chromium's `kCacheEntryTTLSeconds`.
+pub const DEFAULT_HOST_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Names kept at most, like Chromium's `kMaxHostCacheEntries`.
+pub const DEFAULT_MAX_HOST_CACHE_ENTRIES: usize = 1000;
+
+/// Resolver answering repeated lookups from recent results.
+///
+/// Clones share the cache and the metrics.
+///
+/// ```rust,ignore
+/// use chromenet::dns::{HickoryResolver, HostCache};
+/// use std::sync::Arc;
+///
+/// let doh = HickoryResolver::new();
+/// let cache = HostCache::new(Arc::new(doh.clone()));
+/// let client = chromenet::Client::builder().dns_resolver(cache.clone()).build();
+/// // ...
+/// println!("{:?} {:?}", cache.metrics().snapshot(), doh.metrics().snapshot());
+/// ```
+#[derive(Clone)]
+pub struct HostCache {
+    inner: Arc<dyn Resolve>,
+    entries: Arc<Mutex<HashMap<Name, Entry>>>,
+    ttl: Duration,
+    max_entries: usize,
+    metrics: DnsMetrics,
+}
+
+struct Entry {
+    addrs: Vec<SocketAddr>,
+    expires: Instant,
+}
+
+impl HostCache {
+    /// Cache the answers of `inner`.
+    pub fn new(inner: Arc<dyn Resolve>) -> Self {
+        Self {
+            inner,
+            entries: Arc::default(),
+            ttl: DEFAULT_HOST_CACHE_TTL,
+            max_entries: DEFAULT_MAX_HOST_CACHE_ENTRIES,
+            metrics: DnsMetrics::new(),
+        }
+    }
+
+    /// Reuse answers for `ttl` instead of a minute.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Keep at most `max_entries` names, evicting the ones expiring first.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Counters for this cache and its clones.
+    pub fn metrics(&self) -> &DnsMetrics {
+        &self.metrics
+    }
+
+    /// Names cached, expired ones not yet evicted included.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget every answer, e.g. after a network change.
+    pub fn clear(&self) {
+        self.lock().clear();
+        self.metrics.set_cache_entries(0);
+    }
+
+    /// Forget the answer for `name`, e.g. when the host moved. Returns
+    /// whether one was cached.
+    pub fn remove(&self, name: &Name) -> bool {
+        let mut entries = self.lock();
+        let removed = entries.remove(name).is_some();
+        self.metrics.set_cache_entries(entries.len());
+        removed
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Name, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The unexpired answer for `name`.
+    fn get(&self, name: &Name) -> Option<Vec<SocketAddr>> {
+        let mut entries = self.lock();
+        let entry = entries.get(name)?;
+        if entry.expires > Instant::now() {
+            return Some(entry.addrs.clone());
+        }
+        entries.remove(name);
+        self.metrics.set_cache_entries(entries.len());
+        None
+    }
+
+    fn insert(&self, name: Name, addrs: Vec<SocketAddr>) {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        if entries.len() >= self.max_entries && !entries.contains_key(&name) {
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires)
+                    .map(|(name, _)| name.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(
+            name,
+            Entry {
+                addrs,
+                expires: now + self.ttl,
+            },
+        );
+        self.metrics.set_cache_entries(entries.len());
+    }
+}
+
+impl Resolve for HostCache {
+    fn resolve(&self, name: Name) -> Resolving {
+        self.metrics.record_lookup();
+        if let Some(addrs) = self.get(&name) {
+            self.metrics.record_cache_hit();
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            return Box::pin(std::future::ready(Ok(addrs)));
+        }
+        let cache = self.clone();
+        Box::pin(async move {
+            let _timer = cache.metrics.time_lookup();
+            let addrs: Vec<_> = match cache.inner.resolve(name.clone()).await {
+                Ok(addrs) => addrs.collect(),
+                Err(e) => {
+                    cache.metrics.record_failure();
+                    return Err(e);
+                }
+            };
+            if !addrs.is_empty() {
+                cache.insert(name, addrs.clone());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+
+    fn forget(&self, name: &Name) {
+        self.remove(name);
+        self.inner.forget(name);
+    }
+
+    fn forget_all(&self) {
+        self.clear();
+        self.inner.forget_all();
+    }
+}
+
+impl fmt::Debug for HostCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostCache")
+            .field("entries", &self.len())
+            .field("ttl", &self.ttl)
+            .field("max_entries", &self.max_entries)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Answers every name with 192.0.2.1, counting lookups.
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    impl Resolve for Counting {
+        fn resolve(&self, _name: Name) -> Resolving {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let addrs: Addrs = Box::new(std::iter::once("192.0.2.1:0".parse().unwrap()));
+            Box::pin(std::future::ready(Ok(addrs)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_lookups_hit_the_cache() {
+        let inner = Arc::new(Counting::default());
+        let cache = HostCache::new(inner.clone());
+        for _ in 0..3 {
+            let addrs: Vec<_> = cache.resolve(Name::new("a.test")).await.unwrap().collect();
+            assert_eq!(addrs, ["192.0.2.1:0".parse::<SocketAddr>().unwrap()]);
+        }
+        cache.resolve(Name::new("b.test")).await.unwrap();
+
+        assert_eq!(inner.0.load(Ordering::SeqCst), 2);
+        let snapshot = cache.metrics().snapshot();
+        assert_eq!(snapshot.lookups, 4);
+        assert_eq!(snapshot.cache_hits, 2);
+        assert_eq!(snapshot.cache_entries, 2);
+        assert_eq!(snapshot.timed, 2);
+
+        cache.forget(&Name::new("A.test."));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.metrics().snapshot().cache_entries, 1);
+        cache.resolve(Name::new("a.test")).await.unwrap();
+        assert_eq!(inner.0.load(Ordering::SeqCst), 3);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.metrics().snapshot().cache_entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_and_evict() {
+        let inner = Arc::new(Counting::default());
+        let cache = HostCache::new(inner.clone())
+            .with_ttl(Duration::ZERO)
+            .with_max_entries(2);
+        cache.resolve(Name::new("a.test")).await.unwrap();
+        cache.resolve(Name::new("a.test")).await.unwrap();
+        assert_eq!(inner.0.load(Ordering::SeqCst), 2);
+
+        let cache = HostCache::new(inner.clone()).with_max_entries(2);
+        for name in ["a.test", "b.test", "c.test"] {
+            cache.resolve(Name::new(name)).await.unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&Name::new("a.test")).is_none());
+        assert!(cache.get(&Name::new("c.test")).is_some());
+    }
+}
@@ -0,0 +1,245 @@
+//! Resolver counters.
+//!
+//! Shared by the built-in resolvers and the [`HostCache`](super::HostCache)
+//! so callers can see whether lookups are queueing, timing out, failing or
+//! slow, and how often the cache answers. With the `prometheus` feature,
+//! `DnsMetrics::encode_prometheus` renders them for a `/metrics` endpoint.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Live counters of a resolver. Clones share the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct DnsMetrics {
+    inner: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    lookups: AtomicU64,
+    queries: AtomicU64,
+    failures: AtomicU64,
+    timeouts: AtomicU64,
+    rejected: AtomicU64,
+    nxdomain: AtomicU64,
+    cache_hits: AtomicU64,
+    timed: AtomicU64,
+    lookup_nanos: AtomicU64,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    cache_entries: AtomicUsize,
+}
+
+/// Point-in-time copy of [`DnsMetrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DnsMetricsSnapshot {
+    /// Hostname lookups started.
+    pub lookups: u64,
+    /// DNS queries sent; parallel A and AAAA queries count separately.
+    pub queries: u64,
+    /// Lookups that failed, including timeouts.
+    pub failures: u64,
+    /// Lookups that exceeded the resolver's timeout.
+    pub timeouts: u64,
+    /// Lookups refused because the queue was full.
+    pub rejected: u64,
+    /// Lookups of names that do not exist (NXDOMAIN), also counted as
+    /// failures. Only resolvers that can tell count them:
+    /// `HickoryResolver` does, getaddrinfo does not say.
+    pub nxdomain: u64,
+    /// Lookups a [`HostCache`](super::HostCache) answered without asking
+    /// its resolver.
+    pub cache_hits: u64,
+    /// Lookups that finished or were given up on, timed in `lookup_time`.
+    pub timed: u64,
+    /// Total time of the `timed` lookups, queueing included.
+    pub lookup_time: Duration,
+    /// Lookups currently running.
+    pub in_flight: usize,
+    /// Lookups waiting for a free slot.
+    pub queued: usize,
+    /// Names a [`HostCache`](super::HostCache) holds.
+    pub cache_entries: usize,
+}
+
+impl DnsMetricsSnapshot {
+    /// Mean time of a lookup, `None` before the first one finished.
+    pub fn mean_lookup_time(&self) -> Option<Duration> {
+        (self.timed > 0).then(|| {
+            Duration::from_nanos((self.lookup_time.as_nanos() / self.timed as u128) as u64)
+        })
+    }
+}
+
+impl DnsMetrics {
+    /// Create zeroed counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current values.
+    pub fn snapshot(&self) -> DnsMetricsSnapshot {
+        let c = &self.inner;
+        DnsMetricsSnapshot {
+            lookups: c.lookups.load(Ordering::Relaxed),
+            queries: c.queries.load(Ordering::Relaxed),
+            failures: c.failures.load(Ordering::Relaxed),
+            timeouts: c.timeouts.load(Ordering::Relaxed),
+            rejected: c.rejected.load(Ordering::Relaxed),
+            nxdomain: c.nxdomain.load(Ordering::Relaxed),
+            cache_hits: c.cache_hits.load(Ordering::Relaxed),
+            timed: c.timed.load(Ordering::Relaxed),
+            lookup_time: Duration::from_nanos(c.lookup_nanos.load(Ordering::Relaxed)),
+            in_flight: c.in_flight.load(Ordering::Relaxed),
+            queued: c.queued.load(Ordering::Relaxed),
+            cache_entries: c.cache_entries.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn record_lookup(&self) {
+        self.inner.lookups.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_queries(&self, n: u64) {
+        self.inner.queries.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_failure(&self) {
+        self.inner.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_timeout(&self) {
+        self.inner.timeouts.fetch_add(1, Ordering::Relaxed);
+        self.record_failure();
+    }
+
+    pub(crate) fn record_rejected(&self) {
+        self.inner.rejected.fetch_add(1, Ordering::Relaxed);
+        self.record_failure();
+    }
+
+    pub(crate) fn record_nxdomain(&self) {
+        self.inner.nxdomain.fetch_add(1, Ordering::Relaxed);
+        self.record_failure();
+    }
+
+    pub(crate) fn record_cache_hit(&self) {
+        self.inner.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_cache_entries(&self, entries: usize) {
+        self.inner.cache_entries.store(entries, Ordering::Relaxed);
+    }
+
+    /// Time a lookup until the guard is dropped.
+    pub(crate) fn time_lookup(&self) -> LookupTimer {
+        LookupTimer {
+            counters: self.inner.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Count a running lookup until the guard is dropped.
+    pub(crate) fn in_flight(&self) -> GaugeGuard {
+        GaugeGuard::new(&self.inner, |c| &c.in_flight)
+    }
+
+    /// Count a queued lookup until the guard is dropped, unless `limit`
+    /// lookups are queued already.
+    pub(crate) fn try_enqueue(&self, limit: usize) -> Option<GaugeGuard> {
+        self.inner
+            .queued
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                (n < limit).then_some(n + 1)
+            })
+            .ok()?;
+        Some(GaugeGuard {
+            counters: self.inner.clone(),
+            gauge: |c| &c.queued,
+        })
+    }
+}
+
+/// Decrements a gauge when dropped, so cancelled lookups are accounted for.
+pub(crate) struct GaugeGuard {
+    counters: Arc<Counters>,
+    gauge: fn(&Counters) -> &AtomicUsize,
+}
+
+impl GaugeGuard {
+    fn new(counters: &Arc<Counters>, gauge: fn(&Counters) -> &AtomicUsize) -> Self {
+        gauge(counters).fetch_add(1, Ordering::Relaxed);
+        Self {
+            counters: counters.clone(),
+            gauge,
+        }
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        (self.gauge)(&self.counters).fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Adds the time since it was created to the lookup time when dropped.
+pub(crate) struct LookupTimer {
+    counters: Arc<Counters>,
+    started: Instant,
+}
+
+impl Drop for LookupTimer {
+    fn drop(&mut self) {
+        let nanos = u64::try_from(self.started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.counters.timed.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .lookup_nanos
+            .fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gauges_follow_guards() {
+        let metrics = DnsMetrics::new();
+        let running = metrics.in_flight();
+        let queued = metrics.try_enqueue(1).unwrap();
+        assert!(metrics.try_enqueue(1).is_none());
+        assert_eq!(metrics.snapshot().in_flight, 1);
+        assert_eq!(metrics.snapshot().queued, 1);
+
+        drop(running);
+        drop(queued);
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.in_flight, snapshot.queued), (0, 0));
+    }
+
+    #[test]
+    fn test_timeouts_and_rejections_count_as_failures() {
+        let metrics = DnsMetrics::new();
+        metrics.record_timeout();
+        metrics.record_rejected();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.failures, 2);
+        assert_eq!(snapshot.timeouts, 1);
+        assert_eq!(snapshot.rejected, 1);
+    }
+
+    #[test]
+    fn test_lookup_time_averages_timed_lookups() {
+        let metrics = DnsMetrics::new();
+        assert_eq!(metrics.snapshot().mean_lookup_time(), None);
+        drop(metrics.time_lookup());
+        drop(metrics.time_lookup());
+        metrics.record_nxdomain();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.timed, 2);
+        assert!(snapshot.mean_lookup_time().unwrap() <= snapshot.lookup_time);
+        assert_eq!((snapshot.nxdomain, snapshot.failures), (1, 1));
+    }
+}
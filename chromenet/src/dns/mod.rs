@@ -1,32 +1,61 @@
-//! DNS Resolution Module
-//!
-//! Provides pluggable DNS resolution with support for:
-//! - System resolver (getaddrinfo via thread pool)
-//! - Async hickory-dns resolver (DoH/DoT capable)
-//! - Hostname-to-IP override mechanism
-//!
-//! # Architecture
-//!
-//! This module mirrors Chromium's `HostResolver` concept but with a cleaner
-//! Rust-idiomatic design. The `Resolve` trait is the core abstraction that
-//! allows different resolver implementations to be used interchangeably.
-//!
-//! # Example
-//!
-//! ```rust,ignore
-//! use chromenet::dns::{Name, Resolve, HickoryResolver};
-//!
-//! let resolver = HickoryResolver::new();
-//! let addrs = resolver.resolve(Name::new("example.com")).await?;
-//! for addr in addrs {
-//!     println!("Resolved: {}", addr);
-//! }
-//! ```
-
-mod gai;
-mod hickory;
-mod resolve;
-
-pub use gai::GaiResolver;
-pub use hickory::HickoryResolver;
-pub use resolve::{Addrs, DnsResolverWithOverrides, Name, Resolve, Resolving};
+//! DNS Resolution Module
+//!
+//! Provides pluggable DNS resolution with support for:
+//! - System resolver (getaddrinfo via a bounded thread pool)
+//! - Async hickory-dns resolver (DoH/DoT capable, `hickory-dns` feature)
+//! - Hostname-to-IP override mechanism
+//! - Hooks rewriting answers before connecting ([`PostResolve`])
+//! - A cache of recent answers ([`HostCache`])
+//! - Per-resolver counters ([`DnsMetrics`])
+//! - Typed TXT, MX, SRV and HTTPS lookups on [`HickoryResolver`]
+//!
+//! # Architecture
+//!
+//! This module mirrors Chromium's `HostResolver` concept but with a cleaner
+//! Rust-idiomatic design. The `Resolve` trait is the core abstraction that
+//! allows different resolver implementations to be used interchangeably.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use chromenet::dns::{Name, Resolve, HickoryResolver};
+//!
+//! let resolver = HickoryResolver::new();
+//! let addrs = resolver.resolve(Name::new("example.com")).await?;
+//! for addr in addrs {
+//!     println!("Resolved: {}", addr);
+//! }
+//! ```
+
+mod gai;
+#[cfg(feature = "hickory-dns")]
+mod hickory;
+mod hostcache;
+mod metrics;
+mod postresolve;
+#[cfg(feature = "hickory-dns")]
+mod records;
+mod resolve;
+
+pub use gai::{
+    GaiResolver, DEFAULT_LOOKUP_TIMEOUT, DEFAULT_MAX_CONCURRENT_LOOKUPS, DEFAULT_MAX_QUEUED_LOOKUPS,
+};
+#[cfg(feature = "hickory-dns")]
+pub use hickory::HickoryResolver;
+pub use hostcache::{HostCache, DEFAULT_HOST_CACHE_TTL, DEFAULT_MAX_HOST_CACHE_ENTRIES};
+pub use metrics::{DnsMetrics, DnsMetricsSnapshot};
+pub use postresolve::PostResolve;
+#[cfg(feature = "hickory-dns")]
+pub use records::{HttpsRecord, MxRecord, SrvRecord, TxtRecord};
+pub use resolve::{Addrs, DnsResolverWithOverrides, Name, Resolve, Resolving};
+
+use std::sync::Arc;
+
+/// Resolver used when none is configured: [`HickoryResolver`] with the
+/// `hickory-dns` feature, [`GaiResolver`] without it.
+pub fn default_resolver() -> Arc<dyn Resolve> {
+    #[cfg(feature = "hickory-dns")]
+    return Arc::new(HickoryResolver::new());
+    #[cfg(not(feature = "hickory-dns"))]
+    return Arc::new(GaiResolver::new());
+}
@@ -0,0 +1,119 @@
+//! Rewriting DNS answers before connecting.
+//!
+//! Chromium mapping: none. The closest is `--host-resolver-rules`, which
+//! replaces answers up front like [`DnsResolverWithOverrides`].
+//!
+//! A [`PostResolve`] hook sees every answer a request's new connections are
+//! about to use, and returns the addresses to connect to: reordered, e.g.
+//! a known-good edge first, filtered, e.g. without blocked ranges, or
+//! replaced. Unlike a static override it runs after the lookup, so it can
+//! decide from what DNS returned:
+//!
+//! ```
+//! use chromenet::dns::PostResolve;
+//! use std::net::IpAddr;
+//!
+//! // Never connect to 10.0.0.0/8, and try 203.0.113.7 first when offered
+//! let hook = PostResolve::new(|_host, mut addrs: Vec<IpAddr>| {
+//!     addrs.retain(|ip| !matches!(ip, IpAddr::V4(v4) if v4.octets()[0] == 10));
+//!     addrs.sort_by_key(|ip| *ip != IpAddr::from([203, 0, 113, 7]));
+//!     addrs
+//! });
+//! ```
+//!
+//! Returning no addresses fails the connection with
+//! `NameNotResolvedFor`. The answer also decides whether a pooled
+//! connection's address is still current; see
+//! [`ConnectionLifetime`](crate::socket::lifetime::ConnectionLifetime).
+//! IP literals are not looked up and skip the hook. Happy Eyeballs still
+//! tries IPv6 addresses first, in the order returned, so drop the other
+//! family to force one. Through a proxy, the hook sees the proxy's host.
+//!
+//! Connections are only shared between requests with the same hook, so
+//! set it on the client with
+//! [`ClientBuilder::post_resolve`](crate::ClientBuilder::post_resolve), or
+//! per request with
+//! [`RequestBuilder::post_resolve`](crate::RequestBuilder::post_resolve).
+//!
+//! [`DnsResolverWithOverrides`]: super::DnsResolverWithOverrides
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+type Hook = dyn Fn(&str, Vec<IpAddr>) -> Vec<IpAddr> + Send + Sync;
+
+/// Callback rewriting the addresses a host resolved to, before they are
+/// connected to.
+///
+/// Clones share the callback and compare equal; separately created hooks
+/// never do.
+#[derive(Clone)]
+pub struct PostResolve {
+    hook: Arc<Hook>,
+}
+
+impl PostResolve {
+    /// Pass the answers for a host, and the host, through `hook`.
+    pub fn new<F>(hook: F) -> Self
+    where
+        F: Fn(&str, Vec<IpAddr>) -> Vec<IpAddr> + Send + Sync + 'static,
+    {
+        Self {
+            hook: Arc::new(hook),
+        }
+    }
+
+    /// The addresses to connect to for `host`, which resolved to `addrs`.
+    pub fn apply(&self, host: &str, addrs: Vec<IpAddr>) -> Vec<IpAddr> {
+        let before = addrs.len();
+        let addrs = (self.hook)(host, addrs);
+        tracing::trace!(target: "chromenet::dns", host, before, after = addrs.len(), "post-resolve hook");
+        addrs
+    }
+}
+
+impl PartialEq for PostResolve {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.hook, &other.hook)
+    }
+}
+
+impl Eq for PostResolve {}
+
+impl Hash for PostResolve {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.hook) as *const () as usize).hash(state);
+    }
+}
+
+impl fmt::Debug for PostResolve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostResolve").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_and_identity() {
+        let hook = PostResolve::new(|host, mut addrs: Vec<IpAddr>| {
+            if host == "example.com" {
+                addrs.reverse();
+            }
+            addrs
+        });
+        let addrs: Vec<IpAddr> = vec!["192.0.2.1".parse().unwrap(), "192.0.2.2".parse().unwrap()];
+        assert_eq!(
+            hook.apply("example.com", addrs.clone()),
+            [addrs[1], addrs[0]]
+        );
+        assert_eq!(hook.apply("other.test", addrs.clone()), addrs);
+
+        assert_eq!(hook, hook.clone());
+        assert_ne!(hook, PostResolve::new(|_, addrs| addrs));
+    }
+}
@@ -0,0 +1,194 @@
+//! Typed DNS records beyond addresses.
+//!
+//! Returned by the record lookups of [`HickoryResolver`](super::HickoryResolver):
+//! [`lookup_txt`](super::HickoryResolver::lookup_txt),
+//! [`lookup_mx`](super::HickoryResolver::lookup_mx),
+//! [`lookup_srv`](super::HickoryResolver::lookup_srv) and
+//! [`lookup_https`](super::HickoryResolver::lookup_https), so that TXT
+//! verification or service discovery needs no second DNS dependency.
+//!
+//! Domain names are given without the trailing dot of the root; a target
+//! of `.` is the empty string.
+
+use hickory_resolver::proto::rr::rdata::svcb::SvcParamValue;
+use hickory_resolver::proto::rr::rdata::{HTTPS, MX, SRV, TXT};
+use hickory_resolver::proto::rr::Name as DnsName;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// A TXT record: one or more character strings of up to 255 bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxtRecord {
+    strings: Vec<Vec<u8>>,
+}
+
+impl TxtRecord {
+    /// A record of `strings`.
+    pub fn new(strings: Vec<Vec<u8>>) -> Self {
+        Self { strings }
+    }
+
+    /// The character strings, as they were on the wire.
+    pub fn strings(&self) -> &[Vec<u8>] {
+        &self.strings
+    }
+
+    /// The strings joined without separator, as SPF and DKIM read long
+    /// values split across strings; invalid UTF-8 is replaced.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.strings.concat()).into_owned()
+    }
+
+    pub(super) fn from_rdata(txt: &TXT) -> Self {
+        Self::new(txt.iter().map(|s| s.to_vec()).collect())
+    }
+}
+
+/// An MX record: a mail exchanger and its preference, lowest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MxRecord {
+    pub preference: u16,
+    pub exchange: String,
+}
+
+impl MxRecord {
+    pub(super) fn from_rdata(mx: &MX) -> Self {
+        Self {
+            preference: mx.preference(),
+            exchange: domain(mx.exchange()),
+        }
+    }
+}
+
+/// An SRV record (RFC 2782): where a service runs.
+///
+/// An empty `target` means the service is not available at the domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    /// Lowest is tried first.
+    pub priority: u16,
+    /// Relative share among records of the same priority.
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+impl SrvRecord {
+    pub(super) fn from_rdata(srv: &SRV) -> Self {
+        Self {
+            priority: srv.priority(),
+            weight: srv.weight(),
+            port: srv.port(),
+            target: domain(srv.target()),
+        }
+    }
+}
+
+/// An HTTPS record (RFC 9460): how to reach an HTTPS origin.
+///
+/// A priority of 0 makes the record an alias to `target`; otherwise it
+/// describes an endpoint, at `target` or, if empty, the queried name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpsRecord {
+    pub priority: u16,
+    pub target: String,
+    /// ALPN protocol IDs the endpoint supports, e.g. `h3` and `h2`.
+    pub alpn: Vec<String>,
+    /// Port to use instead of 443.
+    pub port: Option<u16>,
+    pub ipv4_hints: Vec<Ipv4Addr>,
+    pub ipv6_hints: Vec<Ipv6Addr>,
+    /// Encrypted ClientHello configuration list, as sent on the wire.
+    pub ech_config: Option<Vec<u8>>,
+}
+
+impl HttpsRecord {
+    /// Whether the record aliases another name rather than describing an
+    /// endpoint.
+    pub fn is_alias(&self) -> bool {
+        self.priority == 0
+    }
+
+    pub(super) fn from_rdata(https: &HTTPS) -> Self {
+        let mut record = Self {
+            priority: https.svc_priority(),
+            target: domain(https.target_name()),
+            ..Self::default()
+        };
+        for (_, value) in https.svc_params() {
+            match value {
+                SvcParamValue::Alpn(alpn) => record.alpn = alpn.0.clone(),
+                SvcParamValue::Port(port) => record.port = Some(*port),
+                SvcParamValue::Ipv4Hint(hint) => {
+                    record.ipv4_hints = hint.0.iter().map(|a| a.0).collect()
+                }
+                SvcParamValue::Ipv6Hint(hint) => {
+                    record.ipv6_hints = hint.0.iter().map(|a| a.0).collect()
+                }
+                SvcParamValue::EchConfigList(ech) => record.ech_config = Some(ech.0.clone()),
+                _ => {}
+            }
+        }
+        record
+    }
+}
+
+/// `name` without the trailing dot.
+fn domain(name: &DnsName) -> String {
+    name.to_utf8().trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::rr::rdata::svcb::{Alpn, IpHint, SvcParamKey, SVCB};
+    use hickory_resolver::proto::rr::rdata::A;
+
+    #[test]
+    fn test_txt_text_joins_strings() {
+        let txt = TXT::from_bytes(vec![b"v=spf1 include:_spf.example.com", b" -all"]);
+        let record = TxtRecord::from_rdata(&txt);
+        assert_eq!(record.strings().len(), 2);
+        assert_eq!(record.text(), "v=spf1 include:_spf.example.com -all");
+    }
+
+    #[test]
+    fn test_mx_and_srv_names() {
+        let mx = MX::new(10, DnsName::from_ascii("mail.example.com.").unwrap());
+        assert_eq!(
+            MxRecord::from_rdata(&mx),
+            MxRecord {
+                preference: 10,
+                exchange: "mail.example.com".to_string()
+            }
+        );
+
+        let srv = SRV::new(0, 0, 0, DnsName::root());
+        assert_eq!(SrvRecord::from_rdata(&srv).target, "");
+    }
+
+    #[test]
+    fn test_https_params() {
+        let svcb = SVCB::new(
+            1,
+            DnsName::root(),
+            vec![
+                (
+                    SvcParamKey::Alpn,
+                    SvcParamValue::Alpn(Alpn(vec!["h3".to_string(), "h2".to_string()])),
+                ),
+                (SvcParamKey::Port, SvcParamValue::Port(8443)),
+                (
+                    SvcParamKey::Ipv4Hint,
+                    SvcParamValue::Ipv4Hint(IpHint(vec![A(Ipv4Addr::new(192, 0, 2, 1))])),
+                ),
+            ],
+        );
+        let record = HttpsRecord::from_rdata(&HTTPS(svcb));
+        assert!(!record.is_alias());
+        assert_eq!(record.target, "");
+        assert_eq!(record.alpn, ["h3", "h2"]);
+        assert_eq!(record.port, Some(8443));
+        assert_eq!(record.ipv4_hints, [Ipv4Addr::new(192, 0, 2, 1)]);
+        assert!(record.ech_config.is_none());
+    }
+}
@@ -3,6 +3,7 @@
 //! This module defines the `Resolve` trait and supporting types that form
 //! the foundation of the DNS abstraction layer.
 
+use crate::base::host::host_key;
 use crate::base::neterror::NetError;
 use std::{
     borrow::Cow, collections::HashMap, fmt, future::Future, net::SocketAddr, pin::Pin, sync::Arc,
@@ -11,7 +12,9 @@ use std::{
 /// A domain name to resolve into IP addresses.
 ///
 /// This is a lightweight wrapper around a hostname string that provides
-/// a type-safe way to pass domain names to resolvers.
+/// a type-safe way to pass domain names to resolvers. The name is kept in
+/// canonical form (see [`crate::base::host`]), so `Example.COM.` and
+/// `example.com` are the same name.
 #[derive(Clone, Hash, Eq, PartialEq)]
 pub struct Name {
     host: Box<str>,
@@ -20,8 +23,10 @@ pub struct Name {
 impl Name {
     /// Creates a new [`Name`] from any string-like type.
     #[inline]
-    pub fn new(host: impl Into<Box<str>>) -> Self {
-        Self { host: host.into() }
+    pub fn new(host: impl AsRef<str>) -> Self {
+        Self {
+            host: host_key(host.as_ref()).into(),
+        }
     }
 
     /// View the hostname as a string slice.
@@ -77,6 +82,14 @@ pub trait Resolve: Send + Sync {
     /// The returned addresses will have port 0; callers should set the
     /// appropriate port based on the target service.
     fn resolve(&self, name: Name) -> Resolving;
+
+    /// Drops any answer cached for `name`, so the next lookup asks again.
+    ///
+    /// Resolvers without a cache have nothing to do.
+    fn forget(&self, _name: &Name) {}
+
+    /// Drops every cached answer.
+    fn forget_all(&self) {}
 }
 
 /// Blanket implementation for Arc-wrapped resolvers.
@@ -84,6 +97,14 @@ impl<R: Resolve + ?Sized> Resolve for Arc<R> {
     fn resolve(&self, name: Name) -> Resolving {
         (**self).resolve(name)
     }
+
+    fn forget(&self, name: &Name) {
+        (**self).forget(name)
+    }
+
+    fn forget_all(&self) {
+        (**self).forget_all()
+    }
 }
 
 /// DNS resolver wrapper that supports hostname overrides.
@@ -128,6 +149,10 @@ impl DnsResolverWithOverrides {
         inner: Arc<dyn Resolve>,
         overrides: HashMap<Cow<'static, str>, Vec<SocketAddr>>,
     ) -> Self {
+        let overrides = overrides
+            .into_iter()
+            .map(|(host, addrs)| (Cow::Owned(host_key(&host)), addrs))
+            .collect();
         Self {
             inner,
             overrides: Arc::new(overrides),
@@ -150,6 +175,14 @@ impl Resolve for DnsResolverWithOverrides {
         // Fall back to inner resolver
         self.inner.resolve(name)
     }
+
+    fn forget(&self, name: &Name) {
+        self.inner.forget(name)
+    }
+
+    fn forget_all(&self) {
+        self.inner.forget_all()
+    }
 }
 
 impl fmt::Debug for DnsResolverWithOverrides {
@@ -179,6 +212,16 @@ mod tests {
         assert_eq!(name.as_str(), "test.example.com");
     }
 
+    #[test]
+    fn test_name_is_canonical() {
+        assert_eq!(Name::new("WWW.Example.COM."), Name::new("www.example.com"));
+        assert_eq!(
+            Name::new("bücher.example").as_str(),
+            "xn--bcher-kva.example"
+        );
+        assert_eq!(Name::new("::1").as_str(), "[::1]");
+    }
+
     #[test]
     fn test_name_equality() {
         let name1 = Name::new("example.com");
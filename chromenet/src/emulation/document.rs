@@ -0,0 +1,861 @@
+//! Emulations as data.
+//!
+//! An [`EmulationDocument`] holds a whole [`Emulation`] in plain values (TLS
+//! options, HTTP/1.1 and HTTP/2 options with the fingerprint, default
+//! headers in order, and the `priority` header mode), so profiles can ship
+//! as files and be loaded at runtime without recompiling.
+//!
+//! [`Emulation::to_json`], [`Emulation::from_json`], [`Emulation::save`] and
+//! [`Emulation::load`] read and write JSON. The document implements serde's
+//! traits, so any other format works too, e.g. TOML with the `toml` crate:
+//!
+//! ```ignore
+//! let document: EmulationDocument = toml::from_str(&text)?;
+//! let emulation = document.into_emulation()?;
+//! ```
+//!
+//! Options left out of a document take their defaults, those of
+//! [`TlsOptions::default`] and [`H2Fingerprint::default`] for the nested
+//! sections. Values are written as in the wire formats: ALPN IDs such as
+//! `"h2"`, TLS versions such as `"1.3"`, TLS extension, HTTP/2 setting and
+//! stream IDs as numbers, pseudo-headers with their colon:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "tls": { "alpn_protocols": ["h2", "http/1.1"], "grease_enabled": true },
+//!   "http2": {
+//!     "fingerprint": {
+//!       "initial_window_size": 6291456,
+//!       "pseudo_order": [":method", ":authority", ":scheme", ":path"],
+//!       "settings_order": [1, 2, 3, 4, 5, 6]
+//!     }
+//!   },
+//!   "headers": [["user-agent", "Mozilla/5.0 ..."], ["accept", "*/*"]],
+//!   "priority_header": "multiplexed"
+//! }
+//! ```
+//!
+//! The experimental HTTP/2 settings of a fingerprint cannot be read back
+//! from it and are not part of a document.
+
+use crate::emulation::{Emulation, Http1Options, Http2Options};
+use crate::http::h2fingerprint::{
+    ConnWindowUpdate, GreaseFrame, GreaseSetting, H2Fingerprint, Priorities, Priority, PseudoId,
+    PseudoOrder, SettingId, SettingsOrder, StreamDependency,
+};
+use crate::http::priority::PriorityHeader;
+use crate::socket::tls::{
+    alps, AlpnProtocol, AlpsProtocol, CertCompressAlg, TlsOptions, TlsVersion,
+};
+use boring::ssl::ExtensionType;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Version of the document format.
+const DOCUMENT_VERSION: u32 = 1;
+
+/// An [`Emulation`] as a serializable document, see
+/// [`crate::emulation::document`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmulationDocument {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tls: Option<TlsDocument>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    http1: Option<Http1Document>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    http2: Option<Http2Document>,
+    /// Name and value pairs, in the order they are sent
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    #[serde(default)]
+    priority_header: PriorityHeaderDocument,
+}
+
+impl EmulationDocument {
+    /// The emulation the document describes.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] on an unknown version or a
+    /// value chromenet cannot use, such as an unsupported ALPN ID.
+    pub fn into_emulation(self) -> io::Result<Emulation> {
+        if self.version != DOCUMENT_VERSION {
+            return Err(invalid(format!(
+                "unsupported emulation document version {}",
+                self.version
+            )));
+        }
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|_| invalid(format!("invalid header name {name:?}")))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .map_err(|_| invalid(format!("invalid value for header {name}")))?;
+            headers.append(name, value);
+        }
+        Ok(Emulation {
+            tls_options: self.tls.map(TlsDocument::into_options).transpose()?,
+            http1_options: self.http1.map(Http1Options::from),
+            http2_options: self.http2.map(Http2Document::into_options).transpose()?,
+            headers,
+            priority_header: self.priority_header.into(),
+        })
+    }
+}
+
+impl From<&Emulation> for EmulationDocument {
+    fn from(emulation: &Emulation) -> Self {
+        Self {
+            version: DOCUMENT_VERSION,
+            tls: emulation.tls_options.as_ref().map(TlsDocument::from),
+            http1: emulation.http1_options.as_ref().map(Http1Document::from),
+            http2: emulation.http2_options.as_ref().map(Http2Document::from),
+            headers: emulation
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                    (name.as_str().to_string(), value)
+                })
+                .collect(),
+            priority_header: emulation.priority_header.into(),
+        }
+    }
+}
+
+impl Emulation {
+    /// The emulation as a JSON document, see [`crate::emulation::document`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&EmulationDocument::from(self))
+            .expect("emulation documents serialize")
+    }
+
+    /// An emulation from a JSON document, see [`crate::emulation::document`].
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let document: EmulationDocument = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        document.into_emulation()
+    }
+
+    /// Write the emulation to `path` as a JSON document.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    /// Read an emulation from a JSON document at `path`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PriorityHeaderDocument {
+    #[default]
+    Auto,
+    Multiplexed,
+    Always,
+    Never,
+}
+
+impl From<PriorityHeader> for PriorityHeaderDocument {
+    fn from(mode: PriorityHeader) -> Self {
+        match mode {
+            PriorityHeader::Auto => Self::Auto,
+            PriorityHeader::Multiplexed => Self::Multiplexed,
+            PriorityHeader::Always => Self::Always,
+            PriorityHeader::Never => Self::Never,
+        }
+    }
+}
+
+impl From<PriorityHeaderDocument> for PriorityHeader {
+    fn from(mode: PriorityHeaderDocument) -> Self {
+        match mode {
+            PriorityHeaderDocument::Auto => Self::Auto,
+            PriorityHeaderDocument::Multiplexed => Self::Multiplexed,
+            PriorityHeaderDocument::Always => Self::Always,
+            PriorityHeaderDocument::Never => Self::Never,
+        }
+    }
+}
+
+// === TLS ===
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TlsDocument {
+    alpn_protocols: Option<Vec<String>>,
+    alps_protocols: Option<Vec<String>>,
+    /// (identifier, value) pairs
+    alps_h2_settings: Option<Vec<(u16, u32)>>,
+    alps_use_new_codepoint: bool,
+    min_tls_version: Option<String>,
+    max_tls_version: Option<String>,
+    session_ticket: bool,
+    pre_shared_key: bool,
+    psk_skip_session_ticket: bool,
+    psk_dhe_ke: bool,
+    key_shares_limit: Option<u8>,
+    enable_ocsp_stapling: bool,
+    enable_signed_cert_timestamps: bool,
+    enable_ech_grease: bool,
+    grease_enabled: Option<bool>,
+    permute_extensions: Option<bool>,
+    renegotiation: bool,
+    delegated_credentials: Option<String>,
+    cipher_list: Option<String>,
+    curves_list: Option<String>,
+    sigalgs_list: Option<String>,
+    certificate_compression_algorithms: Option<Vec<String>>,
+    extension_permutation: Option<Vec<u16>>,
+    record_size_limit: Option<u16>,
+    aes_hw_override: Option<bool>,
+    preserve_tls13_cipher_list: Option<bool>,
+}
+
+impl Default for TlsDocument {
+    fn default() -> Self {
+        Self::from(&TlsOptions::default())
+    }
+}
+
+impl From<&TlsOptions> for TlsDocument {
+    fn from(tls: &TlsOptions) -> Self {
+        Self {
+            alpn_protocols: tls
+                .alpn_protocols
+                .as_ref()
+                .map(|p| p.iter().map(|p| protocol_id(p.0)).collect()),
+            alps_protocols: tls
+                .alps_protocols
+                .as_ref()
+                .map(|p| p.iter().map(|p| protocol_id(p.0)).collect()),
+            alps_h2_settings: tls.alps_h2_settings.as_ref().map(|settings| {
+                settings
+                    .chunks_exact(6)
+                    .map(|s| {
+                        let id = u16::from_be_bytes([s[0], s[1]]);
+                        (id, u32::from_be_bytes([s[2], s[3], s[4], s[5]]))
+                    })
+                    .collect()
+            }),
+            alps_use_new_codepoint: tls.alps_use_new_codepoint,
+            min_tls_version: tls.min_tls_version.map(version_name),
+            max_tls_version: tls.max_tls_version.map(version_name),
+            session_ticket: tls.session_ticket,
+            pre_shared_key: tls.pre_shared_key,
+            psk_skip_session_ticket: tls.psk_skip_session_ticket,
+            psk_dhe_ke: tls.psk_dhe_ke,
+            key_shares_limit: tls.key_shares_limit,
+            enable_ocsp_stapling: tls.enable_ocsp_stapling,
+            enable_signed_cert_timestamps: tls.enable_signed_cert_timestamps,
+            enable_ech_grease: tls.enable_ech_grease,
+            grease_enabled: tls.grease_enabled,
+            permute_extensions: tls.permute_extensions,
+            renegotiation: tls.renegotiation,
+            delegated_credentials: tls.delegated_credentials.as_deref().map(str::to_string),
+            cipher_list: tls.cipher_list.as_deref().map(str::to_string),
+            curves_list: tls.curves_list.as_deref().map(str::to_string),
+            sigalgs_list: tls.sigalgs_list.as_deref().map(str::to_string),
+            certificate_compression_algorithms: tls
+                .certificate_compression_algorithms
+                .as_ref()
+                .map(|algs| algs.iter().map(|&alg| compression_name(alg)).collect()),
+            extension_permutation: tls
+                .extension_permutation
+                .as_ref()
+                .map(|exts| exts.iter().map(|&ext| extension_id(ext)).collect()),
+            record_size_limit: tls.record_size_limit,
+            aes_hw_override: tls.aes_hw_override,
+            preserve_tls13_cipher_list: tls.preserve_tls13_cipher_list,
+        }
+    }
+}
+
+impl TlsDocument {
+    fn into_options(self) -> io::Result<TlsOptions> {
+        Ok(TlsOptions {
+            alpn_protocols: self
+                .alpn_protocols
+                .map(|ids| ids.iter().map(|id| alpn_protocol(id)).collect())
+                .transpose()?
+                .map(Cow::Owned),
+            alps_protocols: self
+                .alps_protocols
+                .map(|ids| ids.iter().map(|id| alps_protocol(id)).collect())
+                .transpose()?
+                .map(Cow::Owned),
+            alps_h2_settings: self
+                .alps_h2_settings
+                .map(|settings| Cow::Owned(alps::encode_h2_settings(settings))),
+            alps_use_new_codepoint: self.alps_use_new_codepoint,
+            min_tls_version: self
+                .min_tls_version
+                .as_deref()
+                .map(tls_version)
+                .transpose()?,
+            max_tls_version: self
+                .max_tls_version
+                .as_deref()
+                .map(tls_version)
+                .transpose()?,
+            session_ticket: self.session_ticket,
+            pre_shared_key: self.pre_shared_key,
+            psk_skip_session_ticket: self.psk_skip_session_ticket,
+            psk_dhe_ke: self.psk_dhe_ke,
+            key_shares_limit: self.key_shares_limit,
+            enable_ocsp_stapling: self.enable_ocsp_stapling,
+            enable_signed_cert_timestamps: self.enable_signed_cert_timestamps,
+            enable_ech_grease: self.enable_ech_grease,
+            grease_enabled: self.grease_enabled,
+            permute_extensions: self.permute_extensions,
+            renegotiation: self.renegotiation,
+            delegated_credentials: self.delegated_credentials.map(Cow::Owned),
+            cipher_list: self.cipher_list.map(Cow::Owned),
+            curves_list: self.curves_list.map(Cow::Owned),
+            sigalgs_list: self.sigalgs_list.map(Cow::Owned),
+            certificate_compression_algorithms: self
+                .certificate_compression_algorithms
+                .map(|names| {
+                    names
+                        .iter()
+                        .map(|name| compression_algorithm(name))
+                        .collect()
+                })
+                .transpose()?
+                .map(Cow::Owned),
+            extension_permutation: self
+                .extension_permutation
+                .map(|ids| Cow::Owned(ids.into_iter().map(ExtensionType::from).collect())),
+            record_size_limit: self.record_size_limit,
+            aes_hw_override: self.aes_hw_override,
+            preserve_tls13_cipher_list: self.preserve_tls13_cipher_list,
+        })
+    }
+}
+
+fn protocol_id(id: &[u8]) -> String {
+    String::from_utf8_lossy(id).into_owned()
+}
+
+/// ALPN IDs are kept as `'static` bytes, so only the known ones load.
+fn alpn_protocol(id: &str) -> io::Result<AlpnProtocol> {
+    [
+        AlpnProtocol::HTTP1,
+        AlpnProtocol::HTTP2,
+        AlpnProtocol::HTTP3,
+    ]
+    .into_iter()
+    .find(|p| p.0 == id.as_bytes())
+    .ok_or_else(|| invalid(format!("unsupported ALPN protocol {id:?}")))
+}
+
+fn alps_protocol(id: &str) -> io::Result<AlpsProtocol> {
+    [AlpsProtocol::HTTP1, AlpsProtocol::HTTP2]
+        .into_iter()
+        .find(|p| p.0 == id.as_bytes())
+        .ok_or_else(|| invalid(format!("unsupported ALPS protocol {id:?}")))
+}
+
+const TLS_VERSIONS: [(TlsVersion, &str); 4] = [
+    (TlsVersion::TLS_1_0, "1.0"),
+    (TlsVersion::TLS_1_1, "1.1"),
+    (TlsVersion::TLS_1_2, "1.2"),
+    (TlsVersion::TLS_1_3, "1.3"),
+];
+
+fn version_name(version: TlsVersion) -> String {
+    TLS_VERSIONS
+        .iter()
+        .find(|(v, _)| *v == version)
+        .map_or("1.3", |(_, name)| name)
+        .to_string()
+}
+
+fn tls_version(name: &str) -> io::Result<TlsVersion> {
+    TLS_VERSIONS
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(v, _)| *v)
+        .ok_or_else(|| invalid(format!("unsupported TLS version {name:?}")))
+}
+
+const COMPRESSION_ALGORITHMS: [(CertCompressAlg, &str); 2] = [
+    (CertCompressAlg::ZLIB, "zlib"),
+    (CertCompressAlg::BROTLI, "brotli"),
+];
+
+fn compression_name(alg: CertCompressAlg) -> String {
+    COMPRESSION_ALGORITHMS
+        .iter()
+        .find(|(a, _)| *a == alg)
+        .map_or_else(|| format!("{alg:?}"), |(_, name)| name.to_string())
+}
+
+fn compression_algorithm(name: &str) -> io::Result<CertCompressAlg> {
+    COMPRESSION_ALGORITHMS
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(a, _)| *a)
+        .ok_or_else(|| invalid(format!("unsupported certificate compression {name:?}")))
+}
+
+/// The code point of `ext`, which BoringSSL only shows in its `Debug`
+/// output, `ExtensionType(43)`.
+fn extension_id(ext: ExtensionType) -> u16 {
+    let debug = format!("{ext:?}");
+    debug
+        .trim_start_matches("ExtensionType(")
+        .trim_end_matches(')')
+        .parse()
+        .unwrap_or_default()
+}
+
+// === HTTP/1.1 ===
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Http1Document {
+    title_case_headers: bool,
+    preserve_header_order: bool,
+}
+
+impl From<&Http1Options> for Http1Document {
+    fn from(options: &Http1Options) -> Self {
+        Self {
+            title_case_headers: options.title_case_headers,
+            preserve_header_order: options.preserve_header_order,
+        }
+    }
+}
+
+impl From<Http1Document> for Http1Options {
+    fn from(document: Http1Document) -> Self {
+        Self {
+            title_case_headers: document.title_case_headers,
+            preserve_header_order: document.preserve_header_order,
+        }
+    }
+}
+
+// === HTTP/2 ===
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Http2Document {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<H2FingerprintDocument>,
+    initial_window_size: Option<u32>,
+    max_frame_size: Option<u32>,
+    max_concurrent_streams: Option<u32>,
+    max_header_list_size: Option<u32>,
+    header_table_size: Option<u32>,
+    enable_push: Option<bool>,
+}
+
+impl From<&Http2Options> for Http2Document {
+    fn from(options: &Http2Options) -> Self {
+        Self {
+            fingerprint: options
+                .fingerprint
+                .as_ref()
+                .map(H2FingerprintDocument::from),
+            initial_window_size: options.initial_window_size,
+            max_frame_size: options.max_frame_size,
+            max_concurrent_streams: options.max_concurrent_streams,
+            max_header_list_size: options.max_header_list_size,
+            header_table_size: options.header_table_size,
+            enable_push: options.enable_push,
+        }
+    }
+}
+
+impl Http2Document {
+    fn into_options(self) -> io::Result<Http2Options> {
+        Ok(Http2Options {
+            fingerprint: self
+                .fingerprint
+                .map(H2FingerprintDocument::into_fingerprint)
+                .transpose()?,
+            initial_window_size: self.initial_window_size,
+            max_frame_size: self.max_frame_size,
+            max_concurrent_streams: self.max_concurrent_streams,
+            max_header_list_size: self.max_header_list_size,
+            header_table_size: self.header_table_size,
+            enable_push: self.enable_push,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct H2FingerprintDocument {
+    header_table_size: Option<u32>,
+    enable_push: Option<bool>,
+    max_concurrent_streams: Option<u32>,
+    initial_window_size: u32,
+    initial_conn_window_size: u32,
+    conn_window_update: ConnWindowUpdateDocument,
+    max_frame_size: Option<u32>,
+    max_header_list_size: Option<u32>,
+    pseudo_order: Option<Vec<String>>,
+    settings_order: Option<Vec<u16>>,
+    priorities: Option<Vec<PriorityDocument>>,
+    stream_dependency: Option<DependencyDocument>,
+    grease_setting: Option<GreaseSettingDocument>,
+    grease_frame: Option<GreaseFrameDocument>,
+    keep_alive_interval_ms: Option<u64>,
+    keep_alive_timeout_ms: Option<u64>,
+    keep_alive_while_idle: bool,
+    initial_stream_id: Option<u32>,
+    adaptive_window: bool,
+    no_rfc7540_priorities: Option<bool>,
+    enable_connect_protocol: Option<bool>,
+}
+
+impl Default for H2FingerprintDocument {
+    fn default() -> Self {
+        Self::from(&H2Fingerprint::default())
+    }
+}
+
+impl From<&H2Fingerprint> for H2FingerprintDocument {
+    fn from(fp: &H2Fingerprint) -> Self {
+        Self {
+            header_table_size: fp.header_table_size,
+            enable_push: fp.enable_push,
+            max_concurrent_streams: fp.max_concurrent_streams,
+            initial_window_size: fp.initial_window_size,
+            initial_conn_window_size: fp.initial_conn_window_size,
+            conn_window_update: fp.conn_window_update.into(),
+            max_frame_size: fp.max_frame_size,
+            max_header_list_size: fp.max_header_list_size,
+            pseudo_order: fp
+                .pseudo_order
+                .as_ref()
+                .map(|order| order.into_iter().map(|&id| pseudo_name(id)).collect()),
+            settings_order: fp
+                .settings_order
+                .as_ref()
+                .map(|order| order.into_iter().map(|&id| u16::from(id)).collect()),
+            priorities: fp
+                .priorities
+                .clone()
+                .map(|priorities| priorities.into_iter().map(PriorityDocument::from).collect()),
+            stream_dependency: fp.stream_dependency.map(DependencyDocument::from),
+            grease_setting: fp.grease_setting.map(GreaseSettingDocument::from),
+            grease_frame: fp.grease_frame.clone().map(GreaseFrameDocument::from),
+            keep_alive_interval_ms: fp.keep_alive_interval.map(|d| d.as_millis() as u64),
+            keep_alive_timeout_ms: fp.keep_alive_timeout.map(|d| d.as_millis() as u64),
+            keep_alive_while_idle: fp.keep_alive_while_idle,
+            initial_stream_id: fp.initial_stream_id,
+            adaptive_window: fp.adaptive_window,
+            no_rfc7540_priorities: fp.no_rfc7540_priorities,
+            enable_connect_protocol: fp.enable_connect_protocol,
+        }
+    }
+}
+
+impl H2FingerprintDocument {
+    fn into_fingerprint(self) -> io::Result<H2Fingerprint> {
+        let pseudo_order = match self.pseudo_order {
+            Some(names) => {
+                let ids = names
+                    .iter()
+                    .map(|name| pseudo_id(name))
+                    .collect::<io::Result<Vec<_>>>()?;
+                Some(PseudoOrder::builder().extend(ids).build())
+            }
+            None => None,
+        };
+        Ok(H2Fingerprint {
+            header_table_size: self.header_table_size,
+            enable_push: self.enable_push,
+            max_concurrent_streams: self.max_concurrent_streams,
+            initial_window_size: self.initial_window_size,
+            initial_conn_window_size: self.initial_conn_window_size,
+            conn_window_update: self.conn_window_update.into(),
+            max_frame_size: self.max_frame_size,
+            max_header_list_size: self.max_header_list_size,
+            pseudo_order,
+            settings_order: self.settings_order.map(|ids| {
+                SettingsOrder::builder()
+                    .extend(ids.into_iter().map(SettingId::from))
+                    .build()
+            }),
+            priorities: self.priorities.map(|priorities| {
+                Priorities::builder()
+                    .extend(priorities.into_iter().map(Priority::from))
+                    .build()
+            }),
+            stream_dependency: self.stream_dependency.map(StreamDependency::from),
+            experimental_settings: None,
+            grease_setting: self.grease_setting.map(GreaseSetting::from),
+            grease_frame: self.grease_frame.map(GreaseFrame::from),
+            keep_alive_interval: self.keep_alive_interval_ms.map(Duration::from_millis),
+            keep_alive_timeout: self.keep_alive_timeout_ms.map(Duration::from_millis),
+            keep_alive_while_idle: self.keep_alive_while_idle,
+            initial_stream_id: self.initial_stream_id,
+            adaptive_window: self.adaptive_window,
+            no_rfc7540_priorities: self.no_rfc7540_priorities,
+            enable_connect_protocol: self.enable_connect_protocol,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ConnWindowUpdateDocument {
+    #[default]
+    AfterSettings,
+    AfterFirstRequest,
+    Never,
+}
+
+impl From<ConnWindowUpdate> for ConnWindowUpdateDocument {
+    fn from(when: ConnWindowUpdate) -> Self {
+        match when {
+            ConnWindowUpdate::AfterSettings => Self::AfterSettings,
+            ConnWindowUpdate::AfterFirstRequest => Self::AfterFirstRequest,
+            ConnWindowUpdate::Never => Self::Never,
+        }
+    }
+}
+
+impl From<ConnWindowUpdateDocument> for ConnWindowUpdate {
+    fn from(when: ConnWindowUpdateDocument) -> Self {
+        match when {
+            ConnWindowUpdateDocument::AfterSettings => Self::AfterSettings,
+            ConnWindowUpdateDocument::AfterFirstRequest => Self::AfterFirstRequest,
+            ConnWindowUpdateDocument::Never => Self::Never,
+        }
+    }
+}
+
+const PSEUDO_HEADERS: [(PseudoId, &str); 6] = [
+    (PseudoId::Method, ":method"),
+    (PseudoId::Scheme, ":scheme"),
+    (PseudoId::Authority, ":authority"),
+    (PseudoId::Path, ":path"),
+    (PseudoId::Protocol, ":protocol"),
+    (PseudoId::Status, ":status"),
+];
+
+fn pseudo_name(id: PseudoId) -> String {
+    PSEUDO_HEADERS
+        .iter()
+        .find(|(i, _)| *i == id)
+        .map_or(":method", |(_, name)| name)
+        .to_string()
+}
+
+fn pseudo_id(name: &str) -> io::Result<PseudoId> {
+    PSEUDO_HEADERS
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(id, _)| *id)
+        .ok_or_else(|| invalid(format!("unknown pseudo-header {name:?}")))
+}
+
+/// A PRIORITY frame sent after the preface.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PriorityDocument {
+    stream_id: u32,
+    depends_on: u32,
+    weight: u8,
+    #[serde(default)]
+    exclusive: bool,
+}
+
+impl From<Priority> for PriorityDocument {
+    fn from(priority: Priority) -> Self {
+        // The dependency is only readable from the encoded frame, after
+        // the 9-byte frame header
+        let mut frame = Vec::with_capacity(14);
+        priority.encode(&mut frame);
+        let dependency = StreamDependency::load(&frame[9..])
+            .map(DependencyDocument::from)
+            .unwrap_or_default();
+        Self {
+            stream_id: priority.stream_id().into(),
+            depends_on: dependency.depends_on,
+            weight: dependency.weight,
+            exclusive: dependency.exclusive,
+        }
+    }
+}
+
+impl From<PriorityDocument> for Priority {
+    fn from(priority: PriorityDocument) -> Self {
+        Priority::new(
+            priority.stream_id.into(),
+            StreamDependency::new(
+                priority.depends_on.into(),
+                priority.weight,
+                priority.exclusive,
+            ),
+        )
+    }
+}
+
+/// The dependency of a stream, weight 0 to 255 as on the wire.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DependencyDocument {
+    depends_on: u32,
+    weight: u8,
+    #[serde(default)]
+    exclusive: bool,
+}
+
+impl From<StreamDependency> for DependencyDocument {
+    fn from(dependency: StreamDependency) -> Self {
+        Self {
+            depends_on: dependency.dependency_id().into(),
+            weight: dependency.weight(),
+            exclusive: dependency.is_exclusive(),
+        }
+    }
+}
+
+impl From<DependencyDocument> for StreamDependency {
+    fn from(dependency: DependencyDocument) -> Self {
+        StreamDependency::new(
+            dependency.depends_on.into(),
+            dependency.weight,
+            dependency.exclusive,
+        )
+    }
+}
+
+/// A reserved setting appended to the initial SETTINGS.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GreaseSettingDocument {
+    id: u16,
+    value: u32,
+}
+
+impl From<GreaseSetting> for GreaseSettingDocument {
+    fn from(setting: GreaseSetting) -> Self {
+        Self {
+            id: setting.id,
+            value: setting.value,
+        }
+    }
+}
+
+impl From<GreaseSettingDocument> for GreaseSetting {
+    fn from(setting: GreaseSettingDocument) -> Self {
+        GreaseSetting::new(setting.id, setting.value)
+    }
+}
+
+/// A frame of a reserved type sent after the initial SETTINGS.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GreaseFrameDocument {
+    kind: u8,
+    #[serde(default)]
+    flags: u8,
+    #[serde(default)]
+    payload: Vec<u8>,
+}
+
+impl From<GreaseFrame> for GreaseFrameDocument {
+    fn from(frame: GreaseFrame) -> Self {
+        Self {
+            kind: frame.kind,
+            flags: frame.flags,
+            payload: frame.payload.to_vec(),
+        }
+    }
+}
+
+impl From<GreaseFrameDocument> for GreaseFrame {
+    fn from(frame: GreaseFrameDocument) -> Self {
+        GreaseFrame::new(frame.kind, frame.flags, frame.payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_document_is_default_emulation() {
+        let emulation = Emulation::from_json(r#"{"version": 1}"#).unwrap();
+        assert!(emulation.tls_options().is_none());
+        assert!(emulation.http2_options().is_none());
+        assert!(emulation.headers().is_empty());
+        assert_eq!(emulation.priority_header(), PriorityHeader::Auto);
+    }
+
+    #[test]
+    fn test_missing_options_take_defaults() {
+        let emulation =
+            Emulation::from_json(r#"{"version": 1, "tls": {"grease_enabled": true}}"#).unwrap();
+        let tls = emulation.tls_options().unwrap();
+        assert_eq!(tls.grease_enabled, Some(true));
+        assert_eq!(tls.alpn_protocols, TlsOptions::default().alpn_protocols);
+        assert_eq!(tls.min_tls_version, Some(TlsVersion::TLS_1_2));
+    }
+
+    #[test]
+    fn test_h2_fingerprint_roundtrip() {
+        let fp = H2Fingerprint {
+            grease_setting: Some(GreaseSetting::new(0x1a1a, 3)),
+            grease_frame: Some(GreaseFrame::new(0x0b, 0, &b"x"[..])),
+            ..H2Fingerprint::chrome()
+        };
+        let document = H2FingerprintDocument::from(&fp);
+        assert_eq!(
+            document.pseudo_order.as_deref().unwrap()[..4],
+            [":method", ":authority", ":scheme", ":path"]
+        );
+        let first = document.priorities.as_ref().unwrap()[0];
+        assert_eq!(
+            (first.stream_id, first.depends_on, first.weight),
+            (3, 0, 200)
+        );
+
+        let restored = document.into_fingerprint().unwrap();
+        assert_eq!(restored.pseudo_order, fp.pseudo_order);
+        assert_eq!(restored.settings_order, fp.settings_order);
+        assert_eq!(restored.priorities, fp.priorities);
+        assert_eq!(restored.grease_setting, fp.grease_setting);
+        assert_eq!(restored.grease_frame, fp.grease_frame);
+    }
+
+    #[test]
+    fn test_extension_id() {
+        assert_eq!(extension_id(ExtensionType::SERVER_NAME), 0);
+        assert_eq!(extension_id(ExtensionType::from(17513)), 17513);
+    }
+
+    #[test]
+    fn test_invalid_values_rejected() {
+        for json in [
+            r#"{"version": 2}"#,
+            r#"{"version": 1, "tls": {"alpn_protocols": ["spdy/3"]}}"#,
+            r#"{"version": 1, "tls": {"min_tls_version": "1.4"}}"#,
+            r#"{"version": 1, "headers": [["bad name", "x"]]}"#,
+            r#"{"version": 1, "unknown": true}"#,
+        ] {
+            let err = Emulation::from_json(json).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{json}");
+        }
+    }
+}
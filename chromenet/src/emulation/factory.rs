@@ -1,8 +1,11 @@
 //! Emulation factory and core types.
 
 use crate::emulation::{Http1Options, Http2Options};
+use crate::http::priority::PriorityHeader;
 use crate::socket::tls::TlsOptions;
 use http::HeaderMap;
+use serde::Serialize;
+use std::fmt;
 
 /// Factory trait for creating emulation configurations.
 ///
@@ -20,6 +23,30 @@ pub struct EmulationBuilder {
     emulation: Emulation,
 }
 
+/// The browser and version an [`Emulation`] reproduces, e.g. `chrome 140.0.0.0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct EmulationProfile {
+    /// Browser or client, lowercase: `chrome`, `firefox`, `okhttp`, ...
+    pub name: String,
+    /// Its version as the profile reports it, e.g. in the User-Agent.
+    pub version: String,
+}
+
+impl EmulationProfile {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+        }
+    }
+}
+
+impl fmt::Display for EmulationProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
 /// HTTP emulation configuration for mimicking browsers.
 ///
 /// Combines:
@@ -38,6 +65,10 @@ pub struct Emulation {
     pub http2_options: Option<Http2Options>,
     /// Default headers to include in requests.
     pub headers: HeaderMap,
+    /// When requests send an RFC 9218 `priority` header.
+    pub priority_header: PriorityHeader,
+    /// Which browser this is, `None` for custom emulations not naming one.
+    pub profile: Option<EmulationProfile>,
 }
 
 impl Emulation {
@@ -71,6 +102,18 @@ impl Emulation {
         &self.headers
     }
 
+    /// When requests send a `priority` header.
+    #[inline]
+    pub fn priority_header(&self) -> PriorityHeader {
+        self.priority_header
+    }
+
+    /// Which browser this emulates, if named.
+    #[inline]
+    pub fn profile(&self) -> Option<&EmulationProfile> {
+        self.profile.as_ref()
+    }
+
     /// Decompose into parts.
     pub fn into_parts(
         self,
@@ -118,6 +161,21 @@ impl EmulationBuilder {
         self
     }
 
+    /// Set when requests send a `priority` header.
+    #[inline]
+    pub fn priority_header(mut self, mode: PriorityHeader) -> Self {
+        self.emulation.priority_header = mode;
+        self
+    }
+
+    /// Name the browser and version emulated, as reported by
+    /// [`HttpResponse::identity_report`](crate::http::HttpResponse::identity_report).
+    #[inline]
+    pub fn profile(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.emulation.profile = Some(EmulationProfile::new(name, version));
+        self
+    }
+
     /// Add a single header.
     #[inline]
     pub fn header<K, V>(mut self, key: K, value: V) -> Self
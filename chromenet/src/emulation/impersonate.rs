@@ -0,0 +1,305 @@
+//! One-call browser impersonation.
+//!
+//! Turns an [`ImpersonateTarget`] into a complete [`Emulation`]: the
+//! target's TLS fingerprint, the matching browser profile's HTTP/2 settings
+//! and default headers, and a User-Agent and client hints that agree with
+//! the chosen operating system.
+
+use crate::emulation::profiles::{Chrome, Firefox, OkHttp, Safari};
+use crate::emulation::{Emulation, EmulationFactory};
+use crate::socket::tls::ImpersonateTarget;
+use http::{header, HeaderMap, HeaderValue};
+
+/// Operating system reported by an impersonated browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImpersonateOs {
+    /// Windows 10/11 desktop
+    Windows,
+    /// macOS desktop
+    MacOS,
+    /// Linux desktop
+    Linux,
+    /// Android phone
+    Android,
+    /// iPhone
+    IOS,
+}
+
+impl ImpersonateOs {
+    fn is_mobile(self) -> bool {
+        matches!(self, ImpersonateOs::Android | ImpersonateOs::IOS)
+    }
+
+    /// `Sec-CH-UA-Platform` value.
+    fn ch_platform(self) -> &'static str {
+        match self {
+            ImpersonateOs::Windows => "\"Windows\"",
+            ImpersonateOs::MacOS => "\"macOS\"",
+            ImpersonateOs::Linux => "\"Linux\"",
+            ImpersonateOs::Android => "\"Android\"",
+            ImpersonateOs::IOS => "\"iOS\"",
+        }
+    }
+}
+
+/// An [`ImpersonateTarget`] on a specific operating system.
+///
+/// Created with [`ImpersonateTarget::with_os`]; the target alone uses
+/// [`ImpersonateTarget::default_os`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Impersonate {
+    target: ImpersonateTarget,
+    os: ImpersonateOs,
+}
+
+impl Impersonate {
+    /// The impersonated browser.
+    pub fn target(&self) -> ImpersonateTarget {
+        self.target
+    }
+
+    /// The operating system actually reported.
+    ///
+    /// Safari is only available on Apple platforms and OkHttp only on
+    /// Android, so other choices fall back to the target's default.
+    pub fn os(&self) -> ImpersonateOs {
+        match (self.target, self.os) {
+            (ImpersonateTarget::Safari17 | ImpersonateTarget::Safari18, os)
+                if os != ImpersonateOs::MacOS && os != ImpersonateOs::IOS =>
+            {
+                ImpersonateOs::MacOS
+            }
+            (ImpersonateTarget::OkHttp4 | ImpersonateTarget::OkHttp5, _) => ImpersonateOs::Android,
+            (_, os) => os,
+        }
+    }
+
+    /// User-Agent string for this target and OS.
+    pub fn user_agent(&self) -> String {
+        let os = self.os();
+        let version = browser_version(self.target);
+        let ios = if self.target == ImpersonateTarget::Safari18 {
+            "18_0"
+        } else {
+            "17_0"
+        };
+        match self.target {
+            ImpersonateTarget::Chrome124 | ImpersonateTarget::Chrome128 => match os {
+                ImpersonateOs::IOS => format!(
+                    "Mozilla/5.0 (iPhone; CPU iPhone OS {ios} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) CriOS/{version} Mobile/15E148 Safari/604.1"
+                ),
+                ImpersonateOs::Android => format!(
+                    "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{version} Mobile Safari/537.36"
+                ),
+                _ => format!(
+                    "Mozilla/5.0 ({}) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{version} Safari/537.36",
+                    desktop_platform(os)
+                ),
+            },
+            ImpersonateTarget::Firefox128 | ImpersonateTarget::Firefox129 => match os {
+                ImpersonateOs::IOS => format!(
+                    "Mozilla/5.0 (iPhone; CPU iPhone OS {ios} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) FxiOS/{version} Mobile/15E148 Safari/605.1.15"
+                ),
+                ImpersonateOs::Android => format!(
+                    "Mozilla/5.0 (Android 14; Mobile; rv:{version}) Gecko/{version} Firefox/{version}"
+                ),
+                ImpersonateOs::MacOS => format!(
+                    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:{version}) Gecko/20100101 Firefox/{version}"
+                ),
+                _ => format!(
+                    "Mozilla/5.0 ({}; rv:{version}) Gecko/20100101 Firefox/{version}",
+                    desktop_platform(os)
+                ),
+            },
+            ImpersonateTarget::Safari17 | ImpersonateTarget::Safari18 => match os {
+                ImpersonateOs::IOS => format!(
+                    "Mozilla/5.0 (iPhone; CPU iPhone OS {ios} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{version} Mobile/15E148 Safari/604.1"
+                ),
+                _ => format!(
+                    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{version} Safari/605.1.15"
+                ),
+            },
+            ImpersonateTarget::OkHttp4 | ImpersonateTarget::OkHttp5 => format!("okhttp/{version}"),
+        }
+    }
+
+    /// Profile whose HTTP/2 settings and default headers match the target.
+    fn base_profile(&self) -> Emulation {
+        let ios = self.os() == ImpersonateOs::IOS;
+        match self.target {
+            ImpersonateTarget::Chrome124 => Chrome::V124.emulation(),
+            ImpersonateTarget::Chrome128 => Chrome::V128.emulation(),
+            ImpersonateTarget::Firefox128 | ImpersonateTarget::Firefox129 => {
+                Firefox::V128.emulation()
+            }
+            ImpersonateTarget::Safari17 if ios => Safari::IOS17.emulation(),
+            ImpersonateTarget::Safari17 => Safari::V17.emulation(),
+            ImpersonateTarget::Safari18 if ios => Safari::IOS18.emulation(),
+            ImpersonateTarget::Safari18 => Safari::V18.emulation(),
+            ImpersonateTarget::OkHttp4 => OkHttp::V4_12.emulation(),
+            ImpersonateTarget::OkHttp5 => OkHttp::V5.emulation(),
+        }
+    }
+
+    /// Rewrite the User-Agent and Chromium client hints for the OS.
+    fn apply_os(&self, headers: &mut HeaderMap) {
+        if let Ok(ua) = HeaderValue::from_str(&self.user_agent()) {
+            headers.insert(header::USER_AGENT, ua);
+        }
+        if !headers.contains_key("sec-ch-ua") {
+            return;
+        }
+
+        let os = self.os();
+        if os == ImpersonateOs::IOS {
+            // Chrome on iOS is WebKit-based and sends no client hints
+            for name in ["sec-ch-ua", "sec-ch-ua-mobile", "sec-ch-ua-platform"] {
+                headers.remove(name);
+            }
+            return;
+        }
+        headers.insert(
+            "sec-ch-ua-mobile",
+            HeaderValue::from_static(if os.is_mobile() { "?1" } else { "?0" }),
+        );
+        headers.insert(
+            "sec-ch-ua-platform",
+            HeaderValue::from_static(os.ch_platform()),
+        );
+    }
+}
+
+impl EmulationFactory for Impersonate {
+    fn emulation(self) -> Emulation {
+        let mut emulation = self.base_profile();
+        emulation.tls_options = Some(self.target.create_tls_options());
+        if let Some(profile) = &mut emulation.profile {
+            profile.version = browser_version(self.target).to_string();
+        }
+        self.apply_os(&mut emulation.headers);
+        emulation
+    }
+}
+
+impl ImpersonateTarget {
+    /// Operating system used when none is chosen.
+    pub fn default_os(self) -> ImpersonateOs {
+        match self {
+            ImpersonateTarget::Safari17 | ImpersonateTarget::Safari18 => ImpersonateOs::MacOS,
+            ImpersonateTarget::OkHttp4 | ImpersonateTarget::OkHttp5 => ImpersonateOs::Android,
+            _ => ImpersonateOs::Windows,
+        }
+    }
+
+    /// Impersonate this target on `os`.
+    pub fn with_os(self, os: ImpersonateOs) -> Impersonate {
+        Impersonate { target: self, os }
+    }
+}
+
+impl From<ImpersonateTarget> for Impersonate {
+    fn from(target: ImpersonateTarget) -> Self {
+        target.with_os(target.default_os())
+    }
+}
+
+impl EmulationFactory for ImpersonateTarget {
+    fn emulation(self) -> Emulation {
+        Impersonate::from(self).emulation()
+    }
+}
+
+fn browser_version(target: ImpersonateTarget) -> &'static str {
+    match target {
+        ImpersonateTarget::Chrome124 => Chrome::V124.version_string(),
+        ImpersonateTarget::Chrome128 => Chrome::V128.version_string(),
+        ImpersonateTarget::Firefox128 => "128.0",
+        ImpersonateTarget::Firefox129 => "129.0",
+        ImpersonateTarget::Safari17 => "17.0",
+        ImpersonateTarget::Safari18 => "18.0",
+        ImpersonateTarget::OkHttp4 => "4.12.0",
+        ImpersonateTarget::OkHttp5 => "5.0.0",
+    }
+}
+
+fn desktop_platform(os: ImpersonateOs) -> &'static str {
+    match os {
+        ImpersonateOs::MacOS => "Macintosh; Intel Mac OS X 10_15_7",
+        ImpersonateOs::Linux => "X11; Linux x86_64",
+        _ => "Windows NT 10.0; Win64; x64",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulation::EmulationProfile;
+
+    fn header(emulation: &Emulation, name: &str) -> Option<String> {
+        emulation
+            .headers()
+            .get(name)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_target_defaults() {
+        let emulation = ImpersonateTarget::Chrome124.emulation();
+        let ua = header(&emulation, "user-agent").unwrap();
+        assert!(ua.contains("Windows NT 10.0"));
+        assert!(ua.contains("Chrome/124.0.0.0"));
+        assert_eq!(
+            header(&emulation, "sec-ch-ua-platform").as_deref(),
+            Some("\"Windows\"")
+        );
+        assert_eq!(
+            emulation.tls_options(),
+            Some(&ImpersonateTarget::Chrome124.create_tls_options())
+        );
+        assert!(emulation.http2_options().is_some());
+    }
+
+    #[test]
+    fn test_os_variant_keeps_hints_consistent() {
+        let android = ImpersonateTarget::Chrome128
+            .with_os(ImpersonateOs::Android)
+            .emulation();
+        assert!(header(&android, "user-agent").unwrap().contains("Android"));
+        assert_eq!(header(&android, "sec-ch-ua-mobile").as_deref(), Some("?1"));
+        assert_eq!(
+            header(&android, "sec-ch-ua-platform").as_deref(),
+            Some("\"Android\"")
+        );
+
+        let ios = ImpersonateTarget::Chrome128
+            .with_os(ImpersonateOs::IOS)
+            .emulation();
+        assert!(header(&ios, "user-agent").unwrap().contains("CriOS/128"));
+        assert!(header(&ios, "sec-ch-ua").is_none());
+    }
+
+    #[test]
+    fn test_firefox_linux() {
+        let emulation = ImpersonateTarget::Firefox129
+            .with_os(ImpersonateOs::Linux)
+            .emulation();
+        let ua = header(&emulation, "user-agent").unwrap();
+        assert!(ua.contains("X11; Linux x86_64; rv:129.0"));
+        assert!(ua.ends_with("Firefox/129.0"));
+        // Built on the 128 profile, reported as what it claims to be
+        assert_eq!(
+            emulation.profile(),
+            Some(&EmulationProfile::new("firefox", "129.0"))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_os_falls_back() {
+        let safari = ImpersonateTarget::Safari18.with_os(ImpersonateOs::Windows);
+        assert_eq!(safari.os(), ImpersonateOs::MacOS);
+        assert!(safari.user_agent().contains("Macintosh"));
+
+        let okhttp = ImpersonateTarget::OkHttp5.with_os(ImpersonateOs::MacOS);
+        assert_eq!(okhttp.user_agent(), "okhttp/5.0.0");
+    }
+}
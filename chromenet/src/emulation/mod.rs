@@ -5,11 +5,26 @@
 //! - HTTP/2 fingerprinting (settings, priorities, pseudo-order)
 //! - HTTP/1.1 options
 //! - Default headers (User-Agent, Accept, etc.)
+//! - [`useragent`]: User-Agent parsing and client hint consistency
+//! - [`document`]: emulations saved to and loaded from JSON documents
+//!
+//! The predefined browser [`profiles`] and [`impersonate`] need the
+//! `emulation-profiles` feature; custom emulations built with
+//! [`EmulationBuilder`] are always available.
 
+pub mod document;
 mod factory;
+#[cfg(feature = "emulation-profiles")]
+pub mod impersonate;
+#[cfg(feature = "emulation-profiles")]
 pub mod profiles;
+pub mod useragent;
 
-pub use factory::{Emulation, EmulationBuilder, EmulationFactory};
+pub use document::EmulationDocument;
+pub use factory::{Emulation, EmulationBuilder, EmulationFactory, EmulationProfile};
+#[cfg(feature = "emulation-profiles")]
+pub use impersonate::{Impersonate, ImpersonateOs};
+pub use useragent::{UaConsistency, UserAgent};
 
 use crate::http::H2Fingerprint;
 
@@ -79,6 +94,26 @@ impl Http2Options {
     pub fn builder() -> Http2OptionsBuilder {
         Http2OptionsBuilder::default()
     }
+
+    /// Settings set on these options, as (identifier, value) pairs for
+    /// [`TlsOptionsBuilder::alps_h2_settings`](crate::socket::tls::TlsOptionsBuilder::alps_h2_settings).
+    ///
+    /// Only settings set directly count, not those of the fingerprint,
+    /// matching browsers that announce just the settings they change.
+    pub fn alps_settings(&self) -> Vec<(u16, u32)> {
+        use crate::socket::tls::alps;
+        [
+            (alps::HEADER_TABLE_SIZE, self.header_table_size),
+            (alps::ENABLE_PUSH, self.enable_push.map(u32::from)),
+            (alps::MAX_CONCURRENT_STREAMS, self.max_concurrent_streams),
+            (alps::INITIAL_WINDOW_SIZE, self.initial_window_size),
+            (alps::MAX_FRAME_SIZE, self.max_frame_size),
+            (alps::MAX_HEADER_LIST_SIZE, self.max_header_list_size),
+        ]
+        .into_iter()
+        .filter_map(|(id, value)| Some((id, value?)))
+        .collect()
+    }
 }
 
 /// Builder for Http2Options.
@@ -4,7 +4,8 @@
 //! Uses `std::sync::LazyLock` to cache profiles for efficient reuse.
 
 use crate::emulation::{Emulation, EmulationFactory, Http2Options};
-use crate::socket::tls::{AlpnProtocol, TlsOptions, TlsVersion};
+use crate::http::PriorityHeader;
+use crate::socket::tls::{AlpnProtocol, AlpsProtocol, CertCompressAlg, TlsOptions, TlsVersion};
 use http::{header, HeaderMap, HeaderValue};
 use std::sync::LazyLock;
 
@@ -195,21 +196,40 @@ impl Chrome {
 
 /// Create Chrome emulation for a specific version.
 fn chrome_emulation(version: &'static str) -> Emulation {
-    let tls = chrome_tls_options();
     let h2 = chrome_h2_options();
+    let tls = chrome_tls_options(&h2);
     let headers = chrome_headers(version);
 
     Emulation::builder()
+        .profile("chrome", version)
         .tls_options(tls)
         .http2_options(h2)
         .headers(headers)
+        .priority_header(chromium_priority_header(version))
         .build()
 }
 
-/// Chrome TLS configuration.
-fn chrome_tls_options() -> TlsOptions {
+/// Chromium sends the `priority` header on HTTP/2 and HTTP/3 since
+/// version 124.
+pub(super) fn chromium_priority_header(version: &str) -> PriorityHeader {
+    let major: u16 = version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok())
+        .unwrap_or(0);
+    if major >= 124 {
+        PriorityHeader::Multiplexed
+    } else {
+        PriorityHeader::Auto
+    }
+}
+
+/// Chrome TLS configuration, announcing the HTTP/2 settings in ALPS.
+fn chrome_tls_options(h2: &Http2Options) -> TlsOptions {
     TlsOptions::builder()
         .alpn_protocols([AlpnProtocol::HTTP2, AlpnProtocol::HTTP1])
+        .alps_protocols([AlpsProtocol::HTTP2])
+        .alps_h2_settings(h2.alps_settings())
         .min_tls_version(TlsVersion::TLS_1_2)
         .max_tls_version(TlsVersion::TLS_1_3)
         .cipher_list(
@@ -229,6 +249,7 @@ fn chrome_tls_options() -> TlsOptions {
         .enable_ocsp_stapling(true)
         .enable_signed_cert_timestamps(true)
         .session_ticket(true)
+        .certificate_compression_algorithms(&[CertCompressAlg::BROTLI])
         .build()
 }
 
@@ -2,8 +2,9 @@
 //!
 //! Edge is Chromium-based, so TLS fingerprint is similar to Chrome.
 
+use super::chrome::chromium_priority_header;
 use crate::emulation::{Emulation, EmulationFactory, Http2Options};
-use crate::socket::tls::{AlpnProtocol, TlsOptions, TlsVersion};
+use crate::socket::tls::{AlpnProtocol, AlpsProtocol, CertCompressAlg, TlsOptions, TlsVersion};
 use http::{header, HeaderMap, HeaderValue};
 
 /// Edge browser versions for emulation.
@@ -76,21 +77,26 @@ impl Edge {
 
 /// Create Edge emulation for a specific version.
 fn edge_emulation(version: &'static str) -> Emulation {
-    let tls = edge_tls_options();
     let h2 = edge_h2_options();
+    let tls = edge_tls_options(&h2);
     let headers = edge_headers(version);
 
     Emulation::builder()
+        .profile("edge", version)
         .tls_options(tls)
         .http2_options(h2)
         .headers(headers)
+        .priority_header(chromium_priority_header(version))
         .build()
 }
 
-/// Edge TLS configuration (Chromium-based).
-fn edge_tls_options() -> TlsOptions {
+/// Edge TLS configuration (Chromium-based), with the HTTP/2 settings in
+/// ALPS.
+fn edge_tls_options(h2: &Http2Options) -> TlsOptions {
     TlsOptions::builder()
         .alpn_protocols([AlpnProtocol::HTTP2, AlpnProtocol::HTTP1])
+        .alps_protocols([AlpsProtocol::HTTP2])
+        .alps_h2_settings(h2.alps_settings())
         .min_tls_version(TlsVersion::TLS_1_2)
         .max_tls_version(TlsVersion::TLS_1_3)
         .cipher_list(
@@ -109,6 +115,7 @@ fn edge_tls_options() -> TlsOptions {
         .permute_extensions(true)
         .enable_ocsp_stapling(true)
         .enable_signed_cert_timestamps(true)
+        .certificate_compression_algorithms(&[CertCompressAlg::BROTLI])
         .build()
 }
 
@@ -3,7 +3,8 @@
 //! Provides emulation configurations for various Firefox versions.
 
 use crate::emulation::{Emulation, EmulationFactory, Http2Options};
-use crate::socket::tls::{AlpnProtocol, TlsOptions, TlsVersion};
+use crate::http::PriorityHeader;
+use crate::socket::tls::{AlpnProtocol, CertCompressAlg, TlsOptions, TlsVersion};
 use http::{header, HeaderMap, HeaderValue};
 
 /// Firefox browser versions for emulation.
@@ -103,9 +104,12 @@ fn firefox_emulation(version: &'static str, is_private: bool, is_android: bool)
     let headers = firefox_headers(version, is_private, is_android);
 
     Emulation::builder()
+        .profile("firefox", version)
         .tls_options(tls)
         .http2_options(h2)
         .headers(headers)
+        // Firefox sends it on HTTP/1.1 too
+        .priority_header(PriorityHeader::Always)
         .build()
 }
 
@@ -135,6 +139,7 @@ fn firefox_tls_options() -> TlsOptions {
         .enable_ocsp_stapling(true)
         .enable_signed_cert_timestamps(true)
         .session_ticket(true)
+        .certificate_compression_algorithms(&[CertCompressAlg::ZLIB, CertCompressAlg::BROTLI])
         .build()
 }
 
@@ -192,7 +197,6 @@ fn firefox_headers(version: &str, is_private: bool, is_android: bool) -> HeaderM
     headers.insert("sec-fetch-mode", HeaderValue::from_static("navigate"));
     headers.insert("sec-fetch-site", HeaderValue::from_static("none"));
     headers.insert("sec-fetch-user", HeaderValue::from_static("?1"));
-    headers.insert("priority", HeaderValue::from_static("u=1"));
 
     // Private mode hint (not actually sent, but useful for testing)
     if is_private {
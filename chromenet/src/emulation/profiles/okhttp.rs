@@ -146,6 +146,7 @@ fn okhttp_emulation(cipher_list: &'static str, max_tls: TlsVersion, ua: &'static
     );
 
     Emulation::builder()
+        .profile("okhttp", ua.trim_start_matches("okhttp/"))
         .tls_options(tls)
         .http2_options(h2)
         .headers(headers)
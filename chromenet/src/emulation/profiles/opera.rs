@@ -3,8 +3,9 @@
 //! Provides emulation configurations for Opera browser.
 //! Opera is Chromium-based with similar TLS/H2 fingerprints.
 
+use super::chrome::chromium_priority_header;
 use crate::emulation::{Emulation, EmulationFactory, Http2Options};
-use crate::socket::tls::{AlpnProtocol, TlsOptions, TlsVersion};
+use crate::socket::tls::{AlpnProtocol, AlpsProtocol, CertCompressAlg, TlsOptions, TlsVersion};
 use http::{header, HeaderMap, HeaderValue};
 
 /// Opera browser versions for emulation.
@@ -72,9 +73,19 @@ pub fn opera_v119() -> Emulation {
 
 /// Create Opera emulation (Chromium-based).
 fn opera_emulation(opera_version: &str, chromium_version: &str, sec_ch_ua: &str) -> Emulation {
+    // Same H2 settings as Chrome
+    let h2 = Http2Options::builder()
+        .initial_window_size(6291456)
+        .max_header_list_size(262144)
+        .header_table_size(65536)
+        .enable_push(false)
+        .build();
+
     // Opera uses Chromium's TLS stack
     let tls = TlsOptions::builder()
         .alpn_protocols([AlpnProtocol::HTTP2, AlpnProtocol::HTTP1])
+        .alps_protocols([AlpsProtocol::HTTP2])
+        .alps_h2_settings(h2.alps_settings())
         .min_tls_version(TlsVersion::TLS_1_2)
         .max_tls_version(TlsVersion::TLS_1_3)
         .cipher_list(
@@ -94,14 +105,7 @@ fn opera_emulation(opera_version: &str, chromium_version: &str, sec_ch_ua: &str)
         .enable_ocsp_stapling(true)
         .enable_signed_cert_timestamps(true)
         .session_ticket(true)
-        .build();
-
-    // Same H2 settings as Chrome
-    let h2 = Http2Options::builder()
-        .initial_window_size(6291456)
-        .max_header_list_size(262144)
-        .header_table_size(65536)
-        .enable_push(false)
+        .certificate_compression_algorithms(&[CertCompressAlg::BROTLI])
         .build();
 
     let mut headers = HeaderMap::new();
@@ -150,8 +154,10 @@ fn opera_emulation(opera_version: &str, chromium_version: &str, sec_ch_ua: &str)
     headers.insert("sec-fetch-user", HeaderValue::from_static("?1"));
 
     Emulation::builder()
+        .profile("opera", opera_version)
         .tls_options(tls)
         .http2_options(h2)
         .headers(headers)
+        .priority_header(chromium_priority_header(chromium_version))
         .build()
 }
@@ -3,7 +3,7 @@
 //! Provides emulation configurations for various Safari versions.
 
 use crate::emulation::{Emulation, EmulationFactory, Http2Options};
-use crate::socket::tls::{AlpnProtocol, TlsOptions, TlsVersion};
+use crate::socket::tls::{AlpnProtocol, CertCompressAlg, TlsOptions, TlsVersion};
 use http::{header, HeaderMap, HeaderValue};
 
 /// Safari browser versions for emulation.
@@ -121,6 +121,7 @@ fn safari_emulation(version: &'static str, platform: SafariPlatform) -> Emulatio
     let headers = safari_headers(version, platform);
 
     Emulation::builder()
+        .profile("safari", version)
         .tls_options(tls)
         .http2_options(h2)
         .headers(headers)
@@ -152,6 +153,7 @@ fn safari_tls_options() -> TlsOptions {
         .permute_extensions(false)
         .enable_ocsp_stapling(true)
         .session_ticket(true)
+        .certificate_compression_algorithms(&[CertCompressAlg::ZLIB])
         .build()
 }
 
@@ -0,0 +1,603 @@
+//! User-Agent parsing and client hint consistency.
+//!
+//! Chromium-based browsers describe themselves twice: in the `User-Agent`
+//! string and in the `Sec-CH-UA`, `Sec-CH-UA-Mobile` and
+//! `Sec-CH-UA-Platform` client hints. Anti-bot checks compare the two, and
+//! compare both with the TLS and HTTP/2 fingerprint. Replacing only the
+//! User-Agent of an emulation profile breaks that agreement.
+//!
+//! [`UserAgent`] derives the client hints a browser would send with a given
+//! User-Agent, and [`check`] lists where a set of request headers disagrees
+//! with itself or with the emulated fingerprint. The client applies this
+//! with [`ClientBuilder::ua_consistency`](crate::ClientBuilder::ua_consistency).
+
+use http::{header, HeaderMap};
+use std::fmt;
+
+/// Browser named by a User-Agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UaBrand {
+    /// Google Chrome, including Chrome on iOS (`CriOS`)
+    Chrome,
+    /// Microsoft Edge
+    Edge,
+    /// Opera
+    Opera,
+    /// Chromium or another Chromium build without its own brand token
+    Chromium,
+    /// Firefox, including Firefox on iOS (`FxiOS`)
+    Firefox,
+    /// Safari
+    Safari,
+    /// Anything else, e.g. `curl/8.0` or `okhttp/4.12.0`
+    Other,
+}
+
+impl UaBrand {
+    /// Brand in `Sec-CH-UA`, next to `Chromium`. `None` for browsers that
+    /// send no client hints or only the `Chromium` brand.
+    fn ch_brand(self) -> Option<&'static str> {
+        match self {
+            UaBrand::Chrome => Some("Google Chrome"),
+            UaBrand::Edge => Some("Microsoft Edge"),
+            UaBrand::Opera => Some("Opera"),
+            _ => None,
+        }
+    }
+}
+
+/// Browser engine, which decides the TLS and HTTP/2 fingerprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Engine {
+    /// Chromium-based browsers
+    Blink,
+    /// Firefox
+    Gecko,
+    /// Safari and every browser on iOS
+    WebKit,
+}
+
+/// Operating system named by a User-Agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UaPlatform {
+    /// Windows
+    Windows,
+    /// macOS
+    MacOS,
+    /// Linux desktop
+    Linux,
+    /// Chrome OS
+    ChromeOS,
+    /// Android
+    Android,
+    /// iOS and iPadOS
+    IOS,
+}
+
+impl UaPlatform {
+    /// `Sec-CH-UA-Platform` value, without quotes.
+    pub fn ch_platform(self) -> &'static str {
+        match self {
+            UaPlatform::Windows => "Windows",
+            UaPlatform::MacOS => "macOS",
+            UaPlatform::Linux => "Linux",
+            UaPlatform::ChromeOS => "Chrome OS",
+            UaPlatform::Android => "Android",
+            UaPlatform::IOS => "iOS",
+        }
+    }
+
+    /// Platform of a reduced Chrome User-Agent, per Chrome's User-Agent
+    /// reduction.
+    fn reduced(self) -> &'static str {
+        match self {
+            UaPlatform::Windows => "Windows NT 10.0; Win64; x64",
+            UaPlatform::MacOS => "Macintosh; Intel Mac OS X 10_15_7",
+            UaPlatform::Linux => "X11; Linux x86_64",
+            UaPlatform::ChromeOS => "X11; CrOS x86_64 14541.0.0",
+            UaPlatform::Android | UaPlatform::IOS => "Linux; Android 10; K",
+        }
+    }
+}
+
+/// What to do when the User-Agent, client hints and fingerprint of a
+/// request disagree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum UaConsistency {
+    /// Send the headers as they are.
+    #[default]
+    Off,
+    /// Rewrite the client hints to match the User-Agent, and log a warning
+    /// if the User-Agent's engine does not match the fingerprint, which
+    /// headers cannot fix.
+    Derive,
+    /// Send the headers as they are and log a warning per mismatch.
+    Warn,
+    /// Fail the request with [`NetError::InconsistentIdentity`].
+    ///
+    /// [`NetError::InconsistentIdentity`]: crate::base::neterror::NetError::InconsistentIdentity
+    Strict,
+}
+
+/// A parsed User-Agent string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserAgent {
+    raw: String,
+    brand: UaBrand,
+    version: Option<u32>,
+    chromium_version: Option<u32>,
+    platform: Option<UaPlatform>,
+    mobile: bool,
+}
+
+impl UserAgent {
+    /// Parse `ua`. Unrecognized strings give [`UaBrand::Other`].
+    pub fn parse(ua: &str) -> Self {
+        let brand = if has_token(ua, &["Edg/", "EdgA/", "EdgiOS/"]) {
+            UaBrand::Edge
+        } else if has_token(ua, &["OPR/"]) {
+            UaBrand::Opera
+        } else if has_token(ua, &["Firefox/", "FxiOS/"]) {
+            UaBrand::Firefox
+        } else if has_token(ua, &["CriOS/"]) {
+            UaBrand::Chrome
+        } else if has_token(ua, &["Chrome/"]) {
+            if has_token(ua, &["Chromium/"]) {
+                UaBrand::Chromium
+            } else {
+                UaBrand::Chrome
+            }
+        } else if has_token(ua, &["Version/"]) && has_token(ua, &["Safari/"]) {
+            UaBrand::Safari
+        } else {
+            UaBrand::Other
+        };
+
+        let version = match brand {
+            UaBrand::Edge => major_version(ua, &["Edg/", "EdgA/", "EdgiOS/"]),
+            UaBrand::Opera => major_version(ua, &["OPR/"]),
+            UaBrand::Chrome | UaBrand::Chromium => major_version(ua, &["Chrome/", "CriOS/"]),
+            UaBrand::Firefox => major_version(ua, &["Firefox/", "FxiOS/"]),
+            UaBrand::Safari => major_version(ua, &["Version/"]),
+            UaBrand::Other => None,
+        };
+
+        let platform = if ua.contains("iPhone") || ua.contains("iPad") || ua.contains("iPod") {
+            Some(UaPlatform::IOS)
+        } else if ua.contains("Android") {
+            Some(UaPlatform::Android)
+        } else if ua.contains("CrOS") {
+            Some(UaPlatform::ChromeOS)
+        } else if ua.contains("Windows") {
+            Some(UaPlatform::Windows)
+        } else if ua.contains("Macintosh") || ua.contains("Mac OS X") {
+            Some(UaPlatform::MacOS)
+        } else if ua.contains("Linux") || ua.contains("X11") {
+            Some(UaPlatform::Linux)
+        } else {
+            None
+        };
+
+        Self {
+            raw: ua.to_string(),
+            brand,
+            version,
+            chromium_version: major_version(ua, &["Chrome/", "CriOS/"]),
+            platform,
+            mobile: ua.contains("Mobile"),
+        }
+    }
+
+    /// The User-Agent string.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The browser.
+    pub fn brand(&self) -> UaBrand {
+        self.brand
+    }
+
+    /// Major version of the browser, e.g. 124 for `Chrome/124.0.6367.60`.
+    pub fn major_version(&self) -> Option<u32> {
+        self.version
+    }
+
+    /// The operating system.
+    pub fn platform(&self) -> Option<UaPlatform> {
+        self.platform
+    }
+
+    /// Whether the User-Agent is a phone's.
+    pub fn is_mobile(&self) -> bool {
+        self.mobile
+    }
+
+    /// The browser's engine. `None` for non-browser clients.
+    pub fn engine(&self) -> Option<Engine> {
+        match self.brand {
+            UaBrand::Other => None,
+            _ if self.platform == Some(UaPlatform::IOS) => Some(Engine::WebKit),
+            UaBrand::Firefox => Some(Engine::Gecko),
+            UaBrand::Safari => Some(Engine::WebKit),
+            _ => Some(Engine::Blink),
+        }
+    }
+
+    /// Whether the browser sends `Sec-CH-UA` client hints: Chromium-based
+    /// browsers, except on iOS.
+    pub fn sends_client_hints(&self) -> bool {
+        self.engine() == Some(Engine::Blink)
+    }
+
+    /// The low-entropy client hints the browser sends with every request,
+    /// in the order Chrome sends them. Empty if it sends none.
+    ///
+    /// The GREASE brand and brand order follow Chromium's algorithm, seeded
+    /// with the Chromium major version, so they match the real browser.
+    pub fn client_hints(&self) -> Vec<(&'static str, String)> {
+        let Some(chromium) = self.chromium_version.filter(|_| self.sends_client_hints()) else {
+            return Vec::new();
+        };
+        let mut brands = vec![("Chromium".to_string(), chromium)];
+        if let Some(brand) = self.brand.ch_brand() {
+            brands.push((brand.to_string(), self.version.unwrap_or(chromium)));
+        }
+        let platform = self.platform.map_or("", UaPlatform::ch_platform);
+        vec![
+            ("sec-ch-ua", brand_list(chromium, brands)),
+            (
+                "sec-ch-ua-mobile",
+                if self.mobile { "?1" } else { "?0" }.into(),
+            ),
+            ("sec-ch-ua-platform", format!("\"{}\"", platform)),
+        ]
+    }
+
+    /// The User-Agent with Chrome's User-Agent reduction applied: minor
+    /// versions zeroed and a fixed platform per OS. `None` for browsers
+    /// that do not reduce it.
+    pub fn reduced(&self) -> Option<String> {
+        let chromium = self.chromium_version?;
+        if !self.sends_client_hints() {
+            return None;
+        }
+        let platform = self.platform.unwrap_or(UaPlatform::Windows);
+        let mobile = if self.mobile { " Mobile" } else { "" };
+        let suffix = match self.brand {
+            UaBrand::Edge if platform == UaPlatform::Android => {
+                format!(" EdgA/{}.0.0.0", self.version?)
+            }
+            UaBrand::Edge => format!(" Edg/{}.0.0.0", self.version?),
+            UaBrand::Opera => format!(" OPR/{}.0.0.0", self.version?),
+            _ => String::new(),
+        };
+        Some(format!(
+            "Mozilla/5.0 ({}) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{}.0.0.0{} Safari/537.36{}",
+            platform.reduced(),
+            chromium,
+            mobile,
+            suffix
+        ))
+    }
+}
+
+impl fmt::Display for UserAgent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// A disagreement found by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentityMismatch {
+    /// The User-Agent names a browser with another engine than the one
+    /// whose TLS and HTTP/2 fingerprint is used.
+    Engine {
+        /// Engine of the User-Agent.
+        user_agent: Engine,
+        /// Engine of the fingerprint.
+        fingerprint: Engine,
+    },
+    /// No `Sec-CH-UA` header, though the User-Agent's browser sends one.
+    MissingClientHints,
+    /// `Sec-CH-UA` headers, though the User-Agent's browser sends none.
+    UnexpectedClientHints,
+    /// A client hint disagrees with the User-Agent.
+    ClientHint {
+        /// Header name.
+        name: &'static str,
+        /// Value matching the User-Agent.
+        expected: String,
+        /// Value sent.
+        actual: String,
+    },
+}
+
+impl IdentityMismatch {
+    /// Whether rewriting the client hints fixes this mismatch.
+    pub fn is_client_hint(&self) -> bool {
+        !matches!(self, IdentityMismatch::Engine { .. })
+    }
+}
+
+impl fmt::Display for IdentityMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdentityMismatch::Engine {
+                user_agent,
+                fingerprint,
+            } => write!(
+                f,
+                "User-Agent is {:?} but the TLS/HTTP2 fingerprint is {:?}",
+                user_agent, fingerprint
+            ),
+            IdentityMismatch::MissingClientHints => {
+                f.write_str("User-Agent sends client hints but sec-ch-ua is missing")
+            }
+            IdentityMismatch::UnexpectedClientHints => {
+                f.write_str("sec-ch-ua is sent but the User-Agent sends no client hints")
+            }
+            IdentityMismatch::ClientHint {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} is {} but the User-Agent implies {}",
+                name, actual, expected
+            ),
+        }
+    }
+}
+
+/// Compare the `User-Agent` of `headers` with its client hints, and with
+/// `fingerprint_ua`, the User-Agent of the emulation profile whose TLS and
+/// HTTP/2 fingerprint is used.
+///
+/// Requests without a User-Agent, or with one of a non-browser client, have
+/// nothing to compare and give no mismatches.
+pub fn check(headers: &HeaderMap, fingerprint_ua: Option<&str>) -> Vec<IdentityMismatch> {
+    check_with(
+        |name| headers.get(name).and_then(|v| v.to_str().ok()),
+        fingerprint_ua,
+    )
+}
+
+/// [`check`] over headers looked up with `header`.
+pub(crate) fn check_with<'a>(
+    header: impl Fn(&str) -> Option<&'a str>,
+    fingerprint_ua: Option<&str>,
+) -> Vec<IdentityMismatch> {
+    let Some(ua) = header(header::USER_AGENT.as_str()).map(UserAgent::parse) else {
+        return Vec::new();
+    };
+    let Some(engine) = ua.engine() else {
+        return Vec::new();
+    };
+    let mut mismatches = Vec::new();
+
+    if let Some(fingerprint) = fingerprint_ua.and_then(|f| UserAgent::parse(f).engine()) {
+        if fingerprint != engine {
+            mismatches.push(IdentityMismatch::Engine {
+                user_agent: engine,
+                fingerprint,
+            });
+        }
+    }
+
+    let sec_ch_ua = header("sec-ch-ua");
+    let hints = ua.client_hints();
+    if hints.is_empty() {
+        if sec_ch_ua.is_some() {
+            mismatches.push(IdentityMismatch::UnexpectedClientHints);
+        }
+        return mismatches;
+    }
+    let Some(sec_ch_ua) = sec_ch_ua else {
+        mismatches.push(IdentityMismatch::MissingClientHints);
+        return mismatches;
+    };
+
+    for (name, expected) in hints {
+        let matches = if name == "sec-ch-ua" {
+            same_brands(sec_ch_ua, &expected)
+        } else {
+            header(name) == Some(expected.as_str())
+        };
+        if !matches {
+            mismatches.push(IdentityMismatch::ClientHint {
+                name,
+                actual: header(name).unwrap_or_default().to_string(),
+                expected,
+            });
+        }
+    }
+    mismatches
+}
+
+fn has_token(ua: &str, tokens: &[&str]) -> bool {
+    tokens.iter().any(|t| ua.contains(t))
+}
+
+/// Major version after the first of `tokens` found in `ua`.
+fn major_version(ua: &str, tokens: &[&str]) -> Option<u32> {
+    tokens.iter().find_map(|token| {
+        let rest = &ua[ua.find(token)? + token.len()..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        rest[..end].parse().ok()
+    })
+}
+
+/// `Sec-CH-UA` value for `brands` plus a GREASE brand, per Chromium's
+/// `GenerateBrandVersionList`.
+fn brand_list(seed: u32, mut brands: Vec<(String, u32)>) -> String {
+    const GREASE_CHARS: [char; 11] = [' ', '(', ':', '-', '.', '/', ')', ';', '=', '?', '_'];
+    const GREASE_VERSIONS: [u32; 3] = [8, 99, 24];
+    let seed = seed as usize;
+    let grease = format!(
+        "Not{}A{}Brand",
+        GREASE_CHARS[seed % GREASE_CHARS.len()],
+        GREASE_CHARS[(seed + 1) % GREASE_CHARS.len()]
+    );
+    brands.insert(0, (grease, GREASE_VERSIONS[seed % GREASE_VERSIONS.len()]));
+
+    let order: &[usize] = match brands.len() {
+        2 => [[0, 1], [1, 0]][seed % 2].as_slice(),
+        _ => [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ][seed % 6]
+            .as_slice(),
+    };
+    let mut shuffled = vec![None; brands.len()];
+    for (brand, &position) in brands.into_iter().zip(order) {
+        shuffled[position] = Some(brand);
+    }
+    shuffled
+        .into_iter()
+        .flatten()
+        .map(|(brand, version)| format!("\"{}\";v=\"{}\"", brand, version))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Whether two `Sec-CH-UA` values list the same real brands and versions,
+/// ignoring the GREASE brand and the order.
+fn same_brands(actual: &str, expected: &str) -> bool {
+    fn real_brands(value: &str) -> Vec<(String, String)> {
+        let mut brands: Vec<_> = value
+            .split(',')
+            .filter_map(|entry| {
+                let (brand, version) = entry.trim().split_once(";v=")?;
+                let brand = brand.trim_matches('"');
+                (!brand.starts_with("Not"))
+                    .then(|| (brand.to_string(), version.trim_matches('"').to_string()))
+            })
+            .collect();
+        brands.sort();
+        brands
+    }
+    real_brands(actual) == real_brands(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    const CHROME_MAC: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/128.0.6613.120 Safari/537.36";
+    const FIREFOX: &str =
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:129.0) Gecko/20100101 Firefox/129.0";
+
+    fn hint<'a>(hints: &'a [(&str, String)], name: &str) -> &'a str {
+        &hints.iter().find(|(n, _)| *n == name).unwrap().1
+    }
+
+    #[test]
+    fn test_parse() {
+        let ua = UserAgent::parse(CHROME_MAC);
+        assert_eq!(ua.brand(), UaBrand::Chrome);
+        assert_eq!(ua.major_version(), Some(128));
+        assert_eq!(ua.platform(), Some(UaPlatform::MacOS));
+        assert_eq!(ua.engine(), Some(Engine::Blink));
+
+        let edge = UserAgent::parse("Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36 EdgA/124.0.2478.64");
+        assert_eq!(edge.brand(), UaBrand::Edge);
+        assert_eq!(edge.platform(), Some(UaPlatform::Android));
+        assert!(edge.is_mobile());
+
+        let crios = UserAgent::parse("Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) CriOS/128.0.0.0 Mobile/15E148 Safari/604.1");
+        assert_eq!(crios.brand(), UaBrand::Chrome);
+        assert_eq!(crios.engine(), Some(Engine::WebKit));
+        assert!(!crios.sends_client_hints());
+
+        assert_eq!(UserAgent::parse(FIREFOX).engine(), Some(Engine::Gecko));
+        assert_eq!(UserAgent::parse("curl/8.5.0").engine(), None);
+    }
+
+    #[test]
+    fn test_client_hints_match_real_browsers() {
+        let hints = UserAgent::parse(CHROME_MAC).client_hints();
+        assert_eq!(
+            hint(&hints, "sec-ch-ua"),
+            r#""Chromium";v="128", "Not;A=Brand";v="24", "Google Chrome";v="128""#
+        );
+        assert_eq!(hint(&hints, "sec-ch-ua-mobile"), "?0");
+        assert_eq!(hint(&hints, "sec-ch-ua-platform"), "\"macOS\"");
+
+        let chrome124 = UserAgent::parse(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+        );
+        assert_eq!(
+            hint(&chrome124.client_hints(), "sec-ch-ua"),
+            r#""Chromium";v="124", "Google Chrome";v="124", "Not-A.Brand";v="99""#
+        );
+
+        let opera = UserAgent::parse("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36 OPR/116.0.0.0");
+        assert_eq!(
+            hint(&opera.client_hints(), "sec-ch-ua"),
+            r#""Opera";v="116", "Chromium";v="131", "Not_A Brand";v="24""#
+        );
+
+        assert!(UserAgent::parse(FIREFOX).client_hints().is_empty());
+    }
+
+    #[test]
+    fn test_reduced() {
+        let ua = UserAgent::parse("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.6367.82 Mobile Safari/537.36");
+        assert_eq!(
+            ua.reduced().unwrap(),
+            "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36"
+        );
+        assert_eq!(UserAgent::parse(FIREFOX).reduced(), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::USER_AGENT, HeaderValue::from_static(CHROME_MAC));
+        for (name, value) in UserAgent::parse(CHROME_MAC).client_hints() {
+            headers.insert(name, value.parse().unwrap());
+        }
+        assert!(check(&headers, Some(CHROME_MAC)).is_empty());
+
+        // Brand order and GREASE do not matter, the platform does
+        headers.insert(
+            "sec-ch-ua",
+            HeaderValue::from_static(
+                r#""Google Chrome";v="128", "Not/A)Brand";v="8", "Chromium";v="128""#,
+            ),
+        );
+        headers.insert(
+            "sec-ch-ua-platform",
+            HeaderValue::from_static("\"Windows\""),
+        );
+        assert_eq!(
+            check(&headers, None),
+            [IdentityMismatch::ClientHint {
+                name: "sec-ch-ua-platform",
+                expected: "\"macOS\"".into(),
+                actual: "\"Windows\"".into(),
+            }]
+        );
+
+        headers.insert(header::USER_AGENT, HeaderValue::from_static(FIREFOX));
+        assert_eq!(
+            check(&headers, Some(CHROME_MAC)),
+            [
+                IdentityMismatch::Engine {
+                    user_agent: Engine::Gecko,
+                    fingerprint: Engine::Blink,
+                },
+                IdentityMismatch::UnexpectedClientHints,
+            ]
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Entry points for the fuzz targets in `fuzz/`.
+//!
+//! Only compiled under `cfg(fuzzing)`, which cargo-fuzz sets. Exposes
+//! parsers that are otherwise private, fed from in-memory streams instead of
+//! sockets.
+
+use crate::base::neterror::NetError;
+use crate::http::response::HttpResponse;
+use crate::socket::connectjob::ConnectJob;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue};
+use http_body_util::Empty;
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use url::Url;
+
+/// Largest SOCKS5 reply accepted, so the proxy side can write it up front.
+pub const MAX_STREAM_INPUT: usize = 64 * 1024;
+
+/// Parse `value` as a Cache-Control header.
+pub fn parse_cache_control(value: &[u8]) {
+    let Ok(value) = HeaderValue::from_bytes(value) else {
+        return;
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(http::header::CACHE_CONTROL, value);
+    let _ = crate::http::httpcache::parse_cache_control(&headers);
+}
+
+/// Run a SOCKS5 handshake against a proxy that answers with `reply`.
+pub async fn socks5_handshake(reply: &[u8]) -> Result<(), NetError> {
+    let (mut client, _proxy) = peer_with(reply).await;
+    let url = Url::parse("http://example.com/").unwrap();
+    ConnectJob::socks5_handshake(&mut client, &url).await
+}
+
+/// Read the body of a chunked HTTP/1.1 response whose body bytes are
+/// `body`.
+pub async fn read_chunked_body(body: &[u8]) -> Result<Bytes, NetError> {
+    let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    response.extend_from_slice(body);
+    let (client, mut server) = tokio::io::duplex(MAX_STREAM_INPUT);
+
+    // Answer only once the request is in, as hyper rejects early responses
+    tokio::spawn(async move {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            match server.read(&mut buf).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => request.extend_from_slice(&buf[..n]),
+            }
+        }
+        let _ = server.write_all(&response).await;
+        let _ = server.shutdown().await;
+        let _ = tokio::io::copy(&mut server, &mut tokio::io::sink()).await;
+    });
+
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(client))
+        .await
+        .map_err(|_| NetError::ConnectionFailed)?;
+    tokio::spawn(conn);
+
+    let request = http::Request::get("/")
+        .header(http::header::HOST, "example.com")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let resp = sender
+        .send_request(request)
+        .await
+        .map_err(|_| NetError::InvalidResponse)?;
+    HttpResponse::from_hyper(resp).bytes().await
+}
+
+/// A stream whose peer has already written `data` and then closed its write
+/// side. The peer is returned so writes to the stream keep succeeding.
+async fn peer_with(data: &[u8]) -> (DuplexStream, DuplexStream) {
+    let data = &data[..data.len().min(MAX_STREAM_INPUT)];
+    let (stream, mut peer) = tokio::io::duplex(MAX_STREAM_INPUT + 1024);
+    peer.write_all(data).await.unwrap();
+    peer.shutdown().await.unwrap();
+    (stream, peer)
+}
@@ -0,0 +1,93 @@
+//! Sending many requests with bounded concurrency.
+//!
+//! Crawlers and API clients often send hundreds of requests and want the
+//! results back in order. Hand-rolled `buffered` or `FuturesUnordered`
+//! loops easily starve the connection pool: a response waiting for earlier
+//! ones to finish keeps its connection busy while its body is unread, and
+//! once a host's connections are all held that way, the remaining requests
+//! to it wait for connections that are only released after they complete.
+//!
+//! A [`Batch`] reads each body before its request counts as done, so
+//! connections return to the pool right away. Requests still go through
+//! the client's [`RateLimiter`](crate::http::RateLimiter), and a batch
+//! [`priority`](Batch::priority) lets a background crawl yield connections
+//! to more urgent requests of the same client.
+
+use crate::base::neterror::NetError;
+use crate::client::RequestBuilder;
+use crate::http::response::HttpResponse;
+use crate::socket::pool::RequestPriority;
+use futures::{Stream, StreamExt};
+
+/// Requests in flight by default, the connection limit per host.
+const DEFAULT_BUFFERED: usize = 6;
+
+/// Requests sent concurrently, with results in input order.
+///
+/// Each result carries its own error, so one failed request does not
+/// affect the others. Responses come with their body already read, subject
+/// to the body limit and timeout of the request.
+pub struct Batch {
+    requests: Vec<RequestBuilder>,
+    buffered: usize,
+    priority: Option<RequestPriority>,
+}
+
+impl Batch {
+    /// Batch of `requests`, sent six at a time.
+    pub fn new<I>(requests: I) -> Self
+    where
+        I: IntoIterator<Item = RequestBuilder>,
+    {
+        Self {
+            requests: requests.into_iter().collect(),
+            buffered: DEFAULT_BUFFERED,
+            priority: None,
+        }
+    }
+
+    /// Keep up to `n` requests in flight. Requests beyond a host's
+    /// connection limit wait for a connection.
+    pub fn buffered(mut self, n: usize) -> Self {
+        self.buffered = n.max(1);
+        self
+    }
+
+    /// Send every request of the batch with `priority`, see
+    /// [`RequestBuilder::priority`].
+    pub fn priority(mut self, priority: RequestPriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Number of requests in the batch.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Whether the batch has no requests.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Results in input order, as they become available.
+    ///
+    /// Requests only make progress while the stream is polled.
+    pub fn stream(self) -> impl Stream<Item = Result<HttpResponse, NetError>> {
+        let priority = self.priority;
+        futures::stream::iter(self.requests)
+            .map(move |request| {
+                let request = match priority {
+                    Some(priority) => request.priority(priority),
+                    None => request,
+                };
+                async move { request.send().await?.buffer().await }
+            })
+            .buffered(self.buffered)
+    }
+
+    /// Send all requests and collect the results in input order.
+    pub async fn send(self) -> Vec<Result<HttpResponse, NetError>> {
+        self.stream().collect().await
+    }
+}
@@ -0,0 +1,221 @@
+//! Conditional requests (RFC 9110 section 13).
+//!
+//! Chromium mapping: the validator handling of `HttpCache::Transaction`,
+//! exposed for callers keeping their own copies
+//!
+//! A response's [`Validators`] are its `ETag` and `Last-Modified`. Sent
+//! back as `If-None-Match` and `If-Modified-Since`, they let the server
+//! answer `304 Not Modified` instead of repeating an unchanged body:
+//!
+//! ```no_run
+//! use chromenet::http::conditional::Conditional;
+//! use chromenet::Client;
+//!
+//! # async fn run() -> Result<(), chromenet::base::neterror::NetError> {
+//! let client = Client::new();
+//! let first = client.get("https://example.com/feed").send().await?;
+//! let validators = first.validators();
+//! let body = first.bytes().await?;
+//!
+//! match client.fetch_if_modified("https://example.com/feed", &validators).await? {
+//!     Conditional::NotModified(_) => println!("still {} bytes", body.len()),
+//!     Conditional::Modified(resp) => println!("changed: {:?}", resp.bytes().await?),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::http::httpdate::parse_http_date;
+use crate::http::response::HttpResponse;
+use crate::http::typedheaders;
+use http::{HeaderMap, StatusCode};
+use std::fmt;
+use std::time::SystemTime;
+
+/// An entity tag, the opaque version identifier of an `ETag` header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    tag: String,
+    weak: bool,
+}
+
+impl EntityTag {
+    /// A strong tag: the representation is byte-for-byte identical while
+    /// the tag is.
+    pub fn strong(tag: impl Into<String>) -> Self {
+        Self {
+            tag: tag.into(),
+            weak: false,
+        }
+    }
+
+    /// A weak tag (`W/"..."`): the representation is only equivalent while
+    /// the tag is.
+    pub fn weak(tag: impl Into<String>) -> Self {
+        Self {
+            tag: tag.into(),
+            weak: true,
+        }
+    }
+
+    /// Parse an `ETag` header value such as `"v1"` or `W/"v1"`.
+    ///
+    /// Returns `None` unless the tag is quoted and free of quotes, as RFC
+    /// 9110 section 8.8.3 requires.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        if tag.contains('"') || tag.chars().any(|c| c.is_ascii_control()) {
+            return None;
+        }
+        Some(Self {
+            tag: tag.to_string(),
+            weak,
+        })
+    }
+
+    /// The tag without quotes and weakness marker.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Whether this is a weak tag.
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Strong comparison: both tags are strong and equal. Used for range
+    /// requests.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Weak comparison: the tags are equal, whether weak or not. Used for
+    /// `If-None-Match`.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// The validators of a response, to make a later request for the same
+/// resource conditional.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// `ETag`, sent back as `If-None-Match`
+    pub etag: Option<EntityTag>,
+    /// `Last-Modified`, sent back as `If-Modified-Since`
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// Validators from response headers. Unparsable values are ignored.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        Self {
+            etag: typedheaders::etag(headers),
+            last_modified: header(http::header::LAST_MODIFIED).and_then(parse_http_date),
+        }
+    }
+
+    /// Whether there is nothing to make a request conditional on.
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Outcome of a conditional request.
+pub enum Conditional {
+    /// `304 Not Modified`: the copy the validators came from is current.
+    /// The response has no body, but may update headers such as
+    /// `Cache-Control`.
+    NotModified(HttpResponse),
+    /// A full response, because the resource changed or the server ignored
+    /// the condition.
+    Modified(HttpResponse),
+}
+
+impl Conditional {
+    /// Whether the server sent a new representation.
+    pub fn is_modified(&self) -> bool {
+        matches!(self, Conditional::Modified(_))
+    }
+
+    /// The response, whichever the outcome.
+    pub fn into_response(self) -> HttpResponse {
+        match self {
+            Conditional::NotModified(resp) | Conditional::Modified(resp) => resp,
+        }
+    }
+}
+
+impl From<HttpResponse> for Conditional {
+    fn from(resp: HttpResponse) -> Self {
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            Conditional::NotModified(resp)
+        } else {
+            Conditional::Modified(resp)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entity_tag() {
+        assert_eq!(EntityTag::parse("\"v1\""), Some(EntityTag::strong("v1")));
+        assert_eq!(EntityTag::parse(" W/\"v1\" "), Some(EntityTag::weak("v1")));
+        assert_eq!(EntityTag::parse("\"\""), Some(EntityTag::strong("")));
+        for invalid in ["v1", "\"v1", "w/\"v1\"", "\"a\"b\""] {
+            assert_eq!(EntityTag::parse(invalid), None, "{:?}", invalid);
+        }
+        assert_eq!(EntityTag::weak("v1").to_string(), "W/\"v1\"");
+        assert_eq!(EntityTag::strong("v1").to_string(), "\"v1\"");
+    }
+
+    #[test]
+    fn test_entity_tag_comparison() {
+        // RFC 9110 section 8.8.3.2
+        let cases = [
+            (EntityTag::weak("1"), EntityTag::weak("1"), false, true),
+            (EntityTag::weak("1"), EntityTag::weak("2"), false, false),
+            (EntityTag::weak("1"), EntityTag::strong("1"), false, true),
+            (EntityTag::strong("1"), EntityTag::strong("1"), true, true),
+        ];
+        for (a, b, strong, weak) in cases {
+            assert_eq!(a.strong_eq(&b), strong, "{} {}", a, b);
+            assert_eq!(a.weak_eq(&b), weak, "{} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_validators_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(Validators::from_headers(&headers).is_empty());
+        headers.insert(http::header::ETAG, "W/\"abc\"".parse().unwrap());
+        headers.insert(
+            http::header::LAST_MODIFIED,
+            "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap(),
+        );
+        let validators = Validators::from_headers(&headers);
+        assert_eq!(validators.etag, Some(EntityTag::weak("abc")));
+        assert_eq!(
+            validators.last_modified,
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT")
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Decoders of response content codings.
+//!
+//! Chromium mapping: `FilterSourceStream` and its `GzipSourceStream`,
+//! `BrotliSourceStream` and `ZstdSourceStream`
+//!
+//! A [`ContentDecoders`] registry maps `Content-Encoding` tokens to
+//! [`ContentDecoder`]s. The default one holds the codings browsers send in
+//! `Accept-Encoding`: `gzip` (and `x-gzip`), `deflate`, `br` and `zstd`.
+//! Other codings, such as `lz4` or an in-house one, are added with
+//! [`ContentDecoders::with`]:
+//!
+//! ```no_run
+//! use chromenet::http::contentdecoder::{ContentDecoder, ContentDecoders};
+//! use std::io::{self, Read};
+//!
+//! struct Reverse;
+//!
+//! impl ContentDecoder for Reverse {
+//!     fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+//!         Ok(Box::new(io::Cursor::new(body.iter().rev().copied().collect::<Vec<_>>())))
+//!     }
+//! }
+//!
+//! let client = chromenet::Client::builder()
+//!     .content_decoders(ContentDecoders::default().with("x-reverse", Reverse))
+//!     .build();
+//! ```
+//!
+//! Bodies are decoded whole, once read, and a decoded body over the
+//! registry's limit, 64 MiB by default, fails with
+//! [`NetError::ResponseBodyTooBig`].
+
+use crate::base::neterror::NetError;
+use crate::http::typedheaders;
+use bytes::Bytes;
+use http::HeaderMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+/// Default limit on a decoded body, 64 MiB.
+pub const DEFAULT_MAX_DECODED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Decoder of one content coding.
+pub trait ContentDecoder: Send + Sync {
+    /// Reader of the decoded bytes of `body`.
+    fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>>;
+}
+
+/// `gzip`, RFC 1952.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gzip;
+
+impl ContentDecoder for Gzip {
+    fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(flate2::read::GzDecoder::new(body)))
+    }
+}
+
+/// `deflate`: zlib, RFC 1950.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deflate;
+
+impl ContentDecoder for Deflate {
+    fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(flate2::read::ZlibDecoder::new(body)))
+    }
+}
+
+/// `br`: Brotli, RFC 7932.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Brotli;
+
+impl ContentDecoder for Brotli {
+    fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(brotli_decompressor::Decompressor::new(body, 4096)))
+    }
+}
+
+/// `zstd`: Zstandard, RFC 8878.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Zstd;
+
+impl ContentDecoder for Zstd {
+    fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(zstd::stream::read::Decoder::with_buffer(body)?))
+    }
+}
+
+/// Content decoders by coding.
+#[derive(Clone)]
+pub struct ContentDecoders {
+    decoders: HashMap<String, Arc<dyn ContentDecoder>>,
+    limit: usize,
+}
+
+impl Default for ContentDecoders {
+    /// `gzip`, `x-gzip`, `deflate`, `br` and `zstd`.
+    fn default() -> Self {
+        Self::empty()
+            .with("gzip", Gzip)
+            .with("x-gzip", Gzip)
+            .with("deflate", Deflate)
+            .with("br", Brotli)
+            .with("zstd", Zstd)
+    }
+}
+
+impl fmt::Debug for ContentDecoders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut codings: Vec<_> = self.decoders.keys().collect();
+        codings.sort();
+        f.debug_struct("ContentDecoders")
+            .field("codings", &codings)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl ContentDecoders {
+    /// A registry without decoders, not even the built-in ones.
+    pub fn empty() -> Self {
+        Self {
+            decoders: HashMap::new(),
+            limit: DEFAULT_MAX_DECODED_SIZE,
+        }
+    }
+
+    /// Decode `coding` with `decoder`, replacing any decoder it had.
+    /// Codings are matched ignoring case.
+    pub fn with<D>(mut self, coding: &str, decoder: D) -> Self
+    where
+        D: ContentDecoder + 'static,
+    {
+        self.decoders
+            .insert(coding.trim().to_ascii_lowercase(), Arc::new(decoder));
+        self
+    }
+
+    /// Fail bodies that decode to more than `limit` bytes.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Whether `coding` has a decoder. `identity` always has.
+    pub fn supports(&self, coding: &str) -> bool {
+        let coding = coding.trim().to_ascii_lowercase();
+        coding == "identity" || self.decoders.contains_key(&coding)
+    }
+
+    /// Undo the content codings `headers` list on `body`, last applied
+    /// first.
+    ///
+    /// Fails with [`NetError::ContentDecodingFailed`] for a coding without
+    /// a decoder or a body that does not decode.
+    pub fn decode(&self, headers: &HeaderMap, body: Bytes) -> Result<Bytes, NetError> {
+        let mut body = body;
+        for coding in typedheaders::content_encodings(headers).iter().rev() {
+            let decoder = self
+                .decoders
+                .get(coding)
+                .ok_or(NetError::ContentDecodingFailed)?;
+            let mut decoded = Vec::new();
+            decoder
+                .decoder(&body)
+                .and_then(|reader| reader.take(self.limit as u64 + 1).read_to_end(&mut decoded))
+                .map_err(|_| NetError::ContentDecodingFailed)?;
+            if decoded.len() > self.limit {
+                return Err(NetError::ResponseBodyTooBig { limit: self.limit });
+            }
+            body = decoded.into();
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn headers(content_encoding: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::CONTENT_ENCODING,
+            content_encoding.parse().unwrap(),
+        );
+        headers
+    }
+
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap().into()
+    }
+
+    struct Reverse;
+
+    impl ContentDecoder for Reverse {
+        fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+            let reversed: Vec<u8> = body.iter().rev().copied().collect();
+            Ok(Box::new(io::Cursor::new(reversed)))
+        }
+    }
+
+    #[test]
+    fn test_decode_layers_last_first() {
+        let decoders = ContentDecoders::default().with("X-Reverse", Reverse);
+        let body: Bytes = gzip(b"hello").iter().rev().copied().collect();
+        assert_eq!(
+            decoders
+                .decode(&headers("GZIP, identity, x-reverse"), body)
+                .unwrap(),
+            "hello"
+        );
+        assert!(decoders.supports("x-reverse"));
+        assert!(!ContentDecoders::default().supports("x-reverse"));
+    }
+
+    #[test]
+    fn test_decode_failures() {
+        let decoders = ContentDecoders::default();
+        assert!(matches!(
+            decoders.decode(&headers("lz4"), Bytes::from_static(b"x")),
+            Err(NetError::ContentDecodingFailed)
+        ));
+        assert!(matches!(
+            decoders.decode(&headers("gzip"), Bytes::from_static(b"not gzip")),
+            Err(NetError::ContentDecodingFailed)
+        ));
+        assert!(matches!(
+            decoders
+                .with_limit(4)
+                .decode(&headers("gzip"), gzip(b"hello")),
+            Err(NetError::ResponseBodyTooBig { limit: 4 })
+        ));
+    }
+}
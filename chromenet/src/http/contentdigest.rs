@@ -0,0 +1,237 @@
+//! Integrity fields of request bodies (RFC 9530).
+//!
+//! Some APIs, and HTTP message signatures covering the body, require a
+//! `Content-Digest` or `Repr-Digest` header carrying a hash of the body:
+//!
+//! ```text
+//! Content-Digest: sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:
+//! ```
+//!
+//! Set a [`ContentDigest`] on a request with
+//! [`RequestBuilder::content_digest`](crate::client::RequestBuilder::content_digest),
+//! or on every request with a body with
+//! [`ClientBuilder::content_digest`](crate::ClientBuilder::content_digest).
+//! Streaming bodies are read once and hashed chunk by chunk before the
+//! request is sent, then sent again from the start, so they must be
+//! [rewindable](crate::http::RewindableBody) within their replay limit.
+//!
+//! chromenet never applies a content coding to request bodies, so both
+//! fields hash the bytes as sent.
+
+use crate::base::neterror::NetError;
+use crate::http::requestbody::RequestBody;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use boring::sha::{Sha256, Sha512};
+use http::HeaderValue;
+use http_body_util::BodyExt;
+
+/// Hash algorithm of an integrity field, from the IANA Hash Algorithms for
+/// HTTP Digest Fields registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigestAlgorithm {
+    /// `sha-256`
+    Sha256,
+    /// `sha-512`
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// The algorithm key in the field.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha-256",
+            Self::Sha512 => "sha-512",
+        }
+    }
+}
+
+/// Which integrity fields are sent and with which algorithms.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentDigest {
+    repr: bool,
+    algorithms: Vec<DigestAlgorithm>,
+}
+
+impl ContentDigest {
+    /// Send `Content-Digest` with `algorithm`.
+    pub fn new(algorithm: DigestAlgorithm) -> Self {
+        Self {
+            repr: false,
+            algorithms: vec![algorithm],
+        }
+    }
+
+    /// Send `Repr-Digest` with `algorithm` instead.
+    pub fn repr(algorithm: DigestAlgorithm) -> Self {
+        Self {
+            repr: true,
+            ..Self::new(algorithm)
+        }
+    }
+
+    /// Also hash with `algorithm`, as another member of the field.
+    pub fn with_algorithm(mut self, algorithm: DigestAlgorithm) -> Self {
+        if !self.algorithms.contains(&algorithm) {
+            self.algorithms.push(algorithm);
+        }
+        self
+    }
+
+    /// Name of the field, `content-digest` or `repr-digest`.
+    pub fn header_name(&self) -> &'static str {
+        if self.repr {
+            "repr-digest"
+        } else {
+            "content-digest"
+        }
+    }
+
+    /// Start hashing a body fed in chunks.
+    pub fn hasher(&self) -> DigestHasher {
+        DigestHasher {
+            states: self
+                .algorithms
+                .iter()
+                .map(|algorithm| match algorithm {
+                    DigestAlgorithm::Sha256 => HashState::Sha256(Sha256::new()),
+                    DigestAlgorithm::Sha512 => HashState::Sha512(Box::new(Sha512::new())),
+                })
+                .collect(),
+        }
+    }
+
+    /// The field value for `body`.
+    pub fn digest(&self, body: &[u8]) -> HeaderValue {
+        let mut hasher = self.hasher();
+        hasher.update(body);
+        hasher.finish()
+    }
+
+    /// The field value for `body`, reading a streaming body to its end.
+    ///
+    /// A buffered [`RewindableBody`](crate::http::RewindableBody) larger
+    /// than its replay limit can be hashed but not sent afterwards; sending
+    /// it fails with [`NetError::UploadStreamRewindNotSupported`].
+    pub async fn digest_body(&self, body: &RequestBody) -> Result<HeaderValue, NetError> {
+        let mut hasher = self.hasher();
+        let mut body = body.open()?;
+        while let Some(frame) = body.frame().await {
+            if let Ok(data) = frame?.into_data() {
+                hasher.update(&data);
+            }
+        }
+        Ok(hasher.finish())
+    }
+}
+
+/// Incremental hash of a body for an integrity field.
+pub struct DigestHasher {
+    states: Vec<HashState>,
+}
+
+enum HashState {
+    Sha256(Sha256),
+    Sha512(Box<Sha512>),
+}
+
+impl DigestHasher {
+    /// Hash the next chunk of the body.
+    pub fn update(&mut self, chunk: &[u8]) {
+        for state in &mut self.states {
+            match state {
+                HashState::Sha256(hasher) => hasher.update(chunk),
+                HashState::Sha512(hasher) => hasher.update(chunk),
+            }
+        }
+    }
+
+    /// The field value: a structured field dictionary of byte sequences,
+    /// `sha-256=:...:, sha-512=:...:`.
+    pub fn finish(self) -> HeaderValue {
+        let members: Vec<String> = self
+            .states
+            .into_iter()
+            .map(|state| match state {
+                HashState::Sha256(hasher) => {
+                    format!("sha-256=:{}:", STANDARD.encode(hasher.finish()))
+                }
+                HashState::Sha512(hasher) => {
+                    format!("sha-512=:{}:", STANDARD.encode(hasher.finish()))
+                }
+            })
+            .collect();
+        HeaderValue::from_str(&members.join(", ")).expect("base64 is a valid header value")
+    }
+}
+
+impl std::fmt::Debug for DigestHasher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let algorithms: Vec<_> = self
+            .states
+            .iter()
+            .map(|state| match state {
+                HashState::Sha256(_) => DigestAlgorithm::Sha256,
+                HashState::Sha512(_) => DigestAlgorithm::Sha512,
+            })
+            .collect();
+        f.debug_struct("DigestHasher")
+            .field("algorithms", &algorithms)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::RewindableBody;
+    use bytes::Bytes;
+
+    // RFC 9530 appendix B
+    const BODY: &[u8] = b"{\"hello\": \"world\"}";
+    const SHA256: &str = "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:";
+    const SHA512: &str = "sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:";
+
+    #[test]
+    fn test_digest_values() {
+        let digest = ContentDigest::new(DigestAlgorithm::Sha256);
+        assert_eq!(digest.header_name(), "content-digest");
+        assert_eq!(digest.digest(BODY), SHA256);
+
+        let digest = ContentDigest::repr(DigestAlgorithm::Sha512);
+        assert_eq!(digest.header_name(), "repr-digest");
+        assert_eq!(digest.digest(BODY), SHA512);
+
+        let both = ContentDigest::new(DigestAlgorithm::Sha256)
+            .with_algorithm(DigestAlgorithm::Sha512)
+            .with_algorithm(DigestAlgorithm::Sha256);
+        assert_eq!(both.digest(BODY), format!("{SHA256}, {SHA512}").as_str());
+    }
+
+    #[test]
+    fn test_hasher_is_incremental() {
+        let digest = ContentDigest::new(DigestAlgorithm::Sha256);
+        let mut hasher = digest.hasher();
+        for chunk in BODY.chunks(5) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finish(), SHA256);
+    }
+
+    #[tokio::test]
+    async fn test_digest_streaming_body() {
+        let digest = ContentDigest::new(DigestAlgorithm::Sha256);
+        let body = RequestBody::rewindable(RewindableBody::from_factory(|| {
+            futures::stream::iter(
+                BODY.chunks(4)
+                    .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                    .collect::<Vec<_>>(),
+            )
+        }));
+        assert_eq!(digest.digest_body(&body).await.unwrap(), SHA256);
+        assert_eq!(
+            digest.digest_body(&RequestBody::from(BODY)).await.unwrap(),
+            SHA256
+        );
+    }
+}
@@ -6,9 +6,11 @@
 //! - Pseudo-header field order in HEADERS frames
 //! - PRIORITY frames sent after handshake
 //! - Window sizes and frame limits
+//! - GREASE: a reserved setting and frame type servers must ignore
 //!
 //! This module provides types to configure all these aspects.
 
+use bytes::Bytes;
 use std::time::Duration;
 
 // Re-export from http2 crate for fingerprint control
@@ -42,6 +44,9 @@ pub struct H2Fingerprint {
     pub initial_window_size: u32,
     /// Connection-level flow control window
     pub initial_conn_window_size: u32,
+    /// When the WINDOW_UPDATE raising the connection window to
+    /// `initial_conn_window_size` is sent
+    pub conn_window_update: ConnWindowUpdate,
     /// SETTINGS_MAX_FRAME_SIZE (0x5) - Maximum frame payload
     pub max_frame_size: Option<u32>,
     /// SETTINGS_MAX_HEADER_LIST_SIZE (0x6) - Maximum header block size
@@ -58,6 +63,10 @@ pub struct H2Fingerprint {
     pub stream_dependency: Option<StreamDependency>,
     /// Experimental SETTINGS (for future protocols)
     pub experimental_settings: Option<ExperimentalSettings>,
+    /// Reserved parameter appended to the initial SETTINGS frame
+    pub grease_setting: Option<GreaseSetting>,
+    /// Frame of a reserved type sent right after the initial SETTINGS frame
+    pub grease_frame: Option<GreaseFrame>,
 
     // Keep-alive
     /// Interval for HTTP/2 PING keep-alive frames
@@ -78,6 +87,86 @@ pub struct H2Fingerprint {
     pub enable_connect_protocol: Option<bool>,
 }
 
+/// When the connection-level WINDOW_UPDATE is sent.
+///
+/// Browsers raise the connection window from the 65,535 bytes of RFC 9113
+/// with a WINDOW_UPDATE on stream 0 (Chrome to 15 MB). Fingerprinting
+/// services look at whether it comes right after SETTINGS or after the
+/// PRIORITY and HEADERS frames of the first request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnWindowUpdate {
+    /// Right after the SETTINGS frame of the preface, as browsers do.
+    #[default]
+    AfterSettings,
+    /// After the PRIORITY and HEADERS frames of the first request.
+    AfterFirstRequest,
+    /// Not sent; the window stays at 65,535 and is only replenished as
+    /// data is read.
+    Never,
+}
+
+/// A reserved SETTINGS parameter, as Chrome sends to keep servers
+/// ignoring unknown settings (RFC 9113 6.5.2).
+///
+/// Chromium greases with ids of the form `0x?a?a`, i.e. `0x0a0a + 0x1010 *
+/// n`, and a random value, both picked once per network session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GreaseSetting {
+    pub id: u16,
+    pub value: u32,
+}
+
+impl GreaseSetting {
+    pub fn new(id: u16, value: u32) -> Self {
+        Self { id, value }
+    }
+
+    /// A reserved id and a value picked at random, as Chromium does.
+    pub fn random() -> Self {
+        let mut random = [0u8; 5];
+        let _ = boring::rand::rand_bytes(&mut random);
+        Self {
+            id: 0x0a0a + 0x1010 * u16::from(random[0] % 16),
+            value: u32::from_be_bytes([random[1], random[2], random[3], random[4]]),
+        }
+    }
+}
+
+/// A frame of a reserved type, which servers must ignore (RFC 9113 5.5).
+///
+/// It is sent on stream 0 right after the client's SETTINGS frame.
+/// Chromium greases with types `0x0b + 0x1f * n`, random flags and up to 6
+/// payload bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreaseFrame {
+    pub kind: u8,
+    pub flags: u8,
+    pub payload: Bytes,
+}
+
+impl GreaseFrame {
+    pub fn new(kind: u8, flags: u8, payload: impl Into<Bytes>) -> Self {
+        Self {
+            kind,
+            flags,
+            payload: payload.into(),
+        }
+    }
+
+    /// A reserved type, flags and payload picked at random, as Chromium
+    /// does.
+    pub fn random() -> Self {
+        let mut random = [0u8; 9];
+        let _ = boring::rand::rand_bytes(&mut random);
+        let len = usize::from(random[2] % 7);
+        Self {
+            kind: 0x0b + 0x1f * (random[0] % 8),
+            flags: random[1],
+            payload: Bytes::copy_from_slice(&random[3..3 + len]),
+        }
+    }
+}
+
 impl Default for H2Fingerprint {
     fn default() -> Self {
         Self::chrome()
@@ -97,13 +186,16 @@ impl H2Fingerprint {
             max_concurrent_streams: Some(1000),
             initial_window_size: 6291456, // 6MB - Chrome's aggressive window
             initial_conn_window_size: 15728640, // 15MB
-            max_frame_size: Some(16384),  // 16KB - RFC default
+            conn_window_update: ConnWindowUpdate::AfterSettings,
+            max_frame_size: Some(16384),        // 16KB - RFC default
             max_header_list_size: Some(262144), // 256KB
             pseudo_order: Some(chrome_pseudo_order()),
             settings_order: Some(chrome_settings_order()),
             priorities: Some(chrome_priorities()),
             stream_dependency: None,
             experimental_settings: None,
+            grease_setting: None,
+            grease_frame: None,
             keep_alive_interval: None,
             keep_alive_timeout: None,
             keep_alive_while_idle: false,
@@ -122,6 +214,7 @@ impl H2Fingerprint {
             max_concurrent_streams: Some(100),
             initial_window_size: 65535,         // RFC default 64KB
             initial_conn_window_size: 12582912, // 12MB
+            conn_window_update: ConnWindowUpdate::AfterSettings,
             max_frame_size: Some(16384),
             max_header_list_size: Some(65536),
             pseudo_order: Some(firefox_pseudo_order()),
@@ -129,6 +222,8 @@ impl H2Fingerprint {
             priorities: None, // Firefox doesn't send initial priorities
             stream_dependency: None,
             experimental_settings: None,
+            grease_setting: None,
+            grease_frame: None,
             keep_alive_interval: None,
             keep_alive_timeout: None,
             keep_alive_while_idle: false,
@@ -147,6 +242,7 @@ impl H2Fingerprint {
             max_concurrent_streams: Some(100),
             initial_window_size: 65535,
             initial_conn_window_size: 10485760, // 10MB
+            conn_window_update: ConnWindowUpdate::AfterSettings,
             max_frame_size: Some(16384),
             max_header_list_size: None, // Safari doesn't send this
             pseudo_order: Some(safari_pseudo_order()),
@@ -154,6 +250,8 @@ impl H2Fingerprint {
             priorities: None,
             stream_dependency: None,
             experimental_settings: None,
+            grease_setting: None,
+            grease_frame: None,
             keep_alive_interval: None,
             keep_alive_timeout: None,
             keep_alive_while_idle: false,
@@ -194,6 +292,11 @@ impl H2FingerprintBuilder {
         self
     }
 
+    pub fn conn_window_update(mut self, when: ConnWindowUpdate) -> Self {
+        self.inner.conn_window_update = when;
+        self
+    }
+
     pub fn max_concurrent_streams(mut self, max: u32) -> Self {
         self.inner.max_concurrent_streams = Some(max);
         self
@@ -224,6 +327,16 @@ impl H2FingerprintBuilder {
         self
     }
 
+    pub fn grease_setting(mut self, setting: GreaseSetting) -> Self {
+        self.inner.grease_setting = Some(setting);
+        self
+    }
+
+    pub fn grease_frame(mut self, frame: GreaseFrame) -> Self {
+        self.inner.grease_frame = Some(frame);
+        self
+    }
+
     pub fn keep_alive_interval(mut self, interval: Duration) -> Self {
         self.inner.keep_alive_interval = Some(interval);
         self
@@ -339,6 +452,7 @@ mod tests {
         let fp = H2Fingerprint::chrome();
         assert_eq!(fp.initial_window_size, 6291456);
         assert_eq!(fp.initial_conn_window_size, 15728640);
+        assert_eq!(fp.conn_window_update, ConnWindowUpdate::AfterSettings);
         assert!(fp.pseudo_order.is_some());
         assert!(fp.settings_order.is_some());
         assert!(fp.priorities.is_some());
@@ -368,6 +482,20 @@ mod tests {
         assert_eq!(fp.max_concurrent_streams, Some(500));
     }
 
+    #[test]
+    fn test_random_grease_is_reserved() {
+        for _ in 0..32 {
+            let setting = GreaseSetting::random();
+            assert_eq!(setting.id & 0x0f0f, 0x0a0a);
+            assert_eq!(setting.id >> 12, (setting.id >> 4) & 0xf);
+
+            let frame = GreaseFrame::random();
+            assert_eq!((frame.kind - 0x0b) % 0x1f, 0);
+            assert!(frame.kind <= 0x0b + 0x1f * 7);
+            assert!(frame.payload.len() <= 6);
+        }
+    }
+
     #[test]
     fn test_default_is_chrome() {
         let default = H2Fingerprint::default();
@@ -0,0 +1,231 @@
+//! GREASE on the frames that open an HTTP/2 connection.
+//!
+//! http2 only sends unknown settings with ids up to 15, while the reserved
+//! ids Chrome greases with look like `0x?a?a`, and it has no way to send a
+//! frame of an unknown type. [`GreaseIo`] holds back the connection preface
+//! and the client's first SETTINGS frame as http2 writes them, appends the
+//! [`GreaseSetting`] to the frame and follows it with the [`GreaseFrame`].
+//! Everything after passes through.
+
+use crate::http::h2fingerprint::{GreaseFrame, GreaseSetting};
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const PREFACE_LEN: usize = 24;
+const FRAME_HEADER_LEN: usize = 9;
+const SETTINGS: u8 = 0x4;
+const ACK: u8 = 0x1;
+
+/// An HTTP/2 connection's I/O, adding GREASE to the client's first
+/// SETTINGS. Reads pass through.
+pub(crate) struct GreaseIo<T> {
+    inner: T,
+    /// The preface and first frame written so far, `None` once rewritten
+    /// or when there is nothing to add.
+    head: Option<Vec<u8>>,
+    /// Rewritten bytes not yet written to `inner`.
+    pending: Vec<u8>,
+    written: usize,
+    setting: Option<GreaseSetting>,
+    frame: Option<GreaseFrame>,
+}
+
+impl<T> GreaseIo<T> {
+    pub(crate) fn new(
+        inner: T,
+        setting: Option<GreaseSetting>,
+        frame: Option<GreaseFrame>,
+    ) -> Self {
+        let rewrite = setting.is_some() || frame.is_some();
+        Self {
+            inner,
+            head: rewrite.then(Vec::new),
+            pending: Vec::new(),
+            written: 0,
+            setting,
+            frame,
+        }
+    }
+
+    /// Take up to the rest of the preface and first frame from `buf`,
+    /// rewriting them once complete. Returns the bytes taken.
+    fn buffer(&mut self, buf: &[u8]) -> usize {
+        let Some(head) = self.head.as_mut() else {
+            return 0;
+        };
+        let mut taken = 0;
+        while taken < buf.len() {
+            let n = head_needed(head).min(buf.len() - taken);
+            head.extend_from_slice(&buf[taken..taken + n]);
+            taken += n;
+            if head_needed(head) == 0 {
+                let head = self.head.take().unwrap_or_default();
+                self.pending = rewrite(head, self.setting, self.frame.as_ref());
+                break;
+            }
+        }
+        taken
+    }
+}
+
+impl<T: AsyncWrite + Unpin> GreaseIo<T> {
+    /// Write out the rewritten bytes held back.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            let n =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        self.pending = Vec::new();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Bytes still missing from the preface and the frame after it.
+fn head_needed(head: &[u8]) -> usize {
+    match head.get(PREFACE_LEN..PREFACE_LEN + FRAME_HEADER_LEN) {
+        Some(frame) => {
+            let len = u32::from_be_bytes([0, frame[0], frame[1], frame[2]]) as usize;
+            PREFACE_LEN + FRAME_HEADER_LEN + len - head.len()
+        }
+        None => PREFACE_LEN + FRAME_HEADER_LEN - head.len(),
+    }
+}
+
+/// The preface and SETTINGS in `head` with the GREASE setting appended to
+/// the frame and the GREASE frame after it.
+fn rewrite(
+    mut head: Vec<u8>,
+    setting: Option<GreaseSetting>,
+    frame: Option<&GreaseFrame>,
+) -> Vec<u8> {
+    let (kind, flags) = (head[PREFACE_LEN + 3], head[PREFACE_LEN + 4]);
+    if kind != SETTINGS || flags & ACK != 0 {
+        return head;
+    }
+    if let Some(setting) = setting {
+        let len = &mut head[PREFACE_LEN..PREFACE_LEN + 3];
+        let new_len = u32::from_be_bytes([0, len[0], len[1], len[2]]) + 6;
+        len.copy_from_slice(&new_len.to_be_bytes()[1..]);
+        head.extend_from_slice(&setting.id.to_be_bytes());
+        head.extend_from_slice(&setting.value.to_be_bytes());
+    }
+    if let Some(frame) = frame {
+        head.extend_from_slice(&(frame.payload.len() as u32).to_be_bytes()[1..]);
+        head.extend_from_slice(&[frame.kind, frame.flags]);
+        head.extend_from_slice(&0u32.to_be_bytes());
+        head.extend_from_slice(&frame.payload);
+    }
+    head
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for GreaseIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for GreaseIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        ready!(this.poll_pending(cx))?;
+        if this.head.is_some() {
+            return Poll::Ready(Ok(this.buffer(buf)));
+        }
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        if self.head.is_some() || !self.pending.is_empty() {
+            let buf = bufs
+                .iter()
+                .find(|b| !b.is_empty())
+                .map_or(&[][..], |b| &**b);
+            return self.poll_write(cx, buf);
+        }
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use tokio::io::AsyncWriteExt;
+
+    const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+    fn frame(kind: u8, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_settings_greased_across_writes() {
+        let settings = frame(SETTINGS, 0, &[0, 2, 0, 0, 0, 0]);
+        let window_update = frame(0x8, 0, &[0, 0xef, 0, 1]);
+        let written = [PREFACE, &settings[..], &window_update[..]].concat();
+
+        let mut io = GreaseIo::new(
+            Vec::new(),
+            Some(GreaseSetting::new(0x1a1a, 7)),
+            Some(GreaseFrame::new(0x2a, 0, Bytes::from_static(b"ok"))),
+        );
+        for chunk in written.chunks(5) {
+            io.write_all(chunk).await.unwrap();
+        }
+        io.flush().await.unwrap();
+
+        let greased_settings = frame(SETTINGS, 0, &[0, 2, 0, 0, 0, 0, 0x1a, 0x1a, 0, 0, 0, 7]);
+        let grease = frame(0x2a, 0, b"ok");
+        assert_eq!(
+            io.inner,
+            [
+                PREFACE,
+                &greased_settings[..],
+                &grease[..],
+                &window_update[..]
+            ]
+            .concat()
+        );
+
+        // Nothing to add: bytes pass through
+        let mut io = GreaseIo::new(Vec::new(), None, None);
+        io.write_all(&written).await.unwrap();
+        assert_eq!(io.inner, written);
+    }
+}
@@ -0,0 +1,365 @@
+//! The frames a client opens an HTTP/2 connection with, as a fingerprint.
+//!
+//! Servers tell HTTP/2 clients apart by their first frames, summarized in
+//! the format Akamai published:
+//! `SETTINGS|WINDOW_UPDATE|PRIORITY|pseudo-header order`, e.g.
+//! `1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p`. [`H2Preface`]
+//! parses those frames. `PrefaceIo` sits below the GREASE writer, so it
+//! sees the frames as they are sent, keeps them up to the first HEADERS
+//! and records the fingerprint of the connection.
+
+use std::io::IoSlice;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const HEADERS: u8 = 0x1;
+const PRIORITY: u8 = 0x2;
+const SETTINGS: u8 = 0x4;
+const WINDOW_UPDATE: u8 = 0x8;
+const ACK: u8 = 0x1;
+const PADDED: u8 = 0x8;
+const PRIORITY_FLAG: u8 = 0x20;
+
+/// Give up on a connection whose first HEADERS has not come within this
+/// many bytes.
+const MAX_KEPT: usize = 64 * 1024;
+
+/// An HTTP/2 connection's I/O, recording the fingerprint of the frames
+/// written up to the first HEADERS. Reads pass through.
+pub(crate) struct PrefaceIo<T> {
+    inner: T,
+    /// Frames written so far, `None` once the fingerprint is recorded.
+    sent: Option<Vec<u8>>,
+    fingerprint: Arc<OnceLock<String>>,
+}
+
+impl<T> PrefaceIo<T> {
+    pub(crate) fn new(inner: T, fingerprint: Arc<OnceLock<String>>) -> Self {
+        Self {
+            inner,
+            sent: Some(Vec::new()),
+            fingerprint,
+        }
+    }
+
+    fn observe(&mut self, data: &[u8]) {
+        let Some(sent) = self.sent.as_mut() else {
+            return;
+        };
+        sent.extend_from_slice(data);
+        let kept = sent.len();
+        let frames = sent.strip_prefix(PREFACE).unwrap_or(&sent[..]);
+        if let Some(preface) = H2Preface::parse(frames) {
+            let _ = self.fingerprint.set(preface.akamai());
+            self.sent = None;
+        } else if kept > MAX_KEPT {
+            self.sent = None;
+        }
+    }
+}
+
+/// The frames that open an HTTP/2 connection, up to the first HEADERS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct H2Preface {
+    /// The first SETTINGS, in order.
+    pub settings: Vec<(u16, u32)>,
+    /// Increment of the first connection WINDOW_UPDATE.
+    pub window_update: Option<u32>,
+    /// PRIORITY frames as (stream, exclusive, dependency, weight).
+    pub priorities: Vec<(u32, bool, u32, u8)>,
+    /// Pseudo-headers of the first HEADERS as `m`, `a`, `s` and `p`.
+    pub pseudo_order: Vec<char>,
+}
+
+impl H2Preface {
+    /// Parse the frames a client sends after the connection preface,
+    /// `None` until they hold a complete HEADERS.
+    pub fn parse(frames: &[u8]) -> Option<Self> {
+        let mut frames = Reader(frames);
+        let mut parsed = H2Preface::default();
+        let mut settings_seen = false;
+        loop {
+            let len = frames.u24()?;
+            let kind = frames.u8()? as u8;
+            let flags = frames.u8()? as u8;
+            let stream = frames.u32()? & 0x7fff_ffff;
+            let mut payload = Reader(frames.bytes(len)?);
+            match kind {
+                SETTINGS if flags & ACK == 0 && !settings_seen => {
+                    settings_seen = true;
+                    while !payload.0.is_empty() {
+                        let id = payload.u16()?;
+                        parsed.settings.push((id, payload.u32()?));
+                    }
+                }
+                WINDOW_UPDATE if stream == 0 && parsed.window_update.is_none() => {
+                    parsed.window_update = Some(payload.u32()? & 0x7fff_ffff);
+                }
+                PRIORITY => {
+                    let dependency = payload.u32()?;
+                    parsed.priorities.push((
+                        stream,
+                        dependency & 0x8000_0000 != 0,
+                        dependency & 0x7fff_ffff,
+                        payload.u8()? as u8,
+                    ));
+                }
+                HEADERS => {
+                    let pad = if flags & PADDED != 0 {
+                        payload.u8()?
+                    } else {
+                        0
+                    };
+                    if flags & PRIORITY_FLAG != 0 {
+                        payload.skip(5)?;
+                    }
+                    let block = payload.bytes(payload.0.len().checked_sub(pad)?)?;
+                    parsed.pseudo_order = pseudo_order(block);
+                    return Some(parsed);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The Akamai fingerprint.
+    pub fn akamai(&self) -> String {
+        let settings: Vec<String> = self
+            .settings
+            .iter()
+            .map(|(id, value)| format!("{}:{}", id, value))
+            .collect();
+        let priorities: Vec<String> = self
+            .priorities
+            .iter()
+            .map(|(stream, exclusive, dependency, weight)| {
+                format!(
+                    "{}:{}:{}:{}",
+                    stream,
+                    u8::from(*exclusive),
+                    dependency,
+                    u16::from(*weight) + 1
+                )
+            })
+            .collect();
+        let pseudo_order: Vec<String> = self.pseudo_order.iter().map(char::to_string).collect();
+        format!(
+            "{}|{}|{}|{}",
+            settings.join(";"),
+            self.window_update
+                .map_or_else(|| "00".to_string(), |n| n.to_string()),
+            if priorities.is_empty() {
+                "0".to_string()
+            } else {
+                priorities.join(",")
+            },
+            pseudo_order.join(","),
+        )
+    }
+}
+
+/// The order of the pseudo-headers at the start of an HPACK block, as
+/// `m`, `a`, `s` and `p`.
+///
+/// A client's first header block has an empty dynamic table, so the
+/// pseudo-headers are named by their static table index.
+fn pseudo_order(block: &[u8]) -> Vec<char> {
+    let mut block = Reader(block);
+    let mut order = Vec::new();
+    while let Some(&first) = block.0.first() {
+        let index = match first {
+            // Indexed field
+            0x80..=0xff => block.int(7),
+            // Literal with incremental indexing
+            0x40..=0x7f => block.int(6),
+            // Dynamic table size update
+            0x20..=0x3f => {
+                if block.int(5).is_none() {
+                    break;
+                }
+                continue;
+            }
+            // Literal without indexing or never indexed
+            _ => block.int(4),
+        };
+        let name = match index {
+            Some(1) => 'a',
+            Some(2 | 3) => 'm',
+            Some(4 | 5) => 'p',
+            Some(6 | 7) => 's',
+            _ => break,
+        };
+        order.push(name);
+        if first < 0x80 {
+            // Skip the literal value
+            match block.int(7) {
+                Some(len) if block.skip(len).is_some() => {}
+                _ => break,
+            }
+        }
+    }
+    order
+}
+
+/// Big-endian reads from a byte slice, `None` past its end.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn skip(&mut self, n: usize) -> Option<()> {
+        self.bytes(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<usize> {
+        self.bytes(1).map(|b| b[0] as usize)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        self.bytes(3)
+            .map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// An HPACK integer with a `prefix`-bit prefix (RFC 7541 Section 5.1).
+    fn int(&mut self, prefix: u8) -> Option<usize> {
+        let max = (1usize << prefix) - 1;
+        let mut value = self.u8()? & max;
+        if value < max {
+            return Some(value);
+        }
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            value += (byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+            shift += 7;
+            if shift > 28 {
+                return None;
+            }
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for PrefaceIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for PrefaceIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            self.observe(&buf[..n]);
+        }
+        poll
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = poll {
+            let mut remaining = n;
+            for buf in bufs {
+                let len = remaining.min(buf.len());
+                self.observe(&buf[..len]);
+                remaining -= len;
+                if remaining == 0 {
+                    break;
+                }
+            }
+        }
+        poll
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    fn frame(kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint_of_first_frames() {
+        let mut bytes = PREFACE.to_vec();
+        bytes.extend(frame(
+            SETTINGS,
+            0,
+            0,
+            &[0, 1, 0, 1, 0, 0, 0, 4, 0, 0x60, 0, 0],
+        ));
+        bytes.extend(frame(WINDOW_UPDATE, 0, 0, &[0, 0xef, 0, 1]));
+        bytes.extend(frame(PRIORITY, 0, 3, &[0x80, 0, 0, 0, 200]));
+        // :method GET, :authority literal, :scheme https, :path /
+        let block = [0x82, 0x41, 0x01, b'a', 0x87, 0x84, 0x40];
+        bytes.extend(frame(
+            HEADERS,
+            0x4 | PRIORITY_FLAG,
+            1,
+            &[&[0; 5][..], &block].concat(),
+        ));
+
+        let fingerprint = Arc::new(OnceLock::new());
+        let mut io = PrefaceIo::new(tokio::io::sink(), fingerprint.clone());
+        let (head, rest) = bytes.split_at(40);
+        io.write_all(head).await.unwrap();
+        assert!(fingerprint.get().is_none());
+        io.write_all(rest).await.unwrap();
+        assert_eq!(
+            fingerprint.get().unwrap(),
+            "1:65536;4:6291456|15663105|3:1:0:201|m,a,s,p"
+        );
+        assert!(io.sent.is_none());
+    }
+}
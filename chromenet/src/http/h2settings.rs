@@ -0,0 +1,221 @@
+//! Settings the server announces on an HTTP/2 connection.
+//!
+//! http2 applies the server's SETTINGS to its own state but does not
+//! expose `SETTINGS_MAX_HEADER_LIST_SIZE`, which is advisory for a sender
+//! and not enforced when a request is encoded. A request over the limit
+//! goes out, split across CONTINUATION frames, and the server answers
+//! with a reset stream, a GOAWAY or a 431. [`PeerSettingsIo`] follows the
+//! frames the server sends and records the limit, so such a request fails
+//! with [`NetError::RequestHeadersTooBig`](crate::base::neterror::NetError::RequestHeadersTooBig)
+//! before it is sent instead.
+//!
+//! Until the server's first SETTINGS is read the limit is unknown and
+//! requests are sent as they are (RFC 9113 6.5.2: the initial value is
+//! unlimited).
+
+use std::io::IoSlice;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const FRAME_HEADER_LEN: usize = 9;
+const SETTINGS: u8 = 0x4;
+const ACK: u8 = 0x1;
+const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+/// Settings the server of a connection announced so far.
+#[derive(Debug)]
+pub(crate) struct PeerSettings {
+    /// `u32::MAX` until the server limits it.
+    max_header_list_size: AtomicU32,
+}
+
+impl Default for PeerSettings {
+    fn default() -> Self {
+        Self {
+            max_header_list_size: AtomicU32::new(u32::MAX),
+        }
+    }
+}
+
+impl PeerSettings {
+    /// The server's `SETTINGS_MAX_HEADER_LIST_SIZE`, `None` while it has
+    /// not announced one.
+    pub(crate) fn max_header_list_size(&self) -> Option<u32> {
+        match self.max_header_list_size.load(Ordering::Relaxed) {
+            u32::MAX => None,
+            size => Some(size),
+        }
+    }
+
+    fn apply(&self, id: u16, value: u32) {
+        if id == SETTINGS_MAX_HEADER_LIST_SIZE {
+            self.max_header_list_size.store(value, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Position in the stream of frames the server sends.
+#[derive(Debug, Default)]
+struct FrameReader {
+    head: [u8; FRAME_HEADER_LEN],
+    head_len: usize,
+    /// Payload bytes of the current frame still to come.
+    remaining: usize,
+    /// Whether the current frame is a SETTINGS to apply.
+    settings: bool,
+    /// Part of a setting split across reads.
+    entry: [u8; 6],
+    entry_len: usize,
+}
+
+impl FrameReader {
+    fn feed(&mut self, mut data: &[u8], peer: &PeerSettings) {
+        while !data.is_empty() {
+            if self.head_len < FRAME_HEADER_LEN {
+                let n = (FRAME_HEADER_LEN - self.head_len).min(data.len());
+                self.head[self.head_len..self.head_len + n].copy_from_slice(&data[..n]);
+                self.head_len += n;
+                data = &data[n..];
+                if self.head_len < FRAME_HEADER_LEN {
+                    return;
+                }
+                let head = &self.head;
+                self.remaining = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+                let stream = u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff;
+                self.settings = head[3] == SETTINGS && head[4] & ACK == 0 && stream == 0;
+                self.entry_len = 0;
+            }
+
+            let n = self.remaining.min(data.len());
+            if self.settings {
+                for &byte in &data[..n] {
+                    self.entry[self.entry_len] = byte;
+                    self.entry_len += 1;
+                    if self.entry_len == self.entry.len() {
+                        let e = self.entry;
+                        peer.apply(
+                            u16::from_be_bytes([e[0], e[1]]),
+                            u32::from_be_bytes([e[2], e[3], e[4], e[5]]),
+                        );
+                        self.entry_len = 0;
+                    }
+                }
+            }
+            self.remaining -= n;
+            data = &data[n..];
+            if self.remaining == 0 {
+                self.head_len = 0;
+            }
+        }
+    }
+}
+
+/// An HTTP/2 connection's I/O, recording the settings in the frames read
+/// from it. Writes pass through.
+pub(crate) struct PeerSettingsIo<T> {
+    inner: T,
+    peer: Arc<PeerSettings>,
+    reader: FrameReader,
+}
+
+impl<T> PeerSettingsIo<T> {
+    pub(crate) fn new(inner: T, peer: Arc<PeerSettings>) -> Self {
+        Self {
+            inner,
+            peer,
+            reader: FrameReader::default(),
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for PeerSettingsIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            let this = &mut *self;
+            this.reader.feed(&buf.filled()[before..], &this.peer);
+        }
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for PeerSettingsIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn settings(entries: &[(u16, u32)]) -> Vec<u8> {
+        let payload: Vec<u8> = entries
+            .iter()
+            .flat_map(|(id, value)| {
+                [id.to_be_bytes().to_vec(), value.to_be_bytes().to_vec()].concat()
+            })
+            .collect();
+        frame(SETTINGS, 0, 0, &payload)
+    }
+
+    #[test]
+    fn test_max_header_list_size_from_settings() {
+        let peer = PeerSettings::default();
+        let mut reader = FrameReader::default();
+        assert_eq!(peer.max_header_list_size(), None);
+
+        // DATA that looks like a setting, then the real SETTINGS, a byte at a time
+        let mut bytes = frame(0x0, 0, 1, &[0, 6, 0, 0, 0, 1]);
+        bytes.extend(settings(&[(0x3, 100), (0x6, 8192)]));
+        for byte in &bytes {
+            reader.feed(std::slice::from_ref(byte), &peer);
+        }
+        assert_eq!(peer.max_header_list_size(), Some(8192));
+
+        // An ACK carries no settings, a later SETTINGS updates the limit
+        reader.feed(&frame(SETTINGS, ACK, 0, &[]), &peer);
+        reader.feed(&settings(&[(0x6, 4096)]), &peer);
+        assert_eq!(peer.max_header_list_size(), Some(4096));
+    }
+}
@@ -0,0 +1,163 @@
+//! Limits and validation for outgoing request headers.
+//!
+//! Servers answer oversized or malformed request headers with a bare 400,
+//! 431 or a reset stream, which says little about the cause. Requests are
+//! checked before they are written instead, and fail with
+//! [`NetError::InvalidHeader`]:
+//!
+//! - A value longer than [`HeaderLimits::max_value_size`]
+//! - Headers larger than [`HeaderLimits::max_total_size`] in all, counted
+//!   as HTTP/1.1 lines: `name: value\r\n`
+//! - A value with a control character other than HTAB, which covers
+//!   CR/LF injection and obs-fold line continuations (RFC 9112 5.2)
+//!
+//! Over HTTP/2 the server may also announce `SETTINGS_MAX_HEADER_LIST_SIZE`;
+//! a request over it fails with [`NetError::RequestHeadersTooBig`], sized
+//! with [`h2_header_list_size`]. Header blocks larger than a frame are
+//! split across CONTINUATION frames.
+//!
+//! [`HeaderValue`](http::HeaderValue) rejects control characters when it
+//! is built, except through its unchecked constructors in release builds;
+//! [`RequestBuilder::header`](crate::RequestBuilder::header) fails the
+//! request on a value it cannot convert rather than dropping the header.
+
+use crate::base::neterror::NetError;
+use http::{HeaderMap, Method, Uri};
+
+/// Limits on the headers of a request.
+///
+/// The defaults match the `SETTINGS_MAX_HEADER_LIST_SIZE` Chrome announces,
+/// 256 KiB, and allow 64 KiB per value, well beyond what servers accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderLimits {
+    /// Largest size of all headers, counted as HTTP/1.1 lines.
+    pub max_total_size: usize,
+    /// Largest size of one header value.
+    pub max_value_size: usize,
+}
+
+impl Default for HeaderLimits {
+    fn default() -> Self {
+        Self {
+            max_total_size: 256 * 1024,
+            max_value_size: 64 * 1024,
+        }
+    }
+}
+
+impl HeaderLimits {
+    /// Limits of `max_total_size` bytes for all headers and
+    /// `max_value_size` bytes per value.
+    pub fn new(max_total_size: usize, max_value_size: usize) -> Self {
+        Self {
+            max_total_size,
+            max_value_size,
+        }
+    }
+
+    /// Check `headers` against the limits and for values that would break
+    /// the message framing.
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), NetError> {
+        let mut total = 0;
+        for (name, value) in headers {
+            let value = value.as_bytes();
+            if value.len() > self.max_value_size {
+                tracing::debug!(target: "chromenet::http", header = %name, size = value.len(), "request header value too large");
+                return Err(NetError::InvalidHeader);
+            }
+            if !is_valid_value(value) {
+                tracing::debug!(target: "chromenet::http", header = %name, "control character in request header value");
+                return Err(NetError::InvalidHeader);
+            }
+            total += name.as_str().len() + value.len() + 4;
+        }
+        if total > self.max_total_size {
+            tracing::debug!(target: "chromenet::http", size = total, "request headers too large");
+            return Err(NetError::InvalidHeader);
+        }
+        Ok(())
+    }
+}
+
+/// Whether `value` can be sent as a header value: no control characters
+/// but HTAB, so no CR, LF or NUL.
+pub fn is_valid_value(value: &[u8]) -> bool {
+    value
+        .iter()
+        .all(|&b| b == b'\t' || (b >= 0x20 && b != 0x7f))
+}
+
+/// Size of a request's header list as HTTP/2 counts it against
+/// `SETTINGS_MAX_HEADER_LIST_SIZE`: name and value lengths plus 32 per
+/// field, the pseudo-headers included (RFC 9113 6.5.2).
+pub fn h2_header_list_size(method: &Method, uri: &Uri, headers: &HeaderMap) -> usize {
+    const OVERHEAD: usize = 32;
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    let mut size = ":method".len() + method.as_str().len() + OVERHEAD;
+    size += ":path".len() + path.len() + OVERHEAD;
+    if let Some(scheme) = uri.scheme_str() {
+        size += ":scheme".len() + scheme.len() + OVERHEAD;
+    }
+    if let Some(authority) = uri.authority() {
+        size += ":authority".len() + authority.as_str().len() + OVERHEAD;
+    }
+    for (name, value) in headers {
+        size += name.as_str().len() + value.len() + OVERHEAD;
+    }
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_control_characters() {
+        assert!(is_valid_value(b"text/html;\tq=0.9"));
+        assert!(is_valid_value("caf\u{e9}".as_bytes()));
+        assert!(!is_valid_value(b"a\r\nSet-Cookie: x=1"));
+        assert!(!is_valid_value(b"folded\r\n value"));
+        assert!(!is_valid_value(b"nul\0"));
+        assert!(!is_valid_value(b"del\x7f"));
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let limits = HeaderLimits::new(40, 10);
+        let mut headers = HeaderMap::new();
+        headers.insert("a", HeaderValue::from_static("0123456789"));
+        assert!(limits.check(&headers).is_ok());
+
+        headers.insert("b", HeaderValue::from_static("0123456789a"));
+        assert!(matches!(
+            limits.check(&headers),
+            Err(NetError::InvalidHeader)
+        ));
+
+        // 15 bytes per line
+        headers.insert("b", HeaderValue::from_static("0123456789"));
+        headers.insert("c", HeaderValue::from_static("0123456789"));
+        assert!(matches!(
+            limits.check(&headers),
+            Err(NetError::InvalidHeader)
+        ));
+        headers.remove("c");
+        assert!(limits.check(&headers).is_ok());
+    }
+
+    #[test]
+    fn test_h2_header_list_size() {
+        let uri: Uri = "https://example.com/a?b".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        // :method GET, :path /a?b, :scheme https, :authority example.com
+        let pseudo = (7 + 3) + (5 + 4) + (7 + 5) + (10 + 11) + 4 * 32;
+        assert_eq!(h2_header_list_size(&Method::GET, &uri, &headers), pseudo);
+
+        headers.insert("cookie", HeaderValue::from_static("a=1"));
+        assert_eq!(
+            h2_header_list_size(&Method::GET, &uri, &headers),
+            pseudo + 6 + 3 + 32
+        );
+    }
+}
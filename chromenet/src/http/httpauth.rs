@@ -0,0 +1,110 @@
+//! HTTP authentication challenges.
+//!
+//! Parses `WWW-Authenticate` headers and picks the scheme to answer.
+//! Mirrors Chromium's `net/http/http_auth.cc`.
+
+use crate::http::digestauth::DigestAuthHandler;
+use crate::socket::authcache::AuthScheme;
+
+/// A parsed authentication challenge.
+#[derive(Debug, Clone)]
+pub enum AuthChallenge {
+    /// `Basic realm="..."`
+    Basic {
+        /// Protection space realm
+        realm: String,
+    },
+    /// `Digest realm="...", nonce="..."`
+    Digest(DigestAuthHandler),
+}
+
+impl AuthChallenge {
+    /// Parse a single `WWW-Authenticate` header value.
+    ///
+    /// Returns `None` for unsupported schemes and malformed challenges.
+    pub fn parse(header: &str) -> Option<Self> {
+        let header = header.trim();
+        let (scheme, params) = header.split_once(' ').unwrap_or((header, ""));
+
+        if scheme.eq_ignore_ascii_case("digest") {
+            DigestAuthHandler::parse_challenge(params)
+                .ok()
+                .map(AuthChallenge::Digest)
+        } else if scheme.eq_ignore_ascii_case("basic") {
+            let realm = params
+                .split(',')
+                .filter_map(|p| p.split_once('='))
+                .find(|(k, _)| k.trim().eq_ignore_ascii_case("realm"))
+                .map(|(_, v)| v.trim().trim_matches('"').to_string())
+                .unwrap_or_default();
+            Some(AuthChallenge::Basic { realm })
+        } else {
+            None
+        }
+    }
+
+    /// The authentication scheme of this challenge.
+    pub fn scheme(&self) -> AuthScheme {
+        match self {
+            AuthChallenge::Basic { .. } => AuthScheme::Basic,
+            AuthChallenge::Digest(_) => AuthScheme::Digest,
+        }
+    }
+
+    /// The protection space realm.
+    pub fn realm(&self) -> &str {
+        match self {
+            AuthChallenge::Basic { realm } => realm,
+            AuthChallenge::Digest(handler) => handler.realm(),
+        }
+    }
+}
+
+/// Pick the strongest supported challenge, preferring Digest over Basic
+/// like Chromium's `HttpAuth::ChooseBestChallenge`.
+pub fn choose_best_challenge<'a, I>(headers: I) -> Option<AuthChallenge>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    headers
+        .into_iter()
+        .filter_map(AuthChallenge::parse)
+        .max_by_key(|c| match c.scheme() {
+            AuthScheme::Digest => 2,
+            AuthScheme::Basic => 1,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic() {
+        let challenge = AuthChallenge::parse(r#"Basic realm="Admin Area""#).unwrap();
+        assert_eq!(challenge.scheme(), AuthScheme::Basic);
+        assert_eq!(challenge.realm(), "Admin Area");
+    }
+
+    #[test]
+    fn test_parse_digest() {
+        let challenge = AuthChallenge::parse(r#"Digest realm="api", nonce="n1""#).unwrap();
+        assert_eq!(challenge.scheme(), AuthScheme::Digest);
+        assert_eq!(challenge.realm(), "api");
+    }
+
+    #[test]
+    fn test_unsupported_scheme() {
+        assert!(AuthChallenge::parse("Negotiate").is_none());
+        assert!(AuthChallenge::parse(r#"Digest realm="no-nonce""#).is_none());
+    }
+
+    #[test]
+    fn test_choose_best_prefers_digest() {
+        let headers = [r#"Basic realm="r""#, r#"Digest realm="r", nonce="n""#];
+        let best = choose_best_challenge(headers).unwrap();
+        assert_eq!(best.scheme(), AuthScheme::Digest);
+
+        assert!(choose_best_challenge(["Bearer"]).is_none());
+    }
+}
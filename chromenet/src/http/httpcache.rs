@@ -4,24 +4,60 @@
 //!
 //! Provides RFC 7234 compliant HTTP caching with:
 //! - Cache-Control header parsing (max-age, no-store, no-cache)
+//! - Freshness from max-age, Expires or the Last-Modified heuristic, and
+//!   response age from Age and Date
 //! - ETag/If-None-Match support for conditional requests
 //! - Last-Modified/If-Modified-Since support
+//! - HEAD requests served from a fresh GET entry, and HEAD responses
+//!   freshening or invalidating the stored GET response
 //! - Thread-safe concurrent access
-
+//! - Optional partitioning by top-frame site (Chromium's split cache)
+//!
+//! ## Content codings
+//!
+//! Like Chromium's disk cache, entries hold the body as it came off the
+//! wire: after transfer decoding (chunked), but with its `Content-Encoding`
+//! still applied and the header kept. Content decoding happens when a
+//! response is served, for cached and network responses alike, so a body is
+//! decoded exactly once and the stored headers always describe the stored
+//! bytes. [`CacheEntry::into_response`] therefore hands out the encoded
+//! body, and [`CacheEntry::content_encodings`] lists what remains to undo.
+//!
+//! A body whose length contradicts its `Content-Length` is not stored: it is
+//! either truncated or was decoded before being passed in, and would be
+//! served corrupted either way.
+//!
+//! ## Vary
+//!
+//! Entries are found by URL, method and partition alone, never by request
+//! headers. A response with `Vary` was chosen by headers of the request,
+//! e.g. its cookies or `Accept-Encoding`, and could be served to requests
+//! it does not fit, so it is not stored and replaces no entry.
+
+use crate::base::clock::{system_clock, Clock};
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::http::httpdate::parse_http_date;
+use crate::http::response::HttpResponse;
+use crate::http::typedheaders;
+use crate::metrics::MetricsRecorder;
 use bytes::Bytes;
 use dashmap::DashMap;
 use http::{HeaderMap, HeaderValue, Response, StatusCode};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use url::Url;
 
-/// Cache key components for proper Vary header handling.
+/// Where an entry is stored: URL, method and, in a split cache, the
+/// partition. Request headers are not part of it, see [Vary](self#vary).
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct CacheKey {
     /// URL without fragment
     url: String,
     /// HTTP method (only GET/HEAD are cacheable)
     method: String,
+    /// Partition the entry belongs to, `None` for the shared partition
+    isolation_key: Option<NetworkIsolationKey>,
 }
 
 impl CacheKey {
@@ -34,8 +70,30 @@ impl CacheKey {
         Self {
             url: url_str,
             method: method.to_uppercase(),
+            isolation_key: None,
         }
     }
+
+    /// Place the key in the partition for `nik`.
+    pub fn with_isolation_key(mut self, nik: NetworkIsolationKey) -> Self {
+        self.isolation_key = Some(nik);
+        self
+    }
+
+    /// URL without fragment.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Uppercase HTTP method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Partition of the entry, `None` for the shared partition.
+    pub fn isolation_key(&self) -> Option<&NetworkIsolationKey> {
+        self.isolation_key.as_ref()
+    }
 }
 
 /// Cached response entry.
@@ -47,12 +105,14 @@ pub struct CacheEntry {
     pub headers: HeaderMap,
     /// Response body
     pub body: Bytes,
-    /// When this entry was cached
-    pub cached_at: Instant,
+    /// When this entry was cached or last freshened, by the cache's clock
+    pub cached_at: SystemTime,
     /// When this entry was inserted into the cache map (for pseudo-LRU)
     pub inserted_at: Instant,
-    /// Time-to-live (from max-age or Expires)
+    /// Freshness lifetime (from max-age, Expires or Last-Modified)
     pub ttl: Option<Duration>,
+    /// Age of the response when it was cached, see [`initial_age`]
+    pub initial_age: Duration,
     /// ETag for conditional requests
     pub etag: Option<String>,
     /// Last-Modified for conditional requests
@@ -62,20 +122,64 @@ pub struct CacheEntry {
 impl CacheEntry {
     /// Check if the entry is still fresh.
     pub fn is_fresh(&self) -> bool {
+        self.is_fresh_at(SystemTime::now())
+    }
+
+    /// Check if the entry is fresh at `now`.
+    pub fn is_fresh_at(&self, now: SystemTime) -> bool {
         match self.ttl {
-            Some(ttl) => self.cached_at.elapsed() < ttl,
+            Some(ttl) => self.current_age_at(now) < ttl,
             None => false, // No TTL means not cacheable
         }
     }
 
+    /// Age of the response: its age when cached plus the time since.
+    pub fn current_age(&self) -> Duration {
+        self.current_age_at(SystemTime::now())
+    }
+
+    /// Age of the response at `now`.
+    pub fn current_age_at(&self, now: SystemTime) -> Duration {
+        self.initial_age + now.duration_since(self.cached_at).unwrap_or_default()
+    }
+
+    /// How long the entry stays fresh, zero once stale.
+    pub fn remaining_freshness(&self) -> Duration {
+        self.remaining_freshness_at(SystemTime::now())
+    }
+
+    /// How long the entry stays fresh after `now`.
+    pub fn remaining_freshness_at(&self, now: SystemTime) -> Duration {
+        self.ttl.map_or(Duration::ZERO, |ttl| {
+            ttl.saturating_sub(self.current_age_at(now))
+        })
+    }
+
     /// Check if we should revalidate (entry exists but stale).
     pub fn needs_revalidation(&self) -> bool {
-        !self.is_fresh() && (self.etag.is_some() || self.last_modified.is_some())
+        self.needs_revalidation_at(SystemTime::now())
+    }
+
+    /// Check if the entry is stale at `now` and can be revalidated.
+    pub fn needs_revalidation_at(&self, now: SystemTime) -> bool {
+        !self.is_fresh_at(now) && (self.etag.is_some() || self.last_modified.is_some())
+    }
+
+    /// Content codings still applied to the stored body, lowercase and in
+    /// the order they were applied. `identity` is left out.
+    pub fn content_encodings(&self) -> Vec<String> {
+        typedheaders::content_encodings(&self.headers)
+    }
+
+    /// The entry as a response, with the stored headers and the body still
+    /// in its [`content_encodings`](Self::content_encodings).
+    pub fn into_response(self) -> HttpResponse {
+        HttpResponse::from_cache(self.status, self.headers, self.body)
     }
 }
 
 /// Cache mode for controlling behavior.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum CacheMode {
     /// Normal caching behavior (RFC 7234)
     #[default]
@@ -86,18 +190,47 @@ pub enum CacheMode {
     ReadOnly,
     /// Force refresh (ignore cached responses)
     ForceRefresh,
+    /// Revalidate a stored entry even while it is fresh, like a request's
+    /// `Cache-Control: no-cache`, and store the result
+    Revalidate,
+}
+
+impl CacheMode {
+    const ALL: [CacheMode; 5] = [
+        CacheMode::Normal,
+        CacheMode::Disabled,
+        CacheMode::ReadOnly,
+        CacheMode::ForceRefresh,
+        CacheMode::Revalidate,
+    ];
+
+    fn from_u8(value: u8) -> Self {
+        Self::ALL.get(value as usize).copied().unwrap_or_default()
+    }
 }
 
 /// In-memory HTTP cache.
 ///
 /// Thread-safe implementation using DashMap for concurrent access.
 /// Enforces size limits and provides LRU-style eviction.
+///
+/// With [`set_split_cache`](Self::set_split_cache) enabled, lookups through
+/// [`partition`](Self::partition) only see entries stored for the same
+/// top-frame site, so one site cannot probe what another has loaded.
+///
+/// The [mode](Self::set_mode) can change while the cache is shared, e.g.
+/// by a [`Client`](crate::Client); requests sent afterwards follow it
+/// unless they set their own with
+/// [`RequestBuilder::cache_mode`](crate::client::RequestBuilder::cache_mode).
 pub struct HttpCache {
     entries: DashMap<CacheKey, CacheEntry>,
     max_entries: usize,
     current_size: AtomicUsize,
     max_size_bytes: usize,
-    mode: CacheMode,
+    mode: AtomicU8,
+    split_cache: bool,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for HttpCache {
@@ -114,7 +247,10 @@ impl HttpCache {
             max_entries: 1000,
             current_size: AtomicUsize::new(0),
             max_size_bytes: 50 * 1024 * 1024, // 50MB default
-            mode: CacheMode::Normal,
+            mode: AtomicU8::new(CacheMode::Normal as u8),
+            split_cache: false,
+            metrics: None,
+            clock: system_clock(),
         }
     }
 
@@ -125,25 +261,105 @@ impl HttpCache {
             max_entries,
             current_size: AtomicUsize::new(0),
             max_size_bytes,
-            mode: CacheMode::Normal,
+            mode: AtomicU8::new(CacheMode::Normal as u8),
+            split_cache: false,
+            metrics: None,
+            clock: system_clock(),
         }
     }
 
-    /// Set the cache mode.
-    pub fn set_mode(&mut self, mode: CacheMode) {
-        self.mode = mode;
+    /// Age and expire entries by `clock` instead of the system time, e.g.
+    /// a [`MockClock`](crate::base::clock::MockClock) in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The current time by the cache's clock, to pass to the `*_at`
+    /// methods of its entries.
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    /// Largest body an entry can hold: the cache's size limit.
+    pub(crate) fn max_entry_size(&self) -> usize {
+        self.max_size_bytes
+    }
+
+    /// Set the cache mode, also while the cache is shared.
+    pub fn set_mode(&self, mode: CacheMode) {
+        self.mode.store(mode as u8, Ordering::Relaxed);
     }
 
     /// Get the current cache mode.
     pub fn mode(&self) -> CacheMode {
-        self.mode
+        CacheMode::from_u8(self.mode.load(Ordering::Relaxed))
+    }
+
+    /// Key entries by top-frame site as well as URL.
+    ///
+    /// Off by default, in which case [`partition`](Self::partition) views
+    /// all share one set of entries.
+    pub fn set_split_cache(&mut self, enabled: bool) {
+        self.split_cache = enabled;
+    }
+
+    /// Report hits and misses of fresh-entry lookups to `metrics`.
+    pub fn set_metrics(&mut self, metrics: Arc<dyn MetricsRecorder>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Whether entries are partitioned by top-frame site.
+    pub fn is_split_cache(&self) -> bool {
+        self.split_cache
+    }
+
+    /// View of the cache as seen by requests made for `nik`.
+    pub fn partition<'a>(&'a self, nik: &'a NetworkIsolationKey) -> CachePartition<'a> {
+        CachePartition { cache: self, nik }
+    }
+
+    /// Remove every entry stored in the partition for `nik`.
+    pub fn clear_partition(&self, nik: &NetworkIsolationKey) {
+        let keys: Vec<CacheKey> = self
+            .entries
+            .iter()
+            .filter(|e| e.key().isolation_key.as_ref() == Some(nik))
+            .map(|e| e.key().clone())
+            .collect();
+        for key in keys {
+            self.remove_by_key(&key);
+        }
+    }
+
+    /// Cache key for `url` in the partition for `nik`, if splitting is on.
+    fn key(&self, url: &Url, method: &str, nik: Option<&NetworkIsolationKey>) -> CacheKey {
+        let key = CacheKey::new(url, method);
+        match nik {
+            Some(nik) if self.split_cache => key.with_isolation_key(nik.clone()),
+            _ => key,
+        }
     }
 
     /// Look up a cached response.
     ///
-    /// Returns the cached entry if found and still fresh.
+    /// Returns the cached entry if found and still fresh. A HEAD lookup
+    /// falls back to a fresh GET entry, returned without its body.
     pub fn get(&self, url: &Url, method: &str) -> Option<CacheEntry> {
-        if self.mode == CacheMode::Disabled || self.mode == CacheMode::ForceRefresh {
+        self.get_in(url, method, None, self.mode())
+    }
+
+    pub(crate) fn get_in(
+        &self,
+        url: &Url,
+        method: &str,
+        nik: Option<&NetworkIsolationKey>,
+        mode: CacheMode,
+    ) -> Option<CacheEntry> {
+        if matches!(
+            mode,
+            CacheMode::Disabled | CacheMode::ForceRefresh | CacheMode::Revalidate
+        ) {
             return None;
         }
 
@@ -153,33 +369,71 @@ impl HttpCache {
             return None;
         }
 
-        let key = CacheKey::new(url, method);
-        let entry = self.entries.get(&key)?;
-
-        if entry.is_fresh() {
-            Some(entry.clone())
-        } else {
-            None
+        let key = self.key(url, method, nik);
+        let now = self.now();
+        let mut entry = self
+            .entries
+            .get(&key)
+            .filter(|e| e.is_fresh_at(now))
+            .map(|e| e.clone());
+        // A fresh GET response answers a HEAD, without its body
+        if entry.is_none() && method_upper == "HEAD" {
+            entry = self
+                .entries
+                .get(&self.key(url, "GET", nik))
+                .filter(|e| e.is_fresh_at(now))
+                .map(|e| CacheEntry {
+                    body: Bytes::new(),
+                    ..e.clone()
+                });
         }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_cache_lookup(entry.is_some());
+        }
+        entry
     }
 
     /// Get entry for conditional request (may be stale).
     ///
     /// Returns entry if it exists (even stale) for revalidation.
     pub fn get_for_revalidation(&self, url: &Url, method: &str) -> Option<CacheEntry> {
-        if self.mode == CacheMode::Disabled {
+        self.get_for_revalidation_in(url, method, None, self.mode())
+    }
+
+    pub(crate) fn get_for_revalidation_in(
+        &self,
+        url: &Url,
+        method: &str,
+        nik: Option<&NetworkIsolationKey>,
+        mode: CacheMode,
+    ) -> Option<CacheEntry> {
+        if mode == CacheMode::Disabled {
             return None;
         }
 
-        let key = CacheKey::new(url, method);
+        let key = self.key(url, method, nik);
         self.entries.get(&key).map(|e| e.clone())
     }
 
     /// Store a response in the cache.
     ///
-    /// Parses Cache-Control headers to determine cacheability.
+    /// Parses Cache-Control headers to determine cacheability. A 200
+    /// response to HEAD also freshens the stored GET response, or removes
+    /// it if its `ETag`, `Last-Modified` or `Content-Length` changed.
     pub fn store<B>(&self, url: &Url, method: &str, response: &Response<B>, body: Bytes) {
-        if self.mode == CacheMode::Disabled || self.mode == CacheMode::ReadOnly {
+        self.store_in(url, method, None, self.mode(), response, body)
+    }
+
+    pub(crate) fn store_in<B>(
+        &self,
+        url: &Url,
+        method: &str,
+        nik: Option<&NetworkIsolationKey>,
+        mode: CacheMode,
+        response: &Response<B>,
+        body: Bytes,
+    ) {
+        if mode == CacheMode::Disabled || mode == CacheMode::ReadOnly {
             return;
         }
 
@@ -194,6 +448,10 @@ impl HttpCache {
             return;
         }
 
+        if method_upper == "HEAD" {
+            self.update_from_head(url, nik, response);
+        }
+
         // Check Cache-Control
         let cache_control = parse_cache_control(response.headers());
 
@@ -202,14 +460,31 @@ impl HttpCache {
             return;
         }
 
-        // Calculate TTL
-        let ttl = cache_control.max_age.map(Duration::from_secs);
+        // The key cannot tell apart the requests a varying response fits
+        if varies(response.headers()) {
+            self.remove_by_key(&self.key(url, method, nik));
+            return;
+        }
+
+        if body.len() > self.max_size_bytes {
+            return;
+        }
 
-        // Skip if not cacheable
-        if ttl.is_none() && cache_control.no_cache {
+        // A HEAD response declares the length of a body it does not have
+        if method_upper == "GET" && !matches_content_length(response.headers(), body.len()) {
+            tracing::debug!(
+                url = %url,
+                len = body.len(),
+                "not caching a body that contradicts its Content-Length"
+            );
             return;
         }
 
+        // Calculate TTL and how old the response already is
+        let now = self.now();
+        let ttl = freshness_lifetime_at(response.headers(), response.status(), now);
+        let initial_age = initial_age(response.headers(), now, now);
+
         // Extract ETag and Last-Modified
         let etag = response
             .headers()
@@ -228,9 +503,10 @@ impl HttpCache {
             status: response.status(),
             headers: response.headers().clone(),
             body: body.clone(),
-            cached_at: Instant::now(),
+            cached_at: now,
             inserted_at: Instant::now(),
             ttl,
+            initial_age,
             etag,
             last_modified,
         };
@@ -239,84 +515,125 @@ impl HttpCache {
         self.maybe_evict(body.len());
 
         // Store
-        let key = CacheKey::new(url, method);
+        let key = self.key(url, method, nik);
         self.current_size.fetch_add(body.len(), Ordering::Relaxed);
         self.entries.insert(key, entry);
     }
 
     /// Update cache entry from a 304 Not Modified response.
     pub fn update_from_not_modified<B>(&self, url: &Url, method: &str, response: &Response<B>) {
-        let key = CacheKey::new(url, method);
+        self.update_from_not_modified_in(url, method, None, response)
+    }
+
+    pub(crate) fn update_from_not_modified_in<B>(
+        &self,
+        url: &Url,
+        method: &str,
+        nik: Option<&NetworkIsolationKey>,
+        response: &Response<B>,
+    ) {
+        let key = self.key(url, method, nik);
 
         if let Some(mut entry) = self.entries.get_mut(&key) {
-            // Update headers from the 304 response
-            for (name, value) in response.headers() {
-                // Update certain headers
-                if name == http::header::CACHE_CONTROL
-                    || name == http::header::ETAG
-                    || name == http::header::EXPIRES
-                    || name == http::header::DATE
-                {
-                    entry.headers.insert(name.clone(), value.clone());
-                }
-            }
+            freshen(&mut entry, response.headers(), self.now());
+        }
+    }
 
-            // Refresh TTL
-            let cache_control = parse_cache_control(response.headers());
-            if let Some(max_age) = cache_control.max_age {
-                entry.ttl = Some(Duration::from_secs(max_age));
-            }
-            entry.cached_at = Instant::now();
-            // Note: We do NOT update inserted_at here, to preserve insertion order for pseudo-LRU.
-            // If we updated it, it would act more like true LRU but with write contention.
-
-            // Update ETag if present
-            if let Some(etag) = response
-                .headers()
-                .get(http::header::ETAG)
-                .and_then(|v| v.to_str().ok())
-            {
-                entry.etag = Some(etag.to_string());
-            }
+    /// Freshen the stored GET response for `url` with a HEAD response, or
+    /// drop it if the HEAD response shows it changed (RFC 9111 section
+    /// 4.3.5).
+    fn update_from_head<B>(
+        &self,
+        url: &Url,
+        nik: Option<&NetworkIsolationKey>,
+        response: &Response<B>,
+    ) {
+        if response.status() != StatusCode::OK {
+            return;
         }
+        let key = self.key(url, "GET", nik);
+        let Some(mut entry) = self.entries.get_mut(&key) else {
+            return;
+        };
+
+        let differs = |name: http::header::HeaderName| match (
+            entry.headers.get(&name),
+            response.headers().get(&name),
+        ) {
+            (Some(stored), Some(new)) => stored != new,
+            _ => false,
+        };
+        if differs(http::header::ETAG)
+            || differs(http::header::LAST_MODIFIED)
+            || differs(http::header::CONTENT_LENGTH)
+        {
+            drop(entry);
+            self.remove_by_key(&key);
+            return;
+        }
+        freshen(&mut entry, response.headers(), self.now());
     }
 
     /// Generate conditional request headers if we have a stale entry.
     pub fn get_conditional_headers(&self, url: &Url, method: &str) -> Option<HeaderMap> {
-        let entry = self.get_for_revalidation(url, method)?;
+        self.get_conditional_headers_in(url, method, None, self.mode())
+    }
 
-        if !entry.needs_revalidation() && entry.is_fresh() {
+    pub(crate) fn get_conditional_headers_in(
+        &self,
+        url: &Url,
+        method: &str,
+        nik: Option<&NetworkIsolationKey>,
+        mode: CacheMode,
+    ) -> Option<HeaderMap> {
+        let entry = self.get_for_revalidation_in(url, method, nik, mode)?;
+
+        let now = self.now();
+        if mode != CacheMode::Revalidate
+            && !entry.needs_revalidation_at(now)
+            && entry.is_fresh_at(now)
+        {
             return None; // Entry is fresh, no need to revalidate
         }
+        validators(&entry)
+    }
 
-        let mut headers = HeaderMap::new();
-
-        if let Some(etag) = &entry.etag {
-            if let Ok(value) = HeaderValue::from_str(etag) {
-                headers.insert(http::header::IF_NONE_MATCH, value);
-            }
-        }
+    /// Conditional request headers for the stored entry, fresh or stale,
+    /// for [`CacheMode::Revalidate`].
+    pub(crate) fn get_validators_in(
+        &self,
+        url: &Url,
+        method: &str,
+        nik: Option<&NetworkIsolationKey>,
+        mode: CacheMode,
+    ) -> Option<HeaderMap> {
+        validators(&self.get_for_revalidation_in(url, method, nik, mode)?)
+    }
 
-        if let Some(last_modified) = &entry.last_modified {
-            if let Ok(value) = HeaderValue::from_str(last_modified) {
-                headers.insert(http::header::IF_MODIFIED_SINCE, value);
-            }
-        }
+    /// Copies of all entries, fresh or stale, with their keys.
+    pub(crate) fn entries(&self) -> Vec<(CacheKey, CacheEntry)> {
+        self.entries
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect()
+    }
 
-        if headers.is_empty() {
-            None
-        } else {
-            Some(headers)
+    /// Insert an entry captured by [`entries`](Self::entries), e.g. from
+    /// another process. Respects the mode and limits like a store.
+    pub(crate) fn restore(&self, key: CacheKey, entry: CacheEntry) {
+        if matches!(self.mode(), CacheMode::Disabled | CacheMode::ReadOnly) {
+            return;
         }
+        self.remove_by_key(&key);
+        self.maybe_evict(entry.body.len());
+        self.current_size
+            .fetch_add(entry.body.len(), Ordering::Relaxed);
+        self.entries.insert(key, entry);
     }
 
     /// Remove an entry from the cache.
     pub fn remove(&self, url: &Url, method: &str) {
-        let key = CacheKey::new(url, method);
-        if let Some((_, entry)) = self.entries.remove(&key) {
-            self.current_size
-                .fetch_sub(entry.body.len(), Ordering::Relaxed);
-        }
+        self.remove_by_key(&CacheKey::new(url, method));
     }
 
     /// Clear all cached entries.
@@ -384,9 +701,240 @@ impl HttpCache {
     }
 }
 
+/// The part of an [`HttpCache`] visible to one top-frame site.
+///
+/// Created by [`HttpCache::partition`]. Mirrors the cache's own lookup and
+/// store methods; when the cache is not split they act on the shared
+/// entries.
+#[derive(Clone, Copy)]
+pub struct CachePartition<'a> {
+    cache: &'a HttpCache,
+    nik: &'a NetworkIsolationKey,
+}
+
+impl CachePartition<'_> {
+    /// The partition's isolation key.
+    pub fn isolation_key(&self) -> &NetworkIsolationKey {
+        self.nik
+    }
+
+    /// Look up a fresh cached response. See [`HttpCache::get`].
+    pub fn get(&self, url: &Url, method: &str) -> Option<CacheEntry> {
+        self.cache
+            .get_in(url, method, Some(self.nik), self.cache.mode())
+    }
+
+    /// Get a possibly stale entry. See [`HttpCache::get_for_revalidation`].
+    pub fn get_for_revalidation(&self, url: &Url, method: &str) -> Option<CacheEntry> {
+        self.cache
+            .get_for_revalidation_in(url, method, Some(self.nik), self.cache.mode())
+    }
+
+    /// Store a response. See [`HttpCache::store`].
+    pub fn store<B>(&self, url: &Url, method: &str, response: &Response<B>, body: Bytes) {
+        self.cache.store_in(
+            url,
+            method,
+            Some(self.nik),
+            self.cache.mode(),
+            response,
+            body,
+        )
+    }
+
+    /// Refresh an entry from a 304. See
+    /// [`HttpCache::update_from_not_modified`].
+    pub fn update_from_not_modified<B>(&self, url: &Url, method: &str, response: &Response<B>) {
+        self.cache
+            .update_from_not_modified_in(url, method, Some(self.nik), response)
+    }
+
+    /// Conditional request headers. See
+    /// [`HttpCache::get_conditional_headers`].
+    pub fn get_conditional_headers(&self, url: &Url, method: &str) -> Option<HeaderMap> {
+        self.cache
+            .get_conditional_headers_in(url, method, Some(self.nik), self.cache.mode())
+    }
+
+    /// Remove an entry from this partition.
+    pub fn remove(&self, url: &Url, method: &str) {
+        self.cache
+            .remove_by_key(&self.cache.key(url, method, Some(self.nik)));
+    }
+}
+
+/// Merge the headers of a 304 or HEAD response into the stored `entry`
+/// and restart its freshness from them at `now`.
+fn freshen(entry: &mut CacheEntry, headers: &HeaderMap, now: SystemTime) {
+    for (name, value) in headers {
+        // Update certain headers
+        if name == http::header::CACHE_CONTROL
+            || name == http::header::ETAG
+            || name == http::header::EXPIRES
+            || name == http::header::DATE
+            || name == http::header::AGE
+        {
+            entry.headers.insert(name.clone(), value.clone());
+        }
+    }
+
+    // Refresh TTL from the merged headers
+    let status = entry.status;
+    if let Some(ttl) = freshness_lifetime_at(&entry.headers, status, now) {
+        entry.ttl = Some(ttl);
+    }
+    entry.initial_age = initial_age(headers, now, now);
+    entry.cached_at = now;
+    // Note: We do NOT update inserted_at here, to preserve insertion order for pseudo-LRU.
+    // If we updated it, it would act more like true LRU but with write contention.
+
+    // Update ETag if present
+    if let Some(etag) = headers
+        .get(http::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+    {
+        entry.etag = Some(etag.to_string());
+    }
+}
+
+/// `If-None-Match` and `If-Modified-Since` from the validators of `entry`.
+fn validators(entry: &CacheEntry) -> Option<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    if let Some(etag) = &entry.etag {
+        if let Ok(value) = HeaderValue::from_str(etag) {
+            headers.insert(http::header::IF_NONE_MATCH, value);
+        }
+    }
+
+    if let Some(last_modified) = &entry.last_modified {
+        if let Ok(value) = HeaderValue::from_str(last_modified) {
+            headers.insert(http::header::IF_MODIFIED_SINCE, value);
+        }
+    }
+
+    if headers.is_empty() {
+        None
+    } else {
+        Some(headers)
+    }
+}
+
+/// Whether a response is worth storing for later requests: a 200 without
+/// `no-store` or `Vary` that either stays fresh for a while or can be
+/// revalidated, received at `now` by the cache's clock.
+pub(crate) fn worth_storing(headers: &HeaderMap, status: StatusCode, now: SystemTime) -> bool {
+    if status != StatusCode::OK || parse_cache_control(headers).no_store || varies(headers) {
+        return false;
+    }
+    let fresh = freshness_lifetime_at(headers, status, now).is_some_and(|ttl| !ttl.is_zero());
+    fresh
+        || headers.contains_key(http::header::ETAG)
+        || headers.contains_key(http::header::LAST_MODIFIED)
+}
+
+/// Whether the response names request headers it depends on in `Vary`.
+fn varies(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(http::header::VARY)
+        .iter()
+        .any(|v| !v.as_bytes().trim_ascii().is_empty())
+}
+
+/// Whether a body of `len` bytes agrees with the `Content-Length` in
+/// `headers`, if any.
+fn matches_content_length(headers: &HeaderMap, len: usize) -> bool {
+    headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok()?.trim().parse::<u64>().ok())
+        .is_none_or(|declared| declared == len as u64)
+}
+
+/// How long a response stays fresh (RFC 9111 section 4.2.1).
+///
+/// Chromium mapping: `HttpResponseHeaders::GetFreshnessLifetimes`
+///
+/// `no-cache` (or `Pragma: no-cache`) makes every use revalidate, otherwise
+/// `max-age` wins over `Expires`, which counts from `Date`; an `Expires`
+/// that is not a valid date means already expired. Without either, 200,
+/// 203 and 206 responses with `Last-Modified` stay fresh for a tenth of the
+/// time since their last modification. `None` when nothing gives a
+/// lifetime.
+pub fn freshness_lifetime(headers: &HeaderMap, status: StatusCode) -> Option<Duration> {
+    freshness_lifetime_at(headers, status, SystemTime::now())
+}
+
+/// [`freshness_lifetime`] of a response received at `now`, which stands
+/// in for a missing `Date`, e.g. [`HttpCache::now`].
+pub fn freshness_lifetime_at(
+    headers: &HeaderMap,
+    status: StatusCode,
+    now: SystemTime,
+) -> Option<Duration> {
+    let cache_control = parse_cache_control(headers);
+    let pragma_no_cache = headers
+        .get_all(http::header::PRAGMA)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.trim().eq_ignore_ascii_case("no-cache"));
+    if cache_control.no_cache || pragma_no_cache {
+        return Some(Duration::ZERO);
+    }
+    if let Some(max_age) = cache_control.max_age {
+        return Some(Duration::from_secs(max_age));
+    }
+
+    let date = date_header(headers, http::header::DATE).unwrap_or(now);
+    if let Some(expires) = headers.get(http::header::EXPIRES) {
+        let expires = expires.to_str().ok().and_then(parse_http_date);
+        return Some(expires.map_or(Duration::ZERO, |expires| {
+            expires.duration_since(date).unwrap_or_default()
+        }));
+    }
+
+    let heuristic = matches!(status.as_u16(), 200 | 203 | 206) && !cache_control.must_revalidate;
+    if heuristic {
+        let last_modified = date_header(headers, http::header::LAST_MODIFIED)?;
+        return Some(date.duration_since(last_modified).unwrap_or_default() / 10);
+    }
+    None
+}
+
+/// Age of a response when it arrived (RFC 9111 section 4.2.3).
+///
+/// Chromium mapping: `HttpResponseHeaders::GetCurrentAge` at response time
+///
+/// The larger of the `Age` header plus the request's round trip and the
+/// time since `Date`, so neither a lagging origin clock nor a shared cache
+/// on the way makes a response look fresher than it is. `request_time` is
+/// when the request was sent and `response_time` when the response
+/// arrived.
+pub fn initial_age(
+    headers: &HeaderMap,
+    request_time: SystemTime,
+    response_time: SystemTime,
+) -> Duration {
+    let apparent_age = date_header(headers, http::header::DATE)
+        .and_then(|date| response_time.duration_since(date).ok())
+        .unwrap_or_default();
+    let age = headers
+        .get(http::header::AGE)
+        .and_then(|v| v.to_str().ok()?.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_default();
+    let response_delay = response_time
+        .duration_since(request_time)
+        .unwrap_or_default();
+    apparent_age.max(age + response_delay)
+}
+
+fn date_header(headers: &HeaderMap, name: http::header::HeaderName) -> Option<SystemTime> {
+    parse_http_date(headers.get(name)?.to_str().ok()?)
+}
+
 /// Parsed Cache-Control directive.
 #[derive(Debug, Default)]
-struct CacheControl {
+pub(crate) struct CacheControl {
     no_store: bool,
     no_cache: bool,
     max_age: Option<u64>,
@@ -394,7 +942,7 @@ struct CacheControl {
 }
 
 /// Parse Cache-Control header.
-fn parse_cache_control(headers: &HeaderMap) -> CacheControl {
+pub(crate) fn parse_cache_control(headers: &HeaderMap) -> CacheControl {
     let mut cc = CacheControl::default();
 
     let value = match headers.get(http::header::CACHE_CONTROL) {
@@ -429,6 +977,8 @@ fn parse_cache_control(headers: &HeaderMap) -> CacheControl {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::base::clock::MockClock;
+    use crate::http::httpdate::format_http_date;
     use http::Response;
 
     fn make_response(cache_control: &str, _body: &str) -> Response<()> {
@@ -454,6 +1004,27 @@ mod tests {
         assert!(entry.is_fresh());
     }
 
+    #[test]
+    fn test_lookups_are_reported_to_metrics() {
+        let metrics = crate::metrics::ClientMetrics::new();
+        let mut cache = HttpCache::new();
+        cache.set_metrics(Arc::new(metrics.clone()));
+        let url = Url::parse("https://example.com/page").unwrap();
+
+        assert!(cache.get(&url, "GET").is_none());
+        cache.store(
+            &url,
+            "GET",
+            &make_response("max-age=3600", ""),
+            Bytes::new(),
+        );
+        assert!(cache.get(&url, "GET").is_some());
+        assert!(cache.get(&url, "GET").is_some());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.cache_hits, snapshot.cache_misses), (2, 1));
+    }
+
     #[test]
     fn test_no_store_not_cached() {
         let cache = HttpCache::new();
@@ -465,6 +1036,41 @@ mod tests {
         assert!(cache.get(&url, "GET").is_none());
     }
 
+    #[test]
+    fn test_varying_response_not_cached() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/account").unwrap();
+        cache.store(
+            &url,
+            "GET",
+            &make_response("max-age=3600", "v1"),
+            Bytes::from("v1"),
+        );
+
+        let varying = Response::builder()
+            .status(200)
+            .header(http::header::CACHE_CONTROL, "max-age=3600")
+            .header(http::header::VARY, "Cookie")
+            .body(())
+            .unwrap();
+        cache.store(&url, "GET", &varying, Bytes::from("v2"));
+
+        // Neither the varying response nor the one it replaced is served
+        assert!(cache.get(&url, "GET").is_none());
+    }
+
+    #[test]
+    fn test_body_over_size_limit_not_cached() {
+        let cache = HttpCache::with_limits(10, 4);
+        let url = Url::parse("https://example.com/big").unwrap();
+        let response = make_response("max-age=3600", "");
+
+        cache.store(&url, "GET", &response, Bytes::from("too big"));
+        assert!(cache.is_empty());
+        cache.store(&url, "GET", &response, Bytes::from("fits"));
+        assert_eq!(cache.size_bytes(), 4);
+    }
+
     #[test]
     fn test_post_not_cached() {
         let cache = HttpCache::new();
@@ -496,6 +1102,172 @@ mod tests {
         assert!(headers.contains_key(http::header::IF_NONE_MATCH));
     }
 
+    #[test]
+    fn test_revalidate_mode_validates_fresh_entries() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/resource").unwrap();
+        let response = Response::builder()
+            .status(200)
+            .header(http::header::CACHE_CONTROL, "max-age=60")
+            .header(http::header::ETAG, "\"abc123\"")
+            .body(())
+            .unwrap();
+        cache.store(&url, "GET", &response, Bytes::from("body"));
+
+        let entry = cache.get(&url, "GET").unwrap();
+        assert!(entry.remaining_freshness() > Duration::from_secs(50));
+        assert!(cache.get_conditional_headers(&url, "GET").is_none());
+        assert!(cache
+            .get_validators_in(&url, "GET", None, CacheMode::Normal)
+            .is_some());
+
+        cache.set_mode(CacheMode::Revalidate);
+        assert!(cache.get(&url, "GET").is_none());
+        assert!(cache.get_conditional_headers(&url, "GET").is_some());
+    }
+
+    #[test]
+    fn test_encoded_body_is_stored_and_served_as_received() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/app.js").unwrap();
+        let wire = Bytes::from_static(b"\x1f\x8b compressed");
+        let response = Response::builder()
+            .status(200)
+            .header(http::header::CACHE_CONTROL, "max-age=3600")
+            .header(http::header::CONTENT_ENCODING, "gzip, identity")
+            .header(http::header::CONTENT_LENGTH, wire.len())
+            .body(())
+            .unwrap();
+
+        cache.store(&url, "GET", &response, wire.clone());
+        let entry = cache.get(&url, "GET").unwrap();
+        assert_eq!(entry.content_encodings(), ["gzip"]);
+        assert_eq!(cache.size_bytes(), wire.len());
+
+        let served = entry.into_response();
+        assert_eq!(
+            served.headers()[http::header::CONTENT_ENCODING],
+            "gzip, identity"
+        );
+        let body = futures::executor::block_on(served.bytes()).unwrap();
+        assert_eq!(body, wire);
+    }
+
+    #[test]
+    fn test_body_contradicting_content_length_not_cached() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/app.js").unwrap();
+        let response = |declared: &[u8]| {
+            Response::builder()
+                .status(200)
+                .header(http::header::CACHE_CONTROL, "max-age=3600")
+                .header(http::header::CONTENT_ENCODING, "gzip")
+                .header(http::header::CONTENT_LENGTH, declared.len())
+                .body(())
+                .unwrap()
+        };
+
+        // Decoded before being stored: longer than declared
+        cache.store(
+            &url,
+            "GET",
+            &response(b"short"),
+            Bytes::from_static(b"decoded and longer"),
+        );
+        assert!(cache.get(&url, "GET").is_none());
+
+        // HEAD responses declare a body they do not carry
+        cache.store(&url, "HEAD", &response(b"short"), Bytes::new());
+        assert!(cache.get(&url, "HEAD").is_some());
+    }
+
+    #[test]
+    fn test_not_modified_keeps_stored_encoding() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/app.js").unwrap();
+        let stored = Response::builder()
+            .status(200)
+            .header(http::header::CACHE_CONTROL, "max-age=0")
+            .header(http::header::ETAG, "\"v1\"")
+            .header(http::header::CONTENT_ENCODING, "br")
+            .body(())
+            .unwrap();
+        cache.store(&url, "GET", &stored, Bytes::from_static(b"brotli"));
+
+        let not_modified = Response::builder()
+            .status(304)
+            .header(http::header::CACHE_CONTROL, "max-age=60")
+            .header(http::header::CONTENT_ENCODING, "gzip")
+            .header(http::header::CONTENT_LENGTH, "0")
+            .body(())
+            .unwrap();
+        cache.update_from_not_modified(&url, "GET", &not_modified);
+
+        let entry = cache.get(&url, "GET").unwrap();
+        assert_eq!(entry.content_encodings(), ["br"]);
+        assert!(entry.headers.get(http::header::CONTENT_LENGTH).is_none());
+        assert_eq!(entry.body, "brotli");
+    }
+
+    #[test]
+    fn test_head_served_from_get() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/file").unwrap();
+        let response = Response::builder()
+            .status(200)
+            .header(http::header::CACHE_CONTROL, "max-age=3600")
+            .header(http::header::CONTENT_LENGTH, "4")
+            .body(())
+            .unwrap();
+        cache.store(&url, "GET", &response, Bytes::from("data"));
+
+        let entry = cache.get(&url, "HEAD").unwrap();
+        assert!(entry.body.is_empty());
+        assert_eq!(entry.headers[http::header::CONTENT_LENGTH], "4");
+        assert_eq!(cache.get(&url, "GET").unwrap().body, "data");
+    }
+
+    #[test]
+    fn test_head_freshens_stored_get() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/file").unwrap();
+        let response = |cache_control: &str, etag: &str| {
+            Response::builder()
+                .status(200)
+                .header(http::header::CACHE_CONTROL, cache_control)
+                .header(http::header::ETAG, etag)
+                .body(())
+                .unwrap()
+        };
+        cache.store(
+            &url,
+            "GET",
+            &response("max-age=0", "\"v1\""),
+            Bytes::from("data"),
+        );
+        assert!(cache.get(&url, "GET").is_none());
+
+        cache.store(
+            &url,
+            "HEAD",
+            &response("max-age=60", "\"v1\""),
+            Bytes::new(),
+        );
+        let entry = cache.get(&url, "GET").unwrap();
+        assert_eq!(entry.body, "data");
+        assert_eq!(entry.headers[http::header::CACHE_CONTROL], "max-age=60");
+
+        // A changed validator means the stored body is outdated
+        cache.store(
+            &url,
+            "HEAD",
+            &response("max-age=60", "\"v2\""),
+            Bytes::new(),
+        );
+        assert!(cache.get_for_revalidation(&url, "GET").is_none());
+        assert_eq!(cache.size_bytes(), 0);
+    }
+
     #[test]
     fn test_cache_clear() {
         let cache = HttpCache::new();
@@ -511,7 +1283,7 @@ mod tests {
 
     #[test]
     fn test_cache_mode_disabled() {
-        let mut cache = HttpCache::new();
+        let cache = HttpCache::new();
         cache.set_mode(CacheMode::Disabled);
 
         let url = Url::parse("https://example.com/page").unwrap();
@@ -521,6 +1293,245 @@ mod tests {
         assert!(cache.get(&url, "GET").is_none());
     }
 
+    #[test]
+    fn test_split_cache_isolates_sites() {
+        let mut cache = HttpCache::new();
+        cache.set_split_cache(true);
+        let url = Url::parse("https://cdn.example/lib.js").unwrap();
+        let site_a = NetworkIsolationKey::opaque("https://a.test");
+        let site_b = NetworkIsolationKey::opaque("https://b.test");
+
+        let response = make_response("max-age=3600", "lib");
+        cache
+            .partition(&site_a)
+            .store(&url, "GET", &response, Bytes::from("lib"));
+
+        assert!(cache.partition(&site_a).get(&url, "GET").is_some());
+        assert!(cache.partition(&site_b).get(&url, "GET").is_none());
+        assert!(cache.get(&url, "GET").is_none());
+
+        cache.clear_partition(&site_a);
+        assert!(cache.is_empty());
+        assert_eq!(cache.size_bytes(), 0);
+    }
+
+    #[test]
+    fn test_unsplit_cache_shares_partitions() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://cdn.example/lib.js").unwrap();
+        let site_a = NetworkIsolationKey::opaque("https://a.test");
+
+        let response = make_response("max-age=3600", "lib");
+        cache
+            .partition(&site_a)
+            .store(&url, "GET", &response, Bytes::from("lib"));
+
+        assert!(cache.get(&url, "GET").is_some());
+        assert!(cache
+            .partition(&NetworkIsolationKey::opaque("https://b.test"))
+            .get(&url, "GET")
+            .is_some());
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (http::HeaderName::from_static(name), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_worth_storing() {
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, HeaderValue::from_static(value));
+            }
+            headers
+        };
+        let ok = StatusCode::OK;
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(946_684_800); // 2000-01-01
+        let stored = |headers: &HeaderMap, status| worth_storing(headers, status, now);
+
+        assert!(stored(&headers(&[("cache-control", "max-age=60")]), ok));
+        assert!(stored(&headers(&[("etag", "\"v1\"")]), ok));
+        assert!(stored(
+            &headers(&[("cache-control", "no-cache"), ("etag", "\"v1\"")]),
+            ok
+        ));
+        assert!(!stored(&headers(&[("cache-control", "no-cache")]), ok));
+        assert!(!stored(&headers(&[]), ok));
+        assert!(!stored(
+            &headers(&[("cache-control", "no-store, max-age=60")]),
+            ok
+        ));
+        assert!(!stored(
+            &headers(&[("cache-control", "max-age=60")]),
+            StatusCode::PARTIAL_CONTENT
+        ));
+        assert!(!stored(
+            &headers(&[("cache-control", "max-age=60"), ("vary", "cookie")]),
+            ok
+        ));
+
+        // Expires counts from the cache's clock when there is no Date
+        let expires = headers(&[("expires", "Sat, 01 Jan 2000 00:01:00 GMT")]);
+        assert!(stored(&expires, ok));
+        assert!(!worth_storing(&expires, ok, now + Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        let date = "Sun, 06 Nov 1994 08:49:37 GMT";
+        let ok = StatusCode::OK;
+        let lifetime = |pairs: &[(&'static str, &str)]| freshness_lifetime(&headers(pairs), ok);
+
+        assert_eq!(
+            lifetime(&[
+                ("cache-control", "max-age=60"),
+                ("expires", "Sun, 06 Nov 1994 09:49:37 GMT"),
+                ("date", date),
+            ]),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            lifetime(&[("expires", "Sun, 06 Nov 1994 09:49:37 GMT"), ("date", date)]),
+            Some(Duration::from_secs(3600))
+        );
+        // Invalid Expires means already expired
+        assert_eq!(
+            lifetime(&[("expires", "0"), ("date", date)]),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            lifetime(&[("cache-control", "max-age=60, no-cache")]),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(lifetime(&[("pragma", "no-cache")]), Some(Duration::ZERO));
+        // A tenth of the time since Last-Modified
+        assert_eq!(
+            lifetime(&[
+                ("last-modified", "Sun, 06 Nov 1994 07:49:37 GMT"),
+                ("date", date)
+            ]),
+            Some(Duration::from_secs(360))
+        );
+        assert_eq!(
+            freshness_lifetime(
+                &headers(&[
+                    ("last-modified", "Sun, 06 Nov 1994 07:49:37 GMT"),
+                    ("date", date)
+                ]),
+                StatusCode::NOT_FOUND
+            ),
+            None
+        );
+        assert_eq!(lifetime(&[("date", date)]), None);
+    }
+
+    #[test]
+    fn test_initial_age() {
+        let response_time = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        let request_time = response_time - Duration::from_secs(2);
+
+        // Age plus the round trip
+        assert_eq!(
+            initial_age(&headers(&[("age", "100")]), request_time, response_time),
+            Duration::from_secs(102)
+        );
+        // Time since Date when larger
+        assert_eq!(
+            initial_age(
+                &headers(&[("age", "100"), ("date", "Sun, 06 Nov 1994 08:39:37 GMT")]),
+                request_time,
+                response_time
+            ),
+            Duration::from_secs(600)
+        );
+        // A Date in the future does not make the age negative
+        assert_eq!(
+            initial_age(
+                &headers(&[("date", "Sun, 06 Nov 1994 09:49:37 GMT")]),
+                response_time,
+                response_time
+            ),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_age_counts_against_lifetime() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/aged").unwrap();
+        let response = Response::builder()
+            .status(200)
+            .header(http::header::CACHE_CONTROL, "max-age=60")
+            .header(http::header::AGE, "120")
+            .body(())
+            .unwrap();
+
+        cache.store(&url, "GET", &response, Bytes::from("old"));
+        assert!(cache.get(&url, "GET").is_none());
+        let entry = cache.get_for_revalidation(&url, "GET").unwrap();
+        assert!(entry.current_age() >= Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_expires_without_max_age() {
+        let cache = HttpCache::new();
+        let url = Url::parse("https://example.com/expires").unwrap();
+        let now = SystemTime::now();
+        let response = Response::builder()
+            .status(200)
+            .header(http::header::DATE, format_http_date(now))
+            .header(
+                http::header::EXPIRES,
+                format_http_date(now + Duration::from_secs(3600)),
+            )
+            .body(())
+            .unwrap();
+
+        cache.store(&url, "GET", &response, Bytes::from("fresh"));
+        assert!(cache.get(&url, "GET").is_some());
+    }
+
+    #[test]
+    fn test_entries_age_by_clock() {
+        let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let cache = HttpCache::new().with_clock(Arc::new(clock.clone()));
+        let url = Url::parse("https://example.com/clock").unwrap();
+        let response = Response::builder()
+            .status(200)
+            .header(http::header::CACHE_CONTROL, "max-age=60")
+            .header(http::header::ETAG, "\"v1\"")
+            .body(())
+            .unwrap();
+        cache.store(&url, "GET", &response, Bytes::from("body"));
+
+        clock.advance(Duration::from_secs(59));
+        let entry = cache.get(&url, "GET").unwrap();
+        assert_eq!(entry.current_age_at(cache.now()), Duration::from_secs(59));
+        assert_eq!(
+            entry.remaining_freshness_at(cache.now()),
+            Duration::from_secs(1)
+        );
+        assert!(cache.get_conditional_headers(&url, "GET").is_none());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(cache.get(&url, "GET").is_none());
+        assert!(cache.get_conditional_headers(&url, "GET").is_some());
+
+        // A 304 restarts freshness from the clock's time
+        let not_modified = Response::builder()
+            .status(304)
+            .header(http::header::CACHE_CONTROL, "max-age=60")
+            .body(())
+            .unwrap();
+        cache.update_from_not_modified(&url, "GET", &not_modified);
+        clock.advance(Duration::from_secs(59));
+        assert!(cache.get(&url, "GET").is_some());
+    }
+
     #[test]
     fn test_parse_cache_control() {
         let mut headers = HeaderMap::new();
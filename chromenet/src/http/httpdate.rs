@@ -0,0 +1,199 @@
+//! HTTP-date parsing and formatting.
+//!
+//! Chromium mapping: `base::Time::FromUTCString` as used by
+//! `HttpResponseHeaders::GetTimeValuedHeader`
+//!
+//! Accepts the three formats of RFC 9110 section 5.6.7:
+//! - IMF-fixdate: `Sun, 06 Nov 1994 08:49:37 GMT`
+//! - RFC 850: `Sunday, 06-Nov-94 08:49:37 GMT`
+//! - asctime: `Sun Nov  6 08:49:37 1994`
+//!
+//! Senders must use IMF-fixdate, which is the only format produced.
+
+use std::time::SystemTime;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parse an HTTP-date in any of the three formats.
+///
+/// Returns `None` for anything else, e.g. `Expires: 0`, which caches treat
+/// as a time in the past.
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let datetime = match value.split_once(',') {
+        Some((weekday, rest)) if rest.contains('-') => {
+            weekday_long(weekday)?;
+            parse_rfc850(rest)?
+        }
+        Some((weekday, rest)) => {
+            weekday_short(weekday)?;
+            parse_imf_fixdate(rest)?
+        }
+        None => parse_asctime(value)?,
+    };
+    Some(datetime.assume_utc().into())
+}
+
+/// Format `time` as an IMF-fixdate, e.g. for `If-Modified-Since`.
+pub fn format_http_date(time: SystemTime) -> String {
+    let datetime = OffsetDateTime::from(time);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        &WEEKDAYS[datetime.weekday().number_days_from_monday() as usize][..3],
+        datetime.day(),
+        MONTHS[datetime.month() as usize - 1],
+        datetime.year(),
+        datetime.hour(),
+        datetime.minute(),
+        datetime.second(),
+    )
+}
+
+/// `06 Nov 1994 08:49:37 GMT`
+fn parse_imf_fixdate(value: &str) -> Option<PrimitiveDateTime> {
+    let [day, month, year, time, "GMT"] = fields(value)? else {
+        return None;
+    };
+    datetime(year.parse().ok()?, month, day, time)
+}
+
+/// `06-Nov-94 08:49:37 GMT`
+fn parse_rfc850(value: &str) -> Option<PrimitiveDateTime> {
+    let [date, time, "GMT"] = fields(value)? else {
+        return None;
+    };
+    let mut parts = date.split('-');
+    let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || year.len() != 2 {
+        return None;
+    }
+    datetime(full_year(year.parse().ok()?), month, day, time)
+}
+
+/// `Sun Nov  6 08:49:37 1994`
+fn parse_asctime(value: &str) -> Option<PrimitiveDateTime> {
+    let [weekday, month, day, time, year] = fields(value)?;
+    weekday_short(weekday)?;
+    datetime(year.parse().ok()?, month, day, time)
+}
+
+/// Split on runs of spaces into exactly `N` fields.
+fn fields<const N: usize>(value: &str) -> Option<[&str; N]> {
+    let mut fields = value.split_ascii_whitespace();
+    let result = std::array::from_fn(|_| fields.next().unwrap_or(""));
+    (fields.next().is_none() && result.iter().all(|f| !f.is_empty())).then_some(result)
+}
+
+fn weekday_short(name: &str) -> Option<()> {
+    WEEKDAYS
+        .iter()
+        .any(|day| day[..3].eq_ignore_ascii_case(name.trim()))
+        .then_some(())
+}
+
+fn weekday_long(name: &str) -> Option<()> {
+    WEEKDAYS
+        .iter()
+        .any(|day| day.eq_ignore_ascii_case(name.trim()))
+        .then_some(())
+}
+
+/// A two-digit year more than 50 years in the future is in the past
+/// century (RFC 9110 section 5.6.7).
+fn full_year(two_digits: i32) -> i32 {
+    let this_year = OffsetDateTime::now_utc().year();
+    let year = this_year - this_year % 100 + two_digits;
+    if year > this_year + 50 {
+        year - 100
+    } else {
+        year
+    }
+}
+
+fn datetime(year: i32, month: &str, day: &str, time: &str) -> Option<PrimitiveDateTime> {
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))?;
+    let month = Month::try_from(month as u8 + 1).ok()?;
+    if day.is_empty() || day.len() > 2 {
+        return None;
+    }
+    let date = Date::from_calendar_date(year, month, day.parse().ok()?).ok()?;
+
+    let mut parts = time.split(':');
+    let mut part = || -> Option<u8> {
+        let part = parts.next()?;
+        (part.len() == 2).then(|| part.parse().ok()).flatten()
+    };
+    let (hour, minute, second) = (part()?, part()?, part()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    // A leap second is read as the last second of the minute
+    let time = Time::from_hms(hour, minute, second.min(59)).ok()?;
+    Some(PrimitiveDateTime::new(date, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const SUNDAY_NOV_6_1994: u64 = 784111777;
+
+    fn epoch(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_parse_all_formats() {
+        let expected = Some(epoch(SUNDAY_NOV_6_1994));
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), expected);
+        assert_eq!(
+            parse_http_date("  sun, 06 nov 1994 08:49:37 GMT "),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_dates() {
+        for value in [
+            "",
+            "0",
+            "-1",
+            "Sun, 06 Nov 1994 08:49:37",
+            "Sun, 06 Nov 1994 08:49:37 PST",
+            "Sun, 31 Feb 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 25:49:37 GMT",
+            "Sun, 06 Nov 1994 8:49:37 GMT",
+            "Someday, 06-Nov-94 08:49:37 GMT",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+            "Sun Nov  6 08:49:37",
+        ] {
+            assert_eq!(parse_http_date(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_format_round_trips() {
+        assert_eq!(
+            format_http_date(epoch(SUNDAY_NOV_6_1994)),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        let now = epoch(1_700_000_000);
+        assert_eq!(parse_http_date(&format_http_date(now)), Some(now));
+    }
+}
@@ -0,0 +1,204 @@
+//! Newline-delimited JSON response bodies.
+//!
+//! Firehose-style APIs stream one JSON value per line (NDJSON, JSON Lines),
+//! or per record in a `application/json-seq` body (RFC 7464). A
+//! [`JsonLines`] deserializes each value as its line completes, so a long
+//! lived stream is consumed without buffering it whole:
+//!
+//! ```ignore
+//! use futures::StreamExt;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Event {
+//!     id: u64,
+//! }
+//!
+//! let mut events = client.get(url).send().await?.json_lines::<Event>()?;
+//! while let Some(event) = events.next().await {
+//!     println!("{}", event?.id);
+//! }
+//! ```
+//!
+//! Lines end with LF or CRLF and may be split across chunks. Blank lines
+//! are skipped, as is the record separator (0x1E) leading JSON text
+//! sequences. A line that is not valid JSON for `T` yields
+//! [`NetError::JsonParseError`] and the stream goes on with the next one;
+//! a transfer error ends it.
+
+use crate::base::neterror::NetError;
+use bytes::{Buf, BytesMut};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Record separator starting each value of a JSON text sequence.
+const RECORD_SEPARATOR: u8 = 0x1e;
+
+/// Values deserialized from a body one line at a time, from
+/// [`HttpResponse::json_lines`](crate::http::HttpResponse::json_lines).
+pub struct JsonLines<T> {
+    inner: BoxStream<'static, Result<bytes::Bytes, NetError>>,
+    buf: BytesMut,
+    /// Bytes of `buf` already searched for a line end.
+    scanned: usize,
+    line_limit: usize,
+    done: bool,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> JsonLines<T> {
+    /// Deserialize the lines of `body`, a stream of body chunks.
+    pub fn new<S>(body: S) -> Self
+    where
+        S: futures::Stream<Item = Result<bytes::Bytes, NetError>> + Send + 'static,
+    {
+        Self {
+            inner: body.boxed(),
+            buf: BytesMut::new(),
+            scanned: 0,
+            line_limit: usize::MAX,
+            done: false,
+            _item: PhantomData,
+        }
+    }
+
+    /// Fail with [`NetError::ResponseBodyTooBig`] on a line longer than
+    /// `limit` bytes.
+    pub fn with_line_limit(mut self, limit: usize) -> Self {
+        self.line_limit = limit;
+        self
+    }
+
+    /// The next complete line in the buffer, or the rest of it once the
+    /// body ended.
+    fn next_line(&mut self) -> Result<Option<BytesMut>, NetError> {
+        let end = self.buf[self.scanned..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|pos| self.scanned + pos);
+        if end.unwrap_or(self.buf.len()) > self.line_limit {
+            return Err(NetError::ResponseBodyTooBig {
+                limit: self.line_limit,
+            });
+        }
+        if let Some(end) = end {
+            let mut line = self.buf.split_to(end + 1);
+            self.scanned = 0;
+            line.truncate(end);
+            return Ok(Some(line));
+        }
+        self.scanned = self.buf.len();
+        if self.done && !self.buf.is_empty() {
+            self.scanned = 0;
+            return Ok(Some(self.buf.split()));
+        }
+        Ok(None)
+    }
+
+    /// Deserialize a line, `None` if it holds no value.
+    fn parse(mut line: BytesMut) -> Option<Result<T, NetError>> {
+        if line.first() == Some(&RECORD_SEPARATOR) {
+            line.advance(1);
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+        Some(serde_json::from_slice(&line).map_err(|_| NetError::JsonParseError))
+    }
+}
+
+impl<T: DeserializeOwned> futures::Stream for JsonLines<T> {
+    type Item = Result<T, NetError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.next_line() {
+                Ok(Some(line)) => match Self::parse(line) {
+                    Some(item) => return Poll::Ready(Some(item)),
+                    None => continue,
+                },
+                Ok(None) if self.done => return Poll::Ready(None),
+                Ok(None) => {}
+                Err(e) => {
+                    self.buf.clear();
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+            match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.buf.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(e))) => {
+                    self.buf.clear();
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Ready(None) => self.done = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for JsonLines<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLines")
+            .field("buffered", &self.buf.len())
+            .field("line_limit", &self.line_limit)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Event {
+        id: u32,
+    }
+
+    fn chunks(body: &'static [u8], size: usize) -> JsonLines<Event> {
+        let chunks: Vec<_> = body
+            .chunks(size)
+            .map(|chunk| Ok(Bytes::from_static(chunk)))
+            .collect();
+        JsonLines::new(futures::stream::iter(chunks))
+    }
+
+    #[tokio::test]
+    async fn test_lines_split_across_chunks() {
+        let body = b"{\"id\":1}\r\n\n{\"id\": 2}\n\x1e{\"id\":3}\n{\"id\":4}";
+        for size in [1, 3, body.len()] {
+            let events: Vec<_> = chunks(body, size)
+                .map(|event| event.unwrap().id)
+                .collect()
+                .await;
+            assert_eq!(events, [1, 2, 3, 4]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bad_line_skipped_and_limit_enforced() {
+        let events: Vec<_> = chunks(b"{\"id\":1}\nnope\n{\"id\":2}\n", 4).collect().await;
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[1], Err(NetError::JsonParseError)));
+        assert_eq!(events[2].as_ref().unwrap(), &Event { id: 2 });
+
+        let events: Vec<_> = chunks(b"{\"id\":1}\n{\"id\":   2}\n", 4)
+            .with_line_limit(9)
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[1],
+            Err(NetError::ResponseBodyTooBig { limit: 9 })
+        ));
+    }
+}
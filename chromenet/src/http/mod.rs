@@ -3,25 +3,97 @@
 //! Provides HTTP/1.1 and HTTP/2 support mirroring Chromium's `net/http/`:
 //! - [`transaction`]: State machine for request/response lifecycle
 //! - [`streamfactory`]: H1/H2 stream creation
+//! - [`batch`]: Many requests with bounded concurrency, results in order
+//! - [`contentdecoder`]: Pluggable decoders of `Content-Encoding` codings
+//! - [`contentdigest`]: `Content-Digest` and `Repr-Digest` request headers
+//! - [`serverproperties`]: Per-origin HTTP/1.1 downgrades
+//! - [`singleflight`]: Coalescing of identical concurrent requests
+//! - [`shutdown`]: Graceful shutdown of a client
+//! - [`shareddictionary`]: Compression dictionary transport (`dcb`/`dcz`)
+//! - [`tracecontext`]: W3C `traceparent`/`tracestate` propagation
 //! - [`httpcache`]: In-memory HTTP cache with Cache-Control
-//! - [`multipart`]: Multipart form data encoding
+//! - [`prefetch`]: Background revalidation and prefetching into the cache
+//! - [`conditional`]: ETag and Last-Modified validators for conditional
+//!   requests
+//! - [`httpdate`]: HTTP-date parsing and formatting
+//! - [`headerlimits`]: Size limits and validation for request headers
+//! - [`requestparts`]: Requests resolved without sending them, for
+//!   signing and inspection
+//! - [`h2preface`]: The frames an HTTP/2 connection opens with, as an
+//!   Akamai fingerprint
+//! - [`identity`]: The fingerprints and identifying headers a request
+//!   presented, for verifying emulation
+//! - [`priority`]: RFC 9218 `priority` request header
+//! - [`typedheaders`]: Typed values of common response headers
+//! - [`resumable`]: Uploads that resume after connection failures
+//! - [`multipart`]: Multipart form data encoding and multipart response
+//!   parsing
+//! - [`jsonlines`]: Newline-delimited JSON response bodies
+//! - [`query`]: Query strings from `serde` values
+//! - [`ratelimit`]: Per-origin delays from `Retry-After` and `RateLimit`
+//!   headers
 //! - [`responsebody`]: Body streaming with `futures::Stream`
 
+pub mod batch;
+pub mod conditional;
+pub mod contentdecoder;
+pub mod contentdigest;
 pub mod digestauth;
 pub mod h2fingerprint;
+pub mod h2preface;
+pub mod headerlimits;
+pub mod httpauth;
 pub mod httpcache;
+pub mod httpdate;
+pub mod identity;
+pub mod jsonlines;
 pub mod multipart;
 pub mod orderedheaders;
+pub mod prefetch;
+pub mod priority;
+pub mod query;
+pub mod ratelimit;
 pub mod requestbody;
+pub mod requestparts;
 pub mod response;
 pub mod responsebody;
+pub mod resumable;
 pub mod retry;
+pub mod serverproperties;
+pub mod shareddictionary;
+pub mod shutdown;
+pub mod singleflight;
 pub mod streamfactory;
+pub mod tracecontext;
 pub mod transaction;
+pub mod typedheaders;
+
+mod h2grease;
+mod h2settings;
 
 // Re-exports for convenience
-pub use h2fingerprint::H2Fingerprint;
-pub use httpcache::{CacheEntry, CacheMode, HttpCache};
-pub use requestbody::RequestBody;
-pub use response::HttpResponse;
-pub use responsebody::ResponseBody;
+pub use batch::Batch;
+pub use conditional::{Conditional, EntityTag, Validators};
+pub use contentdecoder::{ContentDecoder, ContentDecoders};
+pub use contentdigest::{ContentDigest, DigestAlgorithm};
+pub use h2fingerprint::{ConnWindowUpdate, GreaseFrame, GreaseSetting, H2Fingerprint};
+pub use h2preface::H2Preface;
+pub use headerlimits::HeaderLimits;
+pub use httpcache::{CacheEntry, CacheMode, CachePartition, HttpCache};
+pub use identity::IdentityReport;
+pub use jsonlines::JsonLines;
+pub use prefetch::{PrefetchRound, Prefetcher};
+pub use priority::{ExtensiblePriority, PriorityHeader};
+pub use ratelimit::RateLimiter;
+pub use requestbody::{RequestBody, RewindableBody};
+pub use requestparts::{BodyDescriptor, RequestParts};
+pub use response::{HttpResponse, StatusError};
+pub use responsebody::{PartialBody, ResponseBody};
+pub use resumable::{ResumableUpload, ResumeProtocol};
+pub use serverproperties::HttpServerProperties;
+pub use shareddictionary::{SharedDictionary, SharedDictionaryStore};
+pub use shutdown::ShutdownReport;
+pub use singleflight::SingleFlight;
+pub use streamfactory::{H2SessionPolicy, H2cMode, HttpVersionPref};
+pub use tracecontext::{TraceContext, TracePropagator};
+pub use typedheaders::{ContentType, RetryAfter};
@@ -1,6 +1,8 @@
 //! Multipart form data support.
 //!
-//! Provides RFC 2046 multipart/form-data encoding for file uploads.
+//! Provides RFC 2046 multipart/form-data encoding for file uploads, and
+//! parsing of multipart response bodies such as `multipart/x-mixed-replace`
+//! camera feeds or `multipart/byteranges`.
 //! Inspired by wreq's multipart implementation.
 //!
 //! # Example
@@ -13,9 +15,25 @@
 //!
 //! // Use form.into_body() to get the request body
 //! ```
+//!
+//! Responses are read part by part with
+//! [`HttpResponse::multipart`](crate::http::HttpResponse::multipart):
+//! ```ignore
+//! use futures::StreamExt;
+//!
+//! let mut parts = response.multipart()?;
+//! while let Some(part) = parts.next().await {
+//!     let part = part?;
+//!     println!("{:?}: {} bytes", part.content_type(), part.body().len());
+//! }
+//! ```
 
-use bytes::Bytes;
+use crate::base::neterror::NetError;
+use bytes::{Buf, Bytes, BytesMut};
+use http::{HeaderMap, HeaderName, HeaderValue};
 use std::borrow::Cow;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 /// A multipart form for file uploads.
 #[derive(Debug)]
@@ -77,9 +95,10 @@ impl Form {
 
         let mut length = 0usize;
 
+        let delimiter = delimiter(&self.boundary);
         for (name, part) in &self.fields {
             // --boundary\r\n
-            length += 2 + self.boundary.len() + 2;
+            length += delimiter.len() + 2;
 
             // Content-Disposition header
             let header = part.format_headers(name);
@@ -96,7 +115,7 @@ impl Form {
         }
 
         // Final boundary: --boundary--\r\n
-        length += 2 + self.boundary.len() + 4;
+        length += delimiter.len() + 4;
 
         Some(length)
     }
@@ -107,12 +126,12 @@ impl Form {
             return Bytes::new();
         }
 
+        let delimiter = delimiter(&self.boundary);
         let mut output = Vec::new();
 
         for (name, part) in self.fields {
             // --boundary\r\n
-            output.extend_from_slice(b"--");
-            output.extend_from_slice(self.boundary.as_bytes());
+            output.extend_from_slice(delimiter.as_bytes());
             output.extend_from_slice(b"\r\n");
 
             // Headers
@@ -125,8 +144,7 @@ impl Form {
         }
 
         // Final boundary
-        output.extend_from_slice(b"--");
-        output.extend_from_slice(self.boundary.as_bytes());
+        output.extend_from_slice(delimiter.as_bytes());
         output.extend_from_slice(b"--\r\n");
 
         Bytes::from(output)
@@ -225,6 +243,11 @@ fn escape_quotes(s: &str) -> Cow<'_, str> {
     }
 }
 
+/// The line that starts each part: `--` and the boundary (RFC 2046 5.1.1).
+fn delimiter(boundary: &str) -> String {
+    format!("--{}", boundary)
+}
+
 /// Generate a random boundary string.
 fn generate_boundary() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -242,6 +265,255 @@ fn generate_boundary() -> String {
     )
 }
 
+/// A part of a multipart response body.
+#[derive(Debug, Clone)]
+pub struct ReceivedPart {
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl ReceivedPart {
+    /// The part's headers. Parts without headers default to
+    /// `text/plain; charset=us-ascii` (RFC 2046 5.1.1).
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The part's `Content-Type`, if it has one.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+    }
+
+    /// The part's body.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Take the part's body.
+    pub fn into_body(self) -> Bytes {
+        self.body
+    }
+}
+
+/// Where a [`PartStream`] is in the body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    /// Before the first delimiter; the preamble is skipped
+    Preamble,
+    /// At a delimiter line, after the end of a part
+    Delimiter,
+    /// At the headers of a part
+    Headers,
+    /// In the body of a part, with its headers
+    Body,
+    /// After the close delimiter; the epilogue is skipped
+    Done,
+}
+
+/// A stream of the parts of a multipart body, read from a stream of body
+/// chunks.
+///
+/// Each part is buffered until the delimiter after it arrives, so an
+/// endless `multipart/x-mixed-replace` body yields every part as soon as
+/// it is complete. Line endings may be CRLF or bare LF. A body that ends
+/// before the close delimiter, or a part over the limit, fails with
+/// [`NetError::InvalidResponse`] or [`NetError::ResponseBodyTooBig`].
+pub struct PartStream<S> {
+    inner: S,
+    delimiter: Vec<u8>,
+    buf: BytesMut,
+    state: ParseState,
+    headers: HeaderMap,
+    part_limit: usize,
+    inner_done: bool,
+}
+
+impl<S> PartStream<S>
+where
+    S: futures::Stream<Item = Result<Bytes, NetError>> + Unpin,
+{
+    /// Parse the parts of `body`, delimited by `boundary`, the
+    /// `boundary` parameter of its `Content-Type`.
+    pub fn new(body: S, boundary: &str) -> Self {
+        Self {
+            inner: body,
+            delimiter: delimiter(boundary).into_bytes(),
+            buf: BytesMut::new(),
+            state: ParseState::Preamble,
+            headers: HeaderMap::new(),
+            part_limit: usize::MAX,
+            inner_done: false,
+        }
+    }
+
+    /// Fail with [`NetError::ResponseBodyTooBig`] on a part larger than
+    /// `limit` bytes, headers included.
+    pub fn with_part_limit(mut self, limit: usize) -> Self {
+        self.part_limit = limit;
+        self
+    }
+
+    /// Parse what is buffered: a complete part, `None` if more data is
+    /// needed or the body is done.
+    fn parse(&mut self) -> Result<Option<ReceivedPart>, NetError> {
+        loop {
+            match self.state {
+                ParseState::Preamble => {
+                    // The first delimiter starts the body or a line
+                    let at = if self.buf.starts_with(&self.delimiter) {
+                        Some(0)
+                    } else {
+                        find_line(&self.buf, &self.delimiter).map(|i| i + 1)
+                    };
+                    match at {
+                        Some(at) => {
+                            self.buf.advance(at);
+                            self.state = ParseState::Delimiter;
+                        }
+                        None => {
+                            // Keep enough to match a delimiter split
+                            // across chunks
+                            let keep = self.delimiter.len() + 1;
+                            let skip = self.buf.len().saturating_sub(keep);
+                            self.buf.advance(skip);
+                            return Ok(None);
+                        }
+                    }
+                }
+                ParseState::Delimiter => {
+                    let rest = &self.buf[self.delimiter.len().min(self.buf.len())..];
+                    if rest.starts_with(b"--") {
+                        self.buf.clear();
+                        self.state = ParseState::Done;
+                        return Ok(None);
+                    }
+                    // Transport padding up to the end of the line
+                    let Some(eol) = rest.iter().position(|&b| b == b'\n') else {
+                        return Ok(None);
+                    };
+                    self.buf.advance(self.delimiter.len() + eol + 1);
+                    self.state = ParseState::Headers;
+                }
+                ParseState::Headers => {
+                    let Some((len, end)) = header_block(&self.buf) else {
+                        if self.buf.len() > self.part_limit {
+                            return Err(NetError::ResponseBodyTooBig {
+                                limit: self.part_limit,
+                            });
+                        }
+                        return Ok(None);
+                    };
+                    self.headers = parse_headers(&self.buf[..len])?;
+                    self.buf.advance(end);
+                    self.state = ParseState::Body;
+                }
+                ParseState::Body => {
+                    let Some(at) = find_line(&self.buf, &self.delimiter) else {
+                        if self.buf.len() > self.part_limit {
+                            return Err(NetError::ResponseBodyTooBig {
+                                limit: self.part_limit,
+                            });
+                        }
+                        return Ok(None);
+                    };
+                    // The line break before the delimiter belongs to it
+                    let mut body = self.buf.split_to(at + 1).freeze();
+                    body.truncate(at);
+                    if body.ends_with(b"\r") {
+                        body.truncate(at - 1);
+                    }
+                    self.state = ParseState::Delimiter;
+                    return Ok(Some(ReceivedPart {
+                        headers: std::mem::take(&mut self.headers),
+                        body,
+                    }));
+                }
+                ParseState::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+impl<S> futures::Stream for PartStream<S>
+where
+    S: futures::Stream<Item = Result<Bytes, NetError>> + Unpin,
+{
+    type Item = Result<ReceivedPart, NetError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.parse() {
+                Ok(Some(part)) => return Poll::Ready(Some(Ok(part))),
+                Ok(None) if self.state == ParseState::Done => return Poll::Ready(None),
+                Ok(None) if self.inner_done => {
+                    // Ended before the close delimiter
+                    self.state = ParseState::Done;
+                    return Poll::Ready(Some(Err(NetError::InvalidResponse)));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.state = ParseState::Done;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.buf.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(e))) => {
+                    self.state = ParseState::Done;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Ready(None) => self.inner_done = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Position of the line break before a line starting with `delimiter`.
+fn find_line(buf: &[u8], delimiter: &[u8]) -> Option<usize> {
+    buf.windows(delimiter.len() + 1)
+        .position(|w| w[0] == b'\n' && &w[1..] == delimiter)
+}
+
+/// Length of the header lines of a part and where its body starts.
+fn header_block(buf: &[u8]) -> Option<(usize, usize)> {
+    // No headers: the part starts with the empty line
+    if buf.starts_with(b"\r\n") {
+        return Some((0, 2));
+    }
+    if buf.starts_with(b"\n") {
+        return Some((0, 1));
+    }
+    buf.windows(2).enumerate().find_map(|(i, w)| match w {
+        b"\n\n" => Some((i + 1, i + 2)),
+        b"\n\r" if buf.get(i + 2) == Some(&b'\n') => Some((i + 1, i + 3)),
+        _ => None,
+    })
+}
+
+/// Parse `Name: value` lines.
+fn parse_headers(block: &[u8]) -> Result<HeaderMap, NetError> {
+    let mut headers = HeaderMap::new();
+    for line in block.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let colon = line
+            .iter()
+            .position(|&b| b == b':')
+            .ok_or(NetError::InvalidResponse)?;
+        let name = HeaderName::from_bytes(line[..colon].trim_ascii())
+            .map_err(|_| NetError::InvalidResponse)?;
+        let value = HeaderValue::from_bytes(line[colon + 1..].trim_ascii())
+            .map_err(|_| NetError::InvalidResponse)?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,4 +598,65 @@ mod tests {
         assert!(body_str.contains("data.bin"));
         assert!(body_str.ends_with("--\r\n"));
     }
+
+    fn parts(chunks: &[&'static [u8]], boundary: &str) -> Vec<Result<ReceivedPart, NetError>> {
+        use futures::StreamExt;
+        let body = futures::stream::iter(chunks.iter().map(|c| Ok(Bytes::from_static(c))));
+        futures::executor::block_on(PartStream::new(body, boundary).collect())
+    }
+
+    #[test]
+    fn test_parse_form_body() {
+        let form = Form::new()
+            .text("field", "value")
+            .part("file", Part::bytes(b"a\r\nb".as_slice()));
+        let boundary = form.boundary().to_string();
+        let body: &'static [u8] = Vec::leak(form.into_body().to_vec());
+
+        let parts: Vec<_> = parts(&[body], &boundary)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].content_type(), Some("text/plain; charset=utf-8"));
+        assert_eq!(parts[0].body().as_ref(), b"value");
+        assert_eq!(parts[1].body().as_ref(), b"a\r\nb");
+    }
+
+    #[test]
+    fn test_parse_split_chunks() {
+        let body: &[&[u8]] = &[
+            b"preamble\r\n--fr",
+            b"ame\r\nContent-Type: image/jpeg\r\n\r\nJP",
+            b"EG1\r\n--frame\r\n\r\nJPEG2\r",
+            b"\n--frame--\r\nepilogue",
+        ];
+        let parts: Vec<_> = parts(body, "frame")
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].content_type(), Some("image/jpeg"));
+        assert_eq!(parts[0].body().as_ref(), b"JPEG1");
+        assert!(parts[1].headers().is_empty());
+        assert_eq!(parts[1].body().as_ref(), b"JPEG2");
+    }
+
+    #[test]
+    fn test_parse_bare_lf() {
+        let body: &[&[u8]] = &[b"--b\nX-Id: 1\n\none\n--b--\n"];
+        let parts = parts(body, "b");
+        let part = parts[0].as_ref().unwrap();
+        assert_eq!(part.headers()["x-id"], "1");
+        assert_eq!(part.body().as_ref(), b"one");
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let body: &[&[u8]] = &[b"--b\r\n\r\nfirst\r\n--b\r\n\r\nsecond"];
+        let parts = parts(body, "b");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].as_ref().unwrap().body().as_ref(), b"first");
+        assert!(matches!(parts[1], Err(NetError::InvalidResponse)));
+    }
 }
@@ -1,15 +1,40 @@
 use crate::base::neterror::NetError;
+use crate::base::secret::{is_sensitive_header, REDACTED};
 use http::header::{HeaderName, HeaderValue};
 use http::HeaderMap;
 use std::str::FromStr;
 
 /// A header map that strictly preserves insertion order.
 /// Used to construct requests with specific fingerprinting characteristics.
-#[derive(Debug, Clone, Default)]
+///
+/// `Debug` output redacts the values of sensitive headers such as
+/// `Authorization` and `Cookie`.
+#[derive(Clone, Default)]
 pub struct OrderedHeaderMap {
     headers: Vec<(HeaderName, HeaderValue)>,
 }
 
+impl std::fmt::Debug for OrderedHeaderMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), redacted(name.as_str(), value))),
+            )
+            .finish()
+    }
+}
+
+/// `value` for `Debug` output, or `[redacted]` for a sensitive header.
+fn redacted<'a>(name: &str, value: &'a HeaderValue) -> &'a dyn std::fmt::Debug {
+    if is_sensitive_header(name) {
+        &REDACTED
+    } else {
+        value
+    }
+}
+
 impl OrderedHeaderMap {
     pub fn new() -> Self {
         Self {
@@ -51,6 +76,21 @@ impl OrderedHeaderMap {
         }
     }
 
+    /// Iterate over headers in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&HeaderName, &HeaderValue)> {
+        self.headers.iter().map(|(n, v)| (n, v))
+    }
+
+    /// Number of headers.
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Check if empty.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
     /// Consumes the map and returns a standard http::HeaderMap.
     /// Note: http::HeaderMap preserves insertion order.
     pub fn to_header_map(self) -> HeaderMap {
@@ -139,6 +179,21 @@ mod tests {
         assert!(headers.get("Any").is_none());
     }
 
+    #[test]
+    fn test_iter_in_insertion_order() {
+        let mut headers = OrderedHeaderMap::new();
+        headers.insert("User-Agent", "test").unwrap();
+        headers.insert("Accept", "*/*").unwrap();
+        headers.insert("user-agent", "updated").unwrap();
+
+        let pairs: Vec<_> = headers
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.to_str().unwrap()))
+            .collect();
+        assert_eq!(pairs, [("user-agent", "updated"), ("accept", "*/*")]);
+        assert_eq!(headers.len(), 2);
+    }
+
     #[test]
     fn test_clone() {
         let mut headers = OrderedHeaderMap::new();
@@ -146,6 +201,20 @@ mod tests {
         let cloned = headers.clone();
         assert!(cloned.get("Test").is_some());
     }
+
+    #[test]
+    fn test_debug_redacts_sensitive_values() {
+        let mut headers = OrderedHeaderMap::new();
+        headers.insert("Accept", "text/html").unwrap();
+        headers.insert("Authorization", "Bearer t0ken").unwrap();
+        headers.insert("Cookie", "sid=abc").unwrap();
+        let debug = format!("{headers:?}");
+        assert!(debug.contains("text/html"), "{debug}");
+        assert!(
+            !debug.contains("t0ken") && !debug.contains("sid"),
+            "{debug}"
+        );
+    }
 }
 
 /// Case-sensitive header map for HTTP/1.1.
@@ -153,12 +222,27 @@ mod tests {
 /// Preserves original header casing for fingerprinting.
 /// HTTP/1.1 headers are case-insensitive per spec, but some
 /// servers and fingerprinting detectors check exact casing.
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct CaseSensitiveHeaders {
     /// Headers as (original_name, value) pairs
     headers: Vec<(String, String)>,
 }
 
+impl std::fmt::Debug for CaseSensitiveHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.headers.iter().map(|(name, value)| {
+                let value = if is_sensitive_header(name) {
+                    REDACTED
+                } else {
+                    value.as_str()
+                };
+                (name, value)
+            }))
+            .finish()
+    }
+}
+
 impl CaseSensitiveHeaders {
     /// Create a new case-sensitive header map.
     pub fn new() -> Self {
@@ -229,6 +313,19 @@ impl CaseSensitiveHeaders {
     }
 }
 
+impl TryFrom<&CaseSensitiveHeaders> for OrderedHeaderMap {
+    type Error = NetError;
+
+    /// Convert keeping order; names are normalized to lowercase.
+    fn try_from(headers: &CaseSensitiveHeaders) -> Result<Self, Self::Error> {
+        let mut map = OrderedHeaderMap::new();
+        for (name, value) in headers.iter() {
+            map.insert(name, value)?;
+        }
+        Ok(map)
+    }
+}
+
 /// Generate Sec-CH-UA header value for Chrome-based browsers.
 ///
 /// Format: `"Brand";v="version", ...`
@@ -296,6 +393,20 @@ mod case_tests {
         assert_eq!(title_cased[1].0, "Accept-Encoding");
     }
 
+    #[test]
+    fn test_into_ordered_header_map() {
+        let mut headers = CaseSensitiveHeaders::new();
+        headers.insert("X-Custom", "1");
+        headers.insert("Accept", "*/*");
+
+        let ordered = OrderedHeaderMap::try_from(&headers).unwrap();
+        let names: Vec<_> = ordered.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["x-custom", "accept"]);
+
+        headers.insert("Bad Name", "x");
+        assert!(OrderedHeaderMap::try_from(&headers).is_err());
+    }
+
     #[test]
     fn test_sec_ch_ua_chrome() {
         let ua = generate_sec_ch_ua("Chrome", 143, true);
@@ -0,0 +1,315 @@
+//! Keeping the cache entries of a set of URLs warm in the background.
+//!
+//! Latency-sensitive consumers want their requests answered from the
+//! [client's cache](crate::ClientBuilder::cache) rather than wait for a
+//! round trip when an entry just expired. A [`Prefetcher`] checks its URLs
+//! every [`interval`](Prefetcher::interval), give or take a random
+//! [`jitter`](Prefetcher::jitter) so many clients do not hit an origin in
+//! lockstep:
+//!
+//! - a URL without a cache entry is fetched and stored;
+//! - an entry that expires within [`refresh_ahead`](Prefetcher::refresh_ahead),
+//!   or already has, is revalidated with [`CacheMode::Revalidate`], so an
+//!   unchanged resource costs a `304`;
+//! - a fresh entry is left alone.
+//!
+//! Requests are sent one at a time, [`spacing`](Prefetcher::spacing) apart
+//! and at [`RequestPriority::Idle`], so they yield connections to the
+//! client's other requests. They go through the client like any other, so
+//! its [`RateLimiter`](crate::http::RateLimiter), cookies and emulation
+//! apply.
+//!
+//! ```no_run
+//! use chromenet::http::HttpCache;
+//! use chromenet::Client;
+//! use std::time::Duration;
+//! use url::Url;
+//!
+//! # async fn run() {
+//! let client = Client::builder().cache(HttpCache::new()).build();
+//! let prefetcher = client
+//!     .prefetcher()
+//!     .url(Url::parse("https://example.com/config.json").unwrap())
+//!     .interval(Duration::from_secs(30))
+//!     .start();
+//! // Requests for config.json are answered from the cache while it runs
+//! # drop(prefetcher);
+//! # }
+//! ```
+
+use crate::base::neterror::NetError;
+use crate::client::Client;
+use crate::http::httpcache::CacheMode;
+use crate::socket::pool::RequestPriority;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// Time between rounds by default.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Random delay added to each interval by default.
+const DEFAULT_JITTER: Duration = Duration::from_secs(6);
+
+/// Time between two requests of a round by default.
+const DEFAULT_SPACING: Duration = Duration::from_millis(100);
+
+/// What one round of a [`Prefetcher`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrefetchRound {
+    /// URLs without a cache entry, fetched and stored.
+    pub fetched: usize,
+    /// Entries about to expire, or expired, that were revalidated.
+    pub revalidated: usize,
+    /// Entries fresh beyond the refresh window, left alone.
+    pub fresh: usize,
+    /// Requests that failed.
+    pub failed: usize,
+}
+
+/// Refreshes the cache entries of registered URLs on a schedule.
+///
+/// Created with [`Client::prefetcher`]. Nothing is sent until
+/// [`start`](Self::start) or [`run_once`](Self::run_once); dropping a
+/// started prefetcher stops it.
+pub struct Prefetcher {
+    client: Client,
+    urls: Arc<Mutex<Vec<Url>>>,
+    interval: Duration,
+    jitter: Duration,
+    refresh_ahead: Option<Duration>,
+    spacing: Duration,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Prefetcher {
+    /// A prefetcher for `client`, without URLs.
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            urls: Arc::new(Mutex::new(Vec::new())),
+            interval: DEFAULT_INTERVAL,
+            jitter: DEFAULT_JITTER,
+            refresh_ahead: None,
+            spacing: DEFAULT_SPACING,
+            task: None,
+        }
+    }
+
+    /// Keep `url` warm.
+    pub fn url(self, url: Url) -> Self {
+        self.register(url);
+        self
+    }
+
+    /// Keep all of `urls` warm.
+    pub fn urls<I>(self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = Url>,
+    {
+        for url in urls {
+            self.register(url);
+        }
+        self
+    }
+
+    /// Check the URLs every `interval`. Defaults to one minute.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Wait up to `jitter` longer than the interval, chosen at random for
+    /// each round. Defaults to six seconds.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Revalidate entries that expire within `window`. Defaults to the
+    /// interval plus the jitter, so entries are refreshed in the last round
+    /// before they expire.
+    pub fn refresh_ahead(mut self, window: Duration) -> Self {
+        self.refresh_ahead = Some(window);
+        self
+    }
+
+    /// Wait `spacing` between two requests of a round. Defaults to 100 ms.
+    pub fn spacing(mut self, spacing: Duration) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Add `url` to the URLs kept warm, also while running. A URL already
+    /// registered is not added twice.
+    pub fn register(&self, url: Url) {
+        let mut urls = self.urls.lock().unwrap_or_else(|e| e.into_inner());
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+
+    /// Stop keeping `url` warm. Its cache entry stays.
+    pub fn unregister(&self, url: &Url) {
+        self.urls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|u| u != url);
+    }
+
+    /// The URLs kept warm.
+    pub fn registered(&self) -> Vec<Url> {
+        self.urls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Run a round now: fetch, revalidate or skip each URL.
+    pub async fn run_once(&self) -> PrefetchRound {
+        let refresh_ahead = self.refresh_ahead.unwrap_or(self.interval + self.jitter);
+        let nik = self.client.isolation_key();
+        let mut round = PrefetchRound::default();
+        let mut sent = false;
+        for url in self.registered() {
+            let remaining = self.client.cache().and_then(|cache| {
+                cache
+                    .get_for_revalidation_in(&url, "GET", nik, cache.mode())
+                    .map(|entry| entry.remaining_freshness_at(cache.now()))
+            });
+            let mode = match remaining {
+                Some(remaining) if remaining > refresh_ahead => {
+                    round.fresh += 1;
+                    continue;
+                }
+                Some(_) => CacheMode::Revalidate,
+                None => CacheMode::Normal,
+            };
+            if std::mem::replace(&mut sent, true) && !self.spacing.is_zero() {
+                tokio::time::sleep(self.spacing).await;
+            }
+
+            match self.fetch(&url, mode).await {
+                Ok(()) if mode == CacheMode::Revalidate => round.revalidated += 1,
+                Ok(()) => round.fetched += 1,
+                Err(e) => {
+                    tracing::debug!(target: "chromenet::http", url = %url, error = %e, "prefetch failed");
+                    round.failed += 1;
+                }
+            }
+        }
+        round
+    }
+
+    /// Send one request of a round and read its body, which stores it.
+    async fn fetch(&self, url: &Url, mode: CacheMode) -> Result<(), NetError> {
+        self.client
+            .get(url.as_str())
+            .cache_mode(mode)
+            .priority(RequestPriority::Idle)
+            .send()
+            .await?
+            .buffer()
+            .await?;
+        Ok(())
+    }
+
+    /// Run a round now and then after every interval, in a background
+    /// task, until the prefetcher is stopped or dropped or the client
+    /// [shuts down](Client::shutdown).
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn start(mut self) -> Self {
+        if self.task.is_some() {
+            return self;
+        }
+        let schedule = Prefetcher {
+            client: self.client.clone(),
+            urls: self.urls.clone(),
+            interval: self.interval,
+            jitter: self.jitter,
+            refresh_ahead: self.refresh_ahead,
+            spacing: self.spacing,
+            task: None,
+        };
+        let task = tokio::spawn(async move {
+            loop {
+                let round = schedule.run_once().await;
+                tracing::debug!(target: "chromenet::http", ?round, "prefetch round done");
+                tokio::time::sleep(schedule.next_delay()).await;
+            }
+        });
+        self.client.track_task(task.abort_handle());
+        self.task = Some(task);
+        self
+    }
+
+    /// Stop the background task, finishing no request it has in flight.
+    pub fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+
+    /// Whether the background task is running.
+    pub fn is_running(&self) -> bool {
+        self.task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+
+    /// The interval plus a random part of the jitter.
+    fn next_delay(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return self.interval;
+        }
+        let mut random = [0u8; 4];
+        let _ = boring::rand::rand_bytes(&mut random);
+        let fraction = u32::from_be_bytes(random) as f64 / u32::MAX as f64;
+        self.interval + self.jitter.mul_f64(fraction)
+    }
+}
+
+impl Drop for Prefetcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl std::fmt::Debug for Prefetcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Prefetcher")
+            .field("urls", &self.registered())
+            .field("interval", &self.interval)
+            .field("jitter", &self.jitter)
+            .field("refresh_ahead", &self.refresh_ahead)
+            .field("spacing", &self.spacing)
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_delay_within_jitter() {
+        let prefetcher = Prefetcher::new(Client::new())
+            .interval(Duration::from_secs(10))
+            .jitter(Duration::from_secs(2));
+        for _ in 0..20 {
+            let delay = prefetcher.next_delay();
+            assert!(delay >= Duration::from_secs(10) && delay <= Duration::from_secs(12));
+        }
+        let prefetcher = prefetcher.jitter(Duration::ZERO);
+        assert_eq!(prefetcher.next_delay(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_register_once() {
+        let url = Url::parse("https://example.com/a").unwrap();
+        let prefetcher = Prefetcher::new(Client::new())
+            .url(url.clone())
+            .urls([url.clone()]);
+        assert_eq!(prefetcher.registered(), [url.clone()]);
+        prefetcher.unregister(&url);
+        assert!(prefetcher.registered().is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+//! Extensible priorities (RFC 9218): the `priority` request header.
+//!
+//! Chromium mapping: `ConvertRequestPriorityToQuicPriority` and the
+//! `priority` header `SpdyHttpStream` and `QuicHttpStream` add
+//!
+//! A request's [`RequestPriority`] is sent as its urgency, from `u=0` for
+//! [`Highest`](RequestPriority::Highest) down to `u=5` for
+//! [`Throttled`](RequestPriority::Throttled), with `i` when the response is
+//! useful in parts, e.g. a page being rendered as it arrives. Like Chrome,
+//! defaults (`u=3`, not incremental) are left out, and a request with
+//! nothing else to say sends no header.
+//!
+//! Whether the header goes out depends on the [`PriorityHeader`] mode:
+//! Chrome sends it on HTTP/2 and HTTP/3, Firefox on every request. HTTP/2
+//! connections announcing `SETTINGS_NO_RFC7540_PRIORITIES` (see
+//! [`H2Fingerprint::no_rfc7540_priorities`](crate::http::H2Fingerprint::no_rfc7540_priorities))
+//! send it unless it is turned off, as it is then their only priority
+//! signal. A `priority` header set on the request is sent as is.
+
+use crate::socket::pool::RequestPriority;
+use http::HeaderValue;
+use std::fmt;
+
+/// Name of the `priority` request header.
+pub const PRIORITY: &str = "priority";
+
+/// When to send the `priority` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PriorityHeader {
+    /// On HTTP/2 connections without RFC 7540 priorities.
+    #[default]
+    Auto,
+    /// On HTTP/2 and HTTP/3, like Chrome.
+    Multiplexed,
+    /// On every request, HTTP/1.1 included, like Firefox.
+    Always,
+    /// Never.
+    Never,
+}
+
+impl PriorityHeader {
+    /// Whether a request sends the header, `multiplexed` if it goes over
+    /// HTTP/2 or HTTP/3 and `rfc7540_disabled` if its connection announced
+    /// `SETTINGS_NO_RFC7540_PRIORITIES`.
+    pub fn applies(self, multiplexed: bool, rfc7540_disabled: bool) -> bool {
+        match self {
+            Self::Auto => multiplexed && rfc7540_disabled,
+            Self::Multiplexed => multiplexed,
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// An RFC 9218 priority: urgency 0 (most urgent) to 7, and whether the
+/// response is processed incrementally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtensiblePriority {
+    urgency: u8,
+    incremental: bool,
+}
+
+impl Default for ExtensiblePriority {
+    fn default() -> Self {
+        Self {
+            urgency: Self::DEFAULT_URGENCY,
+            incremental: false,
+        }
+    }
+}
+
+impl ExtensiblePriority {
+    /// Urgency of requests that do not say.
+    pub const DEFAULT_URGENCY: u8 = 3;
+    /// Least urgent urgency.
+    pub const MAX_URGENCY: u8 = 7;
+
+    /// A priority with `urgency`, capped at [`MAX_URGENCY`](Self::MAX_URGENCY).
+    pub fn new(urgency: u8, incremental: bool) -> Self {
+        Self {
+            urgency: urgency.min(Self::MAX_URGENCY),
+            incremental,
+        }
+    }
+
+    /// The priority Chrome sends for `priority`.
+    pub fn from_request_priority(priority: RequestPriority, incremental: bool) -> Self {
+        let urgency = match priority {
+            RequestPriority::Highest => 0,
+            RequestPriority::Medium => 1,
+            RequestPriority::Low => 2,
+            RequestPriority::Lowest => 3,
+            RequestPriority::Idle => 4,
+            RequestPriority::Throttled => 5,
+        };
+        Self::new(urgency, incremental)
+    }
+
+    /// Parse a `priority` value. Unknown and invalid members are ignored,
+    /// keeping their defaults (RFC 9218 4).
+    pub fn parse(value: &str) -> Self {
+        let mut priority = Self::default();
+        for member in value.split(',') {
+            let member = member.split(';').next().unwrap_or("").trim();
+            let (key, value) = match member.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (member, None),
+            };
+            match (key, value) {
+                ("u", Some(value)) => match value.parse::<u8>() {
+                    Ok(urgency) if urgency <= Self::MAX_URGENCY => priority.urgency = urgency,
+                    _ => {}
+                },
+                ("i", None | Some("?1")) => priority.incremental = true,
+                ("i", Some("?0")) => priority.incremental = false,
+                _ => {}
+            }
+        }
+        priority
+    }
+
+    /// Urgency, 0 (most urgent) to 7.
+    pub fn urgency(&self) -> u8 {
+        self.urgency
+    }
+
+    /// Whether the response is processed incrementally.
+    pub fn incremental(&self) -> bool {
+        self.incremental
+    }
+
+    /// The header value, `None` when everything is at its default.
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        let value = self.to_string();
+        (!value.is_empty()).then(|| HeaderValue::from_str(&value).expect("valid header value"))
+    }
+}
+
+impl fmt::Display for ExtensiblePriority {
+    /// Members left at their default are omitted: `u=0, i`, `u=1`, `i`
+    /// or nothing.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let urgency = self.urgency != Self::DEFAULT_URGENCY;
+        if urgency {
+            write!(f, "u={}", self.urgency)?;
+        }
+        if self.incremental {
+            f.write_str(if urgency { ", i" } else { "i" })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_request_priority() {
+        let value = |priority, incremental| {
+            ExtensiblePriority::from_request_priority(priority, incremental).to_string()
+        };
+        assert_eq!(value(RequestPriority::Highest, true), "u=0, i");
+        assert_eq!(value(RequestPriority::Medium, false), "u=1");
+        assert_eq!(value(RequestPriority::Idle, false), "u=4");
+        assert_eq!(value(RequestPriority::Lowest, true), "i");
+
+        let default = ExtensiblePriority::from_request_priority(RequestPriority::Lowest, false);
+        assert_eq!(default, ExtensiblePriority::default());
+        assert_eq!(default.header_value(), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ExtensiblePriority::parse("u=0, i"),
+            ExtensiblePriority::new(0, true)
+        );
+        assert_eq!(
+            ExtensiblePriority::parse("i=?0, u=5, x=1"),
+            ExtensiblePriority::new(5, false)
+        );
+        // Out of range or malformed members keep their defaults
+        assert_eq!(
+            ExtensiblePriority::parse("u=9, i=1"),
+            ExtensiblePriority::default()
+        );
+        assert_eq!(ExtensiblePriority::new(12, false).urgency(), 7);
+    }
+
+    #[test]
+    fn test_modes() {
+        assert!(PriorityHeader::Auto.applies(true, true));
+        assert!(!PriorityHeader::Auto.applies(true, false));
+        assert!(PriorityHeader::Multiplexed.applies(true, false));
+        assert!(!PriorityHeader::Multiplexed.applies(false, false));
+        assert!(PriorityHeader::Always.applies(false, false));
+        assert!(!PriorityHeader::Never.applies(true, true));
+    }
+}
@@ -0,0 +1,223 @@
+//! Query string building from `serde` values.
+//!
+//! Values are flattened into `application/x-www-form-urlencoded` pairs:
+//! - A list of `(key, value)` tuples gives the pairs in order
+//! - Struct and map fields become keys; `None` fields are left out
+//! - Sequences repeat the key: `tags=a&tags=b`
+//! - Nested structs and maps use brackets: `filter[status]=open`, with an
+//!   index for structured sequence elements: `items[0][id]=1`
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Serialize;
+use std::fmt;
+use url::Url;
+
+/// Flatten `query` into key/value pairs.
+pub fn to_pairs<T: Serialize + ?Sized>(
+    query: &T,
+) -> Result<Vec<(String, String)>, serde_json::Error> {
+    // A round trip through JSON text keeps struct fields in order, which
+    // `serde_json::Value` maps do not
+    let node: Node = serde_json::from_slice(&serde_json::to_vec(query)?)?;
+    let mut pairs = Vec::new();
+    match node {
+        Node::Map(fields) => {
+            for (key, value) in fields {
+                flatten(&key, value, &mut pairs);
+            }
+        }
+        // `&[("k", "v")]` and other lists of pairs
+        Node::Seq(items) => {
+            for item in items {
+                let Node::Seq(pair) = item else {
+                    return Err(invalid("query list items must be (key, value) pairs"));
+                };
+                let Ok([key, value]) = <[Node; 2]>::try_from(pair) else {
+                    return Err(invalid("query list items must be (key, value) pairs"));
+                };
+                let Node::Scalar(key) = key else {
+                    return Err(invalid("query keys must be strings or numbers"));
+                };
+                flatten(&key, value, &mut pairs);
+            }
+        }
+        Node::Null => {}
+        Node::Scalar(_) => return Err(invalid("query must be a struct, map or list of pairs")),
+    }
+    Ok(pairs)
+}
+
+/// Append `pairs` to the query of `url`, after the parameters it already
+/// has.
+pub fn append(url: &mut Url, pairs: &[(String, String)]) {
+    if pairs.is_empty() {
+        return;
+    }
+    url.query_pairs_mut().extend_pairs(pairs);
+}
+
+fn flatten(key: &str, node: Node, pairs: &mut Vec<(String, String)>) {
+    match node {
+        Node::Null => {}
+        Node::Scalar(value) => pairs.push((key.to_string(), value)),
+        Node::Map(fields) => {
+            for (field, value) in fields {
+                flatten(&format!("{}[{}]", key, field), value, pairs);
+            }
+        }
+        Node::Seq(items) => {
+            for (i, item) in items.into_iter().enumerate() {
+                match item {
+                    Node::Map(_) | Node::Seq(_) => flatten(&format!("{}[{}]", key, i), item, pairs),
+                    _ => flatten(key, item, pairs),
+                }
+            }
+        }
+    }
+}
+
+fn invalid(message: &str) -> serde_json::Error {
+    serde::ser::Error::custom(message)
+}
+
+/// A serialized value, with map entries in their original order.
+enum Node {
+    Null,
+    Scalar(String),
+    Seq(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Node, E> {
+        Ok(Node::Scalar(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Node::Map(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn query<T: Serialize + ?Sized>(value: &T) -> String {
+        let mut url = Url::parse("https://example.com/search?q=rust").unwrap();
+        append(&mut url, &to_pairs(value).unwrap());
+        url.query().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_tuple_list() {
+        assert_eq!(
+            query(&[("page", "2"), ("tag", "a b"), ("tag", "c&d")]),
+            "q=rust&page=2&tag=a+b&tag=c%26d"
+        );
+        assert_eq!(query(&[("n", 1), ("n", 2)]), "q=rust&n=1&n=2");
+    }
+
+    #[test]
+    fn test_struct() {
+        #[derive(Serialize)]
+        struct Filter {
+            status: &'static str,
+            min: u32,
+        }
+        #[derive(Serialize)]
+        struct Item {
+            id: u32,
+        }
+        #[derive(Serialize)]
+        struct Params {
+            tags: Vec<&'static str>,
+            limit: Option<u32>,
+            cursor: Option<String>,
+            exact: bool,
+            filter: Filter,
+            items: Vec<Item>,
+        }
+
+        let params = Params {
+            tags: vec!["x", "y"],
+            limit: Some(10),
+            cursor: None,
+            exact: true,
+            filter: Filter {
+                status: "open",
+                min: 3,
+            },
+            items: vec![Item { id: 1 }, Item { id: 2 }],
+        };
+        assert_eq!(
+            query(&params),
+            "q=rust&tags=x&tags=y&limit=10&exact=true&filter%5Bstatus%5D=open&filter%5Bmin%5D=3\
+             &items%5B0%5D%5Bid%5D=1&items%5B1%5D%5Bid%5D=2"
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let mut map = BTreeMap::new();
+        map.insert("é", "ü");
+        assert_eq!(query(&map), "q=rust&%C3%A9=%C3%BC");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(to_pairs(&"just a string").is_err());
+        assert!(to_pairs(&[1, 2]).is_err());
+        assert!(to_pairs(&[([1], "v")]).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+//! Per-origin politeness delays from `Retry-After` and `RateLimit` headers.
+//!
+//! When a server answers 429 or 503 with `Retry-After`, or reports an
+//! exhausted quota with `RateLimit` headers, later requests to that origin
+//! wait until it allows them again. Supported headers:
+//! - `Retry-After: 120` or an HTTP-date, on 429 and 503 responses
+//! - `RateLimit: limit=100, remaining=0, reset=30` and
+//!   `RateLimit: "default";r=0;t=30` (IETF drafts)
+//! - `RateLimit-Remaining: 0` with `RateLimit-Reset: 30`, and the
+//!   `X-RateLimit-*` equivalents, whose reset may be a Unix timestamp
+
+use crate::http::typedheaders;
+use http::{HeaderMap, StatusCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use url::Url;
+
+/// Resets above this are Unix timestamps rather than seconds.
+const UNIX_TIMESTAMP_THRESHOLD: u64 = 1_000_000_000;
+
+/// Per-origin schedule of when requests may be sent again.
+///
+/// Cloning shares the schedule, so one limiter can serve several clients
+/// and be queried by the caller.
+///
+/// ```no_run
+/// use chromenet::http::RateLimiter;
+/// use chromenet::Client;
+///
+/// let limiter = RateLimiter::new();
+/// let client = Client::builder().rate_limiter(limiter.clone()).build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    blocked: Arc<Mutex<HashMap<String, Instant>>>,
+    max_delay: Duration,
+    default_delay: Duration,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            blocked: Arc::default(),
+            max_delay: Duration::from_secs(300),
+            default_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Create an empty schedule.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Longest delay a server can impose. Defaults to five minutes.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Delay after a 429 or 503 response without rate limit headers.
+    /// Defaults to none.
+    pub fn with_default_delay(mut self, delay: Duration) -> Self {
+        self.default_delay = delay;
+        self
+    }
+
+    /// Update the schedule of `url`'s origin from a response. Returns the
+    /// delay the response imposed, if any.
+    pub fn record(&self, url: &Url, status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+        let limited = matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        );
+        let delay = if limited {
+            retry_after(headers)
+                .or_else(|| quota_reset(headers))
+                .or((!self.default_delay.is_zero()).then_some(self.default_delay))
+        } else {
+            quota_reset(headers)
+        }?;
+        let delay = delay.min(self.max_delay);
+        if delay.is_zero() {
+            return None;
+        }
+        tracing::debug!(
+            origin = %origin(url),
+            status = status.as_u16(),
+            delay_ms = delay.as_millis() as u64,
+            "rate limited"
+        );
+        self.set_delay(url, delay);
+        Some(delay)
+    }
+
+    /// Hold requests to `url`'s origin for `delay`. A later time already
+    /// scheduled is kept.
+    pub fn set_delay(&self, url: &Url, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut blocked = self.blocked.lock().unwrap();
+        let entry = blocked.entry(origin(url)).or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    /// Hold requests to `url`'s origin for the `Crawl-delay` of its
+    /// robots.txt, capped like delays from headers.
+    pub(crate) fn record_crawl_delay(&self, url: &Url, delay: Duration) {
+        self.set_delay(url, delay.min(self.max_delay));
+    }
+
+    /// Time left until requests to `url`'s origin may be sent, `None` if
+    /// they may be sent now.
+    pub fn delay(&self, url: &Url) -> Option<Duration> {
+        let until = *self.blocked.lock().unwrap().get(&origin(url))?;
+        let left = until.saturating_duration_since(Instant::now());
+        (!left.is_zero()).then_some(left)
+    }
+
+    /// Origins currently held, with the time left for each.
+    pub fn limited_origins(&self) -> Vec<(String, Duration)> {
+        let now = Instant::now();
+        let mut blocked = self.blocked.lock().unwrap();
+        blocked.retain(|_, until| *until > now);
+        blocked
+            .iter()
+            .map(|(origin, until)| (origin.clone(), *until - now))
+            .collect()
+    }
+
+    /// Allow requests to `url`'s origin right away.
+    pub fn clear(&self, url: &Url) {
+        self.blocked.lock().unwrap().remove(&origin(url));
+    }
+
+    /// Wait until requests to `url`'s origin may be sent.
+    pub async fn wait(&self, url: &Url) {
+        while let Some(delay) = self.delay(url) {
+            tracing::debug!(
+                origin = %origin(url),
+                delay_ms = delay.as_millis() as u64,
+                "waiting for rate limit"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn origin(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+/// `Retry-After` as a delay from now.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    typedheaders::retry_after(headers).map(|r| r.delay())
+}
+
+/// Time until an exhausted quota resets.
+fn quota_reset(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok();
+
+    if let Some(value) = header("ratelimit") {
+        if let Some(reset) = structured_reset(value) {
+            return Some(reset);
+        }
+    }
+    for prefix in ["ratelimit", "x-ratelimit"] {
+        let remaining =
+            header(&format!("{}-remaining", prefix)).and_then(|v| v.trim().parse::<u64>().ok());
+        if remaining != Some(0) {
+            continue;
+        }
+        if let Some(reset) =
+            header(&format!("{}-reset", prefix)).and_then(|v| v.trim().parse::<u64>().ok())
+        {
+            return Some(reset_delay(reset));
+        }
+    }
+    None
+}
+
+/// Reset of an exhausted `RateLimit` header, in either draft syntax.
+fn structured_reset(value: &str) -> Option<Duration> {
+    // Older draft: one policy as comma-separated parameters
+    if value.contains("remaining=") {
+        return (param_value(value, "remaining") == Some(0))
+            .then(|| param_value(value, "reset"))
+            .flatten()
+            .map(reset_delay);
+    }
+    // Newer draft: a list of policies, each with `r` and `t` parameters
+    value.split(',').find_map(|policy| {
+        (param_value(policy, "r") == Some(0))
+            .then(|| param_value(policy, "t"))
+            .flatten()
+            .map(reset_delay)
+    })
+}
+
+/// Integer parameter `name` of a `,` or `;` separated list.
+fn param_value(value: &str, name: &str) -> Option<u64> {
+    value.split([',', ';']).find_map(|param| {
+        let (key, value) = param.trim().split_once('=')?;
+        if key.trim() != name {
+            return None;
+        }
+        value.trim().parse().ok()
+    })
+}
+
+/// Delay for a reset given in seconds or as a Unix timestamp.
+fn reset_delay(reset: u64) -> Duration {
+    if reset < UNIX_TIMESTAMP_THRESHOLD {
+        return Duration::from_secs(reset);
+    }
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    Duration::from_secs(reset).saturating_sub(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (http::HeaderName::from_static(name), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_retry_after() {
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "120")])),
+            Some(Duration::from_secs(120))
+        );
+        // A date in the past means now
+        assert_eq!(
+            retry_after(&headers(&[(
+                "retry-after",
+                "Sun, 06 Nov 1994 08:49:37 GMT"
+            )])),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&headers(&[("retry-after", "soon")])), None);
+    }
+
+    #[test]
+    fn test_quota_reset() {
+        assert_eq!(
+            quota_reset(&headers(&[(
+                "ratelimit",
+                "limit=100, remaining=0, reset=30"
+            )])),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            quota_reset(&headers(&[(
+                "ratelimit",
+                "limit=100, remaining=5, reset=30"
+            )])),
+            None
+        );
+        assert_eq!(
+            quota_reset(&headers(&[("ratelimit", "\"default\";r=0;t=12")])),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(
+            quota_reset(&headers(&[
+                ("x-ratelimit-remaining", "0"),
+                ("x-ratelimit-reset", "7"),
+            ])),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            quota_reset(&headers(&[
+                ("ratelimit-remaining", "3"),
+                ("ratelimit-reset", "7"),
+            ])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_record_schedules_origin() {
+        let limiter = RateLimiter::new().with_max_delay(Duration::from_secs(60));
+        let url = Url::parse("https://api.example.com/v1/items").unwrap();
+        let other_path = Url::parse("https://api.example.com/other").unwrap();
+        let other_origin = Url::parse("https://www.example.com/").unwrap();
+
+        // Retry-After is ignored on success responses
+        assert_eq!(
+            limiter.record(&url, StatusCode::OK, &headers(&[("retry-after", "30")])),
+            None
+        );
+        assert_eq!(limiter.delay(&url), None);
+
+        // Capped at the maximum delay
+        assert_eq!(
+            limiter.record(
+                &url,
+                StatusCode::TOO_MANY_REQUESTS,
+                &headers(&[("retry-after", "3600")])
+            ),
+            Some(Duration::from_secs(60))
+        );
+        assert!(limiter.delay(&other_path).unwrap() > Duration::from_secs(59));
+        assert_eq!(limiter.delay(&other_origin), None);
+        assert_eq!(limiter.limited_origins().len(), 1);
+
+        // A shorter delay does not shorten the schedule
+        limiter.set_delay(&url, Duration::from_secs(1));
+        assert!(limiter.delay(&url).unwrap() > Duration::from_secs(59));
+
+        limiter.clear(&url);
+        assert_eq!(limiter.delay(&url), None);
+    }
+
+    #[test]
+    fn test_default_delay() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let limiter = RateLimiter::new();
+        assert_eq!(
+            limiter.record(&url, StatusCode::SERVICE_UNAVAILABLE, &HeaderMap::new()),
+            None
+        );
+        let limiter = RateLimiter::new().with_default_delay(Duration::from_secs(2));
+        assert_eq!(
+            limiter.record(&url, StatusCode::SERVICE_UNAVAILABLE, &HeaderMap::new()),
+            Some(Duration::from_secs(2))
+        );
+    }
+}
@@ -1,15 +1,27 @@
 //! Request body for POST/PUT operations.
 //!
 //! Chromium mapping: net/base/upload_data_stream.h
+//!
+//! In-memory bodies can be sent any number of times. Streaming bodies are
+//! wrapped in a [`RewindableBody`] so that a 307/308 redirect or a retry on
+//! a fresh connection can send them again, mirroring
+//! `UploadDataStream::Reset()`.
 
+use crate::base::neterror::NetError;
 use bytes::Bytes;
+use futures::Stream;
 use http_body_util::Full;
+use std::fmt;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 
+/// Stream of body chunks for streaming uploads.
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, NetError>> + Send>>;
+
 /// Request body for HTTP methods that send data.
 ///
-/// Supports in-memory bytes. Streaming body support can be added later.
+/// Supports in-memory bytes and replayable streams.
 #[derive(Debug, Clone, Default)]
 pub enum RequestBody {
     /// No body (GET, HEAD, DELETE).
@@ -17,6 +29,162 @@ pub enum RequestBody {
     Empty,
     /// Body with raw bytes.
     Bytes(Bytes),
+    /// Streaming body that can be rewound for redirects and retries.
+    Rewindable(RewindableBody),
+}
+
+/// A streaming body that can be sent more than once.
+///
+/// Either the first `limit` bytes of a one-shot stream are kept for replay
+/// ([`buffered`](Self::buffered)), or a factory reopens the source for every
+/// attempt ([`from_factory`](Self::from_factory)). Clones share the source.
+#[derive(Clone)]
+pub struct RewindableBody {
+    source: RewindSource,
+}
+
+#[derive(Clone)]
+enum RewindSource {
+    Buffered(Arc<Mutex<ReplayBuffer>>),
+    Factory(Arc<dyn Fn() -> BodyStream + Send + Sync>),
+}
+
+/// Shared state of a buffered stream.
+///
+/// Every chunk pulled from `stream` is counted in `chunks_read` and kept in
+/// `replay` until more than `limit` bytes have been seen.
+struct ReplayBuffer {
+    stream: Option<BodyStream>,
+    replay: Vec<Bytes>,
+    chunks_read: usize,
+    buffered: usize,
+    limit: usize,
+    overflowed: bool,
+}
+
+impl RewindableBody {
+    /// Wrap a one-shot stream, keeping up to `limit` bytes for replay.
+    ///
+    /// Rewinding a body larger than `limit` fails with
+    /// [`NetError::UploadStreamRewindNotSupported`].
+    pub fn buffered<S>(stream: S, limit: usize) -> Self
+    where
+        S: Stream<Item = Result<Bytes, NetError>> + Send + 'static,
+    {
+        Self {
+            source: RewindSource::Buffered(Arc::new(Mutex::new(ReplayBuffer {
+                stream: Some(Box::pin(stream)),
+                replay: Vec::new(),
+                chunks_read: 0,
+                buffered: 0,
+                limit,
+                overflowed: false,
+            }))),
+        }
+    }
+
+    /// Create the body from a factory that opens a fresh stream for every
+    /// attempt. Rewinding never fails.
+    pub fn from_factory<F, S>(factory: F) -> Self
+    where
+        F: Fn() -> S + Send + Sync + 'static,
+        S: Stream<Item = Result<Bytes, NetError>> + Send + 'static,
+    {
+        Self {
+            source: RewindSource::Factory(Arc::new(move || Box::pin(factory()) as BodyStream)),
+        }
+    }
+
+    /// Whether the body can still be sent from the beginning.
+    pub fn can_rewind(&self) -> bool {
+        match &self.source {
+            RewindSource::Buffered(buffer) => !lock(buffer).overflowed,
+            RewindSource::Factory(_) => true,
+        }
+    }
+
+    /// Start a new read from the beginning of the body.
+    fn open(&self) -> Result<BodyWrapper, NetError> {
+        let inner = match &self.source {
+            RewindSource::Buffered(buffer) => {
+                if lock(buffer).overflowed {
+                    return Err(NetError::UploadStreamRewindNotSupported);
+                }
+                WrapperInner::Replay {
+                    buffer: buffer.clone(),
+                    position: 0,
+                }
+            }
+            RewindSource::Factory(factory) => WrapperInner::Stream(factory()),
+        };
+        Ok(BodyWrapper { inner })
+    }
+}
+
+impl fmt::Debug for RewindableBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match &self.source {
+            RewindSource::Buffered(_) => "buffered",
+            RewindSource::Factory(_) => "factory",
+        };
+        f.debug_struct("RewindableBody")
+            .field("kind", &kind)
+            .field("can_rewind", &self.can_rewind())
+            .finish()
+    }
+}
+
+fn lock(buffer: &Mutex<ReplayBuffer>) -> std::sync::MutexGuard<'_, ReplayBuffer> {
+    buffer.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl ReplayBuffer {
+    /// Chunk number `position` of the body, reading from the stream when the
+    /// reader has caught up with it.
+    fn poll_chunk(
+        &mut self,
+        position: usize,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, NetError>>> {
+        if position < self.chunks_read {
+            // Chunks before an overflow are gone
+            return Poll::Ready(Some(
+                self.replay
+                    .get(position)
+                    .cloned()
+                    .ok_or(NetError::UploadStreamRewindNotSupported),
+            ));
+        }
+
+        let Some(stream) = self.stream.as_mut() else {
+            return Poll::Ready(None);
+        };
+        match stream.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.chunks_read += 1;
+                self.buffered += chunk.len();
+                if self.buffered > self.limit {
+                    self.overflowed = true;
+                    self.replay = Vec::new();
+                } else if !self.overflowed {
+                    self.replay.push(chunk.clone());
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => {
+                self.stream = None;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl From<()> for RequestBody {
+    fn from(_: ()) -> Self {
+        RequestBody::Empty
+    }
 }
 
 impl From<String> for RequestBody {
@@ -49,72 +217,146 @@ impl From<&[u8]> for RequestBody {
     }
 }
 
+impl<const N: usize> From<&[u8; N]> for RequestBody {
+    fn from(b: &[u8; N]) -> Self {
+        RequestBody::Bytes(Bytes::copy_from_slice(b))
+    }
+}
+
+impl From<RewindableBody> for RequestBody {
+    fn from(body: RewindableBody) -> Self {
+        RequestBody::Rewindable(body)
+    }
+}
+
 impl RequestBody {
+    /// Create a streaming body that can be replayed, see [`RewindableBody`].
+    pub fn rewindable(body: RewindableBody) -> Self {
+        RequestBody::Rewindable(body)
+    }
+
     /// Check if the body is empty.
     pub fn is_empty(&self) -> bool {
         matches!(self, RequestBody::Empty)
     }
 
     /// Get the length of the body in bytes.
+    ///
+    /// Streaming bodies have no known length and report 0.
     pub fn len(&self) -> usize {
         match self {
-            RequestBody::Empty => 0,
+            RequestBody::Empty | RequestBody::Rewindable(_) => 0,
             RequestBody::Bytes(b) => b.len(),
         }
     }
 
     /// Take the inner bytes, consuming the body.
+    ///
+    /// Streaming bodies have no in-memory bytes and yield an empty buffer.
     pub fn take_bytes(&mut self) -> Bytes {
         match std::mem::take(self) {
-            RequestBody::Empty => Bytes::new(),
+            RequestBody::Empty | RequestBody::Rewindable(_) => Bytes::new(),
             RequestBody::Bytes(b) => b,
         }
     }
 
     /// Convert to a Full<Bytes> for hyper compatibility.
+    ///
+    /// Streaming bodies convert to an empty body; use [`open`](Self::open).
     pub fn into_full(self) -> Full<Bytes> {
         match self {
-            RequestBody::Empty => Full::new(Bytes::new()),
+            RequestBody::Empty | RequestBody::Rewindable(_) => Full::new(Bytes::new()),
             RequestBody::Bytes(b) => Full::new(b),
         }
     }
+
+    /// Start sending the body from the beginning.
+    ///
+    /// Called once per attempt. Fails with
+    /// [`NetError::UploadStreamRewindNotSupported`] when a buffered stream
+    /// has already sent more than it could keep.
+    pub fn open(&self) -> Result<BodyWrapper, NetError> {
+        match self {
+            RequestBody::Empty => Ok(BodyWrapper::bytes(None)),
+            RequestBody::Bytes(b) => Ok(BodyWrapper::bytes(Some(b.clone()))),
+            RequestBody::Rewindable(body) => body.open(),
+        }
+    }
 }
 
 /// Wrapper for RequestBody that implements http_body::Body trait.
 pub struct BodyWrapper {
-    inner: Option<Bytes>,
+    inner: WrapperInner,
+}
+
+enum WrapperInner {
+    Bytes(Option<Bytes>),
+    Stream(BodyStream),
+    Replay {
+        buffer: Arc<Mutex<ReplayBuffer>>,
+        position: usize,
+    },
+    Failed(Option<NetError>),
+}
+
+impl BodyWrapper {
+    fn bytes(data: Option<Bytes>) -> Self {
+        BodyWrapper {
+            inner: WrapperInner::Bytes(data),
+        }
+    }
 }
 
 impl From<RequestBody> for BodyWrapper {
     fn from(body: RequestBody) -> Self {
-        match body {
-            RequestBody::Empty => BodyWrapper { inner: None },
-            RequestBody::Bytes(b) => BodyWrapper { inner: Some(b) },
-        }
+        body.open().unwrap_or_else(|e| BodyWrapper {
+            inner: WrapperInner::Failed(Some(e)),
+        })
     }
 }
 
 impl http_body::Body for BodyWrapper {
     type Data = Bytes;
-    type Error = std::convert::Infallible;
+    type Error = NetError;
 
     fn poll_frame(
         mut self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
     ) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
-        match self.inner.take() {
-            Some(data) if !data.is_empty() => Poll::Ready(Some(Ok(http_body::Frame::data(data)))),
-            _ => Poll::Ready(None),
-        }
+        let chunk = match &mut self.inner {
+            WrapperInner::Bytes(data) => match data.take() {
+                Some(data) if !data.is_empty() => Poll::Ready(Some(Ok(data))),
+                _ => Poll::Ready(None),
+            },
+            WrapperInner::Stream(stream) => stream.as_mut().poll_next(cx),
+            WrapperInner::Replay { buffer, position } => {
+                let chunk = lock(buffer).poll_chunk(*position, cx);
+                if let Poll::Ready(Some(Ok(_))) = chunk {
+                    *position += 1;
+                }
+                chunk
+            }
+            WrapperInner::Failed(e) => Poll::Ready(e.take().map(Err)),
+        };
+        chunk.map(|c| c.map(|r| r.map(http_body::Frame::data)))
     }
 
     fn is_end_stream(&self) -> bool {
-        self.inner.as_ref().is_none_or(|b| b.is_empty())
+        match &self.inner {
+            WrapperInner::Bytes(data) => data.as_ref().is_none_or(|b| b.is_empty()),
+            WrapperInner::Failed(e) => e.is_none(),
+            WrapperInner::Stream(_) | WrapperInner::Replay { .. } => false,
+        }
     }
 
     fn size_hint(&self) -> http_body::SizeHint {
-        let size = self.inner.as_ref().map_or(0, |b| b.len() as u64);
-        http_body::SizeHint::with_exact(size)
+        match &self.inner {
+            WrapperInner::Bytes(data) => {
+                let size = data.as_ref().map_or(0, |b| b.len() as u64);
+                http_body::SizeHint::with_exact(size)
+            }
+            _ => http_body::SizeHint::default(),
+        }
     }
 }
 
@@ -191,4 +433,72 @@ mod tests {
         let empty_wrapper: BodyWrapper = RequestBody::Empty.into();
         assert_eq!(empty_wrapper.size_hint().exact(), Some(0));
     }
+
+    fn chunks(parts: &[&'static str]) -> impl Stream<Item = Result<Bytes, NetError>> {
+        let parts: Vec<_> = parts
+            .iter()
+            .map(|p| Ok(Bytes::from_static(p.as_bytes())))
+            .collect();
+        futures::stream::iter(parts)
+    }
+
+    async fn read_all(body: BodyWrapper) -> Result<Bytes, NetError> {
+        use http_body_util::BodyExt;
+        Ok(body.collect().await?.to_bytes())
+    }
+
+    #[tokio::test]
+    async fn test_buffered_body_replays_within_limit() {
+        let body = RequestBody::rewindable(RewindableBody::buffered(chunks(&["ab", "cd"]), 16));
+
+        assert_eq!(read_all(body.open().unwrap()).await.unwrap(), "abcd");
+        assert_eq!(read_all(body.open().unwrap()).await.unwrap(), "abcd");
+    }
+
+    #[tokio::test]
+    async fn test_buffered_body_resumes_partial_read() {
+        use http_body_util::BodyExt;
+
+        let body = RequestBody::rewindable(RewindableBody::buffered(chunks(&["ab", "cd"]), 16));
+
+        // First attempt stops after one chunk
+        let mut first = body.open().unwrap();
+        let frame = first.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), "ab");
+        drop(first);
+
+        assert_eq!(read_all(body.open().unwrap()).await.unwrap(), "abcd");
+    }
+
+    #[tokio::test]
+    async fn test_buffered_body_over_limit_cannot_rewind() {
+        let rewindable = RewindableBody::buffered(chunks(&["abc", "def"]), 4);
+        let body = RequestBody::rewindable(rewindable.clone());
+
+        assert_eq!(read_all(body.open().unwrap()).await.unwrap(), "abcdef");
+        assert!(!rewindable.can_rewind());
+        assert!(matches!(
+            body.open(),
+            Err(NetError::UploadStreamRewindNotSupported)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_factory_body_reopens() {
+        let body: RequestBody = RewindableBody::from_factory(|| chunks(&["x", "y"])).into();
+
+        for _ in 0..3 {
+            assert_eq!(read_all(body.open().unwrap()).await.unwrap(), "xy");
+        }
+    }
+
+    #[test]
+    fn test_streaming_body_size_unknown() {
+        use http_body::Body;
+
+        let body = RequestBody::rewindable(RewindableBody::from_factory(|| chunks(&[])));
+        let wrapper = body.open().unwrap();
+        assert_eq!(wrapper.size_hint().exact(), None);
+        assert!(!body.is_empty());
+    }
 }
@@ -0,0 +1,143 @@
+//! What a request would send, resolved without sending it.
+//!
+//! Chromium mapping: `HttpRequestInfo` and the `HttpRequestHeaders`
+//! `HttpNetworkTransaction::BuildRequestHeaders` assembles
+//!
+//! [`RequestBuilder::build_parts`](crate::RequestBuilder::build_parts)
+//! layers the headers exactly as sending would: the emulation profile,
+//! client defaults, the request's own headers and removals, rules edits,
+//! then `Host`, `Cookie` from the cookie store, `Authorization` from the
+//! auth cache and the `priority` header. The URL is the one the first
+//! request goes to, after HSTS upgrades and rules redirects. The same
+//! builder gives the same parts, so they can be signed, logged or diffed:
+//!
+//! ```text
+//! GET /api?q=1 HTTP/1.1
+//! user-agent: Mozilla/5.0 ...
+//! accept: text/html
+//! host: example.com
+//! cookie: session=...
+//! ```
+//!
+//! Headers are in the order they are sent over HTTP/1.1; over HTTP/2
+//! `host` becomes `:authority`. Framing headers the connection adds,
+//! `Content-Length` and `Transfer-Encoding`, are not included; the body
+//! descriptor tells them apart.
+
+use crate::http::requestbody::RequestBody;
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::Method;
+use std::fmt;
+use url::Url;
+
+/// The body a request would send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyDescriptor {
+    /// No body.
+    Empty,
+    /// A body of known bytes.
+    Bytes(Bytes),
+    /// A streaming [`RewindableBody`](crate::http::RewindableBody), whose
+    /// bytes are only known once it is sent.
+    Stream,
+}
+
+impl From<&RequestBody> for BodyDescriptor {
+    fn from(body: &RequestBody) -> Self {
+        match body {
+            RequestBody::Empty => Self::Empty,
+            RequestBody::Bytes(bytes) if bytes.is_empty() => Self::Empty,
+            RequestBody::Bytes(bytes) => Self::Bytes(bytes.clone()),
+            RequestBody::Rewindable(_) => Self::Stream,
+        }
+    }
+}
+
+/// A request as it would be sent, from
+/// [`RequestBuilder::build_parts`](crate::RequestBuilder::build_parts).
+///
+/// The headers hold cookies and credentials as sent, so take care where
+/// the parts are logged.
+#[derive(Debug, Clone)]
+pub struct RequestParts {
+    pub method: Method,
+    /// URL of the first request, after HSTS upgrades and rules redirects.
+    pub url: Url,
+    /// Headers in the order they are sent over HTTP/1.1.
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub body: BodyDescriptor,
+}
+
+impl RequestParts {
+    pub(crate) fn new(method: Method, url: Url, headers: HeaderMap, body: &RequestBody) -> Self {
+        Self {
+            method,
+            url,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            body: body.into(),
+        }
+    }
+
+    /// The first value of the header `name`, in any case.
+    pub fn header(&self, name: &str) -> Option<&HeaderValue> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.as_str().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+/// The HTTP/1.1 request head, ending with the empty line.
+impl fmt::Display for RequestParts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url.path())?;
+        if let Some(query) = self.url.query() {
+            write!(f, "?{query}")?;
+        }
+        write!(f, " HTTP/1.1\r\n")?;
+        for (name, value) in &self.headers {
+            write!(
+                f,
+                "{name}: {}\r\n",
+                String::from_utf8_lossy(value.as_bytes())
+            )?;
+        }
+        write!(f, "\r\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_head() {
+        let mut headers = HeaderMap::new();
+        headers.insert("user-agent", HeaderValue::from_static("Agent/1.0"));
+        headers.insert("host", HeaderValue::from_static("example.com"));
+        headers.insert("cookie", HeaderValue::from_static("a=1; b=2"));
+        let body = RequestBody::Bytes(Bytes::from_static(b"{}"));
+        let parts = RequestParts::new(
+            Method::POST,
+            Url::parse("https://example.com/api?q=1#top").unwrap(),
+            headers,
+            &body,
+        );
+
+        assert_eq!(
+            parts.to_string(),
+            "POST /api?q=1 HTTP/1.1\r\nuser-agent: Agent/1.0\r\nhost: example.com\r\n\
+             cookie: a=1; b=2\r\n\r\n"
+        );
+        assert_eq!(parts.header("Cookie").unwrap(), "a=1; b=2");
+        assert_eq!(parts.body, BodyDescriptor::Bytes(Bytes::from_static(b"{}")));
+        assert_eq!(
+            BodyDescriptor::from(&RequestBody::Bytes(Bytes::new())),
+            BodyDescriptor::Empty
+        );
+    }
+}
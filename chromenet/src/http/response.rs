@@ -1,17 +1,52 @@
 //! HTTP Response with body access.
 
+use crate::base::neterror::NetError;
+use crate::base::requestid::RequestId;
+use crate::emulation::EmulationProfile;
+use crate::http::conditional::{EntityTag, Validators};
+use crate::http::contentdecoder::ContentDecoders;
+use crate::http::identity::{IdentityReport, SentIdentity};
+use crate::http::jsonlines::JsonLines;
+use crate::http::multipart::PartStream;
+use crate::http::responsebody::{BodyStream, PartialBody};
 use crate::http::streamfactory::StreamBody;
+use crate::http::typedheaders::{self, ContentType, RetryAfter};
 use crate::http::ResponseBody;
-use http::{HeaderMap, StatusCode, Version};
+use crate::socket::nextproto::NextProto;
+use crate::socket::tls::SslInfo;
+use crate::socket::wire::RequestBytes;
+use crate::tls::certverify::CertVerifyResult;
+use crate::urlrequest::redirect::RedirectHop;
+use crate::urlrequest::robots::RobotsVerdict;
+use futures::StreamExt;
+use http::{Extensions, HeaderMap, StatusCode, Version};
 use hyper::body::Incoming;
+use std::fmt;
+use std::sync::Arc;
+use url::Url;
 
 /// HTTP Response with accessible body.
 /// This is the user-facing response type that owns the body.
 pub struct HttpResponse {
     status: StatusCode,
     version: Version,
+    /// Reason phrase of an HTTP/1.x status line, as received
+    reason_phrase: Option<bytes::Bytes>,
     headers: HeaderMap,
+    negotiated_protocol: NextProto,
+    ssl_info: Option<Arc<SslInfo>>,
+    cert_verify_result: Option<Arc<CertVerifyResult>>,
+    wire_bytes: Option<RequestBytes>,
+    url: Option<Url>,
+    robots: Option<RobotsVerdict>,
+    request_id: Option<RequestId>,
+    redirects: Vec<RedirectHop>,
+    /// Headers, proxy and profile the request presented
+    identity: SentIdentity,
+    extensions: Extensions,
     body: Option<ResponseBody>,
+    body_limit: usize,
+    deadline: Option<std::time::Instant>,
 }
 
 impl HttpResponse {
@@ -21,8 +56,45 @@ impl HttpResponse {
         Self {
             status: parts.status,
             version: parts.version,
+            reason_phrase: reason_phrase(&parts),
             headers: parts.headers,
+            negotiated_protocol: NextProto::Unknown,
+            ssl_info: None,
+            cert_verify_result: None,
+            wire_bytes: None,
+            url: None,
+            robots: None,
+            request_id: None,
+            redirects: Vec::new(),
+            identity: SentIdentity::default(),
+            extensions: Extensions::new(),
             body: Some(ResponseBody::new(body)),
+            body_limit: usize::MAX,
+            deadline: None,
+        }
+    }
+
+    /// Create from a cache entry, see
+    /// [`CacheEntry::into_response`](crate::http::CacheEntry::into_response).
+    pub(crate) fn from_cache(status: StatusCode, headers: HeaderMap, body: bytes::Bytes) -> Self {
+        Self {
+            status,
+            version: Version::default(),
+            reason_phrase: None,
+            headers,
+            negotiated_protocol: NextProto::Unknown,
+            ssl_info: None,
+            cert_verify_result: None,
+            wire_bytes: None,
+            url: None,
+            robots: None,
+            request_id: None,
+            redirects: Vec::new(),
+            identity: SentIdentity::default(),
+            extensions: Extensions::new(),
+            body: Some(ResponseBody::Buffered(body)),
+            body_limit: usize::MAX,
+            deadline: None,
         }
     }
 
@@ -32,8 +104,29 @@ impl HttpResponse {
         Self {
             status: parts.status,
             version: parts.version,
+            reason_phrase: reason_phrase(&parts),
+            negotiated_protocol: parts
+                .extensions
+                .get::<NextProto>()
+                .copied()
+                .unwrap_or_default(),
+            ssl_info: parts.extensions.get::<Arc<SslInfo>>().cloned(),
+            cert_verify_result: None,
+            wire_bytes: parts.extensions.get::<RequestBytes>().cloned(),
+            url: None,
+            robots: None,
+            request_id: None,
+            redirects: Vec::new(),
+            identity: parts
+                .extensions
+                .get::<SentIdentity>()
+                .cloned()
+                .unwrap_or_default(),
+            extensions: Extensions::new(),
             headers: parts.headers,
             body: Some(ResponseBody::from_stream(stream_body)),
+            body_limit: usize::MAX,
+            deadline: None,
         }
     }
 
@@ -47,43 +140,736 @@ impl HttpResponse {
         self.version
     }
 
+    /// Reason phrase of the HTTP/1.x status line as the server sent it,
+    /// e.g. `Not Found` in `HTTP/1.1 404 Not Found`.
+    ///
+    /// May be empty, nonstandard or not UTF-8 (obsolete `obs-text`).
+    /// `None` for HTTP/2 and cached responses, which have no status line.
+    pub fn reason_phrase(&self) -> Option<&[u8]> {
+        self.reason_phrase.as_deref()
+    }
+
+    /// The HTTP/1.x status line, without its CRLF, e.g.
+    /// `HTTP/1.0 200 Okay`, with the reason phrase decoded lossily.
+    ///
+    /// `None` when there was no status line, see [`Self::reason_phrase`].
+    pub fn status_line(&self) -> Option<String> {
+        let reason = String::from_utf8_lossy(self.reason_phrase()?);
+        Some(format!(
+            "{:?} {} {}",
+            self.version,
+            self.status.as_u16(),
+            reason
+        ))
+    }
+
+    /// Get the protocol negotiated via ALPN for the connection.
+    ///
+    /// [`NextProto::Unknown`] for cleartext connections.
+    pub fn negotiated_protocol(&self) -> NextProto {
+        self.negotiated_protocol
+    }
+
+    /// Get the TLS details of the connection.
+    ///
+    /// `None` for cleartext connections.
+    pub fn ssl_info(&self) -> Option<&SslInfo> {
+        self.ssl_info.as_deref()
+    }
+
+    /// Bytes the request took on the wire, `None` unless the client
+    /// counts them, see [`crate::socket::wire`].
+    ///
+    /// The count keeps growing while the body is read, so keep the handle
+    /// to read it once the body is consumed.
+    pub fn wire_bytes(&self) -> Option<RequestBytes> {
+        self.wire_bytes.clone()
+    }
+
     /// Get a reference to the headers.
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
+    /// What checking the certificates of the connection found, `None` for
+    /// cleartext and cached responses. See [`crate::tls::certverify`].
+    pub fn cert_verify_result(&self) -> Option<&CertVerifyResult> {
+        self.cert_verify_result.as_deref()
+    }
+
+    /// Record the certificate checks of the connection.
+    pub(crate) fn with_cert_verify_result(mut self, result: Arc<CertVerifyResult>) -> Self {
+        self.cert_verify_result = Some(result);
+        self
+    }
+
+    /// The URL the response came from, after redirects.
+    ///
+    /// `None` for responses not made by a request, e.g. built from a cache
+    /// entry.
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    /// Record the URL the response came from.
+    pub(crate) fn with_url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// What robots.txt says about the request, `None` unless the client
+    /// checks robots.txt, see [`crate::urlrequest::robots`].
+    ///
+    /// Only [`RobotsMode::Annotate`] lets disallowed requests through.
+    ///
+    /// [`RobotsMode::Annotate`]: crate::urlrequest::robots::RobotsMode::Annotate
+    pub fn robots(&self) -> Option<RobotsVerdict> {
+        self.robots
+    }
+
+    /// Record the robots.txt verdict for the request.
+    pub(crate) fn with_robots(mut self, verdict: RobotsVerdict) -> Self {
+        self.robots = Some(verdict);
+        self
+    }
+
+    /// Identifier of the request this answers, as logged in the
+    /// `request.id` field of its tracing events; `None` for responses not
+    /// made by a [`Client`](crate::Client).
+    pub fn request_id(&self) -> Option<RequestId> {
+        self.request_id
+    }
+
+    /// Record the identifier of the request.
+    pub(crate) fn with_request_id(mut self, id: RequestId) -> Self {
+        self.request_id = Some(id);
+        self
+    }
+
+    /// Redirects followed to get this response, first hop first, and what
+    /// became of their bodies, see [`crate::urlrequest::redirect`].
+    pub fn redirects(&self) -> &[RedirectHop] {
+        &self.redirects
+    }
+
+    /// Record the redirects followed.
+    pub(crate) fn with_redirects(mut self, redirects: Vec<RedirectHop>) -> Self {
+        self.redirects = redirects;
+        self
+    }
+
+    /// The identity the request presented: emulation profile, TLS and
+    /// HTTP/2 fingerprints, User-Agent and client hints, proxy and TLS
+    /// session reuse. See [`crate::http::identity`].
+    ///
+    /// ```no_run
+    /// # async fn run(client: chromenet::Client) -> Result<(), chromenet::base::neterror::NetError> {
+    /// let response = client.get("https://example.com/").send().await?;
+    /// let report = response.identity_report();
+    /// println!("{report}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn identity_report(&self) -> IdentityReport {
+        IdentityReport::new(&self.identity, self.ssl_info())
+    }
+
+    /// Record what the request sent.
+    pub(crate) fn with_identity(mut self, identity: SentIdentity) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    /// Record the emulation profile of the request.
+    pub(crate) fn with_emulation_profile(mut self, profile: Option<EmulationProfile>) -> Self {
+        self.identity.profile = profile;
+        self
+    }
+
+    /// Values attached to the request with
+    /// [`RequestBuilder::extension`](crate::client::RequestBuilder::extension),
+    /// and any added to the response since.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Attach values to the response, e.g. for code further up a
+    /// middleware stack.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Carry over the request's extensions.
+    pub(crate) fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Turn a 4xx or 5xx response into [`NetError::HttpStatus`], dropping
+    /// its body; other responses pass through.
+    ///
+    /// ```no_run
+    /// # async fn run(client: chromenet::Client) -> Result<(), chromenet::base::neterror::NetError> {
+    /// let body = client
+    ///     .get("https://example.com/api")
+    ///     .send()
+    ///     .await?
+    ///     .error_for_status()?
+    ///     .text()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn error_for_status(self) -> Result<Self, NetError> {
+        match self.status_error(None) {
+            Some(error) => Err(error),
+            None => Ok(self),
+        }
+    }
+
+    /// Like [`error_for_status`](Self::error_for_status), keeping the
+    /// response.
+    pub fn error_for_status_ref(&self) -> Result<&Self, NetError> {
+        match self.status_error(None) {
+            Some(error) => Err(error),
+            None => Ok(self),
+        }
+    }
+
+    /// Like [`error_for_status`](Self::error_for_status), keeping up to
+    /// `limit` bytes of the body in the error, e.g. the message of an API
+    /// error. The rest of the body is not read.
+    pub async fn error_for_status_with_body(mut self, limit: usize) -> Result<Self, NetError> {
+        if !is_error_status(self.status) {
+            return Ok(self);
+        }
+        let body = match self.body.take() {
+            Some(body) => {
+                let stream = body.into_stream();
+                let stream = match self.deadline {
+                    Some(deadline) => stream.with_deadline(deadline),
+                    None => stream,
+                };
+                stream.read_prefix(limit).await
+            }
+            None => bytes::Bytes::new(),
+        };
+        Err(self
+            .status_error(Some(body))
+            .expect("error status checked above"))
+    }
+
+    fn status_error(&self, body: Option<bytes::Bytes>) -> Option<NetError> {
+        is_error_status(self.status).then(|| {
+            NetError::HttpStatus(Box::new(StatusError {
+                status: self.status,
+                url: self.url.clone(),
+                body,
+            }))
+        })
+    }
+
+    /// The body length `Content-Length` declares, `None` if it is absent,
+    /// not a number, or repeated with differing values.
+    pub fn content_length(&self) -> Option<u64> {
+        typedheaders::content_length(&self.headers)
+    }
+
+    /// The content codings still applied to the body, lowercase and in the
+    /// order they were applied, e.g. `["gzip"]`. The client does not undo
+    /// them, except `dcb` and `dcz` with
+    /// [shared dictionaries](crate::http::shareddictionary), and all of
+    /// them with [content decoders](crate::http::contentdecoder).
+    pub fn content_encodings(&self) -> Vec<String> {
+        typedheaders::content_encodings(&self.headers)
+    }
+
+    /// The `Set-Cookie` lines, each as received, whatever the protocol.
+    /// They are never joined, as `Expires` dates contain commas.
+    pub fn set_cookies(&self) -> Vec<String> {
+        typedheaders::set_cookies(&self.headers)
+    }
+
+    /// The `Content-Type`, from the last value that parses.
+    pub fn content_type(&self) -> Option<ContentType> {
+        typedheaders::content_type(&self.headers)
+    }
+
+    /// The `ETag` of the response, if any and valid. Repeated `ETag`
+    /// headers with different tags count as none.
+    pub fn etag(&self) -> Option<EntityTag> {
+        self.validators().etag
+    }
+
+    /// The `Last-Modified` time of the response, if any and valid.
+    pub fn last_modified(&self) -> Option<std::time::SystemTime> {
+        self.validators().last_modified
+    }
+
+    /// When to retry, from `Retry-After` as seconds or a date. Repeated
+    /// headers with different values count as none.
+    pub fn retry_after(&self) -> Option<RetryAfter> {
+        typedheaders::retry_after(&self.headers)
+    }
+
+    /// The validators to make a later request for the same resource
+    /// conditional, see [`RequestBuilder::conditional`](crate::RequestBuilder::conditional).
+    pub fn validators(&self) -> Validators {
+        Validators::from_headers(&self.headers)
+    }
+
+    /// MIME type of the `Content-Type` header, lowercase and without
+    /// parameters, e.g. `text/html`.
+    pub fn mime_type(&self) -> Option<String> {
+        Some(self.content_type()?.mime_type().to_string())
+    }
+
+    /// `charset` parameter of the `Content-Type` header, lowercase.
+    pub fn charset(&self) -> Option<String> {
+        self.content_type()?.charset()
+    }
+
+    /// Cap the body size read by [`bytes`](Self::bytes),
+    /// [`text`](Self::text), [`json`](Self::json) and
+    /// [`copy_to`](Self::copy_to).
+    ///
+    /// They fail with [`NetError::ResponseBodyTooBig`] before reading the
+    /// body when `Content-Length` exceeds `limit`, otherwise as soon as more
+    /// than `limit` bytes arrive.
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
+
+    /// Abort reading the body at `deadline`, failing the consuming methods
+    /// with [`NetError::TimedOut`].
+    ///
+    /// An HTTP/2 stream is reset with `CANCEL` and an HTTP/1.1 connection
+    /// closed, see [`ResponseBody::cancel`]. Responses of a client with a
+    /// [`timeout`](crate::ClientBuilder::timeout) carry its deadline.
+    pub fn with_deadline(mut self, deadline: std::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// The deadline for reading the body, if any.
+    pub fn deadline(&self) -> Option<std::time::Instant> {
+        self.deadline
+    }
+
     /// Take the response body for consumption.
     /// Can only be called once - subsequent calls return None.
+    ///
+    /// The body limit and deadline do not apply to a body taken this way;
+    /// use [`BodyStream::with_deadline`](crate::http::responsebody::BodyStream::with_deadline)
+    /// for the latter.
     pub fn take_body(&mut self) -> Option<ResponseBody> {
         self.body.take()
     }
 
-    /// Convenience method to consume body as bytes.
-    pub async fn bytes(mut self) -> Result<bytes::Bytes, crate::base::neterror::NetError> {
-        self.body
-            .take()
-            .ok_or(crate::base::neterror::NetError::HttpBodyError)?
-            .bytes()
-            .await
+    /// Read the whole body so the response can be handed to several callers.
+    pub(crate) async fn into_buffered(mut self) -> Result<BufferedResponse, NetError> {
+        let body = match self.body.take() {
+            Some(body) => body.bytes().await?,
+            None => bytes::Bytes::new(),
+        };
+        Ok(BufferedResponse {
+            status: self.status,
+            version: self.version,
+            reason_phrase: self.reason_phrase,
+            headers: self.headers,
+            negotiated_protocol: self.negotiated_protocol,
+            ssl_info: self.ssl_info,
+            cert_verify_result: self.cert_verify_result,
+            url: self.url,
+            robots: self.robots,
+            request_id: self.request_id,
+            redirects: self.redirects,
+            identity: self.identity,
+            extensions: self.extensions,
+            body,
+        })
+    }
+
+    /// Read the whole body into memory, keeping the response.
+    ///
+    /// Unlike [`into_buffered`](Self::into_buffered), the body limit and
+    /// deadline apply. The connection is released once the body is read.
+    pub(crate) async fn buffer(mut self) -> Result<Self, NetError> {
+        let limit = self.body_limit;
+        let body = self.take_limited_body()?.collect_limited(limit).await?;
+        self.body = Some(ResponseBody::Buffered(body));
+        Ok(self)
+    }
+
+    /// Read the whole body into memory if it is at most `limit` bytes.
+    ///
+    /// Returns whether it was. A body declared or found to be longer is
+    /// left for the caller to read, with the bytes already received put
+    /// back in front of the rest; the body limit does not apply.
+    pub(crate) async fn buffer_within(mut self, limit: usize) -> Result<(Self, bool), NetError> {
+        if self.content_length().is_some_and(|len| len > limit as u64) {
+            return Ok((self, false));
+        }
+        let body = self.body.take().ok_or(NetError::HttpBodyError)?;
+        let mut stream = body.into_stream();
+        if let Some(deadline) = self.deadline {
+            stream = stream.with_deadline(deadline);
+        }
+        let mut data = bytes::BytesMut::new();
+        while let Some(chunk) = stream.next().await {
+            data.extend_from_slice(&chunk?);
+            if data.len() > limit {
+                let rest = Box::new(stream.into_inner());
+                self.body = Some(ResponseBody::Prefixed(data.freeze(), rest));
+                return Ok((self, false));
+            }
+        }
+        self.body = Some(ResponseBody::Buffered(data.freeze()));
+        Ok((self, true))
+    }
+
+    /// The body, if it was read into memory with [`buffer`](Self::buffer)
+    /// or came from the cache.
+    pub(crate) fn buffered_body(&self) -> Option<&bytes::Bytes> {
+        match &self.body {
+            Some(ResponseBody::Buffered(body)) => Some(body),
+            _ => None,
+        }
+    }
+
+    /// Replace the body with `body`, decoded from the response's content
+    /// coding: `Content-Encoding` goes and `Content-Length` is that of the
+    /// new body.
+    pub(crate) fn with_decoded_body(mut self, body: bytes::Bytes) -> Self {
+        self.headers.remove(http::header::CONTENT_ENCODING);
+        self.headers
+            .insert(http::header::CONTENT_LENGTH, body.len().into());
+        self.body = Some(ResponseBody::Buffered(body));
+        self
+    }
+
+    /// The body for one of the consuming methods, checked against the
+    /// declared length and bound to the deadline.
+    ///
+    /// The consuming methods take `self`, so the body can be read only
+    /// once. After [`take_body`](Self::take_body) they fail with
+    /// [`NetError::HttpBodyError`].
+    fn take_limited_body(&mut self) -> Result<BodyStream, NetError> {
+        let body = self.body.take().ok_or(NetError::HttpBodyError)?;
+        let declared = self.content_length();
+        if declared.is_some_and(|len| len > self.body_limit as u64) {
+            body.cancel();
+            return Err(NetError::ResponseBodyTooBig {
+                limit: self.body_limit,
+            });
+        }
+        let stream = body.into_stream();
+        Ok(match self.deadline {
+            Some(deadline) => stream.with_deadline(deadline),
+            None => stream,
+        })
     }
 
-    /// Convenience method to consume body as text.
-    pub async fn text(mut self) -> Result<String, crate::base::neterror::NetError> {
-        self.body
+    /// Read the whole body into memory.
+    pub async fn bytes(mut self) -> Result<bytes::Bytes, NetError> {
+        let limit = self.body_limit;
+        self.take_limited_body()?.collect_limited(limit).await
+    }
+
+    /// Read the whole body into memory, keeping what arrived if the
+    /// transfer fails.
+    ///
+    /// Like curl's `--fail-with-body`, this lets callers salvage a body cut
+    /// short by a connection that died, a deadline or the body limit:
+    ///
+    /// ```no_run
+    /// # async fn run(resp: chromenet::http::HttpResponse) {
+    /// let body = match resp.bytes_or_partial().await {
+    ///     Ok(body) => body,
+    ///     Err(partial) => {
+    ///         eprintln!("body incomplete: {}", partial.error);
+    ///         partial.bytes
+    ///     }
+    /// };
+    /// # }
+    /// ```
+    pub async fn bytes_or_partial(mut self) -> Result<bytes::Bytes, PartialBody> {
+        let limit = self.body_limit;
+        let stream = self.take_limited_body().map_err(|error| PartialBody {
+            bytes: bytes::Bytes::new(),
+            error,
+        })?;
+        stream.collect_partial(limit).await
+    }
+
+    /// Read the whole body as text, decoded with the `Content-Type`
+    /// charset.
+    ///
+    /// A byte order mark overrides the charset, as in browsers. UTF-8 (the
+    /// default), UTF-16 and windows-1252, which also serves `iso-8859-1`
+    /// and `us-ascii`, are decoded; other charsets are read as UTF-8.
+    /// Invalid UTF-8 fails with [`NetError::InvalidUtf8`].
+    pub async fn text(self) -> Result<String, NetError> {
+        let charset = self.charset();
+        let bytes = self.bytes().await?;
+        decode_text(&bytes, charset.as_deref())
+    }
+
+    /// Read the whole body as JSON, deserializing to `T`.
+    pub async fn json<T: serde::de::DeserializeOwned>(self) -> Result<T, NetError> {
+        let bytes = self.bytes().await?;
+        serde_json::from_slice(&bytes).map_err(|_| NetError::JsonParseError)
+    }
+
+    /// Read a `multipart/*` body part by part, e.g. the frames of a
+    /// `multipart/x-mixed-replace` camera feed.
+    ///
+    /// Parts are yielded as each one completes. The body limit applies to
+    /// each part rather than the whole body, and the deadline to the
+    /// whole. Fails with [`NetError::InvalidResponse`] unless the
+    /// `Content-Type` is multipart with a `boundary`.
+    pub fn multipart(mut self) -> Result<PartStream<BodyStream>, NetError> {
+        let boundary = self
+            .content_type()
+            .filter(|ct| ct.mime_type().starts_with("multipart/"))
+            .and_then(|ct| ct.param("boundary").map(str::to_string))
+            .ok_or(NetError::InvalidResponse)?;
+        let body = self
+            .body
             .take()
-            .ok_or(crate::base::neterror::NetError::HttpBodyError)?
-            .text()
-            .await
+            .ok_or(NetError::HttpBodyError)?
+            .into_stream();
+        let body = match self.deadline {
+            Some(deadline) => body.with_deadline(deadline),
+            None => body,
+        };
+        Ok(PartStream::new(body, &boundary).with_part_limit(self.body_limit))
     }
 
-    /// Convenience method to consume body as JSON.
-    pub async fn json<T: serde::de::DeserializeOwned>(
-        mut self,
-    ) -> Result<T, crate::base::neterror::NetError> {
-        self.body
+    /// Read a newline-delimited JSON body (NDJSON, JSON Lines or JSON text
+    /// sequences) value by value, e.g. the events of a firehose endpoint.
+    ///
+    /// Values are yielded as each line completes. The body limit applies
+    /// to each line rather than the whole body, and the deadline to the
+    /// whole. A body still carrying a content coding, when the client has
+    /// no [content decoders](crate::ClientBuilder::content_decoders), is
+    /// read whole and decoded with the default ones first, as decoders
+    /// work on whole bodies. See [`crate::http::jsonlines`].
+    pub fn json_lines<T: serde::de::DeserializeOwned>(mut self) -> Result<JsonLines<T>, NetError> {
+        let encoded = !self.content_encodings().is_empty();
+        let body = self
+            .body
             .take()
-            .ok_or(crate::base::neterror::NetError::HttpBodyError)?
-            .json()
-            .await
+            .ok_or(NetError::HttpBodyError)?
+            .into_stream();
+        let body = match self.deadline {
+            Some(deadline) => body.with_deadline(deadline),
+            None => body,
+        };
+        let lines = if encoded {
+            let limit = self.body_limit;
+            let headers = self.headers;
+            JsonLines::new(futures::stream::once(async move {
+                let body = body.collect_limited(limit).await?;
+                ContentDecoders::default().decode(&headers, body)
+            }))
+        } else {
+            JsonLines::new(body)
+        };
+        Ok(lines.with_line_limit(self.body_limit))
+    }
+
+    /// Stream the body into `writer` without buffering it in memory, e.g.
+    /// to download into a file. Returns the number of bytes written.
+    pub async fn copy_to<W>(mut self, writer: &mut W) -> Result<u64, NetError>
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        let limit = self.body_limit;
+        self.take_limited_body()?.copy_limited(writer, limit).await
+    }
+}
+
+/// Reason phrase of an HTTP/1.x response: hyper only keeps phrases other
+/// than the canonical one.
+fn reason_phrase(parts: &http::response::Parts) -> Option<bytes::Bytes> {
+    if parts.version > Version::HTTP_11 {
+        return None;
+    }
+    let phrase = match parts.extensions.get::<hyper::ext::ReasonPhrase>() {
+        Some(phrase) => phrase.as_bytes(),
+        None => parts.status.canonical_reason().unwrap_or("").as_bytes(),
+    };
+    Some(bytes::Bytes::copy_from_slice(phrase))
+}
+
+fn is_error_status(status: StatusCode) -> bool {
+    status.is_client_error() || status.is_server_error()
+}
+
+/// A 4xx or 5xx response, from [`HttpResponse::error_for_status`] and its
+/// variants.
+#[derive(Debug, Clone)]
+pub struct StatusError {
+    status: StatusCode,
+    url: Option<Url>,
+    body: Option<bytes::Bytes>,
+}
+
+impl StatusError {
+    /// The response status.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The URL of the response, see [`HttpResponse::url`].
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    /// The start of the body, if captured with
+    /// [`HttpResponse::error_for_status_with_body`].
+    pub fn body(&self) -> Option<&bytes::Bytes> {
+        self.body.as_ref()
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.status.is_client_error() {
+            "client"
+        } else {
+            "server"
+        };
+        write!(f, "HTTP status {kind} error ({})", self.status)?;
+        if let Some(url) = &self.url {
+            write!(f, " for url ({url})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// Decode `bytes` per the WHATWG encoding rules for the charsets supported
+/// without an encoding library.
+fn decode_text(bytes: &[u8], charset: Option<&str>) -> Result<String, NetError> {
+    let utf8 = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).map_err(|_| NetError::InvalidUtf8);
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => return utf8(rest),
+        [0xFF, 0xFE, rest @ ..] => return Ok(decode_utf16(rest, u16::from_le_bytes)),
+        [0xFE, 0xFF, rest @ ..] => return Ok(decode_utf16(rest, u16::from_be_bytes)),
+        _ => {}
+    }
+    match charset.unwrap_or("utf-8") {
+        "utf-16" | "utf-16le" => Ok(decode_utf16(bytes, u16::from_le_bytes)),
+        "utf-16be" => Ok(decode_utf16(bytes, u16::from_be_bytes)),
+        "windows-1252" | "iso-8859-1" | "latin1" | "l1" | "us-ascii" | "ascii" | "cp1252" => {
+            Ok(bytes.iter().map(|&b| windows_1252(b)).collect())
+        }
+        _ => utf8(bytes),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let pairs = bytes.chunks_exact(2);
+    let truncated = !pairs.remainder().is_empty();
+    let units: Vec<u16> = pairs.map(|pair| unit([pair[0], pair[1]])).collect();
+    let mut text = String::from_utf16_lossy(&units);
+    if truncated {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text
+}
+
+/// windows-1252 differs from Latin-1 only in 0x80..=0x9F.
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}',
+        '\u{2021}', '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}',
+        '\u{8F}', '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}',
+        '\u{2014}', '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}',
+        '\u{178}',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+/// A response whose body has been read into memory, cheap to clone.
+#[derive(Debug, Clone)]
+pub(crate) struct BufferedResponse {
+    status: StatusCode,
+    version: Version,
+    reason_phrase: Option<bytes::Bytes>,
+    headers: HeaderMap,
+    negotiated_protocol: NextProto,
+    ssl_info: Option<Arc<SslInfo>>,
+    cert_verify_result: Option<Arc<CertVerifyResult>>,
+    url: Option<Url>,
+    robots: Option<RobotsVerdict>,
+    request_id: Option<RequestId>,
+    redirects: Vec<RedirectHop>,
+    identity: SentIdentity,
+    extensions: Extensions,
+    body: bytes::Bytes,
+}
+
+impl From<BufferedResponse> for HttpResponse {
+    fn from(resp: BufferedResponse) -> Self {
+        Self {
+            status: resp.status,
+            version: resp.version,
+            reason_phrase: resp.reason_phrase,
+            headers: resp.headers,
+            negotiated_protocol: resp.negotiated_protocol,
+            ssl_info: resp.ssl_info,
+            cert_verify_result: resp.cert_verify_result,
+            wire_bytes: None,
+            url: resp.url,
+            robots: resp.robots,
+            request_id: resp.request_id,
+            redirects: resp.redirects,
+            identity: resp.identity,
+            extensions: resp.extensions,
+            body: Some(ResponseBody::Buffered(resp.body)),
+            body_limit: usize::MAX,
+            deadline: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text(b"caf\xc3\xa9", None).unwrap(), "café");
+        assert_eq!(decode_text(b"caf\xe9", Some("iso-8859-1")).unwrap(), "café");
+        assert_eq!(decode_text(b"\x80 5", Some("windows-1252")).unwrap(), "€ 5");
+        assert!(matches!(
+            decode_text(b"caf\xe9", Some("utf-8")),
+            Err(NetError::InvalidUtf8)
+        ));
+        // Unknown charsets are read as UTF-8
+        assert_eq!(decode_text(b"ok", Some("x-unknown")).unwrap(), "ok");
+    }
+
+    #[test]
+    fn test_decode_text_bom_overrides_charset() {
+        assert_eq!(
+            decode_text(b"\xef\xbb\xbfhi", Some("iso-8859-1")).unwrap(),
+            "hi"
+        );
+        assert_eq!(decode_text(b"\xff\xfeh\x00i\x00", None).unwrap(), "hi");
+        assert_eq!(decode_text(b"\xfe\xff\x00h\x00i", None).unwrap(), "hi");
+        assert_eq!(decode_text(b"h\x00i\x00", Some("utf-16le")).unwrap(), "hi");
+        assert_eq!(
+            decode_text(b"\x00h\x00", Some("utf-16be")).unwrap(),
+            "h\u{FFFD}"
+        );
     }
 }
@@ -2,18 +2,28 @@
 //! Mirrors Chromium's HttpStream::ReadResponseBody.
 
 use crate::base::neterror::NetError;
-use crate::http::streamfactory::StreamBody;
-use bytes::Bytes;
-use http2::RecvStream;
+use crate::http::streamfactory::{map_h1_error, H2Body, StreamBody};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::StreamExt;
 use hyper::body::Incoming;
+use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 /// Response body wrapper for streaming.
 /// Supports both HTTP/1.1 (hyper Incoming) and HTTP/2 (http2 RecvStream).
+///
+/// Dropping an unfinished body aborts the transfer, see
+/// [`cancel`](Self::cancel).
 pub enum ResponseBody {
     H1(Incoming),
-    H2(RecvStream),
+    H2(H2Body),
+    /// Body already read into memory, e.g. shared between coalesced requests.
+    Buffered(Bytes),
+    /// The first bytes of a body, read ahead e.g. to see whether it fits a
+    /// limit, followed by the rest still being received.
+    Prefixed(Bytes, Box<ResponseBody>),
 }
 
 impl ResponseBody {
@@ -26,7 +36,30 @@ impl ResponseBody {
     pub fn from_stream(stream: StreamBody) -> Self {
         match stream {
             StreamBody::H1(incoming) => ResponseBody::H1(incoming),
-            StreamBody::H2(recv) => ResponseBody::H2(recv),
+            StreamBody::H2(body) => ResponseBody::H2(body),
+        }
+    }
+
+    /// Stop receiving the body.
+    ///
+    /// An HTTP/2 stream is reset with `CANCEL`, so the server stops sending
+    /// while the connection stays usable for other streams. An HTTP/1.1
+    /// connection is closed, since the unread rest of the body would
+    /// otherwise be read as the next response.
+    pub fn cancel(mut self) {
+        self.abort();
+    }
+
+    fn abort(&mut self) {
+        match self {
+            ResponseBody::H2(body) => body.reset(http2::Reason::CANCEL),
+            // hyper closes the connection when the body is dropped unread
+            ResponseBody::H1(_) => *self = ResponseBody::Buffered(Bytes::new()),
+            ResponseBody::Buffered(_) => {}
+            ResponseBody::Prefixed(prefix, rest) => {
+                *prefix = Bytes::new();
+                rest.abort();
+            }
         }
     }
 
@@ -35,25 +68,23 @@ impl ResponseBody {
     /// Note: This collects the entire body into memory.
     /// For large responses, use `stream()` instead.
     pub async fn bytes(self) -> Result<Bytes, NetError> {
-        match self {
-            ResponseBody::H1(incoming) => {
-                use http_body_util::BodyExt;
-                let collected = incoming
-                    .collect()
-                    .await
-                    .map_err(|_| NetError::HttpBodyError)?;
-                Ok(collected.to_bytes())
-            }
-            ResponseBody::H2(mut recv_stream) => {
-                use bytes::BufMut;
-                let mut data = bytes::BytesMut::new();
-                while let Some(chunk) = recv_stream.data().await {
-                    let chunk = chunk.map_err(|_| NetError::HttpBodyError)?;
-                    data.put(chunk);
-                }
-                Ok(data.freeze())
-            }
-        }
+        self.bytes_limited(usize::MAX).await
+    }
+
+    /// Read entire body as bytes, failing with
+    /// [`NetError::ResponseBodyTooBig`] as soon as more than `limit` bytes
+    /// arrive.
+    pub async fn bytes_limited(self, limit: usize) -> Result<Bytes, NetError> {
+        self.into_stream().collect_limited(limit).await
+    }
+
+    /// Write the body to `writer` as it arrives, without buffering it.
+    /// Returns the number of bytes written.
+    pub async fn copy_to<W>(self, writer: &mut W) -> Result<u64, NetError>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        self.into_stream().copy_limited(writer, usize::MAX).await
     }
 
     /// Read body as UTF-8 string.
@@ -82,7 +113,32 @@ impl ResponseBody {
     /// }
     /// ```
     pub fn into_stream(self) -> BodyStream {
-        BodyStream { inner: self }
+        BodyStream {
+            inner: self,
+            deadline: None,
+            timed_out: false,
+        }
+    }
+}
+
+/// A body transfer that failed, with the bytes received before the error.
+///
+/// Returned by [`HttpResponse::bytes_or_partial`](crate::http::HttpResponse::bytes_or_partial),
+/// e.g. when a flaky server closes the connection before sending all of
+/// `Content-Length` ([`NetError::ContentLengthMismatch`]) or the last chunk
+/// ([`NetError::IncompleteChunkedEncoding`]).
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{error} after {} body bytes", bytes.len())]
+pub struct PartialBody {
+    /// Bytes received before the error.
+    pub bytes: Bytes,
+    /// Why the transfer failed.
+    pub error: NetError,
+}
+
+impl From<PartialBody> for NetError {
+    fn from(partial: PartialBody) -> Self {
+        partial.error
     }
 }
 
@@ -91,41 +147,186 @@ impl ResponseBody {
 /// Implements `futures::Stream` for chunk-by-chunk reading.
 pub struct BodyStream {
     inner: ResponseBody,
+    deadline: Option<Pin<Box<tokio::time::Sleep>>>,
+    timed_out: bool,
+}
+
+impl BodyStream {
+    /// Abort the transfer at `deadline`, as with
+    /// [`ResponseBody::cancel`]. The stream then yields
+    /// [`NetError::TimedOut`] and ends.
+    pub fn with_deadline(mut self, deadline: std::time::Instant) -> Self {
+        let deadline = tokio::time::Instant::from_std(deadline);
+        self.deadline = Some(Box::pin(tokio::time::sleep_until(deadline)));
+        self
+    }
+
+    /// The body not read yet, without the deadline.
+    pub(crate) fn into_inner(self) -> ResponseBody {
+        self.inner
+    }
+
+    /// Read the rest of the body, failing with
+    /// [`NetError::ResponseBodyTooBig`] as soon as more than `limit` bytes
+    /// arrive.
+    pub(crate) async fn collect_limited(self, limit: usize) -> Result<Bytes, NetError> {
+        self.collect_partial(limit)
+            .await
+            .map_err(|partial| partial.error)
+    }
+
+    /// Read the first `limit` bytes of the body, or as much as arrived
+    /// before it ended or failed. The rest is not read.
+    pub(crate) async fn read_prefix(mut self, limit: usize) -> Bytes {
+        let mut data = BytesMut::new();
+        while data.len() < limit {
+            match self.next().await {
+                Some(Ok(chunk)) => {
+                    let take = chunk.len().min(limit - data.len());
+                    data.put(chunk.slice(..take));
+                }
+                _ => break,
+            }
+        }
+        self.inner.abort();
+        data.freeze()
+    }
+
+    /// Like [`Self::collect_limited`], keeping the bytes received before
+    /// a failure.
+    pub(crate) async fn collect_partial(mut self, limit: usize) -> Result<Bytes, PartialBody> {
+        if let ResponseBody::Buffered(bytes) = &mut self.inner {
+            return match bytes.len() > limit {
+                true => Err(PartialBody {
+                    bytes: bytes.slice(..limit),
+                    error: NetError::ResponseBodyTooBig { limit },
+                }),
+                false => Ok(std::mem::take(bytes)),
+            };
+        }
+        let mut data = BytesMut::new();
+        while let Some(chunk) = self.next().await {
+            let error = match chunk {
+                Ok(chunk) if data.len() + chunk.len() <= limit => {
+                    data.put(chunk);
+                    continue;
+                }
+                Ok(_) => {
+                    self.inner.abort();
+                    NetError::ResponseBodyTooBig { limit }
+                }
+                Err(error) => error,
+            };
+            return Err(PartialBody {
+                bytes: data.freeze(),
+                error,
+            });
+        }
+        Ok(data.freeze())
+    }
+
+    /// Write the rest of the body to `writer`, failing with
+    /// [`NetError::ResponseBodyTooBig`] before writing past `limit` bytes.
+    pub(crate) async fn copy_limited<W>(
+        mut self,
+        writer: &mut W,
+        limit: usize,
+    ) -> Result<u64, NetError>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let mut written = 0usize;
+        while let Some(chunk) = self.next().await {
+            let chunk = chunk?;
+            if written + chunk.len() > limit {
+                self.inner.abort();
+                return Err(NetError::ResponseBodyTooBig { limit });
+            }
+            writer.write_all(&chunk).await?;
+            written += chunk.len();
+        }
+        writer.flush().await?;
+        Ok(written as u64)
+    }
 }
 
 impl futures::Stream for BodyStream {
     type Item = Result<Bytes, NetError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match &mut self.inner {
-            ResponseBody::H1(incoming) => {
-                use http_body::Body;
-                match Pin::new(incoming).poll_frame(cx) {
-                    Poll::Ready(Some(Ok(frame))) => {
-                        if let Some(data) = frame.data_ref() {
-                            Poll::Ready(Some(Ok(data.clone())))
-                        } else {
-                            // Trailers frame, continue polling
-                            cx.waker().wake_by_ref();
-                            Poll::Pending
-                        }
+        if self.timed_out {
+            return Poll::Ready(None);
+        }
+        if let Some(deadline) = self.deadline.as_mut() {
+            if deadline.as_mut().poll(cx).is_ready() {
+                tracing::debug!("response body deadline exceeded, aborting transfer");
+                self.timed_out = true;
+                self.inner.abort();
+                return Poll::Ready(Some(Err(NetError::TimedOut)));
+            }
+        }
+        poll_body(&mut self.inner, cx)
+    }
+}
+
+/// Poll the next chunk of `body`.
+fn poll_body(
+    body: &mut ResponseBody,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Result<Bytes, NetError>>> {
+    match body {
+        ResponseBody::H1(incoming) => {
+            use http_body::Body;
+            match Pin::new(&mut *incoming).poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => {
+                    if let Some(data) = frame.data_ref() {
+                        Poll::Ready(Some(Ok(data.clone())))
+                    } else {
+                        // Trailers frame, continue polling
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
                     }
-                    Poll::Ready(Some(Err(_))) => Poll::Ready(Some(Err(NetError::HttpBodyError))),
-                    Poll::Ready(None) => Poll::Ready(None),
-                    Poll::Pending => Poll::Pending,
                 }
-            }
-            ResponseBody::H2(recv_stream) => {
-                // For H2, we need to poll the recv_stream
-                // The http2 crate's RecvStream requires different handling
-                match Pin::new(recv_stream).poll_data(cx) {
-                    Poll::Ready(Some(Ok(data))) => Poll::Ready(Some(Ok(data))),
-                    Poll::Ready(Some(Err(_))) => Poll::Ready(Some(Err(NetError::HttpBodyError))),
-                    Poll::Ready(None) => Poll::Ready(None),
-                    Poll::Pending => Poll::Pending,
+                Poll::Ready(Some(Err(e))) => {
+                    Poll::Ready(Some(Err(map_h1_body_error(&e, incoming))))
                 }
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+        ResponseBody::H2(body) => {
+            // For H2, we need to poll the recv_stream
+            // The http2 crate's RecvStream requires different handling
+            match body.recv_stream().poll_data(cx) {
+                Poll::Ready(Some(Ok(data))) => Poll::Ready(Some(Ok(data))),
+                Poll::Ready(Some(Err(_))) => Poll::Ready(Some(Err(NetError::HttpBodyError))),
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
             }
         }
+        ResponseBody::Buffered(bytes) if bytes.is_empty() => Poll::Ready(None),
+        ResponseBody::Buffered(bytes) => Poll::Ready(Some(Ok(std::mem::take(bytes)))),
+        ResponseBody::Prefixed(prefix, _) if !prefix.is_empty() => {
+            Poll::Ready(Some(Ok(std::mem::take(prefix))))
+        }
+        ResponseBody::Prefixed(_, rest) => poll_body(rest, cx),
+    }
+}
+
+/// Map an error reading an HTTP/1.1 body.
+///
+/// hyper reports a connection closed mid-body the same way for both
+/// framings, so the framing is told apart by whether the body still has a
+/// known length.
+fn map_h1_body_error(e: &hyper::Error, incoming: &Incoming) -> NetError {
+    use http_body::Body;
+    let eof = std::error::Error::source(e)
+        .and_then(|s| s.downcast_ref::<std::io::Error>())
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof);
+    match (eof, incoming.size_hint().exact()) {
+        (true, Some(_)) => NetError::ContentLengthMismatch,
+        (true, None) => NetError::IncompleteChunkedEncoding,
+        (false, _) => map_h1_error(e, NetError::HttpBodyError),
     }
 }
 
@@ -141,4 +342,13 @@ mod tests {
         fn assert_stream<S: futures::Stream>() {}
         assert_stream::<BodyStream>();
     }
+
+    #[tokio::test]
+    async fn prefixed_body_yields_prefix_then_rest() {
+        let body = ResponseBody::Prefixed(
+            Bytes::from_static(b"head "),
+            Box::new(ResponseBody::Buffered(Bytes::from_static(b"tail"))),
+        );
+        assert_eq!(body.bytes().await.unwrap(), "head tail");
+    }
 }
@@ -0,0 +1,280 @@
+//! Resumable uploads.
+//!
+//! A large upload over a flaky link should not start over when its
+//! connection drops. A [`ResumableUpload`] asks the server how many bytes
+//! it already has, sends the rest from a reader opened at that offset, and
+//! after a connection failure asks again and resumes from there. Since the
+//! offset always comes from the server, no byte is sent twice.
+//!
+//! Two protocols are supported:
+//! - [`ResumeProtocol::Tus`]: tus 1.0 core. `HEAD` reads `Upload-Offset`;
+//!   `PATCH` sends the rest as `application/offset+octet-stream`.
+//! - [`ResumeProtocol::ContentRange`]: `PUT` with `Content-Range`, as in
+//!   Google Cloud Storage and Drive sessions. `Content-Range: bytes */total`
+//!   asks for the offset, which a `308` carries as `Range: bytes=0-n`.
+//!
+//! The upload URL must exist already: creating it, with a tus `POST` or by
+//! starting a session, is up to the caller.
+//!
+//! ```no_run
+//! use chromenet::base::neterror::NetError;
+//! use chromenet::Client;
+//! use std::io::SeekFrom;
+//! use tokio::io::AsyncSeekExt;
+//!
+//! # async fn run() -> Result<(), NetError> {
+//! let client = Client::new();
+//! let len = tokio::fs::metadata("video.mp4").await?.len();
+//! client
+//!     .resumable_upload("https://uploads.example/files/24e533e0", len)
+//!     .upload(|offset| async move {
+//!         let mut file = tokio::fs::File::open("video.mp4").await?;
+//!         file.seek(SeekFrom::Start(offset)).await?;
+//!         Ok::<_, NetError>(file)
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::base::neterror::NetError;
+use crate::client::Client;
+use crate::http::requestbody::RewindableBody;
+use crate::http::response::HttpResponse;
+use crate::http::retry::{calculate_backoff, should_retry, RetryConfig, RetryReason};
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+use http::StatusCode;
+use std::future::Future;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// tus protocol version sent in `Tus-Resumable`.
+const TUS_VERSION: &str = "1.0.0";
+
+/// Size of the chunks read from the source.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How the server is told where an upload resumes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResumeProtocol {
+    /// tus 1.0: `HEAD` for the offset, `PATCH` with `Upload-Offset`.
+    #[default]
+    Tus,
+    /// `PUT` with `Content-Range`, answered with `308` and `Range` until
+    /// the upload is complete.
+    ContentRange,
+}
+
+/// An upload of `total` bytes to an existing upload URL that survives
+/// connection failures.
+///
+/// Created with [`Client::resumable_upload`].
+#[derive(Clone)]
+pub struct ResumableUpload {
+    client: Client,
+    url: String,
+    total: u64,
+    protocol: ResumeProtocol,
+    retry: RetryConfig,
+}
+
+impl ResumableUpload {
+    /// An upload of `total` bytes to `url` with tus, retried three times.
+    pub fn new<U: AsRef<str>>(client: Client, url: U, total: u64) -> Self {
+        Self {
+            client,
+            url: url.as_ref().to_string(),
+            total,
+            protocol: ResumeProtocol::default(),
+            retry: RetryConfig::default(),
+        }
+    }
+
+    /// Use `protocol` to query and resume the upload.
+    pub fn protocol(mut self, protocol: ResumeProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Resume up to `config.max_attempts` times, waiting with its backoff
+    /// in between.
+    pub fn retry(mut self, config: RetryConfig) -> Self {
+        self.retry = config;
+        self
+    }
+
+    /// How many bytes the server has, from the start of the upload.
+    pub async fn offset(&self) -> Result<u64, NetError> {
+        match self.protocol {
+            ResumeProtocol::Tus => {
+                let response = self
+                    .client
+                    .head(&self.url)
+                    .header("tus-resumable", TUS_VERSION)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                tus_offset(&response)
+            }
+            ResumeProtocol::ContentRange => {
+                let response = self
+                    .client
+                    .put(&self.url)
+                    .header(
+                        http::header::CONTENT_RANGE,
+                        format!("bytes */{}", self.total),
+                    )
+                    .header(http::header::CONTENT_LENGTH, "0")
+                    .body(Bytes::new())
+                    .send()
+                    .await?;
+                self.content_range_offset(&response)
+            }
+        }
+    }
+
+    /// Send the body from `offset` on, read from `reader`, which must be
+    /// positioned at `offset`. Returns the offset the server acknowledged.
+    ///
+    /// At most the `total - offset` bytes left are read. This is one
+    /// attempt; [`upload`](Self::upload) retries.
+    pub async fn send_from<R>(&self, offset: u64, reader: R) -> Result<u64, NetError>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let remaining = self.total.saturating_sub(offset);
+        let body = RewindableBody::buffered(reader_stream(reader.take(remaining)), 0);
+        match self.protocol {
+            ResumeProtocol::Tus => {
+                let response = self
+                    .client
+                    .patch(&self.url)
+                    .header("tus-resumable", TUS_VERSION)
+                    .header("upload-offset", offset.to_string())
+                    .header(
+                        http::header::CONTENT_TYPE,
+                        "application/offset+octet-stream",
+                    )
+                    .header(http::header::CONTENT_LENGTH, remaining.to_string())
+                    .body(body)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                tus_offset(&response)
+            }
+            ResumeProtocol::ContentRange => {
+                let range = match remaining {
+                    0 => format!("bytes */{}", self.total),
+                    n => format!("bytes {}-{}/{}", offset, offset + n - 1, self.total),
+                };
+                let response = self
+                    .client
+                    .put(&self.url)
+                    .header(http::header::CONTENT_RANGE, range)
+                    .header(http::header::CONTENT_LENGTH, remaining.to_string())
+                    .body(body)
+                    .send()
+                    .await?;
+                self.content_range_offset(&response)
+            }
+        }
+    }
+
+    /// Upload the whole body, resuming after connection failures.
+    ///
+    /// `open` is called with the server's offset before each attempt and
+    /// returns the source positioned there, e.g. a file seeked to it.
+    /// Returns the final offset, `total`.
+    pub async fn upload<F, Fut, R>(&self, open: F) -> Result<u64, NetError>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = Result<R, NetError>>,
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let mut attempt = 0;
+        loop {
+            let error = match self.resume(&open).await {
+                Ok(offset) if offset >= self.total => return Ok(offset),
+                // The server stopped reading before the end
+                Ok(_) => NetError::ConnectionClosed,
+                Err(e) if is_resumable(&e) => e,
+                Err(e) => return Err(e),
+            };
+            if !should_retry(attempt, &self.retry) {
+                return Err(error);
+            }
+            attempt += 1;
+            tracing::debug!(attempt, %error, "resuming upload");
+            tokio::time::sleep(calculate_backoff(attempt, &self.retry)).await;
+        }
+    }
+
+    /// Query the offset and send the rest from there.
+    async fn resume<F, Fut, R>(&self, open: &F) -> Result<u64, NetError>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = Result<R, NetError>>,
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let offset = self.offset().await?;
+        if offset >= self.total {
+            return Ok(offset);
+        }
+        let reader = open(offset).await?;
+        self.send_from(offset, reader).await
+    }
+
+    /// The offset from a response to a `Content-Range` `PUT`: all of it
+    /// once the upload completed, the end of `Range` plus one on a `308`.
+    fn content_range_offset(&self, response: &HttpResponse) -> Result<u64, NetError> {
+        match response.status() {
+            StatusCode::OK | StatusCode::CREATED => Ok(self.total),
+            StatusCode::PERMANENT_REDIRECT => {
+                let Some(range) = response.headers().get(http::header::RANGE) else {
+                    return Ok(0);
+                };
+                range
+                    .to_str()
+                    .ok()
+                    .and_then(|r| r.trim().strip_prefix("bytes=0-"))
+                    .and_then(|end| end.parse::<u64>().ok())
+                    .map(|end| end + 1)
+                    .ok_or(NetError::InvalidResponse)
+            }
+            _ => {
+                response.error_for_status_ref()?;
+                Err(NetError::InvalidResponse)
+            }
+        }
+    }
+}
+
+/// The `Upload-Offset` of a tus response.
+fn tus_offset(response: &HttpResponse) -> Result<u64, NetError> {
+    response
+        .headers()
+        .get("upload-offset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .ok_or(NetError::InvalidResponse)
+}
+
+/// Whether an attempt that failed with `error` can be resumed: the
+/// connection failed, or the client's own retry could not replay the
+/// one-shot body stream.
+fn is_resumable(error: &NetError) -> bool {
+    RetryReason::from_error(error).is_some()
+        || matches!(error, NetError::UploadStreamRewindNotSupported)
+}
+
+/// Read `reader` as a stream of body chunks.
+fn reader_stream<R>(reader: R) -> impl Stream<Item = Result<Bytes, NetError>> + Send + 'static
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    futures::stream::try_unfold(reader, |mut reader| async move {
+        let mut buf = BytesMut::with_capacity(CHUNK_SIZE);
+        let n = reader.read_buf(&mut buf).await?;
+        Ok::<_, NetError>((n > 0).then(|| (buf.freeze(), reader)))
+    })
+}
@@ -40,6 +40,10 @@ impl RetryReason {
             NetError::SocketNotConnected => Some(Self::SocketNotConnected),
             NetError::EmptyResponse => Some(Self::EmptyResponse),
             NetError::ConnectionTimedOut => Some(Self::HttpRequestTimeout),
+            NetError::Http2ServerRefusedStream => Some(Self::Http2ServerRefusedStream),
+            NetError::ConnectionAttemptsFailed { attempts, .. } => {
+                attempts.last().and_then(|a| Self::from_error(&a.error))
+            }
             _ => None,
         }
     }
@@ -0,0 +1,120 @@
+//! Per-origin knowledge about servers learned from earlier requests.
+//!
+//! Mirrors the HTTP/1.1-required part of Chromium's
+//! `net/http/http_server_properties.h`: once an origin rejects HTTP/2, the
+//! client talks HTTP/1.1 to it for a while instead of failing every request.
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// How long an origin stays downgraded to HTTP/1.1 by default.
+pub const DEFAULT_HTTP11_REQUIRED_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Shared store of per-origin server properties.
+#[derive(Debug, Clone)]
+pub struct HttpServerProperties {
+    /// Origin -> when the HTTP/1.1 requirement expires
+    http11_required: Arc<DashMap<String, Instant>>,
+    ttl: Duration,
+}
+
+impl Default for HttpServerProperties {
+    fn default() -> Self {
+        Self::new(DEFAULT_HTTP11_REQUIRED_TTL)
+    }
+}
+
+impl HttpServerProperties {
+    /// Create an empty store whose HTTP/1.1 requirements last `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            http11_required: Arc::new(DashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Key as `scheme://host:port`.
+    fn origin_key(url: &Url) -> Option<String> {
+        Some(format!(
+            "{}://{}:{}",
+            url.scheme(),
+            url.host_str()?,
+            url.port_or_known_default()?
+        ))
+    }
+
+    /// Remember that `url`'s origin must be spoken to over HTTP/1.1.
+    pub fn set_http11_required(&self, url: &Url) {
+        if let Some(key) = Self::origin_key(url) {
+            tracing::debug!(target: "chromenet::http", origin = %key, ttl = ?self.ttl, "Origin requires HTTP/1.1");
+            self.http11_required.insert(key, Instant::now() + self.ttl);
+        }
+    }
+
+    /// Whether `url`'s origin is currently downgraded to HTTP/1.1.
+    pub fn requires_http11(&self, url: &Url) -> bool {
+        let Some(key) = Self::origin_key(url) else {
+            return false;
+        };
+        let expired = match self.http11_required.get(&key) {
+            Some(until) => *until <= Instant::now(),
+            None => return false,
+        };
+        if expired {
+            self.http11_required.remove(&key);
+        }
+        !expired
+    }
+
+    /// Origins currently downgraded to HTTP/1.1, with how long they stay so.
+    pub(crate) fn http11_required_origins(&self) -> Vec<(String, Duration)> {
+        let now = Instant::now();
+        self.http11_required
+            .iter()
+            .filter(|e| *e.value() > now)
+            .map(|e| (e.key().clone(), *e.value() - now))
+            .collect()
+    }
+
+    /// Downgrade `origin`, keyed as `scheme://host:port`, for `remaining`.
+    pub(crate) fn restore_http11_required(&self, origin: &str, remaining: Duration) {
+        self.http11_required
+            .insert(origin.to_string(), Instant::now() + remaining);
+    }
+
+    /// Forget all learned properties.
+    pub fn clear(&self) {
+        self.http11_required.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http11_required_is_per_origin() {
+        let props = HttpServerProperties::default();
+        let url = Url::parse("https://example.com/a").unwrap();
+        assert!(!props.requires_http11(&url));
+
+        props.set_http11_required(&url);
+        assert!(props.requires_http11(&Url::parse("https://example.com:443/b").unwrap()));
+        assert!(!props.requires_http11(&Url::parse("http://example.com/").unwrap()));
+        assert!(!props.requires_http11(&Url::parse("https://example.com:8443/").unwrap()));
+
+        props.clear();
+        assert!(!props.requires_http11(&url));
+    }
+
+    #[test]
+    fn test_http11_required_expires() {
+        let props = HttpServerProperties::new(Duration::ZERO);
+        let url = Url::parse("https://example.com/").unwrap();
+
+        props.set_http11_required(&url);
+        assert!(!props.requires_http11(&url));
+    }
+}
@@ -0,0 +1,884 @@
+//! Compression Dictionary Transport (RFC 9842).
+//!
+//! Chromium mapping: `net/shared_dictionary/` (`SharedDictionaryManager`,
+//! `SharedDictionaryNetworkTransaction`)
+//!
+//! A response with `Use-As-Dictionary: match="/js/app.*.js"` is kept as a
+//! dictionary for later requests to matching URLs of the same origin. Those
+//! requests announce it with `Available-Dictionary` (its SHA-256) and
+//! `Dictionary-ID`, and add `dcb` and `dcz` to `Accept-Encoding`. A server
+//! may then answer with the difference to the dictionary, Brotli (`dcb`) or
+//! Zstandard (`dcz`) compressed with the dictionary as prefix; the client
+//! decodes such bodies before handing them out.
+//!
+//! Off unless a [`SharedDictionaryStore`] is given to
+//! [`ClientBuilder::shared_dictionaries`](crate::ClientBuilder::shared_dictionaries),
+//! so the `Accept-Encoding` of other clients stays as it was. Like Chrome,
+//! only secure origins (HTTPS or loopback) use dictionaries, and a
+//! dictionary lives as long as its response stays fresh.
+//!
+//! `match` patterns are a subset of URL patterns: a path, optionally with a
+//! query, where `*` matches any run of characters. Patterns without a query
+//! match any query. Patterns with groups (`(...)`, `{...}`) or named
+//! parameters are ignored, as is a dictionary whose `type` is not `raw`.
+
+use crate::base::neterror::NetError;
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::http::httpcache::freshness_lifetime;
+use crate::http::typedheaders::content_encodings;
+use crate::urlrequest::rules::{glob_match, ResourceType};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, StatusCode};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+use url::Url;
+
+/// `Use-As-Dictionary` response header.
+pub const USE_AS_DICTIONARY: &str = "use-as-dictionary";
+/// `Available-Dictionary` request header.
+pub const AVAILABLE_DICTIONARY: &str = "available-dictionary";
+/// `Dictionary-ID` request header.
+pub const DICTIONARY_ID: &str = "dictionary-id";
+
+/// Largest dictionary kept, like Chrome's.
+const MAX_DICTIONARY_SIZE: usize = 100 * 1024 * 1024;
+/// Total size of the dictionaries a store keeps by default.
+const DEFAULT_MAX_TOTAL_SIZE: usize = 200 * 1024 * 1024;
+/// Longest `id` accepted.
+const MAX_ID_LENGTH: usize = 1024;
+
+/// Start of a `dcb` body, followed by the dictionary hash.
+const DCB_MAGIC: [u8; 4] = [0xff, 0x44, 0x43, 0x42];
+/// Start of a `dcz` body (a Zstandard skippable frame of 32 bytes),
+/// followed by the dictionary hash.
+const DCZ_MAGIC: [u8; 8] = [0x5e, 0x2a, 0x4d, 0x18, 0x20, 0x00, 0x00, 0x00];
+
+/// Content coding of a body compressed with a dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DictionaryEncoding {
+    /// `dcb`: Brotli with the dictionary as prefix.
+    Brotli,
+    /// `dcz`: Zstandard with the dictionary as prefix.
+    Zstd,
+}
+
+impl DictionaryEncoding {
+    /// The encoding of a response, if its only content coding is `dcb` or
+    /// `dcz`.
+    pub fn of(headers: &HeaderMap) -> Option<Self> {
+        let mut codings = content_encodings(headers).into_iter();
+        let encoding = match codings.next()?.as_str() {
+            "dcb" => Self::Brotli,
+            "dcz" => Self::Zstd,
+            _ => return None,
+        };
+        codings.next().is_none().then_some(encoding)
+    }
+
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Self::Brotli => &DCB_MAGIC,
+            Self::Zstd => &DCZ_MAGIC,
+        }
+    }
+
+    /// Decode `body`, which must start with the header naming `dictionary`.
+    pub fn decode(self, body: &[u8], dictionary: &SharedDictionary) -> Result<Bytes, NetError> {
+        let magic = self.magic();
+        let payload = body
+            .strip_prefix(magic)
+            .and_then(|rest| rest.strip_prefix(&dictionary.hash[..]))
+            .ok_or(NetError::ContentDecodingFailed)?;
+        let mut decoded = Vec::new();
+        let result = match self {
+            Self::Brotli => brotli_decompressor::BrotliDecompressCustomDict(
+                &mut &payload[..],
+                &mut decoded,
+                &mut [0u8; 4096],
+                &mut [0u8; 4096],
+                dictionary.bytes.to_vec(),
+            ),
+            Self::Zstd => zstd::stream::read::Decoder::with_dictionary(payload, &dictionary.bytes)
+                .and_then(|mut decoder| decoder.read_to_end(&mut decoded).map(|_| ())),
+        };
+        result.map_err(|e| {
+            tracing::debug!(target: "chromenet::http", error = %e, encoding = ?self, "dictionary decoding failed");
+            NetError::ContentDecodingFailed
+        })?;
+        Ok(decoded.into())
+    }
+
+    /// The dictionary hash a body names in its header.
+    pub fn dictionary_hash(self, body: &[u8]) -> Option<[u8; 32]> {
+        body.strip_prefix(self.magic())?.get(..32)?.try_into().ok()
+    }
+}
+
+/// A response kept to decode later responses with.
+#[derive(Debug)]
+pub struct SharedDictionary {
+    url: Url,
+    match_pattern: String,
+    match_dest: Vec<String>,
+    id: Option<String>,
+    hash: [u8; 32],
+    bytes: Bytes,
+    expires: SystemTime,
+}
+
+impl SharedDictionary {
+    /// URL the dictionary was fetched from.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The `match` pattern, resolved against [`url`](Self::url).
+    pub fn match_pattern(&self) -> &str {
+        &self.match_pattern
+    }
+
+    /// Request destinations (`match-dest`) the dictionary is used for,
+    /// empty for all.
+    pub fn match_dest(&self) -> &[String] {
+        &self.match_dest
+    }
+
+    /// The `id` the server gave it, sent back as `Dictionary-ID`.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// SHA-256 of the dictionary, sent as `Available-Dictionary`.
+    pub fn hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+
+    /// The dictionary itself.
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    /// When the dictionary stops being used.
+    pub fn expires(&self) -> SystemTime {
+        self.expires
+    }
+
+    /// The `Available-Dictionary` value: the hash as a structured field
+    /// byte sequence.
+    pub fn available_dictionary(&self) -> HeaderValue {
+        let value = format!(":{}:", STANDARD.encode(self.hash));
+        HeaderValue::from_str(&value).expect("base64 is a valid header value")
+    }
+
+    /// The `Dictionary-ID` value, a structured field string.
+    pub fn dictionary_id(&self) -> Option<HeaderValue> {
+        let id = self.id.as_ref()?;
+        let value = format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""));
+        HeaderValue::from_str(&value).ok()
+    }
+
+    /// Whether the dictionary is used for `url` with `destination`.
+    fn matches(&self, url: &Url, destination: &str) -> bool {
+        if url.origin() != self.url.origin() {
+            return false;
+        }
+        if !self.match_dest.is_empty() && !self.match_dest.iter().any(|d| d == destination) {
+            return false;
+        }
+        let (path, query) = match self.match_pattern.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (self.match_pattern.as_str(), None),
+        };
+        glob_match(path, url.path())
+            && query.is_none_or(|query| glob_match(query, url.query().unwrap_or("")))
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires <= SystemTime::now()
+    }
+}
+
+/// Dictionary with the partition it belongs to.
+struct Entry {
+    dictionary: Arc<SharedDictionary>,
+    partition: Option<NetworkIsolationKey>,
+    last_used: Instant,
+}
+
+/// Dictionaries kept from `Use-As-Dictionary` responses.
+///
+/// Clones share their dictionaries. Dictionaries are kept apart by
+/// [`NetworkIsolationKey`] like cache entries, and the least recently used
+/// ones go first once the total size is over the limit.
+#[derive(Clone)]
+pub struct SharedDictionaryStore {
+    entries: Arc<Mutex<Vec<Entry>>>,
+    max_total_size: usize,
+}
+
+impl Default for SharedDictionaryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SharedDictionaryStore {
+    /// An empty store keeping up to 200 MiB of dictionaries.
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Vec::new())),
+            max_total_size: DEFAULT_MAX_TOTAL_SIZE,
+        }
+    }
+
+    /// Keep up to `bytes` of dictionaries in all.
+    pub fn with_max_size(mut self, bytes: usize) -> Self {
+        self.max_total_size = bytes;
+        self
+    }
+
+    /// Number of dictionaries kept, including expired ones not yet
+    /// dropped.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Whether no dictionary is kept.
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Drop all dictionaries.
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    /// The dictionary a request to `url` announces, in the shared
+    /// partition and without a destination, so dictionaries limited by
+    /// `match-dest` are skipped.
+    pub fn find(&self, url: &Url) -> Option<Arc<SharedDictionary>> {
+        self.find_in(url, None, ResourceType::Other)
+    }
+
+    /// The dictionary a request to `url` in partition `nik` for
+    /// `resource_type` announces: of the matching ones, the one with the
+    /// longest `match`, then the newest.
+    ///
+    /// Chromium mapping: `SharedDictionaryStorage::GetDictionary`
+    pub fn find_in(
+        &self,
+        url: &Url,
+        nik: Option<&NetworkIsolationKey>,
+        resource_type: ResourceType,
+    ) -> Option<Arc<SharedDictionary>> {
+        if !is_secure(url) {
+            return None;
+        }
+        let destination = destination(resource_type);
+        let mut entries = self.entries();
+        entries.retain(|e| !e.dictionary.is_expired());
+        let entry = entries
+            .iter_mut()
+            .filter(|e| e.partition.as_ref() == nik && e.dictionary.matches(url, destination))
+            // The last of the longest, so the newest
+            .max_by_key(|e| e.dictionary.match_pattern.len())?;
+        entry.last_used = Instant::now();
+        Some(entry.dictionary.clone())
+    }
+
+    /// The dictionary of partition `nik` with SHA-256 `hash`, if still
+    /// usable for `url`.
+    pub(crate) fn get_by_hash(
+        &self,
+        url: &Url,
+        nik: Option<&NetworkIsolationKey>,
+        hash: &[u8; 32],
+    ) -> Option<Arc<SharedDictionary>> {
+        self.entries()
+            .iter()
+            .find(|e| {
+                e.partition.as_ref() == nik
+                    && e.dictionary.hash == *hash
+                    && e.dictionary.url.origin() == url.origin()
+                    && !e.dictionary.is_expired()
+            })
+            .map(|e| e.dictionary.clone())
+    }
+
+    /// Whether a response to a GET for `url` is to be kept as a dictionary:
+    /// a fresh `200` from a secure origin with a valid `Use-As-Dictionary`.
+    pub fn wants(&self, url: &Url, status: StatusCode, headers: &HeaderMap) -> bool {
+        status == StatusCode::OK
+            && is_secure(url)
+            && UseAsDictionary::from_headers(headers, url).is_some()
+            && freshness_lifetime(headers, status).is_some_and(|ttl| !ttl.is_zero())
+    }
+
+    /// Keep the response to a GET for `url` as a dictionary if it
+    /// [`wants`](Self::wants) it. `body` must be decoded from any content
+    /// coding. Returns the dictionary kept.
+    pub fn insert_from_response(
+        &self,
+        url: &Url,
+        nik: Option<&NetworkIsolationKey>,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Bytes,
+    ) -> Option<Arc<SharedDictionary>> {
+        let max_size = MAX_DICTIONARY_SIZE.min(self.max_total_size);
+        if !self.wants(url, status, headers) || body.is_empty() || body.len() > max_size {
+            return None;
+        }
+        let header = UseAsDictionary::from_headers(headers, url)?;
+        let ttl = freshness_lifetime(headers, status)?;
+        let dictionary = Arc::new(SharedDictionary {
+            url: url.clone(),
+            match_pattern: header.match_pattern,
+            match_dest: header.match_dest,
+            id: header.id,
+            hash: boring::sha::sha256(&body),
+            bytes: body,
+            expires: SystemTime::now() + ttl,
+        });
+        tracing::debug!(
+            target: "chromenet::http",
+            url = %url,
+            r#match = %dictionary.match_pattern,
+            size = dictionary.bytes.len(),
+            "stored shared dictionary"
+        );
+
+        let mut entries = self.entries();
+        // A newer dictionary for the same pattern replaces the older one
+        entries.retain(|e| {
+            !(e.partition.as_ref() == nik
+                && e.dictionary.url.origin() == url.origin()
+                && e.dictionary.match_pattern == dictionary.match_pattern
+                && e.dictionary.match_dest == dictionary.match_dest)
+                && !e.dictionary.is_expired()
+        });
+        entries.push(Entry {
+            dictionary: dictionary.clone(),
+            partition: nik.cloned(),
+            last_used: Instant::now(),
+        });
+        let mut total: usize = entries.iter().map(|e| e.dictionary.bytes.len()).sum();
+        while total > self.max_total_size {
+            let Some((oldest, _)) = entries.iter().enumerate().min_by_key(|(_, e)| e.last_used)
+            else {
+                break;
+            };
+            total -= entries.remove(oldest).dictionary.bytes.len();
+        }
+        Some(dictionary)
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Vec<Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for SharedDictionaryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedDictionaryStore")
+            .field("dictionaries", &self.len())
+            .field("max_total_size", &self.max_total_size)
+            .finish()
+    }
+}
+
+/// Add `dcb` and `dcz` to an `Accept-Encoding` value, as Chrome does for
+/// requests announcing a dictionary.
+pub fn accept_encoding_with_dictionaries(accept_encoding: Option<&str>) -> String {
+    let mut value = accept_encoding.unwrap_or_default().trim().to_string();
+    for coding in ["dcb", "dcz"] {
+        let present = value.split(',').any(|c| {
+            c.split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .eq_ignore_ascii_case(coding)
+        });
+        if !present {
+            if !value.is_empty() {
+                value.push_str(", ");
+            }
+            value.push_str(coding);
+        }
+    }
+    value
+}
+
+/// Whether `url` is a potentially trustworthy origin: HTTPS, or HTTP to a
+/// loopback host.
+fn is_secure(url: &Url) -> bool {
+    match url.scheme() {
+        "https" => true,
+        "http" => match url.host() {
+            Some(url::Host::Domain(host)) => host == "localhost" || host.ends_with(".localhost"),
+            Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Fetch destination of a request, as `match-dest` names them.
+fn destination(resource_type: ResourceType) -> &'static str {
+    match resource_type {
+        ResourceType::MainFrame => "document",
+        ResourceType::SubFrame => "iframe",
+        ResourceType::Stylesheet => "style",
+        ResourceType::Script => "script",
+        ResourceType::Image => "image",
+        ResourceType::Font => "font",
+        ResourceType::Media => "video",
+        ResourceType::WebSocket => "websocket",
+        ResourceType::XmlHttpRequest | ResourceType::Ping | ResourceType::Other => "",
+    }
+}
+
+/// A parsed `Use-As-Dictionary` header.
+#[derive(Debug, PartialEq)]
+struct UseAsDictionary {
+    /// Pattern resolved against the response URL: path and optional query
+    match_pattern: String,
+    match_dest: Vec<String>,
+    id: Option<String>,
+}
+
+impl UseAsDictionary {
+    fn from_headers(headers: &HeaderMap, url: &Url) -> Option<Self> {
+        let value = headers.get(USE_AS_DICTIONARY)?.to_str().ok()?;
+        Self::parse(value, url)
+    }
+
+    /// Parse the structured field dictionary `value` of a response to
+    /// `url`.
+    fn parse(value: &str, url: &Url) -> Option<Self> {
+        let members = parse_sf_dictionary(value)?;
+        let member = |key: &str| members.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v);
+
+        let Some(Item::String(pattern)) = member("match") else {
+            return None;
+        };
+        let match_dest = match member("match-dest") {
+            None => Vec::new(),
+            Some(Item::List(items)) => items
+                .iter()
+                .map(|item| match item {
+                    Item::String(dest) => Some(dest.clone()),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?,
+            Some(_) => return None,
+        };
+        let id = match member("id") {
+            None => None,
+            Some(Item::String(id)) if id.len() <= MAX_ID_LENGTH => Some(id.clone()),
+            Some(_) => return None,
+        };
+        match member("type") {
+            None => {}
+            Some(Item::Token(kind)) if kind == "raw" => {}
+            Some(_) => return None,
+        }
+
+        // Groups and named parameters of URL patterns are not supported
+        if pattern.contains(['(', ')', '{', '}']) || pattern.contains("/:") {
+            return None;
+        }
+        let resolved = url.join(pattern).ok()?;
+        if resolved.origin() != url.origin() {
+            return None;
+        }
+        let mut match_pattern = resolved.path().to_string();
+        if let Some(query) = resolved.query() {
+            match_pattern.push('?');
+            match_pattern.push_str(query);
+        }
+        Some(Self {
+            match_pattern,
+            match_dest,
+            id,
+        })
+    }
+}
+
+/// A structured field value (RFC 8941), as far as `Use-As-Dictionary`
+/// needs.
+#[derive(Debug)]
+enum Item {
+    String(String),
+    Token(String),
+    /// Booleans, numbers and byte sequences, which no member takes
+    Other,
+    List(Vec<Item>),
+}
+
+/// Parse a structured field dictionary, dropping parameters. `None` if it
+/// is malformed.
+fn parse_sf_dictionary(input: &str) -> Option<Vec<(String, Item)>> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        pos: 0,
+    };
+    let mut members = Vec::new();
+    parser.skip_spaces();
+    while !parser.at_end() {
+        let key = parser.key()?;
+        let value = if parser.eat(b'=') {
+            if parser.peek() == Some(b'(') {
+                parser.inner_list()?
+            } else {
+                parser.item()?
+            }
+        } else {
+            parser.parameters()?;
+            Item::Other
+        };
+        members.push((key, value));
+        parser.skip_whitespace();
+        if parser.at_end() {
+            break;
+        }
+        if !parser.eat(b',') {
+            return None;
+        }
+        parser.skip_whitespace();
+        if parser.at_end() {
+            return None;
+        }
+    }
+    Some(members)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.eat(b' ') {}
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        // Only ASCII is taken
+        std::str::from_utf8(&self.input[start..self.pos]).unwrap_or_default()
+    }
+
+    fn key(&mut self) -> Option<String> {
+        if !self
+            .peek()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == b'*')
+        {
+            return None;
+        }
+        let key = self
+            .take_while(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || b"_-.*".contains(&c));
+        Some(key.to_string())
+    }
+
+    fn item(&mut self) -> Option<Item> {
+        let item = self.bare_item()?;
+        self.parameters()?;
+        Some(item)
+    }
+
+    fn bare_item(&mut self) -> Option<Item> {
+        match self.peek()? {
+            b'"' => {
+                self.pos += 1;
+                let mut value = String::new();
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.pos += 1;
+                            return Some(Item::String(value));
+                        }
+                        b'\\' => {
+                            self.pos += 1;
+                            let escaped = self.peek().filter(|c| matches!(c, b'"' | b'\\'))?;
+                            value.push(escaped as char);
+                        }
+                        c @ 0x20..=0x7e => value.push(c as char),
+                        _ => return None,
+                    }
+                    self.pos += 1;
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == b'*' => {
+                let token = self
+                    .take_while(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~:/".contains(&c));
+                Some(Item::Token(token.to_string()))
+            }
+            b'?' => {
+                self.pos += 1;
+                if !matches!(self.peek()?, b'0' | b'1') {
+                    return None;
+                }
+                self.pos += 1;
+                Some(Item::Other)
+            }
+            b'-' | b'0'..=b'9' => {
+                self.pos += 1;
+                self.take_while(|c| c.is_ascii_digit() || c == b'.');
+                Some(Item::Other)
+            }
+            b':' => {
+                self.pos += 1;
+                self.take_while(|c| c.is_ascii_alphanumeric() || b"+/=".contains(&c));
+                self.eat(b':').then_some(Item::Other)
+            }
+            _ => None,
+        }
+    }
+
+    fn inner_list(&mut self) -> Option<Item> {
+        if !self.eat(b'(') {
+            return None;
+        }
+        let mut items = Vec::new();
+        loop {
+            self.skip_spaces();
+            if self.eat(b')') {
+                self.parameters()?;
+                return Some(Item::List(items));
+            }
+            items.push(self.item()?);
+            if !matches!(self.peek()?, b' ' | b')') {
+                return None;
+            }
+        }
+    }
+
+    fn parameters(&mut self) -> Option<()> {
+        while self.eat(b';') {
+            self.skip_spaces();
+            self.key()?;
+            if self.eat(b'=') {
+                self.bare_item()?;
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn dictionary_response(header: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(USE_AS_DICTIONARY, header.parse().unwrap());
+        headers.insert(http::header::CACHE_CONTROL, "max-age=3600".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_parse_use_as_dictionary() {
+        let base = url("https://example.com/js/app.v1.js");
+        let parsed = UseAsDictionary::parse(
+            r#"match="/js/app.*.js", match-dest=("script"), id="app;v1", type=raw"#,
+            &base,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            UseAsDictionary {
+                match_pattern: "/js/app.*.js".into(),
+                match_dest: vec!["script".into()],
+                id: Some("app;v1".into()),
+            }
+        );
+
+        // Relative to the response, with a query
+        let parsed = UseAsDictionary::parse(r#"match="app?v=*""#, &base).unwrap();
+        assert_eq!(parsed.match_pattern, "/js/app?v=*");
+
+        for invalid in [
+            "",
+            r#"match=app"#,
+            r#"match="/a", type=shared"#,
+            r#"match="https://other.example/*""#,
+            r#"match="/:name/*""#,
+            r#"match="/(a|b)""#,
+            r#"match="/a", match-dest="script""#,
+            r#"match="/a","#,
+        ] {
+            assert_eq!(UseAsDictionary::parse(invalid, &base), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_find_longest_match() {
+        let store = SharedDictionaryStore::new();
+        let origin = url("https://example.com/js/app.v1.js");
+        let headers = dictionary_response(r#"match="/js/*""#);
+        store
+            .insert_from_response(&origin, None, StatusCode::OK, &headers, "generic".into())
+            .unwrap();
+        let headers = dictionary_response(r#"match="/js/app.*.js", id="app""#);
+        store
+            .insert_from_response(&origin, None, StatusCode::OK, &headers, "specific".into())
+            .unwrap();
+        assert_eq!(store.len(), 2);
+
+        let found = store
+            .find(&url("https://example.com/js/app.v2.js"))
+            .unwrap();
+        assert_eq!(&found.bytes()[..], b"specific");
+        assert_eq!(found.dictionary_id().unwrap(), "\"app\"");
+        assert_eq!(
+            found.available_dictionary(),
+            format!(":{}:", STANDARD.encode(boring::sha::sha256(b"specific"))).as_str()
+        );
+        let found = store.find(&url("https://example.com/js/lib.js")).unwrap();
+        assert_eq!(&found.bytes()[..], b"generic");
+
+        assert!(store.find(&url("https://example.com/css/a.css")).is_none());
+        assert!(store.find(&url("https://other.example/js/a.js")).is_none());
+        assert!(store.find(&url("http://example.com/js/a.js")).is_none());
+        let nik = NetworkIsolationKey::from_top_frame_url(&url("https://top.example/"));
+        assert!(store
+            .find_in(
+                &url("https://example.com/js/a.js"),
+                nik.as_ref(),
+                ResourceType::Script
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_store_requires_freshness_and_limits_size() {
+        let store = SharedDictionaryStore::new().with_max_size(10);
+        let origin = url("https://example.com/a");
+        let mut headers = dictionary_response(r#"match="/a*""#);
+        headers.insert(http::header::CACHE_CONTROL, "no-cache".parse().unwrap());
+        assert!(store
+            .insert_from_response(&origin, None, StatusCode::OK, &headers, "x".into())
+            .is_none());
+
+        let headers = dictionary_response(r#"match="/a*""#);
+        store.insert_from_response(&origin, None, StatusCode::OK, &headers, "123456".into());
+        let headers = dictionary_response(r#"match="/ab*""#);
+        store.insert_from_response(&origin, None, StatusCode::OK, &headers, "abcdef".into());
+        // Over 10 bytes: the least recently used goes
+        assert_eq!(store.len(), 1);
+        assert_eq!(
+            &store.find(&url("https://example.com/abc")).unwrap().bytes()[..],
+            b"abcdef"
+        );
+    }
+
+    #[test]
+    fn test_decode_dcb_and_dcz() {
+        let store = SharedDictionaryStore::new();
+        let origin = url("https://example.com/a");
+        let headers = dictionary_response(r#"match="/*""#);
+        let dictionary = store
+            .insert_from_response(
+                &origin,
+                None,
+                StatusCode::OK,
+                &headers,
+                "hello dictionary".into(),
+            )
+            .unwrap();
+
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+            std::io::Write::write_all(&mut writer, b"hello world").unwrap();
+        }
+        let mut dcb = DCB_MAGIC.to_vec();
+        dcb.extend_from_slice(dictionary.hash());
+        dcb.extend_from_slice(&compressed);
+        assert_eq!(
+            DictionaryEncoding::Brotli.dictionary_hash(&dcb),
+            Some(*dictionary.hash())
+        );
+        let decoded = DictionaryEncoding::Brotli
+            .decode(&dcb, &dictionary)
+            .unwrap();
+        assert_eq!(&decoded[..], b"hello world");
+
+        let compressed = zstd::bulk::Compressor::with_dictionary(3, dictionary.bytes())
+            .unwrap()
+            .compress(b"hello dictionary, again")
+            .unwrap();
+        let mut dcz = DCZ_MAGIC.to_vec();
+        dcz.extend_from_slice(dictionary.hash());
+        dcz.extend_from_slice(&compressed);
+        let decoded = DictionaryEncoding::Zstd.decode(&dcz, &dictionary).unwrap();
+        assert_eq!(&decoded[..], b"hello dictionary, again");
+
+        // A body for another dictionary
+        dcz[DCZ_MAGIC.len()] ^= 1;
+        assert!(matches!(
+            DictionaryEncoding::Zstd.decode(&dcz, &dictionary),
+            Err(NetError::ContentDecodingFailed)
+        ));
+    }
+
+    #[test]
+    fn test_accept_encoding_with_dictionaries() {
+        assert_eq!(
+            accept_encoding_with_dictionaries(Some("gzip, deflate, br, zstd")),
+            "gzip, deflate, br, zstd, dcb, dcz"
+        );
+        assert_eq!(
+            accept_encoding_with_dictionaries(Some("br, dcb")),
+            "br, dcb, dcz"
+        );
+        assert_eq!(accept_encoding_with_dictionaries(None), "dcb, dcz");
+    }
+
+    #[test]
+    fn test_dictionary_encoding_of() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(DictionaryEncoding::of(&headers), None);
+        headers.insert(http::header::CONTENT_ENCODING, "DCZ".parse().unwrap());
+        assert_eq!(
+            DictionaryEncoding::of(&headers),
+            Some(DictionaryEncoding::Zstd)
+        );
+        headers.insert(http::header::CONTENT_ENCODING, "dcb, gzip".parse().unwrap());
+        assert_eq!(DictionaryEncoding::of(&headers), None);
+    }
+}
@@ -0,0 +1,244 @@
+//! Graceful shutdown of a [`Client`](crate::Client).
+//!
+//! Chromium mapping: `URLRequestContext` teardown, which fails new
+//! requests with `ERR_CONTEXT_SHUT_DOWN`, closes idle sockets and sessions
+//! and commits its persistent stores.
+//!
+//! [`Client::shutdown`](crate::Client::shutdown) does the same in order:
+//!
+//! 1. New requests on the client and its clones fail with
+//!    [`NetError::ContextShutDown`](crate::base::neterror::NetError::ContextShutDown).
+//! 2. Background tasks of the client, such as started
+//!    [`Prefetcher`](crate::http::Prefetcher)s, are aborted.
+//! 3. Requests in flight get until the deadline to receive their response
+//!    headers. Bodies read afterwards keep their connection.
+//! 4. HTTP/2 sessions take no new streams and send GOAWAY once their open
+//!    streams finish; idle sockets are closed, and sockets released later
+//!    too.
+//! 5. The hooks registered with
+//!    [`ClientBuilder::on_shutdown`](crate::ClientBuilder::on_shutdown) run,
+//!    e.g. to save the cookie jar.
+//!
+//! [`Client::close`](crate::Client::close) does steps 1, 2 and 4 without
+//! waiting. Background tasks stop on a cancellation token owned by the
+//! client, which is also cancelled once the last clone of the client is
+//! dropped, so an abandoned client leaves no task behind.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+use tokio_util::sync::CancellationToken;
+
+/// A hook run when the client shuts down, e.g. to flush a store to disk.
+pub(crate) type ShutdownHook = Box<dyn FnOnce() -> io::Result<()> + Send>;
+
+/// What a [`Client::shutdown`](crate::Client::shutdown) did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Requests still waiting for their response at the deadline. They
+    /// keep running, but their connections are not reused.
+    pub abandoned: usize,
+    /// Background tasks that were aborted.
+    pub aborted_tasks: usize,
+    /// Shutdown hooks that failed.
+    pub failed_hooks: usize,
+}
+
+impl ShutdownReport {
+    /// Whether every request finished in time and every hook succeeded.
+    pub fn is_clean(&self) -> bool {
+        self.abandoned == 0 && self.failed_hooks == 0
+    }
+}
+
+/// Shutdown state shared by a client and its clones.
+#[derive(Default)]
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Notified when the last request in flight finishes.
+    idle: Notify,
+    tasks: Mutex<Vec<AbortHandle>>,
+    /// Cancelled with the tracked tasks, stops the background tasks that
+    /// select on it.
+    cancel: CancellationToken,
+    hooks: Mutex<Vec<ShutdownHook>>,
+}
+
+impl Drop for Lifecycle {
+    /// The last clone of the client is gone: stop its background tasks.
+    fn drop(&mut self) {
+        self.abort_tasks();
+    }
+}
+
+/// A request in flight, counted until dropped.
+pub(crate) struct InFlight(Arc<Lifecycle>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.leave();
+    }
+}
+
+impl Lifecycle {
+    /// Count a request as in flight, `None` once the client shut down.
+    pub(crate) fn enter(self: &Arc<Self>) -> Option<InFlight> {
+        // Counted before the check, so a shutdown that closes in between
+        // waits for the request or sees it give up
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        if self.closed.load(Ordering::SeqCst) {
+            self.leave();
+            return None;
+        }
+        Some(InFlight(self.clone()))
+    }
+
+    fn leave(&self) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    /// Refuse new requests. Returns `false` if already closed.
+    pub(crate) fn close(&self) -> bool {
+        !self.closed.swap(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Requests in flight.
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Wait until no request is in flight or `deadline` passes. Returns
+    /// the requests still in flight.
+    pub(crate) async fn drain(&self, deadline: Instant) -> usize {
+        loop {
+            let idle = self.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.in_flight() == 0 {
+                return 0;
+            }
+            if tokio::time::timeout_at(deadline.into(), idle)
+                .await
+                .is_err()
+            {
+                return self.in_flight();
+            }
+        }
+    }
+
+    /// Abort `task` on shutdown; aborted right away if already shut down.
+    pub(crate) fn track(&self, task: AbortHandle) {
+        if self.is_closed() {
+            task.abort();
+            return;
+        }
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.retain(|t| !t.is_finished());
+        tasks.push(task);
+    }
+
+    /// Token cancelled when the tasks are aborted, for background tasks
+    /// that should stop without holding the client.
+    pub(crate) fn cancel_token(&self) -> CancellationToken {
+        self.cancel.child_token()
+    }
+
+    /// Cancel the background tasks and abort the tracked ones, returning
+    /// how many of those were still running.
+    pub(crate) fn abort_tasks(&self) -> usize {
+        self.cancel.cancel();
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(|e| e.into_inner()));
+        tasks
+            .into_iter()
+            .filter(|t| !t.is_finished())
+            .inspect(|t| t.abort())
+            .count()
+    }
+
+    pub(crate) fn add_hook(&self, hook: ShutdownHook) {
+        self.hooks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(hook);
+    }
+
+    /// Run the hooks, each once, off the async threads. Returns how many
+    /// failed.
+    pub(crate) async fn run_hooks(&self) -> usize {
+        let hooks = std::mem::take(&mut *self.hooks.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut failed = 0;
+        for hook in hooks {
+            let result = tokio::task::spawn_blocking(hook)
+                .await
+                .unwrap_or_else(|e| Err(io::Error::other(e)));
+            if let Err(e) = result {
+                tracing::warn!(target: "chromenet::http", error = %e, "shutdown hook failed");
+                failed += 1;
+            }
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_requests() {
+        let lifecycle = Arc::new(Lifecycle::default());
+        let request = lifecycle.enter().unwrap();
+        assert!(lifecycle.close());
+        assert!(!lifecycle.close());
+        assert!(lifecycle.enter().is_none());
+        assert_eq!(lifecycle.in_flight(), 1);
+
+        let deadline = Instant::now() + Duration::from_millis(20);
+        assert_eq!(lifecycle.drain(deadline).await, 1);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(request);
+        });
+        let deadline = Instant::now() + Duration::from_secs(5);
+        assert_eq!(lifecycle.drain(deadline).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tasks_and_hooks_run_once() {
+        let lifecycle = Lifecycle::default();
+        let task = tokio::spawn(std::future::pending::<()>());
+        lifecycle.track(task.abort_handle());
+        lifecycle.add_hook(Box::new(|| Ok(())));
+        lifecycle.add_hook(Box::new(|| Err(io::Error::other("disk full"))));
+
+        assert_eq!(lifecycle.abort_tasks(), 1);
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert_eq!(lifecycle.abort_tasks(), 0);
+        assert_eq!(lifecycle.run_hooks().await, 1);
+        assert_eq!(lifecycle.run_hooks().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_drop_stops_tasks() {
+        let lifecycle = Lifecycle::default();
+        let task = tokio::spawn(std::future::pending::<()>());
+        lifecycle.track(task.abort_handle());
+        let cancel = lifecycle.cancel_token();
+
+        drop(lifecycle);
+        assert!(cancel.is_cancelled());
+        assert!(task.await.unwrap_err().is_cancelled());
+    }
+}
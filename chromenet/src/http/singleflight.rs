@@ -0,0 +1,262 @@
+//! Coalescing of identical concurrent requests.
+//!
+//! While a request is in flight, identical requests wait for it instead of
+//! going to the network themselves, and every caller gets its own copy of
+//! the response. Useful in front of caches and for bursts of the same
+//! request.
+//!
+//! The first caller's fetch carries the request. Only if others joined by
+//! the time its response arrives is the body read into memory to share;
+//! alone, the caller gets the response streaming as usual. If the first
+//! caller goes away, e.g. at its deadline, the flight ends and a waiting
+//! caller runs its own fetch.
+//!
+//! A caller that joined gets the response under a
+//! [request id](HttpResponse::request_id) of its own, and
+//! [`Client`](crate::Client) puts its own extensions back on it.
+
+use crate::base::neterror::NetError;
+use crate::base::requestid::RequestId;
+use crate::http::response::{BufferedResponse, HttpResponse};
+use http::{HeaderMap, Method};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use url::Url;
+
+/// Callers waiting for an in-flight request.
+type Waiters = Vec<oneshot::Sender<Result<BufferedResponse, NetError>>>;
+
+/// Identifies requests that may share one network fetch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlightKey {
+    method: Method,
+    /// URL without fragment
+    url: String,
+    /// Hash of the request body and anything else that changes the request
+    /// on the wire
+    request_hash: u64,
+}
+
+impl FlightKey {
+    /// Key for `method` on `url` with `body`.
+    pub fn new(method: &Method, url: &Url, body: &[u8]) -> Self {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        Self {
+            method: method.clone(),
+            url: url.into(),
+            request_hash: hasher.finish(),
+        }
+    }
+
+    /// Also distinguish requests by `headers`.
+    pub fn with_headers(mut self, headers: &HeaderMap) -> Self {
+        let mut hasher = DefaultHasher::new();
+        self.request_hash.hash(&mut hasher);
+        for (name, value) in headers {
+            name.as_str().hash(&mut hasher);
+            value.as_bytes().hash(&mut hasher);
+        }
+        self.request_hash = hasher.finish();
+        self
+    }
+
+    /// Also distinguish requests by an arbitrary value.
+    pub(crate) fn with<T: Hash>(mut self, value: T) -> Self {
+        let mut hasher = DefaultHasher::new();
+        self.request_hash.hash(&mut hasher);
+        value.hash(&mut hasher);
+        self.request_hash = hasher.finish();
+        self
+    }
+}
+
+/// Registry of in-flight requests.
+///
+/// A response that callers share is read fully into memory, so only
+/// coalesce responses of reasonable size.
+#[derive(Clone, Default)]
+pub struct SingleFlight {
+    inflight: Arc<Mutex<HashMap<FlightKey, Waiters>>>,
+}
+
+impl SingleFlight {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of distinct requests currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<FlightKey, Waiters>> {
+        // The map is consistent after every operation, so a panic elsewhere
+        // cannot leave it half-updated
+        self.inflight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run `fetch` unless an identical request is already in flight, in
+    /// which case wait for that one's response instead.
+    ///
+    /// Errors are shared too. If the caller running the request goes away
+    /// before its response arrives, `fetch` runs after all.
+    pub async fn run<F>(&self, key: FlightKey, fetch: F) -> Result<HttpResponse, NetError>
+    where
+        F: Future<Output = Result<HttpResponse, NetError>>,
+    {
+        loop {
+            let joined = {
+                let mut inflight = self.lock();
+                match inflight.get_mut(&key) {
+                    Some(waiters) => {
+                        tracing::trace!(target: "chromenet::http", url = %key.url, "Joining in-flight request");
+                        let (tx, rx) = oneshot::channel();
+                        waiters.push(tx);
+                        rx
+                    }
+                    None => {
+                        inflight.insert(key.clone(), Waiters::new());
+                        break;
+                    }
+                }
+            };
+            match joined.await {
+                Ok(result) => {
+                    return result.map(|shared| {
+                        let id = RequestId::next();
+                        tracing::Span::current().record("request.id", id.get());
+                        HttpResponse::from(shared).with_request_id(id)
+                    })
+                }
+                // The leading caller went away, try again
+                Err(_) => continue,
+            }
+        }
+
+        let mut flight = Leading {
+            flights: self,
+            key: Some(key),
+        };
+        let result = fetch.await;
+        let waiters = flight.finish();
+        if waiters.is_empty() {
+            return result;
+        }
+        let shared = match result {
+            Ok(resp) => resp.into_buffered().await,
+            Err(e) => Err(e),
+        };
+        for waiter in waiters {
+            let _ = waiter.send(shared.clone());
+        }
+        shared.map(HttpResponse::from)
+    }
+}
+
+/// The flight of the caller running the request, ended when the response
+/// arrives or the caller goes away.
+struct Leading<'a> {
+    flights: &'a SingleFlight,
+    key: Option<FlightKey>,
+}
+
+impl Leading<'_> {
+    /// End the flight, returning who joined it.
+    fn finish(&mut self) -> Waiters {
+        self.key
+            .take()
+            .and_then(|key| self.flights.lock().remove(&key))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Leading<'_> {
+    fn drop(&mut self) {
+        // Dropping the waiters wakes them to run their own fetch
+        self.finish();
+    }
+}
+
+impl std::fmt::Debug for SingleFlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SingleFlight")
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ignores_fragment_but_not_body_or_headers() {
+        let url = Url::parse("https://example.com/a#top").unwrap();
+        let same = Url::parse("https://example.com/a").unwrap();
+        let get = FlightKey::new(&Method::GET, &url, b"");
+        assert_eq!(get, FlightKey::new(&Method::GET, &same, b""));
+        assert_ne!(get, FlightKey::new(&Method::HEAD, &same, b""));
+        assert_ne!(get, FlightKey::new(&Method::GET, &same, b"x"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("accept", "text/html".parse().unwrap());
+        assert_ne!(get, get.clone().with_headers(&headers));
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_shared_and_flight_is_cleared() {
+        let flights = SingleFlight::new();
+        let key = FlightKey::new(
+            &Method::GET,
+            &Url::parse("https://example.com/").unwrap(),
+            b"",
+        );
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let leader = flights.run(key.clone(), async move {
+            let _ = rx.await;
+            Err(NetError::ConnectionRefused)
+        });
+        // Never run: the follower joins the leader's fetch
+        let follower = flights.run(key, async { Err(NetError::ConnectionFailed) });
+        let release = async {
+            tokio::task::yield_now().await;
+            let _ = tx.send(());
+        };
+
+        let (a, b, ()) = tokio::join!(leader, follower, release);
+        assert!(matches!(a, Err(NetError::ConnectionRefused)));
+        assert!(matches!(b, Err(NetError::ConnectionRefused)));
+        assert_eq!(flights.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_flight_is_not_joined() {
+        let flights = SingleFlight::new();
+        let key = FlightKey::new(
+            &Method::GET,
+            &Url::parse("https://example.com/").unwrap(),
+            b"",
+        );
+
+        let leader = flights.run(key.clone(), futures::future::pending());
+        let follower = flights.run(key.clone(), async { Err(NetError::ConnectionFailed) });
+        let abandon = tokio::time::timeout(std::time::Duration::from_millis(10), leader);
+        let (abandoned, b) = tokio::join!(abandon, follower);
+        assert!(abandoned.is_err());
+        // The follower ran its own fetch once the leader was gone
+        assert!(matches!(b, Err(NetError::ConnectionFailed)));
+        assert_eq!(flights.in_flight(), 0);
+
+        let later = flights.run(key, async { Err(NetError::ConnectionRefused) });
+        assert!(matches!(later.await, Err(NetError::ConnectionRefused)));
+    }
+}
@@ -2,45 +2,544 @@
 //!
 //! Creates HTTP/1.1 and HTTP/2 streams for network transactions.
 //! Supports H2 multiplexing and browser fingerprint emulation.
+//!
+//! HTTP/2 is normally selected through TLS ALPN. For cleartext origins the
+//! factory can optionally speak h2c, see [`H2cMode`].
+//!
+//! An HTTP/1.1 connection goes back to its pool group once its stream is
+//! dropped and the last response was read to the end, so later requests to
+//! the same destination through the same proxy reuse the socket, tunnel
+//! and TLS sessions included.
 
+use crate::base::host::host_key;
 use crate::base::neterror::NetError;
-use crate::http::h2fingerprint::H2Fingerprint;
-use crate::socket::pool::{ClientSocketPool, PoolResult};
-use bytes::Bytes;
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::emulation::Http1Options;
+use crate::http::h2fingerprint::{ConnWindowUpdate, H2Fingerprint};
+use crate::http::h2grease::GreaseIo;
+use crate::http::h2preface::PrefaceIo;
+use crate::http::h2settings::{PeerSettings, PeerSettingsIo};
+use crate::http::headerlimits::h2_header_list_size;
+use crate::http::requestbody::{BodyWrapper, RequestBody};
+use crate::http::serverproperties::HttpServerProperties;
+#[cfg(feature = "quic")]
+use crate::quic::{race, QuicConnectionBuilder, QuicOutcomes, Raced};
+use crate::socket::nextproto::NextProto;
+use crate::socket::pool::{
+    ClientSocketPool, ConnectionReuse, GroupId, PoolResult, RequestPriority,
+};
+use crate::socket::stream::{BoxedSocket, ConnectionInfo};
+use crate::socket::tls::{AlpnProtocol, SslInfo};
+use crate::socket::wire::WireMeter;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bytes::{BufMut, Bytes, BytesMut};
 use dashmap::DashMap;
-use http::{Request, Response};
+use http::{Method, Request, Response, StatusCode};
 use http2::client;
 use http2::RecvStream;
-use http_body_util::{BodyExt, Full};
+use http_body_util::BodyExt;
 use hyper::body::Incoming;
 use hyper::client::conn::http1;
 use hyper_util::rt::TokioIo;
-use std::sync::Arc;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::task::Poll;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::spawn;
+use tokio::sync::{oneshot, watch};
 use url::Url;
 
-/// Type alias for H2 sender (using http2 crate's forked h2)
+/// Streams a session may open before the server's SETTINGS arrive, like
+/// Chromium's `kDefaultInitialMaxConcurrentStreams`. Without a limit, a
+/// burst sent right after the handshake overshoots a server allowing fewer
+/// and has streams refused.
+const INITIAL_MAX_SEND_STREAMS: usize = 100;
+
+/// Request half of an HTTP/2 session (using http2 crate's forked h2),
+/// counting the streams open on it.
+///
 /// Uses bytes::Bytes as the body type which implements Buf
-type H2Sender = client::SendRequest<Bytes>;
+#[derive(Clone)]
+struct H2Sender {
+    send: client::SendRequest<Bytes>,
+    streams: Arc<SessionStreams>,
+    meter: Option<Arc<WireMeter>>,
+    /// Settings the server announced, read from its frames.
+    peer: Arc<PeerSettings>,
+    /// Akamai fingerprint of the frames the connection opened with, set
+    /// once the first HEADERS is written.
+    fingerprint: Arc<OnceLock<String>>,
+    /// Set on the copy handed to a request until its stream opens.
+    claim: Option<Arc<StreamClaim>>,
+}
+
+/// Stream bookkeeping of an HTTP/2 session.
+struct SessionStreams {
+    open: AtomicUsize,
+    /// Requests given the session whose stream is not open yet.
+    claimed: AtomicUsize,
+    /// Streams ever opened, the requests the connection carried.
+    opened: AtomicU32,
+    /// When the last open stream finished, or the session started.
+    idle_since: std::sync::Mutex<Instant>,
+    /// Tells the connection driver the first request went out, for
+    /// [`ConnWindowUpdate::AfterFirstRequest`].
+    first_request: std::sync::Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl H2Sender {
+    fn new(
+        send: client::SendRequest<Bytes>,
+        meter: Option<Arc<WireMeter>>,
+        first_request: Option<oneshot::Sender<()>>,
+        peer: Arc<PeerSettings>,
+        fingerprint: Arc<OnceLock<String>>,
+    ) -> Self {
+        Self {
+            send,
+            streams: Arc::new(SessionStreams {
+                open: AtomicUsize::new(0),
+                claimed: AtomicUsize::new(0),
+                opened: AtomicU32::new(0),
+                idle_since: std::sync::Mutex::new(Instant::now()),
+                first_request: std::sync::Mutex::new(first_request),
+            }),
+            meter,
+            peer,
+            fingerprint,
+            claim: None,
+        }
+    }
+
+    /// A copy for a request, counted towards the session's load until its
+    /// stream opens.
+    fn claimed(&self) -> Self {
+        self.streams.claimed.fetch_add(1, Ordering::Relaxed);
+        Self {
+            claim: Some(Arc::new(StreamClaim(self.streams.clone()))),
+            ..self.clone()
+        }
+    }
+
+    /// Fail a request whose headers exceed the server's
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`, which the server would refuse.
+    fn check_header_list(&self, req: &Request<BodyWrapper>) -> Result<(), NetError> {
+        let Some(limit) = self.peer.max_header_list_size() else {
+            return Ok(());
+        };
+        let size = h2_header_list_size(req.method(), req.uri(), req.headers());
+        if size > limit as usize {
+            tracing::debug!(target: "chromenet::http", size, limit, "request headers exceed the server's header list limit");
+            return Err(NetError::RequestHeadersTooBig {
+                size,
+                limit: limit as usize,
+            });
+        }
+        Ok(())
+    }
+
+    /// Count a stream as open until the returned guard is dropped.
+    fn open_stream(&mut self) -> OpenStream {
+        self.streams.open.fetch_add(1, Ordering::Relaxed);
+        self.streams.opened.fetch_add(1, Ordering::Relaxed);
+        self.claim = None;
+        OpenStream(self.streams.clone())
+    }
+
+    /// Record that a request's frames were queued on the connection.
+    fn request_sent(&self) {
+        let first = self
+            .streams
+            .first_request
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(first) = first {
+            let _ = first.send(());
+        }
+    }
+
+    /// Streams opened on the session so far.
+    fn requests(&self) -> u32 {
+        self.streams.opened.load(Ordering::Relaxed)
+    }
+
+    /// Since when the session has no open streams, `None` while it has.
+    fn idle_since(&self) -> Option<Instant> {
+        let idle_since = *self
+            .streams
+            .idle_since
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        (self.streams.open.load(Ordering::Relaxed) == 0).then_some(idle_since)
+    }
+
+    /// Open and claimed streams, what [`H2SessionPolicy`] balances.
+    fn load(&self) -> usize {
+        self.streams.open.load(Ordering::Relaxed) + self.streams.claimed.load(Ordering::Relaxed)
+    }
+
+    fn is_same_session(&self, other: &H2Sender) -> bool {
+        Arc::ptr_eq(&self.streams, &other.streams)
+    }
+
+    fn usage(&self) -> H2StreamUsage {
+        H2StreamUsage {
+            open: self.streams.open.load(Ordering::Relaxed),
+            max_concurrent: self.send.current_max_send_streams(),
+        }
+    }
+}
+
+/// A stream counted in [`SessionStreams::open`].
+struct OpenStream(Arc<SessionStreams>);
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        if self.0.open.fetch_sub(1, Ordering::Relaxed) == 1 {
+            *self.0.idle_since.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+        }
+    }
+}
+
+/// A request given a session, counted in [`SessionStreams::claimed`].
+struct StreamClaim(Arc<SessionStreams>);
+
+impl Drop for StreamClaim {
+    fn drop(&mut self) {
+        self.0.claimed.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Streams of an HTTP/2 session, see
+/// [`HttpStreamFactory::h2_stream_usage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct H2StreamUsage {
+    /// Requests sent whose response body was not yet dropped, including
+    /// ones waiting for a stream.
+    pub open: usize,
+    /// Streams the server allows at once (`SETTINGS_MAX_CONCURRENT_STREAMS`).
+    /// Requests beyond it wait for a stream to finish.
+    pub max_concurrent: usize,
+}
+
+/// A cached H2 sender with the TLS details and age of its connection.
+type H2Session = (H2Sender, Option<Arc<SslInfo>>, ConnectionInfo);
+
+/// How many HTTP/2 sessions requests to one destination spread over.
+///
+/// Chromium multiplexes every request to an origin onto one session, which
+/// is the default. A single connection is bound by one TCP window, though,
+/// so large parallel downloads can go faster over a few. With more than one
+/// session allowed, a request goes on the least loaded session with room
+/// and opens another connection once every session has
+/// `max_streams_per_session` streams open, or as many as the server allows.
+/// At `max_sessions`, requests queue on the least loaded session.
+///
+/// Each session holds a socket of its pool group, so sessions beyond the
+/// pool's per-group limit wait for a socket like other connects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct H2SessionPolicy {
+    max_streams_per_session: Option<usize>,
+    max_sessions: usize,
+}
+
+impl Default for H2SessionPolicy {
+    fn default() -> Self {
+        Self {
+            max_streams_per_session: None,
+            max_sessions: 1,
+        }
+    }
+}
+
+impl H2SessionPolicy {
+    /// One session per destination, as in Chromium.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consider a session busy once it has `streams` open, instead of at the
+    /// server's `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    pub fn with_max_streams_per_session(mut self, streams: usize) -> Self {
+        self.max_streams_per_session = Some(streams.max(1));
+        self
+    }
+
+    /// Open up to `sessions` connections to a destination while the others
+    /// are busy.
+    pub fn with_max_sessions(mut self, sessions: usize) -> Self {
+        self.max_sessions = sessions.max(1);
+        self
+    }
+
+    /// Streams `sender` takes before it is busy.
+    fn stream_limit(&self, sender: &H2Sender) -> usize {
+        let server = sender.send.current_max_send_streams();
+        self.max_streams_per_session
+            .map_or(server, |limit| limit.min(server))
+    }
+}
+
+/// How HTTP/2 is negotiated for cleartext (`http://`) origins.
+///
+/// Browsers never speak h2c, so the default keeps plain HTTP on HTTP/1.1.
+/// The other modes are meant for internal services and gRPC backends that
+/// serve HTTP/2 without TLS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum H2cMode {
+    /// Always use HTTP/1.1 for `http://` URLs.
+    #[default]
+    Disabled,
+    /// Send the HTTP/2 connection preface right after TCP connect
+    /// (RFC 9113 Section 3.3).
+    PriorKnowledge,
+    /// Probe with an HTTP/1.1 `Upgrade: h2c` request (RFC 7540 Section 3.2)
+    /// and keep using HTTP/1.1 if the server declines.
+    Upgrade,
+}
+
+/// Which HTTP version a request is allowed to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HttpVersionPref {
+    /// Negotiate through ALPN, preferring HTTP/2 (browser behavior).
+    #[default]
+    Auto,
+    /// Only HTTP/1.1. `h2` is not offered in ALPN and cached HTTP/2
+    /// sessions are not used.
+    Http1Only,
+    /// HTTP/2 without negotiation: h2c prior knowledge on `http://`, and
+    /// `h2` as the only ALPN protocol on `https://`.
+    Http2PriorKnowledge,
+    /// Only HTTP/3.
+    Http3Only,
+}
+
+impl HttpVersionPref {
+    /// ALPN protocols to offer instead of the configured list, if any.
+    fn alpn_override(self) -> Option<&'static [AlpnProtocol]> {
+        match self {
+            HttpVersionPref::Http1Only => Some(&[AlpnProtocol::HTTP1]),
+            HttpVersionPref::Http2PriorKnowledge => Some(&[AlpnProtocol::HTTP2]),
+            HttpVersionPref::Auto | HttpVersionPref::Http3Only => None,
+        }
+    }
+}
+
+/// Map an http2 error, surfacing the reasons that call for an HTTP/1.1
+/// fallback as their own errors.
+fn map_h2_error(e: &http2::Error, default: NetError) -> NetError {
+    match e.reason() {
+        Some(http2::Reason::HTTP_1_1_REQUIRED) => NetError::Http11Required,
+        Some(http2::Reason::INADEQUATE_SECURITY) => NetError::Http2InadequateTransportSecurity,
+        Some(http2::Reason::PROTOCOL_ERROR) if e.is_go_away() => NetError::Http2ProtocolError,
+        Some(http2::Reason::FRAME_SIZE_ERROR) => NetError::Http2FrameSizeError,
+        Some(http2::Reason::COMPRESSION_ERROR) => NetError::Http2CompressionError,
+        Some(http2::Reason::REFUSED_STREAM) => NetError::Http2ServerRefusedStream,
+        _ => default,
+    }
+}
+
+/// Map a hyper HTTP/1.1 error to the closest network error.
+///
+/// hyper rejects a response whose `Content-Length` values conflict while
+/// parsing the head, but does not expose the parse error kind, so the
+/// error is recognized by its message. Chunked decoding failures surface
+/// as I/O errors of kind `InvalidInput` or `InvalidData`, which hyper only
+/// produces for chunked bodies.
+pub(crate) fn map_h1_error(e: &hyper::Error, default: NetError) -> NetError {
+    if e.is_parse() && e.to_string().contains("content-length") {
+        return NetError::ResponseHeadersMultipleContentLength;
+    }
+    let io_error = std::error::Error::source(e).and_then(|s| s.downcast_ref::<std::io::Error>());
+    match io_error.map(|e| e.kind()) {
+        Some(std::io::ErrorKind::InvalidInput | std::io::ErrorKind::InvalidData) => {
+            NetError::InvalidChunkedEncoding
+        }
+        _ => default,
+    }
+}
+
+/// Reject HTTP/1.1 response framing that is open to response smuggling.
+///
+/// Like Chromium, differing `Content-Length` values are an error while
+/// repeated identical ones are not. Unlike Chromium, which lets
+/// `Transfer-Encoding` win, a response carrying both `Transfer-Encoding`
+/// and `Content-Length` is rejected outright.
+fn check_h1_framing(headers: &http::HeaderMap) -> Result<(), NetError> {
+    let mut lengths = headers
+        .get_all(http::header::CONTENT_LENGTH)
+        .iter()
+        .flat_map(|value| value.as_bytes().split(|&b| b == b','))
+        .map(|value| value.trim_ascii());
+    let Some(first) = lengths.next() else {
+        return Ok(());
+    };
+    if lengths.any(|value| value != first) {
+        return Err(NetError::ResponseHeadersMultipleContentLength);
+    }
+    if headers.contains_key(http::header::TRANSFER_ENCODING) {
+        tracing::debug!("response has both Transfer-Encoding and Content-Length");
+        return Err(NetError::InvalidResponse);
+    }
+    Ok(())
+}
 
 /// HTTP response body enum that abstracts over H1 and H2 body types
 pub enum StreamBody {
     H1(Incoming),
-    H2(RecvStream),
+    H2(H2Body),
+}
+
+/// Send half of an HTTP/2 stream, shared between the request body upload
+/// and the response body so either side can reset the stream.
+type SharedSendStream = Arc<std::sync::Mutex<Option<http2::SendStream<Bytes>>>>;
+
+/// Response half of an HTTP/2 stream.
+///
+/// Dropping it before the end of the stream resets the stream with
+/// `CANCEL`, so the server stops sending and the unread data does not use
+/// up the connection's flow control window.
+pub struct H2Body {
+    recv: RecvStream,
+    send: SharedSendStream,
+    _open: OpenStream,
+}
+
+impl H2Body {
+    /// The stream's data frames.
+    pub fn recv_stream(&mut self) -> &mut RecvStream {
+        &mut self.recv
+    }
+
+    /// Whether the server has finished sending.
+    pub fn is_end_stream(&self) -> bool {
+        self.recv.is_end_stream()
+    }
+
+    /// Send `RST_STREAM` with `reason`, also aborting a request body still
+    /// being uploaded. Does nothing once the stream was reset.
+    pub fn reset(&mut self, reason: http2::Reason) {
+        if let Some(mut send) = self.send.lock().unwrap().take() {
+            tracing::debug!(stream_id = ?send.stream_id(), ?reason, "resetting H2 stream");
+            send.send_reset(reason);
+        }
+    }
+}
+
+impl Drop for H2Body {
+    fn drop(&mut self) {
+        if !self.recv.is_end_stream() {
+            self.reset(http2::Reason::CANCEL);
+        }
+    }
 }
 
 /// Wraps the underlying protocol stream (H1/H2).
 pub struct HttpStream {
     inner: HttpStreamInner,
     is_reused: bool,
+    negotiated_protocol: NextProto,
+    ssl_info: Option<Arc<SslInfo>>,
+    meter: Option<Arc<WireMeter>>,
 }
 
 enum HttpStreamInner {
-    // H1 sender now uses Full<Bytes> for body support
-    H1(http1::SendRequest<Full<Bytes>>),
+    H1(H1Sender),
     H2(H2Sender),
 }
 
+type H1Connection = http1::Connection<TokioIo<BoxedSocket>, BodyWrapper>;
+
+/// Request half of an HTTP/1.1 connection.
+///
+/// Dropping it hands the sender to the connection task, which returns the
+/// socket to the pool once the connection is idle again.
+struct H1Sender {
+    sender: Option<http1::SendRequest<BodyWrapper>>,
+    release: Option<oneshot::Sender<http1::SendRequest<BodyWrapper>>>,
+}
+
+impl H1Sender {
+    /// Sender whose connection is not pooled, e.g. after an h2c probe.
+    fn unpooled(sender: http1::SendRequest<BodyWrapper>) -> Self {
+        Self {
+            sender: Some(sender),
+            release: None,
+        }
+    }
+
+    fn get(&mut self) -> &mut http1::SendRequest<BodyWrapper> {
+        self.sender
+            .as_mut()
+            .expect("H1 sender is only taken on drop")
+    }
+}
+
+impl Drop for H1Sender {
+    fn drop(&mut self) {
+        if let (Some(sender), Some(release)) = (self.sender.take(), self.release.take()) {
+            let _ = release.send(sender);
+        }
+    }
+}
+
+/// Drive an HTTP/1.1 connection, then return its socket to `group_id` if
+/// it can carry another request, or discard it.
+async fn run_h1_connection(
+    mut conn: H1Connection,
+    release: oneshot::Receiver<http1::SendRequest<BodyWrapper>>,
+    pool: Arc<ClientSocketPool>,
+    group_id: GroupId,
+    keep_alive: bool,
+) {
+    if wait_h1_idle(&mut conn, release).await && keep_alive {
+        let parts = conn.into_parts();
+        // Bytes the server sent past the response would corrupt the next one
+        if parts.read_buf.is_empty() {
+            tracing::trace!(host = group_id.host(), proxy = ?group_id.proxy(), "releasing H1 socket");
+            pool.release_socket_to_group(&group_id, parts.io.into_inner(), false);
+            return;
+        }
+    }
+    pool.discard_socket_from_group(&group_id);
+}
+
+/// Whether the connection is idle after its stream was dropped: the last
+/// response was read to the end and the server kept the connection alive.
+///
+/// hyper only becomes ready for another request in that state; a closed or
+/// failed connection finishes instead.
+async fn wait_h1_idle(
+    conn: &mut H1Connection,
+    mut release: oneshot::Receiver<http1::SendRequest<BodyWrapper>>,
+) -> bool {
+    let mut sender = None;
+    poll_fn(|cx| {
+        if let Poll::Ready(result) = conn.poll_without_shutdown(cx) {
+            if let Err(e) = result {
+                tracing::debug!("H1 connection error: {:?}", e);
+            }
+            return Poll::Ready(false);
+        }
+        if sender.is_none() {
+            match Pin::new(&mut release).poll(cx) {
+                Poll::Ready(Ok(released)) => sender = Some(released),
+                Poll::Ready(Err(_)) => return Poll::Ready(false),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        sender.as_mut().map_or(Poll::Ready(false), |sender| {
+            sender.poll_ready(cx).map(|r| r.is_ok())
+        })
+    })
+    .await
+}
+
 impl HttpStream {
     pub fn is_h2(&self) -> bool {
         matches!(self.inner, HttpStreamInner::H2(_))
@@ -50,112 +549,465 @@ impl HttpStream {
         self.is_reused
     }
 
+    /// Protocol negotiated via ALPN on the underlying connection.
+    pub fn negotiated_protocol(&self) -> NextProto {
+        self.negotiated_protocol
+    }
+
+    /// TLS details of the underlying connection.
+    pub fn ssl_info(&self) -> Option<&SslInfo> {
+        self.ssl_info.as_deref()
+    }
+
+    /// Akamai fingerprint of the frames the HTTP/2 connection opened
+    /// with, see [`crate::http::identity`]. `None` over HTTP/1 and until
+    /// the connection's first request is written.
+    pub(crate) fn h2_fingerprint(&self) -> Option<&str> {
+        match &self.inner {
+            HttpStreamInner::H2(session) => session.fingerprint.get().map(String::as_str),
+            _ => None,
+        }
+    }
+
     /// Send an HTTP request with a body and get the response.
     ///
-    /// For H1, uses hyper's body types with Full<Bytes>.
-    /// For H2, uses http2 crate's API, sending body via SendStream if non-empty.
+    /// For H1, hyper streams the body itself.
+    /// For H2, the body is written to the SendStream as flow control allows,
+    /// while the response is awaited.
+    ///
+    /// The negotiated protocol and TLS details are attached to the response
+    /// extensions, and with byte counting the request's
+    /// [`RequestBytes`](crate::socket::wire::RequestBytes).
     pub async fn send_request(
         &mut self,
-        req: Request<Full<Bytes>>,
+        req: Request<BodyWrapper>,
+    ) -> Result<Response<StreamBody>, NetError> {
+        let bytes = self.meter.as_ref().map(|meter| match self.inner {
+            HttpStreamInner::H1(_) => meter.begin_request(),
+            HttpStreamInner::H2(_) => meter.begin_shared_request(),
+        });
+        let mut resp = self.send_request_inner(req).await?;
+        resp.extensions_mut().insert(self.negotiated_protocol);
+        if let Some(info) = &self.ssl_info {
+            resp.extensions_mut().insert(info.clone());
+        }
+        if let Some(bytes) = bytes {
+            resp.extensions_mut().insert(bytes);
+        }
+        Ok(resp)
+    }
+
+    async fn send_request_inner(
+        &mut self,
+        req: Request<BodyWrapper>,
     ) -> Result<Response<StreamBody>, NetError> {
         match &mut self.inner {
             HttpStreamInner::H1(sender) => {
-                let resp = sender.send_request(req).await.map_err(|e| {
+                let resp = sender.get().send_request(req).await.map_err(|e| {
                     tracing::debug!("H1 request error: {:?}", e);
-                    NetError::ConnectionClosed
+                    map_h1_error(&e, NetError::ConnectionClosed)
                 })?;
+                check_h1_framing(resp.headers())?;
                 Ok(resp.map(StreamBody::H1))
             }
-            HttpStreamInner::H2(sender) => {
+            HttpStreamInner::H2(session) => {
+                session.check_header_list(&req)?;
+                let open = session.open_stream();
                 // Clone sender because ready() consumes it
-                let sender = sender.clone();
+                let sender = session.send.clone();
 
-                // Wait for the connection to be ready
+                // Wait for the connection to be ready. Past the server's
+                // stream limit, the request is queued by the connection
                 let mut ready_sender = sender.ready().await.map_err(|e| {
                     tracing::debug!("H2 ready error: {:?}", e);
                     NetError::ConnectionFailed
                 })?;
 
-                // Extract body using BodyExt
                 let (parts, body) = req.into_parts();
-                let body_bytes = body
-                    .collect()
-                    .await
-                    .map_err(|_| NetError::ConnectionClosed)?
-                    .to_bytes();
-                let has_body = !body_bytes.is_empty();
+                let has_body = !http_body::Body::is_end_stream(&body);
 
                 // Create H2 request
                 let req_h2 = Request::from_parts(parts, ());
 
                 // Send request - end_of_stream = true only if no body
-                let (response_fut, mut send_stream) =
+                let (response_fut, send_stream) =
                     ready_sender.send_request(req_h2, !has_body).map_err(|e| {
                         tracing::debug!("H2 send_request error: {:?}", e);
                         NetError::ConnectionFailed
                     })?;
+                session.request_sent();
+                let send_stream: SharedSendStream =
+                    Arc::new(std::sync::Mutex::new(Some(send_stream)));
 
-                // Send body data if present
-                if has_body {
-                    send_stream.send_data(body_bytes, true).map_err(|e| {
-                        tracing::debug!("H2 send_data error: {:?}", e);
-                        NetError::ConnectionFailed
-                    })?;
+                // Await the response while the body is sent
+                let resp = if has_body {
+                    let mut upload = Box::pin(send_h2_body(send_stream.clone(), body));
+                    let mut uploaded = false;
+                    tokio::pin!(response_fut);
+                    loop {
+                        tokio::select! {
+                            result = &mut upload, if !uploaded => {
+                                result?;
+                                uploaded = true;
+                            }
+                            resp = &mut response_fut => {
+                                if !uploaded {
+                                    // The server answered early, finish the upload in the background
+                                    spawn(async move {
+                                        if let Err(e) = upload.await {
+                                            tracing::debug!("H2 upload error: {:?}", e);
+                                        }
+                                    });
+                                }
+                                break resp;
+                            }
+                        }
+                    }
+                } else {
+                    response_fut.await
                 }
-
-                // Await the response
-                let resp = response_fut.await.map_err(|e| {
+                .map_err(|e| {
                     tracing::debug!("H2 response error: {:?}", e);
-                    NetError::ConnectionClosed
+                    map_h2_error(&e, NetError::ConnectionClosed)
                 })?;
 
                 // Convert to our response type
-                let (parts, recv_stream) = resp.into_parts();
-                Ok(Response::from_parts(parts, StreamBody::H2(recv_stream)))
+                let (parts, recv) = resp.into_parts();
+                let body = H2Body {
+                    recv,
+                    send: send_stream,
+                    _open: open,
+                };
+                Ok(Response::from_parts(parts, StreamBody::H2(body)))
             }
         }
     }
 }
 
+/// Write a request body to an HTTP/2 stream, respecting flow control.
+///
+/// Each step locks the send stream only briefly, so a reset through the
+/// stream's [`H2Body`] can get in between; the upload then fails with
+/// [`NetError::Aborted`].
+async fn send_h2_body(
+    send_stream: SharedSendStream,
+    mut body: BodyWrapper,
+) -> Result<(), NetError> {
+    while let Some(frame) = body.frame().await {
+        let mut data = match frame.map(|f| f.into_data()) {
+            Ok(Ok(data)) => data,
+            Ok(Err(_)) => continue,
+            Err(e) => {
+                if let Some(mut stream) = send_stream.lock().unwrap().take() {
+                    stream.send_reset(http2::Reason::CANCEL);
+                }
+                return Err(e);
+            }
+        };
+        while !data.is_empty() {
+            with_send_stream(&send_stream, |stream| {
+                stream.reserve_capacity(data.len());
+                Ok(())
+            })?;
+            let capacity = std::future::poll_fn(|cx| match send_stream.lock().unwrap().as_mut() {
+                Some(stream) => stream
+                    .poll_capacity(cx)
+                    .map(|capacity| capacity.ok_or(NetError::ConnectionClosed)),
+                None => std::task::Poll::Ready(Err(NetError::Aborted)),
+            })
+            .await?
+            .map_err(h2_send_error)?;
+            if capacity == 0 {
+                continue;
+            }
+            let chunk = data.split_to(capacity.min(data.len()));
+            with_send_stream(&send_stream, |stream| stream.send_data(chunk, false))?;
+        }
+    }
+
+    with_send_stream(&send_stream, |stream| stream.send_data(Bytes::new(), true))
+}
+
+/// Run `f` on the send stream unless it was reset.
+fn with_send_stream<T>(
+    send_stream: &SharedSendStream,
+    f: impl FnOnce(&mut http2::SendStream<Bytes>) -> Result<T, http2::Error>,
+) -> Result<T, NetError> {
+    match send_stream.lock().unwrap().as_mut() {
+        Some(stream) => f(stream).map_err(h2_send_error),
+        None => Err(NetError::Aborted),
+    }
+}
+
+fn h2_send_error(e: http2::Error) -> NetError {
+    tracing::debug!("H2 send_data error: {:?}", e);
+    NetError::ConnectionFailed
+}
+
 /// HTTP/2 session cache for multiplexing.
-/// Stores active H2 senders by host:port key for reuse.
+/// Stores active H2 senders by connection group for reuse, along with the
+/// TLS details of their connection. Keying by group keeps sessions through
+/// different proxies or for different isolation keys apart.
 struct H2SessionCache {
-    sessions: DashMap<(String, u16), H2Sender>,
+    sessions: DashMap<GroupId, Vec<H2Session>>,
+    /// Groups with a connection being set up that may bring a session.
+    /// Waiters subscribe to the sender, which is dropped once it is done.
+    connecting: DashMap<GroupId, watch::Sender<()>>,
+}
+
+/// Claim on setting up the connection of a group, released on drop.
+struct ConnectClaim<'a> {
+    cache: &'a H2SessionCache,
+    group_id: GroupId,
+}
+
+impl Drop for ConnectClaim<'_> {
+    fn drop(&mut self) {
+        self.cache.connecting.remove(&self.group_id);
+    }
 }
 
 impl H2SessionCache {
     fn new() -> Self {
         Self {
             sessions: DashMap::new(),
+            connecting: DashMap::new(),
         }
     }
 
-    /// Get session key from URL
-    fn key(url: &Url) -> Option<(String, u16)> {
-        Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+    /// Claim setting up a connection for `group_id`, or get notified when
+    /// the request that claimed it is done.
+    fn claim_connect(&self, group_id: &GroupId) -> Result<ConnectClaim<'_>, watch::Receiver<()>> {
+        match self.connecting.entry(group_id.clone()) {
+            dashmap::mapref::entry::Entry::Occupied(entry) => Err(entry.get().subscribe()),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(watch::channel(()).0);
+                Ok(ConnectClaim {
+                    cache: self,
+                    group_id: group_id.clone(),
+                })
+            }
+        }
     }
 
-    /// Get an existing H2 sender if available and ready
-    fn get(&self, url: &Url) -> Option<H2Sender> {
-        let key = Self::key(url)?;
-        let entry = self.sessions.get(&key)?;
-        Some(entry.value().clone())
+    /// Pick a session of `group_id` for a request and claim a stream on
+    /// it, or `None` if there is none or `policy` wants another connection.
+    fn pick(&self, group_id: &GroupId, policy: &H2SessionPolicy) -> Option<H2Session> {
+        // Claimed under the shard lock, so a burst of requests spreads
+        let entry = self.sessions.get_mut(group_id)?;
+        let load = |(sender, ..): &&H2Session| sender.load();
+        let available = entry
+            .iter()
+            .filter(|(sender, ..)| sender.load() < policy.stream_limit(sender))
+            .min_by_key(load);
+        let (sender, ssl_info, info) = match available {
+            Some(session) => session,
+            None if entry.len() < policy.max_sessions => return None,
+            None => entry.iter().min_by_key(load)?,
+        };
+        Some((sender.claimed(), ssl_info.clone(), *info))
+    }
+
+    fn has_sessions(&self, group_id: &GroupId) -> bool {
+        self.sessions.contains_key(group_id)
     }
 
     /// Store an H2 sender for reuse
-    fn store(&self, url: &Url, sender: H2Sender) {
-        if let Some(key) = Self::key(url) {
-            self.sessions.insert(key, sender);
+    fn store(&self, group_id: &GroupId, session: H2Session) {
+        self.sessions
+            .entry(group_id.clone())
+            .or_default()
+            .push(session);
+    }
+
+    /// Record a lookup that confirmed the address of the session
+    /// established at `info.connected_at`.
+    fn confirm(&self, group_id: &GroupId, info: &ConnectionInfo) {
+        if let Some(mut entry) = self.sessions.get_mut(group_id) {
+            for session in entry.iter_mut() {
+                if session.2.connected_at == info.connected_at {
+                    session.2.resolved_at = info.resolved_at;
+                }
+            }
         }
     }
 
-    /// Remove a session (on connection error)
-    #[allow(dead_code)]
-    fn remove(&self, url: &Url) {
-        if let Some(key) = Self::key(url) {
-            self.sessions.remove(&key);
+    /// Remove the session of `sender`, returning whether it was cached.
+    fn remove_session(&self, group_id: &GroupId, sender: &H2Sender) -> bool {
+        let Some(mut entry) = self.sessions.get_mut(group_id) else {
+            return false;
+        };
+        let before = entry.len();
+        entry.retain(|(cached, ..)| !cached.is_same_session(sender));
+        let removed = entry.len() < before;
+        drop(entry);
+        self.sessions
+            .remove_if(group_id, |_, sessions| sessions.is_empty());
+        removed
+    }
+
+    /// Remove the sessions of a group (on connection error), returning how
+    /// many there were.
+    fn remove(&self, group_id: &GroupId) -> usize {
+        self.sessions
+            .remove(group_id)
+            .map_or(0, |(_, sessions)| sessions.len())
+    }
+
+    /// Remove the sessions of the groups `matches` picks, returning each
+    /// group with how many it had.
+    fn remove_groups(&self, matches: impl Fn(&GroupId) -> bool) -> Vec<(GroupId, usize)> {
+        let groups: Vec<GroupId> = self
+            .sessions
+            .iter()
+            .filter(|entry| matches(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        groups
+            .into_iter()
+            .map(|group_id| {
+                let sessions = self.remove(&group_id);
+                (group_id, sessions)
+            })
+            .collect()
+    }
+
+    /// Remove all sessions, returning how many there were.
+    fn clear(&self) -> usize {
+        let count = self.sessions.iter().map(|entry| entry.len()).sum();
+        self.sessions.clear();
+        count
+    }
+}
+
+/// Build a hyper HTTP/1.1 client builder from the emulation options.
+///
+/// hyper can only write header names lowercase or in Title-Case, so
+/// `title_case_headers` is the closest match to a browser's casing.
+fn h1_builder(options: Option<&Http1Options>) -> http1::Builder {
+    let mut builder = http1::Builder::new();
+    if let Some(options) = options {
+        builder.title_case_headers(options.title_case_headers);
+    }
+    builder
+}
+
+/// Build an http2 client builder carrying the fingerprint settings.
+fn h2_builder(fp: &H2Fingerprint) -> client::Builder {
+    let mut builder = client::Builder::new();
+    builder.initial_max_send_streams(INITIAL_MAX_SEND_STREAMS);
+
+    // Apply window sizes
+    builder.initial_window_size(fp.initial_window_size);
+    if fp.conn_window_update == ConnWindowUpdate::AfterSettings {
+        builder.initial_connection_window_size(fp.initial_conn_window_size);
+    }
+
+    // Apply frame limits
+    if let Some(max_frame) = fp.max_frame_size {
+        builder.max_frame_size(max_frame);
+    }
+    if let Some(max_streams) = fp.max_concurrent_streams {
+        builder.max_concurrent_streams(max_streams);
+    }
+    if let Some(max_header_list) = fp.max_header_list_size {
+        builder.max_header_list_size(max_header_list);
+    }
+    if let Some(header_table_size) = fp.header_table_size {
+        builder.header_table_size(header_table_size);
+    }
+
+    // Apply pseudo-header order (critical for fingerprinting)
+    // Note: pseudo_order is set per-request, not on the connection builder
+    // if let Some(order) = &fp.pseudo_order { ... }
+
+    // Apply settings order (critical for fingerprinting)
+    if let Some(order) = &fp.settings_order {
+        builder.settings_order(order.clone());
+    }
+
+    // Apply priority frames (priorities sent after connection)
+    // Note: priorities are sent asynchronously after handshake
+    // if let Some(ref priorities) = fp.priorities { ... }
+
+    // Apply push/connect protocol settings
+    if let Some(enable_push) = fp.enable_push {
+        builder.enable_push(enable_push);
+    }
+    if let Some(enable_connect) = fp.enable_connect_protocol {
+        builder.enable_connect_protocol(enable_connect);
+    }
+    if let Some(no_priorities) = fp.no_rfc7540_priorities {
+        builder.no_rfc7540_priorities(no_priorities);
+    }
+
+    builder
+}
+
+/// Connection window to raise to once the first request went out, for
+/// [`ConnWindowUpdate::AfterFirstRequest`].
+fn deferred_conn_window(fp: &H2Fingerprint) -> Option<u32> {
+    (fp.conn_window_update == ConnWindowUpdate::AfterFirstRequest)
+        .then_some(fp.initial_conn_window_size)
+}
+
+/// Drive an HTTP/2 connection until it closes.
+///
+/// With a deferred window, the WINDOW_UPDATE is queued only once the
+/// connection wrote the frames of the first request: they are queued
+/// before `first_request` fires, and http2 writes WINDOW_UPDATE frames
+/// ahead of everything else queued at the same time.
+async fn drive_h2<T>(
+    mut conn: client::Connection<T, Bytes>,
+    deferred_window: Option<(u32, oneshot::Receiver<()>)>,
+) -> Result<(), http2::Error>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let Some((size, mut first_request)) = deferred_window else {
+        return conn.await;
+    };
+    let mut raised = false;
+    poll_fn(|cx| {
+        if let Poll::Ready(result) = Pin::new(&mut conn).poll(cx) {
+            return Poll::Ready(result);
+        }
+        if !raised && Pin::new(&mut first_request).poll(cx).is_ready() {
+            raised = true;
+            conn.set_target_window_size(size);
+            return Pin::new(&mut conn).poll(cx);
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Encode the `HTTP2-Settings` header value for an h2c upgrade.
+///
+/// The value is the base64url payload of the SETTINGS frame the client
+/// will send in its preface (RFC 7540 Section 3.2.1).
+fn http2_settings_header(fp: &H2Fingerprint) -> String {
+    let settings = [
+        (0x1u16, fp.header_table_size),
+        (0x2, fp.enable_push.map(u32::from)),
+        (0x3, fp.max_concurrent_streams),
+        (0x4, Some(fp.initial_window_size)),
+        (0x5, fp.max_frame_size),
+        (0x6, fp.max_header_list_size),
+        (
+            fp.grease_setting.map_or(0, |s| s.id),
+            fp.grease_setting.map(|s| s.value),
+        ),
+    ];
+
+    let mut payload = BytesMut::with_capacity(settings.len() * 6);
+    for (id, value) in settings {
+        if let Some(value) = value {
+            payload.put_u16(id);
+            payload.put_u32(value);
         }
     }
+    URL_SAFE_NO_PAD.encode(&payload)
 }
 
 /// Factory for creating HTTP streams.
@@ -165,6 +1017,11 @@ impl H2SessionCache {
 pub struct HttpStreamFactory {
     pool: Arc<ClientSocketPool>,
     h2_cache: H2SessionCache,
+    h2_policy: H2SessionPolicy,
+    h2c_mode: H2cMode,
+    server_properties: HttpServerProperties,
+    #[cfg(feature = "quic")]
+    quic: Option<QuicOutcomes>,
 }
 
 impl HttpStreamFactory {
@@ -172,124 +1029,673 @@ impl HttpStreamFactory {
         Self {
             pool,
             h2_cache: H2SessionCache::new(),
+            h2_policy: H2SessionPolicy::default(),
+            h2c_mode: H2cMode::Disabled,
+            server_properties: HttpServerProperties::default(),
+            #[cfg(feature = "quic")]
+            quic: None,
         }
     }
 
+    /// Use `properties` to remember origins that require HTTP/1.1.
+    pub fn with_server_properties(mut self, properties: HttpServerProperties) -> Self {
+        self.server_properties = properties;
+        self
+    }
+
+    /// Per-origin properties learned from earlier requests.
+    pub fn server_properties(&self) -> &HttpServerProperties {
+        &self.server_properties
+    }
+
+    /// Set how HTTP/2 is negotiated for `http://` URLs.
+    pub fn with_h2c_mode(mut self, mode: H2cMode) -> Self {
+        self.h2c_mode = mode;
+        self
+    }
+
+    /// How HTTP/2 is negotiated for `http://` URLs.
+    pub fn h2c_mode(&self) -> H2cMode {
+        self.h2c_mode
+    }
+
+    /// Spread requests to a destination over HTTP/2 sessions as `policy`
+    /// says.
+    pub fn with_h2_session_policy(mut self, policy: H2SessionPolicy) -> Self {
+        self.h2_policy = policy;
+        self
+    }
+
+    /// How requests to a destination spread over HTTP/2 sessions.
+    pub fn h2_session_policy(&self) -> H2SessionPolicy {
+        self.h2_policy
+    }
+
+    /// Race a QUIC handshake against each new TCP connection to an
+    /// `https://` origin, remembering the outcomes in `outcomes`.
+    ///
+    /// Inert until the QUIC transport exists: every handshake fails and
+    /// the connection goes over TCP.
+    #[cfg(feature = "quic")]
+    pub fn with_quic_outcomes(mut self, outcomes: QuicOutcomes) -> Self {
+        self.quic = Some(outcomes);
+        self
+    }
+
+    /// Outcomes of the QUIC races so far, `None` without racing.
+    #[cfg(feature = "quic")]
+    pub fn quic_outcomes(&self) -> Option<&QuicOutcomes> {
+        self.quic.as_ref()
+    }
+
     /// Create an HTTP stream for the given URL.
     ///
     /// For HTTP/2, applies the fingerprint settings during handshake
     /// including pseudo-header order, settings order, and priority frames.
+    /// For HTTP/1.1, `http1_options` controls how header names are written.
+    /// `version` restricts which protocol may be used; a server that cannot
+    /// comply fails the request instead of silently downgrading. With
+    /// [`HttpVersionPref::Auto`], origins known to require HTTP/1.1 get it.
+    /// Connections are only shared with requests using the same `proxy` and
+    /// `nik`.
     pub async fn create_stream(
         &self,
         url: &Url,
         proxy: Option<&crate::socket::proxy::ProxySettings>,
+        nik: Option<&NetworkIsolationKey>,
         h2_fingerprint: Option<&H2Fingerprint>,
+        http1_options: Option<&Http1Options>,
+        version: HttpVersionPref,
     ) -> Result<HttpStream, NetError> {
-        // 1. Check H2 session cache for multiplexing (if HTTPS/H2)
-        if url.scheme() == "https" {
-            if let Some(sender) = self.h2_cache.get(url) {
-                // Reuse existing H2 connection (multiplexing!)
-                return Ok(HttpStream {
-                    inner: HttpStreamInner::H2(sender),
-                    is_reused: true,
-                });
+        let group_id = GroupId::new(url, proxy, nik).ok_or(NetError::InvalidUrl)?;
+        self.create_stream_for_group(
+            &group_id,
+            url,
+            proxy,
+            h2_fingerprint,
+            http1_options,
+            version,
+            RequestPriority::default(),
+            ConnectionReuse::Allowed,
+        )
+        .await
+    }
+
+    /// Get a socket from `tcp` or a QUIC connection to the origin of `url`,
+    /// whichever connects first, like Chromium's `JobController`.
+    #[cfg(feature = "quic")]
+    async fn race_quic(
+        &self,
+        outcomes: &QuicOutcomes,
+        url: &Url,
+        tcp: impl std::future::Future<Output = Result<PoolResult, NetError>>,
+    ) -> Result<PoolResult, NetError> {
+        let quic = async {
+            QuicConnectionBuilder::new()
+                .url(url.as_str())?
+                .connect()
+                .await
+        };
+        let (result, quic_result) = race(quic, tcp, outcomes.tcp_delay(url)).await;
+        tracing::debug!(url = %url, ?quic_result, "QUIC race");
+        outcomes.record(url, quic_result);
+        match result? {
+            Raced::Tcp(pool_result) => Ok(pool_result),
+            // No HTTP/3 streams before the QUIC transport (see crate::quic)
+            Raced::Quic(_) => Err(NetError::NotImplemented),
+        }
+    }
+
+    /// Create an HTTP stream on a connection of `group_id`, which must have
+    /// been built from `url` and `proxy`.
+    ///
+    /// Works like [`Self::create_stream`], but honors the group's
+    /// [`ServerName`](crate::socket::tls::ServerName). `priority` orders
+    /// the request among others waiting for a socket of the group.
+    ///
+    /// The stream goes on a live HTTP/2 session of the group if there is one
+    /// the [`H2SessionPolicy`] lets it use, waiting for a connection already
+    /// being set up that may bring one;
+    /// else on the most recently used idle socket; else on a new
+    /// connection. With [`ConnectionReuse::Fresh`] it always goes on a new
+    /// connection; with [`ConnectionReuse::Close`] that connection is not
+    /// used again.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_stream_for_group(
+        &self,
+        group_id: &GroupId,
+        url: &Url,
+        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        h2_fingerprint: Option<&H2Fingerprint>,
+        http1_options: Option<&Http1Options>,
+        version: HttpVersionPref,
+        priority: RequestPriority,
+        reuse: ConnectionReuse,
+    ) -> Result<HttpStream, NetError> {
+        if version == HttpVersionPref::Http3Only {
+            // No QUIC transport yet (see crate::quic)
+            tracing::debug!(url = %url, "HTTP/3 required but not available");
+            return Err(NetError::NotImplemented);
+        }
+        let version =
+            if version == HttpVersionPref::Auto && self.server_properties.requires_http11(url) {
+                HttpVersionPref::Http1Only
+            } else {
+                version
+            };
+
+        let h2c_mode = match version {
+            HttpVersionPref::Http1Only => H2cMode::Disabled,
+            HttpVersionPref::Http2PriorKnowledge => H2cMode::PriorKnowledge,
+            _ => self.h2c_mode,
+        };
+        let h2c = url.scheme() == "http" && h2c_mode != H2cMode::Disabled;
+
+        // 1. Check H2 session cache for multiplexing (if HTTPS/H2 or h2c)
+        let mut _claim = None;
+        if (url.scheme() == "https" || h2c)
+            && version != HttpVersionPref::Http1Only
+            && reuse == ConnectionReuse::Allowed
+        {
+            let mut waited = false;
+            loop {
+                if let Some(stream) = self.reuse_h2_session(group_id, h2c).await {
+                    return Ok(stream);
+                }
+                // A connection that brought no session spoke HTTP/1.1 or
+                // failed, the waiters connect in parallel. Requests finding
+                // every session busy take turns opening the next one
+                if waited && !self.h2_cache.has_sessions(group_id) {
+                    break;
+                }
+                // Like Chromium's SpdySessionPool, wait for a connection
+                // already being set up for the group instead of racing it:
+                // if it brings an H2 session, the request multiplexes onto it
+                match self.h2_cache.claim_connect(group_id) {
+                    Ok(claim) => {
+                        _claim = Some(claim);
+                        break;
+                    }
+                    Err(mut connecting) => {
+                        let _ = connecting.changed().await;
+                        waited = true;
+                    }
+                }
             }
         }
 
         // 2. Get socket from pool
-        let pool_result: PoolResult = self.pool.request_socket(url, proxy).await?;
+        let alpn = version.alpn_override().filter(|_| url.scheme() == "https");
+        let tcp = self
+            .pool
+            .request_socket_for_group(group_id, url, proxy, alpn, priority, reuse);
+        #[cfg(feature = "quic")]
+        let mut pool_result: PoolResult = match &self.quic {
+            Some(outcomes)
+                if url.scheme() == "https"
+                    && proxy.is_none()
+                    && version == HttpVersionPref::Auto
+                    && outcomes.should_race(url) =>
+            {
+                self.race_quic(outcomes, url, tcp).await?
+            }
+            _ => tcp.await?,
+        };
+        #[cfg(not(feature = "quic"))]
+        let mut pool_result: PoolResult = tcp.await?;
+        // Counted for HTTP/1.1; an HTTP/2 session counts its streams
+        pool_result.socket.connection_info_mut().requests += 1;
+
+        let (negotiated_protocol, ssl_info) = if url.scheme() == "https" {
+            (
+                pool_result.socket.negotiated_protocol(),
+                pool_result.socket.ssl_info().map(Arc::new),
+            )
+        } else {
+            (NextProto::Unknown, None)
+        };
+        match (version, negotiated_protocol) {
+            (HttpVersionPref::Http1Only, NextProto::Http2) => {
+                self.pool.discard_socket_from_group(&pool_result.group_id);
+                return Err(NetError::AlpnNegotiationFailed);
+            }
+            (HttpVersionPref::Http2PriorKnowledge, NextProto::Http11) => {
+                self.pool.discard_socket_from_group(&pool_result.group_id);
+                return Err(NetError::Http11Required);
+            }
+            _ => {}
+        }
 
+        let info = *pool_result.socket.connection_info();
+        let meter = pool_result.socket.meter().cloned();
         let io = TokioIo::new(pool_result.socket);
+        // A session for one request is not shared
+        let cache_as = (reuse != ConnectionReuse::Close).then_some(group_id);
+        let fp = h2_fingerprint.cloned().unwrap_or_default();
 
-        if pool_result.is_h2 {
+        if pool_result.is_h2
+            || version == HttpVersionPref::Http2PriorKnowledge
+            || (h2c && h2c_mode == H2cMode::PriorKnowledge)
+        {
             // H2 Handshake with fingerprint emulation
-            let fp = h2_fingerprint.cloned().unwrap_or_default();
+            let sender = self
+                .h2_handshake(
+                    cache_as,
+                    io,
+                    h2_builder(&fp),
+                    &fp,
+                    ssl_info.clone(),
+                    info,
+                    meter.clone(),
+                )
+                .await?;
 
-            // Build http2 connection with fingerprint settings
-            let mut builder = client::Builder::new();
+            Ok(HttpStream {
+                inner: HttpStreamInner::H2(sender),
+                is_reused: pool_result.is_reused,
+                negotiated_protocol,
+                ssl_info,
+                meter,
+            })
+        } else if h2c && h2c_mode == H2cMode::Upgrade {
+            self.h2c_upgrade(url, cache_as, io, &fp, http1_options, pool_result.is_reused)
+                .await
+        } else {
+            // H1 Handshake (Default)
+            let (sender, conn) = h1_builder(http1_options)
+                .handshake(io)
+                .await
+                .map_err(|_| NetError::ConnectionFailed)?;
 
-            // Apply window sizes
-            builder.initial_window_size(fp.initial_window_size);
-            builder.initial_connection_window_size(fp.initial_conn_window_size);
+            let (release, released) = oneshot::channel();
+            spawn(run_h1_connection(
+                conn,
+                released,
+                self.pool.clone(),
+                pool_result.group_id,
+                reuse != ConnectionReuse::Close,
+            ));
 
-            // Apply frame limits
-            if let Some(max_frame) = fp.max_frame_size {
-                builder.max_frame_size(max_frame);
-            }
-            if let Some(max_streams) = fp.max_concurrent_streams {
-                builder.max_concurrent_streams(max_streams);
-            }
-            if let Some(max_header_list) = fp.max_header_list_size {
-                builder.max_header_list_size(max_header_list);
-            }
-            if let Some(header_table_size) = fp.header_table_size {
-                builder.header_table_size(header_table_size);
-            }
+            Ok(HttpStream {
+                inner: HttpStreamInner::H1(H1Sender {
+                    sender: Some(sender),
+                    release: Some(release),
+                }),
+                is_reused: pool_result.is_reused,
+                negotiated_protocol,
+                ssl_info,
+                meter,
+            })
+        }
+    }
 
-            // Apply pseudo-header order (critical for fingerprinting)
-            // Note: pseudo_order is set per-request, not on the connection builder
-            // if let Some(order) = &fp.pseudo_order { ... }
+    /// Run the HTTP/2 handshake on `io`, cache the session as `cache_as`'s
+    /// if given and spawn its driver. `builder` carries the settings of
+    /// `fp`; the rest of the fingerprint is applied here.
+    async fn h2_handshake<T>(
+        &self,
+        cache_as: Option<&GroupId>,
+        io: T,
+        builder: client::Builder,
+        fp: &H2Fingerprint,
+        ssl_info: Option<Arc<SslInfo>>,
+        info: ConnectionInfo,
+        meter: Option<Arc<WireMeter>>,
+    ) -> Result<H2Sender, NetError>
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        // Perform handshake with Bytes body type
+        let peer = Arc::new(PeerSettings::default());
+        let fingerprint = Arc::new(OnceLock::new());
+        let io = PrefaceIo::new(io, fingerprint.clone());
+        let io = GreaseIo::new(io, fp.grease_setting, fp.grease_frame.clone());
+        let io = PeerSettingsIo::new(io, peer.clone());
+        let (sender, conn) = builder.handshake::<_, Bytes>(io).await.map_err(|e| {
+            tracing::debug!("H2 handshake failed: {:?}", e);
+            map_h2_error(&e, NetError::ConnectionFailed)
+        })?;
 
-            // Apply settings order (critical for fingerprinting)
-            if let Some(order) = &fp.settings_order {
-                builder.settings_order(order.clone());
+        let (first_request, deferred_window) = match deferred_conn_window(fp) {
+            Some(size) => {
+                let (tx, rx) = oneshot::channel();
+                (Some(tx), Some((size, rx)))
             }
+            None => (None, None),
+        };
 
-            // Apply priority frames (priorities sent after connection)
-            // Note: priorities are sent asynchronously after handshake
-            // if let Some(ref priorities) = fp.priorities { ... }
+        // Store sender in cache for multiplexing. The request that set up
+        // the connection claims a stream, so others see it busy
+        let sender = H2Sender::new(sender, meter, first_request, peer, fingerprint);
+        if let Some(group_id) = cache_as {
+            self.h2_cache
+                .store(group_id, (sender.clone(), ssl_info, info));
+        }
+        let sender = sender.claimed();
 
-            // Apply push/connect protocol settings
-            if let Some(enable_push) = fp.enable_push {
-                builder.enable_push(enable_push);
+        // Spawn connection driver
+        spawn(async move {
+            if let Err(e) = drive_h2(conn, deferred_window).await {
+                tracing::debug!("H2 connection error: {:?}", e);
             }
-            if let Some(enable_connect) = fp.enable_connect_protocol {
-                builder.enable_connect_protocol(enable_connect);
+        });
+
+        Ok(sender)
+    }
+
+    /// Open a stream on a cached H2 session of `group_id`, unless there is
+    /// none the policy picks or the connection lifetime retires them.
+    async fn reuse_h2_session(&self, group_id: &GroupId, h2c: bool) -> Option<HttpStream> {
+        let (sender, ssl_info, info) = loop {
+            let (sender, ssl_info, mut info) = self.h2_cache.pick(group_id, &self.h2_policy)?;
+            info.requests = sender.requests();
+            let idle_expired = sender.idle_since().is_some_and(|since| {
+                self.pool.connection_lifetime().is_idle_expired(
+                    since,
+                    info.requests > 0,
+                    Instant::now(),
+                )
+            });
+            if !idle_expired && self.pool.check_reusable(group_id, &mut info).await {
+                break (sender, ssl_info, info);
             }
-            if let Some(no_priorities) = fp.no_rfc7540_priorities {
-                builder.no_rfc7540_priorities(no_priorities);
+            // Open streams finish, new ones go to another connection
+            if self.h2_cache.remove_session(group_id, &sender) {
+                self.pool.discard_socket_from_group(group_id);
             }
+        };
+        self.h2_cache.confirm(group_id, &info);
+        let meter = sender.meter.clone();
+        // Reuse existing H2 connection (multiplexing!)
+        Some(HttpStream {
+            inner: HttpStreamInner::H2(sender),
+            is_reused: true,
+            negotiated_protocol: if h2c {
+                NextProto::Unknown
+            } else {
+                NextProto::Http2
+            },
+            ssl_info,
+            meter,
+        })
+    }
 
-            // Perform handshake with Bytes body type
-            let (sender, conn) = builder.handshake::<_, Bytes>(io).await.map_err(|e| {
-                tracing::debug!("H2 handshake failed: {:?}", e);
-                NetError::ConnectionFailed
-            })?;
+    /// Try to upgrade a fresh cleartext connection to HTTP/2.
+    ///
+    /// The upgrade is requested with `OPTIONS *` so that no real request is
+    /// consumed by the handshake. After `101 Switching Protocols` the server
+    /// answers the probe on stream 1, which the client skips by starting its
+    /// own streams at 3. Any other status leaves the connection on HTTP/1.1.
+    async fn h2c_upgrade(
+        &self,
+        url: &Url,
+        cache_as: Option<&GroupId>,
+        io: TokioIo<crate::socket::stream::BoxedSocket>,
+        fp: &H2Fingerprint,
+        http1_options: Option<&Http1Options>,
+        is_reused: bool,
+    ) -> Result<HttpStream, NetError> {
+        let info = *io.inner().connection_info();
+        let meter = io.inner().meter().cloned();
+        let (mut sender, conn) = h1_builder(http1_options)
+            .handshake(io)
+            .await
+            .map_err(|_| NetError::ConnectionFailed)?;
 
-            // Store sender in cache for multiplexing
-            self.h2_cache.store(url, sender.clone());
+        spawn(async move {
+            if let Err(e) = conn.with_upgrades().await {
+                tracing::debug!("H1 connection error: {:?}", e);
+            }
+        });
 
-            // Spawn connection driver
-            spawn(async move {
-                if let Err(e) = conn.await {
-                    tracing::debug!("H2 connection error: {:?}", e);
-                }
-            });
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let probe = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("*")
+            .header(http::header::HOST, host)
+            .header(http::header::CONNECTION, "Upgrade, HTTP2-Settings")
+            .header(http::header::UPGRADE, "h2c")
+            .header("HTTP2-Settings", http2_settings_header(fp))
+            .body(RequestBody::Empty.into())
+            .map_err(|_| NetError::InvalidUrl)?;
 
-            Ok(HttpStream {
-                inner: HttpStreamInner::H2(sender),
-                is_reused: pool_result.is_reused,
-            })
-        } else {
-            // H1 Handshake (Default)
-            let (sender, conn) = http1::handshake(io)
+        let resp = sender.send_request(probe).await.map_err(|e| {
+            tracing::debug!("h2c upgrade request error: {:?}", e);
+            NetError::ConnectionClosed
+        })?;
+
+        if resp.status() != StatusCode::SWITCHING_PROTOCOLS {
+            tracing::debug!(status = %resp.status(), url = %url, "h2c upgrade declined");
+            // Drain the probe response so the connection can carry the request
+            resp.into_body()
+                .collect()
                 .await
-                .map_err(|_| NetError::ConnectionFailed)?;
+                .map_err(|_| NetError::ConnectionClosed)?;
 
-            spawn(async move {
-                if let Err(e) = conn.await {
-                    tracing::debug!("H1 connection error: {:?}", e);
-                }
+            return Ok(HttpStream {
+                inner: HttpStreamInner::H1(H1Sender::unpooled(sender)),
+                is_reused,
+                negotiated_protocol: NextProto::Unknown,
+                ssl_info: None,
+                meter,
             });
+        }
 
-            Ok(HttpStream {
-                inner: HttpStreamInner::H1(sender),
-                is_reused: pool_result.is_reused,
+        let upgraded = hyper::upgrade::on(resp).await.map_err(|e| {
+            tracing::debug!("h2c upgrade failed: {:?}", e);
+            NetError::ConnectionFailed
+        })?;
+
+        let mut builder = h2_builder(fp);
+        builder.initial_stream_id(3);
+        let sender = self
+            .h2_handshake(
+                cache_as,
+                TokioIo::new(upgraded),
+                builder,
+                fp,
+                None,
+                info,
+                meter.clone(),
+            )
+            .await?;
+
+        Ok(HttpStream {
+            inner: HttpStreamInner::H2(sender),
+            is_reused,
+            negotiated_protocol: NextProto::Unknown,
+            ssl_info: None,
+            meter,
+        })
+    }
+
+    /// Streams of the first cached HTTP/2 session of `group_id`, `None`
+    /// without one.
+    pub fn h2_stream_usage(&self, group_id: &GroupId) -> Option<H2StreamUsage> {
+        self.h2_session_usage(group_id).into_iter().next()
+    }
+
+    /// Streams of each cached HTTP/2 session of `group_id`, oldest first.
+    pub fn h2_session_usage(&self, group_id: &GroupId) -> Vec<H2StreamUsage> {
+        self.h2_cache
+            .sessions
+            .get(group_id)
+            .map_or_else(Vec::new, |sessions| {
+                sessions.iter().map(|(sender, ..)| sender.usage()).collect()
             })
+    }
+
+    /// Report that the HTTP/2 session for `url` through `proxy` with `nik`
+    /// failed and is not coming back.
+    ///
+    /// HTTP/1.1 connections need no report: they leave the pool by
+    /// themselves when they fail.
+    pub fn report_failure(
+        &self,
+        url: &Url,
+        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        nik: Option<&NetworkIsolationKey>,
+    ) {
+        if let Some(group_id) = GroupId::new(url, proxy, nik) {
+            self.report_group_failure(&group_id);
+        }
+    }
+
+    /// Report that the HTTP/2 sessions of `group_id` failed, like
+    /// [`Self::report_failure`].
+    pub fn report_group_failure(&self, group_id: &GroupId) {
+        let sessions = self.h2_cache.remove(group_id);
+        for _ in 0..sessions.max(1) {
+            self.pool.discard_socket_from_group(group_id);
         }
     }
 
-    pub fn report_failure(&self, url: &Url) {
-        self.pool.discard_socket(url);
+    /// Report that the HTTP/2 session carrying `stream` failed, leaving the
+    /// other sessions of `group_id` in use.
+    pub fn report_stream_failure(&self, group_id: &GroupId, stream: &HttpStream) {
+        if let HttpStreamInner::H2(sender) = &stream.inner {
+            if self.h2_cache.remove_session(group_id, sender) {
+                self.pool.discard_socket_from_group(group_id);
+            }
+        }
+    }
+
+    /// Stop reusing connections: HTTP/2 sessions get no new streams and
+    /// send GOAWAY once their open streams finish, and the pool closes its
+    /// idle sockets (see [`ClientSocketPool::close`]).
+    ///
+    /// Chromium: `SpdySessionPool::CloseCurrentIdleSessions` and
+    /// `ClientSocketPool::CloseIdleSockets` on context shutdown.
+    pub fn close(&self) {
+        // The connection driver sends GOAWAY(NO_ERROR) and finishes once
+        // no sender or stream of the session is left
+        let sessions = self.h2_cache.clear();
+        let idle = self.pool.close();
+        tracing::debug!(sessions, idle, "closed connections");
+    }
+
+    /// Stop reusing the connections to `host`, on any port: its HTTP/2
+    /// sessions get no new streams and send GOAWAY once their open streams
+    /// finish, and the pool flushes its sockets and DNS answer (see
+    /// [`ClientSocketPool::flush_host`]). Requests started later connect
+    /// afresh.
+    pub fn flush_host(&self, host: &str) {
+        let key = host_key(host);
+        let sessions = self.remove_sessions(|group_id| host_key(group_id.host()) == key);
+        let idle = self.pool.flush_host(host);
+        tracing::debug!(host = %key, sessions, idle, "flushed connections");
+    }
+
+    /// [`flush_host`](Self::flush_host) for every host, leaving the
+    /// factory open, unlike [`close`](Self::close).
+    pub fn flush_all(&self) {
+        let sessions = self.remove_sessions(|_| true);
+        let idle = self.pool.flush_all();
+        tracing::debug!(sessions, idle, "flushed all connections");
+    }
+
+    /// Stop reusing the sessions of the groups `matches` picks, freeing
+    /// their pool slots, and return how many there were.
+    fn remove_sessions(&self, matches: impl Fn(&GroupId) -> bool) -> usize {
+        let mut sessions = 0;
+        for (group_id, count) in self.h2_cache.remove_groups(matches) {
+            sessions += count;
+            for _ in 0..count {
+                self.pool.discard_socket_from_group(&group_id);
+            }
+        }
+        sessions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::h2fingerprint::GreaseSetting;
+
+    #[test]
+    fn test_http2_settings_header_encodes_present_settings() {
+        let fp = H2Fingerprint {
+            max_concurrent_streams: None,
+            max_frame_size: None,
+            grease_setting: Some(GreaseSetting::new(0x0a0a, 1)),
+            ..H2Fingerprint::chrome()
+        };
+
+        let decoded = URL_SAFE_NO_PAD.decode(http2_settings_header(&fp)).unwrap();
+
+        assert_eq!(
+            decoded,
+            [
+                0, 1, 0, 1, 0, 0, // HEADER_TABLE_SIZE = 65536
+                0, 2, 0, 0, 0, 0, // ENABLE_PUSH = 0
+                0, 4, 0, 0x60, 0, 0, // INITIAL_WINDOW_SIZE = 6291456
+                0, 6, 0, 4, 0, 0, // MAX_HEADER_LIST_SIZE = 262144
+                0x0a, 0x0a, 0, 0, 0, 1, // GREASE
+            ]
+        );
+    }
+
+    #[test]
+    fn test_h2c_mode_default_is_disabled() {
+        assert_eq!(H2cMode::default(), H2cMode::Disabled);
+    }
+
+    #[test]
+    fn test_version_pref_alpn_override() {
+        assert_eq!(HttpVersionPref::Auto.alpn_override(), None);
+        assert_eq!(
+            HttpVersionPref::Http1Only.alpn_override(),
+            Some(&[AlpnProtocol::HTTP1][..])
+        );
+        assert_eq!(
+            HttpVersionPref::Http2PriorKnowledge.alpn_override(),
+            Some(&[AlpnProtocol::HTTP2][..])
+        );
+    }
+
+    #[test]
+    fn test_h1_framing() {
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        http::HeaderName::from_static(name),
+                        http::HeaderValue::from_static(value),
+                    )
+                })
+                .collect::<http::HeaderMap>()
+        };
+
+        assert!(check_h1_framing(&headers(&[("content-length", "5")])).is_ok());
+        assert!(check_h1_framing(&headers(&[("transfer-encoding", "chunked")])).is_ok());
+        assert!(check_h1_framing(&headers(&[
+            ("content-length", "5"),
+            ("content-length", "5")
+        ]))
+        .is_ok());
+        assert!(check_h1_framing(&headers(&[("content-length", "5, 5")])).is_ok());
+        assert!(matches!(
+            check_h1_framing(&headers(&[
+                ("content-length", "5"),
+                ("content-length", "6")
+            ])),
+            Err(NetError::ResponseHeadersMultipleContentLength)
+        ));
+        assert!(matches!(
+            check_h1_framing(&headers(&[("content-length", "5, 6")])),
+            Err(NetError::ResponseHeadersMultipleContentLength)
+        ));
+        assert!(matches!(
+            check_h1_framing(&headers(&[
+                ("transfer-encoding", "chunked"),
+                ("content-length", "5")
+            ])),
+            Err(NetError::InvalidResponse)
+        ));
     }
 }
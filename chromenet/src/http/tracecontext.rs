@@ -0,0 +1,223 @@
+//! W3C Trace Context propagation.
+//!
+//! Carries a distributed trace across requests in the `traceparent` and
+//! `tracestate` headers (<https://www.w3.org/TR/trace-context/>). chromenet
+//! has no OpenTelemetry dependency: bridge a tracer in through a
+//! [`TracePropagator`] that returns the active span's context.
+
+use std::fmt::Write;
+
+/// Trace context sent with a request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    parent_id: [u8; 8],
+    sampled: bool,
+    tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// Context for span `parent_id` of trace `trace_id`.
+    ///
+    /// Returns `None` if either id is all zeros, which the spec forbids.
+    pub fn new(trace_id: [u8; 16], parent_id: [u8; 8], sampled: bool) -> Option<Self> {
+        if trace_id == [0; 16] || parent_id == [0; 8] {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            parent_id,
+            sampled,
+            tracestate: None,
+        })
+    }
+
+    /// Start a new trace with random ids.
+    pub fn random(sampled: bool) -> Self {
+        let mut trace_id = [0u8; 16];
+        random_nonzero(&mut trace_id);
+        let mut parent_id = [0u8; 8];
+        random_nonzero(&mut parent_id);
+        Self {
+            trace_id,
+            parent_id,
+            sampled,
+            tracestate: None,
+        }
+    }
+
+    /// Parse received `traceparent` and `tracestate` header values.
+    ///
+    /// Headers from future versions are accepted as long as they start with
+    /// the version 00 fields.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let traceparent = traceparent.trim();
+        let fields = traceparent.get(..55)?;
+        let rest = &traceparent[55..];
+
+        let mut parts = fields.split('-');
+        let version = parse_hex::<1>(parts.next()?)?[0];
+        let trace_id = parse_hex::<16>(parts.next()?)?;
+        let parent_id = parse_hex::<8>(parts.next()?)?;
+        let flags = parse_hex::<1>(parts.next()?)?[0];
+        if version == 0xff || (version == 0 && !rest.is_empty()) {
+            return None;
+        }
+        if version != 0 && !rest.is_empty() && !rest.starts_with('-') {
+            return None;
+        }
+
+        let mut context = Self::new(trace_id, parent_id, flags & 0x01 != 0)?;
+        context.tracestate = tracestate
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from);
+        Some(context)
+    }
+
+    /// Attach vendor-specific `tracestate`.
+    pub fn with_tracestate(mut self, tracestate: impl Into<String>) -> Self {
+        self.tracestate = Some(tracestate.into());
+        self
+    }
+
+    /// Same trace and flags under a new random span id, for a request made
+    /// as a child of this context's span.
+    pub fn child(&self) -> Self {
+        let mut parent_id = [0u8; 8];
+        random_nonzero(&mut parent_id);
+        Self {
+            parent_id,
+            ..self.clone()
+        }
+    }
+
+    /// The 16-byte trace id.
+    pub fn trace_id(&self) -> [u8; 16] {
+        self.trace_id
+    }
+
+    /// The 8-byte id of the span the request belongs to.
+    pub fn parent_id(&self) -> [u8; 8] {
+        self.parent_id
+    }
+
+    /// Whether the caller recorded the trace.
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// The `tracestate` header value, if any.
+    pub fn tracestate(&self) -> Option<&str> {
+        self.tracestate.as_deref()
+    }
+
+    /// The `traceparent` header value.
+    pub fn traceparent(&self) -> String {
+        let mut value = String::with_capacity(55);
+        value.push_str("00-");
+        push_hex(&mut value, &self.trace_id);
+        value.push('-');
+        push_hex(&mut value, &self.parent_id);
+        value.push('-');
+        push_hex(&mut value, &[self.sampled as u8]);
+        value
+    }
+}
+
+/// Source of the trace context for outgoing requests, typically the active
+/// span of an OpenTelemetry tracer.
+///
+/// Implemented for closures, e.g. `|| Some(TraceContext::random(true))`.
+pub trait TracePropagator: Send + Sync {
+    /// Context to send with the next request, `None` to send none.
+    fn current(&self) -> Option<TraceContext>;
+}
+
+impl<F> TracePropagator for F
+where
+    F: Fn() -> Option<TraceContext> + Send + Sync,
+{
+    fn current(&self) -> Option<TraceContext> {
+        self()
+    }
+}
+
+/// Lowercase hex of exactly `N` bytes, as the spec requires.
+fn parse_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 || !s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+    let mut out = [0u8; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        let _ = write!(out, "{:02x}", b);
+    }
+}
+
+fn random_nonzero(buf: &mut [u8]) {
+    loop {
+        let _ = boring::rand::rand_bytes(buf);
+        if buf.iter().any(|&b| b != 0) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    #[test]
+    fn test_parse_and_format_roundtrip() {
+        let context = TraceContext::parse(TRACEPARENT, Some("congo=t61rcWkgMzE")).unwrap();
+        assert!(context.is_sampled());
+        assert_eq!(
+            context.parent_id(),
+            [0xb7, 0xad, 0x6b, 0x71, 0x69, 0x20, 0x33, 0x31]
+        );
+        assert_eq!(context.tracestate(), Some("congo=t61rcWkgMzE"));
+        assert_eq!(context.traceparent(), TRACEPARENT);
+
+        let child = context.child();
+        assert_eq!(child.trace_id(), context.trace_id());
+        assert_ne!(child.parent_id(), context.parent_id());
+        assert_eq!(child.tracestate(), context.tracestate());
+    }
+
+    #[test]
+    fn test_invalid_traceparents() {
+        for value in [
+            "",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+            "00_0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-+af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        ] {
+            assert!(TraceContext::parse(value, None).is_none(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_future_version_keeps_known_fields() {
+        let value = "cc-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00-what-the-future-holds";
+        let context = TraceContext::parse(value, None).unwrap();
+        assert!(!context.is_sampled());
+        assert_eq!(
+            context.traceparent(),
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00"
+        );
+    }
+}
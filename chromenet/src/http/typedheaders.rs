@@ -0,0 +1,378 @@
+//! Typed values of common response headers.
+//!
+//! Chromium mapping: `HttpResponseHeaders::GetContentLength`,
+//! `GetMimeTypeAndCharset` and `GetRetryAfter`
+//!
+//! Each header is parsed with the rules for repeated and malformed values,
+//! so callers do not each get them slightly wrong:
+//!
+//! - `Content-Length` may repeat, as separate lines or a list, but only
+//!   with one value; differing values or anything but digits give `None`
+//!   (RFC 9110 8.6).
+//! - `Content-Type` is taken from the last value that parses, like the
+//!   Fetch standard's "extract a MIME type".
+//! - `ETag` and `Retry-After` are single values; repeated ones must agree.
+//! - `Content-Encoding` lists are joined across lines, lowercased and
+//!   without `identity`.
+//! - `Set-Cookie` is never split or joined: each line is one cookie, as
+//!   its `Expires` dates contain commas (RFC 6265 3).
+
+use crate::http::conditional::EntityTag;
+use crate::http::httpdate::parse_http_date;
+use http::header::{
+    HeaderName, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, RETRY_AFTER, SET_COOKIE,
+};
+use http::HeaderMap;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// A parsed `Content-Type`: the MIME type and its parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType {
+    mime_type: String,
+    params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    /// Parse a `Content-Type` value such as `text/html; charset="utf-8"`.
+    ///
+    /// The type and parameter names are lowercased; parameter values keep
+    /// their case, with quoting removed. Returns `None` unless the value
+    /// starts with a `type/subtype`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (essence, mut rest) = match value.find(';') {
+            Some(i) => (&value[..i], &value[i + 1..]),
+            None => (value, ""),
+        };
+        let essence = essence.trim();
+        let (kind, subtype) = essence.split_once('/')?;
+        if !is_token(kind) || !is_token(subtype) {
+            return None;
+        }
+
+        let mut params = Vec::new();
+        while !rest.is_empty() {
+            let (param, remainder) = split_param(rest);
+            rest = remainder;
+            let Some((name, value)) = param else {
+                continue;
+            };
+            if !params.iter().any(|(n, _)| *n == name) {
+                params.push((name, value));
+            }
+        }
+        Some(Self {
+            mime_type: essence.to_ascii_lowercase(),
+            params,
+        })
+    }
+
+    /// The MIME type, lowercase and without parameters, e.g. `text/html`.
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// The value of parameter `name`, matched case-insensitively.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The `charset` parameter, lowercase.
+    pub fn charset(&self) -> Option<String> {
+        self.param("charset").map(str::to_ascii_lowercase)
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.mime_type)?;
+        for (name, value) in &self.params {
+            if is_token(value) {
+                write!(f, "; {}={}", name, value)?;
+            } else {
+                let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, "; {}=\"{}\"", name, escaped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `Retry-After` value: seconds to wait or a date to wait for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryAfter {
+    /// `Retry-After: 120`
+    Delay(Duration),
+    /// `Retry-After: Fri, 31 Dec 1999 23:59:59 GMT`
+    Date(SystemTime),
+}
+
+impl RetryAfter {
+    /// Parse a `Retry-After` value.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+            return value
+                .parse()
+                .ok()
+                .map(|seconds| Self::Delay(Duration::from_secs(seconds)));
+        }
+        parse_http_date(value).map(Self::Date)
+    }
+
+    /// How long to wait from now, zero for a date in the past.
+    pub fn delay(&self) -> Duration {
+        match self {
+            Self::Delay(delay) => *delay,
+            Self::Date(date) => date
+                .duration_since(SystemTime::now())
+                .unwrap_or(Duration::ZERO),
+        }
+    }
+}
+
+/// The body length `Content-Length` declares, `None` if absent or invalid.
+pub fn content_length(headers: &HeaderMap) -> Option<u64> {
+    let mut length = None;
+    for value in headers.get_all(CONTENT_LENGTH) {
+        for part in value.as_bytes().split(|&b| b == b',') {
+            let part = part.trim_ascii();
+            if part.is_empty() || !part.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            let parsed = std::str::from_utf8(part).ok()?.parse::<u64>().ok()?;
+            if length.is_some_and(|l| l != parsed) {
+                return None;
+            }
+            length = Some(parsed);
+        }
+    }
+    length
+}
+
+/// The content codings applied to the body, lowercase and in the order
+/// they were applied. `identity` is left out.
+pub fn content_encodings(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(CONTENT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty() && coding != "identity")
+        .collect()
+}
+
+/// The `Set-Cookie` lines, one per header line and in order. Lines that
+/// are not UTF-8 are left out.
+pub fn set_cookies(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|v| String::from_utf8(v.as_bytes().to_vec()).ok())
+        .collect()
+}
+
+/// The last `Content-Type` that parses.
+pub fn content_type(headers: &HeaderMap) -> Option<ContentType> {
+    headers
+        .get_all(CONTENT_TYPE)
+        .iter()
+        .rev()
+        .find_map(|value| ContentType::parse(value.to_str().ok()?))
+}
+
+/// The `ETag`, `None` if absent, invalid or repeated with another tag.
+pub fn etag(headers: &HeaderMap) -> Option<EntityTag> {
+    EntityTag::parse(single(headers, &ETAG)?)
+}
+
+/// The `Retry-After`, `None` if absent, invalid or repeated with another
+/// value.
+pub fn retry_after(headers: &HeaderMap) -> Option<RetryAfter> {
+    RetryAfter::parse(single(headers, &RETRY_AFTER)?)
+}
+
+/// The value of a header that must not differ when repeated.
+fn single<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    let mut values = headers.get_all(name).iter();
+    let first = values.next()?.to_str().ok()?.trim();
+    values
+        .all(|v| v.to_str().is_ok_and(|v| v.trim() == first))
+        .then_some(first)
+}
+
+/// Split the next `name=value` off a parameter list, returning it if it
+/// is well-formed and the rest after its `;`.
+fn split_param(input: &str) -> (Option<(String, String)>, &str) {
+    let input = input.trim_start();
+    let name_end = input.find(['=', ';']).unwrap_or(input.len());
+    let name = input[..name_end].trim();
+    if input[name_end..].starts_with('=') {
+        let value = &input[name_end + 1..];
+        let (value, rest) = if let Some(quoted) = value.strip_prefix('"') {
+            let (value, after) = unquote(quoted);
+            let rest = after.find(';').map_or("", |i| &after[i + 1..]);
+            (Some(value), rest)
+        } else {
+            let end = value.find(';').unwrap_or(value.len());
+            let rest = value.get(end + 1..).unwrap_or("");
+            let token = value[..end].trim();
+            (is_token(token).then(|| token.to_string()), rest)
+        };
+        let param = value.filter(|_| is_token(name));
+        return (param.map(|v| (name.to_ascii_lowercase(), v)), rest);
+    }
+    let rest = input.get(name_end + 1..).unwrap_or("");
+    (None, rest)
+}
+
+/// The content of a quoted string starting after its opening quote, and
+/// what follows its closing quote.
+fn unquote(input: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &input[i + 1..]),
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            _ => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+/// Whether `s` is an RFC 9110 token.
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(
+                HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+        }
+        map
+    }
+
+    fn length(values: &[&str]) -> Option<u64> {
+        let pairs: Vec<_> = values.iter().map(|v| ("content-length", *v)).collect();
+        content_length(&headers(&pairs))
+    }
+
+    #[test]
+    fn test_content_length() {
+        assert_eq!(length(&["42"]), Some(42));
+        assert_eq!(length(&[]), None);
+        // Repeated, as lines or a list, with one value
+        assert_eq!(length(&["42", "42"]), Some(42));
+        assert_eq!(length(&["42, 42"]), Some(42));
+        assert_eq!(length(&["42", "43"]), None);
+        for invalid in [
+            "-1",
+            "+42",
+            "4 2",
+            "0x10",
+            "",
+            "42,",
+            "99999999999999999999",
+        ] {
+            assert_eq!(length(&[invalid]), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_content_type() {
+        let parsed = ContentType::parse("Text/HTML; Charset=\"UTF-8\"; q=\"a\\\"b\"").unwrap();
+        assert_eq!(parsed.mime_type(), "text/html");
+        assert_eq!(parsed.charset().as_deref(), Some("utf-8"));
+        assert_eq!(parsed.param("Q"), Some("a\"b"));
+        assert_eq!(parsed.to_string(), "text/html; charset=UTF-8; q=\"a\\\"b\"");
+
+        // Malformed parameters are skipped, the first of a name wins
+        let parsed = ContentType::parse("text/plain; =x; junk; charset=a; charset=b").unwrap();
+        assert_eq!(parsed.charset().as_deref(), Some("a"));
+
+        assert_eq!(ContentType::parse("text"), None);
+        assert_eq!(ContentType::parse("text/"), None);
+        assert_eq!(ContentType::parse("text plain/x"), None);
+
+        // The last value that parses wins
+        let map = headers(&[
+            ("content-type", "text/html"),
+            ("content-type", "application/json"),
+            ("content-type", "nonsense"),
+        ]);
+        assert_eq!(content_type(&map).unwrap().mime_type(), "application/json");
+    }
+
+    #[test]
+    fn test_content_encodings() {
+        let map = headers(&[
+            ("content-encoding", "GZIP, identity"),
+            ("content-encoding", " br"),
+        ]);
+        assert_eq!(content_encodings(&map), ["gzip", "br"]);
+        assert!(content_encodings(&HeaderMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_set_cookies_kept_apart() {
+        let map = headers(&[
+            ("set-cookie", "a=1; Expires=Wed, 21 Oct 2037 07:28:00 GMT"),
+            ("content-type", "text/plain"),
+            ("set-cookie", "b=2, c=3"),
+        ]);
+        assert_eq!(
+            set_cookies(&map),
+            ["a=1; Expires=Wed, 21 Oct 2037 07:28:00 GMT", "b=2, c=3"]
+        );
+        assert!(set_cookies(&HeaderMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_etag() {
+        let map = headers(&[("etag", "W/\"v1\"")]);
+        assert_eq!(etag(&map), Some(EntityTag::weak("v1")));
+        let map = headers(&[("etag", "\"v1\""), ("etag", "\"v1\"")]);
+        assert_eq!(etag(&map), Some(EntityTag::strong("v1")));
+        let map = headers(&[("etag", "\"v1\""), ("etag", "\"v2\"")]);
+        assert_eq!(etag(&map), None);
+        assert_eq!(etag(&headers(&[("etag", "v1")])), None);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let map = headers(&[("retry-after", " 120 ")]);
+        assert_eq!(
+            retry_after(&map),
+            Some(RetryAfter::Delay(Duration::from_secs(120)))
+        );
+        let map = headers(&[("retry-after", "Fri, 31 Dec 1999 23:59:59 GMT")]);
+        let parsed = retry_after(&map).unwrap();
+        assert!(matches!(parsed, RetryAfter::Date(_)));
+        assert_eq!(parsed.delay(), Duration::ZERO);
+
+        for invalid in ["-5", "1.5", "soon", ""] {
+            assert_eq!(retry_after(&headers(&[("retry-after", invalid)])), None);
+        }
+        let map = headers(&[("retry-after", "1"), ("retry-after", "2")]);
+        assert_eq!(retry_after(&map), None);
+    }
+}
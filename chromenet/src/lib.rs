@@ -15,6 +15,26 @@
 //! - **Browser Emulation**: Device profiles, ordered headers, H2 fingerprinting
 //! - **Proxy Support**: HTTP, HTTPS, and SOCKS5 proxies
 //!
+//! ## Cargo Features
+//!
+//! Everything except `prometheus`, `tower` and `hyper-connector` is enabled
+//! by default. With `default-features = false` only the HTTP client itself
+//! is compiled.
+//!
+//! - `json` - JSON request bodies
+//! - `browser-cookies` - Cookie and Web Storage extraction from installed
+//!   browsers (SQLite, LevelDB, OS keyrings)
+//! - `hickory-dns` - Async DNS resolver with DoH/DoT; the system resolver is
+//!   used without it
+//! - `websocket` - WebSocket client (`ws`)
+//! - `quic` - QUIC/HTTP3 types (`quic`) and `ClientBuilder::http3`, inert
+//!   until a QUIC transport exists
+//! - `emulation-profiles` - Predefined browser profiles and impersonation
+//! - `prometheus` - Prometheus text exposition of [`metrics`]
+//! - `tower` - `tower::Service` implementation for [`Client`]
+//! - `hyper-connector` - hyper-util connector using chromenet's connections
+//!   (`socket::connector`)
+//!
 //! ## Quick Start
 //!
 //! ```rust,ignore
@@ -38,7 +58,11 @@
 //! - [`base`] - Core types and error definitions
 //! - [`cookies`] - Cookie storage, parsing, and browser extraction
 //! - [`http`] - HTTP transactions, headers, and body handling
+//! - [`metrics`] - Request, connection and cache metrics
+//! - [`session`] - Export and import of learned session state
 //! - [`socket`] - Connection pooling, proxy, and TLS sockets
+//! - `storage` - `localStorage`/`sessionStorage` extraction from installed
+//!   browsers
 //! - [`tls`] - HSTS, certificate pinning, and CT verification
 //! - [`urlrequest`] - High-level request API and device emulation
 //!
@@ -55,11 +79,20 @@ pub mod client;
 pub mod cookies;
 pub mod dns;
 pub mod emulation;
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzzing;
 pub mod http;
+pub mod metrics;
+#[cfg(feature = "quic")]
 pub mod quic;
+pub mod session;
 pub mod socket;
+#[cfg(feature = "browser-cookies")]
+pub mod storage;
 pub mod tls;
 pub mod urlrequest;
+#[cfg(feature = "websocket")]
 pub mod ws;
 
 // Convenience re-exports for ergonomic API
@@ -0,0 +1,356 @@
+//! Client metrics.
+//!
+//! The client reports request, connection, pool and cache events to a
+//! [`MetricsRecorder`]. Implement the trait to forward them to an existing
+//! metrics system, or use [`ClientMetrics`], which aggregates them into
+//! counters and histograms:
+//! - Request duration and completed requests per status class
+//! - DNS, TCP connect and TLS handshake time of new connections
+//! - Time requests wait for a pool slot
+//! - HTTP cache hits and misses
+//!
+//! With the `prometheus` feature, [`ClientMetrics`] renders in the
+//! Prometheus text format (see `metrics::prometheus`).
+//!
+//! DNS resolvers and the [`HostCache`](crate::dns::HostCache) keep their own
+//! counters, see [`DnsMetrics`](crate::dns::DnsMetrics); `prometheus::encode_dns`
+//! renders those of several resolvers side by side.
+//!
+//! ```rust,ignore
+//! use chromenet::metrics::ClientMetrics;
+//! use chromenet::Client;
+//!
+//! let metrics = ClientMetrics::new();
+//! let client = Client::builder().metrics(metrics.clone()).build();
+//! // ...
+//! println!("{:?}", metrics.snapshot().request_duration);
+//! ```
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+use crate::base::neterror::NetError;
+use crate::socket::connectjob::ConnectTiming;
+use http::{Method, StatusCode};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the duration histogram buckets.
+pub const DURATION_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Receives client events.
+///
+/// Every method defaults to doing nothing. Implementations are called on the
+/// request path and should not block.
+pub trait MetricsRecorder: Send + Sync {
+    /// A request finished, with a response or an error.
+    fn record_request(&self, _request: &RequestMetrics<'_>) {}
+
+    /// A new connection was established.
+    fn record_connect(&self, _timing: &ConnectTiming) {}
+
+    /// A socket request waited `wait` for a free pool slot; zero if one was
+    /// free.
+    fn record_pool_wait(&self, _wait: Duration) {}
+
+    /// An HTTP cache lookup found a usable entry (`hit`) or not.
+    fn record_cache_lookup(&self, _hit: bool) {}
+}
+
+/// A finished request, as reported to [`MetricsRecorder::record_request`].
+#[derive(Debug, Clone, Copy)]
+pub struct RequestMetrics<'a> {
+    /// Request method.
+    pub method: &'a Method,
+    /// Final status, `None` if the request failed.
+    pub status: Option<StatusCode>,
+    /// Time from sending until the response headers arrived, redirects and
+    /// retries included.
+    pub duration: Duration,
+    /// Why the request failed.
+    pub error: Option<&'a NetError>,
+}
+
+impl RequestMetrics<'_> {
+    /// Outcome class of the request.
+    pub fn status_class(&self) -> StatusClass {
+        StatusClass::of(self.status)
+    }
+}
+
+/// Outcome class of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StatusClass {
+    /// 1xx
+    Informational,
+    /// 2xx
+    Success,
+    /// 3xx
+    Redirection,
+    /// 4xx
+    ClientError,
+    /// 5xx
+    ServerError,
+    /// No response.
+    Error,
+}
+
+impl StatusClass {
+    /// Every class, in order.
+    pub const ALL: [StatusClass; 6] = [
+        StatusClass::Informational,
+        StatusClass::Success,
+        StatusClass::Redirection,
+        StatusClass::ClientError,
+        StatusClass::ServerError,
+        StatusClass::Error,
+    ];
+
+    /// Class of a response with `status`, or of a failed request.
+    pub fn of(status: Option<StatusCode>) -> Self {
+        match status.map(|s| s.as_u16() / 100) {
+            Some(1) => StatusClass::Informational,
+            Some(2) => StatusClass::Success,
+            Some(3) => StatusClass::Redirection,
+            Some(4) => StatusClass::ClientError,
+            Some(5) => StatusClass::ServerError,
+            _ => StatusClass::Error,
+        }
+    }
+
+    /// Label such as `"2xx"`, or `"error"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusClass::Informational => "1xx",
+            StatusClass::Success => "2xx",
+            StatusClass::Redirection => "3xx",
+            StatusClass::ClientError => "4xx",
+            StatusClass::ServerError => "5xx",
+            StatusClass::Error => "error",
+        }
+    }
+}
+
+/// Aggregating [`MetricsRecorder`]. Clones share the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct ClientMetrics {
+    inner: Arc<Aggregates>,
+}
+
+#[derive(Debug, Default)]
+struct Aggregates {
+    requests: [AtomicU64; 6],
+    request_duration: Histogram,
+    dns_duration: Histogram,
+    connect_duration: Histogram,
+    tls_duration: Histogram,
+    pool_wait: Histogram,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+/// Point-in-time copy of [`ClientMetrics`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Completed requests per class, in [`StatusClass::ALL`] order.
+    pub requests: [(StatusClass, u64); 6],
+    /// Request durations.
+    pub request_duration: HistogramSnapshot,
+    /// DNS resolution time of new connections.
+    pub dns_duration: HistogramSnapshot,
+    /// TCP connect time of new connections, proxy handshakes included.
+    pub connect_duration: HistogramSnapshot,
+    /// TLS handshake time of new TLS connections.
+    pub tls_duration: HistogramSnapshot,
+    /// Time socket requests waited for a pool slot.
+    pub pool_wait: HistogramSnapshot,
+    /// HTTP cache lookups that found a usable entry.
+    pub cache_hits: u64,
+    /// HTTP cache lookups that did not.
+    pub cache_misses: u64,
+}
+
+impl MetricsSnapshot {
+    /// Completed requests of `class`.
+    pub fn requests(&self, class: StatusClass) -> u64 {
+        self.requests
+            .iter()
+            .find(|(c, _)| *c == class)
+            .map_or(0, |(_, n)| *n)
+    }
+
+    /// Share of cache lookups that hit, `None` before the first lookup.
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        let total = self.cache_hits + self.cache_misses;
+        (total > 0).then(|| self.cache_hits as f64 / total as f64)
+    }
+}
+
+impl ClientMetrics {
+    /// Create empty metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current values.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let a = &self.inner;
+        MetricsSnapshot {
+            requests: StatusClass::ALL
+                .map(|class| (class, a.requests[class as usize].load(Ordering::Relaxed))),
+            request_duration: a.request_duration.snapshot(),
+            dns_duration: a.dns_duration.snapshot(),
+            connect_duration: a.connect_duration.snapshot(),
+            tls_duration: a.tls_duration.snapshot(),
+            pool_wait: a.pool_wait.snapshot(),
+            cache_hits: a.cache_hits.load(Ordering::Relaxed),
+            cache_misses: a.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MetricsRecorder for ClientMetrics {
+    fn record_request(&self, request: &RequestMetrics<'_>) {
+        self.inner.requests[request.status_class() as usize].fetch_add(1, Ordering::Relaxed);
+        self.inner.request_duration.observe(request.duration);
+    }
+
+    fn record_connect(&self, timing: &ConnectTiming) {
+        self.inner.dns_duration.observe(timing.dns);
+        self.inner.connect_duration.observe(timing.connect);
+        if let Some(tls) = timing.tls {
+            self.inner.tls_duration.observe(tls);
+        }
+    }
+
+    fn record_pool_wait(&self, wait: Duration) {
+        self.inner.pool_wait.observe(wait);
+    }
+
+    fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.inner.cache_hits
+        } else {
+            &self.inner.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Duration histogram over [`DURATION_BUCKETS`].
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Per bucket, plus one for values above the last bound
+    counts: [AtomicU64; DURATION_BUCKETS.len() + 1],
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, value: Duration) {
+        let secs = value.as_secs_f64();
+        let bucket = DURATION_BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(value.as_nanos()).unwrap_or(u64::MAX);
+        self.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = DURATION_BUCKETS
+            .iter()
+            .zip(&self.counts)
+            .map(|(&bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (bound, cumulative)
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets,
+            count: cumulative + self.counts[DURATION_BUCKETS.len()].load(Ordering::Relaxed),
+            sum: Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Point-in-time copy of a duration histogram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+    /// `(upper bound in seconds, observations at or below it)`, cumulative
+    /// like Prometheus buckets.
+    pub buckets: Vec<(f64, u64)>,
+    /// Number of observations.
+    pub count: u64,
+    /// Sum of all observations.
+    pub sum: Duration,
+}
+
+impl HistogramSnapshot {
+    /// Mean observation, `None` if there were none.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0)
+            .then(|| Duration::from_nanos((self.sum.as_nanos() / self.count as u128) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(30));
+        histogram.observe(Duration::from_millis(30));
+        histogram.observe(Duration::from_secs(60));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 4);
+        assert_eq!(snapshot.buckets[0], (0.001, 1));
+        assert_eq!(snapshot.buckets[3], (0.025, 1));
+        assert_eq!(snapshot.buckets[4], (0.05, 3));
+        assert_eq!(snapshot.buckets.last(), Some(&(10.0, 3)));
+        assert_eq!(snapshot.sum, Duration::from_micros(60_060_500));
+    }
+
+    #[test]
+    fn test_client_metrics_aggregate() {
+        let metrics = ClientMetrics::new();
+        for status in [Some(StatusCode::OK), Some(StatusCode::NOT_FOUND), None] {
+            metrics.record_request(&RequestMetrics {
+                method: &Method::GET,
+                status,
+                duration: Duration::from_millis(10),
+                error: None,
+            });
+        }
+        metrics.record_connect(&ConnectTiming {
+            dns: Duration::from_millis(1),
+            connect: Duration::from_millis(2),
+            tls: None,
+        });
+        metrics.record_cache_lookup(true);
+        metrics.record_cache_lookup(false);
+        metrics.record_cache_lookup(true);
+        metrics.record_cache_lookup(true);
+
+        let snapshot = metrics.clone().snapshot();
+        assert_eq!(snapshot.requests(StatusClass::Success), 1);
+        assert_eq!(snapshot.requests(StatusClass::ClientError), 1);
+        assert_eq!(snapshot.requests(StatusClass::Error), 1);
+        assert_eq!(snapshot.request_duration.count, 3);
+        assert_eq!(
+            snapshot.request_duration.mean(),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(snapshot.dns_duration.count, 1);
+        assert_eq!(snapshot.tls_duration.count, 0);
+        assert_eq!(snapshot.cache_hit_ratio(), Some(0.75));
+    }
+}
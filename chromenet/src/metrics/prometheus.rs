@@ -0,0 +1,268 @@
+//! Prometheus text exposition of [`ClientMetrics`] and [`DnsMetrics`].
+//!
+//! Serve the output of [`ClientMetrics::encode_prometheus`] with
+//! [`CONTENT_TYPE`] from the service's `/metrics` endpoint. Every metric is
+//! prefixed with `chromenet_`.
+//!
+//! DNS metrics are labelled with a `resolver` name, so a
+//! [`HostCache`](crate::dns::HostCache) and the resolvers behind it, e.g.
+//! a DoH resolver and the system one it falls back to, can be told apart.
+//! Render them together with [`encode_dns`] to keep each metric's samples
+//! under one header.
+
+use super::{ClientMetrics, HistogramSnapshot, MetricsSnapshot};
+use crate::dns::{DnsMetrics, DnsMetricsSnapshot};
+use std::fmt::Write;
+
+/// Content type of the text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+impl ClientMetrics {
+    /// Current values in the Prometheus text format.
+    pub fn encode_prometheus(&self) -> String {
+        encode(&self.snapshot())
+    }
+}
+
+impl DnsMetrics {
+    /// Current values in the Prometheus text format, labelled
+    /// `resolver="{resolver}"`.
+    pub fn encode_prometheus(&self, resolver: &str) -> String {
+        encode_dns(&[(resolver, self.snapshot())])
+    }
+}
+
+/// Render the metrics of DNS resolvers in the Prometheus text format, each
+/// labelled with its name.
+pub fn encode_dns(resolvers: &[(&str, DnsMetricsSnapshot)]) -> String {
+    let mut out = String::new();
+    let counters: [(&str, &str, fn(&DnsMetricsSnapshot) -> u64); 7] = [
+        ("lookups", "Hostname lookups started.", |s| s.lookups),
+        ("queries", "DNS queries sent.", |s| s.queries),
+        ("cache_hits", "Lookups answered from the host cache.", |s| {
+            s.cache_hits
+        }),
+        (
+            "failures",
+            "Lookups that failed, timeouts and NXDOMAIN included.",
+            |s| s.failures,
+        ),
+        ("timeouts", "Lookups that timed out.", |s| s.timeouts),
+        ("nxdomain", "Lookups of names that do not exist.", |s| {
+            s.nxdomain
+        }),
+        (
+            "rejected",
+            "Lookups refused because the queue was full.",
+            |s| s.rejected,
+        ),
+    ];
+    for (name, help, value) in counters {
+        let name = format!("chromenet_dns_{}_total", name);
+        header(&mut out, &name, "counter", help);
+        for (resolver, snapshot) in resolvers {
+            dns_sample(&mut out, &name, resolver, value(snapshot));
+        }
+    }
+
+    let gauges: [(&str, &str, fn(&DnsMetricsSnapshot) -> usize); 3] = [
+        ("in_flight", "Lookups running.", |s| s.in_flight),
+        ("queued", "Lookups waiting for a free slot.", |s| s.queued),
+        ("cache_entries", "Names in the host cache.", |s| {
+            s.cache_entries
+        }),
+    ];
+    for (name, help, value) in gauges {
+        let name = format!("chromenet_dns_{}", name);
+        header(&mut out, &name, "gauge", help);
+        for (resolver, snapshot) in resolvers {
+            dns_sample(&mut out, &name, resolver, value(snapshot));
+        }
+    }
+
+    // Sum and count only: the mean is sum / count
+    let name = "chromenet_dns_lookup_seconds";
+    header(
+        &mut out,
+        name,
+        "summary",
+        "Time of finished lookups, queueing included.",
+    );
+    for (resolver, snapshot) in resolvers {
+        let sum = snapshot.lookup_time.as_secs_f64();
+        dns_sample(&mut out, &format!("{}_sum", name), resolver, sum);
+        dns_sample(
+            &mut out,
+            &format!("{}_count", name),
+            resolver,
+            snapshot.timed,
+        );
+    }
+    out
+}
+
+fn dns_sample(out: &mut String, name: &str, resolver: &str, value: impl std::fmt::Display) {
+    let resolver = resolver
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    let _ = writeln!(out, "{}{{resolver=\"{}\"}} {}", name, resolver, value);
+}
+
+/// Render `snapshot` in the Prometheus text format.
+pub fn encode(snapshot: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "chromenet_requests_total",
+        "counter",
+        "Completed requests by status class.",
+    );
+    for (class, n) in &snapshot.requests {
+        let _ = writeln!(
+            out,
+            "chromenet_requests_total{{status_class=\"{}\"}} {}",
+            class.as_str(),
+            n
+        );
+    }
+
+    histogram(
+        &mut out,
+        "chromenet_request_duration_seconds",
+        "Time until response headers, redirects and retries included.",
+        &snapshot.request_duration,
+    );
+    histogram(
+        &mut out,
+        "chromenet_dns_duration_seconds",
+        "DNS resolution time of new connections.",
+        &snapshot.dns_duration,
+    );
+    histogram(
+        &mut out,
+        "chromenet_connect_duration_seconds",
+        "TCP connect time of new connections, proxy handshakes included.",
+        &snapshot.connect_duration,
+    );
+    histogram(
+        &mut out,
+        "chromenet_tls_duration_seconds",
+        "TLS handshake time of new connections.",
+        &snapshot.tls_duration,
+    );
+    histogram(
+        &mut out,
+        "chromenet_pool_wait_seconds",
+        "Time socket requests waited for a pool slot.",
+        &snapshot.pool_wait,
+    );
+
+    header(
+        &mut out,
+        "chromenet_cache_lookups_total",
+        "counter",
+        "HTTP cache lookups by result.",
+    );
+    let _ = writeln!(
+        out,
+        "chromenet_cache_lookups_total{{result=\"hit\"}} {}",
+        snapshot.cache_hits
+    );
+    let _ = writeln!(
+        out,
+        "chromenet_cache_lookups_total{{result=\"miss\"}} {}",
+        snapshot.cache_misses
+    );
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, snapshot: &HistogramSnapshot) {
+    header(out, name, "histogram", help);
+    for (bound, n) in &snapshot.buckets {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, n);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, snapshot.count);
+    let _ = writeln!(out, "{}_sum {}", name, snapshot.sum.as_secs_f64());
+    let _ = writeln!(out, "{}_count {}", name, snapshot.count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{MetricsRecorder, RequestMetrics};
+    use http::{Method, StatusCode};
+    use std::time::Duration;
+
+    #[test]
+    fn test_encode_text_format() {
+        let metrics = ClientMetrics::new();
+        metrics.record_request(&RequestMetrics {
+            method: &Method::GET,
+            status: Some(StatusCode::OK),
+            duration: Duration::from_millis(20),
+            error: None,
+        });
+        metrics.record_cache_lookup(false);
+
+        let text = metrics.encode_prometheus();
+        assert!(text.contains("# TYPE chromenet_requests_total counter\n"));
+        assert!(text.contains("chromenet_requests_total{status_class=\"2xx\"} 1\n"));
+        assert!(text.contains("chromenet_requests_total{status_class=\"error\"} 0\n"));
+        assert!(text.contains("# TYPE chromenet_request_duration_seconds histogram\n"));
+        assert!(text.contains("chromenet_request_duration_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(text.contains("chromenet_request_duration_seconds_bucket{le=\"0.025\"} 1\n"));
+        assert!(text.contains("chromenet_request_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("chromenet_request_duration_seconds_sum 0.02\n"));
+        assert!(text.contains("chromenet_pool_wait_seconds_count 0\n"));
+        assert!(text.contains("chromenet_cache_lookups_total{result=\"miss\"} 1\n"));
+        // Every sample line is `name[{labels}] value`
+        for line in text.lines().filter(|l| !l.starts_with('#')) {
+            let (_, value) = line.rsplit_once(' ').unwrap();
+            value.parse::<f64>().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_encode_dns_labels_resolvers() {
+        let cache = DnsMetricsSnapshot {
+            lookups: 10,
+            cache_hits: 7,
+            cache_entries: 3,
+            ..Default::default()
+        };
+        let doh = DnsMetricsSnapshot {
+            lookups: 3,
+            queries: 6,
+            failures: 1,
+            nxdomain: 1,
+            timed: 3,
+            lookup_time: Duration::from_millis(150),
+            ..Default::default()
+        };
+
+        let text = encode_dns(&[("cache", cache), ("doh", doh)]);
+        assert!(text.contains("# TYPE chromenet_dns_lookups_total counter\n"));
+        assert!(text.contains("chromenet_dns_lookups_total{resolver=\"cache\"} 10\n"));
+        assert!(text.contains("chromenet_dns_lookups_total{resolver=\"doh\"} 3\n"));
+        assert!(text.contains("chromenet_dns_cache_hits_total{resolver=\"cache\"} 7\n"));
+        assert!(text.contains("chromenet_dns_nxdomain_total{resolver=\"doh\"} 1\n"));
+        assert!(text.contains("# TYPE chromenet_dns_cache_entries gauge\n"));
+        assert!(text.contains("chromenet_dns_cache_entries{resolver=\"cache\"} 3\n"));
+        assert!(text.contains("chromenet_dns_lookup_seconds_sum{resolver=\"doh\"} 0.15\n"));
+        assert!(text.contains("chromenet_dns_lookup_seconds_count{resolver=\"doh\"} 3\n"));
+        assert_eq!(
+            text.matches("# TYPE chromenet_dns_queries_total").count(),
+            1
+        );
+
+        let text = DnsMetrics::new().encode_prometheus("sys\"tem");
+        assert!(text.contains("chromenet_dns_queries_total{resolver=\"sys\\\"tem\"} 0\n"));
+    }
+}
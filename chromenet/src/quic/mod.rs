@@ -6,6 +6,10 @@
 //! # Status
 //! This module provides the types and API structure for HTTP/3 support.
 //! Full implementation requires the `quinn` crate for QUIC transport.
+//! [`race`] holds the racing of QUIC against TCP and the per-origin
+//! memory of its outcomes, which the stream factory uses with
+//! [`ClientBuilder::http3`](crate::ClientBuilder::http3). Until a
+//! transport exists racing is inert: every race goes to TCP.
 //!
 //! # Example
 //! ```ignore
@@ -18,6 +22,8 @@
 
 mod config;
 mod connection;
+pub mod race;
 
 pub use config::QuicConfig;
 pub use connection::{QuicConnection, QuicConnectionBuilder};
+pub use race::{race, QuicOutcomes, QuicResult, Raced};
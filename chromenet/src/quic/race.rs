@@ -0,0 +1,276 @@
+//! Racing a QUIC handshake against TCP and TLS.
+//!
+//! Chromium mapping: `HttpStreamFactory::JobController`, which runs the
+//! alternative (QUIC) job next to the main (TCP) job, and
+//! `BrokenAlternativeServices`
+//!
+//! For an origin with HTTP/3 enabled, Chrome starts the QUIC handshake and
+//! holds the TCP connect back by a short delay, so a QUIC handshake that
+//! is known to be quick wins without a second connection, while a blocked
+//! UDP path costs no more than that delay. [`race`] does the same with
+//! two connect futures: whichever connects first carries the request, and
+//! a failed QUIC handshake starts TCP right away.
+//!
+//! [`QuicOutcomes`] remembers per origin how the race went, to bias the
+//! next one:
+//!
+//! | Outcome | Next race |
+//! |---------|-----------|
+//! | QUIC won in `t` | TCP waits `1.5 × t`, at most [`MAX_TCP_DELAY`] |
+//! | QUIC lost | Both start together |
+//! | QUIC failed | QUIC is skipped for 5 minutes, doubling per failure up to 2 days |
+//!
+//! With [`ClientBuilder::http3`](crate::ClientBuilder::http3), the stream
+//! factory races each new connection to an `https://` origin. That is
+//! inert until quinn is integrated:
+//! [`QuicConnectionBuilder::connect`](super::QuicConnectionBuilder::connect)
+//! has no transport, so QUIC always fails, the request goes over TCP and
+//! the origin is not raced again for a while. Nothing of this is built
+//! without the `quic` feature.
+
+use dashmap::DashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use url::Url;
+
+/// Longest head start a QUIC handshake gets over TCP.
+pub const MAX_TCP_DELAY: Duration = Duration::from_millis(300);
+
+/// How long QUIC is skipped for an origin after its first failure.
+pub const INITIAL_BROKEN_DURATION: Duration = Duration::from_secs(5 * 60);
+
+/// Longest QUIC is skipped for an origin that keeps failing.
+pub const MAX_BROKEN_DURATION: Duration = Duration::from_secs(2 * 24 * 60 * 60);
+
+/// The connection that won a [`race`].
+#[derive(Debug)]
+pub enum Raced<Q, T> {
+    /// The QUIC handshake finished first.
+    Quic(Q),
+    /// TCP and TLS finished first.
+    Tcp(T),
+}
+
+/// How the QUIC side of a race went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuicResult {
+    /// QUIC connected first, its handshake taking the duration.
+    Won(Duration),
+    /// TCP connected first while the QUIC handshake was still running.
+    Lost,
+    /// The QUIC handshake failed.
+    Failed,
+}
+
+/// Connect with `quic` and, after `tcp_delay` or as soon as `quic` fails,
+/// with `tcp`, returning whichever connects first and how QUIC fared. The
+/// other attempt is dropped.
+///
+/// If both fail, the error is TCP's, as the request would have had without
+/// QUIC.
+pub async fn race<Q, T, E, QF, TF>(
+    quic: QF,
+    tcp: TF,
+    tcp_delay: Duration,
+) -> (Result<Raced<Q, T>, E>, QuicResult)
+where
+    QF: Future<Output = Result<Q, E>>,
+    TF: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    tokio::pin!(quic);
+    tokio::pin!(tcp);
+    let delay = tokio::time::sleep(tcp_delay);
+    tokio::pin!(delay);
+
+    let mut quic_failed = false;
+    let mut tcp_started = tcp_delay.is_zero();
+    let mut tcp_error = None;
+    loop {
+        tokio::select! {
+            result = &mut quic, if !quic_failed => match result {
+                Ok(conn) => {
+                    return (Ok(Raced::Quic(conn)), QuicResult::Won(start.elapsed()));
+                }
+                // Only TCP's error is reported
+                Err(_) => {
+                    tracing::debug!(target: "chromenet::quic", "QUIC handshake failed, using TCP");
+                    if let Some(tcp_error) = tcp_error.take() {
+                        return (Err(tcp_error), QuicResult::Failed);
+                    }
+                    quic_failed = true;
+                    tcp_started = true;
+                }
+            },
+            () = &mut delay, if !tcp_started => tcp_started = true,
+            result = &mut tcp, if tcp_started && tcp_error.is_none() => match result {
+                Ok(conn) => {
+                    let quic = if quic_failed {
+                        QuicResult::Failed
+                    } else {
+                        QuicResult::Lost
+                    };
+                    return (Ok(Raced::Tcp(conn)), quic);
+                }
+                Err(e) if quic_failed => return (Err(e), QuicResult::Failed),
+                // QUIC may still connect
+                Err(e) => tcp_error = Some(e),
+            },
+        }
+    }
+}
+
+/// What the races to an origin taught.
+#[derive(Debug, Clone, Copy, Default)]
+struct OriginState {
+    /// Handshake time of the last QUIC win
+    handshake: Option<Duration>,
+    /// QUIC failures in a row
+    failures: u32,
+    broken_until: Option<Instant>,
+}
+
+/// Outcomes of QUIC races per origin, deciding whether and how the next
+/// race to the origin runs.
+///
+/// Clones share the outcomes.
+#[derive(Debug, Clone, Default)]
+pub struct QuicOutcomes {
+    origins: Arc<DashMap<String, OriginState>>,
+}
+
+impl QuicOutcomes {
+    /// No outcomes yet: every origin races, TCP without delay.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether QUIC should be tried for the origin of `url`, i.e. it has
+    /// not failed there recently.
+    pub fn should_race(&self, url: &Url) -> bool {
+        self.origins
+            .get(&origin_key(url))
+            .and_then(|state| state.broken_until)
+            .is_none_or(|until| Instant::now() >= until)
+    }
+
+    /// How long TCP waits for QUIC in the next race to the origin of `url`.
+    pub fn tcp_delay(&self, url: &Url) -> Duration {
+        self.origins
+            .get(&origin_key(url))
+            .and_then(|state| state.handshake)
+            .map_or(Duration::ZERO, |handshake| {
+                (handshake * 3 / 2).min(MAX_TCP_DELAY)
+            })
+    }
+
+    /// Remember how the QUIC side of a race to the origin of `url` went.
+    pub fn record(&self, url: &Url, result: QuicResult) {
+        let mut state = self.origins.entry(origin_key(url)).or_default();
+        match result {
+            QuicResult::Won(handshake) => {
+                *state = OriginState {
+                    handshake: Some(handshake),
+                    ..OriginState::default()
+                }
+            }
+            QuicResult::Lost => state.handshake = None,
+            QuicResult::Failed => {
+                state.handshake = None;
+                state.failures = state.failures.saturating_add(1);
+                let backoff = INITIAL_BROKEN_DURATION
+                    .saturating_mul(1u32 << (state.failures - 1).min(16))
+                    .min(MAX_BROKEN_DURATION);
+                state.broken_until = Some(Instant::now() + backoff);
+            }
+        }
+    }
+}
+
+fn origin_key(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn after<V>(delay: u64, value: V) -> V {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        value
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_race() {
+        // A quick QUIC handshake wins within TCP's delay
+        let (result, quic) = race(
+            after(50, Ok::<_, ()>("quic")),
+            after(10, Ok("tcp")),
+            Duration::from_millis(100),
+        )
+        .await;
+        assert!(matches!(result, Ok(Raced::Quic("quic"))));
+        assert_eq!(quic, QuicResult::Won(Duration::from_millis(50)));
+
+        // A failed handshake starts TCP at once
+        let start = Instant::now();
+        let (result, quic) = race(
+            after(20, Err::<(), _>("quic")),
+            after(10, Ok("tcp")),
+            Duration::from_millis(100),
+        )
+        .await;
+        assert!(matches!(result, Ok(Raced::Tcp("tcp"))));
+        assert_eq!(quic, QuicResult::Failed);
+        assert_eq!(start.elapsed(), Duration::from_millis(30));
+
+        // A failed TCP connect waits for QUIC, and TCP's error is reported
+        let (result, quic) = race(
+            after(50, Ok("quic")),
+            after(10, Err::<(), _>("tcp")),
+            Duration::ZERO,
+        )
+        .await;
+        assert!(matches!(result, Ok(Raced::Quic("quic"))));
+        assert!(matches!(quic, QuicResult::Won(_)));
+        let (result, quic) = race(
+            after(50, Err::<(), _>("quic")),
+            after(10, Err::<(), _>("tcp")),
+            Duration::ZERO,
+        )
+        .await;
+        assert!(matches!(result, Err("tcp")));
+        assert_eq!(quic, QuicResult::Failed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_outcomes() {
+        let outcomes = QuicOutcomes::new();
+        let url = Url::parse("https://example.com/a").unwrap();
+        let same_origin = Url::parse("https://example.com/b").unwrap();
+        assert!(outcomes.should_race(&url));
+        assert_eq!(outcomes.tcp_delay(&url), Duration::ZERO);
+
+        outcomes.record(&url, QuicResult::Won(Duration::from_millis(40)));
+        assert_eq!(outcomes.tcp_delay(&same_origin), Duration::from_millis(60));
+        outcomes.record(&url, QuicResult::Won(Duration::from_secs(1)));
+        assert_eq!(outcomes.tcp_delay(&url), MAX_TCP_DELAY);
+        outcomes.record(&url, QuicResult::Lost);
+        assert_eq!(outcomes.tcp_delay(&url), Duration::ZERO);
+
+        outcomes.record(&url, QuicResult::Failed);
+        assert!(!outcomes.should_race(&url));
+        tokio::time::advance(INITIAL_BROKEN_DURATION).await;
+        assert!(outcomes.should_race(&url));
+
+        // The second failure in a row doubles the wait
+        outcomes.record(&url, QuicResult::Failed);
+        tokio::time::advance(INITIAL_BROKEN_DURATION).await;
+        assert!(!outcomes.should_race(&url));
+        tokio::time::advance(INITIAL_BROKEN_DURATION).await;
+        assert!(outcomes.should_race(&url));
+        assert!(outcomes.should_race(&Url::parse("https://other.test/").unwrap()));
+    }
+}
@@ -0,0 +1,415 @@
+//! Export and import of what a client learned while browsing.
+//!
+//! A [`SessionSnapshot`] bundles the state that makes a session look
+//! "warmed up" to servers into one file, so it can be moved to another
+//! process or machine:
+//!
+//! - cookies, as saved by [`persistence`](crate::cookies::persistence);
+//! - HSTS entries learned from `Strict-Transport-Security` headers (the
+//!   preload list is not included, every store builds its own);
+//! - origins that required HTTP/1.1, from the client's
+//!   [`HttpServerProperties`](crate::http::HttpServerProperties);
+//! - HTTP cache entries with their validators (`ETag`, `Last-Modified`)
+//!   and bodies, so the restored session revalidates instead of
+//!   downloading again.
+//!
+//! Expiry times are absolute, so time spent on disk counts: what expired
+//! in the meantime is dropped on restore, and cache entries come back as
+//! old as they would be in the original session.
+//!
+//! Alternative services (`Alt-Svc`) are not tracked by this client yet and
+//! so are not part of a snapshot.
+//!
+//! ```no_run
+//! use chromenet::session::SessionSnapshot;
+//! use chromenet::tls::HstsStore;
+//! use chromenet::Client;
+//! use std::path::Path;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let client = Client::new();
+//! let hsts = HstsStore::new();
+//! // ... browse ...
+//! SessionSnapshot::capture(&client)
+//!     .with_hsts(&hsts)
+//!     .save(Path::new("session.json"))?;
+//!
+//! // Elsewhere
+//! let snapshot = SessionSnapshot::load(Path::new("session.json"))?;
+//! let client = Client::new();
+//! let hsts = HstsStore::new();
+//! snapshot.restore_into(&client);
+//! snapshot.restore_hsts(&hsts);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::client::Client;
+use crate::cookies::persistence::{self, PersistentCookie};
+use crate::http::httpcache::{CacheEntry, CacheKey, HttpCache};
+use crate::tls::hsts::{HstsEntry, HstsStore};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use time::OffsetDateTime;
+use url::Url;
+
+/// Version of the snapshot file format.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Learned session state, see [`crate::session`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    version: u32,
+    /// When the snapshot was taken, unix seconds
+    captured_at: i64,
+    #[serde(default)]
+    cookies: Vec<PersistentCookie>,
+    #[serde(default)]
+    hsts: Vec<HstsSnapshot>,
+    #[serde(default)]
+    http11_required: Vec<OriginSnapshot>,
+    #[serde(default)]
+    cache: Vec<CacheSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HstsSnapshot {
+    host: String,
+    include_subdomains: bool,
+    expiry: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OriginSnapshot {
+    /// `scheme://host:port`
+    origin: String,
+    expiry: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheSnapshot {
+    url: String,
+    method: String,
+    /// Top-frame site of the partition, `None` for the shared one
+    partition: Option<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Base64, still in its content coding
+    body: String,
+    /// Age of the response when the snapshot was taken, milliseconds
+    age_ms: u64,
+    /// Freshness lifetime, milliseconds
+    ttl_ms: Option<u64>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl SessionSnapshot {
+    /// Snapshot the cookies and server properties of `client`.
+    pub fn capture(client: &Client) -> Self {
+        let now = OffsetDateTime::now_utc();
+        let http11_required = client
+            .server_properties()
+            .http11_required_origins()
+            .into_iter()
+            .map(|(origin, remaining)| OriginSnapshot {
+                origin,
+                expiry: (now + remaining).unix_timestamp(),
+            })
+            .collect();
+        Self {
+            version: SNAPSHOT_VERSION,
+            captured_at: now.unix_timestamp(),
+            cookies: persistence::export(client.cookie_store()),
+            hsts: Vec::new(),
+            http11_required,
+            cache: Vec::new(),
+        }
+    }
+
+    /// Add the HSTS entries `store` learned from headers.
+    pub fn with_hsts(mut self, store: &HstsStore) -> Self {
+        self.hsts = store
+            .dynamic_entries()
+            .into_iter()
+            .filter_map(|(host, entry)| {
+                Some(HstsSnapshot {
+                    host,
+                    include_subdomains: entry.include_subdomains,
+                    expiry: entry.expires?.unix_timestamp(),
+                })
+            })
+            .collect();
+        self
+    }
+
+    /// Add the entries of `cache`, fresh and stale.
+    ///
+    /// Entries with a header value that is not visible ASCII are left out.
+    pub fn with_cache(mut self, cache: &HttpCache) -> Self {
+        self.cache = cache
+            .entries()
+            .into_iter()
+            .filter_map(|(key, entry)| CacheSnapshot::new(&key, &entry, cache.now()))
+            .collect();
+        self
+    }
+
+    /// Number of cookies in the snapshot.
+    pub fn cookie_count(&self) -> usize {
+        self.cookies.len()
+    }
+
+    /// Number of HSTS entries in the snapshot.
+    pub fn hsts_count(&self) -> usize {
+        self.hsts.len()
+    }
+
+    /// Number of cache entries in the snapshot.
+    pub fn cache_count(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Write the snapshot to `path` as JSON.
+    ///
+    /// Cookie values are stored in plaintext, like
+    /// [`persistence::save_cookies`] does.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+
+    /// Read a snapshot written by [`save`](Self::save).
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let snapshot: Self = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported snapshot version {}", snapshot.version),
+            ));
+        }
+        Ok(snapshot)
+    }
+
+    /// Add the cookies and server properties to `client`. Returns how many
+    /// cookies were added; expired ones are skipped.
+    pub fn restore_into(&self, client: &Client) -> usize {
+        let now = OffsetDateTime::now_utc();
+        for origin in &self.http11_required {
+            let remaining = origin.expiry - now.unix_timestamp();
+            if remaining > 0 {
+                client
+                    .server_properties()
+                    .restore_http11_required(&origin.origin, Duration::from_secs(remaining as u64));
+            }
+        }
+        persistence::import(client.cookie_store(), self.cookies.clone())
+    }
+
+    /// Add the HSTS entries to `store`. Returns how many were added;
+    /// expired ones are skipped.
+    pub fn restore_hsts(&self, store: &HstsStore) -> usize {
+        let now = store.now();
+        let mut restored = 0;
+        for sts in &self.hsts {
+            let Ok(expires) = OffsetDateTime::from_unix_timestamp(sts.expiry) else {
+                continue;
+            };
+            let entry = HstsEntry {
+                include_subdomains: sts.include_subdomains,
+                expires: Some(expires),
+            };
+            if !entry.is_expired_at(now) {
+                store.restore(&sts.host, entry);
+                restored += 1;
+            }
+        }
+        restored
+    }
+
+    /// Add the cache entries to `cache`, aged by the time since the
+    /// snapshot was taken. Returns how many were added.
+    pub fn restore_cache(&self, cache: &HttpCache) -> usize {
+        let elapsed = OffsetDateTime::now_utc().unix_timestamp() - self.captured_at;
+        let elapsed = Duration::from_secs(elapsed.max(0) as u64);
+        let mut restored = 0;
+        for snapshot in &self.cache {
+            if let Some((key, entry)) = snapshot.to_entry(elapsed, cache.now()) {
+                cache.restore(key, entry);
+                restored += 1;
+            }
+        }
+        restored
+    }
+}
+
+impl CacheSnapshot {
+    fn new(key: &CacheKey, entry: &CacheEntry, now: SystemTime) -> Option<Self> {
+        let headers = entry
+            .headers
+            .iter()
+            .map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            url: key.url().to_string(),
+            method: key.method().to_string(),
+            partition: key
+                .isolation_key()
+                .map(|nik| nik.top_frame_site().to_string()),
+            status: entry.status.as_u16(),
+            headers,
+            body: STANDARD.encode(&entry.body),
+            age_ms: entry.current_age_at(now).as_millis() as u64,
+            ttl_ms: entry.ttl.map(|ttl| ttl.as_millis() as u64),
+            etag: entry.etag.clone(),
+            last_modified: entry.last_modified.clone(),
+        })
+    }
+
+    /// The entry, `elapsed` older than when captured, cached at `now`.
+    /// `None` if the snapshot is malformed.
+    fn to_entry(&self, elapsed: Duration, now: SystemTime) -> Option<(CacheKey, CacheEntry)> {
+        let url = Url::parse(&self.url).ok()?;
+        let mut key = CacheKey::new(&url, &self.method);
+        if let Some(site) = &self.partition {
+            key = key.with_isolation_key(NetworkIsolationKey::opaque(site));
+        }
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes()).ok()?,
+                HeaderValue::from_str(value).ok()?,
+            );
+        }
+        let entry = CacheEntry {
+            status: StatusCode::from_u16(self.status).ok()?,
+            headers,
+            body: STANDARD.decode(&self.body).ok()?.into(),
+            cached_at: now,
+            inserted_at: Instant::now(),
+            ttl: self.ttl_ms.map(Duration::from_millis),
+            initial_age: Duration::from_millis(self.age_ms) + elapsed,
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        };
+        Some((key, entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn cached_response(headers: &[(&str, &str)], body: &str) -> http::Response<()> {
+        let mut builder = http::Response::builder().status(200);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder
+            .header("content-length", body.len().to_string())
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip_through_file() {
+        let client = Client::new();
+        client
+            .cookie_store()
+            .parse_and_save_cookie(&Url::parse("https://example.com/").unwrap(), "id=42");
+        let url = Url::parse("https://h1.example.com/").unwrap();
+        client.server_properties().set_http11_required(&url);
+        let hsts = HstsStore::new();
+        hsts.add_from_header("secure.example.com", "max-age=3600; includeSubDomains");
+        let cache = HttpCache::new();
+        let page = Url::parse("https://example.com/page").unwrap();
+        cache.store(
+            &page,
+            "GET",
+            &cached_response(
+                &[("etag", "\"v1\""), ("cache-control", "max-age=60")],
+                "body",
+            ),
+            "body".into(),
+        );
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        SessionSnapshot::capture(&client)
+            .with_hsts(&hsts)
+            .with_cache(&cache)
+            .save(&path)
+            .unwrap();
+
+        let snapshot = SessionSnapshot::load(&path).unwrap();
+        assert_eq!(snapshot.cookie_count(), 1);
+        assert_eq!(snapshot.hsts_count(), 1);
+        assert_eq!(snapshot.cache_count(), 1);
+
+        let restored = Client::new();
+        let restored_hsts = HstsStore::new();
+        let restored_cache = HttpCache::new();
+        assert_eq!(snapshot.restore_into(&restored), 1);
+        assert_eq!(snapshot.restore_hsts(&restored_hsts), 1);
+        assert_eq!(snapshot.restore_cache(&restored_cache), 1);
+
+        let cookies = restored
+            .cookie_store()
+            .get_cookies_for_url(&Url::parse("https://example.com/").unwrap());
+        assert_eq!(cookies[0].value, "42");
+        assert!(restored.server_properties().requires_http11(&url));
+        assert!(restored_hsts.should_upgrade("a.secure.example.com"));
+        let entry = restored_cache.get(&page, "GET").unwrap();
+        assert_eq!(entry.body, "body");
+        assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[test]
+    fn test_restore_ages_cache_entries() {
+        let cache = HttpCache::new();
+        let page = Url::parse("https://example.com/").unwrap();
+        cache.store(
+            &page,
+            "GET",
+            &cached_response(
+                &[
+                    ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+                    ("cache-control", "max-age=60"),
+                ],
+                "x",
+            ),
+            "x".into(),
+        );
+        let mut snapshot = SessionSnapshot::capture(&Client::new()).with_cache(&cache);
+        // Taken two minutes ago: stale now, but still revalidatable
+        snapshot.captured_at -= 120;
+
+        let restored = HttpCache::new();
+        snapshot.restore_cache(&restored);
+        assert!(restored.get(&page, "GET").is_none());
+        let headers = restored.get_conditional_headers(&page, "GET").unwrap();
+        assert_eq!(
+            headers[http::header::IF_MODIFIED_SINCE],
+            "Mon, 01 Jan 2024 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        fs::write(&path, r#"{"version": 99, "captured_at": 0}"#).unwrap();
+        let err = SessionSnapshot::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
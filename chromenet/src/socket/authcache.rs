@@ -2,13 +2,21 @@
 //!
 //! Caches authentication credentials to avoid re-prompting users.
 //! Based on Chromium's HttpAuthCache.
+//!
+//! Entries are keyed by protection space: origin, realm and scheme. Each
+//! entry also remembers the URL paths it was used for, so later requests
+//! under those paths can send `Authorization` preemptively instead of
+//! waiting for a 401.
 
+use crate::base::secret::SecretString;
 use crate::http::digestauth::DigestAuthHandler;
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use url::Url;
 
 /// Authentication scheme.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AuthScheme {
     /// Basic authentication (base64 encoded)
     Basic,
@@ -24,7 +32,7 @@ pub struct BasicAuthEntry {
     /// Username
     pub username: String,
     /// Password
-    pub password: String,
+    pub password: SecretString,
 }
 
 impl BasicAuthEntry {
@@ -37,14 +45,14 @@ impl BasicAuthEntry {
         Self {
             realm: realm.into(),
             username: username.into(),
-            password: password.into(),
+            password: SecretString::new(password),
         }
     }
 
     /// Generate the Authorization header value.
     pub fn to_header_value(&self) -> String {
         use base64::{engine::general_purpose, Engine as _};
-        let creds = format!("{}:{}", self.username, self.password);
+        let creds = format!("{}:{}", self.username, self.password.expose());
         let encoded = general_purpose::STANDARD.encode(creds);
         format!("Basic {}", encoded)
     }
@@ -57,8 +65,8 @@ pub struct DigestAuthSession {
     pub handler: DigestAuthHandler,
     /// Username for this session
     pub username: String,
-    /// Password for this session (consider using Zeroizing in production)
-    pub password: String,
+    /// Password for this session
+    pub password: SecretString,
 }
 
 impl DigestAuthSession {
@@ -71,7 +79,7 @@ impl DigestAuthSession {
         Self {
             handler,
             username: username.into(),
-            password: password.into(),
+            password: SecretString::new(password),
         }
     }
 
@@ -80,16 +88,115 @@ impl DigestAuthSession {
     /// Increments the nonce count automatically.
     pub fn generate_auth_header(&mut self, method: &str, uri: &str) -> String {
         self.handler
-            .generate_auth_token(method, uri, &self.username, &self.password)
+            .generate_auth_token(method, uri, &self.username, self.password.expose())
     }
 }
 
+/// Protection space: (origin, realm, scheme), as in Chromium's HttpAuthCache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EntryKey {
+    origin: String,
+    realm: String,
+    scheme: AuthScheme,
+}
+
+/// Cached credentials for one protection space.
+#[derive(Debug, Clone)]
+pub struct AuthEntry {
+    /// Realm from WWW-Authenticate header
+    pub realm: String,
+    /// Authentication scheme
+    pub scheme: AuthScheme,
+    /// Username
+    pub username: String,
+    /// Password
+    pub password: SecretString,
+    /// Path prefixes known to belong to this protection space
+    pub paths: Vec<String>,
+    /// Latest Digest challenge, None until the server sent one
+    digest: Option<DigestAuthHandler>,
+}
+
+impl AuthEntry {
+    fn new(realm: &str, scheme: AuthScheme, username: &str, password: &str) -> Self {
+        Self {
+            realm: realm.to_string(),
+            scheme,
+            username: username.to_string(),
+            password: SecretString::new(password),
+            paths: Vec::new(),
+            digest: None,
+        }
+    }
+
+    /// Add `path` to the protection space, dropping paths it covers.
+    fn add_path(&mut self, path: &str) {
+        if self.paths.iter().any(|p| path.starts_with(p.as_str())) {
+            return;
+        }
+        self.paths.retain(|p| !p.starts_with(path));
+        self.paths.push(path.to_string());
+    }
+
+    /// Length of the longest path prefix matching `path`.
+    fn match_len(&self, path: &str) -> Option<usize> {
+        self.paths
+            .iter()
+            .filter(|p| path.starts_with(p.as_str()))
+            .map(|p| p.len())
+            .max()
+    }
+
+    /// Authorization header value, or None for a Digest entry that has
+    /// not been challenged yet.
+    fn header_value(&mut self, method: &str, uri: &str) -> Option<String> {
+        match self.scheme {
+            AuthScheme::Basic => Some(
+                BasicAuthEntry::new(self.realm.as_str(), &self.username, self.password.expose())
+                    .to_header_value(),
+            ),
+            AuthScheme::Digest => {
+                let handler = self.digest.as_mut()?;
+                Some(handler.generate_auth_token(
+                    method,
+                    uri,
+                    &self.username,
+                    self.password.expose(),
+                ))
+            }
+        }
+    }
+}
+
+/// An Authorization header generated from the cache.
+#[derive(Debug, Clone)]
+pub struct AuthHeader {
+    /// Realm of the entry used
+    pub realm: String,
+    /// Scheme of the entry used
+    pub scheme: AuthScheme,
+    /// Header value
+    pub value: SecretString,
+}
+
+/// Basic credentials as stored on disk.
+#[derive(Serialize, Deserialize)]
+struct PersistentEntry {
+    origin: String,
+    realm: String,
+    username: String,
+    password: String,
+    paths: Vec<String>,
+}
+
 /// Thread-safe authentication cache.
-/// Keys entries by host:port + realm.
+///
+/// Keys entries by (origin, realm, scheme). The host/port methods are kept
+/// for callers without a URL, such as proxies, and use `host:port` as the
+/// origin.
 #[derive(Clone)]
 pub struct AuthCache {
-    basic_entries: Arc<DashMap<String, BasicAuthEntry>>,
-    digest_sessions: Arc<DashMap<String, DigestAuthSession>>,
+    entries: Arc<DashMap<EntryKey, AuthEntry>>,
 }
 
 impl Default for AuthCache {
@@ -98,46 +205,180 @@ impl Default for AuthCache {
     }
 }
 
+/// The parent directory of a URL path (Chromium's `GetParentDirectory`).
+fn parent_directory(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(i) => &path[..=i],
+        None => "/",
+    }
+}
+
 impl AuthCache {
     /// Create a new empty auth cache.
     pub fn new() -> Self {
         Self {
-            basic_entries: Arc::new(DashMap::new()),
-            digest_sessions: Arc::new(DashMap::new()),
+            entries: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Origin key for a URL: scheme, host and port.
+    fn url_origin(url: &Url) -> String {
+        format!(
+            "{}://{}:{}",
+            url.scheme(),
+            url.host_str().unwrap_or_default().to_lowercase(),
+            url.port_or_known_default().unwrap_or(0)
+        )
+    }
+
+    /// Origin key for the host/port methods.
+    fn host_origin(host: &str, port: u16) -> String {
+        format!("{}:{}", host.to_lowercase(), port)
+    }
+
+    fn key(origin: String, realm: &str, scheme: AuthScheme) -> EntryKey {
+        EntryKey {
+            origin,
+            realm: realm.to_string(),
+            scheme,
+        }
+    }
+
+    // --- Protection Space Methods ---
+
+    /// Add or update credentials for a protection space.
+    ///
+    /// The parent directory of `url`'s path is added to the entry's paths.
+    /// A Digest entry is used preemptively once it has a challenge, see
+    /// [`set_digest_challenge`](Self::set_digest_challenge).
+    pub fn add(&self, url: &Url, realm: &str, scheme: AuthScheme, username: &str, password: &str) {
+        let key = Self::key(Self::url_origin(url), realm, scheme);
+        let mut entry = self
+            .entries
+            .entry(key)
+            .or_insert_with(|| AuthEntry::new(realm, scheme, username, password));
+        if entry.username != username || entry.password != password {
+            entry.username = username.to_string();
+            entry.password = SecretString::new(password);
+            // A new identity starts a new nonce count
+            entry.digest = None;
         }
+        entry.add_path(parent_directory(url.path()));
+    }
+
+    /// Set the latest Digest challenge for a cached entry, keeping its
+    /// credentials.
+    ///
+    /// Used after the first challenge and when the server answers with
+    /// `stale=true`: the nonce expired but the credentials are still valid.
+    /// Returns false if no Digest entry is cached for the challenge's realm.
+    pub fn set_digest_challenge(&self, url: &Url, handler: DigestAuthHandler) -> bool {
+        let key = Self::key(Self::url_origin(url), handler.realm(), AuthScheme::Digest);
+        match self.entries.get_mut(&key) {
+            Some(mut entry) => {
+                entry.digest = Some(handler);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lookup credentials for a protection space.
+    pub fn lookup(&self, url: &Url, realm: &str, scheme: AuthScheme) -> Option<AuthEntry> {
+        let key = Self::key(Self::url_origin(url), realm, scheme);
+        self.entries.get(&key).map(|e| e.clone())
+    }
+
+    /// Lookup the entry whose protection space contains `url`'s path.
+    ///
+    /// The entry with the longest matching path wins.
+    pub fn lookup_by_path(&self, url: &Url) -> Option<AuthEntry> {
+        self.find_by_path(url)
+            .and_then(|key| self.entries.get(&key).map(|e| e.clone()))
+    }
+
+    fn find_by_path(&self, url: &Url) -> Option<EntryKey> {
+        let origin = Self::url_origin(url);
+        self.entries
+            .iter()
+            .filter(|e| e.key().origin == origin)
+            .filter_map(|e| e.match_len(url.path()).map(|len| (len, e.key().clone())))
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, key)| key)
+    }
+
+    /// Generate a preemptive Authorization header for a request to `url`.
+    ///
+    /// Returns None if no cached protection space contains the URL's path.
+    pub fn auth_header(&self, url: &Url, method: &str) -> Option<AuthHeader> {
+        let key = self.find_by_path(url)?;
+        let mut entry = self.entries.get_mut(&key)?;
+        let uri = &url[url::Position::BeforePath..url::Position::AfterQuery];
+        let value = entry.header_value(method, uri)?;
+        Some(AuthHeader {
+            realm: key.realm,
+            scheme: key.scheme,
+            value: SecretString::new(value),
+        })
     }
 
-    /// Generate cache key from host, port, and realm.
-    fn key(host: &str, port: u16, realm: &str) -> String {
-        format!("{}:{}:{}", host.to_lowercase(), port, realm)
+    /// Remove the credentials for a protection space.
+    ///
+    /// Returns true if an entry was removed.
+    pub fn remove(&self, url: &Url, realm: &str, scheme: AuthScheme) -> bool {
+        let key = Self::key(Self::url_origin(url), realm, scheme);
+        self.entries.remove(&key).is_some()
     }
 
     // --- Basic Auth Methods ---
 
     /// Lookup cached Basic credentials for a host and realm.
     pub fn lookup_basic(&self, host: &str, port: u16, realm: &str) -> Option<BasicAuthEntry> {
-        let key = Self::key(host, port, realm);
-        self.basic_entries.get(&key).map(|e| e.clone())
+        let key = Self::key(Self::host_origin(host, port), realm, AuthScheme::Basic);
+        self.entries
+            .get(&key)
+            .map(|e| BasicAuthEntry::new(e.realm.as_str(), &e.username, e.password.expose()))
     }
 
     /// Store Basic credentials for a host and realm.
     pub fn store_basic(&self, host: &str, port: u16, realm: &str, entry: BasicAuthEntry) {
-        let key = Self::key(host, port, realm);
-        self.basic_entries.insert(key, entry);
+        let key = Self::key(Self::host_origin(host, port), realm, AuthScheme::Basic);
+        let mut cached = AuthEntry::new(
+            realm,
+            AuthScheme::Basic,
+            &entry.username,
+            entry.password.expose(),
+        );
+        cached.add_path("/");
+        self.entries.insert(key, cached);
     }
 
     // --- Digest Auth Methods ---
 
     /// Lookup cached Digest session for a host and realm.
     pub fn lookup_digest(&self, host: &str, port: u16, realm: &str) -> Option<DigestAuthSession> {
-        let key = Self::key(host, port, realm);
-        self.digest_sessions.get(&key).map(|e| e.clone())
+        let key = Self::key(Self::host_origin(host, port), realm, AuthScheme::Digest);
+        let entry = self.entries.get(&key)?;
+        let handler = entry.digest.clone()?;
+        Some(DigestAuthSession::new(
+            handler,
+            entry.username.as_str(),
+            entry.password.expose(),
+        ))
     }
 
     /// Store a Digest session for a host and realm.
     pub fn store_digest(&self, host: &str, port: u16, realm: &str, session: DigestAuthSession) {
-        let key = Self::key(host, port, realm);
-        self.digest_sessions.insert(key, session);
+        let key = Self::key(Self::host_origin(host, port), realm, AuthScheme::Digest);
+        let mut cached = AuthEntry::new(
+            realm,
+            AuthScheme::Digest,
+            &session.username,
+            session.password.expose(),
+        );
+        cached.digest = Some(session.handler);
+        cached.add_path("/");
+        self.entries.insert(key, cached);
     }
 
     /// Generate Authorization header for Digest auth.
@@ -152,35 +393,78 @@ impl AuthCache {
         method: &str,
         uri: &str,
     ) -> Option<String> {
-        let key = Self::key(host, port, realm);
-        self.digest_sessions
-            .get_mut(&key)
-            .map(|mut session| session.generate_auth_header(method, uri))
+        let key = Self::key(Self::host_origin(host, port), realm, AuthScheme::Digest);
+        self.entries.get_mut(&key)?.header_value(method, uri)
     }
 
     // --- General Methods ---
 
-    /// Remove all credentials for a host (all realms).
+    /// Remove all credentials for a host (all realms and URL schemes).
     pub fn remove_host(&self, host: &str, port: u16) {
-        let prefix = format!("{}:{}", host.to_lowercase(), port);
-        self.basic_entries.retain(|k, _| !k.starts_with(&prefix));
-        self.digest_sessions.retain(|k, _| !k.starts_with(&prefix));
+        let origin = Self::host_origin(host, port);
+        let suffix = format!("://{}", origin);
+        self.entries
+            .retain(|k, _| k.origin != origin && !k.origin.ends_with(&suffix));
     }
 
     /// Clear all cached credentials.
     pub fn clear(&self) {
-        self.basic_entries.clear();
-        self.digest_sessions.clear();
+        self.entries.clear();
     }
 
     /// Get total number of cached entries.
     pub fn len(&self) -> usize {
-        self.basic_entries.len() + self.digest_sessions.len()
+        self.entries.len()
     }
 
     /// Check if cache is empty.
     pub fn is_empty(&self) -> bool {
-        self.basic_entries.is_empty() && self.digest_sessions.is_empty()
+        self.entries.is_empty()
+    }
+
+    // --- Persistence ---
+
+    /// Save Basic credentials to a JSON file.
+    ///
+    /// Digest entries are not saved: their nonces do not outlive the server
+    /// session. The file holds passwords in plain text.
+    pub fn save_to_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let entries: Vec<PersistentEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.key().scheme == AuthScheme::Basic)
+            .map(|e| PersistentEntry {
+                origin: e.key().origin.clone(),
+                realm: e.realm.clone(),
+                username: e.username.clone(),
+                password: e.password.expose().to_string(),
+                paths: e.paths.clone(),
+            })
+            .collect();
+
+        let json = serde_json::to_string_pretty(&entries)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, json)
+    }
+
+    /// Load Basic credentials saved with [`save_to_file`](Self::save_to_file).
+    ///
+    /// Returns the number of entries loaded.
+    pub fn load_from_file(&self, path: &std::path::Path) -> std::io::Result<usize> {
+        let json = std::fs::read_to_string(path)?;
+        let entries: Vec<PersistentEntry> = serde_json::from_str(&json)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        let loaded = entries.len();
+        for e in entries {
+            let mut entry = AuthEntry::new(&e.realm, AuthScheme::Basic, &e.username, &e.password);
+            for path in &e.paths {
+                entry.add_path(path);
+            }
+            self.entries
+                .insert(Self::key(e.origin, &e.realm, AuthScheme::Basic), entry);
+        }
+        Ok(loaded)
     }
 }
 
@@ -308,4 +592,161 @@ mod tests {
         assert!(header.is_some());
         assert!(header.unwrap().starts_with("Digest username=\"user\""));
     }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parent_directory() {
+        assert_eq!(parent_directory("/a/b/c"), "/a/b/");
+        assert_eq!(parent_directory("/a/"), "/a/");
+        assert_eq!(parent_directory("/"), "/");
+    }
+
+    #[test]
+    fn test_keyed_by_origin_realm_and_scheme() {
+        let cache = AuthCache::new();
+        let page = url("https://example.com/docs/index.html");
+        cache.add(&page, "R", AuthScheme::Basic, "basic", "p");
+        cache.add(&page, "R", AuthScheme::Digest, "digest", "p");
+
+        assert_eq!(
+            cache
+                .lookup(&page, "R", AuthScheme::Basic)
+                .unwrap()
+                .username,
+            "basic"
+        );
+        assert_eq!(
+            cache
+                .lookup(&page, "R", AuthScheme::Digest)
+                .unwrap()
+                .username,
+            "digest"
+        );
+        // Same host over http is a different origin
+        assert!(cache
+            .lookup(&url("http://example.com/docs/"), "R", AuthScheme::Basic)
+            .is_none());
+    }
+
+    #[test]
+    fn test_lookup_by_path() {
+        let cache = AuthCache::new();
+        cache.add(
+            &url("https://example.com/a/page"),
+            "outer",
+            AuthScheme::Basic,
+            "u1",
+            "p",
+        );
+        cache.add(
+            &url("https://example.com/a/b/page"),
+            "inner",
+            AuthScheme::Basic,
+            "u2",
+            "p",
+        );
+
+        let found = |path: &str| {
+            cache
+                .lookup_by_path(&url(&format!("https://example.com{}", path)))
+                .map(|e| e.realm)
+        };
+        assert_eq!(found("/a/other").as_deref(), Some("outer"));
+        assert_eq!(found("/a/b/c/d").as_deref(), Some("inner"));
+        assert_eq!(found("/elsewhere"), None);
+    }
+
+    #[test]
+    fn test_add_path_collapses_subpaths() {
+        let cache = AuthCache::new();
+        cache.add(&url("http://h/a/b/x"), "R", AuthScheme::Basic, "u", "p");
+        cache.add(&url("http://h/a/c/x"), "R", AuthScheme::Basic, "u", "p");
+        cache.add(&url("http://h/a/x"), "R", AuthScheme::Basic, "u", "p");
+
+        let entry = cache
+            .lookup(&url("http://h/"), "R", AuthScheme::Basic)
+            .unwrap();
+        assert_eq!(entry.paths, ["/a/"]);
+    }
+
+    #[test]
+    fn test_digest_header_after_challenge() {
+        let cache = AuthCache::new();
+        let page = url("http://example.com/api/items?id=1");
+        cache.add(&page, "api", AuthScheme::Digest, "user", "pass");
+
+        // No challenge yet: nothing to send
+        assert!(cache.auth_header(&page, "GET").is_none());
+
+        let handler =
+            DigestAuthHandler::parse_challenge(r#"realm="api", nonce="n1", qop="auth""#).unwrap();
+        assert!(cache.set_digest_challenge(&page, handler));
+
+        let first = cache.auth_header(&page, "GET").unwrap();
+        assert_eq!(first.scheme, AuthScheme::Digest);
+        assert!(first.value.expose().contains("uri=\"/api/items?id=1\""));
+        assert!(first.value.expose().contains("nc=00000001"));
+
+        let second = cache.auth_header(&page, "GET").unwrap();
+        assert!(second.value.expose().contains("nc=00000002"));
+    }
+
+    #[test]
+    fn test_stale_challenge_keeps_credentials() {
+        let cache = AuthCache::new();
+        let page = url("http://example.com/");
+        cache.add(&page, "api", AuthScheme::Digest, "user", "pass");
+
+        let stale =
+            DigestAuthHandler::parse_challenge(r#"realm="api", nonce="new", stale=true"#).unwrap();
+        assert!(cache.set_digest_challenge(&page, stale));
+
+        let header = cache
+            .auth_header(&page, "GET")
+            .unwrap()
+            .value
+            .expose()
+            .to_string();
+        assert!(header.contains("nonce=\"new\""));
+        assert!(header.contains("username=\"user\""));
+
+        let unknown = DigestAuthHandler::parse_challenge(r#"realm="other", nonce="x""#).unwrap();
+        assert!(!cache.set_digest_challenge(&page, unknown));
+    }
+
+    #[test]
+    fn test_remove_protection_space() {
+        let cache = AuthCache::new();
+        let page = url("http://a.com/");
+        cache.add(&page, "R", AuthScheme::Basic, "u", "p");
+        assert!(cache.auth_header(&page, "GET").is_some());
+
+        assert!(cache.remove(&page, "R", AuthScheme::Basic));
+        assert!(!cache.remove(&page, "R", AuthScheme::Basic));
+        assert!(cache.auth_header(&page, "GET").is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_basic_only() {
+        let cache = AuthCache::new();
+        let page = url("https://example.com/private/index.html");
+        cache.add(&page, "basic", AuthScheme::Basic, "user", "pass");
+        cache.add(&page, "digest", AuthScheme::Digest, "user", "pass");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auth.json");
+        cache.save_to_file(&path).unwrap();
+
+        let restored = AuthCache::new();
+        assert_eq!(restored.load_from_file(&path).unwrap(), 1);
+        let entry = restored.lookup_by_path(&page).unwrap();
+        assert_eq!(entry.realm, "basic");
+        assert_eq!(entry.password, "pass");
+        assert!(restored
+            .lookup(&page, "digest", AuthScheme::Digest)
+            .is_none());
+    }
 }
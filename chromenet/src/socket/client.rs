@@ -4,7 +4,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncWrite};
 
-/// Represents a connected socket (TCP or SSL).
+/// Represents a connected socket (TCP, SSL or a tunnel stream).
 /// Mimics net::StreamSocket.
 pub trait StreamSocket: AsyncRead + AsyncWrite + Unpin + Send + Sync + fmt::Debug {
     /// Returns true if the socket is still connected.
@@ -20,6 +20,8 @@ pub trait StreamSocket: AsyncRead + AsyncWrite + Unpin + Send + Sync + fmt::Debu
 pub enum SocketType {
     Tcp(tokio::net::TcpStream),
     Ssl(tokio_boring::SslStream<tokio::net::TcpStream>),
+    /// CONNECT stream on an HTTP/2 session to a proxy.
+    H2Tunnel(crate::socket::h2tunnel::H2Tunnel),
 }
 
 impl SocketType {
@@ -58,6 +60,7 @@ impl AsyncRead for SocketType {
         match self.get_mut() {
             SocketType::Tcp(s) => Pin::new(s).poll_read(cx, buf),
             SocketType::Ssl(s) => Pin::new(s).poll_read(cx, buf),
+            SocketType::H2Tunnel(s) => Pin::new(s).poll_read(cx, buf),
         }
     }
 }
@@ -71,6 +74,7 @@ impl AsyncWrite for SocketType {
         match self.get_mut() {
             SocketType::Tcp(s) => Pin::new(s).poll_write(cx, buf),
             SocketType::Ssl(s) => Pin::new(s).poll_write(cx, buf),
+            SocketType::H2Tunnel(s) => Pin::new(s).poll_write(cx, buf),
         }
     }
 
@@ -78,6 +82,7 @@ impl AsyncWrite for SocketType {
         match self.get_mut() {
             SocketType::Tcp(s) => Pin::new(s).poll_flush(cx),
             SocketType::Ssl(s) => Pin::new(s).poll_flush(cx),
+            SocketType::H2Tunnel(s) => Pin::new(s).poll_flush(cx),
         }
     }
 
@@ -85,6 +90,7 @@ impl AsyncWrite for SocketType {
         match self.get_mut() {
             SocketType::Tcp(s) => Pin::new(s).poll_shutdown(cx),
             SocketType::Ssl(s) => Pin::new(s).poll_shutdown(cx),
+            SocketType::H2Tunnel(s) => Pin::new(s).poll_shutdown(cx),
         }
     }
 }
@@ -94,6 +100,7 @@ impl StreamSocket for SocketType {
         match self {
             SocketType::Tcp(s) => Self::check_tcp_connected(s),
             SocketType::Ssl(s) => Self::check_ssl_connected(s),
+            SocketType::H2Tunnel(s) => crate::socket::stream::StreamSocket::is_connected(s),
         }
     }
 
@@ -1,25 +1,168 @@
-use crate::base::neterror::NetError;
-use crate::dns::{HickoryResolver, Name, Resolve};
+use crate::base::host::ip_literal;
+use crate::base::neterror::{ConnectionAttempt, NetError};
+use crate::dns::{Name, PostResolve, Resolve};
+use crate::socket::client::SocketType;
+use crate::socket::h2tunnel::{self, H2Tunnel, ProxySessions};
+use crate::socket::hooks::{ConnectHooks, ConnectOutcome};
+use crate::socket::pool::GroupId;
+use crate::socket::socketfactory::{SocketFactory, TcpSocketFactory};
 use crate::socket::stream::{BoxedSocket, StreamSocket};
-use crate::socket::tls::{get_ssl_connector, TlsOptions};
+use crate::socket::tls::{clienthello, get_ssl_connector, ServerName, TlsOptions};
+use crate::socket::wire::{ConnectionTap, InstrumentedSocket, SocketInstrumentation};
+use boring::ssl::ConnectConfiguration;
+use std::borrow::Cow;
+use std::future::Future;
 use std::net::{IpAddr, SocketAddr};
-use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio_boring::SslStream;
 use url::Url;
 
 /// Chromium's Happy Eyeballs IPv6 fallback delay (250ms).
 const IPV6_FALLBACK_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
 
-/// Connection timeout (4 minutes, matches Chromium).
-const CONNECTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(240);
+/// Default timeout of one TCP connect attempt.
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default budget for a whole connect (4 minutes, Chromium's
+/// `kTransportConnectJobTimeoutInSeconds`).
+const TOTAL_TIMEOUT: Duration = Duration::from_secs(240);
+
+/// How long connecting may take.
+///
+/// A host resolving to several unreachable addresses costs up to one
+/// attempt timeout per address; the total timeout caps the whole connect:
+/// DNS, every attempt, proxy handshakes and TLS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectTimeouts {
+    attempt: Duration,
+    total: Duration,
+}
+
+impl Default for ConnectTimeouts {
+    fn default() -> Self {
+        Self {
+            attempt: ATTEMPT_TIMEOUT,
+            total: TOTAL_TIMEOUT,
+        }
+    }
+}
+
+impl ConnectTimeouts {
+    /// 30 seconds per TCP connect attempt, 4 minutes in total.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up on an address after `timeout` and try the next one. A proxy's
+    /// CONNECT or SOCKS5 handshake gets the same time.
+    pub fn with_attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt = timeout;
+        self
+    }
+
+    /// Fail the connect with `ConnectionTimedOut` after `timeout`.
+    pub fn with_total_timeout(mut self, timeout: Duration) -> Self {
+        self.total = timeout;
+        self
+    }
+
+    /// Timeout of one TCP connect attempt or proxy handshake.
+    pub fn attempt_timeout(&self) -> Duration {
+        self.attempt
+    }
+
+    /// Timeout of the whole connect.
+    pub fn total_timeout(&self) -> Duration {
+        self.total
+    }
+}
+
+/// Transport socket from the socket factory.
+type Transport = Box<dyn StreamSocket>;
+
+/// Transport socket, wrapped for [`crate::socket::wire`] instrumentation.
+type TcpSocket = InstrumentedSocket<Transport>;
 
 /// Result of a connection attempt, includes ALPN negotiation info.
 pub struct ConnectResult {
     pub socket: BoxedSocket,
     /// True if HTTP/2 was negotiated via ALPN.
     pub is_h2: bool,
+    /// Time spent in each phase of the connect.
+    pub timing: ConnectTiming,
+}
+
+/// Time spent establishing a connection, like Chromium's
+/// `LoadTimingInfo::ConnectTiming`.
+///
+/// Through a proxy, `dns` and `connect` cover the proxy and the tunnel setup
+/// counts toward `connect`; `tls` covers every handshake, TLS-in-TLS
+/// included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectTiming {
+    /// Hostname resolution.
+    pub dns: Duration,
+    /// TCP connect, plus the proxy handshake if any.
+    pub connect: Duration,
+    /// TLS handshakes, `None` if there were none.
+    pub tls: Option<Duration>,
+}
+
+/// How to reach the hosts of a connect.
+#[derive(Clone, Copy)]
+struct Dial<'a> {
+    resolver: &'a dyn Resolve,
+    hooks: Option<&'a dyn ConnectHooks>,
+    sockets: &'a dyn SocketFactory,
+    attempt_timeout: Duration,
+    /// HTTP/2 proxy sessions to share, and the group connecting
+    proxy_sessions: Option<(&'a ProxySessions, &'a GroupId)>,
+    /// Host and port to connect to instead of the URL's
+    connect_to: Option<(&'a str, u16)>,
+    /// Hook rewriting the addresses lookups return
+    post_resolve: Option<&'a PostResolve>,
+}
+
+impl Dial<'_> {
+    /// `url` with the host and port connections and tunnels go to.
+    fn destination<'u>(&self, url: &'u Url) -> Result<Cow<'u, Url>, NetError> {
+        let Some((host, port)) = self.connect_to else {
+            return Ok(Cow::Borrowed(url));
+        };
+        let mut destination = url.clone();
+        destination
+            .set_host(Some(host))
+            .map_err(|_| NetError::InvalidUrl)?;
+        destination
+            .set_port(Some(port))
+            .map_err(|_| NetError::InvalidUrl)?;
+        Ok(Cow::Owned(destination))
+    }
+}
+
+/// TLS settings for the handshake with the target host.
+#[derive(Clone, Copy)]
+struct TargetTls<'a> {
+    options: Option<&'a TlsOptions>,
+    server_name: &'a ServerName,
+}
+
+impl<'a> TargetTls<'a> {
+    /// Handshake configuration for `host`, and the name to announce and
+    /// verify the certificate against.
+    fn configure(self, host: &'a str) -> Result<(ConnectConfiguration, &'a str), NetError> {
+        // Use cached connector for default config, or build custom
+        let connector = get_ssl_connector(self.options)?;
+        let mut config = connector
+            .configure()
+            .map_err(|_| NetError::SslProtocolError)?;
+        config.set_use_server_name_indication(self.server_name.sends_sni(host));
+        if let Some(options) = self.options {
+            options.apply_to_connection(&mut config)?;
+        }
+        Ok((config, self.server_name.tls_name(host)))
+    }
 }
 
 /// Manages the connection process: DNS -> TCP -> SSL.
@@ -31,14 +174,15 @@ impl ConnectJob {
     /// Connect to the target URL, optionally through a proxy.
     /// Returns a BoxedSocket for polymorphic handling (supports TLS-in-TLS).
     ///
-    /// Uses the default HickoryResolver for DNS resolution.
+    /// Uses [`default_resolver`](crate::dns::default_resolver) for DNS
+    /// resolution.
     pub async fn connect(
         url: &Url,
         proxy: Option<&crate::socket::proxy::ProxySettings>,
         tls_options: Option<&TlsOptions>,
     ) -> Result<ConnectResult, NetError> {
-        let resolver = Arc::new(HickoryResolver::new());
-        Self::connect_with_resolver(url, proxy, tls_options, &resolver).await
+        let resolver = crate::dns::default_resolver();
+        Self::connect_with_resolver(url, proxy, tls_options, &*resolver).await
     }
 
     /// Connect to the target URL with a custom DNS resolver.
@@ -50,45 +194,170 @@ impl ConnectJob {
         tls_options: Option<&TlsOptions>,
         resolver: &dyn Resolve,
     ) -> Result<ConnectResult, NetError> {
-        match proxy {
-            Some(p) => match p.proxy_type() {
-                crate::socket::proxy::ProxyType::Http => {
-                    Self::http_proxy_connect(url, p, tls_options, resolver).await
-                }
-                crate::socket::proxy::ProxyType::Https => {
-                    Self::https_proxy_connect(url, p, tls_options, resolver).await
-                }
-                crate::socket::proxy::ProxyType::Socks5 => {
-                    Self::socks5_proxy_connect(url, p, tls_options, resolver).await
-                }
-            },
-            None => Self::direct_connect(url, tls_options, resolver).await,
-        }
+        Self::connect_with_hooks(url, proxy, tls_options, resolver, None).await
     }
 
-    /// Direct connection (no proxy).
-    async fn direct_connect(
+    /// Connect with a custom DNS resolver, letting `hooks` fake the DNS,
+    /// TCP and TLS steps.
+    pub async fn connect_with_hooks(
+        url: &Url,
+        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        tls_options: Option<&TlsOptions>,
+        resolver: &dyn Resolve,
+        hooks: Option<&dyn ConnectHooks>,
+    ) -> Result<ConnectResult, NetError> {
+        Self::connect_with_server_name(
+            url,
+            proxy,
+            tls_options,
+            &ServerName::Host,
+            resolver,
+            hooks,
+            &SocketInstrumentation::default(),
+            ConnectTimeouts::default(),
+        )
+        .await
+    }
+
+    /// Connect like [`Self::connect_with_hooks`], announcing `server_name`
+    /// in the TLS handshake with the target. The handshake with an HTTPS
+    /// proxy always uses the proxy's host.
+    ///
+    /// The new connection's sockets get `instrumentation`, see
+    /// [`crate::socket::wire`]. Connecting fails with `ConnectionTimedOut`
+    /// past `timeouts`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn connect_with_server_name(
+        url: &Url,
+        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        tls_options: Option<&TlsOptions>,
+        server_name: &ServerName,
+        resolver: &dyn Resolve,
+        hooks: Option<&dyn ConnectHooks>,
+        instrumentation: &SocketInstrumentation,
+        timeouts: ConnectTimeouts,
+    ) -> Result<ConnectResult, NetError> {
+        let tls = TargetTls {
+            options: tls_options,
+            server_name,
+        };
+        let dial = Dial {
+            resolver,
+            hooks,
+            sockets: &TcpSocketFactory,
+            attempt_timeout: timeouts.attempt,
+            proxy_sessions: None,
+            connect_to: None,
+            post_resolve: None,
+        };
+        Self::connect_dial(url, proxy, tls, dial, instrumentation, timeouts).await
+    }
+
+    /// Connect for the pool group `group_id`, tunneling through the HTTP/2
+    /// proxy sessions in `sessions` when the proxy speaks HTTP/2.
+    ///
+    /// Connections and tunnels go to the group's
+    /// [`connect_to`](GroupId::connect_to) address if it has one, while TLS
+    /// is still with the URL's host.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn connect_for_group(
         url: &Url,
+        proxy: Option<&crate::socket::proxy::ProxySettings>,
         tls_options: Option<&TlsOptions>,
+        group_id: &GroupId,
+        sessions: &ProxySessions,
         resolver: &dyn Resolve,
+        hooks: Option<&dyn ConnectHooks>,
+        sockets: &dyn SocketFactory,
+        instrumentation: &SocketInstrumentation,
+        timeouts: ConnectTimeouts,
+    ) -> Result<ConnectResult, NetError> {
+        let tls = TargetTls {
+            options: tls_options,
+            server_name: group_id.server_name(),
+        };
+        let dial = Dial {
+            resolver,
+            hooks,
+            sockets,
+            attempt_timeout: timeouts.attempt,
+            proxy_sessions: Some((sessions, group_id)),
+            connect_to: group_id.connect_to(),
+            post_resolve: group_id.post_resolve(),
+        };
+        Self::connect_dial(url, proxy, tls, dial, instrumentation, timeouts).await
+    }
+
+    async fn connect_dial(
+        url: &Url,
+        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        tls: TargetTls<'_>,
+        dial: Dial<'_>,
+        instrumentation: &SocketInstrumentation,
+        timeouts: ConnectTimeouts,
+    ) -> Result<ConnectResult, NetError> {
+        let timing = &mut ConnectTiming::default();
+        let tap = &instrumentation.connection();
+        let connect = async {
+            match proxy {
+                Some(p) => match p.proxy_type() {
+                    crate::socket::proxy::ProxyType::Http => {
+                        Self::http_proxy_connect(url, p, tls, dial, tap, timing).await
+                    }
+                    crate::socket::proxy::ProxyType::Https => {
+                        Self::https_proxy_connect(url, p, tls, dial, tap, timing).await
+                    }
+                    crate::socket::proxy::ProxyType::Socks5 => {
+                        Self::socks5_proxy_connect(url, p, tls, dial, tap, timing).await
+                    }
+                },
+                None => Self::direct_connect(url, tls, dial, tap, timing).await,
+            }
+        };
+        tokio::time::timeout(timeouts.total, connect)
+            .await
+            .unwrap_or_else(|_| {
+                tracing::debug!(target: "chromenet::socket", url = %url, timeout = ?timeouts.total, "Connect timed out");
+                Err(NetError::ConnectionTimedOut)
+            })
+    }
+
+    /// Direct connection (no proxy).
+    async fn direct_connect(
+        url: &Url,
+        tls: TargetTls<'_>,
+        dial: Dial<'_>,
+        tap: &ConnectionTap,
+        timing: &mut ConnectTiming,
     ) -> Result<ConnectResult, NetError> {
-        let host = url.host_str().ok_or(NetError::InvalidUrl)?;
-        let port = url.port_or_known_default().ok_or(NetError::InvalidUrl)?;
+        let destination = dial.destination(url)?;
+        let host = destination.host_str().ok_or(NetError::InvalidUrl)?;
+        let port = destination
+            .port_or_known_default()
+            .ok_or(NetError::InvalidUrl)?;
 
         // TCP connect with Happy Eyeballs
-        let tcp = Self::connect_tcp(host, port, resolver).await?;
+        let (tcp, addr) = Self::connect_tcp(host, port, dial, timing).await?;
+        let tcp = tap.raw(tcp);
 
-        // TLS if HTTPS
+        // TLS if HTTPS, with the URL's host
         if url.scheme() == "https" {
-            let (tls, is_h2) = Self::ssl_handshake(tcp, host, tls_options).await?;
+            let host = url.host_str().ok_or(NetError::InvalidUrl)?;
+            let (tls, is_h2) = Self::ssl_handshake(tcp, host, tls, dial.hooks, timing).await?;
             Ok(ConnectResult {
-                socket: BoxedSocket::new(tls),
+                socket: BoxedSocket::new(tap.plaintext(tls))
+                    .with_remote_addr(addr)
+                    .with_meter(tap.meter()),
                 is_h2,
+                timing: *timing,
             })
         } else {
             Ok(ConnectResult {
-                socket: BoxedSocket::new(tcp),
+                socket: BoxedSocket::new(tcp)
+                    .with_remote_addr(addr)
+                    .with_meter(tap.meter()),
                 is_h2: false,
+                timing: *timing,
             })
         }
     }
@@ -97,8 +366,10 @@ impl ConnectJob {
     async fn http_proxy_connect(
         url: &Url,
         proxy: &crate::socket::proxy::ProxySettings,
-        tls_options: Option<&TlsOptions>,
-        resolver: &dyn Resolve,
+        tls: TargetTls<'_>,
+        dial: Dial<'_>,
+        tap: &ConnectionTap,
+        timing: &mut ConnectTiming,
     ) -> Result<ConnectResult, NetError> {
         let proxy_host = proxy.url.host_str().ok_or(NetError::InvalidUrl)?;
         let proxy_port = proxy
@@ -107,35 +378,74 @@ impl ConnectJob {
             .ok_or(NetError::InvalidUrl)?;
 
         // Step 1: TCP to proxy
-        let mut tcp = Self::connect_tcp(proxy_host, proxy_port, resolver).await?;
+        let (tcp, _) = Self::connect_tcp(proxy_host, proxy_port, dial, timing).await?;
+        let mut tcp = tap.raw(tcp);
 
         // Step 2: HTTP CONNECT tunnel
-        Self::send_connect(&mut tcp, url, proxy).await?;
+        let start = Instant::now();
+        let destination = dial.destination(url)?;
+        Self::proxy_handshake(
+            proxy,
+            dial,
+            Self::send_connect(&mut tcp, &destination, proxy),
+        )
+        .await?;
+        timing.connect += start.elapsed();
 
         // Step 3: TLS to target if HTTPS
         if url.scheme() == "https" {
             let target_host = url.host_str().ok_or(NetError::InvalidUrl)?;
-            let (tls, is_h2) = Self::ssl_handshake(tcp, target_host, tls_options).await?;
+            let (tls, is_h2) =
+                Self::ssl_handshake(tcp, target_host, tls, dial.hooks, timing).await?;
             Ok(ConnectResult {
-                socket: BoxedSocket::new(tls),
+                socket: BoxedSocket::new(tap.plaintext(tls)).with_meter(tap.meter()),
                 is_h2,
+                timing: *timing,
             })
         } else {
             Ok(ConnectResult {
-                socket: BoxedSocket::new(tcp),
+                socket: BoxedSocket::new(tcp).with_meter(tap.meter()),
                 is_h2: false,
+                timing: *timing,
             })
         }
     }
 
     /// HTTPS proxy connection (TLS-in-TLS tunneling).
     /// Flow: TCP -> TLS(proxy) -> CONNECT -> TLS(target)
+    ///
+    /// If the proxy negotiates h2, the CONNECT is a stream on an HTTP/2
+    /// session to the proxy, which the group's later tunnels share.
     async fn https_proxy_connect(
         url: &Url,
         proxy: &crate::socket::proxy::ProxySettings,
-        tls_options: Option<&TlsOptions>,
-        resolver: &dyn Resolve,
+        tls: TargetTls<'_>,
+        dial: Dial<'_>,
+        tap: &ConnectionTap,
+        timing: &mut ConnectTiming,
     ) -> Result<ConnectResult, NetError> {
+        let destination = dial.destination(url)?;
+
+        // Open a stream on an existing HTTP/2 session to the proxy
+        if let Some((sessions, group_id)) = dial.proxy_sessions {
+            if let Some(session) = sessions.get(group_id) {
+                let start = Instant::now();
+                let open = H2Tunnel::open(session, &destination, proxy);
+                match Self::proxy_handshake(proxy, dial, open).await {
+                    Ok(tunnel) => {
+                        timing.connect += start.elapsed();
+                        // The session's TCP socket belongs to the connection
+                        // that opened it, so count this tunnel's bytes
+                        let tunnel = tap.raw(SocketType::H2Tunnel(tunnel));
+                        return Self::tunnel_to_target(url, tunnel, tls, dial, tap, timing).await;
+                    }
+                    // The session went away, start a new one
+                    Err(NetError::ConnectionClosed) => sessions.remove(group_id),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
         let proxy_host = proxy.url.host_str().ok_or(NetError::InvalidUrl)?;
         let proxy_port = proxy
             .url
@@ -143,27 +453,68 @@ impl ConnectJob {
             .ok_or(NetError::InvalidUrl)?;
 
         // Step 1: TCP to proxy
-        let tcp = Self::connect_tcp(proxy_host, proxy_port, resolver).await?;
-
-        // Step 2: TLS to proxy (Layer 1)
-        let (mut proxy_tls, _) = Self::ssl_handshake(tcp, proxy_host, tls_options).await?;
-
-        // Step 3: HTTP CONNECT through TLS tunnel
-        Self::send_connect_generic(&mut proxy_tls, url, proxy).await?;
+        let (tcp, _) = Self::connect_tcp(proxy_host, proxy_port, dial, timing).await?;
+        let tcp = tap.raw(tcp);
+
+        // Step 2: TLS to proxy (Layer 1), with the proxy's own options if
+        // it has them
+        let proxy_target = TargetTls {
+            options: proxy.tls_options().or(tls.options),
+            server_name: &ServerName::Host,
+        };
+        let (mut proxy_tls, proxy_h2) =
+            Self::ssl_handshake(tcp, proxy_host, proxy_target, dial.hooks, timing).await?;
+
+        // Step 3: HTTP CONNECT through TLS tunnel, as an HTTP/2 stream if
+        // the proxy speaks HTTP/2
+        let start = Instant::now();
+        if proxy_h2 {
+            let session =
+                Self::proxy_handshake(proxy, dial, h2tunnel::handshake(proxy_tls)).await?;
+            if let Some((sessions, group_id)) = dial.proxy_sessions {
+                sessions.insert(group_id, session.clone());
+            }
+            let open = H2Tunnel::open(session, &destination, proxy);
+            let tunnel = Self::proxy_handshake(proxy, dial, open).await?;
+            timing.connect += start.elapsed();
+            let tunnel = SocketType::H2Tunnel(tunnel);
+            return Self::tunnel_to_target(url, tunnel, tls, dial, tap, timing).await;
+        }
+        Self::proxy_handshake(
+            proxy,
+            dial,
+            Self::send_connect_generic(&mut proxy_tls, &destination, proxy),
+        )
+        .await?;
+        timing.connect += start.elapsed();
+
+        Self::tunnel_to_target(url, proxy_tls, tls, dial, tap, timing).await
+    }
 
-        // Step 4: TLS to target through tunnel (Layer 2 - TLS-in-TLS)
+    /// TLS to the target through a proxy tunnel (Layer 2 - TLS-in-TLS) if
+    /// HTTPS.
+    async fn tunnel_to_target<S: StreamSocket>(
+        url: &Url,
+        tunnel: S,
+        tls: TargetTls<'_>,
+        dial: Dial<'_>,
+        tap: &ConnectionTap,
+        timing: &mut ConnectTiming,
+    ) -> Result<ConnectResult, NetError> {
         if url.scheme() == "https" {
             let target_host = url.host_str().ok_or(NetError::InvalidUrl)?;
             let (target_tls, is_h2) =
-                Self::ssl_handshake_generic(proxy_tls, target_host, tls_options).await?;
+                Self::ssl_handshake_generic(tunnel, target_host, tls, dial.hooks, timing).await?;
             Ok(ConnectResult {
-                socket: BoxedSocket::new(target_tls),
+                socket: BoxedSocket::new(tap.plaintext(target_tls)).with_meter(tap.meter()),
                 is_h2,
+                timing: *timing,
             })
         } else {
             Ok(ConnectResult {
-                socket: BoxedSocket::new(proxy_tls),
+                socket: BoxedSocket::new(tap.plaintext(tunnel)).with_meter(tap.meter()),
                 is_h2: false,
+                timing: *timing,
             })
         }
     }
@@ -172,8 +523,10 @@ impl ConnectJob {
     async fn socks5_proxy_connect(
         url: &Url,
         proxy: &crate::socket::proxy::ProxySettings,
-        tls_options: Option<&TlsOptions>,
-        resolver: &dyn Resolve,
+        tls: TargetTls<'_>,
+        dial: Dial<'_>,
+        tap: &ConnectionTap,
+        timing: &mut ConnectTiming,
     ) -> Result<ConnectResult, NetError> {
         let proxy_host = proxy.url.host_str().ok_or(NetError::InvalidUrl)?;
         let proxy_port = proxy
@@ -182,47 +535,82 @@ impl ConnectJob {
             .ok_or(NetError::InvalidUrl)?;
 
         // Step 1: TCP to proxy
-        let mut tcp = Self::connect_tcp(proxy_host, proxy_port, resolver).await?;
+        let (tcp, _) = Self::connect_tcp(proxy_host, proxy_port, dial, timing).await?;
+        let mut tcp = tap.raw(tcp);
 
         // Step 2: SOCKS5 handshake
-        Self::socks5_handshake(&mut tcp, url).await?;
+        let start = Instant::now();
+        let destination = dial.destination(url)?;
+        Self::proxy_handshake(proxy, dial, Self::socks5_handshake(&mut tcp, &destination)).await?;
+        timing.connect += start.elapsed();
 
         // Step 3: TLS to target if HTTPS
         if url.scheme() == "https" {
             let target_host = url.host_str().ok_or(NetError::InvalidUrl)?;
-            let (tls, is_h2) = Self::ssl_handshake(tcp, target_host, tls_options).await?;
+            let (tls, is_h2) =
+                Self::ssl_handshake(tcp, target_host, tls, dial.hooks, timing).await?;
             Ok(ConnectResult {
-                socket: BoxedSocket::new(tls),
+                socket: BoxedSocket::new(tap.plaintext(tls)).with_meter(tap.meter()),
                 is_h2,
+                timing: *timing,
             })
         } else {
             Ok(ConnectResult {
-                socket: BoxedSocket::new(tcp),
+                socket: BoxedSocket::new(tcp).with_meter(tap.meter()),
                 is_h2: false,
+                timing: *timing,
             })
         }
     }
 
+    /// Look up the addresses of `host`, asking `hooks` before `resolver`,
+    /// and pass the answer through `post_resolve`.
+    pub(crate) async fn resolve(
+        host: &str,
+        resolver: &dyn Resolve,
+        hooks: Option<&dyn ConnectHooks>,
+        post_resolve: Option<&PostResolve>,
+    ) -> Result<Vec<IpAddr>, NetError> {
+        let addrs = match hooks.and_then(|h| h.resolve(host)) {
+            Some(answer) => answer?,
+            None => {
+                // IP literals need no lookup
+                if let Some(ip) = ip_literal(host) {
+                    return Ok(vec![ip]);
+                }
+                resolver
+                    .resolve(Name::new(host))
+                    .await?
+                    .map(|addr| addr.ip())
+                    .collect()
+            }
+        };
+        Ok(match post_resolve {
+            Some(hook) => hook.apply(host, addrs),
+            None => addrs,
+        })
+    }
+
     /// TCP connect with Happy Eyeballs (RFC 8305).
     ///
     /// Uses the provided DNS resolver to look up addresses, then attempts
-    /// connections with IPv6 preference and fallback.
+    /// connections with IPv6 preference and fallback. Returns the stream
+    /// and the address from the DNS answer it connected to.
     async fn connect_tcp(
         host: &str,
         port: u16,
-        resolver: &dyn Resolve,
-    ) -> Result<TcpStream, NetError> {
+        dial: Dial<'_>,
+        timing: &mut ConnectTiming,
+    ) -> Result<(Transport, SocketAddr), NetError> {
         // Resolve hostname to addresses
-        let name = Name::new(host);
-        let resolved = resolver.resolve(name).await?;
-
-        // Collect addresses and set the port
-        let addrs: Vec<SocketAddr> = resolved
-            .map(|mut addr| {
-                addr.set_port(port);
-                addr
-            })
-            .collect();
+        let start = Instant::now();
+        let addrs: Vec<SocketAddr> =
+            Self::resolve(host, dial.resolver, dial.hooks, dial.post_resolve)
+                .await?
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, port))
+                .collect();
+        timing.dns += start.elapsed();
 
         if addrs.is_empty() {
             return Err(NetError::NameNotResolvedFor {
@@ -234,68 +622,146 @@ impl ConnectJob {
             });
         }
 
-        Self::connect_with_happy_eyeballs(&addrs).await
+        let start = Instant::now();
+        let tcp = Self::connect_with_happy_eyeballs(&addrs, dial)
+            .await
+            .map_err(|attempts| {
+                tracing::debug!(target: "chromenet::socket", host = %host, port, ?attempts, "All connection attempts failed");
+                NetError::ConnectionAttemptsFailed {
+                    host: host.to_string(),
+                    port,
+                    attempts,
+                }
+            })?;
+        timing.connect += start.elapsed();
+        Ok(tcp)
     }
 
     /// Connect using Happy Eyeballs (RFC 8305).
-    async fn connect_with_happy_eyeballs(addrs: &[SocketAddr]) -> Result<TcpStream, NetError> {
+    ///
+    /// IPv4 starts after [`IPV6_FALLBACK_DELAY`], or as soon as every IPv6
+    /// address has failed. On failure, returns the attempts of both families.
+    async fn connect_with_happy_eyeballs(
+        addrs: &[SocketAddr],
+        dial: Dial<'_>,
+    ) -> Result<(Transport, SocketAddr), Vec<ConnectionAttempt>> {
         let (ipv6_addrs, ipv4_addrs): (Vec<_>, Vec<_>) =
             addrs.iter().partition(|a| matches!(a.ip(), IpAddr::V6(_)));
 
         if ipv6_addrs.is_empty() {
-            return Self::connect_any(&ipv4_addrs).await;
+            return Self::connect_any(&ipv4_addrs, dial).await;
         }
         if ipv4_addrs.is_empty() {
-            return Self::connect_any(&ipv6_addrs).await;
+            return Self::connect_any(&ipv6_addrs, dial).await;
         }
 
-        tokio::select! {
-            result = Self::connect_any(&ipv6_addrs) => {
-                match result {
-                    Ok(stream) => Ok(stream),
-                    Err(_) => Self::connect_any(&ipv4_addrs).await,
-                }
+        let ipv6_failed = tokio::sync::Notify::new();
+        let ipv6 = async {
+            let result = Self::connect_any(&ipv6_addrs, dial).await;
+            if result.is_err() {
+                ipv6_failed.notify_one();
             }
-            result = async {
-                tokio::time::sleep(IPV6_FALLBACK_DELAY).await;
-                Self::connect_any(&ipv4_addrs).await
-            } => {
-                result
+            result
+        };
+        let ipv4 = async {
+            tokio::select! {
+                _ = tokio::time::sleep(IPV6_FALLBACK_DELAY) => {}
+                _ = ipv6_failed.notified() => {}
             }
+            Self::connect_any(&ipv4_addrs, dial).await
+        };
+        tokio::pin!(ipv6, ipv4);
+
+        // The first success wins; a failure waits for the other family
+        tokio::select! {
+            result = &mut ipv6 => match result {
+                Ok(stream) => Ok(stream),
+                Err(mut attempts) => ipv4.await.map_err(|more| {
+                    attempts.extend(more);
+                    attempts
+                }),
+            },
+            result = &mut ipv4 => match result {
+                Ok(stream) => Ok(stream),
+                Err(mut attempts) => ipv6.await.map_err(|more| {
+                    attempts.extend(more);
+                    attempts
+                }),
+            },
         }
     }
 
-    async fn connect_any(addrs: &[&SocketAddr]) -> Result<TcpStream, NetError> {
-        let mut last_error = NetError::ConnectionFailed;
+    /// Try `addrs` in order, recording each failed attempt.
+    async fn connect_any(
+        addrs: &[&SocketAddr],
+        dial: Dial<'_>,
+    ) -> Result<(Transport, SocketAddr), Vec<ConnectionAttempt>> {
+        let mut attempts = Vec::with_capacity(addrs.len());
         for addr in addrs {
-            match tokio::time::timeout(CONNECTION_TIMEOUT, TcpStream::connect(addr)).await {
-                Ok(Ok(stream)) => return Ok(stream),
-                Ok(Err(_)) => last_error = NetError::ConnectionRefused,
-                Err(_) => last_error = NetError::ConnectionTimedOut,
-            }
+            let start = Instant::now();
+            let target = match dial
+                .hooks
+                .map_or(ConnectOutcome::Proceed, |h| h.connect(**addr))
+            {
+                ConnectOutcome::Proceed => **addr,
+                ConnectOutcome::Redirect(target) => target,
+                ConnectOutcome::Fail { error, delay } => {
+                    let error =
+                        tokio::time::timeout(dial.attempt_timeout, tokio::time::sleep(delay))
+                            .await
+                            .map_or(NetError::ConnectionTimedOut, |()| error);
+                    attempts.push(ConnectionAttempt {
+                        address: **addr,
+                        error,
+                        duration: start.elapsed(),
+                    });
+                    continue;
+                }
+            };
+            let error = match tokio::time::timeout(
+                dial.attempt_timeout,
+                dial.sockets.connect(target),
+            )
+            .await
+            {
+                Ok(Ok(stream)) => return Ok((stream, **addr)),
+                Ok(Err(e)) => NetError::from(e),
+                Err(_) => NetError::ConnectionTimedOut,
+            };
+            attempts.push(ConnectionAttempt {
+                address: **addr,
+                error,
+                duration: start.elapsed(),
+            });
         }
-        Err(last_error)
+        Err(attempts)
     }
 
-    /// SSL handshake for TcpStream, returns (SslStream, is_h2).
+    /// SSL handshake over TCP, returns (SslStream, is_h2).
     async fn ssl_handshake(
-        stream: TcpStream,
+        stream: TcpSocket,
         host: &str,
-        tls_options: Option<&TlsOptions>,
-    ) -> Result<(SslStream<TcpStream>, bool), NetError> {
-        // Use cached connector for default config, or build custom
-        let connector = get_ssl_connector(tls_options)?;
-        let config = connector
-            .configure()
-            .map_err(|_| NetError::SslProtocolError)?;
+        tls: TargetTls<'_>,
+        hooks: Option<&dyn ConnectHooks>,
+        timing: &mut ConnectTiming,
+    ) -> Result<(SslStream<TcpSocket>, bool), NetError> {
+        if let Some(error) = hooks.and_then(|h| h.tls_handshake(host)) {
+            return Err(error);
+        }
+        let start = Instant::now();
+        let (config, name) = tls.configure(host)?;
 
-        let tls_stream = tokio_boring::connect(config, host, stream)
+        let mut tls_stream = tokio_boring::connect(config, name, stream)
             .await
             .map_err(|e| {
                 tracing::debug!(target: "chromenet::socket", error = ?e, host = %host, "SSL handshake failed");
                 NetError::SslProtocolError
             })?;
+        if let Some(record) = tls_stream.get_mut().take_first_record() {
+            clienthello::attach(tls_stream.ssl_mut(), &record);
+        }
 
+        *timing.tls.get_or_insert_default() += start.elapsed();
         let is_h2 = matches!(tls_stream.ssl().selected_alpn_protocol(), Some(b"h2"));
         Ok((tls_stream, is_h2))
     }
@@ -304,28 +770,54 @@ impl ConnectJob {
     async fn ssl_handshake_generic<S: StreamSocket>(
         stream: S,
         host: &str,
-        tls_options: Option<&TlsOptions>,
+        tls: TargetTls<'_>,
+        hooks: Option<&dyn ConnectHooks>,
+        timing: &mut ConnectTiming,
     ) -> Result<(SslStream<S>, bool), NetError> {
-        // Use cached connector for default config, or build custom
-        let connector = get_ssl_connector(tls_options)?;
-        let config = connector
-            .configure()
-            .map_err(|_| NetError::SslProtocolError)?;
+        if let Some(error) = hooks.and_then(|h| h.tls_handshake(host)) {
+            return Err(error);
+        }
+        let start = Instant::now();
+        let (config, name) = tls.configure(host)?;
 
-        let tls_stream = tokio_boring::connect(config, host, stream)
+        let tls_stream = tokio_boring::connect(config, name, stream)
             .await
             .map_err(|_| {
                 tracing::debug!(target: "chromenet::socket", host = %host, "TLS-in-TLS handshake failed");
                 NetError::SslProtocolError
             })?;
 
+        *timing.tls.get_or_insert_default() += start.elapsed();
         let is_h2 = matches!(tls_stream.ssl().selected_alpn_protocol(), Some(b"h2"));
         Ok((tls_stream, is_h2))
     }
 
-    /// Send HTTP CONNECT through a TcpStream.
+    /// Run `handshake` with `proxy`, failing with
+    /// [`NetError::TunnelConnectionTimedOut`] if the proxy has not finished
+    /// it within the attempt timeout. Without this a proxy that accepts the
+    /// connection and never answers holds the connect until the total
+    /// timeout.
+    async fn proxy_handshake<T>(
+        proxy: &crate::socket::proxy::ProxySettings,
+        dial: Dial<'_>,
+        handshake: impl Future<Output = Result<T, NetError>>,
+    ) -> Result<T, NetError> {
+        match tokio::time::timeout(dial.attempt_timeout, handshake).await {
+            Ok(result) => result,
+            Err(_) => {
+                let proxy = proxy
+                    .host_port()
+                    .map(|(host, port)| format!("{host}:{port}"))
+                    .unwrap_or_default();
+                tracing::debug!(target: "chromenet::socket", proxy = %proxy, timeout = ?dial.attempt_timeout, "Proxy handshake timed out");
+                Err(NetError::tunnel_timed_out(proxy, dial.attempt_timeout))
+            }
+        }
+    }
+
+    /// Send HTTP CONNECT over TCP.
     async fn send_connect(
-        stream: &mut TcpStream,
+        stream: &mut TcpSocket,
         url: &Url,
         proxy: &crate::socket::proxy::ProxySettings,
     ) -> Result<(), NetError> {
@@ -398,7 +890,10 @@ impl ConnectJob {
     }
 
     /// SOCKS5 handshake (RFC 1928).
-    async fn socks5_handshake(stream: &mut TcpStream, url: &Url) -> Result<(), NetError> {
+    pub(crate) async fn socks5_handshake<S>(stream: &mut S, url: &Url) -> Result<(), NetError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
         const SOCKS5_VERSION: u8 = 0x05;
         const NO_AUTH: u8 = 0x00;
         const CONNECT_CMD: u8 = 0x01;
@@ -473,3 +968,48 @@ impl ConnectJob {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::duplex;
+
+    async fn handshake_with_reply(reply: &[u8]) -> Result<(), NetError> {
+        let (mut client, mut proxy) = duplex(1024);
+        proxy.write_all(reply).await.unwrap();
+        proxy.shutdown().await.unwrap();
+        let url = Url::parse("http://example.com/").unwrap();
+        ConnectJob::socks5_handshake(&mut client, &url).await
+    }
+
+    #[tokio::test]
+    async fn test_socks5_reply_address_types() {
+        let ipv4 = [5, 0, 5, 0, 0, 1, 127, 0, 0, 1, 0, 80];
+        assert!(handshake_with_reply(&ipv4).await.is_ok());
+
+        let mut domain = vec![5, 0, 5, 0, 0, 3, 4];
+        domain.extend_from_slice(b"host\x00\x50");
+        assert!(handshake_with_reply(&domain).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_socks5_malformed_replies() {
+        // Truncated greeting
+        assert!(matches!(
+            handshake_with_reply(&[5]).await,
+            Err(NetError::SocksConnectionFailed)
+        ));
+        // Proxy insists on authentication
+        assert!(handshake_with_reply(&[5, 2]).await.is_err());
+        // Connect refused
+        assert!(handshake_with_reply(&[5, 0, 5, 5, 0, 1, 0, 0, 0, 0, 0, 0])
+            .await
+            .is_err());
+        // Unknown address type
+        assert!(handshake_with_reply(&[5, 0, 5, 0, 0, 9, 0]).await.is_err());
+        // Domain reply shorter than its length byte
+        assert!(handshake_with_reply(&[5, 0, 5, 0, 0, 3, 200, b'a'])
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Connector for hyper-util's client (feature `hyper-connector`).
+//!
+//! [`Connector`] opens connections the way chromenet does, through the
+//! socket pool: DNS, proxy tunnels and the emulated TLS handshake with its
+//! ALPN. hyper-util's legacy client can then send requests over them, so an
+//! application can keep its own hyper client while moving the TLS layer to
+//! chromenet first:
+//!
+//! ```rust,ignore
+//! use bytes::Bytes;
+//! use http_body_util::Empty;
+//! use hyper_util::client::legacy::Client as HyperClient;
+//! use hyper_util::rt::TokioExecutor;
+//!
+//! let client = chromenet::Client::builder().emulation(Chrome::V140).build();
+//! let hyper = HyperClient::builder(TokioExecutor::new())
+//!     .build::<_, Empty<Bytes>>(client.connector());
+//! let response = hyper.get("https://example.com/".parse()?).await?;
+//! ```
+//!
+//! Only the connection comes from chromenet. Headers, cookies, redirects
+//! and the HTTP/2 settings frames are hyper's, so those layers of the
+//! fingerprint are not emulated. hyper keeps connections alive itself: each
+//! one holds a slot of its pool group until hyper drops it, so the per-host
+//! limits still apply.
+
+use crate::base::neterror::NetError;
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::socket::nextproto::NextProto;
+use crate::socket::pool::{ClientSocketPool, ConnectionReuse, GroupId, RequestPriority};
+use crate::socket::proxy::ProxySettings;
+use crate::socket::stream::BoxedSocket;
+use crate::socket::tls::SslInfo;
+use futures::future::BoxFuture;
+use http::Uri;
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use url::Url;
+
+/// Opens pooled chromenet connections for hyper-util's client, see
+/// [`crate::socket::connector`].
+#[derive(Clone)]
+pub struct Connector {
+    pool: Arc<ClientSocketPool>,
+    proxy: Option<ProxySettings>,
+    network_isolation_key: Option<NetworkIsolationKey>,
+}
+
+impl Default for Connector {
+    fn default() -> Self {
+        Self::from_pool(Arc::new(ClientSocketPool::default()))
+    }
+}
+
+impl std::fmt::Debug for Connector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connector")
+            .field("proxy", &self.proxy.is_some())
+            .field("network_isolation_key", &self.network_isolation_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Connector {
+    /// Connect with a pool of its own and the default TLS settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect through `pool`, with its TLS settings and limits.
+    pub fn from_pool(pool: Arc<ClientSocketPool>) -> Self {
+        Self {
+            pool,
+            proxy: None,
+            network_isolation_key: None,
+        }
+    }
+
+    /// Tunnel connections through `proxy`.
+    pub fn with_proxy(mut self, proxy: ProxySettings) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Open connections in the partition for `nik`.
+    pub fn with_network_isolation_key(mut self, nik: NetworkIsolationKey) -> Self {
+        self.network_isolation_key = Some(nik);
+        self
+    }
+
+    async fn connect(self, uri: Uri) -> Result<PooledConnection, NetError> {
+        let url = Url::parse(&uri.to_string()).map_err(|_| NetError::InvalidUrl)?;
+        let group_id = GroupId::new(
+            &url,
+            self.proxy.as_ref(),
+            self.network_isolation_key.as_ref(),
+        )
+        .ok_or(NetError::InvalidUrl)?;
+        let result = self
+            .pool
+            .request_socket_for_group(
+                &group_id,
+                &url,
+                self.proxy.as_ref(),
+                None,
+                RequestPriority::default(),
+                ConnectionReuse::Allowed,
+            )
+            .await?;
+        Ok(PooledConnection {
+            socket: result.socket,
+            is_h2: result.is_h2,
+            group_id: result.group_id,
+            pool: self.pool,
+        })
+    }
+}
+
+impl tower_service::Service<Uri> for Connector {
+    type Response = PooledConnection;
+    type Error = NetError;
+    type Future = BoxFuture<'static, Result<PooledConnection, NetError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), NetError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        Box::pin(self.clone().connect(uri))
+    }
+}
+
+/// A connection opened by [`Connector`]. Dropping it gives its slot back to
+/// the pool.
+pub struct PooledConnection {
+    socket: BoxedSocket,
+    is_h2: bool,
+    group_id: GroupId,
+    pool: Arc<ClientSocketPool>,
+}
+
+impl PooledConnection {
+    /// Application protocol negotiated via ALPN, [`NextProto::Unknown`]
+    /// for cleartext connections.
+    pub fn negotiated_protocol(&self) -> NextProto {
+        self.socket.negotiated_protocol()
+    }
+
+    /// TLS details of the connection, `None` for cleartext connections.
+    pub fn ssl_info(&self) -> Option<SslInfo> {
+        self.socket.ssl_info()
+    }
+}
+
+impl Connection for PooledConnection {
+    fn connected(&self) -> Connected {
+        let connected = Connected::new();
+        if self.is_h2 {
+            connected.negotiated_h2()
+        } else {
+            connected
+        }
+    }
+}
+
+impl hyper::rt::Read for PooledConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        hyper::rt::Read::poll_read(Pin::new(&mut self.socket), cx, buf)
+    }
+}
+
+impl hyper::rt::Write for PooledConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        hyper::rt::Write::poll_write(Pin::new(&mut self.socket), cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        hyper::rt::Write::poll_flush(Pin::new(&mut self.socket), cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        hyper::rt::Write::poll_shutdown(Pin::new(&mut self.socket), cx)
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        // hyper pools connections itself, so the socket is not reused here
+        self.pool.discard_socket_from_group(&self.group_id);
+    }
+}
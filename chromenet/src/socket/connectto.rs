@@ -0,0 +1,154 @@
+//! Connecting to another address than a URL's host.
+//!
+//! curl mapping: `--connect-to HOST1:PORT1:HOST2:PORT2`
+//!
+//! A [`ConnectTo`] rule makes requests for one host and port connect to
+//! another, e.g. a staging server or one half of a blue/green deployment.
+//! Only where the connection goes changes: the request keeps its URL, so
+//! SNI, certificate verification, the Host header, cookies and HSTS all go
+//! by the URL's host.
+//!
+//! - An empty host or port in the rule's source matches any; an empty
+//!   target host or port keeps the URL's
+//! - IPv6 literals are written in brackets: `[::1]`
+//! - Through a proxy, the CONNECT or SOCKS5 tunnel is opened to the target
+//!
+//! Connections made for a rule only carry requests connecting to the same
+//! target.
+
+use crate::base::host::{canonicalize_host, host_key};
+
+/// A rule sending connections for `host:port` to `target_host:target_port`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectTo {
+    host: Option<String>,
+    port: Option<u16>,
+    target_host: Option<String>,
+    target_port: Option<u16>,
+}
+
+impl ConnectTo {
+    /// Connect to `target_host:target_port` for requests to `host:port`.
+    ///
+    /// Returns `None` if a host is not a valid host name or IP literal.
+    pub fn new(host: &str, port: u16, target_host: &str, target_port: u16) -> Option<Self> {
+        Some(Self {
+            host: Some(canonicalize_host(host)?),
+            port: Some(port),
+            target_host: Some(canonicalize_host(target_host)?),
+            target_port: Some(target_port),
+        })
+    }
+
+    /// Parse curl's `host:port:target_host:target_port` syntax, where any
+    /// field may be empty.
+    ///
+    /// ```
+    /// use chromenet::socket::connectto::ConnectTo;
+    ///
+    /// let rule = ConnectTo::parse("example.com:443:[::1]:8443").unwrap();
+    /// assert_eq!(rule.target("example.com", 443), Some(("[::1]".to_string(), 8443)));
+    ///
+    /// // Any port of example.com, to the same port on staging
+    /// let rule = ConnectTo::parse("example.com::staging.example.com:").unwrap();
+    /// assert_eq!(rule.target("example.com", 80), Some(("staging.example.com".to_string(), 80)));
+    /// ```
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (host, rest) = split_host(spec)?;
+        let (port, rest) = rest.split_once(':')?;
+        let (target_host, target_port) = split_host(rest)?;
+        Some(Self {
+            host: parse_host(host)?,
+            port: parse_port(port)?,
+            target_host: parse_host(target_host)?,
+            target_port: parse_port(target_port)?,
+        })
+    }
+
+    /// Whether the rule applies to connections to `host:port`.
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        self.host.as_ref().is_none_or(|h| *h == host_key(host))
+            && self.port.is_none_or(|p| p == port)
+    }
+
+    /// Where to connect instead of `host:port`, `None` if the rule does not
+    /// apply.
+    pub fn target(&self, host: &str, port: u16) -> Option<(String, u16)> {
+        if !self.matches(host, port) {
+            return None;
+        }
+        let target_host = match &self.target_host {
+            Some(target) => target.clone(),
+            None => host_key(host),
+        };
+        Some((target_host, self.target_port.unwrap_or(port)))
+    }
+}
+
+/// Split a host, IPv6 literals in brackets, from the rest after its `:`.
+fn split_host(s: &str) -> Option<(&str, &str)> {
+    let end = if s.starts_with('[') {
+        s.find(']')? + 1
+    } else {
+        s.find(':')?
+    };
+    Some((&s[..end], s[end..].strip_prefix(':')?))
+}
+
+fn parse_host(field: &str) -> Option<Option<String>> {
+    if field.is_empty() {
+        return Some(None);
+    }
+    canonicalize_host(field).map(Some)
+}
+
+fn parse_port(field: &str) -> Option<Option<u16>> {
+    if field.is_empty() {
+        return Some(None);
+    }
+    field.parse().ok().filter(|&port| port != 0).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let rule = ConnectTo::parse("Example.COM:443:10.0.0.5:8443").unwrap();
+        assert_eq!(
+            rule,
+            ConnectTo::new("example.com", 443, "10.0.0.5", 8443).unwrap()
+        );
+
+        let rule = ConnectTo::parse("[::1]:80:[0:0::2]:").unwrap();
+        assert_eq!(rule.target("[::1]", 80), Some(("[::2]".to_string(), 80)));
+
+        let any = ConnectTo::parse(":::9000").unwrap();
+        assert_eq!(any.target("a.test", 80), Some(("a.test".to_string(), 9000)));
+
+        for spec in [
+            "",
+            "a.test:80",
+            "a.test:80:b.test",
+            "a.test:x:b.test:80",
+            "a.test:80:b.test:0",
+            "[::1:80:b.test:80",
+        ] {
+            assert!(ConnectTo::parse(spec).is_none(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let rule = ConnectTo::parse("example.com:443:staging.example.com:").unwrap();
+        assert!(rule.matches("EXAMPLE.com", 443));
+        assert!(!rule.matches("example.com", 80));
+        assert!(!rule.matches("www.example.com", 443));
+        assert_eq!(rule.target("example.com", 80), None);
+        assert_eq!(
+            rule.target("example.com", 443),
+            Some(("staging.example.com".to_string(), 443))
+        );
+    }
+}
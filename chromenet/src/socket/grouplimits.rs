@@ -0,0 +1,86 @@
+//! Per-host limits on the sockets of a connection group.
+//!
+//! Chromium mapping: `ClientSocketPoolManager::max_sockets_per_group`
+//!
+//! Chromium allows 6 connections per group, the pool's default. Some hosts
+//! call for another number: an API built for many parallel requests may
+//! take 15, while a polite crawl keeps to 2. Requests over a group's limit
+//! wait in its queue for a socket to come free.
+//!
+//! Patterns are those of [`TlsOverrides`](crate::socket::tls::TlsOverrides):
+//! an exact host or IP literal, `*.example.com` for subdomains, or `*`.
+//! When several match a host, the one added last applies, so add broad
+//! patterns before narrow ones.
+
+use crate::base::host::host_key;
+use crate::socket::tls::overrides::HostPattern;
+
+/// Chromium's default number of sockets per group.
+pub const DEFAULT_MAX_SOCKETS_PER_GROUP: usize = 6;
+
+/// Socket limits per connection group, by host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupLimits {
+    default: usize,
+    hosts: Vec<(HostPattern, usize)>,
+}
+
+impl Default for GroupLimits {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SOCKETS_PER_GROUP)
+    }
+}
+
+impl GroupLimits {
+    /// `max` sockets for every group; at least 1.
+    pub fn new(max: usize) -> Self {
+        Self {
+            default: max.max(1),
+            hosts: Vec::new(),
+        }
+    }
+
+    /// `max` sockets for groups whose host matches `pattern`; at least 1.
+    ///
+    /// ```
+    /// use chromenet::socket::grouplimits::GroupLimits;
+    ///
+    /// let limits = GroupLimits::default()
+    ///     .with_host("api.example.com", 15)
+    ///     .with_host("*.crawl.test", 2);
+    /// assert_eq!(limits.limit_for("api.example.com"), 15);
+    /// assert_eq!(limits.limit_for("www.crawl.test"), 2);
+    /// assert_eq!(limits.limit_for("example.com"), 6);
+    /// ```
+    pub fn with_host(mut self, pattern: &str, max: usize) -> Self {
+        self.hosts.push((HostPattern::parse(pattern), max.max(1)));
+        self
+    }
+
+    /// Sockets allowed for a group connecting to `host`.
+    pub fn limit_for(&self, host: &str) -> usize {
+        let host = host_key(host);
+        self.hosts
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.matches(&host))
+            .map_or(self.default, |&(_, max)| max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_matching_pattern_wins() {
+        let limits = GroupLimits::new(4)
+            .with_host("*", 8)
+            .with_host("*.example.com", 10)
+            .with_host("slow.example.com", 0);
+        assert_eq!(limits.limit_for("other.test"), 8);
+        assert_eq!(limits.limit_for("www.Example.com"), 10);
+        assert_eq!(limits.limit_for("slow.example.com"), 1);
+        assert_eq!(GroupLimits::new(0).limit_for("a.test"), 1);
+    }
+}
@@ -0,0 +1,93 @@
+//! Fault injection for the connect phase.
+//!
+//! [`ConnectHooks`] sit in front of DNS resolution, each TCP connect
+//! attempt and each TLS handshake of a [`ConnectJob`], so tests can
+//! simulate NXDOMAIN, timeouts, unreachable IPv6 or certificate errors
+//! deterministically, without network access. Install them with
+//! [`ClientBuilder::connect_hooks`](crate::ClientBuilder::connect_hooks).
+//!
+//! Every method defaults to letting the real step run, so implementations
+//! only override the steps they fake.
+//!
+//! ```no_run
+//! use chromenet::base::neterror::NetError;
+//! use chromenet::socket::hooks::ConnectHooks;
+//! use chromenet::Client;
+//! use std::net::IpAddr;
+//!
+//! struct NoDns;
+//!
+//! impl ConnectHooks for NoDns {
+//!     fn resolve(&self, _host: &str) -> Option<Result<Vec<IpAddr>, NetError>> {
+//!         Some(Err(NetError::NameNotResolved))
+//!     }
+//! }
+//!
+//! let client = Client::builder().connect_hooks(NoDns).build();
+//! ```
+//!
+//! [`ConnectJob`]: crate::socket::connectjob::ConnectJob
+
+use crate::base::neterror::NetError;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// What a TCP connect attempt does.
+#[derive(Debug, Clone)]
+pub enum ConnectOutcome {
+    /// Connect to the address for real.
+    Proceed,
+    /// Connect to this address instead, e.g. a local test server.
+    Redirect(SocketAddr),
+    /// Fail with `error` after `delay`, without touching the network.
+    ///
+    /// A delay longer than the Happy Eyeballs fallback (250 ms) on IPv6
+    /// addresses makes the IPv4 attempts start first. One longer than the
+    /// attempt timeout fails with `ConnectionTimedOut` once it runs out,
+    /// like an unresponsive address.
+    Fail {
+        /// Error of the attempt.
+        error: NetError,
+        /// Time before the attempt fails.
+        delay: Duration,
+    },
+}
+
+impl ConnectOutcome {
+    /// Fail right away with `error`.
+    pub fn fail(error: NetError) -> Self {
+        ConnectOutcome::Fail {
+            error,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+/// Hooks into the DNS, TCP and TLS steps of establishing a connection.
+///
+/// Through a proxy, the hooks see the proxy's host and addresses for the
+/// DNS and TCP steps, and both handshakes of a TLS-in-TLS tunnel.
+pub trait ConnectHooks: Send + Sync {
+    /// Answer the lookup of `host` instead of the resolver.
+    ///
+    /// `Some(Ok(addresses))` fakes an answer and `Some(Err(_))` a failure,
+    /// e.g. [`NetError::NameNotResolved`] for NXDOMAIN. `None` resolves
+    /// for real.
+    fn resolve(&self, host: &str) -> Option<Result<Vec<IpAddr>, NetError>> {
+        let _ = host;
+        None
+    }
+
+    /// Decide how the TCP connect attempt to `addr` goes.
+    fn connect(&self, addr: SocketAddr) -> ConnectOutcome {
+        let _ = addr;
+        ConnectOutcome::Proceed
+    }
+
+    /// Fail the TLS handshake with `host` with the returned error, e.g.
+    /// [`NetError::SslPinnedKeyNotInCertChain`]. `None` runs the handshake.
+    fn tls_handshake(&self, host: &str) -> Option<NetError> {
+        let _ = host;
+        None
+    }
+}
@@ -0,0 +1,179 @@
+//! Retiring long-lived connections.
+//!
+//! Pooled HTTP/1.1 sockets and HTTP/2 sessions are otherwise reused for as
+//! long as the server keeps them open. Behind CDNs that rotate their
+//! addresses, that pins a client to an endpoint DNS stopped handing out
+//! long ago, and some load balancers misbehave on connections that carried
+//! many requests. A [`ConnectionLifetime`] bounds that:
+//! - `max_age` retires connections some time after they were established
+//! - `max_requests` retires connections once they carried that many
+//!   requests, counting the streams of an HTTP/2 session
+//! - `dns_ttl` re-resolves the host of a connection that is about to be
+//!   reused once its last lookup is older than the TTL, and retires the
+//!   connection if its address is no longer in the answer
+//!
+//! It also sets how long connections may sit idle: 5 minutes once they
+//! carried a request and 10 seconds before, like Chromium's
+//! `used_idle_socket_timeout` and `unused_idle_socket_timeout`. An HTTP/2
+//! session is idle while it has no open streams.
+//!
+//! Retired HTTP/2 sessions are not torn down: streams already open finish,
+//! only new requests go to a new connection.
+//!
+//! Connections through a proxy are not re-resolved, since the client never
+//! looked up the target's address. A failed lookup keeps the connection, as
+//! it says nothing about the endpoint.
+
+use std::time::{Duration, Instant};
+
+/// Idle timeout of connections that carried a request (5 minutes).
+const USED_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Idle timeout of connections that never carried one (10 seconds).
+const UNUSED_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// When pooled connections stop being reused. The limits are off by
+/// default, the idle timeouts Chromium's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionLifetime {
+    max_age: Option<Duration>,
+    max_requests: Option<u32>,
+    dns_ttl: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    unused_idle_timeout: Option<Duration>,
+}
+
+impl ConnectionLifetime {
+    /// No limits: connections are reused until they close or idle out.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop reusing connections `max_age` after they were established.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Stop reusing connections once they carried `max_requests` requests.
+    pub fn with_max_requests(mut self, max_requests: u32) -> Self {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Close connections that carried a request after `timeout` without
+    /// one, instead of 5 minutes.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Close connections that never carried a request after `timeout`,
+    /// instead of 10 seconds.
+    pub fn with_unused_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.unused_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Re-resolve the host before reusing a connection whose address was
+    /// last confirmed more than `ttl` ago.
+    pub fn with_dns_ttl(mut self, ttl: Duration) -> Self {
+        self.dns_ttl = Some(ttl);
+        self
+    }
+
+    /// Maximum connection age, if any.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// Maximum number of requests per connection, if any.
+    pub fn max_requests(&self) -> Option<u32> {
+        self.max_requests
+    }
+
+    /// How long a connection may sit idle, depending on whether it
+    /// carried a request.
+    pub fn idle_timeout(&self, was_used: bool) -> Duration {
+        if was_used {
+            self.idle_timeout.unwrap_or(USED_IDLE_TIMEOUT)
+        } else {
+            self.unused_idle_timeout.unwrap_or(UNUSED_IDLE_TIMEOUT)
+        }
+    }
+
+    /// Time after which a connection's address is looked up again, if any.
+    pub fn dns_ttl(&self) -> Option<Duration> {
+        self.dns_ttl
+    }
+
+    /// Whether a connection established at `connected_at` is too old to be
+    /// reused at `now`.
+    pub fn is_expired(&self, connected_at: Instant, now: Instant) -> bool {
+        self.max_age
+            .is_some_and(|max_age| now.saturating_duration_since(connected_at) >= max_age)
+    }
+
+    /// Whether a connection that carried `requests` requests is spent.
+    pub fn is_spent(&self, requests: u32) -> bool {
+        self.max_requests.is_some_and(|max| requests >= max)
+    }
+
+    /// Whether a connection idle since `idle_since` timed out at `now`.
+    pub fn is_idle_expired(&self, idle_since: Instant, was_used: bool, now: Instant) -> bool {
+        now.saturating_duration_since(idle_since) >= self.idle_timeout(was_used)
+    }
+
+    /// Whether an address confirmed at `resolved_at` must be looked up
+    /// again at `now`.
+    pub fn needs_resolution(&self, resolved_at: Instant, now: Instant) -> bool {
+        self.dns_ttl
+            .is_some_and(|ttl| now.saturating_duration_since(resolved_at) >= ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_are_off_by_default() {
+        let lifetime = ConnectionLifetime::new();
+        let then = Instant::now();
+        let later = then + Duration::from_secs(86_400);
+        assert!(!lifetime.is_expired(then, later));
+        assert!(!lifetime.needs_resolution(then, later));
+        assert!(!lifetime.is_spent(u32::MAX));
+    }
+
+    #[test]
+    fn test_idle_timeouts() {
+        let then = Instant::now();
+        let defaults = ConnectionLifetime::new();
+        assert!(!defaults.is_idle_expired(then, true, then + Duration::from_secs(299)));
+        assert!(defaults.is_idle_expired(then, true, then + Duration::from_secs(300)));
+        assert!(defaults.is_idle_expired(then, false, then + Duration::from_secs(10)));
+
+        let custom = ConnectionLifetime::new()
+            .with_idle_timeout(Duration::from_secs(30))
+            .with_unused_idle_timeout(Duration::from_secs(1));
+        assert_eq!(custom.idle_timeout(true), Duration::from_secs(30));
+        assert!(custom.is_idle_expired(then, false, then + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_limits() {
+        let lifetime = ConnectionLifetime::new()
+            .with_max_age(Duration::from_secs(600))
+            .with_dns_ttl(Duration::from_secs(60));
+        let then = Instant::now();
+        assert!(!lifetime.is_expired(then, then + Duration::from_secs(599)));
+        assert!(lifetime.is_expired(then, then + Duration::from_secs(600)));
+        assert!(!lifetime.needs_resolution(then, then + Duration::from_secs(59)));
+        assert!(lifetime.needs_resolution(then, then + Duration::from_secs(60)));
+
+        let lifetime = ConnectionLifetime::new().with_max_requests(100);
+        assert!(!lifetime.is_spent(99));
+        assert!(lifetime.is_spent(100));
+    }
+}
@@ -1,16 +1,36 @@
 //! Socket and connection management.
 //!
 //! Provides connection pooling and socket handling mirroring Chromium's `net/socket/`:
-//! - [`pool`]: Connection pooling (6 per host, 256 total)
+//! - [`pool`]: Connection pooling (6 per host by default, 256 total)
 //! - [`connectjob`]: DNS → TCP → TLS connection flow
+//! - [`connectto`]: Connecting to another address than the URL's host
+//! - `connector`: hyper-util connector over the pool (feature `hyper-connector`)
+//! - [`grouplimits`]: Per-host socket limits of connection groups
+//! - [`h2tunnel`]: CONNECT tunnels over HTTP/2 sessions to HTTPS proxies
+//! - [`hooks`]: Fault injection for DNS, TCP connects and TLS handshakes
+//! - [`lifetime`]: Retiring old connections and ones whose address left DNS
 //! - [`proxy`]: HTTP/HTTPS/SOCKS5 proxy support
+//! - [`socketfactory`]: Pluggable transport connections (TCP by default)
+//! - [`systemproxy`]: Proxy settings of the environment and the OS
 //! - [`tls`]: TLS configuration with BoringSSL
+//! - [`wire`]: Byte counters and wire capture
 
 pub mod authcache;
 pub mod client;
 pub mod connectjob;
+#[cfg(feature = "hyper-connector")]
+pub mod connector;
+pub mod connectto;
+pub mod grouplimits;
+pub mod h2tunnel;
+pub mod hooks;
+pub mod lifetime;
 pub mod matcher;
+pub mod nextproto;
 pub mod pool;
 pub mod proxy;
+pub mod socketfactory;
 pub mod stream;
+pub mod systemproxy;
 pub mod tls;
+pub mod wire;
@@ -0,0 +1,72 @@
+//! Negotiated application protocol.
+//!
+//! Chromium equivalent: `net/socket/next_proto.h`
+
+use std::fmt;
+
+/// Application protocol negotiated for a connection via ALPN.
+///
+/// Cleartext connections and TLS connections where the server did not
+/// select a protocol report [`NextProto::Unknown`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NextProto {
+    /// No protocol was negotiated.
+    #[default]
+    Unknown,
+    /// `http/1.1`
+    Http11,
+    /// `h2`
+    Http2,
+    /// `h3`
+    Http3,
+}
+
+impl NextProto {
+    /// Map an ALPN protocol identifier to a `NextProto`.
+    pub fn from_alpn(alpn: &[u8]) -> Self {
+        match alpn {
+            b"http/1.1" => NextProto::Http11,
+            b"h2" => NextProto::Http2,
+            b"h3" => NextProto::Http3,
+            _ => NextProto::Unknown,
+        }
+    }
+
+    /// ALPN protocol identifier, or `"unknown"` (as in Chromium's
+    /// `NextProtoToString`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NextProto::Unknown => "unknown",
+            NextProto::Http11 => "http/1.1",
+            NextProto::Http2 => "h2",
+            NextProto::Http3 => "h3",
+        }
+    }
+}
+
+impl fmt::Display for NextProto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_alpn() {
+        assert_eq!(NextProto::from_alpn(b"h2"), NextProto::Http2);
+        assert_eq!(NextProto::from_alpn(b"http/1.1"), NextProto::Http11);
+        assert_eq!(NextProto::from_alpn(b"h3"), NextProto::Http3);
+        assert_eq!(NextProto::from_alpn(b"spdy/3.1"), NextProto::Unknown);
+    }
+
+    #[test]
+    fn test_roundtrip_str() {
+        for proto in [NextProto::Http11, NextProto::Http2, NextProto::Http3] {
+            assert_eq!(NextProto::from_alpn(proto.as_str().as_bytes()), proto);
+        }
+        assert_eq!(NextProto::Unknown.to_string(), "unknown");
+    }
+}
@@ -1,13 +1,27 @@
+use crate::base::host::{host_key, url_host};
 use crate::base::neterror::NetError;
-use crate::socket::connectjob::ConnectJob;
-use crate::socket::stream::BoxedSocket;
-use crate::socket::tls::TlsOptions;
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::dns::{PostResolve, Resolve};
+use crate::metrics::MetricsRecorder;
+use crate::socket::connectjob::{ConnectJob, ConnectTimeouts};
+use crate::socket::connectto::ConnectTo;
+use crate::socket::grouplimits::GroupLimits;
+use crate::socket::h2tunnel::ProxySessions;
+use crate::socket::hooks::ConnectHooks;
+use crate::socket::lifetime::ConnectionLifetime;
+use crate::socket::proxy::ProxySettings;
+use crate::socket::socketfactory::{SocketFactory, TcpSocketFactory};
+use crate::socket::stream::{BoxedSocket, ConnectionInfo};
+use crate::socket::tls::{AlpnProtocol, ServerName, TlsOptions, TlsOverrides};
+use crate::socket::wire::SocketInstrumentation;
 use dashmap::DashMap;
+use std::borrow::Cow;
 use std::cmp::Ordering as CmpOrdering;
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
 /// Request priority (matches Chromium's RequestPriority).
@@ -22,36 +36,170 @@ pub enum RequestPriority {
     Highest = 5,
 }
 
-/// Identifies a connection group (scheme, host, port).
+/// Which connections a request may use.
+///
+/// By default a request takes, in order: the group's live HTTP/2 session,
+/// its most recently used idle socket, or a new connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ConnectionReuse {
+    /// Reuse an HTTP/2 session or idle socket of the group if there is one.
+    #[default]
+    Allowed,
+    /// Always open a new connection, e.g. so the TLS and HTTP/2 handshakes
+    /// of a fingerprint-sensitive first request are seen. When the group is
+    /// at its limit, an idle socket is closed to make room. Later requests
+    /// may reuse the new connection.
+    Fresh,
+    /// Open a new connection like [`Fresh`](Self::Fresh) and close it after
+    /// the response: an HTTP/1.1 request is sent with `Connection: close`
+    /// and its socket never returns to the pool, an HTTP/2 session is not
+    /// shared with other requests.
+    Close,
+}
+
+/// Identifies a connection group.
+///
+/// Sockets are only shared within a group. Like Chromium's
+/// `ClientSocketPool::GroupId`, the group covers the destination, the proxy
+/// it is reached through and the requester's [`NetworkIsolationKey`], so
+/// connections via different proxies or for different top-frame sites are
+/// never reused for one another. Connections announcing a [`ServerName`]
+/// other than the host, made to another address under a [`ConnectTo`]
+/// rule, or to addresses a [`PostResolve`] hook picked, get groups of
+/// their own as well.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct GroupId {
+pub struct GroupId {
     scheme: Arc<str>,
     host: Arc<str>,
     port: u16,
+    /// Proxy as `scheme://[user@]host:port`, `None` for direct connections
+    proxy: Option<Arc<str>>,
+    network_isolation_key: Option<NetworkIsolationKey>,
+    server_name: ServerName,
+    /// Host and port connected to instead of the destination
+    connect_to: Option<(Arc<str>, u16)>,
+    post_resolve: Option<PostResolve>,
 }
 
 impl GroupId {
-    fn from_url(url: &Url) -> Option<Self> {
+    /// Group for connections to `url` through `proxy`, partitioned by `nik`.
+    ///
+    /// The proxy username is part of the key, since proxies commonly use it
+    /// to select an exit or session. Returns `None` if `url` has no host or
+    /// port.
+    pub fn new(
+        url: &Url,
+        proxy: Option<&ProxySettings>,
+        nik: Option<&NetworkIsolationKey>,
+    ) -> Option<Self> {
+        let proxy = proxy.and_then(|proxy| {
+            let (host, port) = proxy.host_port()?;
+            let user = proxy
+                .username
+                .as_deref()
+                .filter(|u| !u.is_empty())
+                .map(|u| format!("{}@", u))
+                .unwrap_or_default();
+            Some(format!("{}://{}{}:{}", proxy.url.scheme(), user, host, port).into())
+        });
         Some(GroupId {
             scheme: url.scheme().into(),
-            host: url.host_str()?.into(),
+            host: url_host(url)?.into(),
             port: url.port_or_known_default()?,
+            proxy,
+            network_isolation_key: nik.cloned(),
+            server_name: ServerName::Host,
+            connect_to: None,
+            post_resolve: None,
         })
     }
+
+    /// Announce `server_name` in the TLS handshake instead of the host.
+    pub fn with_server_name(mut self, server_name: ServerName) -> Self {
+        self.server_name = server_name;
+        self
+    }
+
+    /// Connect where the first of `rules` matching the destination says.
+    pub fn with_connect_to(mut self, rules: &[ConnectTo]) -> Self {
+        self.connect_to = rules
+            .iter()
+            .find_map(|rule| rule.target(&self.host, self.port))
+            .map(|(host, port)| (host.into(), port));
+        self
+    }
+
+    /// Pass the DNS answers of new connections through `hook`.
+    pub fn with_post_resolve(mut self, hook: Option<PostResolve>) -> Self {
+        self.post_resolve = hook;
+        self
+    }
+
+    fn from_url(url: &Url) -> Option<Self> {
+        Self::new(url, None, None)
+    }
+
+    /// Destination host, in canonical form.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Destination port.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Proxy the group's connections go through.
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Partition the group belongs to.
+    pub fn network_isolation_key(&self) -> Option<&NetworkIsolationKey> {
+        self.network_isolation_key.as_ref()
+    }
+
+    /// Name the group's TLS connections announce.
+    pub fn server_name(&self) -> &ServerName {
+        &self.server_name
+    }
+
+    /// Host and port the group's connections are made to instead of the
+    /// destination, if any.
+    pub fn connect_to(&self) -> Option<(&str, u16)> {
+        self.connect_to
+            .as_ref()
+            .map(|(host, port)| (&**host, *port))
+    }
+
+    /// Hook rewriting the DNS answers of the group's connections, if any.
+    pub fn post_resolve(&self) -> Option<&PostResolve> {
+        self.post_resolve.as_ref()
+    }
+
+    /// Host the group's direct connections resolve.
+    pub(crate) fn connect_host(&self) -> &str {
+        self.connect_to().map_or(&self.host, |(host, _)| host)
+    }
 }
 
 /// A pending socket request waiting in queue.
 struct PendingRequest {
     priority: RequestPriority,
     sender: oneshot::Sender<Result<PoolResult, NetError>>,
+    group_id: GroupId,
     url: Url,
-    proxy: Option<crate::socket::proxy::ProxySettings>,
+    proxy: Option<ProxySettings>,
+    alpn: Option<&'static [AlpnProtocol]>,
+    reuse: ConnectionReuse,
     created_at: std::time::Instant,
 }
 
 /// Result from the pool.
 pub struct PoolResult {
     pub socket: BoxedSocket,
+    /// Group the socket belongs to; release or discard it there.
+    pub group_id: GroupId,
     pub is_h2: bool,
     pub is_reused: bool,
 }
@@ -94,6 +242,8 @@ struct Group {
     idle_sockets: VecDeque<IdleSocket>,
     active_count: usize,
     pending_requests: Vec<PendingRequest>,
+    /// Sockets connected before this were flushed: closed when released.
+    flushed_at: Option<std::time::Instant>,
 }
 
 /// Idle socket with metadata for timeout tracking.
@@ -112,6 +262,7 @@ impl Group {
             idle_sockets: VecDeque::new(),
             active_count: 0,
             pending_requests: Vec::new(),
+            flushed_at: None,
         }
     }
 
@@ -123,6 +274,17 @@ impl Group {
         self.total_slots() < max_per_group
     }
 
+    /// Take the idle socket to reuse: the most recently used one, so the
+    /// others age out, or else the oldest never used.
+    ///
+    /// Chromium mapping: `TransportClientSocketPool::AssignIdleSocketToRequest`
+    fn pop_idle_socket(&mut self) -> Option<IdleSocket> {
+        match self.idle_sockets.iter().rposition(|s| s.was_used) {
+            Some(i) => self.idle_sockets.remove(i),
+            None => self.idle_sockets.pop_front(),
+        }
+    }
+
     fn pop_highest_priority_request(&mut self) -> Option<PendingRequest> {
         if self.pending_requests.is_empty() {
             return None;
@@ -142,23 +304,45 @@ impl Group {
 /// Now with request queuing when limits are reached.
 pub struct ClientSocketPool {
     // Limits
-    max_sockets_per_group: usize, // Default 6
-    max_sockets_total: usize,     // Default 256
+    group_limits: Arc<GroupLimits>, // Default 6 per group
+    max_sockets_total: usize,       // Default 256
 
     // State
     groups: Arc<DashMap<GroupId, Group>>,
     total_active: Arc<AtomicUsize>,
     tls_options: Option<TlsOptions>,
+    tls_overrides: TlsOverrides,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    connect_hooks: Option<Arc<dyn ConnectHooks>>,
+    resolver: Arc<dyn Resolve>,
+    socket_factory: Arc<dyn SocketFactory>,
+    lifetime: ConnectionLifetime,
+    instrumentation: SocketInstrumentation,
+    connect_timeouts: ConnectTimeouts,
+    proxy_sessions: ProxySessions,
+    /// Set by [`close`](ClientSocketPool::close); released sockets are
+    /// closed instead of kept idle.
+    closed: Arc<AtomicBool>,
 }
 
 impl Clone for ClientSocketPool {
     fn clone(&self) -> Self {
         Self {
-            max_sockets_per_group: self.max_sockets_per_group,
+            group_limits: Arc::clone(&self.group_limits),
             max_sockets_total: self.max_sockets_total,
             groups: Arc::clone(&self.groups),
             total_active: Arc::clone(&self.total_active),
             tls_options: self.tls_options.clone(),
+            tls_overrides: self.tls_overrides.clone(),
+            metrics: self.metrics.clone(),
+            connect_hooks: self.connect_hooks.clone(),
+            resolver: Arc::clone(&self.resolver),
+            socket_factory: Arc::clone(&self.socket_factory),
+            lifetime: self.lifetime,
+            instrumentation: self.instrumentation.clone(),
+            connect_timeouts: self.connect_timeouts,
+            proxy_sessions: self.proxy_sessions.clone(),
+            closed: Arc::clone(&self.closed),
         }
     }
 }
@@ -166,7 +350,7 @@ impl Clone for ClientSocketPool {
 impl std::fmt::Debug for ClientSocketPool {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ClientSocketPool")
-            .field("max_sockets_per_group", &self.max_sockets_per_group)
+            .field("group_limits", &self.group_limits)
             .field("max_sockets_total", &self.max_sockets_total)
             .field("total_active", &self.total_active.load(Ordering::Relaxed))
             .finish()
@@ -182,11 +366,134 @@ impl Default for ClientSocketPool {
 impl ClientSocketPool {
     pub fn new(tls_options: Option<TlsOptions>) -> Self {
         Self {
-            max_sockets_per_group: 6,
+            group_limits: Arc::new(GroupLimits::default()),
             max_sockets_total: 256,
             groups: Arc::new(DashMap::new()),
             total_active: Arc::new(AtomicUsize::new(0)),
             tls_options,
+            tls_overrides: TlsOverrides::default(),
+            metrics: None,
+            connect_hooks: None,
+            resolver: crate::dns::default_resolver(),
+            socket_factory: Arc::new(TcpSocketFactory),
+            lifetime: ConnectionLifetime::default(),
+            instrumentation: SocketInstrumentation::default(),
+            connect_timeouts: ConnectTimeouts::default(),
+            proxy_sessions: ProxySessions::default(),
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Allow groups as many sockets as `limits` gives their host.
+    pub fn with_group_limits(mut self, limits: GroupLimits) -> Self {
+        self.group_limits = Arc::new(limits);
+        self
+    }
+
+    /// Apply per-host TLS overrides on top of the pool's TLS options.
+    pub fn with_tls_overrides(mut self, overrides: TlsOverrides) -> Self {
+        self.tls_overrides = overrides;
+        self
+    }
+
+    /// Report connect timings and queueing delays to `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsRecorder>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Let `hooks` fake the DNS, TCP and TLS steps of new connections.
+    pub fn with_connect_hooks(mut self, hooks: Arc<dyn ConnectHooks>) -> Self {
+        self.connect_hooks = Some(hooks);
+        self
+    }
+
+    /// Resolve hosts with `resolver` instead of
+    /// [`default_resolver`](crate::dns::default_resolver).
+    pub fn with_resolver(mut self, resolver: Arc<dyn Resolve>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Open the transport connections of new connections with `factory`
+    /// instead of plain TCP.
+    pub fn with_socket_factory(mut self, factory: Arc<dyn SocketFactory>) -> Self {
+        self.socket_factory = factory;
+        self
+    }
+
+    /// Give up connecting after `timeouts`.
+    pub fn with_connect_timeouts(mut self, timeouts: ConnectTimeouts) -> Self {
+        self.connect_timeouts = timeouts;
+        self
+    }
+
+    /// Count and record the traffic of new connections, see
+    /// [`crate::socket::wire`].
+    pub fn with_instrumentation(mut self, instrumentation: SocketInstrumentation) -> Self {
+        self.instrumentation = instrumentation;
+        self
+    }
+
+    /// Stop reusing connections that `lifetime` retires, see
+    /// [`crate::socket::lifetime`].
+    pub fn with_connection_lifetime(mut self, lifetime: ConnectionLifetime) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// When connections stop being reused.
+    pub fn connection_lifetime(&self) -> &ConnectionLifetime {
+        &self.lifetime
+    }
+
+    /// TLS options of new connections, before per-host overrides.
+    pub fn tls_options(&self) -> Option<&TlsOptions> {
+        self.tls_options.as_ref()
+    }
+
+    /// Whether a connection of `group_id` described by `info` may carry
+    /// another request.
+    ///
+    /// Looks the group's host up again if the connection's address is due
+    /// for it, and records when the address was confirmed.
+    pub(crate) async fn check_reusable(
+        &self,
+        group_id: &GroupId,
+        info: &mut ConnectionInfo,
+    ) -> bool {
+        let host = group_id.connect_host();
+        let now = std::time::Instant::now();
+        if self.lifetime.is_expired(info.connected_at, now) {
+            tracing::debug!(target: "chromenet::socket", host, "retiring connection past its maximum age");
+            return false;
+        }
+        if self.lifetime.is_spent(info.requests) {
+            tracing::debug!(target: "chromenet::socket", host, requests = info.requests, "retiring connection after its maximum requests");
+            return false;
+        }
+        let Some(addr) = info.remote_addr else {
+            return true;
+        };
+        // IP literals have nothing to look up
+        let literal = host.starts_with('[') || host.parse::<std::net::IpAddr>().is_ok();
+        if literal || !self.lifetime.needs_resolution(info.resolved_at, now) {
+            return true;
+        }
+        let hooks = self.connect_hooks.as_deref();
+        match ConnectJob::resolve(host, &*self.resolver, hooks, group_id.post_resolve()).await {
+            Ok(ips) if ips.contains(&addr.ip()) => {
+                info.resolved_at = now;
+                true
+            }
+            Ok(_) => {
+                tracing::debug!(target: "chromenet::socket", host, %addr, "retiring connection, address no longer in DNS");
+                false
+            }
+            Err(e) => {
+                tracing::debug!(target: "chromenet::socket", host, error = %e, "keeping connection, lookup failed");
+                true
+            }
         }
     }
 
@@ -194,7 +501,7 @@ impl ClientSocketPool {
     pub async fn request_socket(
         &self,
         url: &Url,
-        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        proxy: Option<&ProxySettings>,
     ) -> Result<PoolResult, NetError> {
         self.request_socket_with_priority(url, proxy, RequestPriority::default())
             .await
@@ -205,13 +512,101 @@ impl ClientSocketPool {
     pub async fn request_socket_with_priority(
         &self,
         url: &Url,
-        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        proxy: Option<&ProxySettings>,
         priority: RequestPriority,
     ) -> Result<PoolResult, NetError> {
-        let group_id = GroupId::from_url(url).ok_or(NetError::InvalidUrl)?;
+        let group_id = GroupId::new(url, proxy, None).ok_or(NetError::InvalidUrl)?;
+        self.request_socket_impl(
+            group_id,
+            url,
+            proxy,
+            priority,
+            None,
+            ConnectionReuse::Allowed,
+        )
+        .await
+    }
 
+    /// Request a socket whose TLS handshake offers only the given ALPN protocols.
+    ///
+    /// Used to force a protocol version. Idle sockets are only handed out when
+    /// `alpn` includes HTTP/1.1, since pooled sockets always speak HTTP/1.1.
+    pub async fn request_socket_with_alpn(
+        &self,
+        url: &Url,
+        proxy: Option<&ProxySettings>,
+        alpn: &'static [AlpnProtocol],
+    ) -> Result<PoolResult, NetError> {
+        let group_id = GroupId::new(url, proxy, None).ok_or(NetError::InvalidUrl)?;
+        self.request_socket_impl(
+            group_id,
+            url,
+            proxy,
+            RequestPriority::default(),
+            Some(alpn),
+            ConnectionReuse::Allowed,
+        )
+        .await
+    }
+
+    /// Request a socket from the partition for `nik`.
+    ///
+    /// Sockets are only reused by requests with the same key (or both
+    /// without one). `alpn` works as in [`Self::request_socket_with_alpn`].
+    pub async fn request_socket_isolated(
+        &self,
+        url: &Url,
+        proxy: Option<&ProxySettings>,
+        nik: Option<&NetworkIsolationKey>,
+        alpn: Option<&'static [AlpnProtocol]>,
+    ) -> Result<PoolResult, NetError> {
+        let group_id = GroupId::new(url, proxy, nik).ok_or(NetError::InvalidUrl)?;
+        self.request_socket_impl(
+            group_id,
+            url,
+            proxy,
+            RequestPriority::default(),
+            alpn,
+            ConnectionReuse::Allowed,
+        )
+        .await
+    }
+
+    /// Request a socket from `group_id`, which must have been built from
+    /// `url` and `proxy`.
+    ///
+    /// Unlike [`Self::request_socket_isolated`], this honors the group's
+    /// [`ServerName`]. When the group is at its limit, `priority` decides
+    /// the order in which waiting requests get sockets. With
+    /// [`ConnectionReuse::Fresh`] the socket is always a new connection.
+    pub async fn request_socket_for_group(
+        &self,
+        group_id: &GroupId,
+        url: &Url,
+        proxy: Option<&ProxySettings>,
+        alpn: Option<&'static [AlpnProtocol]>,
+        priority: RequestPriority,
+        reuse: ConnectionReuse,
+    ) -> Result<PoolResult, NetError> {
+        self.request_socket_impl(group_id.clone(), url, proxy, priority, alpn, reuse)
+            .await
+    }
+
+    async fn request_socket_impl(
+        &self,
+        group_id: GroupId,
+        url: &Url,
+        proxy: Option<&ProxySettings>,
+        priority: RequestPriority,
+        alpn: Option<&'static [AlpnProtocol]>,
+        reuse: ConnectionReuse,
+    ) -> Result<PoolResult, NetError> {
         // Try to get socket immediately
-        if let Some(result) = self.try_get_socket_immediate(&group_id, url, proxy).await? {
+        if let Some(result) = self
+            .try_get_socket_immediate(&group_id, url, proxy, alpn, reuse)
+            .await?
+        {
+            self.record_pool_wait(std::time::Duration::ZERO);
             return Ok(result);
         }
 
@@ -225,8 +620,11 @@ impl ClientSocketPool {
             group.pending_requests.push(PendingRequest {
                 priority,
                 sender: tx,
+                group_id,
                 url: url.clone(),
                 proxy: proxy.cloned(),
+                alpn,
+                reuse,
                 created_at: std::time::Instant::now(),
             });
         }
@@ -240,27 +638,59 @@ impl ClientSocketPool {
         &self,
         group_id: &GroupId,
         url: &Url,
-        proxy: Option<&crate::socket::proxy::ProxySettings>,
+        proxy: Option<&ProxySettings>,
+        alpn: Option<&'static [AlpnProtocol]>,
+        reuse: ConnectionReuse,
     ) -> Result<Option<PoolResult>, NetError> {
+        // 1. Check for idle socket (idle sockets always speak HTTP/1.1)
+        let idle_allowed = reuse == ConnectionReuse::Allowed
+            && alpn.is_none_or(|alpn| alpn.contains(&AlpnProtocol::HTTP1));
+        while let Some(mut idle_socket) = idle_allowed
+            .then(|| self.take_idle_socket(group_id))
+            .flatten()
+        {
+            let idle_expired = self.lifetime.is_idle_expired(
+                idle_socket.start_time,
+                idle_socket.was_used,
+                std::time::Instant::now(),
+            );
+            let info = idle_socket.socket.connection_info_mut();
+            if !idle_expired && self.check_reusable(group_id, info).await {
+                return Ok(Some(PoolResult {
+                    socket: idle_socket.socket,
+                    group_id: group_id.clone(),
+                    is_h2: idle_socket.is_h2,
+                    is_reused: true,
+                }));
+            }
+            // Retired: give up its slot without serving waiters, the
+            // caller takes it next
+            let mut group = self
+                .groups
+                .entry(group_id.clone())
+                .or_insert_with(Group::new);
+            group.active_count = group.active_count.saturating_sub(1);
+            self.total_active.fetch_sub(1, Ordering::Relaxed);
+        }
+
         let mut group = self
             .groups
             .entry(group_id.clone())
             .or_insert_with(Group::new);
 
-        // 1. Check for idle socket
-        if let Some(idle_socket) = group.idle_sockets.pop_front() {
-            // For now, assume idle sockets are usable (can add is_connected check later)
-            group.active_count += 1;
-            self.total_active.fetch_add(1, Ordering::Relaxed);
-            return Ok(Some(PoolResult {
-                socket: idle_socket.socket,
-                is_h2: idle_socket.is_h2,
-                is_reused: true,
-            }));
+        // 2. Check limits. A fresh connection makes room by closing the
+        // oldest idle socket
+        let max_per_group = self.group_limits.limit_for(group_id.host());
+        if reuse != ConnectionReuse::Allowed
+            && !group.has_available_slot(max_per_group)
+            && group.idle_sockets.pop_front().is_some()
+        {
+            tracing::trace!(
+                host = group_id.host(),
+                "closed idle socket for a fresh connection"
+            );
         }
-
-        // 2. Check limits
-        if !group.has_available_slot(self.max_sockets_per_group) {
+        if !group.has_available_slot(max_per_group) {
             return Ok(None); // Will be queued
         }
 
@@ -274,12 +704,40 @@ impl ClientSocketPool {
         self.total_active.fetch_add(1, Ordering::Relaxed);
         drop(group); // Release lock before async connect
 
-        match ConnectJob::connect(url, proxy, self.tls_options.as_ref()).await {
-            Ok(result) => Ok(Some(PoolResult {
-                socket: result.socket,
-                is_h2: result.is_h2,
-                is_reused: false,
+        let host = url.host_str().unwrap_or_default();
+        let tls_options = self.tls_overrides.resolve(host, self.tls_options.as_ref());
+        let tls_options = match alpn {
+            Some(alpn) => Some(Cow::Owned(TlsOptions {
+                alpn_protocols: Some(Cow::Borrowed(alpn)),
+                ..tls_options.map(Cow::into_owned).unwrap_or_default()
             })),
+            None => tls_options,
+        };
+
+        let connect = ConnectJob::connect_for_group(
+            url,
+            proxy,
+            tls_options.as_deref(),
+            group_id,
+            &self.proxy_sessions,
+            &*self.resolver,
+            self.connect_hooks.as_deref(),
+            &*self.socket_factory,
+            &self.instrumentation,
+            self.connect_timeouts,
+        );
+        match connect.await {
+            Ok(result) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_connect(&result.timing);
+                }
+                Ok(Some(PoolResult {
+                    socket: result.socket,
+                    group_id: group_id.clone(),
+                    is_h2: result.is_h2,
+                    is_reused: false,
+                }))
+            }
             Err(e) => {
                 // Decrement on failure
                 let mut group = self
@@ -293,12 +751,52 @@ impl ClientSocketPool {
         }
     }
 
-    /// Release a socket back to the pool.
+    /// Take the next idle socket of `group_id`, counting it as active.
+    fn take_idle_socket(&self, group_id: &GroupId) -> Option<IdleSocket> {
+        let mut group = self.groups.get_mut(group_id)?;
+        let idle_socket = group.pop_idle_socket()?;
+        // For now, assume idle sockets are usable (can add is_connected check later)
+        group.active_count += 1;
+        self.total_active.fetch_add(1, Ordering::Relaxed);
+        Some(idle_socket)
+    }
+
+    /// Release a direct, unpartitioned socket for `url` back to the pool.
+    ///
+    /// Sockets obtained through a proxy or with a [`NetworkIsolationKey`]
+    /// must be released with [`Self::release_socket_to_group`].
     pub fn release_socket(&self, url: &Url, socket: BoxedSocket, is_h2: bool) {
-        let Some(group_id) = GroupId::from_url(url) else {
-            return;
-        };
+        if let Some(group_id) = GroupId::from_url(url) {
+            self.release_socket_to_group(&group_id, socket, is_h2);
+        }
+    }
 
+    /// Release a socket back to the group it was taken from.
+    ///
+    /// A socket past the [`ConnectionLifetime`] maximum age or requests, or
+    /// connected before its group was [flushed](Self::flush_host), is
+    /// closed instead.
+    pub fn release_socket_to_group(&self, group_id: &GroupId, socket: BoxedSocket, is_h2: bool) {
+        let info = socket.connection_info();
+        let flushed = self
+            .groups
+            .get(group_id)
+            .and_then(|group| group.flushed_at)
+            .is_some_and(|flushed_at| info.connected_at <= flushed_at);
+        if flushed
+            || self.lifetime.is_spent(info.requests)
+            || self
+                .lifetime
+                .is_expired(info.connected_at, std::time::Instant::now())
+        {
+            tracing::trace!(
+                host = group_id.host(),
+                requests = info.requests,
+                "closing retired socket"
+            );
+            self.discard_socket_from_group(group_id);
+            return;
+        }
         let pending_request = {
             let mut group = self
                 .groups
@@ -312,7 +810,19 @@ impl ClientSocketPool {
             group.pop_highest_priority_request()
         };
 
+        let pending_request = match pending_request {
+            Some(request) if request.reuse != ConnectionReuse::Allowed => {
+                // The request wants a new connection: close this one to
+                // make room for it
+                drop(socket);
+                self.connect_for_pending(request);
+                return;
+            }
+            request => request,
+        };
+
         if let Some(request) = pending_request {
+            self.record_pool_wait(request.created_at.elapsed());
             // Hand socket to waiting request
             let mut group = self
                 .groups
@@ -324,12 +834,21 @@ impl ClientSocketPool {
 
             let _ = request.sender.send(Ok(PoolResult {
                 socket,
+                group_id: request.group_id,
                 is_h2,
                 is_reused: true,
             }));
+        } else if self.closed.load(Ordering::Acquire) {
+            tracing::trace!(
+                host = group_id.host(),
+                "closing socket released after close"
+            );
         } else {
             // Return to idle pool with timestamp
-            let mut group = self.groups.entry(group_id).or_insert_with(Group::new);
+            let mut group = self
+                .groups
+                .entry(group_id.clone())
+                .or_insert_with(Group::new);
             group.idle_sockets.push_back(IdleSocket {
                 socket,
                 is_h2,
@@ -339,12 +858,16 @@ impl ClientSocketPool {
         }
     }
 
-    /// Discard a socket without returning it to the pool.
+    /// Discard a direct, unpartitioned socket for `url` without returning
+    /// it to the pool.
     pub fn discard_socket(&self, url: &Url) {
-        let Some(group_id) = GroupId::from_url(url) else {
-            return;
-        };
+        if let Some(group_id) = GroupId::from_url(url) {
+            self.discard_socket_from_group(&group_id);
+        }
+    }
 
+    /// Discard a socket of `group_id` without returning it to the pool.
+    pub fn discard_socket_from_group(&self, group_id: &GroupId) {
         // Decrement count and process any waiting requests
         let pending = {
             let mut group = self
@@ -357,30 +880,43 @@ impl ClientSocketPool {
         };
 
         if let Some(request) = pending {
-            // Start a new connection for the waiting request
-            let pool = self.clone();
-            tokio::spawn(async move {
-                let result = pool
-                    .try_get_socket_immediate(
-                        &GroupId::from_url(&request.url).unwrap(),
-                        &request.url,
-                        request.proxy.as_ref(),
-                    )
-                    .await;
-
-                match result {
-                    Ok(Some(socket_result)) => {
-                        let _ = request.sender.send(Ok(socket_result));
-                    }
-                    Ok(None) => {
-                        // Still at limit, re-queue (simplified: just fail for now)
-                        let _ = request.sender.send(Err(NetError::PreconnectMaxSocketLimit));
-                    }
-                    Err(e) => {
-                        let _ = request.sender.send(Err(e));
-                    }
+            self.connect_for_pending(request);
+        }
+    }
+
+    /// Start a new connection for a waiting request, in a slot just freed.
+    fn connect_for_pending(&self, request: PendingRequest) {
+        self.record_pool_wait(request.created_at.elapsed());
+        let pool = self.clone();
+        tokio::spawn(async move {
+            let result = pool
+                .try_get_socket_immediate(
+                    &request.group_id,
+                    &request.url,
+                    request.proxy.as_ref(),
+                    request.alpn,
+                    request.reuse,
+                )
+                .await;
+
+            match result {
+                Ok(Some(socket_result)) => {
+                    let _ = request.sender.send(Ok(socket_result));
                 }
-            });
+                Ok(None) => {
+                    // Still at limit, re-queue (simplified: just fail for now)
+                    let _ = request.sender.send(Err(NetError::PreconnectMaxSocketLimit));
+                }
+                Err(e) => {
+                    let _ = request.sender.send(Err(e));
+                }
+            }
+        });
+    }
+
+    fn record_pool_wait(&self, wait: std::time::Duration) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_pool_wait(wait);
         }
     }
 
@@ -404,12 +940,10 @@ impl ClientSocketPool {
     /// Clean up idle sockets based on timeout.
     /// - Used sockets: 5 minute timeout (Chromium default)
     /// - Unused sockets: 10 second timeout (Chromium unused_idle_socket_timeout)
+    /// - Sockets past the [`ConnectionLifetime`] maximum age
+    ///
+    /// [`ConnectionLifetime`] can override both timeouts.
     pub fn cleanup_idle_sockets(&self) {
-        use std::time::Duration;
-
-        const USED_IDLE_TIMEOUT: Duration = Duration::from_secs(300); // 5 minutes
-        const UNUSED_IDLE_TIMEOUT: Duration = Duration::from_secs(10); // 10 seconds
-
         let now = std::time::Instant::now();
         let mut groups_to_remove = Vec::new();
 
@@ -418,15 +952,14 @@ impl ClientSocketPool {
 
             // Remove expired idle sockets
             group.idle_sockets.retain(|idle_socket| {
-                let elapsed = now.duration_since(idle_socket.start_time);
-                let timeout = if idle_socket.was_used {
-                    USED_IDLE_TIMEOUT
-                } else {
-                    UNUSED_IDLE_TIMEOUT
-                };
-
                 // Keep socket if not expired and still connected
-                elapsed < timeout && idle_socket.socket.is_connected()
+                !self
+                    .lifetime
+                    .is_idle_expired(idle_socket.start_time, idle_socket.was_used, now)
+                    && idle_socket.socket.is_connected()
+                    && !self
+                        .lifetime
+                        .is_expired(idle_socket.socket.connection_info().connected_at, now)
             });
 
             // Track empty groups for potential cleanup
@@ -444,9 +977,77 @@ impl ClientSocketPool {
         }
     }
 
-    /// Start a background task to periodically clean up idle sockets.
-    /// Should be called once during initialization.
-    pub fn start_cleanup_task(self: &std::sync::Arc<Self>) {
+    /// Close all idle sockets and keep no more: sockets released later are
+    /// closed, or handed to a request still waiting for one. Sockets in use
+    /// are left alone. Returns how many idle sockets were closed.
+    pub fn close(&self) -> usize {
+        self.closed.store(true, Ordering::Release);
+        self.proxy_sessions.clear();
+        let mut closed = 0;
+        for mut group in self.groups.iter_mut() {
+            closed += group.idle_sockets.len();
+            group.idle_sockets.clear();
+        }
+        self.groups
+            .retain(|_, g| g.active_count > 0 || !g.pending_requests.is_empty());
+        closed
+    }
+
+    /// Close the idle sockets of every group for `host`, whatever its port,
+    /// proxy or partition, and the ones in use once released, e.g. after
+    /// rotating credentials or when the host misbehaves. The resolver
+    /// forgets its cached answer for `host`, so new connections look it up
+    /// again. Unlike [`close`](Self::close), the pool stays open. Returns
+    /// how many idle sockets were closed.
+    ///
+    /// Chromium mapping: `ClientSocketPool::CloseIdleSocketsInGroup` and
+    /// `FlushWithError` on the matching groups
+    pub fn flush_host(&self, host: &str) -> usize {
+        let name = crate::dns::Name::new(host);
+        let flushed = self.flush_groups(|group_id| host_key(group_id.host()) == name.as_str());
+        self.resolver.forget(&name);
+        tracing::debug!(target: "chromenet::socket", host = name.as_str(), closed = flushed, "flushed host");
+        flushed
+    }
+
+    /// [`flush_host`](Self::flush_host) for every host: close all idle
+    /// sockets, and the ones in use once released, and drop every cached
+    /// DNS answer and proxy tunnel. Returns how many idle sockets were
+    /// closed.
+    pub fn flush_all(&self) -> usize {
+        self.proxy_sessions.clear();
+        let flushed = self.flush_groups(|_| true);
+        self.resolver.forget_all();
+        tracing::debug!(target: "chromenet::socket", closed = flushed, "flushed all hosts");
+        flushed
+    }
+
+    /// Close the idle sockets of the groups `matches` picks and mark the
+    /// sockets in use for closing.
+    fn flush_groups(&self, matches: impl Fn(&GroupId) -> bool) -> usize {
+        let now = std::time::Instant::now();
+        let mut closed = 0;
+        for mut entry in self.groups.iter_mut() {
+            if !matches(entry.key()) {
+                continue;
+            }
+            let group = entry.value_mut();
+            closed += group.idle_sockets.len();
+            group.idle_sockets.clear();
+            group.flushed_at = Some(now);
+        }
+        closed
+    }
+
+    /// Whether [`close`](Self::close) was called.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Start a background task to periodically clean up idle sockets,
+    /// until `cancel` is cancelled. Should be called once during
+    /// initialization.
+    pub fn start_cleanup_task(self: &std::sync::Arc<Self>, cancel: CancellationToken) {
         use std::time::Duration;
 
         const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
@@ -454,8 +1055,10 @@ impl ClientSocketPool {
         let pool = std::sync::Arc::clone(self);
         tokio::spawn(async move {
             loop {
-                tokio::time::sleep(CLEANUP_INTERVAL).await;
-                pool.cleanup_idle_sockets();
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(CLEANUP_INTERVAL) => pool.cleanup_idle_sockets(),
+                }
             }
         });
     }
@@ -1,6 +1,7 @@
 use super::matcher::ProxyMatcher;
+use crate::base::secret::{SecretString, REDACTED};
+use crate::socket::tls::TlsOptions;
 use url::Url;
-use zeroize::Zeroizing;
 
 /// Proxy protocol type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -14,16 +15,20 @@ pub enum ProxyType {
 }
 
 /// Proxy configuration with bypass rules.
-#[derive(Debug, Clone)]
+///
+/// Its `Debug` output redacts the password, also one in the URL.
+#[derive(Clone)]
 pub struct ProxySettings {
     /// Proxy URL (e.g., `http://proxy.com:8080`)
     pub url: Url,
     /// Proxy username for authentication
     pub username: Option<String>,
-    /// Proxy password (zeroized on drop)
-    pub password: Option<Zeroizing<String>>,
+    /// Proxy password (redacted and zeroized on drop)
+    pub password: Option<SecretString>,
     /// NO_PROXY bypass matcher
     bypass: ProxyMatcher,
+    /// TLS profile for the handshake with an HTTPS proxy
+    tls_options: Option<TlsOptions>,
 }
 
 impl ProxySettings {
@@ -35,6 +40,7 @@ impl ProxySettings {
             username: None,
             password: None,
             bypass: ProxyMatcher::default(),
+            tls_options: None,
         })
     }
 
@@ -56,7 +62,7 @@ impl ProxySettings {
     /// Add authentication credentials.
     pub fn with_auth(mut self, user: &str, pass: &str) -> Self {
         self.username = Some(user.to_string());
-        self.password = Some(Zeroizing::new(pass.to_string()));
+        self.password = Some(SecretString::new(pass));
         self
     }
 
@@ -66,6 +72,21 @@ impl ProxySettings {
         self
     }
 
+    /// Use `options` for the TLS handshake with an HTTPS proxy, e.g. to
+    /// offer post-quantum groups or other cipher suites to the proxy than
+    /// to the target. Without them, the proxy gets the client's TLS
+    /// options, from its emulation profile if it has one.
+    pub fn with_tls_options(mut self, options: TlsOptions) -> Self {
+        self.tls_options = Some(options);
+        self
+    }
+
+    /// TLS options for the handshake with an HTTPS proxy, if set apart
+    /// from the client's.
+    pub fn tls_options(&self) -> Option<&TlsOptions> {
+        self.tls_options.as_ref()
+    }
+
     /// Get proxy type from URL scheme.
     pub fn proxy_type(&self) -> ProxyType {
         match self.url.scheme() {
@@ -85,7 +106,7 @@ impl ProxySettings {
     pub fn get_auth_header(&self) -> Option<String> {
         if let (Some(u), Some(p)) = (&self.username, &self.password) {
             use base64::{engine::general_purpose, Engine as _};
-            let creds = format!("{}:{}", u, p.as_str());
+            let creds = format!("{}:{}", u, p.expose());
             let encoded = general_purpose::STANDARD.encode(creds);
             Some(format!("Basic {}", encoded))
         } else {
@@ -98,7 +119,7 @@ impl ProxySettings {
     /// Returns (username, password) tuple for SOCKS5 auth.
     pub fn get_socks5_auth(&self) -> Option<(&str, &str)> {
         match (&self.username, &self.password) {
-            (Some(u), Some(p)) => Some((u.as_str(), p.as_str())),
+            (Some(u), Some(p)) => Some((u.as_str(), p.expose())),
             _ => None,
         }
     }
@@ -125,13 +146,34 @@ impl ProxySettings {
     }
 }
 
+impl std::fmt::Debug for ProxySettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let url = match self.url.password() {
+            Some(password) => {
+                self.url
+                    .as_str()
+                    .replacen(&format!(":{password}@"), &format!(":{REDACTED}@"), 1)
+            }
+            None => self.url.to_string(),
+        };
+        f.debug_struct("ProxySettings")
+            .field("url", &url)
+            .field("username", &self.username)
+            .field("password", &self.password)
+            .field("bypass", &self.bypass)
+            .field("tls_options", &self.tls_options)
+            .finish()
+    }
+}
+
 /// Builder for ProxySettings.
 #[derive(Default)]
 pub struct ProxyBuilder {
     url: Option<Url>,
     username: Option<String>,
-    password: Option<String>,
+    password: Option<SecretString>,
     no_proxy: String,
+    tls_options: Option<TlsOptions>,
 }
 
 impl ProxyBuilder {
@@ -164,7 +206,7 @@ impl ProxyBuilder {
     /// Set authentication.
     pub fn auth(mut self, username: &str, password: &str) -> Self {
         self.username = Some(username.to_string());
-        self.password = Some(password.to_string());
+        self.password = Some(SecretString::new(password));
         self
     }
 
@@ -174,6 +216,13 @@ impl ProxyBuilder {
         self
     }
 
+    /// Set the TLS options for an HTTPS proxy, see
+    /// [`ProxySettings::with_tls_options`].
+    pub fn tls_options(mut self, options: TlsOptions) -> Self {
+        self.tls_options = Some(options);
+        self
+    }
+
     /// Build ProxySettings.
     pub fn build(self) -> Option<ProxySettings> {
         let url = self.url?;
@@ -186,8 +235,9 @@ impl ProxyBuilder {
         Some(ProxySettings {
             url,
             username: self.username,
-            password: self.password.map(Zeroizing::new),
+            password: self.password,
             bypass,
+            tls_options: self.tls_options,
         })
     }
 }
@@ -0,0 +1,61 @@
+//! Establishing the transport connection of a connect.
+//!
+//! Chromium mapping: `ClientSocketFactory::CreateTransportClientSocket`
+//!
+//! A [`ConnectJob`](crate::socket::connectjob::ConnectJob) resolves the
+//! host, then hands each address it tries to a [`SocketFactory`]. The
+//! default, [`TcpSocketFactory`], opens a Tokio `TcpStream`; another factory
+//! can use TCP Fast Open, a transparent proxy (`IP_TRANSPARENT`), a
+//! userspace network stack or an in-memory pipe, as long as the socket it
+//! returns is a [`StreamSocket`]. Happy Eyeballs, attempt timeouts,
+//! [connect hooks](crate::socket::hooks), proxy handshakes and TLS work the
+//! same on top of it. Install one with
+//! [`ClientBuilder::socket_factory`](crate::ClientBuilder::socket_factory):
+//!
+//! ```no_run
+//! use chromenet::socket::socketfactory::SocketFactory;
+//! use chromenet::socket::stream::StreamSocket;
+//! use futures::future::BoxFuture;
+//! use std::io;
+//! use std::net::SocketAddr;
+//! use tokio::net::TcpStream;
+//!
+//! struct NoDelay;
+//!
+//! impl SocketFactory for NoDelay {
+//!     fn connect(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn StreamSocket>>> {
+//!         Box::pin(async move {
+//!             let stream = TcpStream::connect(addr).await?;
+//!             stream.set_nodelay(true)?;
+//!             Ok(Box::new(stream) as Box<dyn StreamSocket>)
+//!         })
+//!     }
+//! }
+//!
+//! let client = chromenet::Client::builder().socket_factory(NoDelay).build();
+//! ```
+
+use crate::socket::stream::StreamSocket;
+use futures::future::BoxFuture;
+use std::io;
+use std::net::SocketAddr;
+use tokio::net::TcpStream;
+
+/// Opens the transport connections of connects.
+pub trait SocketFactory: Send + Sync {
+    /// Connect to `addr`. The caller applies the attempt timeout.
+    fn connect(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn StreamSocket>>>;
+}
+
+/// Plain TCP connections with Tokio's `TcpStream`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpSocketFactory;
+
+impl SocketFactory for TcpSocketFactory {
+    fn connect(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn StreamSocket>>> {
+        Box::pin(async move {
+            let stream = TcpStream::connect(addr).await?;
+            Ok(Box::new(stream) as Box<dyn StreamSocket>)
+        })
+    }
+}
@@ -6,8 +6,14 @@
 //! Based on Chromium's `StreamSocket` interface which provides polymorphism
 //! for `TcpClientSocket`, `SSLClientSocket`, and nested tunnel sockets.
 
+use crate::socket::nextproto::NextProto;
+use crate::socket::tls::SslInfo;
+use crate::socket::wire::WireMeter;
+use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Instant;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
 use tokio_boring::SslStream;
@@ -21,18 +27,83 @@ pub trait StreamSocket: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static {
     fn is_connected(&self) -> bool {
         true
     }
+
+    /// Application protocol negotiated on this socket.
+    fn negotiated_protocol(&self) -> NextProto {
+        NextProto::Unknown
+    }
+
+    /// TLS details of the outermost TLS layer, if any.
+    fn ssl_info(&self) -> Option<SslInfo> {
+        None
+    }
 }
 
 // Implement StreamSocket for TcpStream
 impl StreamSocket for TcpStream {}
 
+// Sockets from a `SocketFactory`
+impl StreamSocket for Box<dyn StreamSocket> {
+    fn is_connected(&self) -> bool {
+        (**self).is_connected()
+    }
+
+    fn negotiated_protocol(&self) -> NextProto {
+        (**self).negotiated_protocol()
+    }
+
+    fn ssl_info(&self) -> Option<SslInfo> {
+        (**self).ssl_info()
+    }
+}
+
 // Implement StreamSocket for SslStream<T> where T is any StreamSocket
-impl<S: StreamSocket> StreamSocket for SslStream<S> {}
+impl<S: StreamSocket> StreamSocket for SslStream<S> {
+    fn negotiated_protocol(&self) -> NextProto {
+        self.ssl()
+            .selected_alpn_protocol()
+            .map(NextProto::from_alpn)
+            .unwrap_or_default()
+    }
+
+    fn ssl_info(&self) -> Option<SslInfo> {
+        Some(SslInfo::from_ssl(self.ssl()))
+    }
+}
+
+/// When and where a connection was established, used to retire it (see
+/// [`crate::socket::lifetime`]).
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionInfo {
+    /// When the connection was established.
+    pub connected_at: Instant,
+    /// Address from the DNS answer the connection was made to, `None`
+    /// through a proxy.
+    pub remote_addr: Option<SocketAddr>,
+    /// When DNS last returned `remote_addr` for the host.
+    pub resolved_at: Instant,
+    /// Requests the connection carried so far.
+    pub requests: u32,
+}
+
+impl ConnectionInfo {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            connected_at: now,
+            remote_addr: None,
+            resolved_at: now,
+            requests: 0,
+        }
+    }
+}
 
 /// A wrapper type for boxed dynamic StreamSocket that is object-safe.
 /// This avoids conflicting trait implementations with tokio's blanket impls.
 pub struct BoxedSocket {
     inner: Pin<Box<dyn StreamSocket>>,
+    info: ConnectionInfo,
+    meter: Option<Arc<WireMeter>>,
 }
 
 impl BoxedSocket {
@@ -40,9 +111,37 @@ impl BoxedSocket {
     pub fn new<S: StreamSocket>(socket: S) -> Self {
         Self {
             inner: Box::pin(socket),
+            info: ConnectionInfo::new(),
+            meter: None,
         }
     }
 
+    /// Count the connection's bytes in `meter`, see [`crate::socket::wire`].
+    pub(crate) fn with_meter(mut self, meter: Option<Arc<WireMeter>>) -> Self {
+        self.meter = meter;
+        self
+    }
+
+    /// Byte counts of the connection, `None` without counting.
+    pub(crate) fn meter(&self) -> Option<&Arc<WireMeter>> {
+        self.meter.as_ref()
+    }
+
+    /// Record that the connection was made to `addr` from the DNS answer.
+    pub fn with_remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.info.remote_addr = Some(addr);
+        self
+    }
+
+    /// When and where the connection was established.
+    pub fn connection_info(&self) -> &ConnectionInfo {
+        &self.info
+    }
+
+    pub(crate) fn connection_info_mut(&mut self) -> &mut ConnectionInfo {
+        &mut self.info
+    }
+
     /// Get a pinned mutable reference to the inner socket.
     pub fn as_mut(&mut self) -> Pin<&mut dyn StreamSocket> {
         self.inner.as_mut()
@@ -55,6 +154,16 @@ impl BoxedSocket {
         // Full implementation would require downcast or non-object-safe trait
         true
     }
+
+    /// Application protocol negotiated on the inner socket.
+    pub fn negotiated_protocol(&self) -> NextProto {
+        self.inner.negotiated_protocol()
+    }
+
+    /// TLS details of the inner socket, `None` for plain TCP.
+    pub fn ssl_info(&self) -> Option<SslInfo> {
+        self.inner.ssl_info()
+    }
 }
 
 impl AsyncRead for BoxedSocket {
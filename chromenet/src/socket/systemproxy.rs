@@ -0,0 +1,380 @@
+//! Proxy settings of the operating system.
+//!
+//! Chromium mapping: `net/proxy_resolution/proxy_config_service_*.cc`.
+//!
+//! Clients use the system's proxies unless given one of their own or told
+//! not to with [`ClientBuilder::system_proxy`](crate::client::ClientBuilder::system_proxy).
+//! [`SystemProxyConfig::detect`] looks in order at:
+//! - the `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY`
+//!   environment variables, in upper or lower case
+//! - on Windows, the WinINet settings of the current user
+//!   (`Internet Settings` in the registry)
+//! - on macOS, the SystemConfiguration proxies, as printed by
+//!   `scutil --proxy`
+//!
+//! Only fixed proxies are picked up: PAC scripts and WPAD are not run, and
+//! a configuration that only has them counts as no proxy.
+
+use crate::socket::proxy::ProxySettings;
+use url::Url;
+
+/// Proxies for plain and secure requests, with the bypass list they were
+/// configured with.
+#[derive(Debug, Clone, Default)]
+pub struct SystemProxyConfig {
+    http: Option<ProxySettings>,
+    https: Option<ProxySettings>,
+}
+
+impl SystemProxyConfig {
+    /// The system's proxy settings: those of the environment if it sets a
+    /// proxy, else those of the OS. `None` if neither has one.
+    pub fn detect() -> Option<Self> {
+        Self::from_env().or_else(Self::from_os)
+    }
+
+    /// Proxies from environment variables.
+    ///
+    /// `HTTP_PROXY` is used for `http` URLs and `HTTPS_PROXY` for `https`
+    /// ones, both falling back to `ALL_PROXY`. `NO_PROXY` lists the hosts
+    /// to reach directly.
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(|name| {
+            std::env::var(name)
+                .or_else(|_| std::env::var(name.to_lowercase()))
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        })
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let all = var("ALL_PROXY");
+        let no_proxy = var("NO_PROXY").unwrap_or_default();
+        let proxy = |value: Option<String>| {
+            value
+                .or_else(|| all.clone())
+                .and_then(|url| parse_proxy(&url, "http"))
+                .map(|settings| settings.with_bypass(&no_proxy))
+        };
+        Self {
+            http: proxy(var("HTTP_PROXY")),
+            https: proxy(var("HTTPS_PROXY")),
+        }
+        .non_empty()
+    }
+
+    /// Proxies from the OS settings, where there are any.
+    pub fn from_os() -> Option<Self> {
+        #[cfg(target_os = "windows")]
+        {
+            wininet::detect()
+        }
+        #[cfg(target_os = "macos")]
+        {
+            scutil::detect()
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            None
+        }
+    }
+
+    /// The proxy for `http` URLs.
+    pub fn http(&self) -> Option<&ProxySettings> {
+        self.http.as_ref()
+    }
+
+    /// The proxy for `https` URLs.
+    pub fn https(&self) -> Option<&ProxySettings> {
+        self.https.as_ref()
+    }
+
+    /// The proxy to reach `url` through, `None` to connect directly.
+    ///
+    /// WebSocket URLs use the proxy of their HTTP scheme.
+    pub fn proxy_for(&self, url: &Url) -> Option<&ProxySettings> {
+        let proxy = match url.scheme() {
+            "https" | "wss" => self.https.as_ref(),
+            "http" | "ws" => self.http.as_ref(),
+            _ => None,
+        }?;
+        (!proxy.should_bypass(url)).then_some(proxy)
+    }
+
+    fn non_empty(self) -> Option<Self> {
+        (self.http.is_some() || self.https.is_some()).then_some(self)
+    }
+}
+
+/// Parse a proxy as configured by users, where the scheme may be left out.
+fn parse_proxy(value: &str, default_scheme: &str) -> Option<ProxySettings> {
+    let value = value.trim();
+    if value.contains("://") {
+        ProxySettings::new(value)
+    } else {
+        ProxySettings::new(&format!("{default_scheme}://{value}"))
+    }
+}
+
+/// Turn a WinINet or SystemConfiguration bypass list into `NO_PROXY` form.
+///
+/// `*.example.com` becomes `.example.com`. `<local>`, meaning hosts
+/// without a dot, is approximated by the loopback names. Other wildcard
+/// patterns cannot be expressed and are dropped.
+#[cfg(any(target_os = "windows", target_os = "macos", test))]
+fn bypass_list<'a>(entries: impl Iterator<Item = &'a str>) -> String {
+    let mut rules = Vec::new();
+    for entry in entries.map(str::trim).filter(|e| !e.is_empty()) {
+        if entry.eq_ignore_ascii_case("<local>") {
+            rules.extend(["localhost", "127.0.0.1", "::1"].map(String::from));
+        } else if let Some(domain) = entry.strip_prefix("*.") {
+            if !domain.contains('*') {
+                rules.push(format!(".{domain}"));
+            }
+        } else if !entry.contains('*') {
+            rules.push(entry.to_string());
+        }
+    }
+    rules.join(",")
+}
+
+/// WinINet settings, read from
+/// `HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings`.
+#[cfg(any(target_os = "windows", test))]
+mod wininet {
+    use super::{bypass_list, parse_proxy, SystemProxyConfig};
+
+    #[cfg(target_os = "windows")]
+    const KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings";
+
+    #[cfg(target_os = "windows")]
+    pub(super) fn detect() -> Option<SystemProxyConfig> {
+        let output = std::process::Command::new("reg")
+            .args(["query", KEY])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parse the output of `reg query`, one `name  type  data` line per
+    /// value.
+    pub(super) fn parse(output: &str) -> Option<SystemProxyConfig> {
+        let value = |name: &str| {
+            output.lines().find_map(|line| {
+                let mut fields = line.split_whitespace();
+                if !fields.next()?.eq_ignore_ascii_case(name) {
+                    return None;
+                }
+                fields.next()?;
+                Some(fields.collect::<Vec<_>>().join(" "))
+            })
+        };
+
+        let enabled = value("ProxyEnable")
+            .and_then(|v| u32::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+            .is_some_and(|v| v != 0);
+        if !enabled {
+            return None;
+        }
+        let server = value("ProxyServer")?;
+        let bypass = bypass_list(value("ProxyOverride").unwrap_or_default().split(';'));
+
+        // Either one `host:port` for all schemes, or `scheme=host:port`
+        // entries separated by semicolons
+        let (mut http, mut https, mut socks) = (None, None, None);
+        for entry in server.split(';').map(str::trim) {
+            match entry.split_once('=') {
+                Some((scheme, addr)) if scheme.eq_ignore_ascii_case("http") => {
+                    http = parse_proxy(addr, "http")
+                }
+                Some((scheme, addr)) if scheme.eq_ignore_ascii_case("https") => {
+                    https = parse_proxy(addr, "http")
+                }
+                Some((scheme, addr)) if scheme.eq_ignore_ascii_case("socks") => {
+                    socks = parse_proxy(addr, "socks5")
+                }
+                Some(_) => {}
+                None if !entry.is_empty() => {
+                    http = parse_proxy(entry, "http");
+                    https = http.clone();
+                }
+                None => {}
+            }
+        }
+        SystemProxyConfig {
+            http: http
+                .or_else(|| socks.clone())
+                .map(|p| p.with_bypass(&bypass)),
+            https: https.or(socks).map(|p| p.with_bypass(&bypass)),
+        }
+        .non_empty()
+    }
+}
+
+/// SystemConfiguration settings, as printed by `scutil --proxy`.
+#[cfg(any(target_os = "macos", test))]
+mod scutil {
+    use super::{bypass_list, parse_proxy, SystemProxyConfig};
+
+    #[cfg(target_os = "macos")]
+    pub(super) fn detect() -> Option<SystemProxyConfig> {
+        let output = std::process::Command::new("scutil")
+            .arg("--proxy")
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parse the `key : value` dictionary `scutil --proxy` prints, with
+    /// `ExceptionsList` as a nested array.
+    pub(super) fn parse(output: &str) -> Option<SystemProxyConfig> {
+        let mut values = Vec::new();
+        let mut exceptions = Vec::new();
+        let mut in_exceptions = false;
+        for line in output.lines().map(str::trim) {
+            if in_exceptions {
+                match line.split_once(" : ") {
+                    Some((_, host)) => exceptions.push(host.trim()),
+                    None => in_exceptions = false,
+                }
+            } else if line.starts_with("ExceptionsList") {
+                in_exceptions = true;
+            } else if let Some((key, value)) = line.split_once(" : ") {
+                values.push((key.trim(), value.trim()));
+            }
+        }
+        let value = |key: &str| values.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+        let bypass = bypass_list(exceptions.into_iter());
+
+        let proxy = |prefix: &str, scheme: &str| {
+            if value(&format!("{prefix}Enable")) != Some("1") {
+                return None;
+            }
+            let host = value(&format!("{prefix}Proxy"))?;
+            let addr = match value(&format!("{prefix}Port")) {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_string(),
+            };
+            parse_proxy(&addr, scheme).map(|p| p.with_bypass(&bypass))
+        };
+        let socks = proxy("SOCKS", "socks5");
+        SystemProxyConfig {
+            http: proxy("HTTP", "http").or_else(|| socks.clone()),
+            https: proxy("HTTPS", "http").or(socks),
+        }
+        .non_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn from_vars(vars: &[(&str, &str)]) -> Option<SystemProxyConfig> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        SystemProxyConfig::from_vars(|name| vars.get(name).map(|v| v.to_string()))
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_env_proxies_by_scheme() {
+        let config = from_vars(&[
+            ("HTTP_PROXY", "http://plain.proxy:3128"),
+            ("HTTPS_PROXY", "secure.proxy:8080"),
+        ])
+        .unwrap();
+
+        let http = config.proxy_for(&url("http://example.com/")).unwrap();
+        assert_eq!(http.host_port(), Some(("plain.proxy", 3128)));
+        let https = config.proxy_for(&url("wss://example.com/")).unwrap();
+        assert_eq!(https.host_port(), Some(("secure.proxy", 8080)));
+    }
+
+    #[test]
+    fn test_env_all_proxy_and_no_proxy() {
+        let config = from_vars(&[
+            ("ALL_PROXY", "socks5://socks.proxy:1080"),
+            ("NO_PROXY", "localhost,.internal"),
+        ])
+        .unwrap();
+
+        assert!(config.https().unwrap().is_socks());
+        assert!(config.proxy_for(&url("http://example.com/")).is_some());
+        assert!(config.proxy_for(&url("http://localhost:8080/")).is_none());
+        assert!(config.proxy_for(&url("https://api.internal/")).is_none());
+    }
+
+    #[test]
+    fn test_env_without_proxy() {
+        assert!(from_vars(&[]).is_none());
+        assert!(from_vars(&[("NO_PROXY", "*")]).is_none());
+    }
+
+    #[test]
+    fn test_windows_single_server() {
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings\r\n    \
+                      ProxyEnable    REG_DWORD    0x1\r\n    \
+                      ProxyServer    REG_SZ    proxy.corp:8080\r\n    \
+                      ProxyOverride    REG_SZ    *.corp.example;<local>\r\n";
+        let config = wininet::parse(output).unwrap();
+
+        let proxy = config.proxy_for(&url("https://example.com/")).unwrap();
+        assert_eq!(proxy.host_port(), Some(("proxy.corp", 8080)));
+        assert!(config.proxy_for(&url("http://example.com/")).is_some());
+        assert!(config
+            .proxy_for(&url("https://wiki.corp.example/"))
+            .is_none());
+        assert!(config.proxy_for(&url("http://localhost/")).is_none());
+    }
+
+    #[test]
+    fn test_windows_per_scheme_and_disabled() {
+        let output = "    ProxyEnable    REG_DWORD    0x1\n    \
+                      ProxyServer    REG_SZ    http=web:80;https=secure:443\n";
+        let config = wininet::parse(output).unwrap();
+        assert_eq!(config.http().unwrap().host_port(), Some(("web", 80)));
+        assert_eq!(config.https().unwrap().host_port(), Some(("secure", 443)));
+
+        let disabled = output.replace("0x1", "0x0");
+        assert!(wininet::parse(&disabled).is_none());
+    }
+
+    #[test]
+    fn test_macos_scutil() {
+        let output = "<dictionary> {\n  \
+                      ExceptionsList : <array> {\n    \
+                      0 : *.local\n    \
+                      1 : 169.254/16\n  \
+                      }\n  \
+                      FTPPassive : 1\n  \
+                      HTTPEnable : 1\n  \
+                      HTTPPort : 3128\n  \
+                      HTTPProxy : proxy.lan\n  \
+                      HTTPSEnable : 0\n  \
+                      HTTPSPort : 3129\n  \
+                      HTTPSProxy : secure.lan\n\
+                      }\n";
+        let config = scutil::parse(output).unwrap();
+
+        let proxy = config.proxy_for(&url("http://example.com/")).unwrap();
+        assert_eq!(proxy.host_port(), Some(("proxy.lan", 3128)));
+        assert!(config.https().is_none());
+        assert!(config.proxy_for(&url("http://printer.local/")).is_none());
+    }
+
+    #[test]
+    fn test_macos_without_proxy() {
+        let output = "<dictionary> {\n  HTTPEnable : 0\n  ProxyAutoConfigEnable : 1\n}\n";
+        assert!(scutil::parse(output).is_none());
+    }
+}
@@ -0,0 +1,172 @@
+//! Application-Layer Protocol Settings (ALPS).
+//!
+//! Chromium mapping: `SSLClientSocketImpl::Init` adding
+//! `HttpNetworkSession::GetApplicationSettings` to the connection
+//!
+//! With ALPS, client and server exchange settings for the protocol ALPN
+//! selects during the handshake instead of after it. The ClientHello lists
+//! the protocols the client has settings for, and the settings themselves
+//! follow encrypted once a protocol was selected. Chrome announces its
+//! HTTP/2 SETTINGS this way; the extension is part of its fingerprint, and
+//! servers that check it see a client without it, or with other settings,
+//! as not being Chrome.
+//!
+//! For HTTP/2 the settings are the payload of a SETTINGS frame: a 16-bit
+//! identifier and a 32-bit value per setting, in ascending identifier order
+//! like Chrome's `spdy::SettingsMap`. Other protocols announce empty
+//! settings.
+//!
+//! `TlsOptions::alps_use_new_codepoint` (extension 17613 instead of 17513,
+//! Chrome 131+) needs a BoringSSL with `SSL_set_alps_use_new_codepoint`,
+//! which the bundled one lacks; the old codepoint is always sent.
+
+use super::options::AlpsProtocol;
+use crate::base::neterror::NetError;
+use boring::ssl::SslRef;
+use foreign_types::ForeignTypeRef;
+
+/// SETTINGS_HEADER_TABLE_SIZE
+pub const HEADER_TABLE_SIZE: u16 = 0x1;
+/// SETTINGS_ENABLE_PUSH
+pub const ENABLE_PUSH: u16 = 0x2;
+/// SETTINGS_MAX_CONCURRENT_STREAMS
+pub const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+/// SETTINGS_INITIAL_WINDOW_SIZE
+pub const INITIAL_WINDOW_SIZE: u16 = 0x4;
+/// SETTINGS_MAX_FRAME_SIZE
+pub const MAX_FRAME_SIZE: u16 = 0x5;
+/// SETTINGS_MAX_HEADER_LIST_SIZE
+pub const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+/// HTTP/2 settings Chrome announces, from `HttpNetworkSessionParams`.
+pub const CHROME_H2_SETTINGS: [(u16, u32); 4] = [
+    (HEADER_TABLE_SIZE, 65536),
+    (ENABLE_PUSH, 0),
+    (INITIAL_WINDOW_SIZE, 6291456),
+    (MAX_HEADER_LIST_SIZE, 262144),
+];
+
+/// Encode HTTP/2 settings for ALPS.
+///
+/// Settings are sorted by identifier; of settings given more than once,
+/// the last value counts.
+pub fn encode_h2_settings(settings: impl IntoIterator<Item = (u16, u32)>) -> Vec<u8> {
+    let mut sorted: Vec<(u16, u32)> = Vec::new();
+    for (id, value) in settings {
+        match sorted.binary_search_by_key(&id, |&(id, _)| id) {
+            Ok(i) => sorted[i].1 = value,
+            Err(i) => sorted.insert(i, (id, value)),
+        }
+    }
+    let mut buf = Vec::with_capacity(sorted.len() * 6);
+    for (id, value) in sorted {
+        buf.extend_from_slice(&id.to_be_bytes());
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+    buf
+}
+
+/// Decode HTTP/2 settings received with ALPS, `None` if the payload is
+/// not a whole number of settings.
+pub fn decode_h2_settings(payload: &[u8]) -> Option<Vec<(u16, u32)>> {
+    if payload.len() % 6 != 0 {
+        return None;
+    }
+    Some(
+        payload
+            .chunks_exact(6)
+            .map(|s| {
+                (
+                    u16::from_be_bytes([s[0], s[1]]),
+                    u32::from_be_bytes([s[2], s[3], s[4], s[5]]),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Announce `settings` for `protocol` in the handshake of `ssl`.
+///
+/// Only protocols also offered in ALPN are announced.
+pub(crate) fn add_application_settings(
+    ssl: &mut SslRef,
+    protocol: AlpsProtocol,
+    settings: &[u8],
+) -> Result<(), NetError> {
+    // boring has no safe wrapper for this call
+    // SAFETY: `ssl` is a live SSL object not yet handshaking, and BoringSSL
+    // copies both buffers before returning.
+    let ok = unsafe {
+        boring_sys::SSL_add_application_settings(
+            ssl.as_ptr(),
+            protocol.0.as_ptr(),
+            protocol.0.len(),
+            settings.as_ptr(),
+            settings.len(),
+        )
+    };
+    if ok == 1 {
+        Ok(())
+    } else {
+        Err(NetError::SslProtocolError)
+    }
+}
+
+/// The settings the server sent for the negotiated protocol, `None` if
+/// ALPS was not negotiated.
+pub(crate) fn peer_application_settings(ssl: &SslRef) -> Option<Vec<u8>> {
+    // SAFETY: `ssl` is a live SSL object; the buffer it owns is copied
+    // before `ssl` can be modified.
+    unsafe {
+        if boring_sys::SSL_has_application_settings(ssl.as_ptr()) != 1 {
+            return None;
+        }
+        let mut data = std::ptr::null();
+        let mut len = 0;
+        boring_sys::SSL_get0_peer_application_settings(ssl.as_ptr(), &mut data, &mut len);
+        if data.is_null() || len == 0 {
+            return Some(Vec::new());
+        }
+        Some(std::slice::from_raw_parts(data, len).to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ALPS settings for `h2` in a Chrome 124 handshake, decrypted from a
+    /// capture.
+    const CHROME_124_CAPTURE: [u8; 24] = [
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x00, // HEADER_TABLE_SIZE 65536
+        0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // ENABLE_PUSH 0
+        0x00, 0x04, 0x00, 0x60, 0x00, 0x00, // INITIAL_WINDOW_SIZE 6291456
+        0x00, 0x06, 0x00, 0x04, 0x00, 0x00, // MAX_HEADER_LIST_SIZE 262144
+    ];
+
+    #[test]
+    fn test_chrome_settings_match_capture() {
+        assert_eq!(encode_h2_settings(CHROME_H2_SETTINGS), CHROME_124_CAPTURE);
+    }
+
+    #[test]
+    fn test_settings_sorted_and_deduplicated() {
+        let encoded = encode_h2_settings([
+            (MAX_HEADER_LIST_SIZE, 1),
+            (HEADER_TABLE_SIZE, 2),
+            (MAX_HEADER_LIST_SIZE, 3),
+        ]);
+        assert_eq!(encoded, [0, 1, 0, 0, 0, 2, 0, 6, 0, 0, 0, 3]);
+        assert!(encode_h2_settings([]).is_empty());
+    }
+
+    #[test]
+    fn test_decode_settings() {
+        assert_eq!(
+            decode_h2_settings(&CHROME_124_CAPTURE).unwrap(),
+            CHROME_H2_SETTINGS
+        );
+        assert_eq!(decode_h2_settings(&[]), Some(Vec::new()));
+        assert_eq!(decode_h2_settings(&CHROME_124_CAPTURE[..5]), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Certificate compression (RFC 8879).
+//!
+//! Chromium mapping: `ssl_client_socket_impl.cc` registering
+//! `DecompressBrotliCert` with `SSL_CTX_add_cert_compression_alg`
+//!
+//! Advertising an algorithm in the `compress_certificate` extension is
+//! part of a browser's ClientHello fingerprint, and lets servers send a
+//! much smaller Certificate message. Clients only ever decompress, so the
+//! algorithms here register no compression callback.
+//!
+//! BoringSSL hands the callback a buffer of the uncompressed length the
+//! server declared (at most 2^24 bytes), so a certificate that inflates
+//! beyond it fails the handshake instead of growing without bound.
+
+use boring::ssl::{CertificateCompressionAlgorithm, CertificateCompressor, SslConnectorBuilder};
+use std::io::{self, Write};
+
+/// Brotli (algorithm 2), the only one Chrome advertises.
+pub struct BrotliCertDecompressor;
+
+impl CertificateCompressor for BrotliCertDecompressor {
+    const ALGORITHM: CertificateCompressionAlgorithm = CertificateCompressionAlgorithm::BROTLI;
+    const CAN_COMPRESS: bool = false;
+    const CAN_DECOMPRESS: bool = true;
+
+    fn decompress<W>(&self, input: &[u8], output: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        brotli::BrotliDecompress(&mut io::Cursor::new(input), output)
+    }
+}
+
+/// Zlib (algorithm 1), advertised by Firefox and Safari.
+pub struct ZlibCertDecompressor;
+
+impl CertificateCompressor for ZlibCertDecompressor {
+    const ALGORITHM: CertificateCompressionAlgorithm = CertificateCompressionAlgorithm::ZLIB;
+    const CAN_COMPRESS: bool = false;
+    const CAN_DECOMPRESS: bool = true;
+
+    fn decompress<W>(&self, input: &[u8], output: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let mut decoder = flate2::write::ZlibDecoder::new(output);
+        decoder.write_all(input)?;
+        decoder.finish()?;
+        Ok(())
+    }
+}
+
+/// Advertise and accept `algorithms`, in order.
+pub(crate) fn register(
+    builder: &mut SslConnectorBuilder,
+    algorithms: &[CertificateCompressionAlgorithm],
+) -> Result<(), boring::error::ErrorStack> {
+    for &algorithm in algorithms {
+        if algorithm == CertificateCompressionAlgorithm::BROTLI {
+            builder.add_certificate_compression_algorithm(BrotliCertDecompressor)?;
+        } else if algorithm == CertificateCompressionAlgorithm::ZLIB {
+            builder.add_certificate_compression_algorithm(ZlibCertDecompressor)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in for a DER certificate chain: repetitive, like real ones.
+    fn chain() -> Vec<u8> {
+        (0..4096u32).flat_map(|i| (i % 251).to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn test_brotli_roundtrip() {
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+            writer.write_all(&chain()).unwrap();
+        }
+        assert!(compressed.len() < chain().len());
+
+        let mut output = Vec::new();
+        BrotliCertDecompressor
+            .decompress(&compressed, &mut output)
+            .unwrap();
+        assert_eq!(output, chain());
+    }
+
+    #[test]
+    fn test_zlib_roundtrip() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&chain()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut output = Vec::new();
+        ZlibCertDecompressor
+            .decompress(&compressed, &mut output)
+            .unwrap();
+        assert_eq!(output, chain());
+    }
+
+    #[test]
+    fn test_corrupt_input_fails() {
+        let mut output = Vec::new();
+        assert!(ZlibCertDecompressor
+            .decompress(b"not zlib", &mut output)
+            .is_err());
+        assert!(BrotliCertDecompressor
+            .decompress(&[0xff; 16], &mut output)
+            .is_err());
+    }
+
+    #[test]
+    fn test_output_bounded_by_declared_length() {
+        // BoringSSL's buffer for the declared length rejects extra bytes
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+            writer.write_all(&chain()).unwrap();
+        }
+        let mut buffer = [0u8; 100];
+        let mut output = &mut buffer[..];
+        assert!(BrotliCertDecompressor
+            .decompress(&compressed, &mut output)
+            .is_err());
+    }
+}
@@ -0,0 +1,387 @@
+//! The ClientHello a connection sent, and its fingerprints.
+//!
+//! Chromium mapping: none. Chrome does not look at its own hello; servers
+//! fingerprint it as JA3 or JA4, and so can a caller checking what an
+//! emulation profile puts on the wire.
+//!
+//! The raw socket of every connection keeps the first TLS record it sends
+//! and, once the handshake is done, the parsed hello is kept with the TLS
+//! session, available as [`SslInfo::client_hello`]. It is the hello as
+//! sent, after extension permutation and GREASE, so it can differ between
+//! connections of the same profile. TLS inside a tunnel, to an origin
+//! through an HTTPS proxy, is not captured.
+//!
+//! - JA3: `version,ciphers,extensions,groups,point formats` without GREASE,
+//!   usually published as its MD5
+//! - JA3N: JA3 with extensions sorted, so it survives permutation
+//! - JA4: `t13d1516h2_<ciphers>_<extensions>` with hashed, sorted lists,
+//!   so it survives extension permutation
+//!
+//! [`SslInfo::client_hello`]: super::info::SslInfo::client_hello
+
+use boring::ex_data::Index;
+use boring::ssl::{Ssl, SslRef};
+use std::sync::LazyLock;
+
+/// Where the hello is kept with the TLS session.
+static CLIENT_HELLO_INDEX: LazyLock<Index<Ssl, ClientHello>> =
+    LazyLock::new(|| Ssl::new_ex_index().expect("ex_data index"));
+
+/// The fields of a ClientHello fingerprints are made of.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientHello {
+    pub version: u16,
+    pub ciphers: Vec<u16>,
+    /// Extension types in the order sent.
+    pub extensions: Vec<u16>,
+    pub groups: Vec<u16>,
+    pub point_formats: Vec<u8>,
+    pub supported_versions: Vec<u16>,
+    pub signature_algorithms: Vec<u16>,
+    pub alpn: Vec<Vec<u8>>,
+}
+
+impl ClientHello {
+    /// Parse a TLS record holding a ClientHello.
+    pub fn parse(record: &[u8]) -> Option<Self> {
+        let mut record = Reader(record);
+        if record.u8()? != 0x16 {
+            return None;
+        }
+        record.skip(2)?; // Record version
+        let len = record.u16()?;
+        let mut handshake = Reader(record.bytes(len)?);
+        if handshake.u8()? != 0x01 {
+            return None;
+        }
+        let len = handshake.u24()?;
+        let mut hello = Reader(handshake.bytes(len)?);
+
+        let mut parsed = ClientHello {
+            version: hello.u16()? as u16,
+            ..Default::default()
+        };
+        hello.skip(32)?; // Random
+        let len = hello.u8()?;
+        hello.skip(len)?; // Session ID
+        let len = hello.u16()?;
+        parsed.ciphers = Reader(hello.bytes(len)?).u16s()?;
+        let len = hello.u8()?;
+        hello.skip(len)?; // Compression methods
+        if hello.0.is_empty() {
+            return Some(parsed);
+        }
+
+        let len = hello.u16()?;
+        let mut extensions = Reader(hello.bytes(len)?);
+        while !extensions.0.is_empty() {
+            let kind = extensions.u16()? as u16;
+            let len = extensions.u16()?;
+            let mut data = Reader(extensions.bytes(len)?);
+            parsed.extensions.push(kind);
+            match kind {
+                // supported_groups
+                10 => {
+                    let len = data.u16()?;
+                    parsed.groups = Reader(data.bytes(len)?).u16s()?;
+                }
+                // ec_point_formats
+                11 => {
+                    let len = data.u8()?;
+                    parsed.point_formats = data.bytes(len)?.to_vec();
+                }
+                // signature_algorithms
+                13 => {
+                    let len = data.u16()?;
+                    parsed.signature_algorithms = Reader(data.bytes(len)?).u16s()?;
+                }
+                // application_layer_protocol_negotiation
+                16 => {
+                    let len = data.u16()?;
+                    let mut list = Reader(data.bytes(len)?);
+                    while !list.0.is_empty() {
+                        let len = list.u8()?;
+                        parsed.alpn.push(list.bytes(len)?.to_vec());
+                    }
+                }
+                // supported_versions
+                43 => {
+                    let len = data.u8()?;
+                    parsed.supported_versions = Reader(data.bytes(len)?).u16s()?;
+                }
+                _ => {}
+            }
+        }
+        Some(parsed)
+    }
+
+    /// The JA3 string.
+    pub fn ja3(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.version,
+            join(&known(&self.ciphers), "-", u16::to_string),
+            join(&known(&self.extensions), "-", u16::to_string),
+            join(&known(&self.groups), "-", u16::to_string),
+            join(&self.point_formats, "-", u8::to_string),
+        )
+    }
+
+    /// JA3 with the extensions sorted, so it survives extension
+    /// permutation.
+    pub fn ja3n(&self) -> String {
+        let mut extensions = known(&self.extensions);
+        extensions.sort_unstable();
+        format!(
+            "{},{},{},{},{}",
+            self.version,
+            join(&known(&self.ciphers), "-", u16::to_string),
+            join(&extensions, "-", u16::to_string),
+            join(&known(&self.groups), "-", u16::to_string),
+            join(&self.point_formats, "-", u8::to_string),
+        )
+    }
+
+    /// MD5 of the JA3 string, as usually published.
+    pub fn ja3_hash(&self) -> String {
+        let digest = boring::hash::hash(boring::hash::MessageDigest::md5(), self.ja3().as_bytes())
+            .expect("MD5 is available");
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// The JA4 fingerprint, for TCP.
+    pub fn ja4(&self) -> String {
+        let version = known(&self.supported_versions)
+            .into_iter()
+            .max()
+            .unwrap_or(self.version);
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+        let sni = if self.extensions.contains(&0) {
+            'd'
+        } else {
+            'i'
+        };
+        let ciphers = known(&self.ciphers);
+        let extensions = known(&self.extensions);
+        let alpn = match self.alpn.first() {
+            Some(alpn) if !alpn.is_empty() => {
+                format!("{}{}", alpn[0] as char, alpn[alpn.len() - 1] as char)
+            }
+            _ => "00".to_string(),
+        };
+
+        let mut sorted_ciphers = ciphers.clone();
+        sorted_ciphers.sort_unstable();
+        // SNI and ALPN are already in the first part
+        let mut sorted_extensions: Vec<u16> = extensions
+            .iter()
+            .copied()
+            .filter(|&ext| ext != 0 && ext != 16)
+            .collect();
+        sorted_extensions.sort_unstable();
+        let mut extension_list = join(&sorted_extensions, ",", hex);
+        if !self.signature_algorithms.is_empty() {
+            extension_list.push('_');
+            extension_list.push_str(&join(&self.signature_algorithms, ",", hex));
+        }
+
+        format!(
+            "t{}{}{:02}{:02}{}_{}_{}",
+            version,
+            sni,
+            ciphers.len().min(99),
+            extensions.len().min(99),
+            alpn,
+            truncated_sha256(&join(&sorted_ciphers, ",", hex)),
+            truncated_sha256(&extension_list),
+        )
+    }
+}
+
+/// Keep the hello in `record`, the first TLS record the connection sent,
+/// with its TLS session.
+pub(crate) fn attach(ssl: &mut SslRef, record: &[u8]) {
+    if let Some(hello) = ClientHello::parse(record) {
+        ssl.set_ex_data(*CLIENT_HELLO_INDEX, hello);
+    }
+}
+
+/// The hello kept with `ssl`, if any.
+pub(crate) fn attached(ssl: &SslRef) -> Option<ClientHello> {
+    ssl.ex_data(*CLIENT_HELLO_INDEX).cloned()
+}
+
+/// GREASE values (RFC 8701): `0x?a?a` with both bytes equal.
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn known(values: &[u16]) -> Vec<u16> {
+    values.iter().copied().filter(|&v| !is_grease(v)).collect()
+}
+
+fn join<T>(values: &[T], separator: &str, format: impl Fn(&T) -> String) -> String {
+    values
+        .iter()
+        .map(format)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn hex(value: &u16) -> String {
+    format!("{:04x}", value)
+}
+
+/// The first 12 hex digits of the SHA-256 of `value`, zeros if it is empty.
+fn truncated_sha256(value: &str) -> String {
+    if value.is_empty() {
+        return "0".repeat(12);
+    }
+    boring::sha::sha256(value.as_bytes())[..6]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Big-endian reads from a byte slice, `None` past its end.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn skip(&mut self, n: usize) -> Option<()> {
+        self.bytes(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<usize> {
+        self.bytes(1).map(|b| b[0] as usize)
+    }
+
+    fn u16(&mut self) -> Option<usize> {
+        self.bytes(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        self.bytes(3)
+            .map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize)
+    }
+
+    /// The rest as a list of 16-bit values.
+    fn u16s(mut self) -> Option<Vec<u16>> {
+        let mut values = Vec::with_capacity(self.0.len() / 2);
+        while !self.0.is_empty() {
+            values.push(self.u16()? as u16);
+        }
+        Some(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A record with a ClientHello carrying `extensions`.
+    fn record(ciphers: &[u16], extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut hello = vec![0x03, 0x03];
+        hello.extend([0; 32]);
+        hello.push(0); // Session ID
+        hello.extend(((ciphers.len() * 2) as u16).to_be_bytes());
+        for cipher in ciphers {
+            hello.extend(cipher.to_be_bytes());
+        }
+        hello.extend([1, 0]); // Null compression
+        let mut ext = Vec::new();
+        for (kind, data) in extensions {
+            ext.extend(kind.to_be_bytes());
+            ext.extend((data.len() as u16).to_be_bytes());
+            ext.extend(data);
+        }
+        hello.extend((ext.len() as u16).to_be_bytes());
+        hello.extend(ext);
+
+        let mut handshake = vec![0x01];
+        handshake.extend(&(hello.len() as u32).to_be_bytes()[1..]);
+        handshake.extend(hello);
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend((handshake.len() as u16).to_be_bytes());
+        record.extend(handshake);
+        record
+    }
+
+    #[test]
+    fn test_ja3_skips_grease() {
+        let record = record(
+            &[0x2a2a, 0x1301, 0xc02b],
+            &[
+                (0x0a0a, vec![]),
+                (0, vec![]),
+                (10, vec![0, 6, 0x3a, 0x3a, 0, 29, 0, 23]),
+                (11, vec![1, 0]),
+            ],
+        );
+        let hello = ClientHello::parse(&record).unwrap();
+        assert_eq!(hello.ja3(), "771,4865-49195,0-10-11,29-23,0");
+        assert_eq!(hello.ja3_hash().len(), 32);
+    }
+
+    #[test]
+    fn test_ja4() {
+        let record = record(
+            &[0x1a1a, 0x1302, 0x1301],
+            &[
+                (0x2a2a, vec![]),
+                (43, vec![4, 0x3a, 0x3a, 0x03, 0x04]),
+                (
+                    16,
+                    vec![
+                        0, 12, 2, b'h', b'2', 8, b'h', b't', b't', b'p', b'/', b'1', b'.', b'1',
+                    ],
+                ),
+                (13, vec![0, 4, 0x04, 0x03, 0x08, 0x04]),
+                (0, vec![]),
+            ],
+        );
+        let hello = ClientHello::parse(&record).unwrap();
+        assert_eq!(hello.alpn, [b"h2".to_vec(), b"http/1.1".to_vec()]);
+        let ja4 = hello.ja4();
+        let (prefix, hashes) = ja4.split_at(10);
+        assert_eq!(prefix, "t13d0204h2");
+        assert_eq!(
+            &hashes[1..13],
+            truncated_sha256("1301,1302"),
+            "ciphers are sorted"
+        );
+        assert_eq!(&hashes[14..], truncated_sha256("000d,002b_0403,0804"));
+
+        // Extension order does not change JA4, unlike JA3
+        let permuted = ClientHello {
+            extensions: hello.extensions.iter().rev().copied().collect(),
+            ..hello.clone()
+        };
+        assert_eq!(permuted.ja4(), ja4);
+        assert_eq!(permuted.ja3n(), hello.ja3n());
+        assert_ne!(permuted.ja3(), hello.ja3());
+    }
+
+    #[test]
+    fn test_truncated_hello() {
+        let record = record(&[0x1301], &[]);
+        assert!(ClientHello::parse(&record[..record.len() - 1]).is_none());
+        assert!(ClientHello::parse(b"GET / HTTP/1.1").is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! Post-handshake TLS connection details.
+//!
+//! Mirrors Chromium's `net::SSLInfo`: what was negotiated on the connection a
+//! response arrived on, for callers that make security policy decisions.
+
+use crate::socket::nextproto::NextProto;
+use crate::socket::tls::alps::{decode_h2_settings, peer_application_settings};
+use crate::socket::tls::clienthello::{self, ClientHello};
+use crate::socket::tls::TlsVersion;
+use crate::tls::ct::{Sct, SctStatus};
+use crate::tls::ctverifier::{decode_sct_list, MultiLogCtVerifier};
+use crate::tls::pinning::{spki_hash, SpkiHash};
+use boring::ssl::SslRef;
+
+/// DER encoding of the embedded SCT list extension OID (1.3.6.1.4.1.11129.2.4.2).
+const SCT_LIST_OID: &[u8] = &[
+    0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x02,
+];
+
+/// TLS details of an established connection.
+#[derive(Debug, Clone, Default)]
+pub struct SslInfo {
+    /// Negotiated protocol version.
+    pub version: Option<TlsVersion>,
+    /// Negotiated cipher suite, by its IANA name
+    /// (e.g. `TLS_AES_128_GCM_SHA256`).
+    pub cipher_suite: Option<String>,
+    /// Key exchange group (e.g. `X25519`, `X25519MLKEM768`).
+    pub key_exchange_group: Option<String>,
+    /// Protocol selected via ALPN.
+    pub alpn: NextProto,
+    /// Peer certificate chain as DER, leaf first.
+    pub peer_certificates: Vec<Vec<u8>>,
+    /// Whether the handshake resumed a previous session.
+    pub session_resumed: bool,
+    /// SCTs embedded in the leaf certificate.
+    pub signed_certificate_timestamps: Vec<Sct>,
+    /// SPKI SHA-256 hashes of the peer certificates, leaf first.
+    pub public_key_hashes: Vec<SpkiHash>,
+    /// Why the chain failed verification, `None` if it verified.
+    pub verify_error: Option<String>,
+    /// OCSP response the server stapled to the handshake.
+    pub ocsp_response: Option<Vec<u8>>,
+    /// ALPN protocol as the server selected it, e.g. `h2`, including
+    /// protocols [`alpn`](Self::alpn) does not know.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// Settings the server sent with ALPS for the negotiated protocol,
+    /// `None` if ALPS was not negotiated. For `h2`, a SETTINGS payload:
+    /// see [`alps_h2_settings`](Self::alps_h2_settings).
+    pub alps: Option<Vec<u8>>,
+    /// Whether the server accepted Encrypted Client Hello, so the real
+    /// server name and ClientHello were never sent in the clear. Always
+    /// `false` when ECH was only GREASEd.
+    pub ech_accepted: bool,
+    /// The ClientHello as it was sent, `None` for TLS inside a tunnel.
+    pub client_hello: Option<ClientHello>,
+}
+
+impl SslInfo {
+    /// Capture the details of a completed handshake.
+    pub(crate) fn from_ssl(ssl: &SslRef) -> Self {
+        let peer_certificates: Vec<Vec<u8>> = ssl
+            .peer_cert_chain()
+            .map(|chain| chain.iter().filter_map(|c| c.to_der().ok()).collect())
+            .unwrap_or_default();
+        let signed_certificate_timestamps = peer_certificates
+            .first()
+            .and_then(|leaf| embedded_sct_list(leaf))
+            .and_then(|list| decode_sct_list(list).ok())
+            .unwrap_or_default();
+        let public_key_hashes = peer_certificates
+            .iter()
+            .filter_map(|cert| spki_hash(cert).ok())
+            .collect();
+
+        Self {
+            version: ssl.version2().map(TlsVersion),
+            cipher_suite: ssl.current_cipher().map(|c| {
+                c.standard_name()
+                    .map_or_else(|| c.name().to_string(), str::to_string)
+            }),
+            key_exchange_group: ssl.curve().and_then(|c| c.name()).map(str::to_string),
+            alpn: ssl
+                .selected_alpn_protocol()
+                .map(NextProto::from_alpn)
+                .unwrap_or_default(),
+            peer_certificates,
+            session_resumed: ssl.session_reused(),
+            signed_certificate_timestamps,
+            public_key_hashes,
+            verify_error: ssl
+                .verify_result()
+                .err()
+                .map(|e| e.error_string().to_string()),
+            ocsp_response: ssl.ocsp_status().map(<[u8]>::to_vec),
+            alpn_protocol: ssl.selected_alpn_protocol().map(<[u8]>::to_vec),
+            alps: peer_application_settings(ssl),
+            ech_accepted: ssl.ech_accepted(),
+            client_hello: clienthello::attached(ssl),
+        }
+    }
+
+    /// The HTTP/2 settings the server sent with ALPS, `None` unless `h2`
+    /// was negotiated with ALPS and the payload decodes.
+    pub fn alps_h2_settings(&self) -> Option<Vec<(u16, u32)>> {
+        if self.alpn != NextProto::Http2 {
+            return None;
+        }
+        decode_h2_settings(self.alps.as_deref()?)
+    }
+
+    /// DER of the leaf certificate, if the peer sent one.
+    pub fn leaf_certificate(&self) -> Option<&[u8]> {
+        self.peer_certificates.first().map(Vec::as_slice)
+    }
+
+    /// Verify the embedded SCTs against `verifier`'s known logs.
+    ///
+    /// Pass the results to [`MultiLogCtVerifier::check_requirements`] to
+    /// apply the verifier's CT policy.
+    pub fn verify_scts(&self, verifier: &MultiLogCtVerifier) -> Vec<(Sct, SctStatus)> {
+        verifier.verify(
+            &self.signed_certificate_timestamps,
+            self.leaf_certificate().unwrap_or_default(),
+            verifier.now(),
+        )
+    }
+}
+
+/// Find the TLS-encoded SCT list in a DER certificate's extensions.
+///
+/// The extension value is an OCTET STRING wrapping another OCTET STRING
+/// with the list (RFC 6962 Section 3.3).
+fn embedded_sct_list(cert: &[u8]) -> Option<&[u8]> {
+    let start = cert
+        .windows(SCT_LIST_OID.len())
+        .position(|w| w == SCT_LIST_OID)?
+        + SCT_LIST_OID.len();
+    let mut rest = &cert[start..];
+
+    // Optional `critical` BOOLEAN
+    if rest.first() == Some(&0x01) {
+        rest = der_value(rest)?.1;
+    }
+    let (outer, _) = der_value(rest)?;
+    let (list, _) = der_value(outer)?;
+    Some(list)
+}
+
+/// Split a DER OCTET STRING into its contents and the bytes after it.
+fn der_value(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (&tag, data) = data.split_first()?;
+    if tag != 0x04 && tag != 0x01 {
+        return None;
+    }
+    let (&first, data) = data.split_first()?;
+    let (len, data) = if first < 0x80 {
+        (first as usize, data)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || data.len() < n {
+            return None;
+        }
+        let len = data[..n]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, &data[n..])
+    };
+    if data.len() < len {
+        return None;
+    }
+    Some(data.split_at(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A TLS-encoded SCT list holding one SCT with a 4-byte signature.
+    fn sct_list() -> Vec<u8> {
+        let mut sct = vec![0u8]; // v1
+        sct.extend_from_slice(&[0xab; 32]); // log id
+        sct.extend_from_slice(&1_600_000_000_000u64.to_be_bytes());
+        sct.extend_from_slice(&[0, 0]); // no extensions
+        sct.extend_from_slice(&[4, 3, 0, 0]); // hash/sig alg + length
+        sct.extend_from_slice(&[0, 0]);
+
+        let mut list = (sct.len() as u16 + 2).to_be_bytes().to_vec();
+        list.extend_from_slice(&(sct.len() as u16).to_be_bytes());
+        list.extend_from_slice(&sct);
+        list
+    }
+
+    /// Wrap `list` in the extension encoding used inside a certificate.
+    fn extension(list: &[u8], critical: bool) -> Vec<u8> {
+        let mut inner = vec![0x04, 0x81, list.len() as u8];
+        inner.extend_from_slice(list);
+
+        let mut ext = vec![0x30, 0x00]; // surrounding SEQUENCE, length unused
+        ext.extend_from_slice(SCT_LIST_OID);
+        if critical {
+            ext.extend_from_slice(&[0x01, 0x01, 0x00]);
+        }
+        ext.extend_from_slice(&[0x04, inner.len() as u8]);
+        ext.extend_from_slice(&inner);
+        ext
+    }
+
+    #[test]
+    fn test_embedded_sct_list() {
+        let list = sct_list();
+        for critical in [false, true] {
+            let cert = extension(&list, critical);
+            assert_eq!(embedded_sct_list(&cert), Some(list.as_slice()));
+        }
+
+        let scts = decode_sct_list(embedded_sct_list(&extension(&list, false)).unwrap()).unwrap();
+        assert_eq!(scts.len(), 1);
+        assert_eq!(scts[0].log_id, [0xab; 32]);
+    }
+
+    #[test]
+    fn test_missing_or_truncated_extension() {
+        assert_eq!(embedded_sct_list(b"\x30\x03\x02\x01\x00"), None);
+
+        let cert = extension(&sct_list(), false);
+        assert_eq!(embedded_sct_list(&cert[..cert.len() - 10]), None);
+    }
+
+    #[test]
+    fn test_verify_scts_unknown_log() {
+        let list = sct_list();
+        let info = SslInfo {
+            signed_certificate_timestamps: decode_sct_list(&list).unwrap(),
+            ..Default::default()
+        };
+
+        let results = info.verify_scts(&MultiLogCtVerifier::new());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1, SctStatus::UnknownLog);
+    }
+}
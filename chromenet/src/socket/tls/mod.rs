@@ -41,22 +41,35 @@ pub fn get_ssl_connector(tls_options: Option<&TlsOptions>) -> Result<SslConnecto
             let mut builder =
                 SslConnector::builder(SslMethod::tls()).map_err(|_| NetError::SslProtocolError)?;
             opts.apply_to_builder(&mut builder)?;
-            builder
-                .set_alpn_protos(ALPN_PROTOS)
-                .map_err(|_| NetError::SslProtocolError)?;
+            // Keep the ALPN list from the options if it has one
+            if opts.alpn_protocols.is_none() {
+                builder
+                    .set_alpn_protos(ALPN_PROTOS)
+                    .map_err(|_| NetError::SslProtocolError)?;
+            }
             Ok(builder.build())
         }
     }
 }
 
+pub mod alps;
+pub mod certcompress;
+pub mod clienthello;
 pub mod impersonate;
+pub mod info;
 pub mod options;
+pub mod overrides;
+pub mod servername;
 
 // Re-export all types from options
+pub use self::clienthello::ClientHello;
 pub use self::impersonate::ImpersonateTarget;
+pub use self::info::SslInfo;
 pub use self::options::{
     AlpnProtocol, AlpsProtocol, CertCompressAlg, TlsOptions, TlsOptionsBuilder, TlsVersion,
 };
+pub use self::overrides::{TlsOverrideFn, TlsOverrides};
+pub use self::servername::ServerName;
 
 /// Configuration for TLS Client Hello fingerprinting.
 /// Matches Chromium's TLS configuration for accurate fingerprinting.
@@ -161,6 +174,6 @@ impl TlsConfig {
     /// Check if SNI should be set for this host.
     /// Per RFC 6066, SNI MUST NOT be set for raw IP addresses.
     pub fn should_set_sni(host: &str) -> bool {
-        host.parse::<std::net::IpAddr>().is_err()
+        crate::base::host::ip_literal(host).is_none()
     }
 }
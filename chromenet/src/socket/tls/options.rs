@@ -5,7 +5,7 @@
 
 use crate::base::neterror::NetError;
 use boring::ssl::{
-    CertificateCompressionAlgorithm, ExtensionType, SslConnectorBuilder, SslVerifyMode,
+    CertificateCompressionAlgorithm, ExtensionType, SslConnectorBuilder, SslRef, SslVerifyMode,
 };
 use std::borrow::Cow;
 
@@ -108,6 +108,10 @@ pub struct TlsOptions {
     pub alpn_protocols: Option<Cow<'static, [AlpnProtocol]>>,
     /// ALPS protocols (Application-Layer Protocol Settings).
     pub alps_protocols: Option<Cow<'static, [AlpsProtocol]>>,
+    /// HTTP/2 SETTINGS announced in ALPS, encoded with
+    /// [`encode_h2_settings`](super::alps::encode_h2_settings). `None`
+    /// announces empty settings.
+    pub alps_h2_settings: Option<Cow<'static, [u8]>>,
     /// Use alternative ALPS codepoint.
     pub alps_use_new_codepoint: bool,
     /// Minimum TLS version.
@@ -135,8 +139,25 @@ pub struct TlsOptions {
     /// Enable GREASE ECH extension.
     pub enable_ech_grease: bool,
     /// Enable GREASE extensions (RFC 8701).
+    ///
+    /// BoringSSL picks fresh GREASE values for every connection from its
+    /// internal RNG, which cannot be seeded. Fingerprints such as JA3 and
+    /// JA4 ignore GREASE values, so only their presence is observable.
     pub grease_enabled: Option<bool>,
     /// Permute ClientHello extensions.
+    ///
+    /// Like Chrome, BoringSSL shuffles the extensions anew for every
+    /// connection, from an RNG that cannot be seeded. Turn permutation off
+    /// for a stable order, e.g. in tests; to do so for some hosts only,
+    /// while profiles keep it, use a per-host override:
+    ///
+    /// ```
+    /// use chromenet::Client;
+    ///
+    /// let client = Client::builder()
+    ///     .tls_override("fingerprint.test", |tls| tls.permute_extensions(false))
+    ///     .build();
+    /// ```
     pub permute_extensions: Option<bool>,
     /// Enable TLS renegotiation.
     pub renegotiation: bool,
@@ -154,7 +175,14 @@ pub struct TlsOptions {
     pub certificate_compression_algorithms: Option<Cow<'static, [CertificateCompressionAlgorithm]>>,
 
     // === Extension Configuration ===
-    /// Extension permutation order.
+    /// Fixed ClientHello extension order, in place of a random
+    /// permutation.
+    ///
+    /// Upstream BoringSSL, which chromenet builds on, has no way to set
+    /// the order or to seed its permutation, so a connector with an order
+    /// set fails to build with [`NetError::SslProtocolError`] rather than
+    /// sending the hello in another order. For a reproducible order turn
+    /// [`permute_extensions`](Self::permute_extensions) off.
     pub extension_permutation: Option<Cow<'static, [ExtensionType]>>,
     /// Maximum TLS record size.
     pub record_size_limit: Option<u16>,
@@ -171,6 +199,7 @@ impl Default for TlsOptions {
         Self {
             alpn_protocols: Some(Cow::Borrowed(&[AlpnProtocol::HTTP2, AlpnProtocol::HTTP1])),
             alps_protocols: None,
+            alps_h2_settings: None,
             alps_use_new_codepoint: false,
             min_tls_version: Some(TlsVersion::TLS_1_2),
             max_tls_version: Some(TlsVersion::TLS_1_3),
@@ -256,13 +285,42 @@ impl TlsOptions {
         if let Some(permute) = self.permute_extensions {
             builder.set_permute_extensions(permute);
         }
+        if let Some(order) = &self.extension_permutation {
+            tracing::debug!(
+                ?order,
+                "a fixed extension order is not supported by this BoringSSL"
+            );
+            return Err(NetError::SslProtocolError);
+        }
+
+        if self.alps_use_new_codepoint {
+            tracing::debug!("ALPS new codepoint unsupported by this BoringSSL, using 17513");
+        }
 
-        // Certificate compression - BoringSSL 4.x requires CertificateCompressor trait
-        // TODO: Implement custom compressor if needed
-        // if let Some(ref algs) = self.certificate_compression_algorithms { ... }
+        // Certificate compression: advertised in this order, decompressed
+        // when the server compresses
+        if let Some(ref algs) = self.certificate_compression_algorithms {
+            super::certcompress::register(builder, algs).map_err(|_| NetError::SslProtocolError)?;
+        }
 
         Ok(())
     }
+
+    /// Apply the per-connection part of these options to the SSL object of
+    /// one handshake: the ALPS settings and ECH GREASE.
+    pub fn apply_to_connection(&self, ssl: &mut SslRef) -> Result<(), NetError> {
+        if self.enable_ech_grease {
+            ssl.set_enable_ech_grease(true);
+        }
+        for &protocol in self.alps_protocols.iter().flat_map(|p| p.iter()) {
+            let settings = match self.alps_h2_settings.as_deref() {
+                Some(settings) if protocol == AlpsProtocol::HTTP2 => settings,
+                _ => &[],
+            };
+            super::alps::add_application_settings(ssl, protocol, settings)?;
+        }
+        Ok(())
+    }
 }
 
 // === TlsOptionsBuilder Implementation ===
@@ -295,6 +353,17 @@ impl TlsOptionsBuilder {
         self
     }
 
+    /// Set the HTTP/2 SETTINGS announced in ALPS, as (identifier, value)
+    /// pairs. See [`crate::socket::tls::alps`].
+    #[inline]
+    pub fn alps_h2_settings<I>(mut self, settings: I) -> Self
+    where
+        I: IntoIterator<Item = (u16, u32)>,
+    {
+        self.config.alps_h2_settings = Some(Cow::Owned(super::alps::encode_h2_settings(settings)));
+        self
+    }
+
     /// Set ALPS new codepoint flag.
     #[inline]
     pub fn alps_use_new_codepoint(mut self, enabled: bool) -> Self {
@@ -431,7 +500,8 @@ impl TlsOptionsBuilder {
         self
     }
 
-    /// Set extension permutation order.
+    /// Set a fixed extension order, which this BoringSSL cannot apply;
+    /// see [`TlsOptions::extension_permutation`].
     #[inline]
     pub fn extension_permutation<T>(mut self, permutation: T) -> Self
     where
@@ -468,3 +538,30 @@ impl TlsOptionsBuilder {
         self.config
     }
 }
+
+impl From<TlsOptions> for TlsOptionsBuilder {
+    /// Start a builder from existing options, e.g. to adjust a profile.
+    fn from(config: TlsOptions) -> Self {
+        Self { config }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boring::ssl::{SslConnector, SslMethod};
+
+    #[test]
+    fn test_fixed_extension_order_is_refused() {
+        let options = TlsOptions::builder()
+            .extension_permutation(vec![ExtensionType::SERVER_NAME])
+            .build();
+        let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+        let result = options.apply_to_builder(&mut builder);
+        assert!(matches!(result, Err(NetError::SslProtocolError)));
+
+        let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+        let stable = TlsOptions::builder().permute_extensions(false).build();
+        assert!(stable.apply_to_builder(&mut builder).is_ok());
+    }
+}
@@ -0,0 +1,191 @@
+//! Per-host TLS option overrides.
+//!
+//! Lets a client adjust the TLS configuration for specific hosts, e.g.
+//! capping the version for an appliance that breaks on TLS 1.3, without
+//! giving up the emulation profile for everything else. Overrides are
+//! resolved when a connection is made.
+
+use crate::base::host::host_key;
+use crate::socket::tls::{TlsOptions, TlsOptionsBuilder};
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+/// Adjusts the base TLS options for matching hosts.
+pub type TlsOverrideFn = Arc<dyn Fn(TlsOptionsBuilder) -> TlsOptionsBuilder + Send + Sync>;
+
+/// Host pattern of an override rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HostPattern {
+    /// `*`: every host.
+    Any,
+    /// `*.example.com`: subdomains of `example.com`, not the domain itself.
+    Subdomains(String),
+    /// `example.com` or an IP literal.
+    Exact(String),
+}
+
+impl HostPattern {
+    pub(crate) fn parse(pattern: &str) -> Self {
+        let pattern = pattern.trim();
+        if pattern == "*" {
+            HostPattern::Any
+        } else if let Some(domain) = pattern.strip_prefix("*.") {
+            HostPattern::Subdomains(format!(".{}", host_key(domain)))
+        } else {
+            HostPattern::Exact(host_key(pattern))
+        }
+    }
+
+    /// Whether the canonical `host` matches.
+    pub(crate) fn matches(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Any => true,
+            HostPattern::Subdomains(suffix) => {
+                host.len() > suffix.len() && host.ends_with(suffix.as_str())
+            }
+            HostPattern::Exact(name) => host == name,
+        }
+    }
+}
+
+/// Ordered set of per-host TLS overrides.
+///
+/// Every rule matching a host is applied in registration order on top of
+/// the client's TLS options, so register broad patterns before narrow ones.
+#[derive(Clone, Default)]
+pub struct TlsOverrides {
+    rules: Vec<(HostPattern, TlsOverrideFn)>,
+}
+
+impl TlsOverrides {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an override for hosts matching `pattern`.
+    ///
+    /// Patterns are `example.com` (exact host or IP literal),
+    /// `*.example.com` (any subdomain) or `*` (every host).
+    pub fn add<F>(&mut self, pattern: &str, f: F)
+    where
+        F: Fn(TlsOptionsBuilder) -> TlsOptionsBuilder + Send + Sync + 'static,
+    {
+        self.rules.push((HostPattern::parse(pattern), Arc::new(f)));
+    }
+
+    /// Whether no overrides are registered.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Number of registered overrides.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Resolve the TLS options for `host`, layering matching overrides over
+    /// `base`.
+    ///
+    /// Returns `base` unchanged when no rule matches. When one does and
+    /// there is no base, the overrides start from [`TlsOptions::default`].
+    pub fn resolve<'a>(
+        &self,
+        host: &str,
+        base: Option<&'a TlsOptions>,
+    ) -> Option<Cow<'a, TlsOptions>> {
+        let host = host_key(host);
+        let mut matching = self
+            .rules
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(&host))
+            .peekable();
+        if matching.peek().is_none() {
+            return base.map(Cow::Borrowed);
+        }
+
+        let builder = TlsOptionsBuilder::from(base.cloned().unwrap_or_default());
+        let options = matching.fold(builder, |b, (_, f)| f(b)).build();
+        Some(Cow::Owned(options))
+    }
+}
+
+impl fmt::Debug for TlsOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.rules.iter().map(|(pattern, _)| pattern))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::tls::TlsVersion;
+
+    #[test]
+    fn test_host_patterns() {
+        assert!(HostPattern::parse("*").matches("anything.test"));
+
+        let sub = HostPattern::parse("*.Example.com");
+        assert!(sub.matches("a.example.com"));
+        assert!(sub.matches("a.b.example.com"));
+        assert!(!sub.matches("example.com"));
+        assert!(!sub.matches("notexample.com"));
+
+        let exact = HostPattern::parse("example.com");
+        assert!(exact.matches("example.com"));
+        assert!(!exact.matches("www.example.com"));
+    }
+
+    #[test]
+    fn test_resolve_layers_over_base() {
+        let mut overrides = TlsOverrides::new();
+        overrides.add("legacy.internal", |b| {
+            b.max_tls_version(TlsVersion::TLS_1_2)
+        });
+        overrides.add("*.internal", |b| b.cipher_list("AES128-GCM-SHA256"));
+
+        let base = TlsOptions::builder().session_ticket(false).build();
+
+        let resolved = overrides.resolve("legacy.internal", Some(&base)).unwrap();
+        assert_eq!(resolved.max_tls_version, Some(TlsVersion::TLS_1_2));
+        assert_eq!(resolved.cipher_list.as_deref(), Some("AES128-GCM-SHA256"));
+        assert!(!resolved.session_ticket);
+
+        let other = overrides.resolve("example.com", Some(&base)).unwrap();
+        assert!(matches!(other, Cow::Borrowed(_)));
+        assert!(overrides.resolve("example.com", None).is_none());
+    }
+
+    #[test]
+    fn test_disable_permutation_for_one_host() {
+        let mut overrides = TlsOverrides::new();
+        overrides.add("fingerprint.test", |b| b.permute_extensions(false));
+
+        let profile = TlsOptions::builder()
+            .grease_enabled(true)
+            .permute_extensions(true)
+            .build();
+
+        let resolved = overrides
+            .resolve("fingerprint.test", Some(&profile))
+            .unwrap();
+        assert_eq!(resolved.permute_extensions, Some(false));
+        assert_eq!(resolved.grease_enabled, Some(true));
+
+        let other = overrides.resolve("example.com", Some(&profile)).unwrap();
+        assert_eq!(other.permute_extensions, Some(true));
+        assert_eq!(profile.permute_extensions, Some(true));
+    }
+
+    #[test]
+    fn test_resolve_ip_literal() {
+        let mut overrides = TlsOverrides::new();
+        overrides.add("::1", |b| b.max_tls_version(TlsVersion::TLS_1_2));
+
+        let resolved = overrides.resolve("[::1]", None).unwrap();
+        assert_eq!(resolved.max_tls_version, Some(TlsVersion::TLS_1_2));
+    }
+}
@@ -0,0 +1,92 @@
+//! Server name sent in the TLS ClientHello.
+//!
+//! Normally the SNI extension carries the URL's host, the same name as the
+//! `Host` header or `:authority`. Some CDN setups and research tools need
+//! the two to differ (domain fronting) or no SNI at all. Requests only get
+//! a [`ServerName`] other than [`ServerName::Host`] through
+//! [`RequestBuilder::server_name`](crate::client::RequestBuilder::server_name),
+//! and only on clients built with
+//! [`ClientBuilder::allow_sni_override`](crate::ClientBuilder::allow_sni_override).
+//!
+//! Connections are pooled per server name, so a fronted connection is never
+//! reused for a request to the front domain itself, nor the other way round.
+
+use crate::base::host::host_key;
+
+/// Name a TLS connection announces and verifies the certificate against.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum ServerName {
+    /// The URL's host. IP literals are not sent (RFC 6066 section 3).
+    #[default]
+    Host,
+    /// A name independent of the URL's host, e.g. the front domain of a
+    /// CDN. The certificate must be valid for this name.
+    Custom(String),
+    /// No SNI extension. The certificate is still verified against the
+    /// URL's host.
+    Disabled,
+}
+
+impl ServerName {
+    /// Send `name` instead of the URL's host.
+    pub fn custom(name: impl Into<String>) -> Self {
+        ServerName::Custom(host_key(&name.into()))
+    }
+
+    /// Whether this differs from the default of sending the URL's host.
+    pub fn is_override(&self) -> bool {
+        *self != ServerName::Host
+    }
+
+    /// Name to announce and verify for a connection to `host`.
+    ///
+    /// IPv6 literals lose their URL brackets, so the certificate is checked
+    /// against its IP address SANs.
+    pub fn tls_name<'a>(&'a self, host: &'a str) -> &'a str {
+        let name = match self {
+            ServerName::Custom(name) => name,
+            ServerName::Host | ServerName::Disabled => host,
+        };
+        name.strip_prefix('[')
+            .and_then(|name| name.strip_suffix(']'))
+            .unwrap_or(name)
+    }
+
+    /// Whether the ClientHello carries the SNI extension for `host`.
+    pub fn sends_sni(&self, host: &str) -> bool {
+        match self {
+            ServerName::Disabled => false,
+            ServerName::Host | ServerName::Custom(_) => {
+                super::TlsConfig::should_set_sni(self.tls_name(host))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_name() {
+        assert_eq!(ServerName::Host.tls_name("hidden.test"), "hidden.test");
+        assert_eq!(
+            ServerName::custom("Front.CDN.test.").tls_name("hidden.test"),
+            "front.cdn.test"
+        );
+        assert_eq!(ServerName::Disabled.tls_name("hidden.test"), "hidden.test");
+        assert_eq!(ServerName::Host.tls_name("[2001:db8::1]"), "2001:db8::1");
+        assert_eq!(ServerName::custom("[::1]").tls_name("hidden.test"), "::1");
+    }
+
+    #[test]
+    fn test_sends_sni() {
+        assert!(ServerName::Host.sends_sni("example.com"));
+        assert!(!ServerName::Host.sends_sni("192.0.2.1"));
+        assert!(!ServerName::Host.sends_sni("[2001:db8::1]"));
+        assert!(ServerName::custom("front.test").sends_sni("192.0.2.1"));
+        assert!(!ServerName::Disabled.sends_sni("example.com"));
+        assert!(!ServerName::Host.is_override());
+        assert!(ServerName::Disabled.is_override());
+    }
+}
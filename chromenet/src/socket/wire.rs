@@ -0,0 +1,528 @@
+//! Socket instrumentation: byte counters and wire capture.
+//!
+//! Chromium mapping: the `SOCKET_BYTES_SENT`/`SOCKET_BYTES_RECEIVED`
+//! NetLog events, and `URLRequest::GetTotalSentBytes`/`GetTotalReceivedBytes`
+//!
+//! Enabled per client, every new connection is wrapped twice:
+//!
+//! - at the TCP socket, counting the bytes that went over the network and
+//!   recording them as [`WireLayer::Raw`]: proxy handshakes, TLS records,
+//!   or cleartext HTTP;
+//! - above the TLS layers, if any, recording what went through them as
+//!   [`WireLayer::Plaintext`].
+//!
+//! With counting enabled, [`HttpResponse::wire_bytes`](crate::http::HttpResponse::wire_bytes)
+//! reports the bytes of a request, also after its body was consumed. Over
+//! HTTP/1.1 a connection carries one request at a time, so the count is
+//! exact, TLS overhead included. Over HTTP/2 the streams of a connection
+//! share its frames, so the count is everything the connection sent and
+//! received since the request started.
+//!
+//! ```no_run
+//! use chromenet::socket::wire::{WireEvent, WireLayer};
+//! use chromenet::Client;
+//!
+//! let client = Client::builder()
+//!     .count_bytes(true)
+//!     .wire_capture(|event: &WireEvent<'_>| {
+//!         if event.layer == WireLayer::Plaintext {
+//!             eprintln!("#{} {:?} {} bytes", event.connection, event.direction, event.data.len());
+//!         }
+//!     })
+//!     .build();
+//! ```
+
+use crate::socket::nextproto::NextProto;
+use crate::socket::stream::StreamSocket;
+use crate::socket::tls::SslInfo;
+use std::io::IoSlice;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Bytes sent and received.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ByteCount {
+    pub sent: u64,
+    pub received: u64,
+}
+
+impl ByteCount {
+    /// Bytes transferred since `earlier`.
+    pub fn since(self, earlier: ByteCount) -> ByteCount {
+        ByteCount {
+            sent: self.sent.saturating_sub(earlier.sent),
+            received: self.received.saturating_sub(earlier.received),
+        }
+    }
+}
+
+/// Running totals, shared with the socket that updates them.
+#[derive(Debug, Default)]
+struct ByteCounter {
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+impl ByteCounter {
+    fn add(&self, direction: Direction, n: usize) {
+        let counter = match direction {
+            Direction::Sent => &self.sent,
+            Direction::Received => &self.received,
+        };
+        counter.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> ByteCount {
+        ByteCount {
+            sent: self.sent.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Byte counts of a connection: its total, and the request it carries.
+#[derive(Debug, Default)]
+pub(crate) struct WireMeter {
+    total: Arc<ByteCounter>,
+    request: Mutex<Arc<ByteCounter>>,
+}
+
+impl WireMeter {
+    fn add(&self, direction: Direction, n: usize) {
+        self.total.add(direction, n);
+        self.request
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .add(direction, n);
+    }
+
+    /// Count the bytes of a request that has the connection to itself,
+    /// until the next one starts.
+    pub(crate) fn begin_request(&self) -> RequestBytes {
+        let counter = Arc::new(ByteCounter::default());
+        *self.request.lock().unwrap_or_else(|e| e.into_inner()) = counter.clone();
+        RequestBytes {
+            counter,
+            base: ByteCount::default(),
+        }
+    }
+
+    /// Count the bytes of the whole connection from now on, for a request
+    /// sharing it with others.
+    pub(crate) fn begin_shared_request(&self) -> RequestBytes {
+        RequestBytes {
+            counter: self.total.clone(),
+            base: self.total.get(),
+        }
+    }
+}
+
+/// Bytes on the wire for one request, see [`crate::socket::wire`].
+///
+/// Reading the body adds to the count as it arrives.
+#[derive(Debug, Clone)]
+pub struct RequestBytes {
+    counter: Arc<ByteCounter>,
+    base: ByteCount,
+}
+
+impl RequestBytes {
+    /// Bytes sent and received so far.
+    pub fn get(&self) -> ByteCount {
+        self.counter.get().since(self.base)
+    }
+}
+
+/// Where in the socket stack bytes were seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WireLayer {
+    /// On the TCP socket, before any TLS.
+    Raw,
+    /// Above the innermost TLS layer. Not recorded for cleartext
+    /// connections, whose raw bytes are already plaintext.
+    Plaintext,
+}
+
+/// Which way bytes went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Bytes that went through a socket.
+#[derive(Debug, Clone, Copy)]
+pub struct WireEvent<'a> {
+    /// Identifies the connection, unique within the process.
+    pub connection: u64,
+    pub layer: WireLayer,
+    pub direction: Direction,
+    pub data: &'a [u8],
+}
+
+/// Receives the traffic of instrumented connections.
+///
+/// Called on the task doing the I/O, so implementations should be quick,
+/// e.g. copy the data into a buffer or channel.
+pub trait WireSink: Send + Sync {
+    fn record(&self, event: &WireEvent<'_>);
+}
+
+impl<F> WireSink for F
+where
+    F: Fn(&WireEvent<'_>) + Send + Sync,
+{
+    fn record(&self, event: &WireEvent<'_>) {
+        self(event)
+    }
+}
+
+/// Which instrumentation new connections get. Nothing by default.
+#[derive(Clone, Default)]
+pub struct SocketInstrumentation {
+    count_bytes: bool,
+    sink: Option<Arc<dyn WireSink>>,
+}
+
+impl std::fmt::Debug for SocketInstrumentation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SocketInstrumentation")
+            .field("count_bytes", &self.count_bytes)
+            .field("capture", &self.sink.is_some())
+            .finish()
+    }
+}
+
+impl SocketInstrumentation {
+    /// No instrumentation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the bytes of connections and requests.
+    pub fn with_byte_counting(mut self, enabled: bool) -> Self {
+        self.count_bytes = enabled;
+        self
+    }
+
+    /// Record all traffic to `sink`.
+    pub fn with_sink(mut self, sink: Arc<dyn WireSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Instrumentation for a new connection.
+    pub(crate) fn connection(&self) -> ConnectionTap {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        ConnectionTap {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            meter: self.count_bytes.then(|| Arc::new(WireMeter::default())),
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+/// Instrumentation of one connection, wrapping its sockets as it is set up.
+pub(crate) struct ConnectionTap {
+    id: u64,
+    meter: Option<Arc<WireMeter>>,
+    sink: Option<Arc<dyn WireSink>>,
+}
+
+impl ConnectionTap {
+    /// Wrap the TCP socket, counting and recording raw bytes.
+    pub(crate) fn raw<S>(&self, socket: S) -> InstrumentedSocket<S> {
+        InstrumentedSocket {
+            inner: socket,
+            meter: self.meter.clone(),
+            capture: self.capture(WireLayer::Raw),
+            first_record: Some(FirstRecord::default()),
+        }
+    }
+
+    /// Wrap the socket above the TLS layers, recording plaintext.
+    pub(crate) fn plaintext<S>(&self, socket: S) -> InstrumentedSocket<S> {
+        InstrumentedSocket {
+            inner: socket,
+            meter: None,
+            capture: self.capture(WireLayer::Plaintext),
+            first_record: None,
+        }
+    }
+
+    /// The connection's byte counts, `None` without counting.
+    pub(crate) fn meter(&self) -> Option<Arc<WireMeter>> {
+        self.meter.clone()
+    }
+
+    fn capture(&self, layer: WireLayer) -> Option<Capture> {
+        self.sink.clone().map(|sink| Capture {
+            sink,
+            connection: self.id,
+            layer,
+        })
+    }
+}
+
+struct Capture {
+    sink: Arc<dyn WireSink>,
+    connection: u64,
+    layer: WireLayer,
+}
+
+/// The first TLS record a connection sends, its ClientHello, skipping
+/// what precedes the handshake, e.g. a proxy CONNECT.
+#[derive(Default)]
+struct FirstRecord {
+    buf: Vec<u8>,
+    complete: bool,
+}
+
+impl FirstRecord {
+    fn feed(&mut self, data: &[u8]) {
+        if self.complete || (self.buf.is_empty() && data.first() != Some(&0x16)) {
+            return;
+        }
+        self.buf.extend_from_slice(data);
+        if let Some(len) = self
+            .buf
+            .get(3..5)
+            .map(|len| 5 + u16::from_be_bytes([len[0], len[1]]) as usize)
+        {
+            if self.buf.len() >= len {
+                self.buf.truncate(len);
+                self.complete = true;
+            }
+        }
+    }
+}
+
+/// A socket counting and recording the bytes going through it. Without
+/// instrumentation it only forwards.
+pub(crate) struct InstrumentedSocket<S> {
+    inner: S,
+    meter: Option<Arc<WireMeter>>,
+    capture: Option<Capture>,
+    /// Kept on raw sockets until the TLS handshake takes it.
+    first_record: Option<FirstRecord>,
+}
+
+impl<S> InstrumentedSocket<S> {
+    /// The first complete TLS record sent, once. Later calls, and calls
+    /// on a plaintext socket, return `None`.
+    pub(crate) fn take_first_record(&mut self) -> Option<Vec<u8>> {
+        match self.first_record.take() {
+            Some(record) if record.complete => Some(record.buf),
+            _ => None,
+        }
+    }
+
+    fn observe(&mut self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        if let (Direction::Sent, Some(record)) = (direction, &mut self.first_record) {
+            record.feed(data);
+        }
+        if let Some(meter) = &self.meter {
+            meter.add(direction, data.len());
+        }
+        if let Some(capture) = &self.capture {
+            capture.sink.record(&WireEvent {
+                connection: capture.connection,
+                layer: capture.layer,
+                direction,
+                data,
+            });
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for InstrumentedSocket<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            self.observe(Direction::Received, &buf.filled()[before..]);
+        }
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for InstrumentedSocket<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            self.observe(Direction::Sent, &buf[..n]);
+        }
+        poll
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = poll {
+            let mut remaining = n;
+            for buf in bufs {
+                let len = remaining.min(buf.len());
+                self.observe(Direction::Sent, &buf[..len]);
+                remaining -= len;
+                if remaining == 0 {
+                    break;
+                }
+            }
+        }
+        poll
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<S: StreamSocket> StreamSocket for InstrumentedSocket<S> {
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn negotiated_protocol(&self) -> NextProto {
+        self.inner.negotiated_protocol()
+    }
+
+    fn ssl_info(&self) -> Option<SslInfo> {
+        self.inner.ssl_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_counts_and_records_both_directions() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let instrumentation = SocketInstrumentation::new()
+            .with_byte_counting(true)
+            .with_sink(Arc::new(move |event: &WireEvent<'_>| {
+                seen.lock()
+                    .unwrap()
+                    .push((event.layer, event.direction, event.data.to_vec()));
+            }));
+        let tap = instrumentation.connection();
+        let meter = tap.meter().unwrap();
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut socket = tap.raw(client);
+
+        let first = meter.begin_request();
+        socket.write_all(b"ping").await.unwrap();
+        server.write_all(b"pong!").await.unwrap();
+        let mut buf = [0u8; 5];
+        socket.read_exact(&mut buf).await.unwrap();
+        assert_eq!(
+            first.get(),
+            ByteCount {
+                sent: 4,
+                received: 5
+            }
+        );
+
+        // The next request stops the count of the first
+        let second = meter.begin_request();
+        socket.write_all(b"again").await.unwrap();
+        assert_eq!(
+            first.get(),
+            ByteCount {
+                sent: 4,
+                received: 5
+            }
+        );
+        assert_eq!(
+            second.get(),
+            ByteCount {
+                sent: 5,
+                received: 0
+            }
+        );
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events[..2],
+            [
+                (WireLayer::Raw, Direction::Sent, b"ping".to_vec()),
+                (WireLayer::Raw, Direction::Received, b"pong!".to_vec()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_request_counts_connection() {
+        let tap = SocketInstrumentation::new()
+            .with_byte_counting(true)
+            .connection();
+        let meter = tap.meter().unwrap();
+        let (client, _server) = tokio::io::duplex(64);
+        let mut socket = tap.raw(client);
+
+        socket.write_all(b"preface").await.unwrap();
+        let a = meter.begin_shared_request();
+        socket.write_all(b"a").await.unwrap();
+        let b = meter.begin_shared_request();
+        socket.write_all(b"bb").await.unwrap();
+        assert_eq!(a.get().sent, 3);
+        assert_eq!(b.get().sent, 2);
+    }
+
+    #[tokio::test]
+    async fn test_first_tls_record_kept() {
+        let tap = SocketInstrumentation::new().connection();
+        let (client, _server) = tokio::io::duplex(1024);
+        let mut socket = tap.raw(client);
+
+        let record = [0x16, 0x03, 0x01, 0x00, 0x03, 1, 2, 3];
+        socket
+            .write_all(b"CONNECT a.test:443 HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        socket.write_all(&record[..4]).await.unwrap();
+        socket.write_all(&record[4..]).await.unwrap();
+        socket.write_all(&record).await.unwrap();
+        assert_eq!(socket.take_first_record().unwrap(), record);
+        assert!(socket.take_first_record().is_none());
+
+        let (client, _server) = tokio::io::duplex(1024);
+        let mut socket = tap.plaintext(client);
+        socket.write_all(&record).await.unwrap();
+        assert!(socket.take_first_record().is_none());
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let tap = SocketInstrumentation::new().connection();
+        assert!(tap.meter().is_none());
+        assert!(tap.capture(WireLayer::Raw).is_none());
+    }
+}
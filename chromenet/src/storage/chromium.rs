@@ -0,0 +1,177 @@
+//! Chromium's Web Storage LevelDB layouts.
+//!
+//! `Local Storage/leveldb`:
+//! - `_<storage key>\0<string>` → `<string>`, one per item
+//! - `META:<storage key>`, `METAACCESS:<storage key>`, `VERSION` (ignored)
+//!
+//! Strings start with a format byte: 0 for UTF-16LE, 1 for Latin-1.
+//!
+//! `Session Storage`:
+//! - `namespace-<guid>-<storage key>` → map id, one per tab and origin
+//! - `map-<map id>-<key>` → value, both UTF-16LE
+//!
+//! Storage keys are origins, followed by `^`-separated partition attributes
+//! for third-party contexts.
+
+use super::StorageItem;
+use std::collections::{BTreeMap, HashMap};
+
+/// Items of a `Local Storage/leveldb` database.
+pub fn local_storage(db: &BTreeMap<Vec<u8>, Vec<u8>>) -> Vec<StorageItem> {
+    db.iter()
+        .filter_map(|(key, value)| {
+            let rest = key.strip_prefix(b"_")?;
+            let separator = rest.iter().position(|&b| b == 0)?;
+            let storage_key = std::str::from_utf8(&rest[..separator]).ok()?;
+            Some(StorageItem {
+                origin: origin_of(storage_key),
+                key: decode_prefixed(&rest[separator + 1..])?,
+                value: decode_prefixed(value)?,
+            })
+        })
+        .collect()
+}
+
+/// Items of a `Session Storage` database.
+pub fn session_storage(db: &BTreeMap<Vec<u8>, Vec<u8>>) -> Vec<StorageItem> {
+    // Several tabs may share a map until one of them writes to it
+    let mut origins_by_map: HashMap<&[u8], Vec<String>> = HashMap::new();
+    for (key, value) in db {
+        let Some(rest) = key.strip_prefix(b"namespace-") else {
+            continue;
+        };
+        // The namespace id is a 36-character GUID
+        let Some(storage_key) = rest.get(36..).and_then(|s| s.strip_prefix(b"-")) else {
+            continue;
+        };
+        let Ok(storage_key) = std::str::from_utf8(storage_key) else {
+            continue;
+        };
+        origins_by_map
+            .entry(value.as_slice())
+            .or_default()
+            .push(origin_of(storage_key));
+    }
+
+    let mut items = Vec::new();
+    for (key, value) in db {
+        let Some(rest) = key.strip_prefix(b"map-") else {
+            continue;
+        };
+        let Some(separator) = rest.iter().position(|&b| b == b'-') else {
+            continue;
+        };
+        let Some(origins) = origins_by_map.get(&rest[..separator]) else {
+            continue;
+        };
+        let (Some(key), Some(value)) = (
+            decode_utf16le(&rest[separator + 1..]),
+            decode_utf16le(value),
+        ) else {
+            continue;
+        };
+        items.extend(origins.iter().map(|origin| StorageItem {
+            origin: origin.clone(),
+            key: key.clone(),
+            value: value.clone(),
+        }));
+    }
+    items
+}
+
+/// Origin part of a storage key, without the trailing slash.
+fn origin_of(storage_key: &str) -> String {
+    let origin = storage_key.split('^').next().unwrap_or_default();
+    origin.trim_end_matches('/').to_string()
+}
+
+/// String with a leading format byte.
+fn decode_prefixed(bytes: &[u8]) -> Option<String> {
+    match bytes.split_first()? {
+        (0, utf16) => decode_utf16le(utf16),
+        (1, latin1) => Some(latin1.iter().map(|&b| b as char).collect()),
+        _ => None,
+    }
+}
+
+fn decode_utf16le(bytes: &[u8]) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units).collect::<Result<_, _>>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_local_storage() {
+        let mut db = BTreeMap::new();
+        db.insert(b"VERSION".to_vec(), b"1".to_vec());
+        db.insert(b"META:https://example.com".to_vec(), vec![8, 1]);
+        db.insert(
+            b"_https://example.com\0\x01token".to_vec(),
+            [&[0u8][..], &utf16("héllo ✓")].concat(),
+        );
+        db.insert(
+            [&b"_https://app.test:8443/\0\x00"[..], &utf16("ключ")].concat(),
+            b"\x01caf\xe9".to_vec(),
+        );
+        // Partitioned storage of a third-party iframe
+        db.insert(
+            b"_https://widget.test/^0https://example.com\0\x01k".to_vec(),
+            b"\x01v".to_vec(),
+        );
+
+        let items = local_storage(&db);
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            items[0],
+            StorageItem {
+                origin: "https://app.test:8443".into(),
+                key: "ключ".into(),
+                value: "café".into(),
+            }
+        );
+        assert_eq!(items[1].origin, "https://example.com");
+        assert_eq!(items[1].value, "héllo ✓");
+        assert_eq!(items[2].origin, "https://widget.test");
+    }
+
+    #[test]
+    fn test_session_storage() {
+        let mut db = BTreeMap::new();
+        db.insert(b"version".to_vec(), b"1".to_vec());
+        db.insert(b"next-map-id".to_vec(), b"3".to_vec());
+        db.insert(
+            b"namespace-0a5c9a7f_0c4e_4f4b_9a3b_1234567890ab-https://example.com/".to_vec(),
+            b"1".to_vec(),
+        );
+        db.insert(
+            b"namespace-9f8e7d6c_0c4e_4f4b_9a3b_1234567890ab-https://other.test/".to_vec(),
+            b"2".to_vec(),
+        );
+        db.insert([&b"map-1-"[..], &utf16("csrf")].concat(), utf16("abc123"));
+        db.insert([&b"map-2-"[..], &utf16("k")].concat(), utf16("v"));
+        // Map without a namespace
+        db.insert([&b"map-7-"[..], &utf16("x")].concat(), utf16("y"));
+
+        let mut items = session_storage(&db);
+        items.sort();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].origin, "https://example.com");
+        assert_eq!(
+            (items[0].key.as_str(), items[0].value.as_str()),
+            ("csrf", "abc123")
+        );
+        assert_eq!(items[1].origin, "https://other.test");
+    }
+}
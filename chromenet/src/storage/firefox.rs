@@ -0,0 +1,332 @@
+//! Firefox Web Storage.
+//!
+//! - `localStorage` lives in one SQLite database per origin under
+//!   `storage/default/<origin>/ls/data.sqlite`. Profiles from before
+//!   Firefox 92 may still have items in the shared `webappsstore.sqlite`;
+//!   both are read, the per-origin databases taking precedence.
+//! - `sessionStorage` is only kept in the session file,
+//!   `sessionstore-backups/recovery.jsonlz4` while Firefox runs and
+//!   `sessionstore.jsonlz4` after it quit.
+//!
+//! Items of container tabs are included.
+
+use super::{lz4, snappy, StorageItem};
+use crate::base::neterror::NetError;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// `localStorage` items of the profile in `profile_dir`.
+pub fn local_storage(profile_dir: &Path) -> Result<Vec<StorageItem>, NetError> {
+    let legacy = profile_dir.join("webappsstore.sqlite");
+    let origins = profile_dir.join("storage").join("default");
+    if !legacy.exists() && !origins.exists() {
+        return Err(NetError::cookie_db_not_found(origins.to_string_lossy()));
+    }
+
+    let mut items = BTreeMap::new();
+    if legacy.exists() {
+        for item in read_webappsstore(&legacy)? {
+            items.insert((item.origin.clone(), item.key.clone()), item);
+        }
+    }
+    for entry in std::fs::read_dir(&origins).into_iter().flatten().flatten() {
+        let db = entry.path().join("ls").join("data.sqlite");
+        if !db.exists() {
+            continue;
+        }
+        let fallback = entry.file_name().to_str().and_then(origin_from_dir_name);
+        match read_origin_db(&db, fallback) {
+            Ok(origin_items) => {
+                for item in origin_items {
+                    items.insert((item.origin.clone(), item.key.clone()), item);
+                }
+            }
+            Err(e) => tracing::debug!(path = %db.display(), error = %e, "skipping storage db"),
+        }
+    }
+    Ok(items.into_values().collect())
+}
+
+/// `sessionStorage` items of the open (or last) session of the profile in
+/// `profile_dir`.
+pub fn session_storage(profile_dir: &Path) -> Result<Vec<StorageItem>, NetError> {
+    let candidates = [
+        profile_dir
+            .join("sessionstore-backups")
+            .join("recovery.jsonlz4"),
+        profile_dir.join("sessionstore.jsonlz4"),
+    ];
+    let path = candidates
+        .iter()
+        .find(|p| p.exists())
+        .ok_or_else(|| NetError::cookie_db_not_found(candidates[0].to_string_lossy()))?;
+
+    let data =
+        std::fs::read(path).map_err(|_| NetError::cookie_db_not_found(path.to_string_lossy()))?;
+    let json = lz4::decompress_mozlz4(&data)
+        .ok_or_else(|| NetError::cookie_invalid_data("malformed session file"))?;
+    let session: serde_json::Value = serde_json::from_slice(&json)
+        .map_err(|e| NetError::cookie_invalid_data(format!("session file: {}", e)))?;
+    Ok(session_items(&session))
+}
+
+/// Items of the `storage` objects of every open tab:
+/// `{"windows": [{"tabs": [{"storage": {origin: {key: value}}}]}]}`.
+fn session_items(session: &serde_json::Value) -> Vec<StorageItem> {
+    let tabs = session["windows"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|window| window["tabs"].as_array().into_iter().flatten());
+
+    let mut items = Vec::new();
+    for tab in tabs {
+        let Some(storage) = tab["storage"].as_object() else {
+            continue;
+        };
+        for (origin, values) in storage {
+            let Some(values) = values.as_object() else {
+                continue;
+            };
+            for (key, value) in values {
+                if let Some(value) = value.as_str() {
+                    items.push(StorageItem {
+                        origin: origin.trim_end_matches('/').to_string(),
+                        key: key.clone(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    items
+}
+
+/// Items of the pre-Firefox 92 `webappsstore.sqlite`.
+fn read_webappsstore(path: &Path) -> Result<Vec<StorageItem>, NetError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("SELECT originKey, key, value FROM webappsstore2")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut items = Vec::new();
+    for (origin_key, key, value) in rows.flatten() {
+        if let Some(origin) = origin_from_origin_key(&origin_key) {
+            items.push(StorageItem { origin, key, value });
+        }
+    }
+    Ok(items)
+}
+
+/// Items of one origin's `ls/data.sqlite`. The origin is read from the
+/// database, or taken from `fallback` (the directory name).
+fn read_origin_db(path: &Path, fallback: Option<String>) -> Result<Vec<StorageItem>, NetError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let origin = conn
+        .query_row("SELECT origin FROM database", [], |row| {
+            row.get::<_, String>(0)
+        })
+        .ok()
+        .map(|origin| strip_origin_attributes(&origin))
+        .or(fallback)
+        .ok_or_else(|| NetError::cookie_invalid_data("storage database without origin"))?;
+
+    let mut stmt =
+        conn.prepare("SELECT key, value, conversion_type, compression_type FROM data")?;
+    let mut rows = stmt.query([])?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let raw = match row.get_ref(1)? {
+            ValueRef::Blob(b) | ValueRef::Text(b) => b.to_vec(),
+            _ => continue,
+        };
+        let conversion: i64 = row.get(2)?;
+        let compression: i64 = row.get(3)?;
+        if let Some(value) = decode_lsng_value(&raw, conversion, compression) {
+            items.push(StorageItem {
+                origin: origin.clone(),
+                key,
+                value,
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// Value of a `data` row: optionally Snappy-compressed (compression type
+/// 1), then UTF-8 (conversion type 1) or Latin-1 (conversion type 0).
+fn decode_lsng_value(raw: &[u8], conversion: i64, compression: i64) -> Option<String> {
+    let bytes = match compression {
+        0 => raw.to_vec(),
+        1 => snappy::decompress(raw)?,
+        _ => return None,
+    };
+    match conversion {
+        0 => Some(bytes.iter().map(|&b| b as char).collect()),
+        1 => String::from_utf8(bytes).ok(),
+        _ => None,
+    }
+}
+
+/// Origin of a `webappsstore2.originKey`, e.g. `moc.elpmaxe.:https:443`:
+/// the reversed host with a trailing dot, the scheme and the port.
+fn origin_from_origin_key(origin_key: &str) -> Option<String> {
+    let mut parts = origin_key.split(':');
+    let reversed_host = parts.next()?;
+    let scheme = parts.next()?;
+    let port = parts.next();
+    let host: String = reversed_host.chars().rev().collect();
+    let host = host.trim_start_matches('.');
+    if host.is_empty() || scheme.is_empty() {
+        return None;
+    }
+    Some(format_origin(scheme, host, port))
+}
+
+/// Origin of a `storage/default` directory name, e.g.
+/// `https+++example.com+8443^userContextId=1`, where `:` and `/` were
+/// replaced by `+`.
+fn origin_from_dir_name(name: &str) -> Option<String> {
+    let name = name.split('^').next()?;
+    let (scheme, host_port) = name.split_once("+++")?;
+    let (host, port) = match host_port.rsplit_once('+') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => (host, Some(port)),
+        _ => (host_port, None),
+    };
+    Some(format_origin(scheme, host, port))
+}
+
+/// Serialized origin, the scheme's default port omitted.
+fn format_origin(scheme: &str, host: &str, port: Option<&str>) -> String {
+    let default_port = match scheme {
+        "http" | "ws" => Some("80"),
+        "https" | "wss" => Some("443"),
+        _ => None,
+    };
+    match port {
+        Some(port) if !port.is_empty() && Some(port) != default_port => {
+            format!("{}://{}:{}", scheme, host, port)
+        }
+        _ => format!("{}://{}", scheme, host),
+    }
+}
+
+/// Origin without the `^userContextId=...` suffix of container tabs.
+fn strip_origin_attributes(origin: &str) -> String {
+    origin.split('^').next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_decoding() {
+        assert_eq!(
+            origin_from_origin_key("moc.elpmaxe.:https:443").as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            origin_from_origin_key("tsohlacol.:http:8080").as_deref(),
+            Some("http://localhost:8080")
+        );
+        assert_eq!(origin_from_origin_key("garbage"), None);
+        assert_eq!(
+            origin_from_dir_name("https+++example.com").as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            origin_from_dir_name("http+++localhost+8080^userContextId=2").as_deref(),
+            Some("http://localhost:8080")
+        );
+    }
+
+    #[test]
+    fn test_lsng_values() {
+        assert_eq!(
+            decode_lsng_value(b"caf\xc3\xa9", 1, 0).as_deref(),
+            Some("café")
+        );
+        assert_eq!(decode_lsng_value(b"caf\xe9", 0, 0).as_deref(), Some("café"));
+        // Snappy: length 5, literal "hello"
+        let compressed = [5, 0x10, b'h', b'e', b'l', b'l', b'o'];
+        assert_eq!(
+            decode_lsng_value(&compressed, 1, 1).as_deref(),
+            Some("hello")
+        );
+        assert_eq!(decode_lsng_value(b"x", 1, 9), None);
+    }
+
+    #[test]
+    fn test_local_storage_profile() {
+        let profile = tempfile::tempdir().unwrap();
+
+        let legacy = Connection::open(profile.path().join("webappsstore.sqlite")).unwrap();
+        legacy
+            .execute_batch(
+                "CREATE TABLE webappsstore2 (originAttributes TEXT, originKey TEXT, scope TEXT, key TEXT, value TEXT);
+                 INSERT INTO webappsstore2 VALUES ('', 'moc.elpmaxe.:https:443', '', 'token', 'stale');
+                 INSERT INTO webappsstore2 VALUES ('', 'moc.elpmaxe.:https:443', '', 'theme', 'dark');",
+            )
+            .unwrap();
+
+        let dir = profile
+            .path()
+            .join("storage/default/https+++example.com/ls");
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Connection::open(dir.join("data.sqlite")).unwrap();
+        db.execute_batch(
+            "CREATE TABLE database (origin TEXT NOT NULL, usage INTEGER NOT NULL DEFAULT 0);
+             INSERT INTO database (origin) VALUES ('https://example.com');
+             CREATE TABLE data (key TEXT PRIMARY KEY, utf16_length INTEGER NOT NULL,
+                 conversion_type INTEGER NOT NULL, compression_type INTEGER NOT NULL,
+                 last_access_time INTEGER NOT NULL DEFAULT 0, value BLOB NOT NULL);
+             INSERT INTO data VALUES ('token', 5, 1, 0, 0, X'6672657368');",
+        )
+        .unwrap();
+
+        let items = local_storage(profile.path()).unwrap();
+        let pairs: Vec<_> = items
+            .iter()
+            .map(|i| (i.origin.as_str(), i.key.as_str(), i.value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("https://example.com", "theme", "dark"),
+                ("https://example.com", "token", "fresh"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_session_items() {
+        let session = serde_json::json!({
+            "windows": [{
+                "tabs": [
+                    {"storage": {"https://example.com": {"csrf": "abc"}}},
+                    {"entries": []},
+                ]
+            }],
+            "_closedWindows": [{
+                "tabs": [{"storage": {"https://closed.test": {"k": "v"}}}]
+            }]
+        });
+        assert_eq!(
+            session_items(&session),
+            [StorageItem {
+                origin: "https://example.com".into(),
+                key: "csrf".into(),
+                value: "abc".into(),
+            }]
+        );
+    }
+}
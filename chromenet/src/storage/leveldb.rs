@@ -0,0 +1,354 @@
+//! Read-only LevelDB access, enough to dump a database a browser holds open.
+//!
+//! Every table (`.ldb`, `.sst`) and write-ahead log (`.log`) in the
+//! directory is read and the entry with the highest sequence number wins
+//! for each key. The manifest and the `LOCK` file are never touched, so a
+//! running browser does not get in the way. Checksums are not verified and
+//! malformed records are skipped.
+//!
+//! Format reference: <https://github.com/google/leveldb/tree/main/doc>.
+
+use super::snappy::{self, read_varint};
+use crate::base::neterror::NetError;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Log files are split into blocks of this size.
+const LOG_BLOCK_SIZE: usize = 32 * 1024;
+/// Log record header: checksum (4), length (2), type (1).
+const LOG_HEADER_SIZE: usize = 7;
+/// Table footer: two padded block handles and the magic number.
+const FOOTER_SIZE: usize = 48;
+const TABLE_MAGIC: u64 = 0xdb47_7524_8b80_fb57;
+/// Table block trailer: compression type (1), checksum (4).
+const BLOCK_TRAILER_SIZE: usize = 5;
+
+const TYPE_DELETION: u8 = 0;
+const TYPE_VALUE: u8 = 1;
+
+/// Newest sequence number and value (`None` if deleted) of each key.
+type Entries = HashMap<Vec<u8>, (u64, Option<Vec<u8>>)>;
+
+/// Live key/value pairs of the database in `dir`.
+pub fn read_dir(dir: &Path) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, NetError> {
+    let files =
+        std::fs::read_dir(dir).map_err(|_| NetError::cookie_db_not_found(dir.to_string_lossy()))?;
+
+    let mut entries = Entries::new();
+    for file in files.flatten() {
+        let path = file.path();
+        let extension = path.extension().and_then(|e| e.to_str());
+        // Files may be deleted by a compaction while we read
+        let Ok(data) = std::fs::read(&path) else {
+            continue;
+        };
+        match extension {
+            Some("log") => read_log(&data, &mut entries),
+            Some("ldb" | "sst") if read_table(&data, &mut entries).is_none() => {
+                tracing::debug!(path = %path.display(), "skipping malformed leveldb table");
+            }
+            _ => {}
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|(key, (_, value))| Some((key, value?)))
+        .collect())
+}
+
+fn insert(entries: &mut Entries, key: Vec<u8>, seq: u64, value: Option<Vec<u8>>) {
+    match entries.get(&key) {
+        Some((newest, _)) if *newest >= seq => {}
+        _ => {
+            entries.insert(key, (seq, value));
+        }
+    }
+}
+
+/// Apply the write batches of a write-ahead log.
+fn read_log(data: &[u8], entries: &mut Entries) {
+    let mut record = Vec::new();
+    let mut in_record = false;
+
+    for block in data.chunks(LOG_BLOCK_SIZE) {
+        let mut pos = 0;
+        while pos + LOG_HEADER_SIZE <= block.len() {
+            let len = u16::from_le_bytes([block[pos + 4], block[pos + 5]]) as usize;
+            let kind = block[pos + 6];
+            let Some(payload) = block.get(pos + LOG_HEADER_SIZE..pos + LOG_HEADER_SIZE + len)
+            else {
+                break;
+            };
+            pos += LOG_HEADER_SIZE + len;
+
+            match kind {
+                // Full
+                1 => {
+                    apply_batch(payload, entries);
+                    in_record = false;
+                }
+                // First
+                2 => {
+                    record.clear();
+                    record.extend_from_slice(payload);
+                    in_record = true;
+                }
+                // Middle
+                3 if in_record => record.extend_from_slice(payload),
+                // Last
+                4 if in_record => {
+                    record.extend_from_slice(payload);
+                    apply_batch(&record, entries);
+                    in_record = false;
+                }
+                // Zero-filled tail of a preallocated block
+                0 => break,
+                // A fragment whose start was lost
+                _ => in_record = false,
+            }
+        }
+    }
+}
+
+/// Apply one `WriteBatch`: sequence (8), count (4), then the operations.
+fn apply_batch(batch: &[u8], entries: &mut Entries) -> Option<()> {
+    let seq = u64::from_le_bytes(batch.get(..8)?.try_into().ok()?);
+    let count = u32::from_le_bytes(batch.get(8..12)?.try_into().ok()?);
+    let mut pos = 12;
+    for i in 0..u64::from(count) {
+        let kind = *batch.get(pos)?;
+        pos += 1;
+        let key = read_slice(batch, &mut pos)?.to_vec();
+        let value = match kind {
+            TYPE_VALUE => Some(read_slice(batch, &mut pos)?.to_vec()),
+            TYPE_DELETION => None,
+            _ => return None,
+        };
+        insert(entries, key, seq + i, value);
+    }
+    Some(())
+}
+
+/// Apply the entries of a sorted table file.
+fn read_table(data: &[u8], entries: &mut Entries) -> Option<()> {
+    let footer = data.get(data.len().checked_sub(FOOTER_SIZE)?..)?;
+    if footer[40..] != TABLE_MAGIC.to_le_bytes() {
+        return None;
+    }
+    let mut pos = 0;
+    let _metaindex = read_handle(footer, &mut pos)?;
+    let index = read_handle(footer, &mut pos)?;
+
+    for (_, handle) in block_entries(&read_block(data, index)?)? {
+        let handle = read_handle(&handle, &mut 0)?;
+        for (key, value) in block_entries(&read_block(data, handle)?)? {
+            // Internal key: user key, then (sequence << 8 | type)
+            let split = key.len().checked_sub(8)?;
+            let tag = u64::from_le_bytes(key[split..].try_into().ok()?);
+            let value = match (tag & 0xff) as u8 {
+                TYPE_VALUE => Some(value),
+                TYPE_DELETION => None,
+                _ => continue,
+            };
+            insert(entries, key[..split].to_vec(), tag >> 8, value);
+        }
+    }
+    Some(())
+}
+
+/// Block handle: offset and size varints.
+fn read_handle(data: &[u8], pos: &mut usize) -> Option<(usize, usize)> {
+    let (offset, n) = read_varint(data.get(*pos..)?)?;
+    *pos += n;
+    let (size, n) = read_varint(data.get(*pos..)?)?;
+    *pos += n;
+    Some((usize::try_from(offset).ok()?, usize::try_from(size).ok()?))
+}
+
+/// Contents of the block at `handle`, decompressed.
+fn read_block(data: &[u8], (offset, size): (usize, usize)) -> Option<Vec<u8>> {
+    let end = offset.checked_add(size)?;
+    let raw = data.get(offset..end)?;
+    let trailer = data.get(end..end + BLOCK_TRAILER_SIZE)?;
+    match trailer[0] {
+        0 => Some(raw.to_vec()),
+        1 => snappy::decompress(raw),
+        _ => None,
+    }
+}
+
+/// Key/value pairs of a block, prefix-compressed keys expanded.
+fn block_entries(block: &[u8]) -> Option<Vec<(Vec<u8>, Vec<u8>)>> {
+    let restarts_at = block.len().checked_sub(4)?;
+    let num_restarts = u32::from_le_bytes(block[restarts_at..].try_into().ok()?) as usize;
+    let end = restarts_at.checked_sub(num_restarts.checked_mul(4)?)?;
+
+    let mut out = Vec::new();
+    let mut key = Vec::new();
+    let mut pos = 0;
+    while pos < end {
+        let mut varint = || {
+            let (value, n) = read_varint(block.get(pos..end)?)?;
+            pos += n;
+            usize::try_from(value).ok()
+        };
+        let shared = varint()?;
+        let non_shared = varint()?;
+        let value_len = varint()?;
+        if shared > key.len() {
+            return None;
+        }
+        key.truncate(shared);
+        key.extend_from_slice(block.get(pos..pos + non_shared)?);
+        pos += non_shared;
+        out.push((key.clone(), block.get(pos..pos + value_len)?.to_vec()));
+        pos += value_len;
+    }
+    Some(out)
+}
+
+/// Varint-length-prefixed slice.
+fn read_slice<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    let (len, n) = read_varint(data.get(*pos..)?)?;
+    let start = *pos + n;
+    let slice = data.get(start..start.checked_add(usize::try_from(len).ok()?)?)?;
+    *pos = start + slice.len();
+    Some(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::error::CookieExtractionError;
+
+    /// Sequence number and operations; `None` deletes the key.
+    type Batch<'a> = (u64, &'a [(&'a [u8], Option<&'a [u8]>)]);
+
+    fn push_varint(out: &mut Vec<u8>, mut value: usize) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    /// A log holding one write batch per element of `batches`.
+    fn log_file(batches: &[Batch<'_>]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (seq, ops) in batches {
+            let mut batch = seq.to_le_bytes().to_vec();
+            batch.extend((ops.len() as u32).to_le_bytes());
+            for (key, value) in *ops {
+                batch.push(if value.is_some() {
+                    TYPE_VALUE
+                } else {
+                    TYPE_DELETION
+                });
+                push_varint(&mut batch, key.len());
+                batch.extend_from_slice(key);
+                if let Some(value) = value {
+                    push_varint(&mut batch, value.len());
+                    batch.extend_from_slice(value);
+                }
+            }
+            out.extend([0u8; 4]);
+            out.extend((batch.len() as u16).to_le_bytes());
+            out.push(1);
+            out.extend(batch);
+        }
+        out
+    }
+
+    /// A table with one uncompressed data block of `entries` (sorted, with
+    /// sequence number `seq`).
+    fn table_file(seq: u64, entries: &[(&[u8], &[u8])]) -> Vec<u8> {
+        fn block(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+            let mut out = Vec::new();
+            for (key, value) in entries {
+                push_varint(&mut out, 0);
+                push_varint(&mut out, key.len());
+                push_varint(&mut out, value.len());
+                out.extend_from_slice(key);
+                out.extend_from_slice(value);
+            }
+            out.extend(0u32.to_le_bytes());
+            out.extend(1u32.to_le_bytes());
+            out
+        }
+
+        let data: Vec<_> = entries
+            .iter()
+            .map(|(key, value)| {
+                let tag = (seq << 8) | u64::from(TYPE_VALUE);
+                ([*key, &tag.to_le_bytes()].concat(), value.to_vec())
+            })
+            .collect();
+        let mut file = block(&data);
+        let data_size = file.len();
+        file.extend([0u8; BLOCK_TRAILER_SIZE]);
+
+        let mut handle = Vec::new();
+        push_varint(&mut handle, 0);
+        push_varint(&mut handle, data_size);
+        let index_offset = file.len();
+        let index = block(&[(data.last().unwrap().0.clone(), handle)]);
+        file.extend(&index);
+        file.extend([0u8; BLOCK_TRAILER_SIZE]);
+
+        let mut footer = vec![0, 0];
+        push_varint(&mut footer, index_offset);
+        push_varint(&mut footer, index.len());
+        footer.resize(40, 0);
+        footer.extend(TABLE_MAGIC.to_le_bytes());
+        file.extend(footer);
+        file
+    }
+
+    #[test]
+    fn test_newest_entry_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("000005.ldb"),
+            table_file(1, &[(b"a", b"old"), (b"b", b"kept"), (b"c", b"deleted")]),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("000007.log"),
+            log_file(&[
+                (2, &[(b"a", Some(b"new")), (b"c", None)]),
+                (4, &[(b"d", Some(b"added"))]),
+            ]),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("LOCK"), b"").unwrap();
+
+        let db = read_dir(dir.path()).unwrap();
+        let entries: Vec<_> = db
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+        assert_eq!(
+            entries,
+            [(&b"a"[..], &b"new"[..]), (b"b", b"kept"), (b"d", b"added")]
+        );
+    }
+
+    #[test]
+    fn test_snappy_block() {
+        let compressed = [5, 0x10, b'h', b'e', b'l', b'l', b'o'];
+        let mut data = compressed.to_vec();
+        data.extend([1, 0, 0, 0, 0]);
+        assert_eq!(read_block(&data, (0, compressed.len())).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_missing_dir() {
+        assert!(matches!(
+            read_dir(Path::new("/nonexistent/leveldb")),
+            Err(NetError::CookieExtraction(
+                CookieExtractionError::DatabaseNotFound(_)
+            ))
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//! LZ4 block decompression, for Firefox's `.jsonlz4` session files.
+//!
+//! Firefox wraps a raw LZ4 block ("mozLz4"): the magic `mozLz40\0`, the
+//! decompressed size as a little-endian `u32`, then the block. See
+//! <https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md>.
+
+/// Magic number of Firefox's LZ4 files.
+pub const MOZLZ4_MAGIC: &[u8; 8] = b"mozLz40\0";
+
+/// Largest output accepted; session files are a few MiB at most.
+const MAX_UNCOMPRESSED: usize = 256 * 1024 * 1024;
+
+/// Decompress a `.jsonlz4`/`.mozlz4` file. Returns `None` if it is malformed.
+pub fn decompress_mozlz4(data: &[u8]) -> Option<Vec<u8>> {
+    let rest = data.strip_prefix(MOZLZ4_MAGIC)?;
+    let size = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+    decompress_block(rest.get(4..)?, size)
+}
+
+/// Decompress a raw LZ4 block of `size` decompressed bytes.
+pub fn decompress_block(input: &[u8], size: usize) -> Option<Vec<u8>> {
+    if size > MAX_UNCOMPRESSED {
+        return None;
+    }
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 0;
+
+    loop {
+        let token = *input.get(pos)?;
+        pos += 1;
+
+        let literal_len = read_length(input, &mut pos, (token >> 4) as usize)?;
+        let literal = input.get(pos..pos.checked_add(literal_len)?)?;
+        out.extend_from_slice(literal);
+        pos += literal_len;
+        // The last sequence has literals only
+        if pos == input.len() {
+            break;
+        }
+
+        let offset = u16::from_le_bytes(input.get(pos..pos + 2)?.try_into().ok()?) as usize;
+        pos += 2;
+        let match_len = read_length(input, &mut pos, (token & 0x0f) as usize)? + 4;
+        if offset == 0 || offset > out.len() || out.len() + match_len > size {
+            return None;
+        }
+        // Byte by byte, as the match may overlap the bytes being written
+        let start = out.len() - offset;
+        for i in 0..match_len {
+            out.push(out[start + i]);
+        }
+    }
+
+    (out.len() == size).then_some(out)
+}
+
+/// A 4-bit length, extended by following bytes while it saturates.
+fn read_length(input: &[u8], pos: &mut usize, mut len: usize) -> Option<usize> {
+    if len == 15 {
+        loop {
+            let byte = *input.get(*pos)?;
+            *pos += 1;
+            len = len.checked_add(byte as usize)?;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mozlz4() {
+        // "abcabcabcabc!": literal "abc", match of 9 at offset 3, literal "!"
+        let mut file = MOZLZ4_MAGIC.to_vec();
+        file.extend(13u32.to_le_bytes());
+        file.extend([0x35, b'a', b'b', b'c', 3, 0, 0x10, b'!']);
+        assert_eq!(decompress_mozlz4(&file).unwrap(), b"abcabcabcabc!");
+    }
+
+    #[test]
+    fn test_long_literal() {
+        let text = [b'x'; 300];
+        let mut block = vec![0xf0, 255, 30];
+        block.extend_from_slice(&text);
+        assert_eq!(decompress_block(&block, 300).unwrap(), text);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(decompress_mozlz4(b"notlz4").is_none());
+        // Match before any output
+        assert!(decompress_block(&[0x00, 1, 0, 0x10, b'!'], 5).is_none());
+        // Size mismatch
+        assert!(decompress_block(&[0x10, b'a'], 2).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+//! Web Storage extraction from installed browsers.
+//!
+//! Reads the `localStorage` and `sessionStorage` data sites keep in the
+//! browser profile, e.g. to recover tokens that never reach a cookie.
+//! Profiles are found the same way as for [`BrowserCookieReader`].
+//!
+//! | Browser | `localStorage` | `sessionStorage` |
+//! |---------|----------------|------------------|
+//! | Chromium-based | `Local Storage/leveldb` | `Session Storage` (LevelDB) |
+//! | Firefox | `storage/default/*/ls/data.sqlite`, legacy `webappsstore.sqlite` | `sessionstore-backups/recovery.jsonlz4` |
+//! | Safari | unsupported | unsupported |
+//!
+//! The databases are read while the browser runs; data the browser has not
+//! flushed yet is missing.
+//!
+//! ```rust,no_run
+//! use chromenet::cookies::browser::Browser;
+//! use chromenet::storage::BrowserStorageReader;
+//!
+//! let reader = BrowserStorageReader::new(Browser::Chrome).origin("https://example.com");
+//! for item in reader.read_local_storage().unwrap_or_default() {
+//!     println!("{} = {}", item.key, item.value);
+//! }
+//! ```
+
+pub mod chromium;
+pub mod firefox;
+pub mod leveldb;
+pub mod lz4;
+pub mod snappy;
+
+use crate::base::neterror::NetError;
+use crate::cookies::browser::{Browser, BrowserCookieReader};
+use std::path::PathBuf;
+
+/// Web Storage area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageArea {
+    /// `localStorage`, kept across sessions.
+    Local,
+    /// `sessionStorage`, per tab. Items of the same origin in several tabs
+    /// are reported once.
+    Session,
+}
+
+/// One key/value pair of an origin's storage.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StorageItem {
+    /// Serialized origin without a trailing slash, e.g.
+    /// `https://example.com` or `http://localhost:8080`.
+    pub origin: String,
+    /// Storage key.
+    pub key: String,
+    /// Stored value.
+    pub value: String,
+}
+
+/// Reads a browser profile's Web Storage.
+#[derive(Debug, Clone)]
+pub struct BrowserStorageReader {
+    browser: Browser,
+    profile: Option<String>,
+    origin: Option<String>,
+}
+
+impl BrowserStorageReader {
+    /// Reader for `browser`'s default profile.
+    pub fn new(browser: Browser) -> Self {
+        Self {
+            browser,
+            profile: None,
+            origin: None,
+        }
+    }
+
+    /// Read `profile` instead of the default one; see
+    /// [`BrowserCookieReader::list_profiles`].
+    pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Return only the items of `origin`, e.g. `https://example.com`.
+    pub fn origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Directory of the selected profile.
+    pub fn profile_dir(&self) -> Option<PathBuf> {
+        let mut reader = BrowserCookieReader::new(self.browser);
+        if let Some(profile) = &self.profile {
+            reader = reader.with_profile(profile.clone());
+        }
+        reader.profile_dir()
+    }
+
+    /// Items of `localStorage`.
+    pub fn read_local_storage(&self) -> Result<Vec<StorageItem>, NetError> {
+        self.read(StorageArea::Local)
+    }
+
+    /// Items of `sessionStorage`.
+    pub fn read_session_storage(&self) -> Result<Vec<StorageItem>, NetError> {
+        self.read(StorageArea::Session)
+    }
+
+    /// Items of `area`, sorted by origin and key.
+    pub fn read(&self, area: StorageArea) -> Result<Vec<StorageItem>, NetError> {
+        if self.browser == Browser::Safari {
+            return Err(NetError::cookie_platform_not_supported(
+                "Safari web storage",
+            ));
+        }
+        let profile_dir = self
+            .profile_dir()
+            .ok_or_else(|| NetError::browser_not_found(format!("{:?}", self.browser)))?;
+
+        let mut items = match (self.browser, area) {
+            (Browser::Firefox, StorageArea::Local) => firefox::local_storage(&profile_dir)?,
+            (Browser::Firefox, StorageArea::Session) => firefox::session_storage(&profile_dir)?,
+            (_, StorageArea::Local) => chromium::local_storage(&leveldb::read_dir(
+                &profile_dir.join("Local Storage").join("leveldb"),
+            )?),
+            (_, StorageArea::Session) => {
+                chromium::session_storage(&leveldb::read_dir(&profile_dir.join("Session Storage"))?)
+            }
+        };
+
+        if let Some(origin) = &self.origin {
+            items.retain(|item| item.origin == *origin);
+        }
+        items.sort();
+        items.dedup();
+        Ok(items)
+    }
+
+    /// Origins with items in `area`.
+    pub fn list_origins(&self, area: StorageArea) -> Result<Vec<String>, NetError> {
+        let mut origins: Vec<_> = self.read(area)?.into_iter().map(|i| i.origin).collect();
+        origins.dedup();
+        Ok(origins)
+    }
+}
@@ -0,0 +1,115 @@
+//! Snappy block decompression, for compressed LevelDB table blocks.
+//!
+//! Implements the raw (unframed) format from
+//! <https://github.com/google/snappy/blob/main/format_description.txt>.
+
+/// Largest output accepted, well above LevelDB's 4 KiB blocks.
+const MAX_UNCOMPRESSED: usize = 64 * 1024 * 1024;
+
+/// Decompress a raw Snappy block. Returns `None` if it is malformed.
+pub fn decompress(input: &[u8]) -> Option<Vec<u8>> {
+    let (len, mut pos) = read_varint(input)?;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|&l| l <= MAX_UNCOMPRESSED)?;
+    let mut out = Vec::with_capacity(len);
+
+    while pos < input.len() {
+        let tag = input[pos];
+        pos += 1;
+        let (copy_len, offset) = match tag & 0x03 {
+            0 => {
+                let mut literal_len = (tag >> 2) as usize;
+                if literal_len >= 60 {
+                    let extra = literal_len - 59;
+                    literal_len = le_uint(input.get(pos..pos + extra)?);
+                    pos += extra;
+                }
+                let literal = input.get(pos..pos + literal_len + 1)?;
+                out.extend_from_slice(literal);
+                pos += literal.len();
+                continue;
+            }
+            1 => {
+                let low = *input.get(pos)? as usize;
+                pos += 1;
+                (
+                    ((tag >> 2) & 0x07) as usize + 4,
+                    ((tag as usize >> 5) << 8) | low,
+                )
+            }
+            2 => {
+                let offset = le_uint(input.get(pos..pos + 2)?);
+                pos += 2;
+                ((tag >> 2) as usize + 1, offset)
+            }
+            _ => {
+                let offset = le_uint(input.get(pos..pos + 4)?);
+                pos += 4;
+                ((tag >> 2) as usize + 1, offset)
+            }
+        };
+        if offset == 0 || offset > out.len() || out.len() + copy_len > len {
+            return None;
+        }
+        // Byte by byte, as the source may overlap the bytes being written
+        let start = out.len() - offset;
+        for i in 0..copy_len {
+            out.push(out[start + i]);
+        }
+    }
+
+    (out.len() == len).then_some(out)
+}
+
+/// LEB128 varint, as used by Snappy and LevelDB. Returns the value and the
+/// number of bytes read.
+pub fn read_varint(input: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in input.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn le_uint(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, &b| (acc << 8) | b as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_and_copies() {
+        // "abcd" then "abcdabcd" copied from 4 back (overlapping), then "!"
+        let input = [
+            13, // uncompressed length
+            0x0c, b'a', b'b', b'c', b'd', // literal, 4 bytes
+            0x11, 0x04, // copy 8 bytes, 1-byte offset 4
+            0x00, b'!', // literal, 1 byte
+        ];
+        assert_eq!(decompress(&input).unwrap(), b"abcdabcdabcd!");
+
+        // 2-byte offset form
+        let input = [6, 0x08, b'x', b'y', b'z', 0x0a, 0x03, 0x00];
+        assert_eq!(decompress(&input).unwrap(), b"xyzxyz");
+    }
+
+    #[test]
+    fn test_malformed() {
+        // Length mismatch
+        assert!(decompress(&[5, 0x00, b'a']).is_none());
+        // Copy before any output
+        assert!(decompress(&[4, 0x01, 0x01]).is_none());
+        // Truncated literal
+        assert!(decompress(&[4, 0x0c, b'a']).is_none());
+        assert!(decompress(&[]).is_none());
+    }
+}
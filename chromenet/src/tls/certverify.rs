@@ -0,0 +1,266 @@
+//! Per-request results of certificate verification.
+//!
+//! Chromium mapping: `net::CertVerifyResult`, with the pinning and CT
+//! checks of `TransportSecurityState`
+//!
+//! BoringSSL verifies the chain and the hostname during the handshake and
+//! fails it on an untrusted chain or a name mismatch. A [`CertPolicy`] adds
+//! the pins of a [`PinStore`] and the CT policy of a [`MultiLogCtVerifier`],
+//! checked on each request's connection before the request is sent. What
+//! every check found is returned with the response as a
+//! [`CertVerifyResult`], see
+//! [`HttpResponse::cert_verify_result`](crate::http::HttpResponse::cert_verify_result),
+//! so callers can make their own trust decisions or feed a dashboard
+//! without another handshake:
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), chromenet::base::neterror::NetError> {
+//! use chromenet::tls::certverify::CertPolicy;
+//! use chromenet::tls::{MultiLogCtVerifier, PinStore};
+//!
+//! let client = chromenet::Client::builder()
+//!     .cert_policy(
+//!         CertPolicy::new()
+//!             .with_pins(PinStore::new())
+//!             .with_ct_verifier(MultiLogCtVerifier::new()),
+//!     )
+//!     .build();
+//! let response = client.get("https://example.com/").send().await?;
+//! if let Some(result) = response.cert_verify_result() {
+//!     println!("{:?} {:?} {:?}", result.pinning, result.ct, result.ocsp);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::base::host::ip_literal;
+use crate::base::neterror::NetError;
+use crate::socket::tls::SslInfo;
+use crate::tls::ct::{Sct, SctStatus};
+use crate::tls::ctverifier::MultiLogCtVerifier;
+use crate::tls::pinning::{PinStore, PinningResult, SpkiHash};
+use boring::x509::X509;
+use std::fmt;
+use std::sync::Arc;
+
+/// Outcome of the CT policy for a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtResult {
+    /// The policy has no CT verifier.
+    NotChecked,
+    /// At least one SCT verified against a known log.
+    Compliant,
+    /// No SCT verified. The connection only fails if CT is
+    /// [required](crate::tls::CtRequirement::Required).
+    NotCompliant,
+}
+
+/// OCSP stapled to the handshake.
+///
+/// The response is not parsed; revocation is left to the caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcspStatus {
+    NotStapled,
+    /// The DER `OCSPResponse` the server sent.
+    Stapled(Vec<u8>),
+}
+
+/// What the checks of a request's connection found.
+#[derive(Debug, Clone)]
+pub struct CertVerifyResult {
+    /// Whether the chain verified to a trusted root.
+    pub chain_valid: bool,
+    /// Why the chain did not verify.
+    pub verify_error: Option<String>,
+    /// Whether the leaf certificate is valid for the request's host.
+    pub hostname_matches: bool,
+    /// SPKI SHA-256 hashes of the served chain, leaf first.
+    pub public_key_hashes: Vec<SpkiHash>,
+    pub pinning: PinningResult,
+    pub ct: CtResult,
+    /// The leaf's embedded SCTs and what verifying each found; empty
+    /// without a CT verifier.
+    pub scts: Vec<(Sct, SctStatus)>,
+    pub ocsp: OcspStatus,
+}
+
+impl CertVerifyResult {
+    /// Whether no check found a problem; a report-only pin mismatch or
+    /// non-compliant CT counts as one.
+    pub fn is_trusted(&self) -> bool {
+        self.chain_valid
+            && self.hostname_matches
+            && matches!(
+                self.pinning,
+                PinningResult::NotPinned | PinningResult::Matched
+            )
+            && self.ct != CtResult::NotCompliant
+    }
+}
+
+/// Checks applied to the connection of every request beyond the
+/// handshake's own.
+#[derive(Clone, Default)]
+pub struct CertPolicy {
+    pins: Option<PinStore>,
+    ct_verifier: Option<Arc<MultiLogCtVerifier>>,
+}
+
+impl fmt::Debug for CertPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertPolicy")
+            .field("pins", &self.pins)
+            .field("ct_logs", &self.ct_verifier.as_ref().map(|v| v.log_count()))
+            .finish()
+    }
+}
+
+impl CertPolicy {
+    /// A policy without pins or CT checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check connections against the pins in `pins`. Mismatches fail the
+    /// request unless the pin set is report-only.
+    pub fn with_pins(mut self, pins: PinStore) -> Self {
+        self.pins = Some(pins);
+        self
+    }
+
+    /// Verify the SCTs of connections against `verifier`'s logs, failing
+    /// requests as its [requirement](crate::tls::CtRequirement) says.
+    pub fn with_ct_verifier(mut self, verifier: MultiLogCtVerifier) -> Self {
+        self.ct_verifier = Some(Arc::new(verifier));
+        self
+    }
+
+    /// Check the connection to `host:port` that `info` describes.
+    ///
+    /// Fails with [`NetError::CertPinningFailed`] on an enforced pin
+    /// mismatch and with [`NetError::CertificateTransparencyRequired`]
+    /// when required CT is missing.
+    pub fn verify(
+        &self,
+        host: &str,
+        port: u16,
+        info: &SslInfo,
+    ) -> Result<CertVerifyResult, NetError> {
+        let pinning = self.pins.as_ref().map_or(PinningResult::NotPinned, |pins| {
+            pins.verify(host, port, &info.public_key_hashes, &info.peer_certificates)
+        });
+        pinning.into_result()?;
+
+        let (ct, scts) = match &self.ct_verifier {
+            None => (CtResult::NotChecked, Vec::new()),
+            Some(verifier) => {
+                let scts = info.verify_scts(verifier);
+                verifier.check_requirements(&scts)?;
+                let ct = if scts.iter().any(|(_, status)| *status == SctStatus::Valid) {
+                    CtResult::Compliant
+                } else {
+                    CtResult::NotCompliant
+                };
+                (ct, scts)
+            }
+        };
+
+        Ok(CertVerifyResult {
+            chain_valid: info.verify_error.is_none(),
+            verify_error: info.verify_error.clone(),
+            hostname_matches: info
+                .leaf_certificate()
+                .is_some_and(|leaf| leaf_matches(leaf, host)),
+            public_key_hashes: info.public_key_hashes.clone(),
+            pinning,
+            ct,
+            scts,
+            ocsp: match &info.ocsp_response {
+                Some(response) => OcspStatus::Stapled(response.clone()),
+                None => OcspStatus::NotStapled,
+            },
+        })
+    }
+}
+
+/// Whether the DER certificate `leaf` names `host`, a DNS name or an IP
+/// literal.
+fn leaf_matches(leaf: &[u8], host: &str) -> bool {
+    let Ok(cert) = X509::from_der(leaf) else {
+        return false;
+    };
+    match ip_literal(host) {
+        Some(ip) => cert.check_ip_asc(&ip.to_string()),
+        None => cert.check_host(host.trim_end_matches('.')),
+    }
+    .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::pinning::PinSet;
+
+    fn info() -> SslInfo {
+        SslInfo {
+            public_key_hashes: vec![[1u8; 32], [2u8; 32]],
+            ocsp_response: Some(vec![0x30, 0x03]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_verify_without_checks() {
+        let result = CertPolicy::new().verify("a.test", 443, &info()).unwrap();
+        assert!(result.chain_valid);
+        assert_eq!(result.pinning, PinningResult::NotPinned);
+        assert_eq!(result.ct, CtResult::NotChecked);
+        assert_eq!(result.ocsp, OcspStatus::Stapled(vec![0x30, 0x03]));
+        // No leaf certificate to match the host
+        assert!(!result.hostname_matches);
+        assert!(!result.is_trusted());
+    }
+
+    #[test]
+    fn test_verify_pins() {
+        let pins = PinStore::new();
+        let mut pin_set = PinSet::new("a.test");
+        pin_set.add_pin([2u8; 32]);
+        pins.add(pin_set);
+        let mut pin_set = PinSet::new("b.test").report_only(true);
+        pin_set.add_pin([3u8; 32]);
+        pins.add(pin_set);
+        let mut pin_set = PinSet::new("c.test");
+        pin_set.add_pin([3u8; 32]);
+        pins.add(pin_set);
+        let policy = CertPolicy::new().with_pins(pins);
+
+        let result = policy.verify("a.test", 443, &info()).unwrap();
+        assert_eq!(result.pinning, PinningResult::Matched);
+        let result = policy.verify("b.test", 443, &info()).unwrap();
+        assert_eq!(result.pinning, PinningResult::ReportOnlyMismatch);
+        assert!(matches!(
+            policy.verify("c.test", 443, &info()),
+            Err(NetError::CertPinningFailed)
+        ));
+    }
+
+    #[test]
+    fn test_verify_ct() {
+        let verifier = MultiLogCtVerifier::new();
+        let result = CertPolicy::new()
+            .with_ct_verifier(verifier)
+            .verify("a.test", 443, &info())
+            .unwrap();
+        assert_eq!(result.ct, CtResult::NotCompliant);
+
+        let required =
+            MultiLogCtVerifier::new().with_requirement(crate::tls::CtRequirement::Required);
+        assert!(matches!(
+            CertPolicy::new()
+                .with_ct_verifier(required)
+                .verify("a.test", 443, &info()),
+            Err(NetError::CertificateTransparencyRequired)
+        ));
+    }
+}
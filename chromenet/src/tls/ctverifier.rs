@@ -8,6 +8,7 @@
 //! Google publishes the list of known logs at:
 //! https://www.gstatic.com/ct/log_list/v3/all_logs_list.json
 
+use crate::base::clock::{system_clock, Clock};
 use crate::base::neterror::NetError;
 use crate::tls::ct::{CtRequirement, Sct, SctStatus};
 use dashmap::DashMap;
@@ -62,6 +63,8 @@ pub struct MultiLogCtVerifier {
     logs: Arc<DashMap<[u8; 32], CtLog>>,
     /// CT requirement level
     requirement: CtRequirement,
+    /// Time SCT timestamps are checked against in `SslInfo::verify_scts`
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for MultiLogCtVerifier {
@@ -76,9 +79,23 @@ impl MultiLogCtVerifier {
         Self {
             logs: Arc::new(DashMap::new()),
             requirement: CtRequirement::SoftFail,
+            clock: system_clock(),
         }
     }
 
+    /// Check SCT timestamps against `clock` instead of the system time,
+    /// e.g. a [`MockClock`](crate::base::clock::MockClock) in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The current time by the verifier's clock, to pass to
+    /// [`verify`](Self::verify).
+    pub fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::from(self.clock.now())
+    }
+
     /// Set the CT requirement level.
     pub fn with_requirement(mut self, requirement: CtRequirement) -> Self {
         self.requirement = requirement;
@@ -283,6 +300,7 @@ fn decode_single_sct(data: &[u8]) -> Option<Sct> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::base::clock::MockClock;
 
     fn create_test_log() -> CtLog {
         let mut id = [0u8; 32];
@@ -338,6 +356,28 @@ mod tests {
         assert_eq!(results[0].1, SctStatus::Valid);
     }
 
+    #[test]
+    fn test_timestamp_checked_against_clock() {
+        let start = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let clock = MockClock::new(start.into());
+        let verifier = MultiLogCtVerifier::new().with_clock(Arc::new(clock.clone()));
+        let log = create_test_log();
+        let log_id = log.id;
+        verifier.add_log(log);
+
+        let sct = Sct {
+            log_id,
+            timestamp: start + time::Duration::seconds(10),
+            signature: vec![0x01, 0x02],
+        };
+        let results = verifier.verify(&[sct.clone()], &[], verifier.now());
+        assert_eq!(results[0].1, SctStatus::FutureTimestamp);
+
+        clock.advance(std::time::Duration::from_secs(10));
+        let results = verifier.verify(&[sct], &[], verifier.now());
+        assert_eq!(results[0].1, SctStatus::Valid);
+    }
+
     #[test]
     fn test_future_timestamp() {
         let verifier = MultiLogCtVerifier::new();
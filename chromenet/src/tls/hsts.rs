@@ -3,13 +3,23 @@
 //! Enforces HTTPS for domains that require it, supporting both:
 //! - Static preload list (hardcoded domains)
 //! - Dynamic HSTS headers from Strict-Transport-Security
+//! - Entries imported from a Chrome profile's `TransportSecurity` file
 //!
 //! Based on Chromium's TransportSecurityState.
 
+use crate::base::clock::{system_clock, Clock};
+use crate::base::host::{host_key, ip_literal};
+use crate::tls::persister::DirtySignal;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use dashmap::DashMap;
 use std::sync::Arc;
 use time::{Duration, OffsetDateTime};
 
+/// SHA-256 of a host in DNS wire format, as Chrome keys its dynamic
+/// transport security state.
+type HostHash = [u8; 32];
+
 /// HSTS entry for a domain.
 #[derive(Debug, Clone)]
 pub struct HstsEntry {
@@ -40,11 +50,12 @@ impl HstsEntry {
 
     /// Check if this entry has expired.
     pub fn is_expired(&self) -> bool {
-        if let Some(exp) = self.expires {
-            OffsetDateTime::now_utc() > exp
-        } else {
-            false
-        }
+        self.is_expired_at(OffsetDateTime::now_utc())
+    }
+
+    /// Check if this entry has expired at `now`.
+    pub fn is_expired_at(&self, now: OffsetDateTime) -> bool {
+        self.expires.is_some_and(|exp| now > exp)
     }
 }
 
@@ -52,6 +63,12 @@ impl HstsEntry {
 #[derive(Clone)]
 pub struct HstsStore {
     entries: Arc<DashMap<String, HstsEntry>>,
+    /// Entries imported from Chrome, which stores hashes instead of hosts
+    hashed: Arc<DashMap<HostHash, HstsEntry>>,
+    /// Raised when dynamic entries change, for the persister
+    dirty: Arc<DirtySignal>,
+    /// Time that max-age and expiry are measured against
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for HstsStore {
@@ -65,9 +82,24 @@ impl HstsStore {
     pub fn new() -> Self {
         Self {
             entries: Arc::new(DashMap::new()),
+            hashed: Arc::new(DashMap::new()),
+            dirty: Arc::new(DirtySignal::default()),
+            clock: system_clock(),
         }
     }
 
+    /// Measure max-age and expiry against `clock` instead of the system
+    /// time, e.g. a [`MockClock`](crate::base::clock::MockClock) in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The current time by the store's clock.
+    pub(crate) fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::from(self.clock.now())
+    }
+
     /// Create an HSTS store with common preloaded domains.
     pub fn with_preload() -> Self {
         let store = Self::new();
@@ -95,21 +127,25 @@ impl HstsStore {
 
     /// Add a preloaded (permanent) HSTS entry.
     pub fn add_preloaded(&self, domain: &str, include_subdomains: bool) {
-        self.entries.insert(
-            domain.to_lowercase(),
-            HstsEntry::preloaded(include_subdomains),
-        );
+        self.entries
+            .insert(host_key(domain), HstsEntry::preloaded(include_subdomains));
     }
 
     /// Check if a host should be upgraded to HTTPS.
     ///
     /// Chromium: net/http/transport_security_state.cc
+    ///
+    /// IP literals are never upgraded.
     pub fn should_upgrade(&self, host: &str) -> bool {
-        let host_lower = host.to_lowercase();
+        if ip_literal(host).is_some() {
+            return false;
+        }
+        let host_lower = host_key(host);
+        let now = self.now();
 
         // Check exact match
         if let Some(entry) = self.entries.get(&host_lower) {
-            if !entry.is_expired() {
+            if !entry.is_expired_at(now) {
                 return true;
             }
         }
@@ -123,18 +159,44 @@ impl HstsStore {
             }
             current = &current[idx + 1..];
             if let Some(entry) = self.entries.get(current) {
-                if !entry.is_expired() && entry.include_subdomains {
+                if !entry.is_expired_at(now) && entry.include_subdomains {
                     return true;
                 }
             }
         }
 
-        false
+        self.should_upgrade_hashed(&host_lower, now)
+    }
+
+    /// Look `host` and its parent domains up among the imported entries.
+    fn should_upgrade_hashed(&self, host: &str, now: OffsetDateTime) -> bool {
+        if self.hashed.is_empty() {
+            return false;
+        }
+        let mut current = host;
+        let mut exact = true;
+        loop {
+            if let Some(entry) = hash_host(current).and_then(|h| self.hashed.get(&h)) {
+                if !entry.is_expired_at(now) && (exact || entry.include_subdomains) {
+                    return true;
+                }
+            }
+            match current.split_once('.') {
+                Some((_, parent)) if !parent.is_empty() => current = parent,
+                _ => return false,
+            }
+            exact = false;
+        }
     }
 
     /// Parse and add HSTS from a Strict-Transport-Security header.
     /// Format: "max-age=31536000; includeSubDomains; preload"
+    ///
+    /// Headers from IP literals are ignored (RFC 6797 section 8.1).
     pub fn add_from_header(&self, host: &str, header: &str) {
+        if ip_literal(host).is_some() {
+            return;
+        }
         let mut max_age: Option<u64> = None;
         let mut include_subdomains = false;
 
@@ -154,108 +216,196 @@ impl HstsStore {
         if let Some(secs) = max_age {
             if secs == 0 {
                 // max-age=0 removes the entry
-                self.entries.remove(&host.to_lowercase());
+                self.entries.remove(&host_key(host));
             } else {
+                let expires = self.now() + Duration::seconds(secs as i64);
                 self.entries.insert(
-                    host.to_lowercase(),
-                    HstsEntry::new(include_subdomains, Some(secs)),
+                    host_key(host),
+                    HstsEntry {
+                        include_subdomains,
+                        expires: Some(expires),
+                    },
                 );
             }
+            self.dirty.mark();
         }
     }
 
+    /// Unexpired entries learned from headers, i.e. everything but the
+    /// preload list.
+    pub(crate) fn dynamic_entries(&self) -> Vec<(String, HstsEntry)> {
+        let now = self.now();
+        self.entries
+            .iter()
+            .filter(|e| e.expires.is_some() && !e.is_expired_at(now))
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect()
+    }
+
+    /// Restore a dynamic entry without marking the store dirty.
+    pub(crate) fn restore(&self, domain: &str, entry: HstsEntry) {
+        self.entries.insert(host_key(domain), entry);
+    }
+
+    pub(crate) fn dirty_signal(&self) -> &Arc<DirtySignal> {
+        &self.dirty
+    }
+
     /// Get the number of entries.
     pub fn len(&self) -> usize {
-        self.entries.len()
+        self.entries.len() + self.hashed.len()
     }
 
     /// Check if store is empty.
     pub fn is_empty(&self) -> bool {
-        self.entries.is_empty()
+        self.entries.is_empty() && self.hashed.is_empty()
     }
 
-    /// Save HSTS entries to a JSON file.
+    /// Import the HSTS entries of a Chrome `TransportSecurity` file, found
+    /// in the profile directory.
     ///
-    /// Serializes non-expired entries for persistence across restarts.
-    pub fn save_to_file(&self, path: &std::path::Path) -> std::io::Result<()> {
-        use std::io::Write;
+    /// Chrome stores a hash of each host rather than its name, so imported
+    /// entries upgrade matching hosts but cannot be listed, and are not
+    /// written back by the persister. Expired entries are skipped. Returns
+    /// how many entries were imported.
+    pub fn load_chrome_transport_security(&self, path: &std::path::Path) -> std::io::Result<usize> {
+        let contents = std::fs::read_to_string(path)?;
+        let json: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
 
-        #[derive(serde::Serialize)]
-        struct SerializableEntry {
-            domain: String,
-            include_subdomains: bool,
-            expires_timestamp: Option<i64>,
+        // Version 2 has an "sts" list; older files map hashes to entries
+        let entries: Vec<(&str, &serde_json::Value)> = match json.get("sts") {
+            Some(sts) => sts
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|e| Some((e.get("host")?.as_str()?, e)))
+                .collect(),
+            None => json
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(host, e)| (host.as_str(), e))
+                .collect(),
+        };
+
+        let now = self.now();
+        let mut loaded = 0;
+        for (host, entry) in entries {
+            let Some(hash) = STANDARD
+                .decode(host)
+                .ok()
+                .and_then(|h| HostHash::try_from(h).ok())
+            else {
+                continue;
+            };
+            if entry.get("mode").and_then(|m| m.as_str()) != Some("force-https") {
+                continue;
+            }
+            let Some(expires) = entry
+                .get("expiry")
+                .and_then(|e| e.as_f64())
+                .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs as i64).ok())
+            else {
+                continue;
+            };
+            let include_subdomains = entry
+                .get("sts_include_subdomains")
+                .or_else(|| entry.get("include_subdomains"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let entry = HstsEntry {
+                include_subdomains,
+                expires: Some(expires),
+            };
+            if !entry.is_expired_at(now) {
+                self.hashed.insert(hash, entry);
+                loaded += 1;
+            }
         }
+        Ok(loaded)
+    }
 
-        let entries: Vec<SerializableEntry> = self
-            .entries
-            .iter()
-            .filter(|e| !e.is_expired())
-            .map(|e| SerializableEntry {
-                domain: e.key().clone(),
-                include_subdomains: e.include_subdomains,
-                expires_timestamp: e.expires.map(|dt| dt.unix_timestamp()),
-            })
-            .collect();
-
-        let json = serde_json::to_string_pretty(&entries)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    /// Import the HSTS state of an installed Chromium-based browser; see
+    /// [`load_chrome_transport_security`](Self::load_chrome_transport_security).
+    ///
+    /// `profile` selects a profile other than the default one.
+    #[cfg(feature = "browser-cookies")]
+    pub fn import_from_browser(
+        &self,
+        browser: crate::cookies::browser::Browser,
+        profile: Option<&str>,
+    ) -> Result<usize, crate::base::neterror::NetError> {
+        use crate::base::neterror::NetError;
+        use crate::cookies::browser::BrowserCookieReader;
+
+        if !browser.is_chromium_based() {
+            return Err(NetError::cookie_platform_not_supported(format!(
+                "{:?} transport security state",
+                browser
+            )));
+        }
+        let mut reader = BrowserCookieReader::new(browser);
+        if let Some(profile) = profile {
+            reader = reader.with_profile(profile);
+        }
+        let path = reader
+            .profile_dir()
+            .ok_or_else(|| NetError::browser_not_found(format!("{:?}", browser)))?
+            .join("TransportSecurity");
+        if !path.exists() {
+            return Err(NetError::cookie_db_not_found(path.to_string_lossy()));
+        }
+        self.load_chrome_transport_security(&path)
+            .map_err(|e| NetError::cookie_invalid_data(format!("TransportSecurity: {}", e)))
+    }
 
-        let mut file = std::fs::File::create(path)?;
-        file.write_all(json.as_bytes())?;
-        Ok(())
+    /// Save the HSTS entries learned from headers to a JSON file.
+    ///
+    /// The file has the format of a
+    /// [`TransportSecurityPersister`](crate::tls::TransportSecurityPersister)
+    /// state file without pins. Expired entries and the preload list are
+    /// not saved.
+    pub fn save_to_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        crate::tls::persister::save_hsts(path, self)
     }
 
-    /// Load HSTS entries from a JSON file.
+    /// Load HSTS entries saved with [`save_to_file`](Self::save_to_file) or
+    /// by a [`TransportSecurityPersister`](crate::tls::TransportSecurityPersister),
+    /// returning how many were loaded. Expired entries are skipped.
     ///
-    /// Restores entries from a previous save. Expired entries are skipped.
+    /// Loaded entries mark the store changed, so a persister of this store
+    /// saves them.
     pub fn load_from_file(&self, path: &std::path::Path) -> std::io::Result<usize> {
-        use std::io::Read;
-
-        #[derive(serde::Deserialize)]
-        struct SerializableEntry {
-            domain: String,
-            include_subdomains: bool,
-            expires_timestamp: Option<i64>,
+        let loaded = crate::tls::persister::load_hsts(path, self)?;
+        if loaded > 0 {
+            self.dirty.mark();
         }
+        Ok(loaded)
+    }
+}
 
-        let mut file = std::fs::File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-
-        let entries: Vec<SerializableEntry> = serde_json::from_str(&contents)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-
-        let mut loaded = 0;
-        for entry in entries {
-            let expires = entry
-                .expires_timestamp
-                .and_then(|ts| OffsetDateTime::from_unix_timestamp(ts).ok());
-
-            // Skip expired entries
-            if let Some(exp) = expires {
-                if OffsetDateTime::now_utc() > exp {
-                    continue;
-                }
-            }
-
-            self.entries.insert(
-                entry.domain,
-                HstsEntry {
-                    include_subdomains: entry.include_subdomains,
-                    expires,
-                },
-            );
-            loaded += 1;
+/// Chrome's `HashHost`: SHA-256 of the host in DNS wire format, each label
+/// prefixed with its length and the name terminated by an empty label.
+fn hash_host(host: &str) -> Option<HostHash> {
+    let host = host.trim_end_matches('.');
+    let mut wire = Vec::with_capacity(host.len() + 2);
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return None;
         }
-
-        Ok(loaded)
+        wire.push(label.len() as u8);
+        wire.extend(label.bytes().map(|b| b.to_ascii_lowercase()));
     }
+    wire.push(0);
+    let digest = boring::hash::hash(boring::hash::MessageDigest::sha256(), &wire).ok()?;
+    digest.as_ref().try_into().ok()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::base::clock::MockClock;
 
     #[test]
     fn test_should_upgrade_exact_match() {
@@ -301,6 +451,33 @@ mod tests {
         assert!(!store.should_upgrade("sub.example.com"));
     }
 
+    #[test]
+    fn test_ip_literals_ignored() {
+        let store = HstsStore::new();
+        store.add_from_header("10.0.0.5", "max-age=31536000");
+        store.add_from_header("[::1]", "max-age=31536000");
+        assert!(store.is_empty());
+
+        // A domain entry never covers an address "below" it
+        store.add_preloaded("0.5", true);
+        assert!(!store.should_upgrade("10.0.0.5"));
+    }
+
+    #[test]
+    fn test_max_age_expires_by_clock() {
+        let clock = MockClock::new(std::time::SystemTime::now());
+        let store = HstsStore::new().with_clock(Arc::new(clock.clone()));
+        store.add_from_header("example.com", "max-age=60; includeSubDomains");
+
+        clock.advance(std::time::Duration::from_secs(60));
+        assert!(store.should_upgrade("sub.example.com"));
+        assert_eq!(store.dynamic_entries().len(), 1);
+
+        clock.advance(std::time::Duration::from_secs(1));
+        assert!(!store.should_upgrade("example.com"));
+        assert!(store.dynamic_entries().is_empty());
+    }
+
     #[test]
     fn test_max_age_zero_removes() {
         let store = HstsStore::new();
@@ -327,4 +504,64 @@ mod tests {
         assert!(store.should_upgrade("example.com"));
         assert!(store.should_upgrade("EXAMPLE.COM"));
     }
+
+    #[test]
+    fn test_hash_host() {
+        let hash = hash_host("Example.com.").unwrap();
+        assert_eq!(
+            STANDARD.encode(hash),
+            "kC6cRk+kP8qxCdGmuV3fgzOKjLw6UrT7/hqdhfIkbQ8="
+        );
+        assert!(hash_host("a..b").is_none());
+    }
+
+    #[test]
+    fn test_load_chrome_transport_security() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("TransportSecurity");
+        let future = (OffsetDateTime::now_utc() + Duration::days(30)).unix_timestamp() as f64;
+        let json = serde_json::json!({
+            "version": 2,
+            "expect_ct": [],
+            "sts": [
+                // example.org, includeSubDomains
+                {"host": "ZAzydWuEQK4qaLUxtpJX7MoPCJ900QQKQEJvKh4t/do=", "mode": "force-https",
+                 "expiry": future + 0.5, "sts_include_subdomains": true, "sts_observed": 1.0},
+                // example.com, "default" mode does not upgrade
+                {"host": "kC6cRk+kP8qxCdGmuV3fgzOKjLw6UrT7/hqdhfIkbQ8=", "mode": "default",
+                 "expiry": future, "sts_include_subdomains": false, "sts_observed": 1.0},
+                // old.example.org, expired
+                {"host": "TXSUaRkWW69k2SH0vXGrZ/MbAJAla387SakRFa4i7T0=", "mode": "force-https",
+                 "expiry": 1000.0, "sts_include_subdomains": false, "sts_observed": 1.0},
+                {"host": "not base64", "mode": "force-https", "expiry": future},
+            ]
+        });
+        std::fs::write(&path, json.to_string()).unwrap();
+
+        let store = HstsStore::new();
+        assert_eq!(store.load_chrome_transport_security(&path).unwrap(), 1);
+        assert_eq!(store.len(), 1);
+        assert!(store.should_upgrade("example.org"));
+        assert!(store.should_upgrade("www.Example.org"));
+        assert!(!store.should_upgrade("example.com"));
+        assert!(!store.should_upgrade("org"));
+    }
+
+    #[test]
+    fn test_load_legacy_transport_security() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("TransportSecurity");
+        let future = (OffsetDateTime::now_utc() + Duration::days(30)).unix_timestamp() as f64;
+        let json = serde_json::json!({
+            "kC6cRk+kP8qxCdGmuV3fgzOKjLw6UrT7/hqdhfIkbQ8=": {
+                "expiry": future, "include_subdomains": false, "mode": "force-https",
+            }
+        });
+        std::fs::write(&path, json.to_string()).unwrap();
+
+        let store = HstsStore::new();
+        assert_eq!(store.load_chrome_transport_security(&path).unwrap(), 1);
+        assert!(store.should_upgrade("example.com"));
+        assert!(!store.should_upgrade("www.example.com"));
+    }
 }
@@ -1,16 +1,26 @@
 //! TLS security features.
 //!
 //! Provides TLS security mechanisms mirroring Chromium's transport security:
-//! - [`hsts`]: HTTP Strict Transport Security with JSON persistence
+//! - [`hsts`]: HTTP Strict Transport Security with JSON persistence and
+//!   import of a Chrome profile's learned entries
 //! - [`pinning`]: Certificate pinning with SPKI hash verification
+//! - [`persister`]: Coalesced on-disk persistence of learned HSTS entries and pins
 //! - [`ctverifier`]: Certificate Transparency verification
+//! - [`certverify`]: Per-request verification results, with pinning and CT
+//!   checked on every request's connection
 
+pub mod certverify;
 pub mod ct;
 pub mod ctverifier;
 pub mod hsts;
+pub mod persister;
 pub mod pinning;
 
+pub use certverify::{CertPolicy, CertVerifyResult, CtResult, OcspStatus};
 pub use ct::{CtRequirement, Sct, SctStatus};
 pub use ctverifier::{decode_sct_list, CtLog, MultiLogCtVerifier};
 pub use hsts::{HstsEntry, HstsStore};
-pub use pinning::{spki_hash, PinSet, PinStore, SpkiHash};
+pub use persister::TransportSecurityPersister;
+pub use pinning::{
+    spki_hash, PinReportSender, PinSet, PinStore, PinViolationReport, PinningResult, SpkiHash,
+};
@@ -0,0 +1,500 @@
+//! On-disk persistence of transport security state.
+//!
+//! Mirrors Chromium's `net/http/transport_security_persister.h`: HSTS entries
+//! learned from headers and certificate pins are loaded when the persister
+//! starts and written back after they change. Writes are coalesced, so a
+//! burst of changes costs one write, and go through a temporary file so a
+//! crash never leaves a truncated state file behind.
+//!
+//! [`HstsStore::save_to_file`] and [`HstsStore::load_from_file`] use the
+//! same file format without the pins, so either can read what the other
+//! wrote.
+
+use crate::base::host::host_key;
+use crate::tls::hsts::{HstsEntry, HstsStore};
+use crate::tls::pinning::{PinSet, PinStore, SpkiHash};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// Version of the state file format.
+const STATE_VERSION: u32 = 1;
+
+/// Set by a store whenever its persisted state changes.
+#[derive(Debug, Default)]
+pub(crate) struct DirtySignal {
+    dirty: AtomicBool,
+    notify: Notify,
+}
+
+impl DirtySignal {
+    pub(crate) fn mark(&self) {
+        self.dirty.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    fn take(&self) -> bool {
+        self.dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn is_set(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    version: u32,
+    #[serde(default)]
+    sts: Vec<StsState>,
+    #[serde(default)]
+    pins: Vec<PinState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StsState {
+    host: String,
+    include_subdomains: bool,
+    expiry: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PinState {
+    host: String,
+    include_subdomains: bool,
+    /// Base64 SPKI SHA-256 hashes
+    pins: Vec<String>,
+    expiry: Option<i64>,
+    #[serde(default)]
+    report_only: bool,
+    #[serde(default)]
+    report_uri: Option<String>,
+}
+
+impl State {
+    /// Snapshot the dynamic entries of `hsts` and, if given, `pins`.
+    fn of(hsts: &HstsStore, pins: Option<&PinStore>) -> Self {
+        Self {
+            version: STATE_VERSION,
+            sts: hsts
+                .dynamic_entries()
+                .into_iter()
+                .filter_map(|(host, entry)| {
+                    Some(StsState {
+                        host,
+                        include_subdomains: entry.include_subdomains,
+                        expiry: entry.expires?.unix_timestamp(),
+                    })
+                })
+                .collect(),
+            pins: pins
+                .map(PinStore::pin_sets)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| PinState {
+                    host: host_key(&p.domain),
+                    include_subdomains: p.include_subdomains,
+                    pins: p.pins.iter().map(|h| STANDARD.encode(h)).collect(),
+                    expiry: p.expires.map(|e| e.unix_timestamp()),
+                    report_only: p.report_only,
+                    report_uri: p.report_uri.as_ref().map(|uri| uri.to_string()),
+                })
+                .collect(),
+        }
+    }
+
+    fn parse(json: &str) -> io::Result<Self> {
+        let state: State = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if state.version != STATE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported state version {}", state.version),
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Replace `path` with the state.
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Add the unexpired HSTS entries to `hsts` without marking it dirty,
+    /// returning how many were added.
+    fn restore_sts(&mut self, hsts: &HstsStore) -> usize {
+        let now = hsts.now();
+        let mut loaded = 0;
+        for sts in self.sts.drain(..) {
+            let Ok(expires) = OffsetDateTime::from_unix_timestamp(sts.expiry) else {
+                continue;
+            };
+            let entry = HstsEntry {
+                include_subdomains: sts.include_subdomains,
+                expires: Some(expires),
+            };
+            if !entry.is_expired_at(now) {
+                hsts.restore(&sts.host, entry);
+                loaded += 1;
+            }
+        }
+        loaded
+    }
+}
+
+/// Write the HSTS entries of `hsts` to `path`, for
+/// [`HstsStore::save_to_file`].
+pub(crate) fn save_hsts(path: &Path, hsts: &HstsStore) -> io::Result<()> {
+    State::of(hsts, None).write(path)
+}
+
+/// Add the HSTS entries saved in `path` to `hsts`, for
+/// [`HstsStore::load_from_file`]. Pins in the file are ignored.
+pub(crate) fn load_hsts(path: &Path, hsts: &HstsStore) -> io::Result<usize> {
+    State::parse(&std::fs::read_to_string(path)?).map(|mut state| state.restore_sts(hsts))
+}
+
+/// Keeps an [`HstsStore`] and a [`PinStore`] in sync with a JSON file.
+///
+/// Only state that cannot be rebuilt is saved: HSTS entries learned from
+/// `Strict-Transport-Security` headers (not the preload list) and pins.
+/// Expired entries are dropped on load and save.
+///
+/// # Example
+/// ```ignore
+/// let hsts = HstsStore::with_preload();
+/// let pins = PinStore::new();
+/// let persister = TransportSecurityPersister::new("TransportSecurity", &hsts, &pins)
+///     .start()?;
+/// ```
+pub struct TransportSecurityPersister {
+    path: PathBuf,
+    hsts: HstsStore,
+    pins: PinStore,
+    commit_interval: Duration,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl TransportSecurityPersister {
+    /// Default delay between a change and the write that saves it.
+    ///
+    /// Chromium: ImportantFileWriter's default commit interval.
+    pub const DEFAULT_COMMIT_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// Persist `hsts` and `pins` to `path`. Nothing is read or written until
+    /// [`start`](Self::start).
+    pub fn new(path: impl Into<PathBuf>, hsts: &HstsStore, pins: &PinStore) -> Self {
+        Self {
+            path: path.into(),
+            hsts: hsts.clone(),
+            pins: pins.clone(),
+            commit_interval: Self::DEFAULT_COMMIT_INTERVAL,
+            writer: None,
+        }
+    }
+
+    /// Wait `interval` after a change before writing, so changes within it
+    /// are saved together.
+    pub fn with_commit_interval(mut self, interval: Duration) -> Self {
+        self.commit_interval = interval;
+        self
+    }
+
+    /// Load the saved state into the stores, then save changes as they
+    /// happen. A missing file is not an error.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn start(mut self) -> io::Result<Self> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => {
+                let loaded = self.restore(&json)?;
+                tracing::debug!(target: "chromenet::tls", path = %self.path.display(), loaded, "Loaded transport security state");
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        // Changes made before start are pending already and get written
+        // after the first interval
+        let hsts_dirty = self.hsts.dirty_signal().clone();
+        let pins_dirty = self.pins.dirty_signal().clone();
+
+        let path = self.path.clone();
+        let (hsts, pins) = (self.hsts.clone(), self.pins.clone());
+        let interval = self.commit_interval;
+        self.writer = Some(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = hsts_dirty.notify.notified() => {}
+                    _ = pins_dirty.notify.notified() => {}
+                }
+                tokio::time::sleep(interval).await;
+                // Both flags are cleared before the snapshot, so changes made
+                // during the write mark the state dirty again
+                if !(hsts_dirty.take() | pins_dirty.take()) {
+                    continue;
+                }
+                let (path, hsts, pins) = (path.clone(), hsts.clone(), pins.clone());
+                let result =
+                    tokio::task::spawn_blocking(move || State::of(&hsts, Some(&pins)).write(&path))
+                        .await
+                        .unwrap_or_else(|e| Err(io::Error::other(e)));
+                if let Err(e) = result {
+                    tracing::warn!(target: "chromenet::tls", error = %e, "Failed to save transport security state");
+                }
+            }
+        }));
+        Ok(self)
+    }
+
+    /// Whether changes are waiting to be written.
+    pub fn has_pending_write(&self) -> bool {
+        self.hsts.dirty_signal().is_set() || self.pins.dirty_signal().is_set()
+    }
+
+    /// Write the current state now instead of waiting for the commit
+    /// interval.
+    ///
+    /// This blocks on file I/O; from async code call it with
+    /// [`spawn_blocking`](tokio::task::spawn_blocking).
+    pub fn commit_now(&self) -> io::Result<()> {
+        self.hsts.dirty_signal().take();
+        self.pins.dirty_signal().take();
+        State::of(&self.hsts, Some(&self.pins)).write(&self.path)
+    }
+
+    /// Path of the state file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The HSTS store kept on disk.
+    pub(crate) fn hsts(&self) -> &HstsStore {
+        &self.hsts
+    }
+
+    /// The pin store kept on disk.
+    pub(crate) fn pins(&self) -> &PinStore {
+        &self.pins
+    }
+
+    /// Add the entries in `json` to the stores, returning how many were
+    /// loaded.
+    fn restore(&self, json: &str) -> io::Result<usize> {
+        let mut state = State::parse(json)?;
+        let mut loaded = state.restore_sts(&self.hsts);
+        for pin in state.pins {
+            let mut pin_set = PinSet::new(pin.host)
+                .include_subdomains(pin.include_subdomains)
+                .report_only(pin.report_only);
+            pin_set.report_uri = pin.report_uri.and_then(|uri| uri.parse().ok());
+            if let Some(expiry) = pin.expiry {
+                let Ok(expires) = OffsetDateTime::from_unix_timestamp(expiry) else {
+                    continue;
+                };
+                pin_set = pin_set.expires_at(expires);
+            }
+            if pin_set.is_expired() {
+                continue;
+            }
+            pin_set.pins = pin.pins.iter().filter_map(|p| decode_pin(p)).collect();
+            if !pin_set.pins.is_empty() {
+                self.pins.restore(pin_set);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+}
+
+impl Drop for TransportSecurityPersister {
+    /// Stop the writer and save what it did not get to on a background
+    /// thread, so dropping never blocks on file I/O.
+    fn drop(&mut self) {
+        let Some(writer) = self.writer.take() else {
+            return;
+        };
+        writer.abort();
+        if !(self.hsts.dirty_signal().take() | self.pins.dirty_signal().take()) {
+            return;
+        }
+        let state = State::of(&self.hsts, Some(&self.pins));
+        let path = self.path.clone();
+        let save = move || {
+            if let Err(e) = state.write(&path) {
+                tracing::warn!(target: "chromenet::tls", error = %e, "Failed to save transport security state");
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(save)),
+            Err(_) => drop(std::thread::spawn(save)),
+        }
+    }
+}
+
+impl std::fmt::Debug for TransportSecurityPersister {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransportSecurityPersister")
+            .field("path", &self.path)
+            .field("commit_interval", &self.commit_interval)
+            .field("started", &self.writer.is_some())
+            .finish()
+    }
+}
+
+fn decode_pin(pin: &str) -> Option<SpkiHash> {
+    STANDARD.decode(pin).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn stores() -> (HstsStore, PinStore) {
+        (HstsStore::new(), PinStore::new())
+    }
+
+    #[tokio::test]
+    async fn test_state_survives_restart() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("TransportSecurity");
+
+        let (hsts, pins) = stores();
+        hsts.add_preloaded("preloaded.com", true);
+        hsts.add_from_header("learned.com", "max-age=3600; includeSubDomains");
+        let mut pin_set = PinSet::new("Pinned.com").include_subdomains(true);
+        pin_set.add_pin([7u8; 32]);
+        pins.add(pin_set);
+        let expired = PinSet::new("expired.com")
+            .expires_at(OffsetDateTime::now_utc() - time::Duration::hours(1));
+        pins.add(expired);
+        pins.add_report_only_from_header(
+            "report.com",
+            &format!(
+                "pin-sha256=\"{}\"; max-age=3600",
+                STANDARD.encode([9u8; 32])
+            ),
+        );
+
+        let persister = TransportSecurityPersister::new(&path, &hsts, &pins)
+            .start()
+            .unwrap();
+        persister.commit_now().unwrap();
+        drop(persister);
+
+        let (hsts, pins) = stores();
+        let _persister = TransportSecurityPersister::new(&path, &hsts, &pins)
+            .start()
+            .unwrap();
+        assert!(hsts.should_upgrade("sub.learned.com"));
+        // The preload list is rebuilt, not persisted
+        assert!(!hsts.should_upgrade("preloaded.com"));
+        assert_eq!(pins.len(), 2);
+        assert!(pins.check("www.pinned.com", &[[7u8; 32]]).is_ok());
+        assert!(pins.check("www.pinned.com", &[[8u8; 32]]).is_err());
+        assert!(pins.check("report.com", &[[8u8; 32]]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_coalesced_into_one_write() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("TransportSecurity");
+        let (hsts, pins) = stores();
+        let persister = TransportSecurityPersister::new(&path, &hsts, &pins)
+            .with_commit_interval(Duration::from_millis(100))
+            .start()
+            .unwrap();
+
+        hsts.add_from_header("a.com", "max-age=3600");
+        hsts.add_from_header("b.com", "max-age=3600");
+        let mut pin_set = PinSet::new("c.com");
+        pin_set.add_pin([1u8; 32]);
+        pins.add(pin_set);
+        assert!(persister.has_pending_write());
+        assert!(!path.exists());
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!persister.has_pending_write());
+        let state: State = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(state.sts.len(), 2);
+        assert_eq!(state.pins.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_drop_saves_pending_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("TransportSecurity");
+        let (hsts, pins) = stores();
+        let persister = TransportSecurityPersister::new(&path, &hsts, &pins)
+            .start()
+            .unwrap();
+
+        hsts.add_from_header("example.com", "max-age=3600");
+        drop(persister);
+        assert!(!hsts.dirty_signal().is_set());
+
+        // The final write happens off the dropping thread
+        for _ in 0..50 {
+            if path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let (hsts, pins) = stores();
+        let persister = TransportSecurityPersister::new(&path, &hsts, &pins);
+        let _persister = persister.start().unwrap();
+        assert!(hsts.should_upgrade("example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_hsts_files_share_the_state_format() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("TransportSecurity");
+        let hsts = HstsStore::with_preload();
+        hsts.add_from_header("learned.com", "max-age=3600");
+        hsts.save_to_file(&path).unwrap();
+
+        let state: State = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.sts.len(), 1);
+
+        let (hsts, pins) = stores();
+        let _persister = TransportSecurityPersister::new(&path, &hsts, &pins)
+            .start()
+            .unwrap();
+        assert!(hsts.should_upgrade("learned.com"));
+        assert!(!hsts.dirty_signal().is_set());
+
+        // Loading into a store is a change its persister saves
+        let loaded = HstsStore::new();
+        assert_eq!(loaded.load_from_file(&path).unwrap(), 1);
+        assert!(loaded.dirty_signal().is_set());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_state_is_rejected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("TransportSecurity");
+        std::fs::write(&path, "not json").unwrap();
+
+        let (hsts, pins) = stores();
+        let err = TransportSecurityPersister::new(&path, &hsts, &pins)
+            .start()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -5,11 +5,27 @@
 //!
 //! Note: HPKP (HTTP Public Key Pinning) is deprecated in browsers, but
 //! preloaded pins and programmatic pinning are still valuable for security.
-
+//!
+//! Pins are also learned at runtime from `Public-Key-Pins` headers (RFC 7469)
+//! with [`PinStore::add_from_header`], expiring after their `max-age`. A pin
+//! set in report-only mode, from `Public-Key-Pins-Report-Only` or
+//! [`PinSet::report_only`], never fails a connection: a mismatch is only
+//! reported. Violations of sets with a `report-uri` are sent there as RFC 7469
+//! JSON reports by the store's [`PinReportSender`], such as a [`Client`].
+
+use crate::base::host::{host_key, ip_literal};
 use crate::base::neterror::NetError;
+use crate::client::Client;
+use crate::tls::persister::DirtySignal;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use dashmap::DashMap;
+use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
+use url::Url;
 
 /// SHA-256 hash of a certificate's SPKI (Subject Public Key Info).
 pub type SpkiHash = [u8; 32];
@@ -25,6 +41,10 @@ pub struct PinSet {
     pub pins: Vec<SpkiHash>,
     /// Optional expiration time (fail-open after expiry).
     pub expires: Option<OffsetDateTime>,
+    /// Report mismatches instead of failing the connection.
+    pub report_only: bool,
+    /// Where violation reports are sent.
+    pub report_uri: Option<Url>,
 }
 
 impl PinSet {
@@ -35,6 +55,8 @@ impl PinSet {
             include_subdomains: false,
             pins: Vec::new(),
             expires: None,
+            report_only: false,
+            report_uri: None,
         }
     }
 
@@ -71,6 +93,24 @@ impl PinSet {
         self
     }
 
+    /// Expire `max_age` from now.
+    pub fn max_age(self, max_age: Duration) -> Self {
+        self.expires_at(OffsetDateTime::now_utc() + max_age)
+    }
+
+    /// Report mismatches to the `report_uri` instead of failing the
+    /// connection.
+    pub fn report_only(mut self, report_only: bool) -> Self {
+        self.report_only = report_only;
+        self
+    }
+
+    /// Send violation reports to `uri`.
+    pub fn report_uri(mut self, uri: Url) -> Self {
+        self.report_uri = Some(uri);
+        self
+    }
+
     /// Check if pin set is expired.
     pub fn is_expired(&self) -> bool {
         if let Some(exp) = self.expires {
@@ -91,10 +131,141 @@ impl PinSet {
     }
 }
 
+/// Outcome of checking a connection against its host's pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinningResult {
+    /// The host has no unexpired pins.
+    NotPinned,
+    /// A certificate of the chain matched a pin.
+    Matched,
+    /// No certificate matched a report-only pin set; the connection goes
+    /// ahead.
+    ReportOnlyMismatch,
+    /// No certificate matched; the connection fails.
+    Mismatch,
+}
+
+impl PinningResult {
+    /// `Err(CertPinningFailed)` for [`Mismatch`](Self::Mismatch).
+    pub fn into_result(self) -> Result<(), NetError> {
+        match self {
+            Self::Mismatch => Err(NetError::CertPinningFailed),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A pin validation failure, as reported to a pin set's `report-uri`.
+///
+/// Chromium mapping: `TransportSecurityState::PKPState::CheckPublicKeyPins`
+/// report generation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinViolationReport {
+    /// Host the connection was made to.
+    pub hostname: String,
+    pub port: u16,
+    /// Domain of the violated pin set, a parent of `hostname` for
+    /// `includeSubDomains` sets.
+    pub noted_hostname: String,
+    pub include_subdomains: bool,
+    pub effective_expiration_date: Option<OffsetDateTime>,
+    /// PEM certificates the server sent.
+    pub served_certificate_chain: Vec<String>,
+    /// Pins of the set, as `pin-sha256="<base64>"`.
+    pub known_pins: Vec<String>,
+    pub date_time: OffsetDateTime,
+    /// Whether the connection went ahead anyway.
+    pub report_only: bool,
+}
+
+impl PinViolationReport {
+    fn new(
+        hostname: &str,
+        port: u16,
+        pin_set: &PinSet,
+        served_certificate_chain: Vec<String>,
+    ) -> Self {
+        Self {
+            hostname: host_key(hostname),
+            port,
+            noted_hostname: host_key(&pin_set.domain),
+            include_subdomains: pin_set.include_subdomains,
+            effective_expiration_date: pin_set.expires,
+            served_certificate_chain,
+            known_pins: pin_set
+                .pins
+                .iter()
+                .map(|pin| format!("pin-sha256=\"{}\"", STANDARD.encode(pin)))
+                .collect(),
+            date_time: OffsetDateTime::now_utc(),
+            report_only: pin_set.report_only,
+        }
+    }
+
+    /// The report as the JSON body of RFC 7469 section 3.
+    pub fn to_json(&self) -> String {
+        let date = |t: OffsetDateTime| t.format(&Rfc3339).ok();
+        serde_json::json!({
+            "date-time": date(self.date_time),
+            "hostname": self.hostname,
+            "port": self.port,
+            "effective-expiration-date": self.effective_expiration_date.and_then(date),
+            "include-subdomains": self.include_subdomains,
+            "noted-hostname": self.noted_hostname,
+            "served-certificate-chain": self.served_certificate_chain,
+            // The verified chain is not kept; the served one stands in
+            "validated-certificate-chain": self.served_certificate_chain,
+            "known-pins": self.known_pins,
+        })
+        .to_string()
+    }
+}
+
+/// Delivers pin violation reports.
+///
+/// Chromium mapping: `TransportSecurityState::ReportSenderInterface`
+pub trait PinReportSender: Send + Sync {
+    /// Send `report` to `report_uri`. Must not block.
+    fn send(&self, report_uri: &Url, report: &PinViolationReport);
+}
+
+/// POSTs reports as `application/json` on the current Tokio runtime,
+/// dropping them outside of one.
+impl PinReportSender for Client {
+    fn send(&self, report_uri: &Url, report: &PinViolationReport) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::debug!(target: "chromenet::tls", %report_uri, "no runtime to send pin report");
+            return;
+        };
+        let request = self
+            .post(report_uri.as_str())
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(report.to_json());
+        let report_uri = report_uri.clone();
+        runtime.spawn(async move {
+            if let Err(error) = request.send().await {
+                tracing::debug!(target: "chromenet::tls", %report_uri, %error, "pin report not sent");
+            }
+        });
+    }
+}
+
 /// Thread-safe store for certificate pins.
 #[derive(Clone)]
 pub struct PinStore {
     pins: Arc<DashMap<String, PinSet>>,
+    /// Raised when pins change, for the persister
+    dirty: Arc<DirtySignal>,
+    report_sender: Option<Arc<dyn PinReportSender>>,
+}
+
+impl fmt::Debug for PinStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PinStore")
+            .field("len", &self.pins.len())
+            .field("report_sender", &self.report_sender.is_some())
+            .finish()
+    }
 }
 
 impl Default for PinStore {
@@ -108,30 +279,177 @@ impl PinStore {
     pub fn new() -> Self {
         Self {
             pins: Arc::new(DashMap::new()),
+            dirty: Arc::new(DirtySignal::default()),
+            report_sender: None,
         }
     }
 
+    /// Send violation reports of pin sets with a `report-uri` with `sender`.
+    /// Without one they are only logged.
+    pub fn with_report_sender(mut self, sender: impl PinReportSender + 'static) -> Self {
+        self.report_sender = Some(Arc::new(sender));
+        self
+    }
+
     /// Add or replace a pin set.
     pub fn add(&self, pin_set: PinSet) {
-        self.pins.insert(pin_set.domain.to_lowercase(), pin_set);
+        self.restore(pin_set);
+        self.dirty.mark();
+    }
+
+    /// Parse and add pins from a `Public-Key-Pins` header.
+    /// Format: `pin-sha256="<base64>"; max-age=5184000; includeSubDomains;
+    /// report-uri="https://example.com/hpkp"`
+    ///
+    /// The header needs a `max-age` and at least one pin; `max-age=0`
+    /// removes the host's pins. Headers from IP literals are ignored
+    /// (RFC 7469 section 2.3.1).
+    pub fn add_from_header(&self, host: &str, header: &str) {
+        self.learn(host, header, false);
+    }
+
+    /// Parse and add report-only pins from a `Public-Key-Pins-Report-Only`
+    /// header, which has the format of [`add_from_header`](Self::add_from_header).
+    pub fn add_report_only_from_header(&self, host: &str, header: &str) {
+        self.learn(host, header, true);
+    }
+
+    fn learn(&self, host: &str, header: &str, report_only: bool) {
+        if ip_literal(host).is_some() {
+            return;
+        }
+        let mut pin_set = PinSet::new(host_key(host)).report_only(report_only);
+        let mut max_age: Option<u64> = None;
+
+        for part in header.split(';') {
+            let (name, value) = part.split_once('=').unwrap_or((part, ""));
+            let name = name.trim().to_ascii_lowercase();
+            let value = value.trim().trim_matches('"');
+
+            match name.as_str() {
+                "pin-sha256" => {
+                    if pin_set.add_pin_base64(value).is_err() {
+                        tracing::debug!(target: "chromenet::tls", host, "ignoring malformed pin");
+                    }
+                }
+                "max-age" => max_age = value.parse().ok(),
+                "includesubdomains" => pin_set.include_subdomains = true,
+                "report-uri" => pin_set.report_uri = Url::parse(value).ok(),
+                _ => {}
+            }
+        }
+
+        match max_age {
+            Some(0) => self.remove(host),
+            Some(secs) if !pin_set.pins.is_empty() => {
+                self.add(pin_set.max_age(Duration::from_secs(secs)))
+            }
+            _ => {}
+        }
     }
 
     /// Remove pins for a domain.
     pub fn remove(&self, domain: &str) {
-        self.pins.remove(&domain.to_lowercase());
+        if self.pins.remove(&host_key(domain)).is_some() {
+            self.dirty.mark();
+        }
+    }
+
+    /// Unexpired pin sets.
+    pub(crate) fn pin_sets(&self) -> Vec<PinSet> {
+        self.pins
+            .iter()
+            .filter(|p| !p.is_expired())
+            .map(|p| p.value().clone())
+            .collect()
+    }
+
+    /// Add a pin set without marking the store dirty.
+    pub(crate) fn restore(&self, pin_set: PinSet) {
+        self.pins.insert(host_key(&pin_set.domain), pin_set);
+    }
+
+    pub(crate) fn dirty_signal(&self) -> &Arc<DirtySignal> {
+        &self.dirty
     }
 
     /// Check if the connection to `host` with given certificate hashes is allowed.
     /// Returns Ok(()) if pins match or no pins exist for this domain.
     /// Returns Err(CertPinningFailed) if pins exist but don't match.
     ///
+    /// Violations are reported for port 443 without a certificate chain;
+    /// see [`check_chain`](Self::check_chain).
+    ///
     /// Chromium: net/http/transport_security_state.cc
     pub fn check(&self, host: &str, cert_hashes: &[SpkiHash]) -> Result<(), NetError> {
-        let host_lower = host.to_lowercase();
+        self.verify(host, 443, cert_hashes, &[] as &[&[u8]])
+            .into_result()
+    }
+
+    /// Check a connection to `host:port` that served the DER certificates
+    /// `chain`, like [`check`](Self::check), with the chain in violation
+    /// reports.
+    pub fn check_chain<C: AsRef<[u8]>>(
+        &self,
+        host: &str,
+        port: u16,
+        chain: &[C],
+    ) -> Result<(), NetError> {
+        let cert_hashes: Vec<SpkiHash> = chain
+            .iter()
+            .filter_map(|cert| spki_hash(cert.as_ref()).ok())
+            .collect();
+        self.verify(host, port, &cert_hashes, chain).into_result()
+    }
+
+    /// Check a connection to `host:port` whose DER certificates `chain`
+    /// have the SPKI hashes `cert_hashes`, reporting a violation.
+    pub(crate) fn verify<C: AsRef<[u8]>>(
+        &self,
+        host: &str,
+        port: u16,
+        cert_hashes: &[SpkiHash],
+        chain: &[C],
+    ) -> PinningResult {
+        // No pins configured for this host, or expired ones (fail-open,
+        // like Chromium) - allow
+        let Some(pin_set) = self.find(host).filter(|p| !p.is_expired()) else {
+            return PinningResult::NotPinned;
+        };
+        if pin_set.matches(cert_hashes) {
+            return PinningResult::Matched;
+        }
+        self.report(
+            &pin_set,
+            PinViolationReport::new(host, port, &pin_set, pem_chain(chain)),
+        );
+        if pin_set.report_only {
+            PinningResult::ReportOnlyMismatch
+        } else {
+            PinningResult::Mismatch
+        }
+    }
+
+    fn report(&self, pin_set: &PinSet, report: PinViolationReport) {
+        tracing::warn!(
+            target: "chromenet::tls",
+            host = %report.hostname,
+            noted_host = %report.noted_hostname,
+            report_only = report.report_only,
+            "certificate pin mismatch"
+        );
+        if let (Some(uri), Some(sender)) = (&pin_set.report_uri, &self.report_sender) {
+            sender.send(uri, &report);
+        }
+    }
+
+    /// The pin set applying to `host`, if any.
+    fn find(&self, host: &str) -> Option<PinSet> {
+        let host_lower = host_key(host);
 
         // Check for exact domain match
         if let Some(pin_set) = self.pins.get(&host_lower) {
-            return self.verify_pins(&pin_set, cert_hashes);
+            return Some(pin_set.clone());
         }
 
         // Check parent domains for wildcard pins
@@ -144,27 +462,11 @@ impl PinStore {
             current = &current[idx + 1..];
             if let Some(pin_set) = self.pins.get(current) {
                 if pin_set.include_subdomains {
-                    return self.verify_pins(&pin_set, cert_hashes);
+                    return Some(pin_set.clone());
                 }
             }
         }
-
-        // No pins configured for this host - allow
-        Ok(())
-    }
-
-    fn verify_pins(&self, pin_set: &PinSet, cert_hashes: &[SpkiHash]) -> Result<(), NetError> {
-        // Expired pins fail-open (like Chromium)
-        if pin_set.is_expired() {
-            return Ok(());
-        }
-
-        // Check if any cert in chain matches any pin
-        if pin_set.matches(cert_hashes) {
-            Ok(())
-        } else {
-            Err(NetError::CertPinningFailed)
-        }
+        None
     }
 
     /// Get the number of pinned domains.
@@ -202,9 +504,40 @@ pub fn spki_hash(cert_der: &[u8]) -> Result<SpkiHash, NetError> {
     Ok(result)
 }
 
+/// `chain` as PEM certificates, skipping any that do not parse.
+fn pem_chain<C: AsRef<[u8]>>(chain: &[C]) -> Vec<String> {
+    chain
+        .iter()
+        .filter_map(|cert| {
+            boring::x509::X509::from_der(cert.as_ref())
+                .ok()?
+                .to_pem()
+                .ok()
+        })
+        .filter_map(|pem| String::from_utf8(pem).ok())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(Url, PinViolationReport)>>>);
+
+    impl PinReportSender for Recorder {
+        fn send(&self, report_uri: &Url, report: &PinViolationReport) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((report_uri.clone(), report.clone()));
+        }
+    }
+
+    fn pin_header(hash: SpkiHash, directives: &str) -> String {
+        format!("pin-sha256=\"{}\"; {directives}", STANDARD.encode(hash))
+    }
 
     #[test]
     fn test_pin_set_new() {
@@ -300,4 +633,76 @@ mod tests {
         let result = store.check("example.com", &[hash]);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_pins_learned_from_header() {
+        let store = PinStore::new();
+        store.add_from_header(
+            "Example.com",
+            &pin_header([5u8; 32], "max-age=3600; includeSubDomains"),
+        );
+        assert_eq!(store.len(), 1);
+        assert!(store.check("www.example.com", &[[5u8; 32]]).is_ok());
+        assert!(store.check("www.example.com", &[[6u8; 32]]).is_err());
+        let expires = store.pin_sets()[0].expires.unwrap();
+        assert!(expires > OffsetDateTime::now_utc() + time::Duration::minutes(59));
+
+        // Without max-age or pins, or from an IP literal, nothing is learned
+        store.add_from_header("a.test", &pin_header([5u8; 32], "includeSubDomains"));
+        store.add_from_header("b.test", "max-age=3600");
+        store.add_from_header("127.0.0.1", &pin_header([5u8; 32], "max-age=3600"));
+        assert_eq!(store.len(), 1);
+
+        store.add_from_header("example.com", &pin_header([5u8; 32], "max-age=0"));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_report_only_reports_without_failing() {
+        let recorder = Recorder::default();
+        let store = PinStore::new().with_report_sender(recorder.clone());
+        store.add_report_only_from_header(
+            "example.com",
+            &pin_header(
+                [5u8; 32],
+                "max-age=60; includeSubDomains; report-uri=\"https://r.test/hpkp\"",
+            ),
+        );
+
+        assert!(store.check("api.example.com", &[[6u8; 32]]).is_ok());
+        assert!(store.check("api.example.com", &[[5u8; 32]]).is_ok());
+        let reports = recorder.0.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        let (uri, report) = &reports[0];
+        assert_eq!(uri.as_str(), "https://r.test/hpkp");
+        assert_eq!(report.hostname, "api.example.com");
+        assert_eq!(report.noted_hostname, "example.com");
+        assert!(report.report_only);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["port"], 443);
+        assert_eq!(json["include-subdomains"], true);
+        assert_eq!(
+            json["known-pins"][0],
+            format!("pin-sha256=\"{}\"", STANDARD.encode([5u8; 32]))
+        );
+        assert!(json["effective-expiration-date"].is_string());
+    }
+
+    #[test]
+    fn test_enforced_violation_reported_and_failed() {
+        let recorder = Recorder::default();
+        let store = PinStore::new().with_report_sender(recorder.clone());
+        let mut pin_set =
+            PinSet::new("example.com").report_uri(Url::parse("https://r.test/").unwrap());
+        pin_set.add_pin([1u8; 32]);
+        store.add(pin_set);
+        store.add(PinSet::new("quiet.test"));
+
+        assert!(store.check("example.com", &[[2u8; 32]]).is_err());
+        assert!(store.check("quiet.test", &[[2u8; 32]]).is_err());
+        let reports = recorder.0.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(!reports[0].1.report_only);
+    }
 }
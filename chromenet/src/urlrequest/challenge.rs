@@ -0,0 +1,417 @@
+//! Interstitial challenges answered and the request retried.
+//!
+//! Chromium mapping: none; a browser renders the interstitial and the page
+//! script retries the navigation
+//!
+//! Bot-protection services answer a request with a challenge, a 403 or 503
+//! page or a redirect, and let it through once it comes back with a
+//! clearance cookie or header. A [`Challenges`] registry pairs a
+//! [`ChallengeDetector`], which recognizes a vendor's challenge by status,
+//! headers and body, with a [`ChallengeHandler`] computing the cookies and
+//! headers that answer it. The job checks every response, including each
+//! redirect hop, against the detectors in registration order; the handler
+//! of the first match gets the challenge, and the request is sent again to
+//! the same URL with its solution. Solvers live outside the crate:
+//!
+//! ```no_run
+//! use chromenet::base::neterror::NetError;
+//! use chromenet::urlrequest::challenge::{
+//!     Challenge, ChallengeDetector, ChallengeHandler, ChallengeSolution, Challenges,
+//! };
+//! use futures::future::BoxFuture;
+//!
+//! struct EchoToken;
+//!
+//! impl ChallengeHandler for EchoToken {
+//!     fn solve<'a>(
+//!         &'a self,
+//!         challenge: &'a Challenge,
+//!     ) -> BoxFuture<'a, Result<Option<ChallengeSolution>, NetError>> {
+//!         Box::pin(async move {
+//!             let token = challenge.headers.get("x-challenge-token").cloned();
+//!             Ok(token.and_then(|token| {
+//!                 let token = token.to_str().ok()?;
+//!                 Some(ChallengeSolution::new().cookie(&format!("clearance={token}; Path=/")))
+//!             }))
+//!         })
+//!     }
+//! }
+//!
+//! let challenges = Challenges::new().with(
+//!     ChallengeDetector::new()
+//!         .status(503)
+//!         .header("x-challenge-token")
+//!         .body_contains("Checking your browser"),
+//!     EchoToken,
+//! );
+//! let client = chromenet::Client::builder().challenges(challenges).build();
+//! ```
+//!
+//! Cookies of a solution go to the request's cookie jar, so later requests
+//! carry them too; they are not sent if the request has cookies
+//! [disabled](crate::RequestBuilder::no_cookies). Headers of a solution
+//! replace same-named headers of the request and stay on it for its
+//! redirects. After [`Challenges::with_max_attempts`] answered challenges,
+//! or if the handler declines with `Ok(None)`, the challenge response is
+//! returned to the caller as is; an error of the handler fails the request.
+
+use crate::base::neterror::NetError;
+use crate::base::secret::REDACTED;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{Extensions, HeaderMap, HeaderName, Method, StatusCode};
+use std::fmt;
+use std::sync::Arc;
+use url::Url;
+
+/// Default number of challenges answered for one request.
+pub const DEFAULT_MAX_ATTEMPTS: u8 = 3;
+
+/// Default limit on the body of a challenge response, 1 MiB.
+pub const DEFAULT_BODY_LIMIT: usize = 1024 * 1024;
+
+type BodyPredicate = Arc<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
+/// Recognizes the challenge responses of one vendor.
+///
+/// A response matches when it has one of the statuses, every header and a
+/// body the predicates accept. A detector without conditions matches every
+/// response.
+#[derive(Clone, Default)]
+pub struct ChallengeDetector {
+    statuses: Vec<StatusCode>,
+    headers: Vec<(HeaderName, Option<String>)>,
+    body: Vec<BodyPredicate>,
+}
+
+impl fmt::Debug for ChallengeDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChallengeDetector")
+            .field("statuses", &self.statuses)
+            .field("headers", &self.headers)
+            .field("body_predicates", &self.body.len())
+            .finish()
+    }
+}
+
+impl ChallengeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match responses with `status`, or any of the statuses of several
+    /// calls.
+    ///
+    /// # Panics
+    ///
+    /// If `status` is not a valid status code.
+    pub fn status(mut self, status: u16) -> Self {
+        self.statuses
+            .push(StatusCode::from_u16(status).expect("valid status code"));
+        self
+    }
+
+    /// Match responses with header `name`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is not a valid header name.
+    pub fn header(mut self, name: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes()).expect("valid header name");
+        self.headers.push((name, None));
+        self
+    }
+
+    /// Match responses with a header `name` whose value contains `value`,
+    /// ignoring ASCII case.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is not a valid header name.
+    pub fn header_contains(mut self, name: &str, value: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes()).expect("valid header name");
+        self.headers.push((name, Some(value.to_ascii_lowercase())));
+        self
+    }
+
+    /// Match responses whose body contains `needle`.
+    pub fn body_contains(self, needle: impl Into<Vec<u8>>) -> Self {
+        let needle = needle.into();
+        self.body_matches(move |body| {
+            needle.is_empty() || body.windows(needle.len()).any(|w| w == needle.as_slice())
+        })
+    }
+
+    /// Match responses whose body `predicate` accepts. The body is decoded
+    /// from its content codings if the client's
+    /// [decoders](crate::ClientBuilder::content_decoders), or the default
+    /// ones, can.
+    pub fn body_matches<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&[u8]) -> bool + Send + Sync + 'static,
+    {
+        self.body.push(Arc::new(predicate));
+        self
+    }
+
+    /// Whether a response with `status` and `headers` may be a challenge,
+    /// before its body is looked at.
+    pub fn matches_head(&self, status: StatusCode, headers: &HeaderMap) -> bool {
+        (self.statuses.is_empty() || self.statuses.contains(&status))
+            && self.headers.iter().all(|(name, value)| {
+                headers.get_all(name).iter().any(|v| match value {
+                    None => true,
+                    Some(value) => v
+                        .to_str()
+                        .is_ok_and(|v| v.to_ascii_lowercase().contains(value.as_str())),
+                })
+            })
+    }
+
+    /// Whether the detector looks at bodies.
+    pub fn checks_body(&self) -> bool {
+        !self.body.is_empty()
+    }
+
+    /// Whether every body predicate accepts `body`.
+    pub fn matches_body(&self, body: &[u8]) -> bool {
+        self.body.iter().all(|predicate| predicate(body))
+    }
+}
+
+/// A challenge response, with its body read.
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    /// URL of the request that was challenged.
+    pub url: Url,
+    pub method: Method,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// The body, decoded from its content codings where possible.
+    pub body: Bytes,
+    /// Challenges already answered for this request.
+    pub attempt: u8,
+    /// Values attached to the request with
+    /// [`RequestBuilder::extension`](crate::client::RequestBuilder::extension).
+    pub extensions: Extensions,
+}
+
+/// Cookies and headers to send the challenged request again with.
+///
+/// Clearance tokens are credentials: `Debug` output shows cookie and
+/// header names only.
+#[derive(Clone, Default)]
+pub struct ChallengeSolution {
+    cookies: Vec<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl fmt::Debug for ChallengeSolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cookies: Vec<_> = self
+            .cookies
+            .iter()
+            .map(|cookie| cookie.split('=').next().unwrap_or_default().trim())
+            .collect();
+        let headers: Vec<_> = self.headers.iter().map(|(name, _)| name).collect();
+        f.debug_struct("ChallengeSolution")
+            .field("cookies", &cookies)
+            .field("headers", &headers)
+            .field("values", &REDACTED)
+            .finish()
+    }
+}
+
+impl ChallengeSolution {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a cookie for the challenged URL, given as a `Set-Cookie`
+    /// value such as `clearance=abc; Path=/; Max-Age=1800`.
+    pub fn cookie(mut self, set_cookie: &str) -> Self {
+        self.cookies.push(set_cookie.to_string());
+        self
+    }
+
+    /// Send header `name` with `value`, replacing the request's own.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// `Set-Cookie` values to store.
+    pub fn cookies(&self) -> &[String] {
+        &self.cookies
+    }
+
+    /// Headers to send.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
+
+/// Answers the challenges of a [`ChallengeDetector`].
+pub trait ChallengeHandler: Send + Sync {
+    /// The solution to `challenge`, or `None` to give up and return the
+    /// challenge response.
+    fn solve<'a>(
+        &'a self,
+        challenge: &'a Challenge,
+    ) -> BoxFuture<'a, Result<Option<ChallengeSolution>, NetError>>;
+}
+
+/// Challenge detectors and their handlers.
+#[derive(Clone)]
+pub struct Challenges {
+    handlers: Vec<(ChallengeDetector, Arc<dyn ChallengeHandler>)>,
+    max_attempts: u8,
+    body_limit: usize,
+}
+
+impl Default for Challenges {
+    fn default() -> Self {
+        Self {
+            handlers: Vec::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            body_limit: DEFAULT_BODY_LIMIT,
+        }
+    }
+}
+
+impl fmt::Debug for Challenges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let detectors: Vec<_> = self.handlers.iter().map(|(d, _)| d).collect();
+        f.debug_struct("Challenges")
+            .field("detectors", &detectors)
+            .field("max_attempts", &self.max_attempts)
+            .field("body_limit", &self.body_limit)
+            .finish()
+    }
+}
+
+impl Challenges {
+    /// A registry without detectors, answering up to 3 challenges per
+    /// request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the challenges `detector` recognizes with `handler`.
+    /// Detectors are tried in the order they were added.
+    pub fn with<H>(mut self, detector: ChallengeDetector, handler: H) -> Self
+    where
+        H: ChallengeHandler + 'static,
+    {
+        self.handlers.push((detector, Arc::new(handler)));
+        self
+    }
+
+    /// Answer at most `attempts` challenges per request, redirects
+    /// included; the next one is returned as the response.
+    pub fn with_max_attempts(mut self, attempts: u8) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Read at most `limit` bytes of a possible challenge's body. A
+    /// response with a longer body is not a challenge and is returned as
+    /// it is.
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u8 {
+        self.max_attempts
+    }
+
+    pub fn body_limit(&self) -> usize {
+        self.body_limit
+    }
+
+    /// Detectors and handlers whose detector may match a response with
+    /// `status` and `headers`, leaving out those that check the body unless
+    /// `with_body`.
+    pub(crate) fn candidates(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+        with_body: bool,
+    ) -> Vec<(ChallengeDetector, Arc<dyn ChallengeHandler>)> {
+        self.handlers
+            .iter()
+            .filter(|(detector, _)| {
+                (with_body || !detector.checks_body()) && detector.matches_head(status, headers)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    struct Never;
+
+    impl ChallengeHandler for Never {
+        fn solve<'a>(
+            &'a self,
+            _challenge: &'a Challenge,
+        ) -> BoxFuture<'a, Result<Option<ChallengeSolution>, NetError>> {
+            Box::pin(async { Ok(None) })
+        }
+    }
+
+    #[test]
+    fn test_detector_matches() {
+        let detector = ChallengeDetector::new()
+            .status(403)
+            .status(503)
+            .header_contains("Server", "shield")
+            .body_contains("challenge-form");
+        let mut headers = HeaderMap::new();
+        headers.insert("server", HeaderValue::from_static("Vendor-Shield/2"));
+
+        assert!(detector.matches_head(StatusCode::SERVICE_UNAVAILABLE, &headers));
+        assert!(!detector.matches_head(StatusCode::OK, &headers));
+        assert!(!detector.matches_head(StatusCode::FORBIDDEN, &HeaderMap::new()));
+        assert!(detector.matches_body(b"<form id=\"challenge-form\">"));
+        assert!(!detector.matches_body(b"<p>Forbidden</p>"));
+        assert!(ChallengeDetector::new().matches_head(StatusCode::OK, &HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_candidates_without_body() {
+        let challenges = Challenges::new()
+            .with(
+                ChallengeDetector::new().status(503).body_contains("x"),
+                Never,
+            )
+            .with(
+                ChallengeDetector::new().status(302).header("cf-mitigated"),
+                Never,
+            );
+        let mut headers = HeaderMap::new();
+        headers.insert("cf-mitigated", HeaderValue::from_static("challenge"));
+
+        let service_unavailable = StatusCode::SERVICE_UNAVAILABLE;
+        assert_eq!(
+            challenges
+                .candidates(service_unavailable, &headers, true)
+                .len(),
+            1
+        );
+        assert!(challenges
+            .candidates(service_unavailable, &headers, false)
+            .is_empty());
+        assert_eq!(
+            challenges
+                .candidates(StatusCode::FOUND, &headers, false)
+                .len(),
+            1
+        );
+    }
+}
@@ -4,8 +4,9 @@
 //! configuration point for network stack components.
 
 use crate::cookies::monster::CookieMonster;
-use crate::dns::{DnsResolverWithOverrides, HickoryResolver, Resolve};
+use crate::dns::{default_resolver, DnsResolverWithOverrides, Resolve};
 use crate::http::streamfactory::HttpStreamFactory;
+use crate::socket::grouplimits::GroupLimits;
 use crate::socket::pool::ClientSocketPool;
 use crate::socket::proxy::ProxySettings;
 use crate::socket::tls::TlsOptions;
@@ -14,6 +15,7 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio_util::sync::{CancellationToken, DropGuard};
 
 /// Configuration options for URLRequestContext.
 #[derive(Clone)]
@@ -39,7 +41,7 @@ pub struct URLRequestContextConfig {
     /// TLS options (overrides device if both set).
     pub tls_options: Option<TlsOptions>,
 
-    /// Custom DNS resolver (None = use HickoryResolver).
+    /// Custom DNS resolver (None = [`default_resolver`]).
     pub dns_resolver: Option<Arc<dyn Resolve>>,
 
     /// DNS hostname overrides (hostname -> addresses).
@@ -104,6 +106,9 @@ pub struct URLRequestContext {
 
     /// Configuration options.
     config: URLRequestContextConfig,
+
+    /// Stops the idle socket cleanup task when the context is dropped.
+    _cleanup: DropGuard,
 }
 
 impl URLRequestContext {
@@ -122,10 +127,8 @@ impl URLRequestContext {
         }
 
         // Setup DNS resolver with optional overrides
-        let base_resolver: Arc<dyn Resolve> = config
-            .dns_resolver
-            .clone()
-            .unwrap_or_else(|| Arc::new(HickoryResolver::new()));
+        let base_resolver: Arc<dyn Resolve> =
+            config.dns_resolver.clone().unwrap_or_else(default_resolver);
 
         let resolver: Arc<dyn Resolve> = if config.dns_overrides.is_empty() {
             base_resolver
@@ -136,12 +139,17 @@ impl URLRequestContext {
             ))
         };
 
-        let socket_pool = Arc::new(ClientSocketPool::new(config.tls_options.clone()));
+        let socket_pool = Arc::new(
+            ClientSocketPool::new(config.tls_options.clone())
+                .with_group_limits(GroupLimits::new(config.max_sockets_per_group))
+                .with_resolver(resolver.clone()),
+        );
         let cookie_store = Arc::new(CookieMonster::new());
         let stream_factory = Arc::new(HttpStreamFactory::new(Arc::clone(&socket_pool)));
 
         // Start idle socket cleanup task
-        socket_pool.start_cleanup_task();
+        let cleanup = CancellationToken::new();
+        socket_pool.start_cleanup_task(cleanup.clone());
 
         Self {
             stream_factory,
@@ -149,6 +157,7 @@ impl URLRequestContext {
             cookie_store,
             resolver,
             config,
+            _cleanup: cleanup.drop_guard(),
         }
     }
 
@@ -1,15 +1,39 @@
 use crate::base::loadstate::LoadState;
 use crate::base::neterror::NetError;
-use crate::http::streamfactory::{HttpStreamFactory, StreamBody};
+use crate::base::networkisolationkey::NetworkIsolationKey;
+use crate::base::requestid::RequestId;
+use crate::base::secret::SecretString;
+use crate::dns::PostResolve;
+use crate::http::contentdecoder::ContentDecoders;
+use crate::http::httpcache::{worth_storing, CacheMode, HttpCache};
+use crate::http::priority::PriorityHeader;
+use crate::http::shareddictionary::{
+    accept_encoding_with_dictionaries, DictionaryEncoding, SharedDictionaryStore,
+    AVAILABLE_DICTIONARY, DICTIONARY_ID,
+};
+use crate::http::streamfactory::{HttpStreamFactory, HttpVersionPref, StreamBody};
 use crate::http::transaction::HttpNetworkTransaction;
-use crate::http::RequestBody;
-use http::{Method, Response};
+use crate::http::{HeaderLimits, HttpResponse, RequestBody};
+use http::{Extensions, HeaderMap, Method, Response, StatusCode};
 use std::collections::HashSet;
 use std::sync::Arc;
+use tracing::Instrument;
 use url::Url;
 
 use crate::cookies::monster::CookieMonster;
+use crate::cookies::samesite::SameSiteContext;
+use crate::socket::authcache::AuthCache;
+use crate::socket::connectto::ConnectTo;
+use crate::socket::pool::{ConnectionReuse, RequestPriority};
+use crate::socket::tls::ServerName;
+use crate::tls::certverify::CertPolicy;
+use crate::tls::hsts::HstsStore;
+use crate::urlrequest::challenge::{Challenge, Challenges};
 use crate::urlrequest::device::Device;
+use crate::urlrequest::redirect::{self, BodyDrain, RedirectHop, DEFAULT_MAX_REDIRECT_DRAIN};
+use crate::urlrequest::robots::{robots_url, Robots, RobotsMode, RobotsTxt, RobotsVerdict};
+use crate::urlrequest::rules::{HeaderEdit, ResourceType, RuleSet};
+use crate::urlrequest::urllimits::UrlLimits;
 
 /// Compute the method to use after a redirect.
 /// Mirrors Chromium's ComputeMethodForRedirect in redirect_info.cc.
@@ -27,18 +51,135 @@ fn compute_method_for_redirect(method: &Method, status_code: u16) -> Method {
     }
 }
 
+/// Delay in seconds and target of a `Refresh` header value such as
+/// `0; url=/next`, parsed like HTML's shared declarative refresh steps.
+///
+/// `None` if the value is malformed or names no URL.
+fn parse_refresh(value: &str) -> Option<(u64, &str)> {
+    let value = value.trim_start();
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let delay = value[..end].parse().ok()?;
+
+    // A fraction is allowed and ignored
+    let rest = value[end..].trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let after = rest.trim_start();
+    let rest = match after.strip_prefix([';', ',']) {
+        Some(rest) => rest.trim_start(),
+        None if after.len() < rest.len() => after,
+        None => return None,
+    };
+
+    let target = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            match rest[3..].trim_start().strip_prefix('=') {
+                Some(target) => target.trim_start(),
+                None => rest,
+            }
+        }
+        _ => rest,
+    };
+    let target = match target.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let target = &target[1..];
+            target.find(quote).map_or(target, |end| &target[..end])
+        }
+        _ => target,
+    };
+    let target = target.trim_end();
+    (!target.is_empty()).then_some((delay, target))
+}
+
+/// Where `response` to a request for `url` redirects, with the status
+/// code that decides the method of the next request.
+///
+/// Only a 3xx `Location` counts unless `follow_refresh` also allows an
+/// immediate `Refresh` header.
+fn redirect_target(
+    url: &Url,
+    response: &Response<StreamBody>,
+    follow_refresh: bool,
+) -> Option<(Url, u16)> {
+    let status = response.status();
+    if status.is_redirection() {
+        if let Some(location) = response.headers().get("Location") {
+            // Resolve potentially relative URL
+            let target = location.to_str().ok()?;
+            return url.join(target).ok().map(|url| (url, status.as_u16()));
+        }
+    }
+    if !follow_refresh {
+        return None;
+    }
+    let refresh = response.headers().get("Refresh")?.to_str().ok()?;
+    match parse_refresh(refresh)? {
+        // Refresh navigates like a 303: the next request is a GET
+        (0, target) => url.join(target).ok().map(|url| (url, 303)),
+        _ => None,
+    }
+}
+
 pub struct URLRequestHttpJob {
+    id: RequestId,
     transaction: HttpNetworkTransaction,
     factory: Arc<HttpStreamFactory>,
     url: Url,
     method: Method,
     body: RequestBody,
     cookie_store: Arc<CookieMonster>,
+    allow_cookies: bool,
+    /// Site the request is made from, `None` for a top-level navigation
+    site_for_cookies: Option<Url>,
+    /// URLs the request was sent to, the current one last
+    url_chain: Vec<Url>,
     device: Option<Device>,
     proxy_settings: Option<crate::socket::proxy::ProxySettings>,
+    network_isolation_key: Option<NetworkIsolationKey>,
+    server_name: ServerName,
+    connect_to: Vec<ConnectTo>,
+    post_resolve: Option<PostResolve>,
+    cert_policy: CertPolicy,
     redirect_limit: u8,
     visited_urls: HashSet<String>,
     extra_headers: Vec<(String, String)>,
+    http1_options: Option<crate::emulation::Http1Options>,
+    h2_fingerprint: Option<crate::http::H2Fingerprint>,
+    emulation_profile: Option<crate::emulation::EmulationProfile>,
+    header_limits: HeaderLimits,
+    url_limits: UrlLimits,
+    version_pref: HttpVersionPref,
+    http1_0: bool,
+    priority: RequestPriority,
+    priority_header: PriorityHeader,
+    incremental: bool,
+    connection_reuse: ConnectionReuse,
+    auth_cache: Option<AuthCache>,
+    credentials: Option<(String, SecretString)>,
+    rules: Option<Arc<RuleSet>>,
+    resource_type: ResourceType,
+    robots: Option<Robots>,
+    robots_verdict: Option<RobotsVerdict>,
+    follow_refresh: bool,
+    max_redirect_drain: usize,
+    /// Redirects followed so far
+    redirects: Vec<RedirectHop>,
+    cache: Option<Arc<HttpCache>>,
+    cache_mode: CacheMode,
+    /// Whether the current transaction revalidates a stale cache entry
+    revalidating: bool,
+    /// Response served by the cache instead of the transaction
+    cached_response: Option<HttpResponse>,
+    shared_dictionaries: Option<SharedDictionaryStore>,
+    /// Decoders of the final response's content codings, if it is decoded
+    content_decoders: Option<ContentDecoders>,
+    challenges: Option<Challenges>,
+    hsts: Option<HstsStore>,
+    /// Challenges answered so far
+    challenge_attempts: u8,
+    /// Values attached by the caller, handed to challenge handlers and the
+    /// response
+    extensions: Extensions,
 }
 
 impl URLRequestHttpJob {
@@ -51,138 +192,827 @@ impl URLRequestHttpJob {
         visited.insert(url.to_string());
 
         Self {
+            id: RequestId::next(),
             transaction: HttpNetworkTransaction::new(
                 factory.clone(),
                 url.clone(),
                 cookie_store.clone(),
             ),
             factory,
+            url_chain: vec![url.clone()],
             url,
             method: Method::GET,
             body: RequestBody::default(),
             cookie_store,
+            allow_cookies: true,
+            site_for_cookies: None,
             device: None,
             proxy_settings: None,
+            network_isolation_key: None,
+            server_name: ServerName::Host,
+            connect_to: Vec::new(),
+            post_resolve: None,
+            cert_policy: CertPolicy::default(),
             redirect_limit: 20, // Chromium default is 20
             visited_urls: visited,
             extra_headers: Vec::new(),
+            http1_options: None,
+            h2_fingerprint: None,
+            emulation_profile: None,
+            header_limits: HeaderLimits::default(),
+            url_limits: UrlLimits::default(),
+            version_pref: HttpVersionPref::default(),
+            http1_0: false,
+            priority: RequestPriority::default(),
+            priority_header: PriorityHeader::default(),
+            incremental: false,
+            connection_reuse: ConnectionReuse::default(),
+            auth_cache: None,
+            credentials: None,
+            rules: None,
+            resource_type: ResourceType::default(),
+            robots: None,
+            robots_verdict: None,
+            follow_refresh: false,
+            max_redirect_drain: DEFAULT_MAX_REDIRECT_DRAIN,
+            redirects: Vec::new(),
+            cache: None,
+            cache_mode: CacheMode::default(),
+            revalidating: false,
+            cached_response: None,
+            shared_dictionaries: None,
+            content_decoders: None,
+            challenges: None,
+            hsts: None,
+            challenge_attempts: 0,
+            extensions: Extensions::new(),
         }
     }
 
+    /// URL the request is sent to next, or was last sent to.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Identifier of the request, kept across redirects.
+    pub fn id(&self) -> RequestId {
+        self.id
+    }
+
     /// Set the HTTP method.
     pub fn set_method(&mut self, method: Method) {
+        self.transaction.set_method(method.clone());
         self.method = method;
     }
 
     /// Set the request body.
+    ///
+    /// Bodies are resent on 307/308 redirects; streaming bodies must be
+    /// wrapped in a [`RewindableBody`](crate::http::RewindableBody).
     pub fn set_body(&mut self, body: impl Into<RequestBody>) {
         self.body = body.into();
+        self.transaction.set_body(self.body.clone());
     }
 
+    /// Run the request, following redirects.
+    ///
+    /// Runs in a `URLRequest` span with the request's ID, so everything
+    /// logged for the request on the way carries it.
     pub async fn start(&mut self) -> Result<(), NetError> {
+        let span = tracing::debug_span!(
+            target: "chromenet::urlrequest",
+            "URLRequest",
+            request.id = self.id.get(),
+        );
+        self.run().instrument(span).await
+    }
+
+    /// Set the first hop up the way [`start`](Self::start) would and
+    /// return the headers it would be sent with over HTTP/1.1, without
+    /// connecting or sending anything.
+    ///
+    /// robots.txt and the cache are not consulted.
+    pub(crate) fn prepare(&mut self) -> Result<HeaderMap, NetError> {
+        while self.begin_hop()? {}
+        self.check_url_limits()?;
+        self.advertise_dictionary()?;
+        self.transaction.prepare_headers(false)
+    }
+
+    async fn run(&mut self) -> Result<(), NetError> {
         loop {
-            // Apply Headers to current transaction
-            for (k, v) in &self.extra_headers {
-                self.transaction.add_header(k, v)?;
+            if self.begin_hop()? {
+                continue;
+            }
+            self.check_url_limits()?;
+            self.check_robots().await?;
+            self.advertise_dictionary()?;
+            if self.check_cache()? {
+                self.use_dictionaries().await?;
+                self.decode_body().await?;
+                break;
             }
 
             // Start current transaction
             self.transaction.start().await?;
+            self.note_hsts();
+            if self.answer_challenge().await? {
+                continue;
+            }
 
             // Check for redirect
-            let should_redirect = if let Some(response) = self.transaction.get_response() {
-                let status = response.status();
-                if status.is_redirection() {
-                    if let Some(location) = response.headers().get("Location") {
-                        if let Ok(loc_str) = location.to_str() {
-                            // Resolve potentially relative URL
-                            self.url.join(loc_str).ok()
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
+            let redirect = match self.transaction.get_response() {
+                Some(response) => redirect_target(&self.url, response, self.follow_refresh),
+                None => None,
             };
 
-            if let Some(mut new_url) = should_redirect {
-                if self.redirect_limit == 0 {
-                    return Err(NetError::TooManyRedirects);
-                }
-
-                // Get status code for method computation
-                let status_code = self
-                    .transaction
-                    .get_response()
-                    .map(|r| r.status().as_u16())
-                    .unwrap_or(0);
-
+            if let Some((new_url, status_code)) = redirect {
                 // Compute new method per RFC 7231 (Chromium's ComputeMethodForRedirect)
                 let new_method = compute_method_for_redirect(&self.method, status_code);
 
-                // If method changed to GET, clear the body
+                // If method changed to GET, clear the body and the headers
+                // describing it (Chromium's RedirectUtil::UpdateHttpRequest)
                 if new_method != self.method && new_method == Method::GET {
                     self.body = RequestBody::default();
+                    self.extra_headers.retain(|(k, _)| {
+                        !k.eq_ignore_ascii_case("Content-Type")
+                            && !k.eq_ignore_ascii_case("Content-Length")
+                            && !k.eq_ignore_ascii_case("Content-Digest")
+                            && !k.eq_ignore_ascii_case("Repr-Digest")
+                            && !k.eq_ignore_ascii_case("Origin")
+                    });
                 }
                 self.method = new_method;
 
-                // Check for redirect cycle (exact URL match)
-                if !self.visited_urls.insert(new_url.to_string()) {
-                    return Err(NetError::RedirectCycleDetected);
-                }
+                self.drain_redirect().await;
+                self.follow_redirect(new_url)?;
 
-                // Check Cross-Origin for Auth Stripping
-                let is_cross_origin = self.url.origin() != new_url.origin();
+                // CONTINUE LOOP
+            } else {
+                // Done or error
+                self.update_cache().await?;
+                self.use_dictionaries().await?;
+                self.decode_body().await?;
+                break;
+            }
+        }
+        Ok(())
+    }
 
-                if is_cross_origin {
-                    self.extra_headers
-                        .retain(|(k, _)| !k.eq_ignore_ascii_case("Authorization"));
-                    // Strip credentials from URL (CVE-2014-1829 fix)
-                    let _ = new_url.set_username("");
-                    let _ = new_url.set_password(None);
-                }
+    /// Set the transaction up for the current hop: upgrade it to HTTPS if
+    /// HSTS asks for it, add the request's headers and its SameSite
+    /// context, and apply the rules.
+    ///
+    /// Returns `true` if the rules sent the request to another URL.
+    fn begin_hop(&mut self) -> Result<bool, NetError> {
+        self.upgrade_hsts();
+        // Apply Headers to current transaction
+        for (k, v) in &self.extra_headers {
+            self.transaction.add_header(k, v)?;
+        }
+        // SameSite cookies for this hop, from the whole chain so far
+        self.transaction
+            .set_same_site_context(SameSiteContext::for_request(
+                &self.url_chain,
+                self.site_for_cookies.as_ref(),
+                &self.method,
+            ));
+        self.apply_rules()
+    }
+
+    /// Send an `http` request to a host HSTS knows over `https` instead,
+    /// port 80 becoming 443. Like Chromium's internal 307 redirect it keeps
+    /// the method, body and headers and does not count against the
+    /// redirect limit. The upgraded URL takes the place of the `http` one
+    /// in the chain, so the scheme change leaves the SameSite context as
+    /// it was.
+    ///
+    /// Chromium mapping: `URLRequestHttpJob::Start` and
+    /// `TransportSecurityState::ShouldUpgradeToSSL`
+    fn upgrade_hsts(&mut self) {
+        let Some(hsts) = &self.hsts else {
+            return;
+        };
+        if self.url.scheme() != "http" {
+            return;
+        }
+        let Some(host) = self.url.host_str() else {
+            return;
+        };
+        if !hsts.should_upgrade(host) {
+            return;
+        }
+        let mut upgraded = self.url.clone();
+        if upgraded.set_scheme("https").is_err() {
+            return;
+        }
+        tracing::debug!(target: "chromenet::urlrequest", from = %self.url, to = %upgraded, "HSTS upgrade");
+        self.visited_urls.insert(upgraded.to_string());
+        if let Some(last) = self.url_chain.last_mut() {
+            *last = upgraded.clone();
+        }
+        self.url = upgraded;
+        self.new_transaction();
+    }
+
+    /// Learn the `Strict-Transport-Security` header of a response received
+    /// over HTTPS. Headers on plain HTTP responses are ignored (RFC 6797
+    /// 8.1).
+    fn note_hsts(&mut self) {
+        let Some(hsts) = &self.hsts else {
+            return;
+        };
+        if self.url.scheme() != "https" {
+            return;
+        }
+        let (Some(host), Some(response)) = (self.url.host_str(), self.transaction.get_response())
+        else {
+            return;
+        };
+        if let Some(value) = response
+            .headers()
+            .get(http::header::STRICT_TRANSPORT_SECURITY)
+            .and_then(|v| v.to_str().ok())
+        {
+            hsts.add_from_header(host, value);
+        }
+    }
+
+    /// Drain the body of the redirect response, so its connection can
+    /// carry the next hop, and record the hop.
+    async fn drain_redirect(&mut self) {
+        let Some(mut response) = self.transaction.take_response() else {
+            return;
+        };
+        let drain = match response.take_body() {
+            Some(body) => redirect::drain(body, response.headers(), self.max_redirect_drain).await,
+            None => BodyDrain::Drained { bytes: 0 },
+        };
+        self.redirects.push(RedirectHop {
+            url: self.url.clone(),
+            status: response.status(),
+            drain,
+        });
+    }
+
+    /// Point the job at `new_url`, with a fresh transaction carrying over
+    /// the request's settings.
+    ///
+    /// Counts against the redirect limit and strips credentials when the
+    /// origin changes.
+    fn follow_redirect(&mut self, mut new_url: Url) -> Result<(), NetError> {
+        if self.redirect_limit == 0 {
+            return Err(NetError::TooManyRedirects);
+        }
 
-                self.redirect_limit -= 1;
-                self.url = new_url;
+        // Check for redirect cycle (exact URL match)
+        if !self.visited_urls.insert(new_url.to_string()) {
+            return Err(NetError::RedirectCycleDetected);
+        }
 
-                // Create new transaction for the new URL
-                self.transaction = HttpNetworkTransaction::new(
-                    self.factory.clone(),
-                    self.url.clone(),
-                    self.cookie_store.clone(),
-                );
+        // Check Cross-Origin for Auth Stripping
+        let is_cross_origin = self.url.origin() != new_url.origin();
 
-                // Restore device if set
-                if let Some(device) = &self.device {
-                    self.transaction.set_device(device.clone());
+        if is_cross_origin {
+            self.extra_headers.retain(|(k, _)| {
+                !k.eq_ignore_ascii_case("Authorization") && !k.eq_ignore_ascii_case("Host")
+            });
+            // The new origin did not make the request (Chromium's
+            // RedirectUtil::UpdateHttpRequestHeadersAfterRedirect)
+            for (k, v) in &mut self.extra_headers {
+                if k.eq_ignore_ascii_case("Origin") {
+                    *v = "null".to_string();
                 }
+            }
+            // Strip credentials from URL (CVE-2014-1829 fix)
+            let _ = new_url.set_username("");
+            let _ = new_url.set_password(None);
+            self.credentials = None;
+            // The overridden name was chosen for the original origin
+            self.server_name = ServerName::Host;
+        }
+
+        self.redirect_limit -= 1;
+        self.url_chain.push(new_url.clone());
+        self.url = new_url;
+        self.new_transaction();
+        Ok(())
+    }
+
+    /// Replace the transaction with a new one for the current URL,
+    /// carrying over the request's settings.
+    fn new_transaction(&mut self) {
+        self.transaction = HttpNetworkTransaction::new(
+            self.factory.clone(),
+            self.url.clone(),
+            self.cookie_store.clone(),
+        );
+
+        self.transaction.set_allow_cookies(self.allow_cookies);
+        self.transaction.set_method(self.method.clone());
+        self.transaction.set_body(self.body.clone());
+
+        // Restore device if set
+        if let Some(device) = &self.device {
+            self.transaction.set_device(device.clone());
+        }
+
+        // Restore proxy if set
+        if let Some(proxy) = &self.proxy_settings {
+            self.transaction.set_proxy(proxy.clone());
+        }
+        if let Some(nik) = &self.network_isolation_key {
+            self.transaction.set_network_isolation_key(nik.clone());
+        }
+        self.transaction.set_server_name(self.server_name.clone());
+        self.transaction.set_connect_to(self.connect_to.clone());
+        if let Some(hook) = &self.post_resolve {
+            self.transaction.set_post_resolve(hook.clone());
+        }
+        self.transaction.set_cert_policy(self.cert_policy.clone());
+
+        if let Some(options) = &self.http1_options {
+            self.transaction.set_http1_options(options.clone());
+        }
+        if let Some(fingerprint) = &self.h2_fingerprint {
+            self.transaction.set_h2_fingerprint(fingerprint.clone());
+        }
+        self.transaction.set_header_limits(self.header_limits);
+
+        self.transaction.set_version_pref(self.version_pref);
+        self.transaction.set_http1_0(self.http1_0);
+        self.transaction.set_priority(self.priority);
+        self.transaction
+            .set_priority_header(self.priority_header, self.incremental);
+        if self.connection_reuse == ConnectionReuse::Close {
+            self.transaction
+                .set_connection_reuse(ConnectionReuse::Close);
+        }
+
+        if let Some(cache) = &self.auth_cache {
+            self.transaction.set_auth_cache(cache.clone());
+        }
+        if let Some((username, password)) = &self.credentials {
+            self.transaction
+                .set_credentials(username, password.expose());
+        }
+    }
+
+    /// Answer a challenge in the response with the handler of the first
+    /// detector recognizing it, and set the request up to be sent again to
+    /// the same URL with the solution. Returns whether it was.
+    ///
+    /// A response whose body was read to look for a challenge becomes the
+    /// final response if none is answered, as does one whose body is over
+    /// the challenges' body limit, unchecked.
+    async fn answer_challenge(&mut self) -> Result<bool, NetError> {
+        let Some(challenges) = self.challenges.clone() else {
+            return Ok(false);
+        };
+        let Some(response) = self.transaction.get_response() else {
+            return Ok(false);
+        };
+        // Bodies of redirects the job follows are drained, not searched
+        let followed = redirect_target(&self.url, response, self.follow_refresh).is_some();
+        let candidates = challenges.candidates(response.status(), response.headers(), !followed);
+        if candidates.is_empty() {
+            return Ok(false);
+        }
+        if self.challenge_attempts >= challenges.max_attempts() {
+            tracing::debug!(target: "chromenet::urlrequest", url = %self.url, "challenge attempts exhausted");
+            return Ok(false);
+        }
+
+        let Some(response) = self.transaction.take_response() else {
+            return Ok(false);
+        };
+        let (response, complete) = response.buffer_within(challenges.body_limit()).await?;
+        if !complete {
+            tracing::debug!(target: "chromenet::urlrequest", url = %self.url, "body too big to look for a challenge");
+            self.cached_response = Some(response);
+            return Ok(false);
+        }
+        let raw = response.buffered_body().cloned().unwrap_or_default();
+        let decoders = self.content_decoders.clone().unwrap_or_default();
+        let body = decoders
+            .decode(response.headers(), raw.clone())
+            .unwrap_or(raw);
+        let Some((_, handler)) = candidates
+            .iter()
+            .find(|(detector, _)| detector.matches_body(&body))
+        else {
+            self.cached_response = Some(response);
+            return Ok(false);
+        };
 
-                // Restore proxy if set
-                if let Some(proxy) = &self.proxy_settings {
-                    self.transaction.set_proxy(proxy.clone());
+        let challenge = Challenge {
+            url: self.url.clone(),
+            method: self.method.clone(),
+            status: response.status(),
+            headers: response.headers().clone(),
+            body,
+            attempt: self.challenge_attempts,
+            extensions: self.extensions.clone(),
+        };
+        let Some(solution) = handler.solve(&challenge).await? else {
+            tracing::debug!(target: "chromenet::urlrequest", url = %self.url, status = %challenge.status, "challenge not answered");
+            self.cached_response = Some(response);
+            return Ok(false);
+        };
+        tracing::debug!(target: "chromenet::urlrequest", url = %self.url, status = %challenge.status, "answering challenge");
+        self.challenge_attempts += 1;
+
+        for cookie in solution.cookies() {
+            self.cookie_store.parse_and_save_cookie(&self.url, cookie);
+        }
+        for (name, value) in solution.headers() {
+            match self
+                .extra_headers
+                .iter_mut()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            {
+                Some((_, v)) => v.clone_from(value),
+                None => self.extra_headers.push((name.clone(), value.clone())),
+            }
+        }
+        self.new_transaction();
+        Ok(true)
+    }
+
+    /// Check the URL and request line of the request about to be sent
+    /// against the limits.
+    fn check_url_limits(&self) -> Result<(), NetError> {
+        // Plain-HTTP requests to an HTTP proxy carry the whole URL
+        let absolute_form = self.url.scheme() == "http"
+            && self
+                .proxy_settings
+                .as_ref()
+                .is_some_and(|proxy| !proxy.is_socks() && !proxy.should_bypass(&self.url));
+        self.url_limits
+            .check(&self.method, &self.url, absolute_form)
+    }
+
+    /// Apply the rule set to the request about to be sent.
+    ///
+    /// Returns `true` if the rules sent the request to another URL.
+    fn apply_rules(&mut self) -> Result<bool, NetError> {
+        let Some(rules) = &self.rules else {
+            return Ok(false);
+        };
+        let decision = rules.evaluate(&self.url, &self.method, self.resource_type);
+        if decision.block {
+            return Err(NetError::BlockedByClient);
+        }
+        if let Some(new_url) = decision.redirect {
+            self.follow_redirect(new_url)?;
+            return Ok(true);
+        }
+        for edit in decision.headers {
+            match edit {
+                HeaderEdit::Set(name, value) => {
+                    let value = value.to_str().map_err(|_| NetError::InvalidHeader)?;
+                    self.transaction.remove_header(name.as_str());
+                    self.transaction.add_header(name.as_str(), value)?;
                 }
+                HeaderEdit::Remove(name) => self.transaction.remove_header(name.as_str()),
+            }
+        }
+        Ok(false)
+    }
 
-                // CONTINUE LOOP
-            } else {
-                // Done or error
-                break;
+    /// Check the request about to be sent against the robots.txt of its
+    /// origin, fetching it on first use.
+    async fn check_robots(&mut self) -> Result<(), NetError> {
+        let Some(robots) = self.robots.clone() else {
+            return Ok(());
+        };
+        let robots_txt = match robots.get(&self.url) {
+            Some(robots_txt) => robots_txt,
+            None => {
+                let robots_txt = self.fetch_robots().await;
+                robots.insert(&self.url, robots_txt)
             }
+        };
+        let verdict = robots.verdict(&robots_txt, &self.url, self.header("User-Agent"));
+        if !verdict.allowed && robots.mode() == RobotsMode::Block {
+            return Err(NetError::BlockedByClient);
         }
+        self.robots_verdict = Some(verdict);
         Ok(())
     }
 
+    /// Whether the current request may use the cache: a GET or HEAD the
+    /// caller did not make conditional or partial itself.
+    fn cacheable_request(&self) -> bool {
+        const CALLER_VALIDATION: [&str; 6] = [
+            "If-None-Match",
+            "If-Modified-Since",
+            "If-Match",
+            "If-Unmodified-Since",
+            "If-Range",
+            "Range",
+        ];
+        self.cache.is_some()
+            && self.cache_mode != CacheMode::Disabled
+            && (self.method == Method::GET || self.method == Method::HEAD)
+            && !self.extra_headers.iter().any(|(k, _)| {
+                CALLER_VALIDATION
+                    .iter()
+                    .any(|name| k.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// Answer the current request from the cache if it has a fresh entry,
+    /// or add the validators of a stale one to revalidate it. With
+    /// [`CacheMode::Revalidate`] any stored entry is revalidated. Returns
+    /// whether the cache answered.
+    ///
+    /// Chromium mapping: `HttpCache::Transaction::DoCacheReadResponse` and
+    /// `BeginCacheValidation`
+    fn check_cache(&mut self) -> Result<bool, NetError> {
+        self.revalidating = false;
+        if !self.cacheable_request() || self.cache_mode == CacheMode::ForceRefresh {
+            return Ok(false);
+        }
+        let Some(cache) = self.cache.clone() else {
+            return Ok(false);
+        };
+        let nik = self.network_isolation_key.as_ref();
+        let method = self.method.as_str();
+        let validators = if self.cache_mode == CacheMode::Revalidate {
+            cache.get_validators_in(&self.url, method, nik, self.cache_mode)
+        } else {
+            if let Some(entry) = cache.get_in(&self.url, method, nik, self.cache_mode) {
+                tracing::debug!(target: "chromenet::http", url = %self.url, "served from cache");
+                self.cached_response = Some(entry.into_response());
+                return Ok(true);
+            }
+            cache.get_conditional_headers_in(&self.url, method, nik, self.cache_mode)
+        };
+        if let Some(validators) = validators {
+            for (name, value) in &validators {
+                let value = value.to_str().map_err(|_| NetError::InvalidHeader)?;
+                self.transaction.add_header(name.as_str(), value)?;
+            }
+            self.revalidating = true;
+        }
+        Ok(false)
+    }
+
+    /// Bring the cache up to date with the final response: a 304 to a
+    /// revalidation freshens the stored entry, which then answers the
+    /// request, and a response worth keeping is read and stored if it fits
+    /// the cache.
+    ///
+    /// Chromium mapping: `HttpCache::Transaction::DoUpdateCachedResponse`
+    /// and `DoCacheWriteResponse`
+    async fn update_cache(&mut self) -> Result<(), NetError> {
+        if !self.cacheable_request() {
+            return Ok(());
+        }
+        let Some(cache) = self.cache.clone() else {
+            return Ok(());
+        };
+        let Some(response) = self.transaction.get_response() else {
+            return Ok(());
+        };
+        let nik = self.network_isolation_key.clone();
+        let method = self.method.as_str();
+
+        if self.revalidating && response.status() == StatusCode::NOT_MODIFIED {
+            cache.update_from_not_modified_in(&self.url, method, nik.as_ref(), response);
+            if let Some(entry) =
+                cache.get_for_revalidation_in(&self.url, method, nik.as_ref(), self.cache_mode)
+            {
+                tracing::debug!(target: "chromenet::http", url = %self.url, "cache entry revalidated");
+                // A 304 has no body, the connection is free already
+                drop(self.transaction.take_response());
+                self.cached_response = Some(entry.into_response());
+            }
+            return Ok(());
+        }
+        if self.cache_mode == CacheMode::ReadOnly
+            || !worth_storing(response.headers(), response.status(), cache.now())
+        {
+            return Ok(());
+        }
+
+        let Some(response) = self.transaction.take_response() else {
+            return Ok(());
+        };
+        // Read no more than an entry can hold, the rest goes to the caller
+        let (response, complete) = response.buffer_within(cache.max_entry_size()).await?;
+        if !complete {
+            tracing::debug!(target: "chromenet::http", url = %self.url, "response too big to cache");
+            self.cached_response = Some(response);
+            return Ok(());
+        }
+        if let Some(body) = response.buffered_body() {
+            let mut head = Response::new(());
+            *head.status_mut() = response.status();
+            *head.headers_mut() = response.headers().clone();
+            cache.store_in(
+                &self.url,
+                method,
+                nik.as_ref(),
+                self.cache_mode,
+                &head,
+                body.clone(),
+            );
+        }
+        self.cached_response = Some(response);
+        Ok(())
+    }
+
+    /// Announce the shared dictionary matching the current URL, if any,
+    /// and accept the encodings that use it.
+    ///
+    /// Chromium mapping: `SharedDictionaryNetworkTransaction::Start`
+    fn advertise_dictionary(&mut self) -> Result<(), NetError> {
+        let Some(store) = &self.shared_dictionaries else {
+            return Ok(());
+        };
+        let nik = self.network_isolation_key.as_ref();
+        let Some(dictionary) = store.find_in(&self.url, nik, self.resource_type) else {
+            return Ok(());
+        };
+        let available = dictionary.available_dictionary();
+        let available = available.to_str().map_err(|_| NetError::InvalidHeader)?;
+        self.transaction
+            .add_header(AVAILABLE_DICTIONARY, available)?;
+        if let Some(id) = dictionary.dictionary_id() {
+            let id = id.to_str().map_err(|_| NetError::InvalidHeader)?;
+            self.transaction.add_header(DICTIONARY_ID, id)?;
+        }
+        let accept_encoding = self
+            .extra_headers
+            .iter()
+            .rev()
+            .find(|(k, _)| k.eq_ignore_ascii_case("Accept-Encoding"))
+            .map(|(_, v)| v.as_str());
+        let accept_encoding = accept_encoding_with_dictionaries(accept_encoding);
+        self.transaction
+            .add_header("Accept-Encoding", &accept_encoding)?;
+        Ok(())
+    }
+
+    /// Decode a final response compressed with a shared dictionary, and
+    /// keep one marked `Use-As-Dictionary` for later requests.
+    ///
+    /// Chromium mapping: `SharedDictionaryNetworkTransaction` and
+    /// `SharedDictionaryWriter`
+    async fn use_dictionaries(&mut self) -> Result<(), NetError> {
+        let Some(store) = self.shared_dictionaries.clone() else {
+            return Ok(());
+        };
+        let response = match self.cached_response.take() {
+            Some(response) => response,
+            None => match self.transaction.take_response() {
+                Some(response) => response,
+                None => return Ok(()),
+            },
+        };
+        let encoding = DictionaryEncoding::of(response.headers());
+        let keep = self.method == Method::GET
+            && store.wants(&self.url, response.status(), response.headers());
+        if encoding.is_none() && !keep {
+            self.cached_response = Some(response);
+            return Ok(());
+        }
+
+        let nik = self.network_isolation_key.clone();
+        let mut response = response.buffer().await?;
+        let body = response.buffered_body().cloned().unwrap_or_default();
+        if let Some(encoding) = encoding {
+            let dictionary = encoding
+                .dictionary_hash(&body)
+                .and_then(|hash| store.get_by_hash(&self.url, nik.as_ref(), &hash))
+                .ok_or(NetError::ContentDecodingFailed)?;
+            let decoded = encoding.decode(&body, &dictionary)?;
+            response = response.with_decoded_body(decoded);
+        }
+        if keep {
+            // Kept as the page would see it, without content codings
+            let body = response.buffered_body().cloned().unwrap_or_default();
+            let decoders = self.content_decoders.clone().unwrap_or_default();
+            match decoders.decode(response.headers(), body) {
+                Ok(body) => {
+                    store.insert_from_response(
+                        &self.url,
+                        nik.as_ref(),
+                        response.status(),
+                        response.headers(),
+                        body,
+                    );
+                }
+                Err(_) => {
+                    tracing::debug!(target: "chromenet::http", url = %self.url, "dictionary not decodable, not kept");
+                }
+            }
+        }
+        self.cached_response = Some(response);
+        Ok(())
+    }
+
+    /// Undo the content codings of the final response with the decoders
+    /// set, replacing its body with the decoded one.
+    ///
+    /// Chromium mapping: `URLRequestHttpJob::SetUpSourceStream`
+    async fn decode_body(&mut self) -> Result<(), NetError> {
+        let Some(decoders) = self.content_decoders.clone() else {
+            return Ok(());
+        };
+        let response = match self.cached_response.take() {
+            Some(response) => response,
+            None => match self.transaction.take_response() {
+                Some(response) => response,
+                None => return Ok(()),
+            },
+        };
+        if self.method == Method::HEAD || response.content_encodings().is_empty() {
+            self.cached_response = Some(response);
+            return Ok(());
+        }
+
+        let response = response.buffer().await?;
+        let body = response.buffered_body().cloned().unwrap_or_default();
+        // 204 and 304 responses keep their codings with nothing to decode
+        let response = if body.is_empty() {
+            response
+        } else {
+            let decoded = decoders.decode(response.headers(), body)?;
+            response.with_decoded_body(decoded)
+        };
+        self.cached_response = Some(response);
+        Ok(())
+    }
+
+    /// Fetch the robots.txt of the current origin with the request's
+    /// headers and connection settings.
+    async fn fetch_robots(&self) -> RobotsTxt {
+        let mut job = URLRequestHttpJob::new(
+            self.factory.clone(),
+            robots_url(&self.url),
+            self.cookie_store.clone(),
+        );
+        job.set_allow_cookies(self.allow_cookies);
+        for (k, v) in &self.extra_headers {
+            let describes_body = k
+                .get(..8)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("content-"));
+            if !describes_body && !k.eq_ignore_ascii_case("Authorization") {
+                job.add_header(k, v);
+            }
+        }
+        if let Some(device) = &self.device {
+            job.set_device(device.clone());
+        }
+        if let Some(proxy) = &self.proxy_settings {
+            job.set_proxy(proxy.clone());
+        }
+        if let Some(nik) = &self.network_isolation_key {
+            job.set_network_isolation_key(nik.clone());
+        }
+        if let Some(options) = &self.http1_options {
+            job.set_http1_options(options.clone());
+        }
+        job.set_header_limits(self.header_limits);
+        job.set_url_limits(self.url_limits);
+        job.set_connect_to(self.connect_to.clone());
+        job.set_cert_policy(self.cert_policy.clone());
+        job.set_version_pref(self.version_pref);
+        job.set_http1_0(self.http1_0);
+        job.set_priority(self.priority);
+
+        // Boxed, as `start` awaits this
+        match Box::pin(job.start()).await.map(|()| job.take_response()) {
+            Ok(Some(response)) => RobotsTxt::from_response(response).await,
+            _ => RobotsTxt::disallow_all(),
+        }
+    }
+
     pub fn get_response(&mut self) -> Option<&Response<StreamBody>> {
         self.transaction.get_response()
     }
 
     /// Take ownership of the response with body.
     pub fn take_response(&mut self) -> Option<crate::http::HttpResponse> {
-        self.transaction.take_response()
+        let response = match self.cached_response.take() {
+            Some(response) => response,
+            None => self
+                .transaction
+                .take_response()?
+                .with_emulation_profile(self.emulation_profile.clone()),
+        };
+        let response = response
+            .with_url(self.url.clone())
+            .with_request_id(self.id)
+            .with_redirects(self.redirects.clone())
+            .with_extensions(self.extensions.clone());
+        Some(match self.robots_verdict {
+            Some(verdict) => response.with_robots(verdict),
+            None => response,
+        })
     }
 
     pub fn set_device(&mut self, device: crate::urlrequest::device::Device) {
@@ -195,6 +1025,37 @@ impl URLRequestHttpJob {
         self.transaction.set_proxy(proxy);
     }
 
+    /// Partition connections for the whole redirect chain by `nik`.
+    ///
+    /// The key stays that of the original top frame across redirects, like
+    /// Chromium's `IsolationInfo` for subresources.
+    pub fn set_network_isolation_key(&mut self, nik: NetworkIsolationKey) {
+        self.network_isolation_key = Some(nik.clone());
+        self.transaction.set_network_isolation_key(nik);
+    }
+
+    /// Announce `server_name` in the TLS handshake instead of the URL's
+    /// host. Same-origin redirects keep it; cross-origin ones go back to
+    /// the host.
+    pub fn set_server_name(&mut self, server_name: ServerName) {
+        self.server_name = server_name.clone();
+        self.transaction.set_server_name(server_name);
+    }
+
+    /// Connect where the first of `rules` matching the URL's host and port
+    /// says. Redirects are matched against the rules again.
+    pub fn set_connect_to(&mut self, rules: Vec<ConnectTo>) {
+        self.connect_to = rules.clone();
+        self.transaction.set_connect_to(rules);
+    }
+
+    /// Pass the DNS answers of the connections of the request and its
+    /// redirects through `hook`.
+    pub fn set_post_resolve(&mut self, hook: PostResolve) {
+        self.post_resolve = Some(hook.clone());
+        self.transaction.set_post_resolve(hook);
+    }
+
     pub fn add_header(&mut self, key: &str, value: &str) {
         self.extra_headers
             .push((key.to_string(), value.to_string()));
@@ -202,11 +1063,207 @@ impl URLRequestHttpJob {
         let _ = self.transaction.add_header(key, value);
     }
 
+    /// Use `cookie_store` instead of the jar the job was created with.
+    ///
+    /// Applies to the whole redirect chain.
+    pub fn set_cookie_store(&mut self, cookie_store: Arc<CookieMonster>) {
+        self.transaction.set_cookie_store(cookie_store.clone());
+        self.cookie_store = cookie_store;
+    }
+
+    /// Answer 401 challenges and send preemptive auth using `cache`.
+    pub fn set_auth_cache(&mut self, cache: AuthCache) {
+        self.transaction.set_auth_cache(cache.clone());
+        self.auth_cache = Some(cache);
+    }
+
+    /// Credentials for 401 challenges. They are dropped on cross-origin
+    /// redirects, like an explicit Authorization header.
+    pub fn set_credentials(&mut self, username: &str, password: &str) {
+        self.transaction.set_credentials(username, password);
+        self.credentials = Some((username.to_string(), SecretString::new(password)));
+    }
+
+    /// Enable or disable cookies for the whole redirect chain.
+    pub fn set_allow_cookies(&mut self, allow: bool) {
+        self.allow_cookies = allow;
+        self.transaction.set_allow_cookies(allow);
+    }
+
+    /// Treat the request as made from a page on `site`, rather than as a
+    /// top-level navigation, when choosing the `SameSite` cookies of each
+    /// hop. See [`crate::cookies::samesite`].
+    pub fn set_site_for_cookies(&mut self, site: Url) {
+        self.site_for_cookies = Some(site);
+    }
+
+    /// Value of a header set with [`add_header`](Self::add_header).
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.extra_headers
+            .iter()
+            .rev()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Remove a previously added header so it is not sent at all.
+    pub fn remove_header(&mut self, key: &str) {
+        self.extra_headers
+            .retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+        self.transaction.remove_header(key);
+    }
+
+    /// Set HTTP/1.1 options (header casing) for browser emulation.
+    pub fn set_http1_options(&mut self, options: crate::emulation::Http1Options) {
+        self.http1_options = Some(options.clone());
+        self.transaction.set_http1_options(options);
+    }
+
     /// Set HTTP/2 fingerprint for browser emulation.
     pub fn set_h2_fingerprint(&mut self, fingerprint: crate::http::H2Fingerprint) {
+        self.h2_fingerprint = Some(fingerprint.clone());
         self.transaction.set_h2_fingerprint(fingerprint);
     }
 
+    /// Name the emulation profile in the response's
+    /// [`identity_report`](crate::http::HttpResponse::identity_report).
+    pub fn set_emulation_profile(&mut self, profile: crate::emulation::EmulationProfile) {
+        self.emulation_profile = Some(profile);
+    }
+
+    /// Check the certificates of the request's and its redirects'
+    /// connections with `policy`. See [`crate::tls::certverify`].
+    pub fn set_cert_policy(&mut self, policy: CertPolicy) {
+        self.cert_policy = policy.clone();
+        self.transaction.set_cert_policy(policy);
+    }
+
+    /// Limit the size of the headers of the request and its redirects.
+    pub fn set_header_limits(&mut self, limits: HeaderLimits) {
+        self.header_limits = limits;
+        self.transaction.set_header_limits(limits);
+    }
+
+    /// Limit the length of the URL and request line of the request and its
+    /// redirects.
+    pub fn set_url_limits(&mut self, limits: UrlLimits) {
+        self.url_limits = limits;
+    }
+
+    /// Restrict which HTTP version the request (and its redirects) may use.
+    pub fn set_version_pref(&mut self, version: HttpVersionPref) {
+        self.version_pref = version;
+        self.transaction.set_version_pref(version);
+    }
+
+    /// Upgrade `http` URLs of the request and its redirects to `https` for
+    /// hosts in `hsts`, and learn `Strict-Transport-Security` headers
+    /// into it.
+    pub fn set_hsts(&mut self, hsts: HstsStore) {
+        self.hsts = Some(hsts);
+    }
+
+    /// Send HTTP/1.0 request lines for the request and its redirects.
+    pub fn set_http1_0(&mut self, http1_0: bool) {
+        self.http1_0 = http1_0;
+        self.transaction.set_http1_0(http1_0);
+    }
+
+    /// Set the socket priority of the request and its redirects.
+    pub fn set_priority(&mut self, priority: RequestPriority) {
+        self.priority = priority;
+        self.transaction.set_priority(priority);
+    }
+
+    /// Announce the priority in a `priority` header as `mode` says, for
+    /// the request and its redirects. See [`crate::http::priority`].
+    pub fn set_priority_header(&mut self, mode: PriorityHeader, incremental: bool) {
+        self.priority_header = mode;
+        self.incremental = incremental;
+        self.transaction.set_priority_header(mode, incremental);
+    }
+
+    /// Whether the first request may reuse a connection. Redirects may
+    /// too, so only the request made first gets a fresh connection, unless
+    /// `reuse` is [`ConnectionReuse::Close`], which every redirect uses.
+    pub fn set_connection_reuse(&mut self, reuse: ConnectionReuse) {
+        self.connection_reuse = reuse;
+        self.transaction.set_connection_reuse(reuse);
+    }
+
+    /// Check every request of the job, including redirects, against
+    /// `rules` before sending it.
+    pub fn set_rules(&mut self, rules: Arc<RuleSet>) {
+        self.rules = Some(rules);
+    }
+
+    /// Check every request of the job, including redirects, against the
+    /// robots.txt of its origin, see [`crate::urlrequest::robots`].
+    pub fn set_robots(&mut self, robots: Robots) {
+        self.robots = Some(robots);
+    }
+
+    /// Also follow `Refresh` headers with a zero delay, like a 303
+    /// redirect. Off by default, as in modern browsers.
+    pub fn set_follow_refresh(&mut self, follow: bool) {
+        self.follow_refresh = follow;
+    }
+
+    /// Read at most `limit` bytes of a redirect's body to keep its
+    /// connection; longer bodies cost the connection instead.
+    pub fn set_max_redirect_drain(&mut self, limit: usize) {
+        self.max_redirect_drain = limit;
+    }
+
+    /// Answer the request from `cache` when it can, and store what the
+    /// network answers, as `mode` allows.
+    ///
+    /// Only GET and HEAD requests without caller-set conditional or
+    /// `Range` headers use the cache, on every hop of a redirect chain.
+    pub fn set_cache(&mut self, cache: Arc<HttpCache>, mode: CacheMode) {
+        self.cache = Some(cache);
+        self.cache_mode = mode;
+    }
+
+    /// Keep `Use-As-Dictionary` responses in `store`, and announce and
+    /// decode with them later. See [`crate::http::shareddictionary`].
+    pub fn set_shared_dictionaries(&mut self, store: SharedDictionaryStore) {
+        self.shared_dictionaries = Some(store);
+    }
+
+    /// Decode the final response's body with `decoders`. See
+    /// [`crate::http::contentdecoder`].
+    pub fn set_content_decoders(&mut self, decoders: ContentDecoders) {
+        self.content_decoders = Some(decoders);
+    }
+
+    /// Answer the challenges `challenges` recognizes and send the request
+    /// again. See [`crate::urlrequest::challenge`].
+    pub fn set_challenges(&mut self, challenges: Challenges) {
+        self.challenges = Some(challenges);
+    }
+
+    /// Replace the values attached to the request, passed on to challenge
+    /// handlers and the response.
+    pub fn set_extensions(&mut self, extensions: Extensions) {
+        self.extensions = extensions;
+    }
+
+    /// Values attached to the request.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Attach values to the request.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// What the request is for, matched by rules with resource types.
+    pub fn set_resource_type(&mut self, resource_type: ResourceType) {
+        self.resource_type = resource_type;
+    }
+
     /// Get the current load state of the job.
     ///
     /// Returns the internal transaction's load state for progress reporting.
@@ -284,6 +1341,21 @@ mod tests {
         assert_eq!(result, Method::PUT);
     }
 
+    #[test]
+    fn test_parse_refresh() {
+        assert_eq!(parse_refresh("0; url=/next"), Some((0, "/next")));
+        assert_eq!(
+            parse_refresh("0;URL = 'https://a.test/x' "),
+            Some((0, "https://a.test/x"))
+        );
+        assert_eq!(parse_refresh("5, \"/later\""), Some((5, "/later")));
+        assert_eq!(parse_refresh("0.5 /page"), Some((0, "/page")));
+        assert_eq!(parse_refresh("0; urlish"), Some((0, "urlish")));
+        for value in ["0", "0;", "; url=/x", "0x; url=/x", "url=/x"] {
+            assert_eq!(parse_refresh(value), None, "{:?}", value);
+        }
+    }
+
     #[test]
     fn test_200_no_redirect() {
         // Non-redirect status code
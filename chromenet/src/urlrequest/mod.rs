@@ -1,4 +1,9 @@
+pub mod challenge;
 pub mod context;
 pub mod device;
 pub mod job;
+pub mod redirect;
 pub mod request;
+pub mod robots;
+pub mod rules;
+pub mod urllimits;
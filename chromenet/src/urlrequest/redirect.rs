@@ -0,0 +1,69 @@
+//! Redirect chains and the bodies of their hops.
+//!
+//! Chromium mapping: `HttpResponseBodyDrainer`
+//!
+//! An HTTP/1.1 connection can only carry the next hop once the redirect's
+//! body was read to the end. Bodies up to the drain limit, 16 KiB by
+//! default, are read and thrown away; a longer body, by `Content-Length`
+//! or while reading, or one that takes over 5 seconds, costs the
+//! connection instead, and the next hop reconnects. Over HTTP/2 a body
+//! over the limit only costs its stream, which is reset.
+//!
+//! Each hop and what became of its body is listed in
+//! [`HttpResponse::redirects`](crate::http::HttpResponse::redirects).
+
+use crate::http::typedheaders;
+use crate::http::ResponseBody;
+use http::{HeaderMap, StatusCode};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Default limit on the body read from a redirect, 16 KiB.
+pub const DEFAULT_MAX_REDIRECT_DRAIN: usize = 16 * 1024;
+
+/// Longest a redirect body is read for.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What became of a redirect response's body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyDrain {
+    /// Read to the end, `bytes` long, leaving the connection reusable.
+    Drained { bytes: u64 },
+    /// Over the limit or failed, and its HTTP/2 stream reset.
+    Reset,
+    /// Over the limit or failed, and its HTTP/1.1 connection closed.
+    Discarded,
+}
+
+/// A redirect followed on the way to the final response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    /// URL that answered with the redirect.
+    pub url: Url,
+    pub status: StatusCode,
+    pub drain: BodyDrain,
+}
+
+/// Read `body` to the end if it is at most `limit` bytes, or give it up.
+pub(crate) async fn drain(body: ResponseBody, headers: &HeaderMap, limit: usize) -> BodyDrain {
+    let given_up = match body {
+        ResponseBody::H2(_) => BodyDrain::Reset,
+        _ => BodyDrain::Discarded,
+    };
+    let length = typedheaders::content_length(headers);
+    if length.is_some_and(|length| length > limit as u64) {
+        tracing::debug!(target: "chromenet::urlrequest", ?length, limit, "redirect body too big to drain");
+        body.cancel();
+        return given_up;
+    }
+    let stream = body
+        .into_stream()
+        .with_deadline(Instant::now() + DRAIN_TIMEOUT);
+    match stream.copy_limited(&mut tokio::io::sink(), limit).await {
+        Ok(bytes) => BodyDrain::Drained { bytes },
+        Err(error) => {
+            tracing::debug!(target: "chromenet::urlrequest", %error, limit, "redirect body not drained");
+            given_up
+        }
+    }
+}
@@ -77,6 +77,16 @@ impl URLRequest {
         self.job.set_proxy(proxy);
     }
 
+    /// Partition this request's connections by `nik`.
+    ///
+    /// Chromium: net/url_request/url_request.h::set_isolation_info()
+    pub fn set_network_isolation_key(
+        &mut self,
+        nik: crate::base::networkisolationkey::NetworkIsolationKey,
+    ) {
+        self.job.set_network_isolation_key(nik);
+    }
+
     /// Add a custom HTTP header.
     ///
     /// Chromium: net/url_request/url_request.h::SetExtraRequestHeaderByName()
@@ -84,6 +94,18 @@ impl URLRequest {
         self.job.add_header(key, value);
     }
 
+    /// Use `cookie_store` instead of the shared cookie jar.
+    pub fn set_cookie_store(&mut self, cookie_store: Arc<CookieMonster>) {
+        self.job.set_cookie_store(cookie_store);
+    }
+
+    /// Enable or disable sending and saving cookies.
+    ///
+    /// Chromium: net/url_request/url_request.h::set_allow_credentials()
+    pub fn set_allow_cookies(&mut self, allow: bool) {
+        self.job.set_allow_cookies(allow);
+    }
+
     /// Set the HTTP method.
     pub fn set_method(&mut self, method: http::Method) {
         self.job.set_method(method);
@@ -94,6 +116,14 @@ impl URLRequest {
         self.job.set_body(body);
     }
 
+    /// Values attached to the request, handed to challenge handlers and
+    /// carried over to the response.
+    ///
+    /// Chromium: net/url_request/url_request.h::SetUserData()
+    pub fn extensions_mut(&mut self) -> &mut http::Extensions {
+        self.job.extensions_mut()
+    }
+
     /// Create a POST request.
     pub fn post(url_str: &str) -> Result<Self, NetError> {
         let mut req = Self::new(url_str)?;
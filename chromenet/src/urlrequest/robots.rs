@@ -0,0 +1,497 @@
+//! robots.txt (RFC 9309) for polite crawling.
+//!
+//! Chromium mapping: none; browsers do not read robots.txt. This follows
+//! RFC 9309 and the conventions of large crawlers.
+//!
+//! With [`Robots`] set on a client, every request, including each redirect
+//! hop, is checked against the robots.txt of its origin. The file is
+//! fetched with the request's headers the first time an origin is seen and
+//! cached for a day. A disallowed request either fails with
+//! [`NetError::BlockedByClient`](crate::base::neterror::NetError::BlockedByClient)
+//! ([`RobotsMode::Block`]) or is sent anyway, with the verdict on the
+//! response ([`RobotsMode::Annotate`]):
+//!
+//! ```no_run
+//! use chromenet::http::RateLimiter;
+//! use chromenet::urlrequest::robots::{Robots, RobotsMode};
+//! use chromenet::Client;
+//!
+//! let client = Client::builder()
+//!     .robots(Robots::new().with_mode(RobotsMode::Block))
+//!     // Crawl-delay holds later requests to the origin
+//!     .rate_limiter(RateLimiter::new())
+//!     .build();
+//! ```
+//!
+//! Groups are picked with the request's `User-Agent`: a group applies if
+//! its product token occurs in it, case-insensitively, the longest such
+//! token winning, and the `*` group applies otherwise. A crawler announcing
+//! a browser's User-Agent can name its own token with
+//! [`Robots::with_user_agent`].
+//!
+//! As RFC 9309 requires, a robots.txt answered with a 4xx status allows
+//! everything, and one that cannot be fetched (5xx, network errors)
+//! disallows everything; the latter is retried after a minute.
+
+use crate::http::HttpResponse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// How long a fetched robots.txt is used, the RFC 9309 maximum.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long an unreachable robots.txt disallows everything before it is
+/// fetched again.
+const UNREACHABLE_TTL: Duration = Duration::from_secs(60);
+
+/// Bytes of a robots.txt that are parsed, the RFC 9309 minimum.
+pub(crate) const MAX_SIZE: usize = 500 * 1024;
+
+/// A parsed robots.txt.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+    /// Could not be fetched: everything is disallowed
+    unreachable: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Group {
+    /// Lowercase product tokens, `*` for any
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+impl RobotsTxt {
+    /// Parse robots.txt content. Unknown lines are ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        // Whether the last group still collects user-agent lines
+        let mut open = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !open {
+                        groups.push(Group::default());
+                        open = true;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                directive @ ("allow" | "disallow") => {
+                    open = false;
+                    // An empty pattern matches nothing
+                    if let (Some(group), false) = (groups.last_mut(), value.is_empty()) {
+                        group.rules.push(Rule {
+                            allow: directive == "allow",
+                            pattern: encode_pattern(value),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    open = false;
+                    let delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+                    if let (Some(group), Some(delay)) = (groups.last_mut(), delay) {
+                        group.crawl_delay = Some(delay);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            groups,
+            unreachable: false,
+        }
+    }
+
+    /// Allows everything, as for a missing robots.txt.
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Disallows everything, as for a robots.txt that could not be
+    /// fetched.
+    pub fn disallow_all() -> Self {
+        Self {
+            groups: Vec::new(),
+            unreachable: true,
+        }
+    }
+
+    /// Whether `user_agent` may fetch `url`.
+    pub fn is_allowed(&self, user_agent: &str, url: &Url) -> bool {
+        if url.path() == "/robots.txt" {
+            return true;
+        }
+        if self.unreachable {
+            return false;
+        }
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        // The longest matching pattern decides, allow winning ties
+        self.groups_for(user_agent)
+            .flat_map(|group| &group.rules)
+            .filter(|rule| matches(&rule.pattern, &path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    /// Delay `user_agent` should keep between requests, from the
+    /// non-standard `Crawl-delay` directive.
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.groups_for(user_agent)
+            .filter_map(|group| group.crawl_delay)
+            .max()
+    }
+
+    /// The groups that apply to `user_agent`: those of the longest product
+    /// token in it, else those for `*`.
+    fn groups_for<'a>(&'a self, user_agent: &str) -> impl Iterator<Item = &'a Group> + 'a {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let token = self
+            .groups
+            .iter()
+            .flat_map(|group| &group.agents)
+            .filter(|agent| agent.as_str() != "*" && user_agent.contains(agent.as_str()))
+            .max_by_key(|agent| agent.len())
+            .cloned()
+            .unwrap_or_else(|| "*".to_string());
+        self.groups
+            .iter()
+            .filter(move |group| group.agents.contains(&token))
+    }
+
+    /// The robots.txt in `response`, per the RFC 9309 status rules.
+    pub(crate) async fn from_response(mut response: HttpResponse) -> Self {
+        let status = response.status();
+        if status.is_success() {
+            let body = match response.take_body() {
+                Some(body) => body.into_stream().read_prefix(MAX_SIZE).await,
+                None => bytes::Bytes::new(),
+            };
+            Self::parse(&String::from_utf8_lossy(&body))
+        } else if status.is_client_error() {
+            Self::allow_all()
+        } else {
+            Self::disallow_all()
+        }
+    }
+}
+
+/// Percent-encode the non-ASCII bytes of a pattern, to compare it with the
+/// serialized URL.
+fn encode_pattern(pattern: &str) -> String {
+    let mut encoded = String::with_capacity(pattern.len());
+    for &byte in pattern.as_bytes() {
+        if byte.is_ascii() {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Whether `pattern` matches the start of `path`; `*` matches any
+/// sequence, and a trailing `$` anchors the end.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern.as_bytes(), true),
+        None => (pattern.as_bytes(), false),
+    };
+    let path = path.as_bytes();
+    let (mut p, mut s) = (0, 0);
+    // Position after the last `*`, and where in `path` it resumes
+    let mut backtrack: Option<(usize, usize)> = None;
+    loop {
+        if p == pattern.len() {
+            if !anchored || s == path.len() {
+                return true;
+            }
+        } else if pattern[p] == b'*' {
+            p += 1;
+            backtrack = Some((p, s));
+            continue;
+        } else if s < path.len() && pattern[p] == path[s] {
+            p += 1;
+            s += 1;
+            continue;
+        }
+        match backtrack {
+            Some((star, resume)) if resume < path.len() => {
+                backtrack = Some((star, resume + 1));
+                p = star;
+                s = resume + 1;
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// What to do with requests robots.txt disallows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RobotsMode {
+    /// Fail them with `NetError::BlockedByClient`.
+    #[default]
+    Block,
+    /// Send them, with the verdict on the response.
+    Annotate,
+}
+
+/// robots.txt verdict for a request, see
+/// [`HttpResponse::robots`](crate::http::HttpResponse::robots).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RobotsVerdict {
+    /// Whether robots.txt allows the request.
+    pub allowed: bool,
+    /// The `Crawl-delay` of the origin, if any.
+    pub crawl_delay: Option<Duration>,
+}
+
+struct CachedRobots {
+    robots: Arc<RobotsTxt>,
+    expires: Instant,
+}
+
+/// robots.txt checking and per-origin cache.
+///
+/// Cloning shares the cache, so one cache can serve several clients.
+#[derive(Clone)]
+pub struct Robots {
+    cache: Arc<Mutex<HashMap<String, CachedRobots>>>,
+    mode: RobotsMode,
+    ttl: Duration,
+    user_agent: Option<String>,
+}
+
+impl Default for Robots {
+    fn default() -> Self {
+        Self {
+            cache: Arc::default(),
+            mode: RobotsMode::default(),
+            ttl: DEFAULT_TTL,
+            user_agent: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Robots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Robots")
+            .field("mode", &self.mode)
+            .field("ttl", &self.ttl)
+            .field("user_agent", &self.user_agent)
+            .field("origins", &self.cache.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Robots {
+    /// Block disallowed requests, caching robots.txt for a day.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What to do with disallowed requests.
+    pub fn with_mode(mut self, mode: RobotsMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// How long a fetched robots.txt is used. Defaults to [`DEFAULT_TTL`].
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Pick groups with `user_agent` instead of the request's
+    /// `User-Agent` header.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// What happens to disallowed requests.
+    pub fn mode(&self) -> RobotsMode {
+        self.mode
+    }
+
+    /// The cached robots.txt of `url`'s origin, if not expired.
+    pub fn get(&self, url: &Url) -> Option<Arc<RobotsTxt>> {
+        let mut cache = self.cache.lock().unwrap();
+        let key = origin(url);
+        match cache.get(&key) {
+            Some(cached) if cached.expires > Instant::now() => Some(cached.robots.clone()),
+            Some(_) => {
+                cache.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Use `robots` for `url`'s origin instead of fetching it.
+    pub fn insert(&self, url: &Url, robots: RobotsTxt) -> Arc<RobotsTxt> {
+        let ttl = if robots.unreachable {
+            UNREACHABLE_TTL.min(self.ttl)
+        } else {
+            self.ttl
+        };
+        let robots = Arc::new(robots);
+        self.cache.lock().unwrap().insert(
+            origin(url),
+            CachedRobots {
+                robots: robots.clone(),
+                expires: Instant::now() + ttl,
+            },
+        );
+        robots
+    }
+
+    /// Forget all cached robots.txt files.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// The verdict of `robots` for a request to `url` sent with
+    /// `user_agent`.
+    pub(crate) fn verdict(
+        &self,
+        robots: &RobotsTxt,
+        url: &Url,
+        user_agent: Option<&str>,
+    ) -> RobotsVerdict {
+        let user_agent = self.user_agent.as_deref().or(user_agent).unwrap_or("");
+        RobotsVerdict {
+            allowed: robots.is_allowed(user_agent, url),
+            crawl_delay: robots.crawl_delay(user_agent),
+        }
+    }
+}
+
+/// The robots.txt URL of `url`'s origin.
+pub(crate) fn robots_url(url: &Url) -> Url {
+    let mut robots = url.clone();
+    robots.set_path("/robots.txt");
+    robots.set_query(None);
+    robots.set_fragment(None);
+    let _ = robots.set_username("");
+    let _ = robots.set_password(None);
+    robots
+}
+
+fn origin(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com")
+            .unwrap()
+            .join(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_longest_match_wins() {
+        let robots = RobotsTxt::parse(
+            "User-agent: *\nDisallow: /private\nAllow: /private/public\nDisallow: /*.pdf$\n",
+        );
+        assert!(robots.is_allowed("any", &url("/")));
+        assert!(!robots.is_allowed("any", &url("/private/x")));
+        assert!(robots.is_allowed("any", &url("/private/public/x")));
+        assert!(!robots.is_allowed("any", &url("/docs/a.pdf")));
+        assert!(robots.is_allowed("any", &url("/docs/a.pdf?download")));
+        assert!(robots.is_allowed("any", &url("/robots.txt")));
+    }
+
+    #[test]
+    fn test_allow_wins_ties() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /page\nAllow: /page\n");
+        assert!(robots.is_allowed("any", &url("/page")));
+    }
+
+    #[test]
+    fn test_groups_by_user_agent() {
+        let robots = RobotsTxt::parse(
+            "User-agent: *\nDisallow: /\n\n\
+             User-agent: goodbot\nUser-agent: otherbot\nAllow: /\nCrawl-delay: 2.5\n\n\
+             User-agent: goodbot-news\nDisallow: /news # comment\n",
+        );
+        assert!(!robots.is_allowed("Mozilla/5.0 Chrome/120", &url("/a")));
+        assert!(robots.is_allowed("Mozilla/5.0 (compatible; GoodBot/1.0)", &url("/a")));
+        assert!(robots.is_allowed("OtherBot", &url("/a")));
+        assert_eq!(
+            robots.crawl_delay("goodbot"),
+            Some(Duration::from_millis(2500))
+        );
+        // The most specific token wins
+        assert!(!robots.is_allowed("GoodBot-News/2", &url("/news/1")));
+        assert_eq!(robots.crawl_delay("GoodBot-News/2"), None);
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("/*/edit", "/page/edit"));
+        assert!(matches("/a*b*c", "/axxbyyc/tail"));
+        assert!(!matches("/a*b*c$", "/axxbyyc/tail"));
+        assert!(matches("/a*b*c$", "/axxbyyc"));
+        assert!(matches("*", "/"));
+        assert!(!matches("/a", "/"));
+    }
+
+    #[test]
+    fn test_non_ascii_patterns_are_encoded() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /café\n");
+        assert!(!robots.is_allowed("any", &url("/café/menu")));
+    }
+
+    #[test]
+    fn test_unreachable_and_unavailable() {
+        assert!(RobotsTxt::allow_all().is_allowed("any", &url("/x")));
+        assert!(!RobotsTxt::disallow_all().is_allowed("any", &url("/x")));
+        assert!(RobotsTxt::disallow_all().is_allowed("any", &url("/robots.txt")));
+    }
+
+    #[test]
+    fn test_cache_per_origin() {
+        let robots = Robots::new();
+        robots.insert(&url("/"), RobotsTxt::disallow_all());
+        assert!(robots.get(&url("/other")).is_some());
+        assert!(robots
+            .get(&Url::parse("https://example.com:8443/").unwrap())
+            .is_none());
+        assert_eq!(
+            robots_url(&Url::parse("https://u:p@example.com/a?b#c").unwrap()).as_str(),
+            "https://example.com/robots.txt"
+        );
+
+        let expired = Robots::new().with_ttl(Duration::ZERO);
+        expired.insert(&url("/"), RobotsTxt::allow_all());
+        assert!(expired.get(&url("/")).is_none());
+    }
+}
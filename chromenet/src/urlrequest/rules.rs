@@ -0,0 +1,455 @@
+//! Declarative request rules: block, redirect or add headers by pattern.
+//!
+//! Chromium mapping: a small subset of the `declarativeNetRequest`
+//! extension API, applied where `URLRequestHttpJob` starts a transaction
+//!
+//! A [`RuleSet`] is checked before every request of a job is sent,
+//! including each redirect hop, so a redirect cannot lead around a block.
+//! Rules match on the URL (a glob or a regular expression over the whole
+//! serialized URL), the method and a [`ResourceType`] hint, and either
+//! decide the request's fate or edit its headers:
+//!
+//! ```no_run
+//! use chromenet::urlrequest::rules::{Rule, RuleAction, RuleSet, UrlPattern};
+//! use chromenet::Client;
+//! use http::{HeaderName, HeaderValue};
+//!
+//! let rules = RuleSet::new()
+//!     .with_rule(Rule::new(RuleAction::Block).with_url(UrlPattern::glob("*://ads.example.com/*")))
+//!     .with_rule(
+//!         Rule::new(RuleAction::RewriteHost("mirror.example.net".into()))
+//!             .with_url(UrlPattern::glob("https://downloads.example.com/*")),
+//!     )
+//!     .with_rule(Rule::new(RuleAction::SetHeader(
+//!         HeaderName::from_static("x-pipeline"),
+//!         HeaderValue::from_static("crawler"),
+//!     )));
+//! let client = Client::builder().rules(rules).build();
+//! ```
+//!
+//! Rules are evaluated from the highest priority down, in insertion order
+//! within a priority. Header rules apply until the first matching rule that
+//! decides the request's fate: [`RuleAction::Allow`] sends the request
+//! unchanged by lower rules, [`RuleAction::Block`] fails it with
+//! [`NetError::BlockedByClient`](crate::base::neterror::NetError::BlockedByClient),
+//! and a redirecting action sends it to another URL, where the rules are
+//! checked again. Redirects by rules count against the redirect limit and
+//! strip credentials when they change the origin, like HTTP redirects.
+
+use http::{HeaderName, HeaderValue, Method};
+use regex::Regex;
+use url::Url;
+
+/// What a request is for, as far as the caller knows. Set with
+/// [`RequestBuilder::resource_type`](crate::RequestBuilder::resource_type);
+/// requests without a hint are [`ResourceType::Other`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ResourceType {
+    MainFrame,
+    SubFrame,
+    Stylesheet,
+    Script,
+    Image,
+    Font,
+    Media,
+    XmlHttpRequest,
+    WebSocket,
+    Ping,
+    #[default]
+    Other,
+}
+
+/// Pattern over the whole serialized URL, e.g. `https://example.com/a?b`.
+#[derive(Debug, Clone)]
+pub enum UrlPattern {
+    /// `*` matches any run of characters, everything else itself.
+    /// `?` is not special, since it starts the query of a URL.
+    Glob(String),
+    /// A regular expression, unanchored unless it uses `^` and `$`.
+    Regex(Regex),
+}
+
+impl UrlPattern {
+    /// Glob pattern, e.g. `*://*.example.com/*`.
+    pub fn glob(pattern: impl Into<String>) -> Self {
+        UrlPattern::Glob(pattern.into())
+    }
+
+    /// Regular expression pattern. Its capture groups can be used in
+    /// [`RuleAction::RegexSubstitution`].
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(UrlPattern::Regex)
+    }
+
+    /// Whether `url` matches.
+    pub fn matches(&self, url: &Url) -> bool {
+        match self {
+            UrlPattern::Glob(pattern) => glob_match(pattern, url.as_str()),
+            UrlPattern::Regex(regex) => regex.is_match(url.as_str()),
+        }
+    }
+}
+
+/// What a matching rule does.
+#[derive(Debug, Clone)]
+pub enum RuleAction {
+    /// Send the request, ignoring lower rules.
+    Allow,
+    /// Fail the request with `BlockedByClient`.
+    Block,
+    /// Send the request to this URL instead.
+    Redirect(Url),
+    /// Send the request to this host instead, keeping scheme, port, path
+    /// and query. E.g. to force a mirror.
+    RewriteHost(String),
+    /// Send the request to the URL built by replacing the match of the
+    /// rule's [`UrlPattern::Regex`] with this template, where `$1` or
+    /// `${name}` stand for capture groups. Ignored with other patterns.
+    RegexSubstitution(String),
+    /// Set a request header, replacing any value it had.
+    SetHeader(HeaderName, HeaderValue),
+    /// Do not send a request header.
+    RemoveHeader(HeaderName),
+}
+
+impl RuleAction {
+    /// Whether the action decides the request's fate, ending evaluation.
+    fn is_terminal(&self) -> bool {
+        !matches!(
+            self,
+            RuleAction::SetHeader(..) | RuleAction::RemoveHeader(_)
+        )
+    }
+}
+
+/// A condition and the action to take for requests meeting it.
+///
+/// Without conditions, a rule matches every request.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    action: RuleAction,
+    url: Option<UrlPattern>,
+    methods: Vec<Method>,
+    resource_types: Vec<ResourceType>,
+    priority: i32,
+}
+
+impl Rule {
+    /// Rule taking `action` on every request, until narrowed down.
+    pub fn new(action: RuleAction) -> Self {
+        Self {
+            action,
+            url: None,
+            methods: Vec::new(),
+            resource_types: Vec::new(),
+            priority: 0,
+        }
+    }
+
+    /// Only match URLs matching `pattern`.
+    pub fn with_url(mut self, pattern: UrlPattern) -> Self {
+        self.url = Some(pattern);
+        self
+    }
+
+    /// Only match requests with one of `methods`.
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Only match requests of one of `types`.
+    pub fn with_resource_types(mut self, types: impl IntoIterator<Item = ResourceType>) -> Self {
+        self.resource_types = types.into_iter().collect();
+        self
+    }
+
+    /// Evaluate before rules of lower priority. Defaults to 0.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// The rule's action.
+    pub fn action(&self) -> &RuleAction {
+        &self.action
+    }
+
+    /// Whether the rule applies to a request.
+    pub fn matches(&self, url: &Url, method: &Method, resource_type: ResourceType) -> bool {
+        self.url.as_ref().is_none_or(|pattern| pattern.matches(url))
+            && (self.methods.is_empty() || self.methods.contains(method))
+            && (self.resource_types.is_empty() || self.resource_types.contains(&resource_type))
+    }
+
+    /// Where a redirecting action sends `url`, `None` if the action does
+    /// not redirect or yields no valid URL.
+    fn redirect(&self, url: &Url) -> Option<Url> {
+        match &self.action {
+            RuleAction::Redirect(target) => Some(target.clone()),
+            RuleAction::RewriteHost(host) => {
+                let mut target = url.clone();
+                target.set_host(Some(host)).ok()?;
+                Some(target)
+            }
+            RuleAction::RegexSubstitution(template) => match &self.url {
+                Some(UrlPattern::Regex(regex)) => {
+                    Url::parse(&regex.replace(url.as_str(), template.as_str())).ok()
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A change to the headers of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderEdit {
+    Set(HeaderName, HeaderValue),
+    Remove(HeaderName),
+}
+
+/// What the rules decided for a request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Decision {
+    /// Fail the request instead of sending it.
+    pub block: bool,
+    /// Send the request to this URL instead.
+    pub redirect: Option<Url>,
+    /// Header changes, in the order they apply.
+    pub headers: Vec<HeaderEdit>,
+}
+
+/// Ordered collection of [`Rule`]s.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    /// Highest priority first, insertion order within a priority
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Empty rule set, letting every request through.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `rule`.
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.add_rule(rule);
+        self
+    }
+
+    /// Add `rule`.
+    pub fn add_rule(&mut self, rule: Rule) {
+        let at = self.rules.partition_point(|r| r.priority >= rule.priority);
+        self.rules.insert(at, rule);
+    }
+
+    /// Number of rules.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Decide what happens to a request for `url`.
+    pub fn evaluate(&self, url: &Url, method: &Method, resource_type: ResourceType) -> Decision {
+        let mut decision = Decision::default();
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(url, method, resource_type))
+        {
+            match &rule.action {
+                RuleAction::SetHeader(name, value) => decision
+                    .headers
+                    .push(HeaderEdit::Set(name.clone(), value.clone())),
+                RuleAction::RemoveHeader(name) => {
+                    decision.headers.push(HeaderEdit::Remove(name.clone()))
+                }
+                RuleAction::Block => {
+                    return Decision {
+                        block: true,
+                        ..Decision::default()
+                    }
+                }
+                action => {
+                    debug_assert!(action.is_terminal());
+                    // The headers are decided again for the new URL
+                    if let Some(target) = rule.redirect(url).filter(|target| target != url) {
+                        return Decision {
+                            redirect: Some(target),
+                            ..Decision::default()
+                        };
+                    }
+                    break;
+                }
+            }
+        }
+        decision
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of
+/// characters.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it resumes from
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(
+            "*://ads.example.com/*",
+            "https://ads.example.com/x.js"
+        ));
+        assert!(glob_match(
+            "https://*.example.com/*",
+            "https://a.b.example.com/"
+        ));
+        assert!(!glob_match(
+            "https://*.example.com/*",
+            "https://example.com/"
+        ));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        // `?` is a literal
+        assert!(glob_match(
+            "*/search?q=*",
+            "https://example.com/search?q=rust"
+        ));
+        assert!(!glob_match("*/a?c", "https://example.com/abc"));
+    }
+
+    #[test]
+    fn test_rule_conditions() {
+        let rule = Rule::new(RuleAction::Block)
+            .with_url(UrlPattern::glob("*.png"))
+            .with_methods([Method::GET])
+            .with_resource_types([ResourceType::Image]);
+        let png = url("https://example.com/a.png");
+        assert!(rule.matches(&png, &Method::GET, ResourceType::Image));
+        assert!(!rule.matches(&png, &Method::POST, ResourceType::Image));
+        assert!(!rule.matches(&png, &Method::GET, ResourceType::Other));
+        assert!(!rule.matches(
+            &url("https://example.com/a.js"),
+            &Method::GET,
+            ResourceType::Image
+        ));
+    }
+
+    #[test]
+    fn test_priority_and_terminal_rules() {
+        let rules = RuleSet::new()
+            .with_rule(Rule::new(RuleAction::Block).with_url(UrlPattern::glob("*://ads.*")))
+            .with_rule(
+                Rule::new(RuleAction::Allow)
+                    .with_url(UrlPattern::glob("*://ads.example.com/allowed"))
+                    .with_priority(10),
+            )
+            .with_rule(Rule::new(RuleAction::SetHeader(
+                HeaderName::from_static("x-a"),
+                HeaderValue::from_static("1"),
+            )))
+            .with_rule(
+                Rule::new(RuleAction::RemoveHeader(HeaderName::from_static("x-b")))
+                    .with_priority(20),
+            );
+
+        let blocked = rules.evaluate(
+            &url("https://ads.example.com/x"),
+            &Method::GET,
+            ResourceType::Other,
+        );
+        assert!(blocked.block);
+        assert!(blocked.headers.is_empty());
+
+        // The allow rule outranks the block, and ends evaluation before the
+        // lower priority header rule
+        let allowed = rules.evaluate(
+            &url("https://ads.example.com/allowed"),
+            &Method::GET,
+            ResourceType::Other,
+        );
+        assert!(!allowed.block);
+        assert_eq!(
+            allowed.headers,
+            [HeaderEdit::Remove(HeaderName::from_static("x-b"))]
+        );
+
+        let other = rules.evaluate(
+            &url("https://example.com/"),
+            &Method::GET,
+            ResourceType::Other,
+        );
+        assert_eq!(other.headers.len(), 2);
+        assert_eq!(other.redirect, None);
+    }
+
+    #[test]
+    fn test_redirecting_actions() {
+        let rules = RuleSet::new()
+            .with_rule(
+                Rule::new(RuleAction::RewriteHost("mirror.example.net".into()))
+                    .with_url(UrlPattern::glob("https://dl.example.com:*")),
+            )
+            .with_rule(
+                Rule::new(RuleAction::RegexSubstitution(
+                    "https://cdn.example.com/v2/$1".into(),
+                ))
+                .with_url(UrlPattern::regex(r"^https://cdn\.example\.com/v1/(.*)$").unwrap()),
+            );
+        let evaluate = |s: &str| {
+            rules
+                .evaluate(&url(s), &Method::GET, ResourceType::Other)
+                .redirect
+        };
+
+        assert_eq!(
+            evaluate("https://dl.example.com:8443/f.tar?x=1"),
+            Some(url("https://mirror.example.net:8443/f.tar?x=1"))
+        );
+        assert_eq!(
+            evaluate("https://cdn.example.com/v1/lib.js"),
+            Some(url("https://cdn.example.com/v2/lib.js"))
+        );
+        // A rewrite to the same URL is no redirect
+        assert_eq!(evaluate("https://cdn.example.com/v2/lib.js"), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Limits on the length of request URLs.
+//!
+//! Chromium mapping: `url::kMaxURLChars`
+//!
+//! A URL too long for the server otherwise fails deep in the HTTP stack,
+//! or with a bare 414 or a reset stream. Requests and each of their
+//! redirects are checked before they are sent instead:
+//!
+//! - A URL longer than [`UrlLimits::max_url_length`], 2 MiB by default
+//!   like Chromium's, fails with [`NetError::UrlTooLong`]
+//! - A request line, `METHOD target HTTP/1.1\r\n`, longer than
+//!   [`UrlLimits::max_request_line`] fails with
+//!   [`NetError::RequestLineTooLong`]. The target is the path and query,
+//!   or the whole URL when sent to an HTTP proxy. Over HTTP/2 the same
+//!   size is counted for the `:method` and `:path` pseudo-headers.
+//!
+//! Servers' own limits vary, 8 KiB in nginx and Apache by default, so the
+//! request line is not limited unless set.
+
+use crate::base::neterror::NetError;
+use http::Method;
+use url::Url;
+
+/// Longest URL Chromium handles, 2 MiB (`url::kMaxURLChars`).
+pub const MAX_URL_CHARS: usize = 2 * 1024 * 1024;
+
+/// Limits on a request's URL and request line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UrlLimits {
+    /// Longest serialized URL, fragment included.
+    pub max_url_length: usize,
+    /// Longest HTTP/1.1 request line, CRLF included.
+    pub max_request_line: usize,
+}
+
+impl Default for UrlLimits {
+    fn default() -> Self {
+        Self {
+            max_url_length: MAX_URL_CHARS,
+            max_request_line: usize::MAX,
+        }
+    }
+}
+
+impl UrlLimits {
+    /// Limits of `max_url_length` bytes for the URL and `max_request_line`
+    /// bytes for the request line.
+    pub fn new(max_url_length: usize, max_request_line: usize) -> Self {
+        Self {
+            max_url_length,
+            max_request_line,
+        }
+    }
+
+    /// Check a `method` request for `url`, its target in absolute form if
+    /// `absolute_form`, as for a plain-HTTP request through a proxy.
+    pub fn check(&self, method: &Method, url: &Url, absolute_form: bool) -> Result<(), NetError> {
+        let length = url.as_str().len();
+        if length > self.max_url_length {
+            tracing::debug!(target: "chromenet::urlrequest", length, limit = self.max_url_length, "URL too long");
+            return Err(NetError::UrlTooLong {
+                length,
+                limit: self.max_url_length,
+            });
+        }
+        let length = request_line_length(method, url, absolute_form);
+        if length > self.max_request_line {
+            tracing::debug!(target: "chromenet::urlrequest", length, limit = self.max_request_line, "request line too long");
+            return Err(NetError::RequestLineTooLong {
+                length,
+                limit: self.max_request_line,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Length of `METHOD target HTTP/1.1\r\n`; the fragment is never sent.
+fn request_line_length(method: &Method, url: &Url, absolute_form: bool) -> usize {
+    let target = if absolute_form {
+        url[..url::Position::AfterQuery].len()
+    } else {
+        url[url::Position::BeforePath..url::Position::AfterQuery].len()
+    };
+    method.as_str().len() + 1 + target + " HTTP/1.1\r\n".len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_length() {
+        let limits = UrlLimits::new(30, usize::MAX);
+        let url = Url::parse("https://example.com/abcdefghij").unwrap();
+        assert!(limits.check(&Method::GET, &url, false).is_ok());
+
+        let url = Url::parse("https://example.com/abcdefghijk").unwrap();
+        assert!(matches!(
+            limits.check(&Method::GET, &url, false),
+            Err(NetError::UrlTooLong {
+                length: 31,
+                limit: 30
+            })
+        ));
+    }
+
+    #[test]
+    fn test_request_line_length() {
+        let url = Url::parse("http://example.com/path?q=1#frag").unwrap();
+        // GET /path?q=1 HTTP/1.1\r\n
+        assert_eq!(request_line_length(&Method::GET, &url, false), 24);
+        // GET http://example.com/path?q=1 HTTP/1.1\r\n
+        assert_eq!(request_line_length(&Method::GET, &url, true), 42);
+
+        let limits = UrlLimits::new(MAX_URL_CHARS, 24);
+        assert!(limits.check(&Method::GET, &url, false).is_ok());
+        assert!(matches!(
+            limits.check(&Method::POST, &url, false),
+            Err(NetError::RequestLineTooLong { length: 25, .. })
+        ));
+    }
+}
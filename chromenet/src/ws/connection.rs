@@ -1,20 +1,32 @@
 //! WebSocket connection with tokio-tungstenite.
 //!
-//! Provides full WebSocket client functionality.
+//! Provides full WebSocket client functionality. Connections go through
+//! [`ConnectJob`] with the fingerprinting TLS connector; the handshake is
+//! written by [`handshake`](super::handshake), tungstenite only handles
+//! frames.
 
+use super::deflate::{self, DeflateIo, PerMessageDeflate};
+use super::handshake::{self, Handshake};
 use super::message::{CloseCode, CloseFrame, Message};
 use crate::base::neterror::NetError;
+use crate::emulation::{Emulation, EmulationFactory};
+use crate::socket::connectjob::ConnectJob;
+use crate::socket::proxy::ProxySettings;
+use crate::socket::stream::BoxedSocket;
+use crate::socket::tls::{AlpnProtocol, TlsOptions};
 use bytes::Bytes;
 use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
+use std::borrow::Cow;
 use std::sync::Arc;
-use tokio::net::TcpStream;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
-use tokio_tungstenite::{connect_async, tungstenite, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::protocol::{Role, WebSocketConfig};
+use tokio_tungstenite::{tungstenite, WebSocketStream};
 use url::Url;
 
 /// Type alias for the WebSocket stream.
-type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type WsStream = WebSocketStream<DeflateIo<BoxedSocket>>;
 
 /// WebSocket connection.
 ///
@@ -23,6 +35,7 @@ pub struct WebSocket {
     sink: Arc<Mutex<SplitSink<WsStream, tungstenite::Message>>>,
     stream: Arc<Mutex<SplitStream<WsStream>>>,
     url: Url,
+    deflate: Option<PerMessageDeflate>,
 }
 
 impl WebSocket {
@@ -33,25 +46,7 @@ impl WebSocket {
     /// let ws = WebSocket::connect("wss://echo.websocket.org").await?;
     /// ```
     pub async fn connect(url: &str) -> Result<Self, NetError> {
-        let url = Url::parse(url).map_err(|_| NetError::InvalidUrl)?;
-
-        // Validate scheme
-        if url.scheme() != "ws" && url.scheme() != "wss" {
-            return Err(NetError::InvalidUrl);
-        }
-
-        let (ws_stream, _response) = connect_async(url.as_str()).await.map_err(|e| {
-            tracing::debug!("WebSocket connect error: {:?}", e);
-            NetError::ConnectionFailed
-        })?;
-
-        let (sink, stream) = ws_stream.split();
-
-        Ok(Self {
-            sink: Arc::new(Mutex::new(sink)),
-            stream: Arc::new(Mutex::new(stream)),
-            url,
-        })
+        WebSocketBuilder::new().url(url)?.connect().await
     }
 
     /// Get the URL this WebSocket is connected to.
@@ -59,13 +54,19 @@ impl WebSocket {
         &self.url
     }
 
+    /// The permessage-deflate parameters the server accepted, `None` if
+    /// messages are not compressed.
+    pub fn permessage_deflate(&self) -> Option<PerMessageDeflate> {
+        self.deflate
+    }
+
     /// Send a message.
     pub async fn send(&self, msg: Message) -> Result<(), NetError> {
         let tung_msg = message_to_tungstenite(msg);
         let mut sink = self.sink.lock().await;
         sink.send(tung_msg).await.map_err(|e| {
             tracing::debug!("WebSocket send error: {:?}", e);
-            NetError::ConnectionClosed
+            ws_error(e)
         })
     }
 
@@ -81,14 +82,15 @@ impl WebSocket {
 
     /// Receive a message.
     ///
-    /// Returns `None` if the connection is closed.
+    /// Returns `None` if the connection is closed. A message or frame
+    /// over the builder's limits fails with [`NetError::MsgTooBig`].
     pub async fn recv(&self) -> Result<Option<Message>, NetError> {
         let mut stream = self.stream.lock().await;
         match stream.next().await {
             Some(Ok(msg)) => Ok(Some(tungstenite_to_message(msg))),
             Some(Err(e)) => {
                 tracing::debug!("WebSocket recv error: {:?}", e);
-                Err(NetError::ConnectionClosed)
+                Err(ws_error(e))
             }
             None => Ok(None),
         }
@@ -112,6 +114,12 @@ pub struct WebSocketBuilder {
     url: Option<Url>,
     headers: http::HeaderMap,
     subprotocols: Vec<String>,
+    config: WebSocketConfig,
+    deflate: Option<PerMessageDeflate>,
+    emulation: Option<Emulation>,
+    tls_options: Option<TlsOptions>,
+    proxy: Option<ProxySettings>,
+    origin: Option<String>,
 }
 
 impl Default for WebSocketBuilder {
@@ -127,6 +135,12 @@ impl WebSocketBuilder {
             url: None,
             headers: http::HeaderMap::new(),
             subprotocols: Vec::new(),
+            config: WebSocketConfig::default(),
+            deflate: None,
+            emulation: None,
+            tls_options: None,
+            proxy: None,
+            origin: None,
         }
     }
 
@@ -160,6 +174,59 @@ impl WebSocketBuilder {
         self
     }
 
+    /// Fail [`WebSocket::recv`] with [`NetError::MsgTooBig`] on a message
+    /// larger than `size` bytes, 64 MiB by default. Compressed messages
+    /// count their inflated size.
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.config.max_message_size = Some(size);
+        self
+    }
+
+    /// Fail [`WebSocket::recv`] with [`NetError::MsgTooBig`] on a frame
+    /// larger than `size` bytes, 16 MiB by default. Compressed frames
+    /// count their size on the wire.
+    pub fn max_frame_size(mut self, size: usize) -> Self {
+        self.config.max_frame_size = Some(size);
+        self
+    }
+
+    /// Offer permessage-deflate, compressing messages both ways if the
+    /// server accepts. See [`WebSocket::permessage_deflate`] for what it
+    /// accepted.
+    pub fn permessage_deflate(mut self, deflate: PerMessageDeflate) -> Self {
+        self.deflate = Some(deflate);
+        self
+    }
+
+    /// Handshake like the browser `emulation` mimics: its TLS fingerprint,
+    /// its `User-Agent`, `Accept-Encoding` and `Accept-Language`, and
+    /// Chrome's header order, `Origin` included. See
+    /// [`Client::websocket`](crate::Client::websocket) to take a client's.
+    pub fn emulation<E: EmulationFactory>(mut self, emulation: E) -> Self {
+        self.emulation = Some(emulation.emulation());
+        self
+    }
+
+    /// Set TLS options (overrides emulation TLS if set).
+    pub fn tls_options(mut self, opts: TlsOptions) -> Self {
+        self.tls_options = Some(opts);
+        self
+    }
+
+    /// Connect through `proxy`. HTTP and HTTPS proxies are always asked
+    /// for a `CONNECT` tunnel, also for `ws` URLs, as in Chrome.
+    pub fn proxy(mut self, proxy: ProxySettings) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Send `origin` as the `Origin` header. With an emulation profile it
+    /// defaults to the origin of the URL, as for a page on the same site.
+    pub fn origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
+
     /// Get the URL if set.
     pub fn get_url(&self) -> Option<&Url> {
         self.url.as_ref()
@@ -176,9 +243,93 @@ impl WebSocketBuilder {
     }
 
     /// Connect to the server.
+    ///
+    /// Fails with [`NetError::WsProtocolError`] if the server accepted an
+    /// extension that was not offered, or a response that does not accept
+    /// the handshake.
     pub async fn connect(self) -> Result<WebSocket, NetError> {
         let url = self.url.ok_or(NetError::InvalidUrl)?;
-        WebSocket::connect(url.as_str()).await
+        let values = [
+            self.subprotocols.join(", "),
+            self.origin.clone().unwrap_or_default(),
+        ];
+        if values
+            .into_iter()
+            .any(|value| http::HeaderValue::try_from(value).is_err())
+        {
+            return Err(NetError::InvalidUrl);
+        }
+        let handshake = Handshake {
+            url: &url,
+            profile: self.emulation.as_ref().map(|e| &e.headers),
+            origin: self.origin.as_deref(),
+            headers: &self.headers,
+            subprotocols: &self.subprotocols,
+            extensions: self.deflate.as_ref().map(PerMessageDeflate::offer),
+        };
+        let (request, key) = handshake.request();
+
+        let tls_options = self
+            .tls_options
+            .or_else(|| self.emulation.and_then(|e| e.tls_options));
+        let stream = connect_stream(&url, self.proxy.as_ref(), tls_options).await?;
+        let mut io = DeflateIo::new(stream, self.deflate, self.config.max_frame_size);
+        let written = match io.write_all(&request).await {
+            Ok(()) => io.flush().await,
+            Err(e) => Err(e),
+        };
+        written.map_err(|e| {
+            tracing::debug!("WebSocket connect error: {:?}", e);
+            NetError::ConnectionFailed
+        })?;
+        handshake::read_response(&mut io, &key, &self.subprotocols).await?;
+        let deflate = io.negotiated()?;
+        let ws_stream = WebSocketStream::from_raw_socket(io, Role::Client, Some(self.config)).await;
+
+        let (sink, stream) = ws_stream.split();
+
+        Ok(WebSocket {
+            sink: Arc::new(Mutex::new(sink)),
+            stream: Arc::new(Mutex::new(stream)),
+            url,
+            deflate,
+        })
+    }
+}
+
+/// Open the connection for `url` through `proxy`, with TLS for `wss`. As
+/// in Chrome, only HTTP/1.1 is offered in ALPN, whatever `tls_options`
+/// offer.
+async fn connect_stream(
+    url: &Url,
+    proxy: Option<&ProxySettings>,
+    tls_options: Option<TlsOptions>,
+) -> Result<BoxedSocket, NetError> {
+    let mut target = url.clone();
+    let scheme = if url.scheme() == "wss" {
+        "https"
+    } else {
+        "http"
+    };
+    target
+        .set_scheme(scheme)
+        .map_err(|_| NetError::InvalidUrl)?;
+    let tls_options = TlsOptions {
+        alpn_protocols: Some(Cow::Borrowed(&[AlpnProtocol::HTTP1])),
+        alps_protocols: None,
+        ..tls_options.unwrap_or_default()
+    };
+    let connected = ConnectJob::connect(&target, proxy, Some(&tls_options)).await?;
+    Ok(connected.socket)
+}
+
+/// The [`NetError`] for a tungstenite error.
+fn ws_error(error: tungstenite::Error) -> NetError {
+    match error {
+        tungstenite::Error::Capacity(_) => NetError::MsgTooBig,
+        tungstenite::Error::Protocol(_) | tungstenite::Error::Utf8 => NetError::WsProtocolError,
+        tungstenite::Error::Io(e) => deflate::io_error(&e),
+        _ => NetError::ConnectionClosed,
     }
 }
 
@@ -260,6 +411,26 @@ mod tests {
         assert_eq!(builder.subprotocols.len(), 2);
     }
 
+    #[test]
+    fn test_builder_limits_and_deflate() {
+        let builder = WebSocketBuilder::new()
+            .max_message_size(1 << 20)
+            .max_frame_size(1 << 16)
+            .permessage_deflate(PerMessageDeflate::new());
+        assert_eq!(builder.config.max_message_size, Some(1 << 20));
+        assert_eq!(builder.config.max_frame_size, Some(1 << 16));
+        assert!(builder.deflate.is_some());
+    }
+
+    #[test]
+    fn test_builder_emulation() {
+        let builder = WebSocketBuilder::new()
+            .emulation(Emulation::default())
+            .origin("https://app.example.com");
+        assert!(builder.emulation.is_some());
+        assert_eq!(builder.origin.as_deref(), Some("https://app.example.com"));
+    }
+
     #[test]
     fn test_message_conversion() {
         // Text
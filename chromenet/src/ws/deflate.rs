@@ -0,0 +1,1063 @@
+//! permessage-deflate (RFC 7692).
+//!
+//! Chromium mapping: `WebSocketDeflateParameters`, `WebSocketDeflater`,
+//! `WebSocketInflater` and `WebSocketDeflateStream`
+//!
+//! tungstenite rejects frames with RSV1 set and has no extension support,
+//! so [`DeflateIo`] rewrites frames below it, the way `GreaseIo` rewrites
+//! HTTP/2 frames. It reads the handshake response to learn what the server
+//! accepted, then:
+//!
+//! - inflates compressed messages from the server into uncompressed frames
+//!   of at most 16 KiB, only as tungstenite reads them, so a small frame
+//!   inflating to gigabytes is cut off by its
+//!   [`max_message_size`](super::WebSocketBuilder::max_message_size)
+//!   without ever being held in memory;
+//! - compresses the text and binary messages tungstenite sends and sets
+//!   RSV1 on them.
+//!
+//! Control frames pass through. The offer never carries
+//! `client_max_window_bits`, since miniz only deflates with the full 32 KiB
+//! window, so the server cannot ask for a smaller one.
+
+use crate::base::neterror::NetError;
+use bytes::{Buf, BytesMut};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Name of the extension in `Sec-WebSocket-Extensions`.
+const EXTENSION: &str = "permessage-deflate";
+
+/// The end of a sync flush, removed from compressed messages and added
+/// back before inflating them.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Inflated data per frame handed to tungstenite.
+const INFLATE_CHUNK: usize = 16 * 1024;
+
+/// Bytes read from the connection at a time.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// Longest handshake response accepted.
+const MAX_HEAD: usize = 64 * 1024;
+
+const FIN: u8 = 0x80;
+const RSV1: u8 = 0x40;
+const MASKED: u8 = 0x80;
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+
+/// permessage-deflate parameters: what a client offers and, once
+/// connected, what the server accepted.
+///
+/// ```rust,ignore
+/// use chromenet::ws::{PerMessageDeflate, WebSocketBuilder};
+///
+/// let ws = WebSocketBuilder::new()
+///     .url("wss://feed.example.com/ws")?
+///     .permessage_deflate(PerMessageDeflate::new().with_client_no_context_takeover())
+///     .connect()
+///     .await?;
+/// println!("{:?}", ws.permessage_deflate());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PerMessageDeflate {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: Option<u8>,
+}
+
+impl PerMessageDeflate {
+    /// Offer permessage-deflate with context takeover both ways and the
+    /// server's choice of window.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the server to compress each message on its own, so its
+    /// messages inflate without keeping a 32 KiB window between them.
+    pub fn with_server_no_context_takeover(mut self) -> Self {
+        self.server_no_context_takeover = true;
+        self
+    }
+
+    /// Compress each message on its own, keeping no window between
+    /// messages. The server may ask for it too.
+    pub fn with_client_no_context_takeover(mut self) -> Self {
+        self.client_no_context_takeover = true;
+        self
+    }
+
+    /// Ask the server to compress with a window of at most `2^bits`
+    /// bytes, `bits` clamped to 8..=15.
+    pub fn with_server_max_window_bits(mut self, bits: u8) -> Self {
+        self.server_max_window_bits = Some(bits.clamp(8, 15));
+        self
+    }
+
+    /// Whether the server compresses each message on its own.
+    pub fn server_no_context_takeover(&self) -> bool {
+        self.server_no_context_takeover
+    }
+
+    /// Whether the client compresses each message on its own.
+    pub fn client_no_context_takeover(&self) -> bool {
+        self.client_no_context_takeover
+    }
+
+    /// The server's window, `None` for the default of 15 bits.
+    pub fn server_max_window_bits(&self) -> Option<u8> {
+        self.server_max_window_bits
+    }
+
+    /// The `Sec-WebSocket-Extensions` value offering these parameters.
+    pub(crate) fn offer(&self) -> String {
+        let mut offer = EXTENSION.to_string();
+        if self.server_no_context_takeover {
+            offer.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            offer.push_str("; client_no_context_takeover");
+        }
+        if let Some(bits) = self.server_max_window_bits {
+            offer.push_str(&format!("; server_max_window_bits={bits}"));
+        }
+        offer
+    }
+
+    /// The parameters the server accepted in its `Sec-WebSocket-Extensions`
+    /// `values`, `None` if it declined.
+    ///
+    /// Fails with [`NetError::WsProtocolError`] on extensions or
+    /// parameters that were not offered, or a response not compatible with
+    /// the offer, as Chromium's `WebSocketDeflateParameters::IsValidAsResponse`
+    /// and `IsCompatibleWith`.
+    pub(crate) fn negotiate(&self, values: &[&str]) -> Result<Option<Self>, NetError> {
+        let mut accepted = None;
+        for extension in values.iter().flat_map(|value| value.split(',')) {
+            let mut params = extension.split(';').map(str::trim);
+            match params.next() {
+                Some("") => continue,
+                Some(name) if name.eq_ignore_ascii_case(EXTENSION) && accepted.is_none() => {}
+                _ => return Err(NetError::WsProtocolError),
+            }
+            let mut response = Self::default();
+            let mut window_bits = false;
+            for param in params {
+                let (name, value) = match param.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (param, None),
+                };
+                let flag = match (name, value) {
+                    ("server_no_context_takeover", None) => {
+                        &mut response.server_no_context_takeover
+                    }
+                    ("client_no_context_takeover", None) => {
+                        &mut response.client_no_context_takeover
+                    }
+                    ("server_max_window_bits", Some(value)) => {
+                        let bits = value
+                            .parse()
+                            .ok()
+                            .filter(|bits| (8..=15).contains(bits) && !value.starts_with('0'))
+                            .ok_or(NetError::WsProtocolError)?;
+                        response.server_max_window_bits = Some(bits);
+                        &mut window_bits
+                    }
+                    _ => return Err(NetError::WsProtocolError),
+                };
+                if std::mem::replace(flag, true) {
+                    return Err(NetError::WsProtocolError);
+                }
+            }
+            let compatible = (!self.server_no_context_takeover
+                || response.server_no_context_takeover)
+                && match (self.server_max_window_bits, response.server_max_window_bits) {
+                    (Some(offered), Some(bits)) => bits <= offered,
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                };
+            if !compatible {
+                return Err(NetError::WsProtocolError);
+            }
+            // The client keeps no context if it offered not to either
+            response.client_no_context_takeover |= self.client_no_context_takeover;
+            accepted = Some(response);
+        }
+        Ok(accepted)
+    }
+}
+
+/// A frame too large for the configured
+/// [`max_frame_size`](super::WebSocketBuilder::max_frame_size).
+#[derive(Debug)]
+struct FrameTooBig;
+
+impl fmt::Display for FrameTooBig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("compressed frame too big")
+    }
+}
+
+impl std::error::Error for FrameTooBig {}
+
+/// The [`NetError`] for an I/O error of a [`DeflateIo`].
+pub(crate) fn io_error(error: &io::Error) -> NetError {
+    if error.get_ref().is_some_and(|e| e.is::<FrameTooBig>()) {
+        NetError::MsgTooBig
+    } else if error.kind() == io::ErrorKind::InvalidData {
+        NetError::WsProtocolError
+    } else {
+        NetError::ConnectionClosed
+    }
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// A parsed frame header.
+#[derive(Debug, Clone, Copy)]
+struct FrameHeader {
+    first: u8,
+    masked: bool,
+    payload_len: u64,
+    /// Bytes of the header, the masking key included.
+    len: usize,
+}
+
+impl FrameHeader {
+    /// The header at the start of `buf`, `None` until it is complete.
+    fn parse(buf: &[u8]) -> Option<Self> {
+        let (&first, rest) = buf.split_first()?;
+        let &second = rest.first()?;
+        let masked = second & MASKED != 0;
+        let (payload_len, mut len) = match second & 0x7f {
+            126 => (
+                u16::from_be_bytes(buf.get(2..4)?.try_into().ok()?) as u64,
+                4,
+            ),
+            127 => (u64::from_be_bytes(buf.get(2..10)?.try_into().ok()?), 10),
+            short => (short as u64, 2),
+        };
+        if masked {
+            len += 4;
+        }
+        (buf.len() >= len).then_some(Self {
+            first,
+            masked,
+            payload_len,
+            len,
+        })
+    }
+
+    fn fin(&self) -> bool {
+        self.first & FIN != 0
+    }
+
+    fn rsv1(&self) -> bool {
+        self.first & RSV1 != 0
+    }
+
+    fn opcode(&self) -> u8 {
+        self.first & 0x0f
+    }
+}
+
+/// Append a frame header to `out`.
+fn put_header(out: &mut Vec<u8>, first: u8, mask: Option<[u8; 4]>, payload_len: usize) {
+    out.push(first);
+    let mask_bit = if mask.is_some() { MASKED } else { 0 };
+    match payload_len {
+        0..=125 => out.push(mask_bit | payload_len as u8),
+        126..=0xffff => {
+            out.push(mask_bit | 126);
+            out.extend_from_slice(&(payload_len as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(mask_bit | 127);
+            out.extend_from_slice(&(payload_len as u64).to_be_bytes());
+        }
+    }
+    if let Some(mask) = mask {
+        out.extend_from_slice(&mask);
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ReadState {
+    /// Holding back the handshake response until it is complete.
+    Head,
+    /// Nothing to rewrite: bytes pass through.
+    Raw,
+    /// Waiting for the next frame header.
+    Header,
+    /// Passing this many payload bytes of a frame through.
+    Passthrough(u64),
+    /// Inflating this many payload bytes of a compressed frame.
+    Inflate { remaining: u64, fin: bool },
+    /// Inflating the trailer ending a compressed message, this many of
+    /// its bytes fed.
+    Trailer(usize),
+}
+
+/// What to inflate next.
+#[derive(Debug, Clone, Copy)]
+enum Input {
+    /// This many payload bytes at the start of `rbuf`.
+    Payload(usize),
+    /// The trailer, after this many of its bytes.
+    Trailer(usize),
+}
+
+/// The compressed message being inflated.
+#[derive(Debug, Clone, Copy)]
+struct Inflating {
+    opcode: u8,
+    /// Whether no frame of it was handed on yet.
+    first: bool,
+}
+
+/// A WebSocket connection's I/O, compressing and inflating messages with
+/// permessage-deflate once the handshake response accepted it.
+pub(crate) struct DeflateIo<T> {
+    inner: T,
+    offer: Option<PerMessageDeflate>,
+    negotiated: Result<Option<PerMessageDeflate>, NetError>,
+    max_frame_size: Option<usize>,
+
+    read: ReadState,
+    /// Bytes read from `inner` not yet handled.
+    rbuf: BytesMut,
+    /// Rewritten bytes for the reader.
+    out: BytesMut,
+    eof: bool,
+    inflating: Option<Inflating>,
+    inflate: Decompress,
+    /// The server ended its deflate stream: the rest of the message is
+    /// skipped and the stream starts over.
+    inflate_ended: bool,
+
+    /// The frame being written, until complete.
+    frame: Vec<u8>,
+    /// Rewritten bytes not yet written to `inner`.
+    pending: Vec<u8>,
+    written: usize,
+    /// Whether the message being written is compressed.
+    deflating: bool,
+    deflate: Compress,
+}
+
+impl<T> DeflateIo<T> {
+    /// Wrap `inner`, whose handshake request offers `offer`. Compressed
+    /// frames larger than `max_frame_size` fail the read.
+    pub(crate) fn new(
+        inner: T,
+        offer: Option<PerMessageDeflate>,
+        max_frame_size: Option<usize>,
+    ) -> Self {
+        Self {
+            inner,
+            read: ReadState::Head,
+            offer,
+            negotiated: Ok(None),
+            max_frame_size,
+            rbuf: BytesMut::new(),
+            out: BytesMut::new(),
+            eof: false,
+            inflating: None,
+            inflate: Decompress::new(false),
+            inflate_ended: false,
+            frame: Vec::new(),
+            pending: Vec::new(),
+            written: 0,
+            deflating: false,
+            deflate: Compress::new(Compression::default(), false),
+        }
+    }
+
+    /// What the server accepted, once the handshake is done.
+    pub(crate) fn negotiated(&self) -> Result<Option<PerMessageDeflate>, NetError> {
+        self.negotiated.clone()
+    }
+
+    fn params(&self) -> Option<PerMessageDeflate> {
+        self.negotiated.as_ref().ok().copied().flatten()
+    }
+
+    /// Turn bytes of `rbuf` into bytes of `out`, returning whether
+    /// anything changed.
+    fn process(&mut self) -> io::Result<bool> {
+        match self.read {
+            ReadState::Head => self.process_head(),
+            ReadState::Raw => {
+                if self.rbuf.is_empty() {
+                    return Ok(false);
+                }
+                self.out = self.rbuf.split();
+                Ok(true)
+            }
+            ReadState::Header => self.process_header(),
+            ReadState::Passthrough(remaining) => {
+                let n = remaining.min(self.rbuf.len() as u64) as usize;
+                self.out.extend_from_slice(&self.rbuf.split_to(n));
+                let remaining = remaining - n as u64;
+                self.read = if remaining == 0 {
+                    ReadState::Header
+                } else {
+                    ReadState::Passthrough(remaining)
+                };
+                Ok(n > 0 || remaining == 0)
+            }
+            ReadState::Inflate { remaining, fin } => {
+                let available = remaining.min(self.rbuf.len() as u64) as usize;
+                let (consumed, produced) = self.inflate_step(Input::Payload(available))?;
+                self.rbuf.advance(consumed);
+                let remaining = remaining - consumed as u64;
+                self.read = match (remaining, produced) {
+                    (0, 0) if fin => ReadState::Trailer(0),
+                    (0, 0) => ReadState::Header,
+                    _ => ReadState::Inflate { remaining, fin },
+                };
+                Ok(consumed > 0 || produced > 0 || remaining == 0)
+            }
+            ReadState::Trailer(fed) => {
+                let (consumed, produced) = self.inflate_step(Input::Trailer(fed))?;
+                let fed = fed + consumed;
+                if consumed == 0 && produced == 0 && fed < TRAILER.len() {
+                    return Err(invalid_data("deflate stream stalled"));
+                }
+                if fed == TRAILER.len() && produced == 0 {
+                    self.finish_message();
+                    self.read = ReadState::Header;
+                } else {
+                    self.read = ReadState::Trailer(fed);
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn process_head(&mut self) -> io::Result<bool> {
+        let Some(end) = self.rbuf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if self.rbuf.len() > MAX_HEAD {
+                return Err(invalid_data("handshake response too long"));
+            }
+            return Ok(false);
+        };
+        let head = self.rbuf.split_to(end + 4);
+        {
+            let head_text = String::from_utf8_lossy(&head);
+            let values: Vec<&str> = head_text
+                .split("\r\n")
+                .filter_map(|line| line.split_once(':'))
+                .filter(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-extensions"))
+                .map(|(_, value)| value)
+                .collect();
+            self.negotiated = match &self.offer {
+                Some(offer) => offer.negotiate(&values),
+                None if values.is_empty() => Ok(None),
+                None => Err(NetError::WsProtocolError),
+            };
+        }
+        self.read = match self.params() {
+            Some(_) => ReadState::Header,
+            None => ReadState::Raw,
+        };
+        self.out = head;
+        Ok(true)
+    }
+
+    fn process_header(&mut self) -> io::Result<bool> {
+        let Some(header) = FrameHeader::parse(&self.rbuf) else {
+            return Ok(false);
+        };
+        let opcode = header.opcode();
+        let compressed = !header.masked
+            && match self.inflating {
+                None => header.rsv1() && (opcode == TEXT || opcode == BINARY),
+                Some(_) => opcode == CONTINUATION && !header.rsv1(),
+            };
+        if !compressed {
+            // Control frames, uncompressed messages, and frames tungstenite
+            // rejects itself
+            self.out.extend_from_slice(&self.rbuf.split_to(header.len));
+            self.read = ReadState::Passthrough(header.payload_len);
+            return Ok(true);
+        }
+        if self
+            .max_frame_size
+            .is_some_and(|max| header.payload_len > max as u64)
+        {
+            return Err(invalid_data(FrameTooBig));
+        }
+        self.rbuf.advance(header.len);
+        if self.inflating.is_none() {
+            self.inflating = Some(Inflating {
+                opcode,
+                first: true,
+            });
+        }
+        self.read = ReadState::Inflate {
+            remaining: header.payload_len,
+            fin: header.fin(),
+        };
+        Ok(true)
+    }
+
+    /// Inflate `input` into a frame, returning the bytes consumed and
+    /// produced.
+    fn inflate_step(&mut self, input: Input) -> io::Result<(usize, usize)> {
+        let input = match input {
+            Input::Payload(available) => &self.rbuf[..available],
+            Input::Trailer(fed) => &TRAILER[fed..],
+        };
+        if self.inflate_ended {
+            return Ok((input.len(), 0));
+        }
+        let mut chunk = vec![0u8; INFLATE_CHUNK];
+        let (before_in, before_out) = (self.inflate.total_in(), self.inflate.total_out());
+        let status = self
+            .inflate
+            .decompress(input, &mut chunk, FlushDecompress::None)
+            .map_err(invalid_data)?;
+        let consumed = (self.inflate.total_in() - before_in) as usize;
+        let produced = (self.inflate.total_out() - before_out) as usize;
+        if status == Status::StreamEnd {
+            self.inflate_ended = true;
+        }
+        if produced > 0 {
+            self.emit(false, &chunk[..produced]);
+        }
+        Ok((consumed, produced))
+    }
+
+    /// Hand on inflated `data` as a frame of the message being inflated.
+    fn emit(&mut self, fin: bool, data: &[u8]) {
+        let Some(inflating) = self.inflating.as_mut() else {
+            return;
+        };
+        let opcode = if std::mem::replace(&mut inflating.first, false) {
+            inflating.opcode
+        } else {
+            CONTINUATION
+        };
+        let mut frame = Vec::with_capacity(data.len() + 4);
+        put_header(
+            &mut frame,
+            if fin { FIN | opcode } else { opcode },
+            None,
+            data.len(),
+        );
+        frame.extend_from_slice(data);
+        self.out.extend_from_slice(&frame);
+    }
+
+    fn finish_message(&mut self) {
+        self.emit(true, &[]);
+        self.inflating = None;
+        let no_context = self
+            .params()
+            .is_some_and(|params| params.server_no_context_takeover);
+        if no_context || std::mem::take(&mut self.inflate_ended) {
+            self.inflate.reset(false);
+        }
+    }
+
+    /// Bytes still missing from the frame being written.
+    fn frame_needed(&self) -> usize {
+        match FrameHeader::parse(&self.frame) {
+            Some(header) => header.len + header.payload_len as usize - self.frame.len(),
+            None if self.frame.len() < 2 => 2 - self.frame.len(),
+            // The rest of the header: extended length and masking key
+            None => 1,
+        }
+    }
+
+    /// The complete frame in `frame`, compressed if it is part of a text
+    /// or binary message.
+    fn rewrite(&mut self, mut frame: Vec<u8>) -> io::Result<Vec<u8>> {
+        let Some(header) = FrameHeader::parse(&frame) else {
+            return Ok(frame);
+        };
+        let opcode = header.opcode();
+        let data = opcode == TEXT || opcode == BINARY;
+        let compress = (data && !self.deflating) || (opcode == CONTINUATION && self.deflating);
+        if !header.masked || !compress {
+            return Ok(frame);
+        }
+        let mask: [u8; 4] = frame[header.len - 4..header.len]
+            .try_into()
+            .map_err(invalid_data)?;
+        let payload = &mut frame[header.len..];
+        apply_mask(payload, mask);
+        let mut compressed = self.deflate_payload(payload)?;
+        if header.fin() && compressed.ends_with(&TRAILER) {
+            compressed.truncate(compressed.len() - TRAILER.len());
+        }
+        apply_mask(&mut compressed, mask);
+
+        let first = header.first | if data { RSV1 } else { 0 };
+        let mut out = Vec::with_capacity(compressed.len() + 14);
+        put_header(&mut out, first, Some(mask), compressed.len());
+        out.extend_from_slice(&compressed);
+
+        self.deflating = !header.fin();
+        let no_context = self
+            .params()
+            .is_some_and(|params| params.client_no_context_takeover);
+        if header.fin() && no_context {
+            self.deflate.reset();
+        }
+        Ok(out)
+    }
+
+    fn deflate_payload(&mut self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(payload.len() / 2 + 64);
+        let start = self.deflate.total_in();
+        loop {
+            if out.capacity() - out.len() < 64 {
+                out.reserve(out.capacity().max(64));
+            }
+            let consumed = (self.deflate.total_in() - start) as usize;
+            self.deflate
+                .compress_vec(&payload[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(invalid_data)?;
+            let done = (self.deflate.total_in() - start) as usize == payload.len();
+            if done && out.len() < out.capacity() {
+                return Ok(out);
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> DeflateIo<T> {
+    /// Write out the rewritten bytes held back.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            let n =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        self.pending = Vec::new();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for DeflateIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        loop {
+            if !this.out.is_empty() {
+                let n = this.out.len().min(buf.remaining());
+                buf.put_slice(&this.out[..n]);
+                this.out.advance(n);
+                return Poll::Ready(Ok(()));
+            }
+            if matches!(this.read, ReadState::Raw) && this.rbuf.is_empty() {
+                return Pin::new(&mut this.inner).poll_read(cx, buf);
+            }
+            if this.process()? {
+                continue;
+            }
+            if this.eof {
+                return Poll::Ready(Ok(()));
+            }
+            let mut chunk = [0u8; READ_CHUNK];
+            let mut read = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            if read.filled().is_empty() {
+                this.eof = true;
+            } else {
+                this.rbuf.extend_from_slice(read.filled());
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for DeflateIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        ready!(this.poll_pending(cx))?;
+        if this.params().is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        let n = this.frame_needed().min(buf.len());
+        this.frame.extend_from_slice(&buf[..n]);
+        if this.frame_needed() == 0 {
+            let frame = std::mem::take(&mut this.frame);
+            this.pending = this.rewrite(frame)?;
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_negotiate() {
+        let offer = PerMessageDeflate::new().with_server_max_window_bits(12);
+        assert_eq!(
+            offer.offer(),
+            "permessage-deflate; server_max_window_bits=12"
+        );
+
+        assert_eq!(offer.negotiate(&[]).unwrap(), None);
+        let accepted = offer
+            .negotiate(&[
+                "permessage-deflate; server_max_window_bits=10; client_no_context_takeover",
+            ])
+            .unwrap()
+            .unwrap();
+        assert_eq!(accepted.server_max_window_bits(), Some(10));
+        assert!(accepted.client_no_context_takeover());
+        assert!(!accepted.server_no_context_takeover());
+
+        for bad in [
+            "permessage-deflate",
+            "permessage-deflate; server_max_window_bits=13",
+            "permessage-deflate; server_max_window_bits=10; client_max_window_bits=10",
+            "permessage-deflate; server_max_window_bits=10, permessage-deflate",
+            "x-webkit-deflate-frame",
+        ] {
+            assert!(
+                matches!(offer.negotiate(&[bad]), Err(NetError::WsProtocolError)),
+                "{bad}"
+            );
+        }
+
+        let offer = PerMessageDeflate::new().with_server_no_context_takeover();
+        assert!(offer.negotiate(&["permessage-deflate"]).is_err());
+        assert!(offer
+            .negotiate(&["permessage-deflate; server_no_context_takeover"])
+            .unwrap()
+            .is_some());
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        compress_with(&mut Compress::new(Compression::default(), false), data)
+    }
+
+    /// Compress a message with `deflate`, keeping its window for the next.
+    fn compress_with(deflate: &mut Compress, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + 64);
+        deflate
+            .compress_vec(data, &mut out, FlushCompress::Sync)
+            .unwrap();
+        out.truncate(out.len() - TRAILER.len());
+        out
+    }
+
+    /// Inflate a message with `inflate`, keeping its window for the next.
+    fn inflate_with(inflate: &mut Decompress, payload: &[u8]) -> Vec<u8> {
+        let mut input = payload.to_vec();
+        input.extend_from_slice(&TRAILER);
+        let mut out = Vec::with_capacity(64 * 1024);
+        inflate
+            .decompress_vec(&input, &mut out, FlushDecompress::Sync)
+            .unwrap();
+        out
+    }
+
+    fn frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        put_header(&mut frame, first, None, payload.len());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn masked_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut frame = Vec::new();
+        put_header(&mut frame, first, Some(mask), payload.len());
+        let start = frame.len();
+        frame.extend_from_slice(payload);
+        apply_mask(&mut frame[start..], mask);
+        frame
+    }
+
+    fn head(extensions: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 101 Switching Protocols\r\nSec-WebSocket-Extensions: {extensions}\r\n\r\n"
+        )
+        .into_bytes()
+    }
+
+    /// Bytes that do not compress.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    /// The payloads of the data messages in `frames`, unmasked.
+    fn messages(mut frames: &[u8]) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        let mut message = Vec::new();
+        while let Some(header) = FrameHeader::parse(frames) {
+            let end = header.len + header.payload_len as usize;
+            if header.opcode() < 0x8 {
+                let start = message.len();
+                message.extend_from_slice(&frames[header.len..end]);
+                if header.masked {
+                    let mask = frames[header.len - 4..header.len].try_into().unwrap();
+                    apply_mask(&mut message[start..], mask);
+                }
+                if header.fin() {
+                    messages.push(std::mem::take(&mut message));
+                }
+            }
+            frames = &frames[end..];
+        }
+        messages
+    }
+
+    /// Reads at most one byte at a time, so every frame arrives split.
+    struct OneByte<T>(T);
+
+    impl<T: AsyncRead + Unpin> AsyncRead for OneByte<T> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let mut byte = [0u8; 1];
+            let mut one = ReadBuf::new(&mut byte);
+            ready!(Pin::new(&mut self.0).poll_read(cx, &mut one))?;
+            buf.put_slice(one.filled());
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inflate_fragmented_message() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let mut io = DeflateIo::new(client, Some(PerMessageDeflate::new()), None);
+
+        let head = b"HTTP/1.1 101 Switching Protocols\r\nSec-WebSocket-Extensions: permessage-deflate\r\n\r\n";
+        let compressed = compress(b"hello hello hello");
+        let (a, b) = compressed.split_at(4);
+        let mut sent = head.to_vec();
+        sent.extend(frame(RSV1 | TEXT, a));
+        sent.extend(frame(FIN | 0x9, b"ping"));
+        sent.extend(frame(FIN | CONTINUATION, b));
+        server.write_all(&sent).await.unwrap();
+        drop(server);
+
+        let mut received = Vec::new();
+        io.read_to_end(&mut received).await.unwrap();
+        assert_eq!(io.negotiated().unwrap(), Some(PerMessageDeflate::new()));
+
+        // The head is passed on as is, the message as uncompressed frames
+        // around the ping
+        assert_eq!(&received[..head.len()], head);
+        let mut rest = &received[head.len()..];
+        let mut text = Vec::new();
+        let mut opcodes = Vec::new();
+        while let Some(header) = FrameHeader::parse(rest) {
+            let end = header.len + header.payload_len as usize;
+            opcodes.push(header.first);
+            if header.opcode() != 0x9 {
+                text.extend_from_slice(&rest[header.len..end]);
+            }
+            rest = &rest[end..];
+        }
+        assert_eq!(text, b"hello hello hello");
+        assert_eq!(opcodes.first(), Some(&TEXT));
+        assert!(opcodes.contains(&(FIN | 0x9)));
+        assert_eq!(opcodes.last(), Some(&(FIN | CONTINUATION)));
+    }
+
+    #[tokio::test]
+    async fn test_frames_split_across_reads() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let mut io = DeflateIo::new(OneByte(client), Some(PerMessageDeflate::new()), None);
+
+        // A message with a 16-bit length, a ping, and one referring back
+        // to the first
+        let long = noise(300);
+        let mut deflate = Compress::new(Compression::default(), false);
+        let head = head("permessage-deflate");
+        let mut sent = head.clone();
+        sent.extend(frame(
+            FIN | RSV1 | BINARY,
+            &compress_with(&mut deflate, &long),
+        ));
+        sent.extend(frame(FIN | 0x9, b"ping"));
+        sent.extend(frame(
+            FIN | RSV1 | BINARY,
+            &compress_with(&mut deflate, &long),
+        ));
+        server.write_all(&sent).await.unwrap();
+        drop(server);
+
+        let mut received = Vec::new();
+        io.read_to_end(&mut received).await.unwrap();
+        assert_eq!(&received[..head.len()], &head[..]);
+        assert_eq!(messages(&received[head.len()..]), [long.clone(), long]);
+    }
+
+    #[tokio::test]
+    async fn test_inflate_context_takeover() {
+        let message = b"the same message, again and again".to_vec();
+        for no_context in [false, true] {
+            let (offer, extensions) = if no_context {
+                (
+                    PerMessageDeflate::new().with_server_no_context_takeover(),
+                    "permessage-deflate; server_no_context_takeover",
+                )
+            } else {
+                (PerMessageDeflate::new(), "permessage-deflate")
+            };
+            let (client, mut server) = tokio::io::duplex(64 * 1024);
+            let mut io = DeflateIo::new(client, Some(offer), None);
+
+            let mut deflate = Compress::new(Compression::default(), false);
+            let mut sent = head(extensions);
+            let mut sizes = Vec::new();
+            for _ in 0..2 {
+                if no_context {
+                    deflate.reset();
+                }
+                let compressed = compress_with(&mut deflate, &message);
+                sizes.push(compressed.len());
+                sent.extend(frame(FIN | RSV1 | TEXT, &compressed));
+            }
+            server.write_all(&sent).await.unwrap();
+            drop(server);
+
+            let mut received = Vec::new();
+            io.read_to_end(&mut received).await.unwrap();
+            let head_len = head(extensions).len();
+            assert_eq!(
+                messages(&received[head_len..]),
+                [message.clone(), message.clone()]
+            );
+            if no_context {
+                // Each message inflates on its own
+                assert_eq!(sizes[0], sizes[1]);
+                assert_eq!(io.inflate.total_out(), 0);
+            } else {
+                // The second message only refers back to the first
+                assert!(sizes[1] < sizes[0], "{sizes:?}");
+                assert_eq!(io.inflate.total_out(), 2 * message.len() as u64);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deflate_context_takeover() {
+        let message = noise(200);
+        for no_context in [false, true] {
+            let offer = if no_context {
+                PerMessageDeflate::new().with_client_no_context_takeover()
+            } else {
+                PerMessageDeflate::new()
+            };
+            let (client, mut server) = tokio::io::duplex(64 * 1024);
+            let mut io = DeflateIo::new(client, Some(offer), None);
+            let head = head("permessage-deflate");
+            server.write_all(&head).await.unwrap();
+            let mut received = vec![0u8; head.len()];
+            io.read_exact(&mut received).await.unwrap();
+
+            // The second message is written in two fragments
+            io.write_all(&masked_frame(FIN | BINARY, &message))
+                .await
+                .unwrap();
+            let (a, b) = message.split_at(10);
+            io.write_all(&masked_frame(BINARY, a)).await.unwrap();
+            io.write_all(&masked_frame(FIN | CONTINUATION, b))
+                .await
+                .unwrap();
+            io.flush().await.unwrap();
+            drop(io);
+            let mut sent = Vec::new();
+            server.read_to_end(&mut sent).await.unwrap();
+
+            let first = FrameHeader::parse(&sent).unwrap();
+            assert_eq!(first.first, FIN | RSV1 | BINARY);
+            let compressed = messages(&sent);
+            assert_eq!(compressed.len(), 2);
+            if no_context {
+                for payload in &compressed {
+                    let mut inflate = Decompress::new(false);
+                    assert_eq!(inflate_with(&mut inflate, payload), message);
+                }
+            } else {
+                assert!(compressed[1].len() < compressed[0].len());
+                let mut inflate = Decompress::new(false);
+                assert_eq!(inflate_with(&mut inflate, &compressed[0]), message);
+                assert_eq!(inflate_with(&mut inflate, &compressed[1]), message);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inflate_bomb_is_handed_on_in_chunks() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let mut io = DeflateIo::new(client, Some(PerMessageDeflate::new()), None);
+
+        // 16 MiB of zeros in a frame of a few KiB
+        let compressed = compress(&vec![0u8; 16 << 20]);
+        assert!(compressed.len() < 32 * 1024);
+        let head = head("permessage-deflate");
+        let mut sent = head.clone();
+        sent.extend(frame(FIN | RSV1 | BINARY, &compressed));
+        server.write_all(&sent).await.unwrap();
+
+        // Only what tungstenite reads is inflated, one chunk at a time, so
+        // its max_message_size cuts the message off before it is held
+        let mut received = vec![0u8; head.len()];
+        io.read_exact(&mut received).await.unwrap();
+        let mut read = 0;
+        let mut buf = [0u8; 4096];
+        while read < 256 * 1024 {
+            read += io.read(&mut buf).await.unwrap();
+            assert!(io.out.len() <= INFLATE_CHUNK + 4);
+            assert!(io.inflate.total_out() <= (read + INFLATE_CHUNK) as u64);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! The opening handshake, written the way Chrome writes it.
+//!
+//! Chromium mapping: `WebSocketBasicHandshakeStream` and
+//! `WebSocketHandshakeRequestInfo`
+//!
+//! tungstenite writes its own fixed set of handshake headers, so servers
+//! could tell a chromenet WebSocket from a browser's. The request is
+//! written here instead, and tungstenite takes over the connection once
+//! the response is checked. With an emulation profile, the headers follow
+//! Chrome's order and take `User-Agent`, `Accept-Encoding` and
+//! `Accept-Language` from the profile:
+//!
+//! ```text
+//! GET /feed HTTP/1.1
+//! Host: example.com
+//! Connection: Upgrade
+//! Pragma: no-cache
+//! Cache-Control: no-cache
+//! User-Agent: Mozilla/5.0 ...
+//! Upgrade: websocket
+//! Origin: https://example.com
+//! Sec-WebSocket-Version: 13
+//! Accept-Encoding: gzip, deflate, br, zstd
+//! Accept-Language: en-US,en;q=0.9
+//! <builder headers>
+//! Sec-WebSocket-Key: ...
+//! Sec-WebSocket-Extensions: permessage-deflate
+//! Sec-WebSocket-Protocol: ...
+//! ```
+//!
+//! Without a profile only the headers RFC 6455 requires are sent, with
+//! `Origin` if one was set.
+
+use crate::base::neterror::NetError;
+use http::header::{self, HeaderMap};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use url::Url;
+
+/// What goes into a handshake request.
+pub(crate) struct Handshake<'a> {
+    pub url: &'a Url,
+    /// Headers of the emulation profile, if one is applied.
+    pub profile: Option<&'a HeaderMap>,
+    pub origin: Option<&'a str>,
+    pub headers: &'a HeaderMap,
+    pub subprotocols: &'a [String],
+    pub extensions: Option<String>,
+}
+
+impl Handshake<'_> {
+    /// The request's headers in the order they are sent.
+    pub(crate) fn headers(&self, key: &str) -> Vec<(String, String)> {
+        let mut host = self.url.host_str().unwrap_or_default().to_string();
+        if let Some(port) = self.url.port() {
+            host = format!("{host}:{port}");
+        }
+        let origin = self.origin.map(str::to_string).or_else(|| {
+            self.profile
+                .map(|_| http_origin(self.url).ascii_serialization())
+        });
+
+        let mut lines = vec![
+            ("Host".to_string(), host),
+            ("Connection".to_string(), "Upgrade".to_string()),
+        ];
+        if self.profile.is_some() {
+            lines.push(("Pragma".to_string(), "no-cache".to_string()));
+            lines.push(("Cache-Control".to_string(), "no-cache".to_string()));
+        }
+        lines.extend(self.profile_header("User-Agent", header::USER_AGENT));
+        lines.push(("Upgrade".to_string(), "websocket".to_string()));
+        if let Some(origin) = origin {
+            lines.push(("Origin".to_string(), origin));
+        }
+        lines.push(("Sec-WebSocket-Version".to_string(), "13".to_string()));
+        lines.extend(self.profile_header("Accept-Encoding", header::ACCEPT_ENCODING));
+        lines.extend(self.profile_header("Accept-Language", header::ACCEPT_LANGUAGE));
+
+        // Builder headers replace generated ones of the same name in place
+        for (name, value) in self.headers {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            match lines
+                .iter_mut()
+                .find(|(line, _)| line.eq_ignore_ascii_case(name.as_str()))
+            {
+                Some(line) => line.1 = value,
+                None => lines.push((name.as_str().to_string(), value)),
+            }
+        }
+
+        lines.push(("Sec-WebSocket-Key".to_string(), key.to_string()));
+        if let Some(extensions) = &self.extensions {
+            lines.push(("Sec-WebSocket-Extensions".to_string(), extensions.clone()));
+        }
+        if !self.subprotocols.is_empty() {
+            lines.push((
+                "Sec-WebSocket-Protocol".to_string(),
+                self.subprotocols.join(", "),
+            ));
+        }
+        lines
+    }
+
+    /// The profile's `name` header, sent as `display`.
+    fn profile_header(&self, display: &str, name: header::HeaderName) -> Option<(String, String)> {
+        let value = self.profile?.get(name)?.to_str().ok()?;
+        Some((display.to_string(), value.to_string()))
+    }
+
+    /// The request for a new key, and the key.
+    pub(crate) fn request(&self) -> (Vec<u8>, String) {
+        let key = generate_key();
+        let mut target = self.url.path().to_string();
+        if let Some(query) = self.url.query() {
+            target = format!("{target}?{query}");
+        }
+        let mut request = format!("GET {target} HTTP/1.1\r\n");
+        for (name, value) in self.headers(&key) {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        (request.into_bytes(), key)
+    }
+}
+
+/// The `http` or `https` origin of a `ws` or `wss` URL.
+fn http_origin(url: &Url) -> url::Origin {
+    let mut http = url.clone();
+    let scheme = if url.scheme() == "wss" {
+        "https"
+    } else {
+        "http"
+    };
+    let _ = http.set_scheme(scheme);
+    http.origin()
+}
+
+/// Read the handshake response from `io` and check it accepts the
+/// request sent with `key` and `subprotocols`: with subprotocols, the
+/// server must pick one of them.
+///
+/// [`DeflateIo`](super::deflate::DeflateIo) hands the response head on by
+/// itself, so this never reads into the frames after it.
+pub(crate) async fn read_response<T: AsyncRead + Unpin>(
+    io: &mut T,
+    key: &str,
+    subprotocols: &[String],
+) -> Result<(), NetError> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        let n = io
+            .read(&mut buf)
+            .await
+            .map_err(|e| super::deflate::io_error(&e))?;
+        if n == 0 {
+            return Err(NetError::ConnectionClosed);
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.split("\r\n");
+    let status = lines.next().unwrap_or_default();
+    if status.split(' ').nth(1) != Some("101") {
+        tracing::debug!("WebSocket handshake refused: {}", status);
+        return Err(NetError::ConnectionFailed);
+    }
+    let mut upgrade = false;
+    let mut connection = false;
+    let mut accept = None;
+    let mut protocol = None;
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+            "connection" => {
+                connection = value
+                    .split(',')
+                    .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+            }
+            "sec-websocket-accept" => accept = Some(value.to_string()),
+            "sec-websocket-protocol" => protocol = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if !upgrade || !connection || accept != Some(derive_accept_key(key.as_bytes())) {
+        return Err(NetError::WsProtocolError);
+    }
+    match &protocol {
+        Some(picked) if !subprotocols.contains(picked) => Err(NetError::WsProtocolError),
+        None if !subprotocols.is_empty() => Err(NetError::WsProtocolError),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_chrome_header_order() {
+        let url = Url::parse("wss://example.com:8443/feed?v=2").unwrap();
+        let mut profile = HeaderMap::new();
+        profile.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en-US"));
+        profile.insert(header::USER_AGENT, HeaderValue::from_static("Chrome"));
+        profile.insert(header::ACCEPT, HeaderValue::from_static("text/html"));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-token", HeaderValue::from_static("t"));
+        headers.insert("user-agent", HeaderValue::from_static("Custom"));
+        let handshake = Handshake {
+            url: &url,
+            profile: Some(&profile),
+            origin: None,
+            headers: &headers,
+            subprotocols: &[],
+            extensions: Some("permessage-deflate".to_string()),
+        };
+
+        let names: Vec<_> = handshake
+            .headers("k")
+            .into_iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Host: example.com:8443",
+                "Connection: Upgrade",
+                "Pragma: no-cache",
+                "Cache-Control: no-cache",
+                "User-Agent: Custom",
+                "Upgrade: websocket",
+                "Origin: https://example.com:8443",
+                "Sec-WebSocket-Version: 13",
+                "Accept-Language: en-US",
+                "x-token: t",
+                "Sec-WebSocket-Key: k",
+                "Sec-WebSocket-Extensions: permessage-deflate",
+            ]
+        );
+        let (request, key) = handshake.request();
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("GET /feed?v=2 HTTP/1.1\r\nHost: "));
+        assert!(request.contains(&format!("Sec-WebSocket-Key: {key}\r\n")));
+    }
+
+    #[tokio::test]
+    async fn test_read_response() {
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let response = |protocol: &str| {
+            format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: keep-alive, Upgrade\r\nSec-WebSocket-Accept: {}\r\n{protocol}\r\n",
+                derive_accept_key(key.as_bytes())
+            )
+        };
+        let protocols = ["chat".to_string()];
+
+        let ok = response("Sec-WebSocket-Protocol: chat\r\n");
+        read_response(&mut ok.as_bytes(), key, &protocols)
+            .await
+            .unwrap();
+
+        let missing = response("");
+        let result = read_response(&mut missing.as_bytes(), key, &protocols).await;
+        assert!(matches!(result, Err(NetError::WsProtocolError)));
+
+        let result = read_response(&mut ok.as_bytes(), "other key", &protocols).await;
+        assert!(matches!(result, Err(NetError::WsProtocolError)));
+
+        let refused = b"HTTP/1.1 403 Forbidden\r\n\r\n";
+        let result = read_response(&mut &refused[..], key, &[]).await;
+        assert!(matches!(result, Err(NetError::ConnectionFailed)));
+    }
+}
@@ -11,9 +11,18 @@
 //! ws.send(Message::Text("Hello".into())).await?;
 //! let msg = ws.recv().await?;
 //! ```
+//!
+//! [`WebSocketBuilder`] bounds incoming frames and messages and can offer
+//! permessage-deflate (see [`PerMessageDeflate`]). With an emulation
+//! profile, or from [`Client::websocket`](crate::Client::websocket), the
+//! handshake carries the profile's headers in Chrome's order and its TLS
+//! fingerprint.
 
 mod connection;
+mod deflate;
+mod handshake;
 mod message;
 
 pub use connection::{WebSocket, WebSocketBuilder};
+pub use deflate::PerMessageDeflate;
 pub use message::{CloseCode, CloseFrame, Message};
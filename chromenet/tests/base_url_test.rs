@@ -0,0 +1,68 @@
+//! Base URL resolution tests against a local HTTP/1.1 server.
+
+use chromenet::base::neterror::NetError;
+use chromenet::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Serve requests and report each request line.
+async fn capture_server() -> (String, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let head = String::from_utf8_lossy(&buf[..n]).into_owned();
+            let _ = tx.send(head.lines().next().unwrap_or_default().to_string());
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+        }
+    });
+
+    (url, rx)
+}
+
+#[tokio::test]
+async fn test_relative_urls_resolve_against_base() {
+    let (origin, mut request_lines) = capture_server().await;
+    let client = Client::builder()
+        .base_url(format!("{}/v2/", origin))
+        .build();
+    assert_eq!(client.base_url().map(|u| u.path()), Some("/v2/"));
+
+    for (path, expected) in [
+        ("users?id=1", "/v2/users?id=1"),
+        ("/health", "/health"),
+        ("../v1/legacy", "/v1/legacy"),
+    ] {
+        client.get(path).send().await.unwrap();
+        assert_eq!(
+            request_lines.recv().await.unwrap(),
+            format!("GET {} HTTP/1.1", expected)
+        );
+    }
+
+    // Absolute URLs ignore the base
+    client.get(format!("{}/abs", origin)).send().await.unwrap();
+    assert_eq!(request_lines.recv().await.unwrap(), "GET /abs HTTP/1.1");
+}
+
+#[tokio::test]
+async fn test_relative_url_without_base_fails() {
+    let result = Client::new().get("/users").send().await;
+    assert!(matches!(result, Err(NetError::InvalidUrl)));
+
+    let client = Client::builder()
+        .base_url("ftp://files.example.com/")
+        .build();
+    assert!(client.base_url().is_none());
+    let result = client.get("users").send().await;
+    assert!(matches!(result, Err(NetError::InvalidUrl)));
+}
@@ -0,0 +1,113 @@
+//! Batches of requests: bounded concurrency, results in input order and
+//! connections released before results are handed out.
+
+mod common;
+
+use chromenet::base::neterror::NetError;
+use chromenet::Client;
+use common::server::read_head;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Read one request head and return its path, or `None` at end of stream.
+async fn read_path(socket: &mut TcpStream) -> Option<String> {
+    let head = read_head(socket).await?;
+    Some(head.split_whitespace().nth(1)?.to_string())
+}
+
+/// Keep-alive server echoing the path of every request followed by
+/// `padding` dots, answering `/slow` after a delay. Returns the address
+/// and the highest number of requests seen in flight at once.
+async fn echo_server(padding: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (open, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let peaked = peak.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (open, peak) = (open.clone(), peaked.clone());
+            tokio::spawn(async move {
+                while let Some(path) = read_path(&mut socket).await {
+                    let now = open.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    let delay = if path == "/slow" { 200 } else { 10 };
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    open.fetch_sub(1, Ordering::SeqCst);
+                    let body = format!("{}{}", path, ".".repeat(padding));
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (addr, peak)
+}
+
+#[tokio::test]
+async fn test_results_in_input_order_with_bounded_concurrency() {
+    let (addr, peak) = echo_server(0).await;
+    let client = Client::new();
+    let requests = (0..12).map(|n| client.get(format!("http://{}/{}", addr, n)));
+
+    let results = client.batch(requests).buffered(3).send().await;
+
+    assert_eq!(results.len(), 12);
+    for (n, result) in results.into_iter().enumerate() {
+        assert_eq!(result.unwrap().text().await.unwrap(), format!("/{}", n));
+    }
+    assert!(peak.load(Ordering::SeqCst) <= 3);
+}
+
+#[tokio::test]
+async fn test_errors_are_per_request() {
+    let (addr, _) = echo_server(0).await;
+    let client = Client::new();
+    let requests = vec![
+        client.get(format!("http://{}/first", addr)),
+        client.get("not a url"),
+        client.get(format!("http://{}/third", addr)),
+    ];
+
+    let results = client.batch(requests).send().await;
+
+    assert_eq!(results[0].as_ref().unwrap().status(), 200);
+    assert!(matches!(results[1], Err(NetError::InvalidUrl)));
+    assert_eq!(results[2].as_ref().unwrap().status(), 200);
+}
+
+#[tokio::test]
+async fn test_held_results_do_not_starve_the_pool() {
+    // Bodies too large to be read along with the headers
+    let (addr, _) = echo_server(1 << 20).await;
+    let client = Client::new();
+    // More requests in flight than connections per host, with the first
+    // answered last: the others finish while it is outstanding, and their
+    // results are held until it completes
+    let requests = std::iter::once(client.get(format!("http://{}/slow", addr)))
+        .chain((1..16).map(|n| client.get(format!("http://{}/{}", addr, n))));
+
+    let results = tokio::time::timeout(
+        Duration::from_secs(10),
+        client.batch(requests).buffered(16).send(),
+    )
+    .await
+    .expect("batch stalled waiting for connections");
+
+    let mut bodies = Vec::new();
+    for result in results {
+        bodies.push(result.unwrap().text().await.unwrap());
+    }
+    assert!(bodies[0].starts_with("/slow."));
+    assert!(bodies[15].starts_with("/15."));
+}
@@ -0,0 +1,215 @@
+//! Requests through a client with an HTTP cache: fresh entries answer
+//! without a round trip, stale ones are revalidated.
+
+mod common;
+
+use chromenet::http::{CacheMode, HttpCache};
+use chromenet::Client;
+use common::server::read_head;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+/// Server for a resource at version `"v1"`, sent with `cache_control` and
+/// answering 304 when the request names the current version.
+struct Origin {
+    addr: SocketAddr,
+    version: Arc<Mutex<String>>,
+    heads: Arc<Mutex<Vec<String>>>,
+}
+
+impl Origin {
+    async fn start(cache_control: &'static str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let version = Arc::new(Mutex::new("\"v1\"".to_string()));
+        let heads = Arc::new(Mutex::new(Vec::new()));
+        let (current, seen) = (version.clone(), heads.clone());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let (current, seen) = (current.clone(), seen.clone());
+                tokio::spawn(async move {
+                    while let Some(head) = read_head(&mut socket).await {
+                        let head = head.to_ascii_lowercase();
+                        let etag = current.lock().unwrap().clone();
+                        let matched = head.contains(&format!("if-none-match: {}", etag));
+                        seen.lock().unwrap().push(head);
+                        let response = if matched {
+                            format!(
+                                "HTTP/1.1 304 Not Modified\r\nETag: {}\r\nCache-Control: {}\r\n\r\n",
+                                etag, cache_control
+                            )
+                        } else {
+                            format!(
+                                "HTTP/1.1 200 OK\r\nETag: {}\r\nCache-Control: {}\r\nContent-Length: {}\r\n\r\n{}",
+                                etag,
+                                cache_control,
+                                etag.len(),
+                                etag
+                            )
+                        };
+                        if socket.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        Self {
+            addr,
+            version,
+            heads,
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("http://{}/resource", self.addr)
+    }
+
+    fn heads(&self) -> Vec<String> {
+        self.heads.lock().unwrap().clone()
+    }
+}
+
+fn cached_client() -> Client {
+    Client::builder().cache(HttpCache::new()).build()
+}
+
+async fn fetch(client: &Client, url: &str, mode: CacheMode) -> (u16, String) {
+    let resp = client.get(url).cache_mode(mode).send().await.unwrap();
+    let status = resp.status().as_u16();
+    (status, resp.text().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_fresh_entry_served_from_cache() {
+    let origin = Origin::start("max-age=60").await;
+    let client = cached_client();
+
+    let first = fetch(&client, &origin.url(), CacheMode::Normal).await;
+    let second = fetch(&client, &origin.url(), CacheMode::Normal).await;
+    assert_eq!(first, (200, "\"v1\"".to_string()));
+    assert_eq!(second, first);
+    assert_eq!(origin.heads().len(), 1);
+    assert_eq!(client.cache().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_stale_entry_revalidated() {
+    let origin = Origin::start("no-cache").await;
+    let client = cached_client();
+
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    let (status, body) = fetch(&client, &origin.url(), CacheMode::Normal).await;
+    // The 304 freshens the entry, which answers the request
+    assert_eq!((status, body.as_str()), (200, "\"v1\""));
+    let heads = origin.heads();
+    assert_eq!(heads.len(), 2);
+    assert!(!heads[0].contains("if-none-match"));
+    assert!(heads[1].contains("if-none-match: \"v1\""));
+}
+
+#[tokio::test]
+async fn test_changed_resource_replaces_entry() {
+    let origin = Origin::start("no-cache").await;
+    let client = cached_client();
+
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    *origin.version.lock().unwrap() = "\"v2\"".to_string();
+    let (status, body) = fetch(&client, &origin.url(), CacheMode::Normal).await;
+    assert_eq!((status, body.as_str()), (200, "\"v2\""));
+
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    assert!(origin.heads()[2].contains("if-none-match: \"v2\""));
+}
+
+#[tokio::test]
+async fn test_cache_modes() {
+    let origin = Origin::start("max-age=60").await;
+    let client = cached_client();
+
+    // Not stored
+    fetch(&client, &origin.url(), CacheMode::ReadOnly).await;
+    fetch(&client, &origin.url(), CacheMode::Disabled).await;
+    assert!(client.cache().unwrap().is_empty());
+    assert_eq!(origin.heads().len(), 2);
+
+    // Goes to the network, but stores
+    fetch(&client, &origin.url(), CacheMode::ForceRefresh).await;
+    assert_eq!(origin.heads().len(), 3);
+    fetch(&client, &origin.url(), CacheMode::ReadOnly).await;
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    assert_eq!(origin.heads().len(), 3);
+
+    fetch(&client, &origin.url(), CacheMode::Disabled).await;
+    fetch(&client, &origin.url(), CacheMode::ForceRefresh).await;
+    assert_eq!(origin.heads().len(), 5);
+    assert!(origin.heads().iter().all(|h| !h.contains("if-none-match")));
+}
+
+#[tokio::test]
+async fn test_cache_mode_changed_while_shared() {
+    let origin = Origin::start("max-age=60").await;
+    let client = cached_client();
+    let get = || async { client.get(origin.url()).send().await.unwrap().text().await };
+
+    client.cache().unwrap().set_mode(CacheMode::Disabled);
+    get().await.unwrap();
+    get().await.unwrap();
+    assert_eq!(origin.heads().len(), 2);
+    assert!(client.cache().unwrap().is_empty());
+
+    // A request's own mode wins over the cache's
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    assert_eq!(origin.heads().len(), 3);
+
+    client.cache().unwrap().set_mode(CacheMode::Normal);
+    get().await.unwrap();
+    assert_eq!(origin.heads().len(), 3);
+}
+
+#[tokio::test]
+async fn test_caller_validators_bypass_cache() {
+    let origin = Origin::start("no-cache").await;
+    let client = cached_client();
+
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    let resp = client
+        .get(origin.url())
+        .header("If-None-Match", "\"v1\"")
+        .send()
+        .await
+        .unwrap();
+    // The caller sees the 304 it asked for
+    assert_eq!(resp.status().as_u16(), 304);
+}
+
+#[tokio::test]
+async fn test_uncached_client_unaffected() {
+    let origin = Origin::start("max-age=60").await;
+    let client = Client::new();
+
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    fetch(&client, &origin.url(), CacheMode::Normal).await;
+    assert_eq!(origin.heads().len(), 2);
+    assert!(client.cache().is_none());
+}
+
+#[tokio::test]
+async fn test_response_too_big_for_cache_passed_through() {
+    let origin = Origin::start("max-age=60").await;
+    // The 4-byte body does not fit in a 3-byte cache
+    let client = Client::builder()
+        .cache(HttpCache::with_limits(10, 3))
+        .build();
+
+    let first = fetch(&client, &origin.url(), CacheMode::Normal).await;
+    let second = fetch(&client, &origin.url(), CacheMode::Normal).await;
+    assert_eq!(first, (200, "\"v1\"".to_string()));
+    assert_eq!(second, first);
+    assert_eq!(origin.heads().len(), 2);
+    assert!(client.cache().unwrap().is_empty());
+}
@@ -0,0 +1,231 @@
+//! Challenge handling: interstitials recognized by detectors, answered by
+//! handlers and the request sent again.
+
+use chromenet::base::neterror::NetError;
+use chromenet::urlrequest::challenge::{
+    Challenge, ChallengeDetector, ChallengeHandler, ChallengeSolution, Challenges,
+};
+use chromenet::Client;
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Server challenging requests without the `clearance=t0k3n` cookie or an
+/// `x-clearance: t0k3n` header with a 503 page, counting requests.
+async fn shielded_server() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/page", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counted = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let counted = counted.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                    let cleared = request.contains("clearance=t0k3n")
+                        || request.contains("x-clearance: t0k3n");
+                    let response = if cleared {
+                        "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_string()
+                    } else {
+                        let body = "<form id=\"challenge-form\" data-token=\"t0k3n\">";
+                        format!(
+                            "HTTP/1.1 503 Service Unavailable\r\nServer: Shield\r\nContent-Length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                    };
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (url, requests)
+}
+
+fn detector() -> ChallengeDetector {
+    ChallengeDetector::new()
+        .status(503)
+        .header_contains("server", "shield")
+        .body_contains("challenge-form")
+}
+
+/// Reads the token from the challenge page and answers with it, or not.
+#[derive(Clone, Copy)]
+enum Solver {
+    Cookie,
+    Header,
+    Wrong,
+    Decline,
+    Fail,
+}
+
+impl ChallengeHandler for Solver {
+    fn solve<'a>(
+        &'a self,
+        challenge: &'a Challenge,
+    ) -> BoxFuture<'a, Result<Option<ChallengeSolution>, NetError>> {
+        Box::pin(async move {
+            let page = String::from_utf8_lossy(&challenge.body);
+            let token = page.split("data-token=\"").nth(1).unwrap_or_default();
+            let token = token.split('"').next().unwrap_or_default();
+            Ok(match self {
+                Solver::Cookie => {
+                    Some(ChallengeSolution::new().cookie(&format!("clearance={token}; Path=/")))
+                }
+                Solver::Header => Some(ChallengeSolution::new().header("X-Clearance", token)),
+                Solver::Wrong => Some(ChallengeSolution::new().cookie("clearance=nope")),
+                Solver::Decline => None,
+                Solver::Fail => return Err(NetError::BlockedByClient),
+            })
+        })
+    }
+}
+
+fn client(solver: Solver) -> Client {
+    Client::builder()
+        .challenges(Challenges::new().with(detector(), solver))
+        .build()
+}
+
+#[tokio::test]
+async fn test_challenge_answered_with_cookie() {
+    let (url, requests) = shielded_server().await;
+    let client = client(Solver::Cookie);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "ok");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // The clearance cookie stays in the jar
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_challenge_answered_with_header() {
+    let (url, _) = shielded_server().await;
+    let response = client(Solver::Header).get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_challenge_attempts_limited() {
+    let (url, requests) = shielded_server().await;
+    let client = Client::builder()
+        .challenges(
+            Challenges::new()
+                .with(detector(), Solver::Wrong)
+                .with_max_attempts(2),
+        )
+        .build();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert!(response.text().await.unwrap().contains("challenge-form"));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_challenge_declined_or_failed() {
+    let (url, requests) = shielded_server().await;
+
+    let response = client(Solver::Decline).get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert!(response.text().await.unwrap().contains("challenge-form"));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let error = client(Solver::Fail).get(&url).send().await.unwrap_err();
+    assert!(matches!(error, NetError::BlockedByClient), "{error:?}");
+}
+
+/// Answers with the token of the request's [`Clearance`] extension.
+struct ExtensionSolver;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Clearance(&'static str);
+
+impl ChallengeHandler for ExtensionSolver {
+    fn solve<'a>(
+        &'a self,
+        challenge: &'a Challenge,
+    ) -> BoxFuture<'a, Result<Option<ChallengeSolution>, NetError>> {
+        Box::pin(async move {
+            Ok(challenge
+                .extensions
+                .get::<Clearance>()
+                .map(|token| ChallengeSolution::new().header("X-Clearance", token.0)))
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_request_extensions_reach_handler_and_response() {
+    let (url, requests) = shielded_server().await;
+    let client = Client::builder()
+        .challenges(Challenges::new().with(detector(), ExtensionSolver))
+        .build();
+
+    let response = client
+        .get(&url)
+        .extension(Clearance("t0k3n"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.extensions().get::<Clearance>(),
+        Some(&Clearance("t0k3n"))
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Without the extension the handler declines
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert!(response.extensions().get::<Clearance>().is_none());
+}
+
+#[tokio::test]
+async fn test_unmatched_response_returned() {
+    let (url, requests) = shielded_server().await;
+    let detector = ChallengeDetector::new()
+        .status(503)
+        .body_contains("captcha");
+    let client = Client::builder()
+        .challenges(Challenges::new().with(detector, Solver::Cookie))
+        .build();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert!(response.text().await.unwrap().contains("challenge-form"));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_body_over_limit_passed_through() {
+    let (url, requests) = shielded_server().await;
+    let client = Client::builder()
+        .challenges(
+            Challenges::new()
+                .with_body_limit(8)
+                .with(detector(), Solver::Cookie),
+        )
+        .build();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert!(response.text().await.unwrap().contains("challenge-form"));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,111 @@
+//! The `chromenet` binary against the local httpbin-like server.
+
+mod common;
+
+use common::httpbin::HttpBin;
+use serde_json::Value;
+use std::process::Output;
+use tokio::process::Command;
+
+async fn chromenet(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chromenet"))
+        .args(args)
+        .output()
+        .await
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[tokio::test]
+async fn test_get_with_headers() {
+    let bin = HttpBin::start().await;
+    let output = chromenet(&["-H", "X-Test: yes", "-A", "cli/1.0", &bin.url("/get?a=1")]).await;
+    let json: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["method"], "GET");
+    assert_eq!(json["args"]["a"], "1");
+    assert_eq!(json["headers"]["x-test"], "yes");
+    assert_eq!(json["headers"]["user-agent"], "cli/1.0");
+}
+
+#[tokio::test]
+async fn test_post_form_data() {
+    let bin = HttpBin::start().await;
+    let output = chromenet(&["-d", "a=1", "-d", "b=2", &bin.url("/anything")]).await;
+    let json: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["method"], "POST");
+    assert_eq!(json["data"], "a=1&b=2");
+    assert_eq!(
+        json["headers"]["content-type"],
+        "application/x-www-form-urlencoded"
+    );
+}
+
+#[tokio::test]
+async fn test_head_prints_status_line() {
+    let bin = HttpBin::start().await;
+    let output = chromenet(&["-I", &bin.url("/status/418")]).await;
+    let head = stdout(&output);
+    assert!(
+        head.starts_with("HTTP/1.1 418 I'm a teapot\r\n"),
+        "{}",
+        head
+    );
+    assert!(head.ends_with("\r\n\r\n"));
+}
+
+#[tokio::test]
+async fn test_har_output() {
+    let bin = HttpBin::start().await;
+    let url = bin.url("/get?q=x");
+    let output = chromenet(&["--har", "-", "-o", "/dev/null", "--http1.1", &url]).await;
+    let har: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let entry = &har["log"]["entries"][0];
+    assert_eq!(har["log"]["version"], "1.2");
+    assert_eq!(entry["request"]["url"], url);
+    assert_eq!(entry["request"]["queryString"][0]["value"], "x");
+    assert_eq!(entry["response"]["status"], 200);
+    assert_eq!(entry["response"]["content"]["mimeType"], "application/json");
+}
+
+#[tokio::test]
+async fn test_gzipped_har_output() {
+    use std::io::Read;
+
+    let bin = HttpBin::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("request.har.gz");
+    let output = chromenet(&[
+        "--har",
+        path.to_str().unwrap(),
+        "-o",
+        "/dev/null",
+        &bin.url("/get"),
+    ])
+    .await;
+    stdout(&output);
+
+    let mut json = String::new();
+    flate2::read::GzDecoder::new(std::fs::File::open(&path).unwrap())
+        .read_to_string(&mut json)
+        .unwrap();
+    let har: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(har["log"]["entries"][0]["response"]["status"], 200);
+}
+
+#[tokio::test]
+async fn test_usage_errors() {
+    let output = chromenet(&["--impersonate", "netscape4", "http://a.test/"]).await;
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("netscape4"));
+
+    let output = chromenet(&["--help"]).await;
+    assert!(stdout(&output).contains("--impersonate"));
+}
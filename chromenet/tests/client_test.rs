@@ -1,6 +1,7 @@
 //! Tests for Client API.
 
 use chromenet::client::Client;
+#[cfg(feature = "emulation-profiles")]
 use chromenet::emulation::profiles::chrome::Chrome;
 use std::time::Duration; // Keep this as it's used later
 
@@ -16,6 +17,7 @@ fn test_client_builder() {
     let _client = Client::builder().build();
 }
 
+#[cfg(feature = "emulation-profiles")]
 #[test]
 fn test_client_with_emulation() {
     let _client = Client::builder().emulation(Chrome::V140).build();
@@ -58,6 +60,7 @@ fn test_request_builder_body() {
         .body(b"test body".to_vec());
 }
 
+#[cfg(feature = "emulation-profiles")]
 #[test]
 fn test_request_builder_emulation_override() {
     let client = Client::builder().emulation(Chrome::V120).build();
@@ -66,6 +69,7 @@ fn test_request_builder_emulation_override() {
     let _req = client.get("https://example.com").emulation(Chrome::V140);
 }
 
+#[cfg(feature = "emulation-profiles")]
 #[test]
 fn test_client_clone() {
     let client = Client::builder().emulation(Chrome::V140).build();
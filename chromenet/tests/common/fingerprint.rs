@@ -0,0 +1,128 @@
+//! Fingerprints of what a client puts on the wire.
+//!
+//! Local servers record the first bytes of a connection: the ClientHello
+//! of a TLS connection and the frames an HTTP/2 connection opens with,
+//! up to the first HEADERS, parsed by the library's [`ClientHello`] and
+//! [`H2Preface`], which compute the fingerprints.
+
+use chromenet::base::neterror::NetError;
+use chromenet::http::H2Preface;
+use chromenet::socket::hooks::{ConnectHooks, ConnectOutcome};
+use chromenet::socket::tls::ClientHello;
+use chromenet::ClientBuilder;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Host the capturing servers are reached under.
+const HOST: &str = "fingerprint.test";
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Resolves every host to loopback and connects to the capturing server.
+struct Loopback(SocketAddr);
+
+impl ConnectHooks for Loopback {
+    fn resolve(&self, _host: &str) -> Option<Result<Vec<IpAddr>, NetError>> {
+        Some(Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]))
+    }
+
+    fn connect(&self, _addr: SocketAddr) -> ConnectOutcome {
+        ConnectOutcome::Redirect(self.0)
+    }
+}
+
+/// The ClientHello a client from `builder` sends.
+pub async fn client_hello(builder: ClientBuilder) -> ClientHello {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = builder
+        .connect_hooks(Loopback(listener.local_addr().unwrap()))
+        .build();
+    let request = tokio::spawn(async move {
+        let _ = client.get(format!("https://{}/", HOST)).send().await;
+    });
+
+    let (mut socket, _) = listener.accept().await.unwrap();
+    let mut record = vec![0; 5];
+    socket.read_exact(&mut record).await.unwrap();
+    let len = u16::from_be_bytes([record[3], record[4]]) as usize;
+    record.resize(5 + len, 0);
+    socket.read_exact(&mut record[5..]).await.unwrap();
+    request.abort();
+
+    ClientHello::parse(&record).expect("a ClientHello")
+}
+
+/// The frames a client from `builder` opens an HTTP/2 connection with.
+pub async fn h2_preface(builder: ClientBuilder) -> H2Preface {
+    let frames = h2_frames(builder, Duration::ZERO).await;
+    H2Preface::parse(&frames).expect("HTTP/2 frames")
+}
+
+/// Type and stream of the frames a client from `builder` opens an HTTP/2
+/// connection with, including those sent within `linger` after the first
+/// HEADERS.
+pub async fn h2_frame_order(builder: ClientBuilder, linger: Duration) -> Vec<(u8, u32)> {
+    let frames = h2_frames(builder, linger).await;
+    let mut rest = &frames[..];
+    let mut order = Vec::new();
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes([0, rest[0], rest[1], rest[2]]) as usize;
+        let stream = u32::from_be_bytes([rest[5], rest[6], rest[7], rest[8]]);
+        order.push((rest[3], stream & 0x7fff_ffff));
+        rest = &rest[9 + len..];
+    }
+    order
+}
+
+/// The raw frames after the connection preface, up to `linger` after the
+/// first HEADERS.
+async fn h2_frames(builder: ClientBuilder, linger: Duration) -> Vec<u8> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = builder
+        .connect_hooks(Loopback(listener.local_addr().unwrap()))
+        .build();
+    let request = tokio::spawn(async move {
+        let _ = client
+            .get(format!("http://{}/", HOST))
+            .http2_prior_knowledge()
+            .send()
+            .await;
+    });
+
+    let (mut socket, _) = listener.accept().await.unwrap();
+    let mut preface = [0; PREFACE.len()];
+    socket.read_exact(&mut preface).await.unwrap();
+    assert_eq!(preface, PREFACE);
+    // Empty server SETTINGS
+    socket
+        .write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0])
+        .await
+        .unwrap();
+
+    let mut frames = Vec::new();
+    let mut headers_seen = false;
+    loop {
+        let mut head = [0; 9];
+        if headers_seen {
+            if linger.is_zero() {
+                break;
+            }
+            match tokio::time::timeout(linger, socket.read_exact(&mut head)).await {
+                Ok(read) => read.unwrap(),
+                Err(_) => break,
+            };
+        } else {
+            socket.read_exact(&mut head).await.unwrap();
+        }
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0; len];
+        socket.read_exact(&mut payload).await.unwrap();
+        frames.extend_from_slice(&head);
+        frames.extend_from_slice(&payload);
+        headers_seen |= head[3] == 0x1;
+    }
+    request.abort();
+    frames
+}
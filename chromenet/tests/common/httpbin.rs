@@ -0,0 +1,418 @@
+//! Local httpbin-like server for offline end-to-end tests.
+//!
+//! Serves HTTP/1.1 and cleartext HTTP/2 (prior knowledge) on a random
+//! loopback port. Endpoints follow httpbin.org:
+//!
+//! - `/get`, `/anything/*`, `/headers`: echo the request as JSON
+//! - `/status/{code}`
+//! - `/redirect/{n}`, `/absolute-redirect/{n}`, `/redirect-to?url=&status_code=`
+//! - `/cookies`, `/cookies/set?name=value`, `/cookies/delete?name`
+//! - `/delay/{seconds}`: fractional seconds allowed
+//! - `/basic-auth/{user}/{passwd}`, `/digest-auth/{qop}/{user}/{passwd}`
+//! - `/gzip`: JSON body with `Content-Encoding: gzip`
+//! - `/bytes/{n}`
+
+use boring::hash::{hash, MessageDigest};
+use bytes::Bytes;
+use http::{header, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Nonce handed out in every digest challenge.
+pub const DIGEST_NONCE: &str = "7b3a9f2c4e1d8a6b";
+const DIGEST_REALM: &str = "me@kennethreitz.com";
+
+/// Running server. Stops accepting when the test's runtime shuts down.
+pub struct HttpBin {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+}
+
+impl HttpBin {
+    /// Bind to a random loopback port and start serving.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        handle(req, addr)
+                    });
+                    let _ = auto::Builder::new(TokioExecutor::new())
+                        .serve_connection(TokioIo::new(socket), service)
+                        .await;
+                });
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    /// Address the server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Absolute URL for `path`, which must start with `/`.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Requests served so far, across all connections.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+type HttpBinResponse = Response<Full<Bytes>>;
+
+async fn handle(req: Request<Incoming>, addr: SocketAddr) -> Result<HttpBinResponse, Infallible> {
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map(|b| b.to_bytes())
+        .unwrap_or_default();
+    let req = Request::from_parts(parts, body);
+
+    let path = req.uri().path().to_string();
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+    let resp = match segments.as_slice() {
+        ["get"] if req.method() == Method::GET => json_response(StatusCode::OK, echo(&req, addr)),
+        ["anything", ..] => json_response(StatusCode::OK, echo(&req, addr)),
+        ["headers"] => json_response(StatusCode::OK, json!({ "headers": headers_json(&req) })),
+        ["status", code] => match code.parse::<u16>().ok().and_then(|c| StatusCode::from_u16(c).ok()) {
+            Some(status) => empty(status),
+            None => empty(StatusCode::BAD_REQUEST),
+        },
+        ["redirect", n] | ["relative-redirect", n] => match n.parse::<u32>() {
+            Ok(n) => redirect(StatusCode::FOUND, &next_redirect("/redirect", n)),
+            Err(_) => empty(StatusCode::BAD_REQUEST),
+        },
+        ["absolute-redirect", n] => match n.parse::<u32>() {
+            Ok(n) => redirect(
+                StatusCode::FOUND,
+                &format!("http://{}{}", addr, next_redirect("/absolute-redirect", n)),
+            ),
+            Err(_) => empty(StatusCode::BAD_REQUEST),
+        },
+        ["redirect-to"] => {
+            let args = query(&req);
+            let status = args
+                .get("status_code")
+                .and_then(|c| c.parse::<u16>().ok())
+                .and_then(|c| StatusCode::from_u16(c).ok())
+                .filter(StatusCode::is_redirection)
+                .unwrap_or(StatusCode::FOUND);
+            match args.get("url") {
+                Some(url) => redirect(status, url),
+                None => empty(StatusCode::BAD_REQUEST),
+            }
+        }
+        ["cookies"] => json_response(StatusCode::OK, json!({ "cookies": cookies(&req) })),
+        ["cookies", "set"] => {
+            let mut resp = redirect(StatusCode::FOUND, "/cookies");
+            for (name, value) in query(&req) {
+                resp.headers_mut().append(
+                    header::SET_COOKIE,
+                    format!("{}={}; Path=/", name, value).parse().unwrap(),
+                );
+            }
+            resp
+        }
+        ["cookies", "delete"] => {
+            let mut resp = redirect(StatusCode::FOUND, "/cookies");
+            for name in query(&req).keys() {
+                resp.headers_mut().append(
+                    header::SET_COOKIE,
+                    format!(
+                        "{}=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=0; Path=/",
+                        name
+                    )
+                    .parse()
+                    .unwrap(),
+                );
+            }
+            resp
+        }
+        ["delay", secs] => match secs.parse::<f64>() {
+            Ok(secs) if (0.0..=10.0).contains(&secs) => {
+                tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+                json_response(StatusCode::OK, echo(&req, addr))
+            }
+            _ => empty(StatusCode::BAD_REQUEST),
+        },
+        ["basic-auth", user, passwd] => basic_auth(&req, user, passwd),
+        ["digest-auth", qop, user, passwd] => digest_auth(&req, qop, user, passwd),
+        ["gzip"] => {
+            let body = json!({ "gzipped": true, "method": req.method().as_str() });
+            let mut resp = Response::new(Full::new(Bytes::from(gzip(
+                body.to_string().as_bytes(),
+            ))));
+            resp.headers_mut()
+                .insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+            resp.headers_mut()
+                .insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+            resp
+        }
+        ["bytes", n] => match n.parse::<usize>() {
+            Ok(n) if n <= 100 * 1024 => {
+                let body: Vec<u8> = (0..n).map(|i| (i % 251) as u8).collect();
+                Response::new(Full::new(Bytes::from(body)))
+            }
+            _ => empty(StatusCode::BAD_REQUEST),
+        },
+        _ => empty(StatusCode::NOT_FOUND),
+    };
+    Ok(resp)
+}
+
+/// Location for the next hop of an `n`-hop redirect chain, ending at `/get`.
+fn next_redirect(prefix: &str, n: u32) -> String {
+    if n <= 1 {
+        "/get".to_string()
+    } else {
+        format!("{}/{}", prefix, n - 1)
+    }
+}
+
+fn empty(status: StatusCode) -> HttpBinResponse {
+    let mut resp = Response::new(Full::new(Bytes::new()));
+    *resp.status_mut() = status;
+    resp
+}
+
+fn redirect(status: StatusCode, location: &str) -> HttpBinResponse {
+    let mut resp = empty(status);
+    resp.headers_mut()
+        .insert(header::LOCATION, location.parse().unwrap());
+    resp
+}
+
+fn json_response(status: StatusCode, value: Value) -> HttpBinResponse {
+    let mut resp = Response::new(Full::new(Bytes::from(value.to_string())));
+    *resp.status_mut() = status;
+    resp.headers_mut()
+        .insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+    resp
+}
+
+fn echo(req: &Request<Bytes>, addr: SocketAddr) -> Value {
+    json!({
+        "method": req.method().as_str(),
+        "url": format!("http://{}{}", addr, req.uri()),
+        "args": query(req),
+        "headers": headers_json(req),
+        "data": String::from_utf8_lossy(req.body()),
+    })
+}
+
+/// Request headers, keyed by lowercase name. Repeated headers are joined
+/// with `, `.
+fn headers_json(req: &Request<Bytes>) -> Map<String, Value> {
+    let mut headers: Map<String, Value> = Map::new();
+    for (name, value) in req.headers() {
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        match headers.get_mut(name.as_str()) {
+            Some(Value::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            _ => {
+                headers.insert(name.as_str().to_string(), Value::String(value));
+            }
+        }
+    }
+    headers
+}
+
+fn query(req: &Request<Bytes>) -> HashMap<String, String> {
+    req.uri()
+        .query()
+        .map(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn cookies(req: &Request<Bytes>) -> HashMap<String, String> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn basic_auth(req: &Request<Bytes>, user: &str, passwd: &str) -> HttpBinResponse {
+    use base64::Engine;
+    let expected = format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, passwd))
+    );
+    let authorized = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .is_some_and(|v| v.as_bytes() == expected.as_bytes());
+    if authorized {
+        json_response(
+            StatusCode::OK,
+            json!({ "authenticated": true, "user": user }),
+        )
+    } else {
+        let mut resp = empty(StatusCode::UNAUTHORIZED);
+        resp.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            "Basic realm=\"Fake Realm\"".parse().unwrap(),
+        );
+        resp
+    }
+}
+
+fn digest_auth(req: &Request<Bytes>, qop: &str, user: &str, passwd: &str) -> HttpBinResponse {
+    let params = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Digest "))
+        .map(digest_params)
+        .unwrap_or_default();
+
+    if digest_response_valid(&params, req.method(), qop, user, passwd) {
+        return json_response(
+            StatusCode::OK,
+            json!({ "authenticated": true, "user": user }),
+        );
+    }
+
+    let mut challenge = format!(
+        "Digest realm=\"{}\", nonce=\"{}\", opaque=\"{}\", algorithm=MD5",
+        DIGEST_REALM,
+        DIGEST_NONCE,
+        md5_hex("opaque")
+    );
+    if qop == "auth" {
+        challenge.push_str(", qop=\"auth\"");
+    }
+    let mut resp = empty(StatusCode::UNAUTHORIZED);
+    resp.headers_mut()
+        .insert(header::WWW_AUTHENTICATE, challenge.parse().unwrap());
+    resp
+}
+
+fn digest_response_valid(
+    params: &HashMap<String, String>,
+    method: &Method,
+    qop: &str,
+    user: &str,
+    passwd: &str,
+) -> bool {
+    let get = |name: &str| params.get(name).map(String::as_str);
+    if get("username") != Some(user) || get("nonce") != Some(DIGEST_NONCE) {
+        return false;
+    }
+    let (Some(uri), Some(response)) = (get("uri"), get("response")) else {
+        return false;
+    };
+
+    let ha1 = md5_hex(&format!("{}:{}:{}", user, DIGEST_REALM, passwd));
+    let ha2 = md5_hex(&format!("{}:{}", method, uri));
+    let expected = if qop == "auth" {
+        let (Some(nc), Some(cnonce), Some("auth")) = (get("nc"), get("cnonce"), get("qop")) else {
+            return false;
+        };
+        md5_hex(&format!("{}:{}:{}:{}:auth:{}", ha1, DIGEST_NONCE, nc, cnonce, ha2))
+    } else {
+        md5_hex(&format!("{}:{}:{}", ha1, DIGEST_NONCE, ha2))
+    };
+    response == expected
+}
+
+/// `name=value` and `name="value"` pairs of a Digest authorization header.
+fn digest_params(header: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = header.trim();
+    while let Some((name, after)) = rest.split_once('=') {
+        let name = name.trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, remaining)) => (value, remaining),
+                None => (quoted, ""),
+            },
+            None => after.split_once(',').unwrap_or((after, "")),
+        };
+        params.insert(name, value.trim().to_string());
+        rest = remaining.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+    }
+    params
+}
+
+fn md5_hex(input: &str) -> String {
+    hash(MessageDigest::md5(), input.as_bytes())
+        .unwrap()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Gzip `data` using stored (uncompressed) deflate blocks, which every
+/// decoder accepts and needs no compression library.
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    // Header: magic, CM=deflate, no flags, no mtime, no extra flags, OS=unknown
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    let mut chunks = data.chunks(u16::MAX as usize).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
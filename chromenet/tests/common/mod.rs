@@ -0,0 +1,8 @@
+//! Shared helpers for integration tests.
+
+// Each test binary compiles this module and uses only part of it
+#![allow(dead_code)]
+
+pub mod fingerprint;
+pub mod httpbin;
+pub mod server;
@@ -0,0 +1,79 @@
+//! Raw-socket helpers for hand-written test servers.
+
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Read one request head, or `None` at end of stream.
+///
+/// Reads a byte at a time, so nothing after the head is consumed.
+pub async fn read_head<S: AsyncRead + Unpin>(socket: &mut S) -> Option<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if socket.read(&mut byte).await.ok()? == 0 {
+            return None;
+        }
+        head.push(byte[0]);
+    }
+    Some(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Read one request head followed by its `Content-Length` body, or `None`
+/// at end of stream.
+pub async fn read_request<S: AsyncRead + Unpin>(socket: &mut S) -> Option<String> {
+    let mut request = read_head(socket).await?;
+    let length = request
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .map_or(0, |(_, len)| len.trim().parse().unwrap());
+    let mut body = vec![0u8; length];
+    socket.read_exact(&mut body).await.ok()?;
+    request.push_str(&String::from_utf8_lossy(&body));
+    Some(request)
+}
+
+/// Answer every request on `listener`, read with [`read_request`], with
+/// the bytes `respond` returns for it.
+///
+/// Connections stay open for further requests unless the response says
+/// `Connection: close`.
+pub fn serve<F>(listener: TcpListener, respond: F)
+where
+    F: Fn(&str) -> Vec<u8> + Send + Sync + 'static,
+{
+    let respond = Arc::new(respond);
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                while let Some(request) = read_request(&mut socket).await {
+                    let response = respond(&request);
+                    if socket.write_all(&response).await.is_err() {
+                        break;
+                    }
+                    if closes(&response) {
+                        let _ = socket.shutdown().await;
+                        break;
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Whether the head of `response` has `Connection: close`.
+fn closes(response: &[u8]) -> bool {
+    let end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .unwrap_or(response.len());
+    String::from_utf8_lossy(&response[..end])
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .any(|(name, value)| {
+            name.eq_ignore_ascii_case("connection") && value.trim().eq_ignore_ascii_case("close")
+        })
+}
@@ -0,0 +1,113 @@
+//! Conditional requests from the validators of an earlier response.
+
+mod common;
+
+use chromenet::http::conditional::{Conditional, EntityTag, Validators};
+use chromenet::Client;
+use common::server::read_head;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+const LAST_MODIFIED: &str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+/// Server for a resource at version `etag`, answering 304 when the request
+/// names that version. Returns the address, a handle to change the version
+/// and the request heads received.
+async fn versioned_server() -> (SocketAddr, Arc<Mutex<String>>, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let etag = Arc::new(Mutex::new("\"v1\"".to_string()));
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let (current, seen) = (etag.clone(), heads.clone());
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (current, seen) = (current.clone(), seen.clone());
+            tokio::spawn(async move {
+                while let Some(head) = read_head(&mut socket).await {
+                    let head = head.to_ascii_lowercase();
+                    let etag = current.lock().unwrap().clone();
+                    let matched = head.contains(&format!("if-none-match: {}", etag));
+                    seen.lock().unwrap().push(head);
+                    let response = if matched {
+                        format!("HTTP/1.1 304 Not Modified\r\nETag: {}\r\n\r\n", etag)
+                    } else {
+                        format!(
+                            "HTTP/1.1 200 OK\r\nETag: {}\r\nLast-Modified: {}\r\nContent-Length: {}\r\n\r\n{}",
+                            etag,
+                            LAST_MODIFIED,
+                            etag.len(),
+                            etag
+                        )
+                    };
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (addr, etag, heads)
+}
+
+#[tokio::test]
+async fn test_if_none_match_from_response() {
+    let (addr, _, heads) = versioned_server().await;
+    let client = Client::new();
+    let url = format!("http://{}/feed", addr);
+
+    let first = client.get(&url).send().await.unwrap();
+    assert_eq!(first.etag(), Some(EntityTag::strong("v1")));
+    let etag = first.etag();
+    first.bytes().await.unwrap();
+
+    let second = client.get(&url).if_none_match(etag).send().await.unwrap();
+    assert_eq!(second.status(), 304);
+    assert!(heads.lock().unwrap()[1].contains("if-none-match: \"v1\""));
+}
+
+#[tokio::test]
+async fn test_fetch_if_modified() {
+    let (addr, version, heads) = versioned_server().await;
+    let client = Client::new();
+    let url = format!("http://{}/feed", addr);
+
+    let first = client.get(&url).send().await.unwrap();
+    let validators = first.validators();
+    first.bytes().await.unwrap();
+    assert!(validators.last_modified.is_some());
+
+    let unchanged = client.fetch_if_modified(&url, &validators).await.unwrap();
+    assert!(matches!(unchanged, Conditional::NotModified(_)));
+    assert!(heads.lock().unwrap()[1].contains(&format!(
+        "if-modified-since: {}",
+        LAST_MODIFIED.to_ascii_lowercase()
+    )));
+    unchanged.into_response().bytes().await.unwrap();
+
+    *version.lock().unwrap() = "\"v2\"".to_string();
+    match client.fetch_if_modified(&url, &validators).await.unwrap() {
+        Conditional::Modified(resp) => {
+            assert_eq!(resp.etag(), Some(EntityTag::strong("v2")));
+            assert_eq!(resp.text().await.unwrap(), "\"v2\"");
+        }
+        Conditional::NotModified(_) => panic!("resource changed"),
+    }
+}
+
+#[tokio::test]
+async fn test_without_validators_request_is_unconditional() {
+    let (addr, _, heads) = versioned_server().await;
+    let client = Client::new();
+    let url = format!("http://{}/feed", addr);
+
+    let result = client
+        .fetch_if_modified(&url, &Validators::default())
+        .await
+        .unwrap();
+    assert!(result.is_modified());
+    let head = heads.lock().unwrap()[0].clone();
+    assert!(!head.contains("if-none-match") && !head.contains("if-modified-since"));
+}
@@ -0,0 +1,230 @@
+//! Connect hook tests: faked DNS, TCP and TLS outcomes without a network.
+
+use chromenet::base::neterror::NetError;
+use chromenet::socket::connectjob::ConnectTimeouts;
+use chromenet::socket::hooks::{ConnectHooks, ConnectOutcome};
+use chromenet::Client;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Serve requests with an empty 200 response.
+async fn local_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        }
+    });
+
+    addr
+}
+
+/// Scripted hooks that record the connect attempts they see.
+#[derive(Default)]
+struct Script {
+    answer: Option<Result<Vec<IpAddr>, NetError>>,
+    server: Option<SocketAddr>,
+    fail_ipv6: bool,
+    fail_all: Option<NetError>,
+    tls_error: Option<NetError>,
+    attempts: Arc<Mutex<Vec<SocketAddr>>>,
+}
+
+impl ConnectHooks for Script {
+    fn resolve(&self, _host: &str) -> Option<Result<Vec<IpAddr>, NetError>> {
+        self.answer.clone()
+    }
+
+    fn connect(&self, addr: SocketAddr) -> ConnectOutcome {
+        self.attempts.lock().unwrap().push(addr);
+        if let Some(error) = &self.fail_all {
+            return ConnectOutcome::fail(error.clone());
+        }
+        if self.fail_ipv6 && addr.is_ipv6() {
+            return ConnectOutcome::Fail {
+                error: NetError::AddressUnreachable,
+                delay: Duration::from_millis(10),
+            };
+        }
+        match self.server {
+            Some(server) => ConnectOutcome::Redirect(server),
+            None => ConnectOutcome::Proceed,
+        }
+    }
+
+    fn tls_handshake(&self, _host: &str) -> Option<NetError> {
+        self.tls_error.clone()
+    }
+}
+
+#[tokio::test]
+async fn test_nxdomain() {
+    let client = Client::builder()
+        .connect_hooks(Script {
+            answer: Some(Err(NetError::NameNotResolved)),
+            ..Default::default()
+        })
+        .build();
+
+    let result = client.get("http://missing.test/").send().await;
+    assert!(matches!(result, Err(NetError::NameNotResolved)));
+}
+
+#[tokio::test]
+async fn test_fake_dns_answer_reaches_server() {
+    let server = local_server().await;
+    let client = Client::builder()
+        .connect_hooks(Script {
+            answer: Some(Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)])),
+            ..Default::default()
+        })
+        .build();
+
+    let url = format!("http://api.test:{}/", server.port());
+    let response = client.get(url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_unreachable_ipv6_falls_back_to_ipv4() {
+    let server = local_server().await;
+    let attempts = Arc::new(Mutex::new(Vec::new()));
+    let client = Client::builder()
+        .connect_hooks(Script {
+            answer: Some(Ok(vec![
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            ])),
+            server: Some(server),
+            fail_ipv6: true,
+            attempts: attempts.clone(),
+            ..Default::default()
+        })
+        .build();
+
+    let response = client.get("http://dual.test/").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let attempts = attempts.lock().unwrap();
+    assert_eq!(attempts.len(), 2);
+    assert!(attempts[0].is_ipv6());
+    assert!(attempts[1].is_ipv4());
+}
+
+#[tokio::test]
+async fn test_connect_timeout() {
+    let client = Client::builder()
+        .connect_hooks(Script {
+            answer: Some(Ok(vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))])),
+            fail_all: Some(NetError::ConnectionTimedOut),
+            ..Default::default()
+        })
+        .build();
+
+    let error = client.get("http://slow.test/").send().await.err().unwrap();
+    match &error {
+        NetError::ConnectionAttemptsFailed { attempts, .. } => {
+            assert!(matches!(attempts[0].error, NetError::ConnectionTimedOut))
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+    assert_eq!(error.as_i32(), NetError::ConnectionTimedOut.as_i32());
+}
+
+/// Hooks answering with two addresses, of which `stalled` ones never answer
+/// and the others reach `server`.
+struct Stalled {
+    stalled: Vec<IpAddr>,
+    server: SocketAddr,
+    attempts: Arc<Mutex<Vec<SocketAddr>>>,
+}
+
+impl ConnectHooks for Stalled {
+    fn resolve(&self, _host: &str) -> Option<Result<Vec<IpAddr>, NetError>> {
+        Some(Ok(vec![
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+        ]))
+    }
+
+    fn connect(&self, addr: SocketAddr) -> ConnectOutcome {
+        self.attempts.lock().unwrap().push(addr);
+        if self.stalled.contains(&addr.ip()) {
+            ConnectOutcome::Fail {
+                error: NetError::ConnectionRefused,
+                delay: Duration::from_secs(600),
+            }
+        } else {
+            ConnectOutcome::Redirect(self.server)
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_attempt_timeout_moves_to_next_address() {
+    let server = local_server().await;
+    let attempts = Arc::new(Mutex::new(Vec::new()));
+    let client = Client::builder()
+        .connect_hooks(Stalled {
+            stalled: vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))],
+            server,
+            attempts: attempts.clone(),
+        })
+        .connect_timeouts(ConnectTimeouts::new().with_attempt_timeout(Duration::from_millis(100)))
+        .build();
+
+    let response = client.get("http://stalled.test/").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(attempts.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_total_connect_timeout() {
+    let server = local_server().await;
+    let client = Client::builder()
+        .connect_hooks(Stalled {
+            stalled: vec![
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+            ],
+            server,
+            attempts: Arc::default(),
+        })
+        .connect_timeouts(
+            ConnectTimeouts::new()
+                .with_attempt_timeout(Duration::from_secs(60))
+                .with_total_timeout(Duration::from_millis(200)),
+        )
+        .build();
+
+    let start = std::time::Instant::now();
+    let result = client.get("http://stalled.test/").send().await;
+    assert!(matches!(result, Err(NetError::ConnectionTimedOut)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_tls_handshake_failure() {
+    let server = local_server().await;
+    let client = Client::builder()
+        .connect_hooks(Script {
+            answer: Some(Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)])),
+            server: Some(server),
+            tls_error: Some(NetError::SslPinnedKeyNotInCertChain),
+            ..Default::default()
+        })
+        .build();
+
+    let result = client.get("https://secure.test/").send().await;
+    assert!(matches!(result, Err(NetError::SslPinnedKeyNotInCertChain)));
+}
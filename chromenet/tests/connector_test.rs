@@ -0,0 +1,81 @@
+//! hyper-util's client over chromenet connections.
+#![cfg(feature = "hyper-connector")]
+
+mod common;
+
+use bytes::Bytes;
+use chromenet::socket::connector::Connector;
+use chromenet::socket::pool::ClientSocketPool;
+use common::server::read_head;
+use http_body_util::{BodyExt, Empty};
+use hyper_util::client::legacy::Client as HyperClient;
+use hyper_util::rt::TokioExecutor;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+/// Keep-alive server answering every request with "hello".
+async fn server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                while read_head(&mut socket).await.is_some() {
+                    socket
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+                        .await
+                        .unwrap();
+                }
+            });
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_hyper_client_over_pool() {
+    let url = server().await;
+    let pool = Arc::new(ClientSocketPool::default());
+    let hyper = HyperClient::builder(TokioExecutor::new())
+        .build::<_, Empty<Bytes>>(Connector::from_pool(pool.clone()));
+
+    for _ in 0..2 {
+        let response = hyper.get(url.parse().unwrap()).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "hello");
+    }
+    // hyper reuses its connection, which holds one slot of the pool
+    assert_eq!(pool.total_active_count(), 1);
+
+    drop(hyper);
+    for _ in 0..50 {
+        if pool.total_active_count() == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(pool.total_active_count(), 0);
+    assert_eq!(pool.idle_socket_count(), 0);
+}
+
+#[tokio::test]
+async fn test_client_connector() {
+    let url = server().await;
+    let client = chromenet::Client::new();
+    let hyper =
+        HyperClient::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(client.connector());
+
+    let response = hyper.get(url.parse().unwrap()).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Connection failures surface as hyper connect errors
+    let err = hyper
+        .get("http://127.0.0.1:1/".parse().unwrap())
+        .await
+        .unwrap_err();
+    assert!(err.is_connect());
+}
@@ -0,0 +1,55 @@
+//! Connect-to overrides: connecting elsewhere while the request keeps its URL.
+
+use chromenet::socket::connectto::ConnectTo;
+use chromenet::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn test_connect_to_keeps_host_and_cookies() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, mut heads) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                while let Ok(n @ 1..) = socket.read(&mut buf).await {
+                    let _ = tx.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+                    let response =
+                        b"HTTP/1.1 200 OK\r\nSet-Cookie: id=1\r\nContent-Length: 0\r\n\r\n";
+                    if socket.write_all(response).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    let client = Client::new();
+    let rule = ConnectTo::parse(&format!("app.test:80:127.0.0.1:{}", port)).unwrap();
+    for _ in 0..2 {
+        let response = client
+            .get("http://app.test/status")
+            .connect_to(rule.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.url().unwrap().as_str(), "http://app.test/status");
+    }
+
+    let first = heads.recv().await.unwrap();
+    assert!(first.starts_with("GET /status HTTP/1.1\r\n"));
+    assert!(first
+        .to_ascii_lowercase()
+        .contains("\r\nhost: app.test\r\n"));
+    let second = heads.recv().await.unwrap();
+    assert!(
+        second.contains("id=1"),
+        "cookie of app.test not sent: {second}"
+    );
+}
@@ -0,0 +1,145 @@
+//! Content-Digest and Repr-Digest request headers against a local
+//! HTTP/1.1 server.
+
+use bytes::Bytes;
+use chromenet::http::{ContentDigest, DigestAlgorithm, RewindableBody};
+use chromenet::Client;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+// RFC 9530 appendix B
+const BODY: &[u8] = b"{\"hello\": \"world\"}";
+const SHA256: &str = "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:";
+const SHA512: &str = "sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:";
+
+/// Server answering with the request's digest headers, one per line with
+/// lowercase names, after reading its Content-Length or chunked body.
+async fn digest_echo_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/upload", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(socket);
+                let mut line = String::new();
+                let mut digests = String::new();
+                let mut content_length = 0;
+                let mut chunked = false;
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    let header = line.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    let lower = header.to_ascii_lowercase();
+                    if let Some((name, value)) = header.split_once(':') {
+                        let name = name.to_ascii_lowercase();
+                        if name == "content-digest" || name == "repr-digest" {
+                            digests.push_str(&format!("{name}: {}\n", value.trim()));
+                        }
+                    }
+                    if let Some(value) = lower.strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                    if lower == "transfer-encoding: chunked" {
+                        chunked = true;
+                    }
+                }
+                if chunked {
+                    loop {
+                        line.clear();
+                        reader.read_line(&mut line).await.unwrap();
+                        let size = usize::from_str_radix(line.trim(), 16).unwrap();
+                        let mut chunk = vec![0u8; size + 2];
+                        reader.read_exact(&mut chunk).await.unwrap();
+                        if size == 0 {
+                            break;
+                        }
+                    }
+                } else {
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).await.unwrap();
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    digests.len(),
+                    digests
+                );
+                let _ = reader.get_mut().write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    url
+}
+
+fn streaming_body() -> RewindableBody {
+    RewindableBody::from_factory(|| {
+        futures::stream::iter(
+            BODY.chunks(4)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect::<Vec<_>>(),
+        )
+    })
+}
+
+#[tokio::test]
+async fn test_request_content_digest() {
+    let url = digest_echo_server().await;
+    let response = Client::new()
+        .post(&url)
+        .body(BODY)
+        .content_digest(ContentDigest::new(DigestAlgorithm::Sha256))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.text().await.unwrap(),
+        format!("content-digest: {SHA256}\n")
+    );
+}
+
+#[tokio::test]
+async fn test_client_digest_over_streaming_body() {
+    let url = digest_echo_server().await;
+    let client = Client::builder()
+        .content_digest(
+            ContentDigest::repr(DigestAlgorithm::Sha256).with_algorithm(DigestAlgorithm::Sha512),
+        )
+        .build();
+
+    let response = client
+        .put(&url)
+        .body(streaming_body())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.text().await.unwrap(),
+        format!("repr-digest: {SHA256}, {SHA512}\n")
+    );
+
+    // Requests without a body get no digest
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "");
+}
+
+#[tokio::test]
+async fn test_explicit_digest_header_kept() {
+    let url = digest_echo_server().await;
+    let response = Client::builder()
+        .content_digest(ContentDigest::new(DigestAlgorithm::Sha256))
+        .build()
+        .post(&url)
+        .header("Content-Digest", "sha-256=:c2lnbmVk:")
+        .body(BODY)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.text().await.unwrap(),
+        "content-digest: sha-256=:c2lnbmVk:\n"
+    );
+}
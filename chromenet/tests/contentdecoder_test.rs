@@ -0,0 +1,71 @@
+//! Content decoders: response bodies decoded with built-in and registered
+//! codings.
+
+mod common;
+
+use chromenet::base::neterror::NetError;
+use chromenet::http::{ContentDecoder, ContentDecoders};
+use chromenet::Client;
+use common::server::serve;
+use std::io::{self, Read, Write};
+use tokio::net::TcpListener;
+
+/// A coding that XORs every byte with 0x5a.
+struct Xor;
+
+impl ContentDecoder for Xor {
+    fn decoder<'a>(&self, body: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+        let decoded: Vec<u8> = body.iter().map(|b| b ^ 0x5a).collect();
+        Ok(Box::new(io::Cursor::new(decoded)))
+    }
+}
+
+/// Serve `body` with `Content-Encoding: content_encoding` to every request.
+async fn server(content_encoding: &'static str, body: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    serve(listener, move |_| {
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            content_encoding,
+            body.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(&body);
+        response
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_registered_coding_decoded_after_gzip() {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"hello, decoders").unwrap();
+    let body: Vec<u8> = encoder.finish().unwrap().iter().map(|b| b ^ 0x5a).collect();
+    let url = server("gzip, x-xor", body).await;
+
+    let client = Client::builder()
+        .content_decoders(ContentDecoders::default().with("x-xor", Xor))
+        .build();
+    let response = client.get(&url).send().await.unwrap();
+    assert!(response.content_encodings().is_empty());
+    assert_eq!(response.content_length(), Some(15));
+    assert_eq!(response.text().await.unwrap(), "hello, decoders");
+
+    // Without decoders the body is returned as received
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(response.content_encodings(), ["gzip", "x-xor"]);
+}
+
+#[tokio::test]
+async fn test_unknown_coding_fails() {
+    let url = server("lz4", b"raw".to_vec()).await;
+    let client = Client::builder()
+        .content_decoders(ContentDecoders::default())
+        .build();
+    let error = client.get(&url).send().await.unwrap_err();
+    assert!(
+        matches!(error, NetError::ContentDecodingFailed),
+        "{error:?}"
+    );
+}
@@ -71,3 +71,177 @@ fn test_secure_flag() {
     let cookies_http = store.get_cookies_for_url(&http_url);
     assert_eq!(cookies_http.len(), 0);
 }
+
+#[test]
+fn test_domain_matching_uses_canonical_hosts() {
+    let store = CookieMonster::new();
+    let unicode = Url::parse("https://www.Bücher.example./").unwrap();
+    store.parse_and_save_cookie(&unicode, "idn=val; Domain=BÜCHER.example");
+    store.parse_and_save_cookie(&unicode, "host=val");
+
+    let punycode = Url::parse("https://www.xn--bcher-kva.example/").unwrap();
+    let mut names: Vec<_> = store
+        .get_cookies_for_url(&punycode)
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["host", "idn"]);
+
+    let sibling = Url::parse("https://shop.xn--bcher-kva.example/").unwrap();
+    let cookies = store.get_cookies_for_url(&sibling);
+    assert_eq!(cookies.len(), 1);
+    assert_eq!(cookies[0].domain, "xn--bcher-kva.example");
+}
+
+fn values(store: &CookieMonster, url: &Url) -> Vec<String> {
+    store
+        .get_cookies_for_url(url)
+        .into_iter()
+        .map(|c| format!("{}={}", c.name, c.value))
+        .collect()
+}
+
+#[test]
+fn test_insecure_origin_cannot_set_secure_cookie() {
+    let store = CookieMonster::new();
+    let http_url = Url::parse("http://example.com/").unwrap();
+    let https_url = Url::parse("https://example.com/").unwrap();
+
+    store.parse_and_save_cookie(&http_url, "sec=1; Secure");
+    assert_eq!(store.total_cookie_count(), 0);
+
+    let ws_url = Url::parse("wss://example.com/").unwrap();
+    store.parse_and_save_cookie(&ws_url, "sec=2; Secure");
+    assert_eq!(values(&store, &https_url), ["sec=2"]);
+}
+
+#[test]
+fn test_insecure_origin_cannot_shadow_secure_cookie() {
+    let store = CookieMonster::new();
+    let https_url = Url::parse("https://www.example.com/").unwrap();
+    let http_url = Url::parse("http://www.example.com/").unwrap();
+    store.parse_and_save_cookie(&https_url, "A=secure; Secure; Path=/");
+    store.parse_and_save_cookie(
+        &https_url,
+        "B=secure; Secure; Path=/foo; Domain=example.com",
+    );
+
+    // Same name, domain and path: an overwrite
+    store.parse_and_save_cookie(&http_url, "A=plain; Path=/");
+    // Subpath of the Secure cookie's path: shadows it for /foo/bar
+    store.parse_and_save_cookie(&http_url, "B=plain; Path=/foo/bar");
+    // Host cookie under the Secure cookie's domain
+    store.parse_and_save_cookie(&http_url, "B=plain; Path=/foo");
+    // Parent domain of the Secure host cookie
+    store.parse_and_save_cookie(&http_url, "A=plain; Domain=example.com");
+    assert_eq!(store.total_cookie_count(), 2);
+
+    // Not shadowing: other name, or a path outside the Secure cookie's
+    store.parse_and_save_cookie(&http_url, "C=plain");
+    store.parse_and_save_cookie(&http_url, "B=plain; Path=/");
+    store.parse_and_save_cookie(&http_url, "B=plain; Path=/foobar");
+    assert_eq!(store.total_cookie_count(), 5);
+
+    // A secure origin may still overwrite
+    store.parse_and_save_cookie(&https_url, "A=updated; Path=/");
+    let all = values(&store, &Url::parse("https://www.example.com/foo").unwrap());
+    assert!(all.contains(&"A=updated".to_string()));
+    assert!(all.contains(&"B=secure".to_string()));
+}
+
+#[test]
+fn test_secure_cookie_protection_ignores_ports() {
+    let store = CookieMonster::new();
+    store.parse_and_save_cookie(
+        &Url::parse("https://example.com:8443/").unwrap(),
+        "sid=secure; Secure",
+    );
+    store.parse_and_save_cookie(
+        &Url::parse("http://example.com:8080/").unwrap(),
+        "sid=plain",
+    );
+
+    // Cookies are not scoped to ports, so every port sees the same jar
+    for url in ["https://example.com/", "https://example.com:8443/"] {
+        assert_eq!(values(&store, &Url::parse(url).unwrap()), ["sid=secure"]);
+    }
+    assert!(values(&store, &Url::parse("http://example.com:8080/").unwrap()).is_empty());
+}
+
+#[test]
+fn test_lax_secure_mode_allows_overwrite() {
+    let store = CookieMonster::new().with_strict_secure(false);
+    assert!(!store.is_strict_secure());
+    let https_url = Url::parse("https://example.com/").unwrap();
+    let http_url = Url::parse("http://example.com/").unwrap();
+
+    store.parse_and_save_cookie(&https_url, "sid=secure; Secure");
+    store.parse_and_save_cookie(&http_url, "sid=plain");
+    assert_eq!(values(&store, &https_url), ["sid=plain"]);
+
+    store.parse_and_save_cookie(&http_url, "other=1; Secure");
+    assert_eq!(store.total_cookie_count(), 2);
+}
+
+#[test]
+fn test_domain_cookie_on_multi_label_suffix() {
+    let store = CookieMonster::new();
+    store.parse_and_save_cookie(
+        &Url::parse("https://a.example.co.uk/").unwrap(),
+        "sid=1; Domain=example.co.uk",
+    );
+    store.parse_and_save_cookie(
+        &Url::parse("https://a.example.co.uk/").unwrap(),
+        "uk=1; Domain=co.uk",
+    );
+
+    let url = Url::parse("https://b.c.example.co.uk/").unwrap();
+    assert_eq!(values(&store, &url), ["sid=1"]);
+    let other = Url::parse("https://other.co.uk/").unwrap();
+    assert!(values(&store, &other).is_empty());
+}
+
+#[test]
+fn test_exclusion_reasons_reported() {
+    use chromenet::cookies::inclusionstatus::ExclusionReason;
+
+    let store = CookieMonster::new();
+    let https_url = Url::parse("https://www.example.com/").unwrap();
+    let http_url = Url::parse("http://www.example.com/").unwrap();
+    let reasons = |url: &Url, line: &str| {
+        store
+            .try_parse_and_save_cookie(url, line)
+            .unwrap_err()
+            .exclusion_reasons()
+    };
+
+    assert!(store
+        .try_parse_and_save_cookie(&https_url, "sid=1; Secure")
+        .is_ok());
+    assert_eq!(
+        reasons(&https_url, "no-equals-sign"),
+        [ExclusionReason::FailureToStore]
+    );
+    assert_eq!(
+        reasons(&https_url, "a=1; Domain=com"),
+        [ExclusionReason::InvalidDomain]
+    );
+    assert_eq!(
+        reasons(&https_url, "a=1; Domain=other.com"),
+        [ExclusionReason::InvalidDomain]
+    );
+    assert_eq!(
+        reasons(&http_url, "sid=2"),
+        [ExclusionReason::OverwriteSecure]
+    );
+    // Every reason that applies is reported
+    assert_eq!(
+        reasons(&http_url, "sid=3; Secure"),
+        [
+            ExclusionReason::SecureOnly,
+            ExclusionReason::OverwriteSecure
+        ]
+    );
+    assert_eq!(store.total_cookie_count(), 1);
+}
@@ -0,0 +1,132 @@
+//! Transfer deadlines and cancellation: streams are actively aborted.
+
+use bytes::Bytes;
+use chromenet::base::neterror::NetError;
+use chromenet::http::H2cMode;
+use chromenet::Client;
+use http::Response;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// HTTP/2 server sending the first part of a body and never finishing it.
+/// Reports how the client reset the stream.
+async fn stalled_h2_server() -> (String, oneshot::Receiver<http2::Reason>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let (tx, rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(socket).await.unwrap();
+        let mut tx = Some(tx);
+        while let Some(Ok((_req, mut respond))) = conn.accept().await {
+            let tx = tx.take();
+            tokio::spawn(async move {
+                let response = Response::builder().status(200).body(()).unwrap();
+                let mut send = respond.send_response(response, false).unwrap();
+                send.send_data(Bytes::from_static(b"partial"), false)
+                    .unwrap();
+                let reason = std::future::poll_fn(|cx| send.poll_reset(cx)).await;
+                if let (Some(tx), Ok(reason)) = (tx, reason) {
+                    let _ = tx.send(reason);
+                }
+            });
+        }
+    });
+
+    (url, rx)
+}
+
+/// HTTP/1.1 server sending the first part of a body and never finishing
+/// it. Reports when the client closed the connection.
+async fn stalled_h1_server() -> (String, oneshot::Receiver<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let (tx, rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = socket.read(&mut buf).await;
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial")
+            .await
+            .unwrap();
+        while socket.read(&mut buf).await.unwrap_or(0) > 0 {}
+        let _ = tx.send(());
+    });
+
+    (url, rx)
+}
+
+#[tokio::test]
+async fn test_body_deadline_resets_h2_stream() {
+    let (url, reset) = stalled_h2_server().await;
+    let client = Client::builder()
+        .h2c(H2cMode::PriorKnowledge)
+        .timeout(Duration::from_millis(300))
+        .build();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert!(response.deadline().is_some());
+    assert!(matches!(response.text().await, Err(NetError::TimedOut)));
+
+    let reason = tokio::time::timeout(Duration::from_secs(5), reset)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reason, http2::Reason::CANCEL);
+}
+
+#[tokio::test]
+async fn test_cancel_resets_h2_stream() {
+    let (url, reset) = stalled_h2_server().await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+
+    let mut response = client.get(&url).send().await.unwrap();
+    response.take_body().unwrap().cancel();
+
+    let reason = tokio::time::timeout(Duration::from_secs(5), reset)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reason, http2::Reason::CANCEL);
+}
+
+#[tokio::test]
+async fn test_body_deadline_closes_h1_connection() {
+    let (url, closed) = stalled_h1_server().await;
+    let client = Client::builder()
+        .timeout(Duration::from_millis(300))
+        .build();
+
+    let response = client.get(&url).send().await.unwrap();
+    let mut out = Vec::new();
+    let result = response.copy_to(&mut out).await;
+    assert!(matches!(result, Err(NetError::TimedOut)));
+    assert_eq!(out, b"partial");
+
+    tokio::time::timeout(Duration::from_secs(5), closed)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_request_timeout_before_headers() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+
+    let result = Client::new()
+        .get(&url)
+        .timeout(Duration::from_millis(200))
+        .send()
+        .await;
+    assert!(matches!(result, Err(NetError::TimedOut)));
+}
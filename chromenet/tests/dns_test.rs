@@ -4,13 +4,21 @@
 //! - `Name` struct
 //! - `DnsResolverWithOverrides` using a MockResolver
 //! - `GaiResolver` (Basic System Resolver)
+//! - `HostCache` in front of a client's resolver
+//! - `PostResolve` hooks on the client and on requests
 
-use chromenet::dns::{Addrs, DnsResolverWithOverrides, GaiResolver, Name, Resolve, Resolving};
+use chromenet::base::neterror::NetError;
+use chromenet::dns::{
+    Addrs, DnsResolverWithOverrides, GaiResolver, HostCache, Name, PostResolve, Resolve, Resolving,
+};
+use chromenet::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 struct MockResolver {
     response: Vec<SocketAddr>,
@@ -81,3 +89,84 @@ async fn test_gai_resolver_localhost() {
         println!("GaiResolver failed for localhost - possibly no network access");
     }
 }
+
+/// Local server answering every request with `ok`, returning its port.
+async fn ok_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    )
+                    .await;
+            });
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn test_client_lookups_hit_host_cache() {
+    let port = ok_server().await;
+    let cache = HostCache::new(Arc::new(MockResolver {
+        response: vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)],
+    }));
+    let client = Client::builder().dns_resolver(cache.clone()).build();
+    let url = format!("http://cached.test:{}/", port);
+    for _ in 0..3 {
+        let response = client.get(&url).close_connection().send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    // One connection per request, one query for all of them
+    let snapshot = cache.metrics().snapshot();
+    assert_eq!(snapshot.lookups, 3);
+    assert_eq!(snapshot.cache_hits, 2);
+    assert_eq!(snapshot.cache_entries, 1);
+}
+
+#[tokio::test]
+async fn test_post_resolve_hooks() {
+    let port = ok_server().await;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let hook = {
+        let seen = seen.clone();
+        // Drop the unreachable TEST-NET address DNS put first
+        PostResolve::new(move |host, addrs: Vec<IpAddr>| {
+            seen.lock().unwrap().push((host.to_string(), addrs.len()));
+            addrs.into_iter().filter(IpAddr::is_loopback).collect()
+        })
+    };
+    let client = Client::builder()
+        .dns_resolver(MockResolver {
+            response: vec![
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 0),
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            ],
+        })
+        .post_resolve(hook)
+        .build();
+    let url = format!("http://edge.test:{}/", port);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "ok");
+    assert_eq!(*seen.lock().unwrap(), [("edge.test".to_string(), 2)]);
+
+    // The request's hook replaces the client's, and an empty answer fails
+    let result = client
+        .get(&url)
+        .post_resolve(PostResolve::new(|_, _| Vec::new()))
+        .send()
+        .await;
+    assert!(matches!(
+        result,
+        Err(NetError::NameNotResolvedFor { ref domain, .. }) if domain == "edge.test"
+    ));
+    assert_eq!(seen.lock().unwrap().len(), 1);
+}
@@ -2,7 +2,7 @@
 
 use chromenet::emulation::profiles::chrome::Chrome;
 use chromenet::emulation::{Emulation, EmulationFactory, Http1Options, Http2Options};
-use chromenet::socket::tls::TlsOptions;
+use chromenet::socket::tls::{AlpsProtocol, CertCompressAlg, ImpersonateTarget, TlsOptions};
 
 // === Emulation Tests ===
 
@@ -104,6 +104,10 @@ fn test_chrome_latest_emulation() {
     assert!(emu.tls_options().is_some());
     let tls = emu.tls_options().unwrap();
     assert!(tls.grease_enabled.unwrap_or(false));
+    assert_eq!(
+        tls.certificate_compression_algorithms.as_deref(),
+        Some(&[CertCompressAlg::BROTLI][..])
+    );
 
     // Should have H2 options
     assert!(emu.http2_options().is_some());
@@ -132,8 +136,58 @@ fn test_all_chrome_versions_valid() {
     }
 }
 
+/// ALPS settings for `h2` in a Chrome 124 handshake, decrypted from a
+/// capture: HEADER_TABLE_SIZE 65536, ENABLE_PUSH 0, INITIAL_WINDOW_SIZE
+/// 6291456, MAX_HEADER_LIST_SIZE 262144.
+const CHROME_ALPS_H2: [u8; 24] = [
+    0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x60,
+    0x00, 0x00, 0x00, 0x06, 0x00, 0x04, 0x00, 0x00,
+];
+
+#[test]
+fn test_chrome_alps_matches_capture() {
+    let impersonated = ImpersonateTarget::Chrome124.create_tls_options();
+    for tls in [
+        Chrome::V124.emulation().tls_options().unwrap(),
+        &impersonated,
+    ] {
+        assert_eq!(
+            tls.alps_protocols.as_deref(),
+            Some(&[AlpsProtocol::HTTP2][..])
+        );
+        assert_eq!(tls.alps_h2_settings.as_deref(), Some(&CHROME_ALPS_H2[..]));
+    }
+
+    // The settings follow the profile's HTTP/2 options
+    let h2 = Http2Options::builder()
+        .max_header_list_size(262144)
+        .initial_window_size(6291456)
+        .enable_push(false)
+        .header_table_size(65536)
+        .build();
+    let tls = TlsOptions::builder()
+        .alps_h2_settings(h2.alps_settings())
+        .build();
+    assert_eq!(tls.alps_h2_settings.as_deref(), Some(&CHROME_ALPS_H2[..]));
+}
+
 // === Http1Options Tests ===
 
+#[test]
+fn test_chrome_emulation_saved_and_loaded() {
+    let emu = Chrome::V143.emulation();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("chrome.json");
+    emu.save(&path).unwrap();
+
+    let loaded = Emulation::load(&path).unwrap();
+    assert_eq!(loaded.to_json(), emu.to_json());
+    assert_eq!(loaded.tls_options(), emu.tls_options());
+    assert_eq!(loaded.priority_header(), emu.priority_header());
+    let names = |e: &Emulation| e.headers().keys().cloned().collect::<Vec<_>>();
+    assert_eq!(names(&loaded), names(&emu));
+}
+
 #[test]
 fn test_h1_options_builder() {
     let opts = Http1Options::builder()
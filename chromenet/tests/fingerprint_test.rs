@@ -0,0 +1,197 @@
+//! Fingerprint regression suite: every emulation profile against the JA3N,
+//! JA4 and Akamai fingerprints recorded in `tests/fingerprints.json`.
+//!
+//! A change to a profile that moves its fingerprint fails here. When the
+//! change is intended, record the new fingerprints with
+//!
+//! ```text
+//! CHROMENET_BLESS=1 cargo test --test fingerprint_test
+//! ```
+//!
+//! and commit the updated file.
+
+mod common;
+
+use chromenet::http::H2Preface;
+use chromenet::socket::tls::{ClientHello, ImpersonateTarget};
+use chromenet::Client;
+use common::fingerprint::{client_hello, h2_preface};
+use serde_json::{json, Map, Value};
+use tokio::net::TcpListener;
+
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fingerprints.json");
+
+const PROFILES: &[(&str, ImpersonateTarget)] = &[
+    ("chrome124", ImpersonateTarget::Chrome124),
+    ("chrome128", ImpersonateTarget::Chrome128),
+    ("firefox128", ImpersonateTarget::Firefox128),
+    ("firefox129", ImpersonateTarget::Firefox129),
+    ("safari17", ImpersonateTarget::Safari17),
+    ("safari18", ImpersonateTarget::Safari18),
+    ("okhttp4", ImpersonateTarget::OkHttp4),
+    ("okhttp5", ImpersonateTarget::OkHttp5),
+];
+
+#[tokio::test]
+async fn test_profiles_match_golden_fingerprints() {
+    let mut actual = Map::new();
+    for (name, target) in PROFILES {
+        let hello = client_hello(Client::builder().impersonate(*target)).await;
+        let h2 = h2_preface(Client::builder().impersonate(*target)).await;
+        actual.insert(
+            name.to_string(),
+            json!({
+                "ja3n": hello.ja3n(),
+                "ja4": hello.ja4(),
+                "akamai": h2.akamai(),
+            }),
+        );
+    }
+
+    if std::env::var_os("CHROMENET_BLESS").is_some() {
+        let json = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::write(GOLDEN, json + "\n").unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(GOLDEN)
+        .unwrap_or_else(|e| panic!("{}: {}; record it with CHROMENET_BLESS=1", GOLDEN, e));
+    let golden: Map<String, Value> = serde_json::from_str(&golden).unwrap();
+    let mut drift = Vec::new();
+    for (name, fingerprints) in &actual {
+        let Some(recorded) = golden.get(name) else {
+            drift.push(format!("{}: no recorded fingerprints", name));
+            continue;
+        };
+        for kind in ["ja3n", "ja4", "akamai"] {
+            if recorded[kind] != fingerprints[kind] {
+                drift.push(format!(
+                    "{} {}:\n  recorded {}\n  actual   {}",
+                    name, kind, recorded[kind], fingerprints[kind]
+                ));
+            }
+        }
+    }
+    assert!(
+        drift.is_empty(),
+        "fingerprints drifted, rerun with CHROMENET_BLESS=1 if intended:\n{}",
+        drift.join("\n")
+    );
+}
+
+#[tokio::test]
+async fn test_permuted_extensions_keep_fingerprints() {
+    // Chrome shuffles its extensions on every connection
+    let first = client_hello(Client::builder().impersonate(ImpersonateTarget::Chrome124)).await;
+    let second = client_hello(Client::builder().impersonate(ImpersonateTarget::Chrome124)).await;
+    assert_eq!(first.ja3n(), second.ja3n());
+    assert_eq!(first.ja4(), second.ja4());
+}
+
+#[tokio::test]
+async fn test_identity_report_matches_wire() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (heads, head) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(socket).await.unwrap();
+        if let Some(Ok((request, mut respond))) = conn.accept().await {
+            heads.send(request.headers().clone()).unwrap();
+            let response = http::Response::builder().status(200).body(()).unwrap();
+            respond.send_response(response, true).unwrap();
+        }
+        while conn.accept().await.is_some() {}
+    });
+
+    let target = ImpersonateTarget::Chrome124;
+    let client = Client::builder().impersonate(target).build();
+    let response = client
+        .get(format!("http://{}/", addr))
+        .http2_prior_knowledge()
+        .send()
+        .await
+        .unwrap();
+    let report = response.identity_report();
+    let sent = head.await.unwrap();
+
+    assert_eq!(
+        report.profile.as_ref().map(ToString::to_string).as_deref(),
+        Some("chrome 124.0.0.0")
+    );
+    let h2 = h2_preface(Client::builder().impersonate(target)).await;
+    assert_eq!(report.h2_fingerprint, Some(h2.akamai()));
+    assert_eq!(
+        report.user_agent.as_deref(),
+        sent.get("user-agent").map(|ua| ua.to_str().unwrap())
+    );
+    let hints: Vec<(String, String)> = sent
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("sec-ch-ua"))
+        .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(report.client_hints, hints);
+    // Cleartext and direct
+    assert_eq!(report.ja4, None);
+    assert_eq!(report.tls_session_resumed, None);
+    assert_eq!(report.proxy, None);
+}
+
+#[test]
+fn test_ja4_of_client_hello() {
+    let mut hello = vec![0x03, 0x03];
+    hello.extend([0; 32]);
+    hello.push(0); // Session ID
+    hello.extend([0, 6, 0x2a, 0x2a, 0x13, 0x01, 0xc0, 0x2b]);
+    hello.extend([1, 0]); // Null compression
+    let extensions: &[(u16, &[u8])] = &[
+        (0x0a0a, &[]),
+        (0, &[0, 4, 0, 0, 1, b'a']),
+        (16, &[0, 3, 2, b'h', b'2']),
+        (43, &[2, 0x03, 0x04]),
+        (13, &[0, 4, 0x04, 0x03, 0x08, 0x04]),
+        (10, &[0, 2, 0, 29]),
+        (11, &[1, 0]),
+    ];
+    let mut ext = Vec::new();
+    for (kind, data) in extensions {
+        ext.extend(kind.to_be_bytes());
+        ext.extend((data.len() as u16).to_be_bytes());
+        ext.extend(*data);
+    }
+    hello.extend((ext.len() as u16).to_be_bytes());
+    hello.extend(ext);
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend((hello.len() as u16 + 4).to_be_bytes());
+    record.push(0x01);
+    record.extend(&(hello.len() as u32).to_be_bytes()[1..]);
+    record.extend(hello);
+
+    let hello = ClientHello::parse(&record).unwrap();
+    assert_eq!(hello.ja3n(), "771,4865-49195,0-10-11-13-16-43,29,0");
+    assert_eq!(hello.ja4(), "t13d0206h2_777cda164f4b_fb71836bce29");
+}
+
+#[test]
+fn test_akamai_of_h2_frames() {
+    let mut frames = Vec::new();
+    // SETTINGS
+    frames.extend([0, 0, 12, 0x4, 0, 0, 0, 0, 0]);
+    frames.extend([0, 1, 0, 1, 0, 0, 0, 4, 0, 0x60, 0, 0]);
+    // WINDOW_UPDATE
+    frames.extend([0, 0, 4, 0x8, 0, 0, 0, 0, 0]);
+    frames.extend(15663105u32.to_be_bytes());
+    // PRIORITY on stream 3
+    frames.extend([0, 0, 5, 0x2, 0, 0, 0, 0, 3, 0, 0, 0, 0, 200]);
+    // HEADERS with END_HEADERS and PRIORITY
+    let mut block = vec![0x82, 0x41, 11];
+    block.extend(b"example.com");
+    block.extend([0x87, 0x84, 0x7a]);
+    frames.extend(&((block.len() + 5) as u32).to_be_bytes()[1..]);
+    frames.extend([0x1, 0x24, 0, 0, 0, 1]);
+    frames.extend([0x80, 0, 0, 0, 255]);
+    frames.extend(block);
+
+    let h2 = H2Preface::parse(&frames).unwrap();
+    assert_eq!(h2.akamai(), "1:65536;4:6291456|15663105|3:0:0:201|m,a,s,p");
+}
@@ -0,0 +1,148 @@
+//! Connection selection: requests reuse the live HTTP/2 session or an idle
+//! socket, unless they ask for a fresh connection or one closed after the
+//! response.
+
+use bytes::Bytes;
+use chromenet::http::H2cMode;
+use chromenet::Client;
+use http::Response;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// HTTP/2 server counting its connections.
+async fn h2_server() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counted = connections.clone();
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            counted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut conn = http2::server::handshake::<_, Bytes>(socket).await.unwrap();
+                while let Some(Ok((_, mut respond))) = conn.accept().await {
+                    let response = Response::builder().status(200).body(()).unwrap();
+                    let mut send = respond.send_response(response, false).unwrap();
+                    send.send_data(Bytes::from_static(b"ok"), true).unwrap();
+                }
+            });
+        }
+    });
+    (url, connections)
+}
+
+/// Keep-alive HTTP/1.1 server counting its connections.
+async fn h1_server() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counted = connections.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            counted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (url, connections)
+}
+
+async fn fetch(request: chromenet::RequestBuilder) {
+    let text = request.send().await.unwrap().text().await.unwrap();
+    assert_eq!(text, "ok");
+}
+
+#[tokio::test]
+async fn test_fresh_connection_bypasses_h2_session() {
+    let (url, connections) = h2_server().await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+
+    fetch(client.get(&url)).await;
+    fetch(client.get(&url)).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    fetch(client.get(&url).fresh_connection()).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    // Later requests multiplex again
+    fetch(client.get(&url)).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_fresh_connection_bypasses_idle_socket() {
+    let (url, connections) = h1_server().await;
+    let client = Client::new();
+    // Time for the connection to return to the pool
+    let settle = || tokio::time::sleep(Duration::from_millis(50));
+
+    fetch(client.get(&url)).await;
+    settle().await;
+    fetch(client.get(&url)).await;
+    settle().await;
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    fetch(client.get(&url).fresh_connection()).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_close_connection_h2_session_not_shared() {
+    let (url, connections) = h2_server().await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+
+    fetch(client.get(&url)).await;
+    fetch(client.get(&url).close_connection()).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    // Later requests multiplex onto the first session, not the closed one
+    fetch(client.get(&url)).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_close_connection_h1_socket_not_pooled() {
+    // The server keeps connections alive whatever the request says
+    let (url, connections) = h1_server().await;
+    let client = Client::new();
+    let settle = || tokio::time::sleep(Duration::from_millis(50));
+
+    fetch(client.get(&url).close_connection()).await;
+    settle().await;
+    fetch(client.get(&url)).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_close_connection_sends_connection_close() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase()
+    });
+
+    fetch(Client::new().get(&url).close_connection()).await;
+    let request = server.await.unwrap();
+    assert!(request.contains("\r\nconnection: close\r\n"), "{request}");
+    assert!(!request.contains("keep-alive"), "{request}");
+}
@@ -0,0 +1,68 @@
+//! HTTP/1.1 response framing tests: responses open to smuggling are rejected.
+
+use chromenet::base::neterror::NetError;
+use chromenet::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Answer one request with a raw `response`.
+async fn raw_server(response: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = socket.read(&mut buf).await;
+        let _ = socket.write_all(response.as_bytes()).await;
+        let _ = socket.shutdown().await;
+    });
+
+    url
+}
+
+#[tokio::test]
+async fn test_conflicting_content_length() {
+    let url = raw_server(
+        "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nContent-Length: 6\r\nConnection: close\r\n\r\nhello!",
+    )
+    .await;
+    let result = Client::new().get(&url).send().await;
+    assert!(matches!(
+        result,
+        Err(NetError::ResponseHeadersMultipleContentLength)
+    ));
+}
+
+#[tokio::test]
+async fn test_identical_content_lengths_accepted() {
+    let url = raw_server(
+        "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "hello");
+}
+
+#[tokio::test]
+async fn test_transfer_encoding_with_content_length() {
+    let url = raw_server(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\nConnection: close\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+    )
+    .await;
+    let result = Client::new().get(&url).send().await;
+    assert!(matches!(result, Err(NetError::InvalidResponse)));
+}
+
+#[tokio::test]
+async fn test_invalid_chunk_size() {
+    let url = raw_server(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\nzz\r\nhello\r\n0\r\n\r\n",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert!(matches!(
+        response.bytes().await,
+        Err(NetError::InvalidChunkedEncoding)
+    ));
+}
@@ -0,0 +1,111 @@
+//! HTTP/2 over cleartext (h2c) tests against local servers.
+
+mod common;
+
+use bytes::Bytes;
+use chromenet::http::H2cMode;
+use chromenet::Client;
+use common::server::read_head;
+use http::{Response, Version};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Serve every request on an HTTP/2 connection with `body`.
+async fn serve_h2(socket: TcpStream, body: &'static str) {
+    let mut conn = http2::server::handshake(socket).await.unwrap();
+    while let Some(Ok((_req, mut respond))) = conn.accept().await {
+        let response = Response::builder().status(200).body(()).unwrap();
+        let mut send = respond.send_response(response, false).unwrap();
+        send.send_data(Bytes::from_static(body.as_bytes()), true)
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_h2c_prior_knowledge() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        serve_h2(socket, "h2c").await;
+    });
+
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    let resp = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.version(), Version::HTTP_2);
+    assert_eq!(resp.text().await.unwrap(), "h2c");
+}
+
+#[tokio::test]
+async fn test_h2c_upgrade_accepted() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let head = read_head(&mut socket).await.unwrap().to_lowercase();
+        assert!(head.starts_with("options * http/1.1"));
+        assert!(head.contains("upgrade: h2c"));
+        assert!(head.contains("http2-settings: "));
+
+        socket
+            .write_all(
+                b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        serve_h2(socket, "upgraded").await;
+    });
+
+    let client = Client::builder().h2c(H2cMode::Upgrade).build();
+    let resp = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.version(), Version::HTTP_2);
+    assert_eq!(resp.text().await.unwrap(), "upgraded");
+}
+
+#[tokio::test]
+async fn test_h2c_upgrade_declined_stays_on_http1() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        // Probe is answered as plain HTTP/1.1
+        let head = read_head(&mut socket).await.unwrap().to_lowercase();
+        assert!(head.starts_with("options * http/1.1"));
+        socket
+            .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .await
+            .unwrap();
+
+        // Real request follows on the same connection
+        let head = read_head(&mut socket).await.unwrap().to_lowercase();
+        assert!(head.starts_with("get /path?q=1 http/1.1"));
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nh1")
+            .await
+            .unwrap();
+    });
+
+    let client = Client::builder().h2c(H2cMode::Upgrade).build();
+    let resp = client
+        .get(format!("http://{}/path?q=1", addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.version(), Version::HTTP_11);
+    assert_eq!(resp.text().await.unwrap(), "h1");
+}
@@ -0,0 +1,100 @@
+//! Large request headers over HTTP/2: header blocks beyond a frame go out
+//! as CONTINUATION frames, and headers over the server's
+//! SETTINGS_MAX_HEADER_LIST_SIZE fail before they are sent.
+
+use bytes::Bytes;
+use chromenet::base::neterror::NetError;
+use chromenet::http::H2cMode;
+use chromenet::Client;
+use http::Response;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// HTTP/2 server answering with the size of the request's `Cookie`,
+/// announcing `max_header_list_size` if set. Returns the address and the
+/// number of requests it received.
+async fn cookie_server(max_header_list_size: Option<u32>) -> (SocketAddr, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counted = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let counted = counted.clone();
+            tokio::spawn(async move {
+                let mut builder = http2::server::Builder::new();
+                if let Some(max) = max_header_list_size {
+                    builder.max_header_list_size(max);
+                }
+                let mut conn = builder.handshake::<_, Bytes>(socket).await.unwrap();
+                while let Some(Ok((req, mut respond))) = conn.accept().await {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    let size = req
+                        .headers()
+                        .get_all(http::header::COOKIE)
+                        .iter()
+                        .map(|v| v.len())
+                        .sum::<usize>();
+                    let response = Response::builder().status(200).body(()).unwrap();
+                    let mut send = respond.send_response(response, false).unwrap();
+                    send.send_data(Bytes::from(size.to_string()), true).unwrap();
+                }
+            });
+        }
+    });
+    (addr, requests)
+}
+
+fn cookie(len: usize) -> String {
+    format!("session={}", "x".repeat(len - "session=".len()))
+}
+
+#[tokio::test]
+async fn test_headers_beyond_a_frame_sent_as_continuation() {
+    let (addr, _) = cookie_server(None).await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+
+    // Well over the 16 KiB default SETTINGS_MAX_FRAME_SIZE
+    let body = client
+        .get(format!("http://{}/", addr))
+        .header("Cookie", cookie(40_000))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, "40000");
+}
+
+#[tokio::test]
+async fn test_headers_over_server_limit_fail_before_sending() {
+    let (addr, requests) = cookie_server(Some(4096)).await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    let url = format!("http://{}/", addr);
+
+    // The server's SETTINGS arrive with the first response
+    let small = client.get(&url).header("Cookie", cookie(1000)).send().await;
+    assert_eq!(small.unwrap().text().await.unwrap(), "1000");
+
+    let err = client
+        .get(&url)
+        .header("Cookie", cookie(8000))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NetError::RequestHeadersTooBig { size, limit: 4096 } if size > 8000
+    ));
+    assert_eq!(err.as_i32(), -10034);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // The connection carries requests within the limit
+    let small = client.get(&url).header("Cookie", cookie(1000)).send().await;
+    assert_eq!(small.unwrap().text().await.unwrap(), "1000");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
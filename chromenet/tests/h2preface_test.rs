@@ -0,0 +1,81 @@
+//! Frames an HTTP/2 connection opens with: when the connection-level
+//! WINDOW_UPDATE goes out relative to SETTINGS and the first request, and
+//! GREASE.
+
+mod common;
+
+use chromenet::emulation::Http2Options;
+use chromenet::http::{ConnWindowUpdate, GreaseFrame, GreaseSetting, H2Fingerprint};
+use chromenet::Client;
+use common::fingerprint::{h2_frame_order, h2_preface};
+use std::time::Duration;
+
+const SETTINGS: u8 = 0x4;
+const WINDOW_UPDATE: u8 = 0x8;
+const HEADERS: u8 = 0x1;
+
+/// Frame types the client sends on a connection with `when`, leaving out
+/// the ACK of the server's SETTINGS.
+async fn frame_order(when: ConnWindowUpdate) -> Vec<u8> {
+    let fp = H2Fingerprint::builder().conn_window_update(when).build();
+    let builder = Client::builder().emulation(Http2Options::builder().fingerprint(fp).build());
+    let mut seen_settings = false;
+    h2_frame_order(builder, Duration::from_millis(200))
+        .await
+        .into_iter()
+        .filter(|&(kind, _)| kind != SETTINGS || !std::mem::replace(&mut seen_settings, true))
+        .map(|(kind, _)| kind)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_window_update_after_settings() {
+    assert_eq!(
+        frame_order(ConnWindowUpdate::AfterSettings).await,
+        [SETTINGS, WINDOW_UPDATE, HEADERS]
+    );
+}
+
+#[tokio::test]
+async fn test_window_update_after_first_request() {
+    assert_eq!(
+        frame_order(ConnWindowUpdate::AfterFirstRequest).await,
+        [SETTINGS, HEADERS, WINDOW_UPDATE]
+    );
+}
+
+#[tokio::test]
+async fn test_window_update_never() {
+    assert_eq!(
+        frame_order(ConnWindowUpdate::Never).await,
+        [SETTINGS, HEADERS]
+    );
+}
+
+#[tokio::test]
+async fn test_grease_setting_and_frame() {
+    // 0x2a = 0x0b + 0x1f, a reserved frame type
+    let fp = H2Fingerprint::builder()
+        .grease_setting(GreaseSetting::new(0x3a3a, 42))
+        .grease_frame(GreaseFrame::new(0x2a, 0, &b"grease"[..]))
+        .build();
+    let builder =
+        || Client::builder().emulation(Http2Options::builder().fingerprint(fp.clone()).build());
+
+    let preface = h2_preface(builder()).await;
+    assert_eq!(preface.settings.last(), Some(&(0x3a3a, 42)));
+    assert!(
+        preface.akamai().contains(";14906:42|"),
+        "{}",
+        preface.akamai()
+    );
+
+    let order: Vec<u8> = h2_frame_order(builder(), Duration::ZERO)
+        .await
+        .into_iter()
+        .map(|(kind, _)| kind)
+        .collect();
+    // The frame goes out with the SETTINGS, ahead of the server's
+    assert_eq!(order[..3], [SETTINGS, 0x2a, WINDOW_UPDATE]);
+    assert!(order.contains(&HEADERS));
+}
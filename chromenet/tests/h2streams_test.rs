@@ -0,0 +1,164 @@
+//! HTTP/2 requests beyond the server's MAX_CONCURRENT_STREAMS wait for a
+//! free stream instead of failing.
+
+use bytes::Bytes;
+use chromenet::http::requestbody::RequestBody;
+use chromenet::http::streamfactory::{H2StreamUsage, HttpStreamFactory, HttpVersionPref};
+use chromenet::http::{H2SessionPolicy, H2cMode};
+use chromenet::socket::pool::{ClientSocketPool, GroupId};
+use chromenet::Client;
+use http::{Request, Response};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use url::Url;
+
+/// HTTP/2 server allowing `max_streams` concurrent streams, each answered
+/// after `delay`. Returns the address, the connection count and the
+/// highest number of streams seen open at once.
+async fn slow_h2_server(
+    max_streams: u32,
+    delay: Duration,
+) -> (SocketAddr, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (counted, peaked) = (connections.clone(), peak.clone());
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            counted.fetch_add(1, Ordering::SeqCst);
+            let peak = peaked.clone();
+            tokio::spawn(async move {
+                let mut conn = http2::server::Builder::new()
+                    .max_concurrent_streams(max_streams)
+                    .handshake::<_, Bytes>(socket)
+                    .await
+                    .unwrap();
+                let open = Arc::new(AtomicUsize::new(0));
+                while let Some(Ok((_req, mut respond))) = conn.accept().await {
+                    let now = open.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    let open = open.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        let response = Response::builder().status(200).body(()).unwrap();
+                        let mut send = respond.send_response(response, false).unwrap();
+                        open.fetch_sub(1, Ordering::SeqCst);
+                        send.send_data(Bytes::from_static(b"ok"), true).unwrap();
+                    });
+                }
+            });
+        }
+    });
+    (addr, connections, peak)
+}
+
+#[tokio::test]
+async fn test_burst_waits_for_free_streams() {
+    let (addr, connections, peak) = slow_h2_server(2, Duration::from_millis(20)).await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    let url = format!("http://{}/", addr);
+
+    let requests = (0..10).map(|_| {
+        let (client, url) = (client.clone(), url.clone());
+        tokio::spawn(async move { client.get(&url).send().await?.text().await })
+    });
+    for request in futures::future::join_all(requests).await {
+        assert_eq!(request.unwrap().unwrap(), "ok");
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+    assert!(peak.load(Ordering::SeqCst) <= 2);
+}
+
+#[tokio::test]
+async fn test_stream_usage() {
+    let (addr, _, _) = slow_h2_server(2, Duration::ZERO).await;
+    let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+    let group_id = GroupId::new(&url, None, None).unwrap();
+    let factory = HttpStreamFactory::new(Arc::new(ClientSocketPool::new(None)))
+        .with_h2c_mode(H2cMode::PriorKnowledge);
+    assert_eq!(factory.h2_stream_usage(&group_id), None);
+
+    let mut stream = factory
+        .create_stream(&url, None, None, None, None, HttpVersionPref::Auto)
+        .await
+        .unwrap();
+    let request = Request::get(url.as_str())
+        .body(RequestBody::Empty.into())
+        .unwrap();
+    let response = stream.send_request(request).await.unwrap();
+    let usage = factory.h2_stream_usage(&group_id).unwrap();
+    assert_eq!(
+        usage,
+        H2StreamUsage {
+            open: 1,
+            max_concurrent: 2
+        }
+    );
+
+    // The stream is open until its body is dropped
+    drop(response);
+    assert_eq!(factory.h2_stream_usage(&group_id).unwrap().open, 0);
+}
+
+#[tokio::test]
+async fn test_session_policy_opens_more_connections() {
+    let (addr, connections, peak) = slow_h2_server(100, Duration::from_millis(100)).await;
+    let client = Client::builder()
+        .h2c(H2cMode::PriorKnowledge)
+        .h2_session_policy(
+            H2SessionPolicy::new()
+                .with_max_streams_per_session(2)
+                .with_max_sessions(3),
+        )
+        .build();
+    let url = format!("http://{}/", addr);
+
+    let requests = (0..9).map(|_| {
+        let (client, url) = (client.clone(), url.clone());
+        tokio::spawn(async move { client.get(&url).send().await?.text().await })
+    });
+    for request in futures::future::join_all(requests).await {
+        assert_eq!(request.unwrap().unwrap(), "ok");
+    }
+
+    // Two streams per session until the third, then the least loaded
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+    assert!(peak.load(Ordering::SeqCst) <= 3);
+}
+
+#[tokio::test]
+async fn test_session_policy_fills_server_limit_first() {
+    let (addr, connections, _) = slow_h2_server(2, Duration::from_millis(100)).await;
+    let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+    let group_id = GroupId::new(&url, None, None).unwrap();
+    let factory = HttpStreamFactory::new(Arc::new(ClientSocketPool::new(None)))
+        .with_h2c_mode(H2cMode::PriorKnowledge)
+        .with_h2_session_policy(H2SessionPolicy::new().with_max_sessions(2));
+
+    let mut responses = Vec::new();
+    for _ in 0..3 {
+        let mut stream = factory
+            .create_stream(&url, None, None, None, None, HttpVersionPref::Auto)
+            .await
+            .unwrap();
+        let request = Request::get(url.as_str())
+            .body(RequestBody::Empty.into())
+            .unwrap();
+        responses.push(stream.send_request(request).await.unwrap());
+    }
+
+    // The server allows two streams, the third request opened a session
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+    let open: Vec<_> = factory
+        .h2_session_usage(&group_id)
+        .iter()
+        .map(|usage| usage.open)
+        .collect();
+    assert_eq!(open, [2, 1]);
+}
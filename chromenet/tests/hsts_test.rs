@@ -69,3 +69,14 @@ fn test_case_insensitivity() {
     assert!(store.should_upgrade("EXAMPLE.COM"));
     assert!(store.should_upgrade("Example.Com"));
 }
+
+#[test]
+fn test_hosts_are_canonicalized() {
+    let store = HstsStore::new();
+    store.add_from_header("Secure.Example.COM.", "max-age=3600; includeSubDomains");
+    assert!(store.should_upgrade("secure.example.com"));
+    assert!(store.should_upgrade("WWW.secure.example.com."));
+
+    store.add_from_header("bücher.example", "max-age=3600");
+    assert!(store.should_upgrade("xn--bcher-kva.example"));
+}
@@ -0,0 +1,231 @@
+//! HTTP authentication tests against a local HTTP/1.1 server.
+
+use chromenet::socket::authcache::{AuthCache, AuthScheme};
+use chromenet::Client;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// Answer each request head with `respond(head)` and report the lowercased
+/// head. Each connection carries one request.
+async fn auth_server<F>(respond: F) -> (SocketAddr, mpsc::UnboundedReceiver<String>)
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let respond = Arc::new(respond);
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let tx = tx.clone();
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let response = respond(&head);
+                let _ = tx.send(head);
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    (addr, rx)
+}
+
+fn unauthorized(challenge: &str) -> String {
+    format!(
+        "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        challenge
+    )
+}
+
+#[tokio::test]
+async fn test_digest_challenge_then_preemptive() {
+    let (addr, mut heads) = auth_server(|head| {
+        if head.contains("authorization: digest username=\"user\"") && head.contains("nonce=\"n1\"")
+        {
+            OK.to_string()
+        } else {
+            unauthorized(r#"Digest realm="api", nonce="n1", qop="auth""#)
+        }
+    })
+    .await;
+    let client = Client::new();
+
+    let resp = client
+        .get(format!("http://{}/a", addr))
+        .credentials("user", "pass")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(!heads.recv().await.unwrap().contains("authorization"));
+    assert!(heads.recv().await.unwrap().contains("nc=00000001"));
+
+    // Same origin, no credentials given: answered from the cache up front
+    let resp = client
+        .get(format!("http://{}/b", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("uri=\"/b\""));
+    assert!(head.contains("nc=00000002"));
+}
+
+#[tokio::test]
+async fn test_digest_stale_nonce_is_renewed() {
+    let (addr, mut heads) = auth_server(|head| {
+        if head.contains("nonce=\"n2\"") {
+            OK.to_string()
+        } else if head.contains("nonce=\"n1\"") {
+            unauthorized(r#"Digest realm="api", nonce="n2", stale=true"#)
+        } else {
+            unauthorized(r#"Digest realm="api", nonce="n1""#)
+        }
+    })
+    .await;
+
+    let resp = Client::new()
+        .get(format!("http://{}/", addr))
+        .credentials("user", "pass")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let mut nonces = Vec::new();
+    for _ in 0..3 {
+        let head = heads.recv().await.unwrap();
+        nonces.push(["n1", "n2"].into_iter().find(|n| head.contains(n)));
+    }
+    assert_eq!(nonces, [None, Some("n1"), Some("n2")]);
+}
+
+#[tokio::test]
+async fn test_rejected_credentials_return_401() {
+    let (addr, mut heads) = auth_server(|head| {
+        // user:pass
+        if head.contains("authorization: basic dxnlcjpwyxnz") {
+            OK.to_string()
+        } else {
+            unauthorized(r#"Basic realm="r""#)
+        }
+    })
+    .await;
+    let client = Client::new();
+
+    let resp = client
+        .get(format!("http://{}/", addr))
+        .credentials("user", "wrong")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    heads.recv().await.unwrap();
+    assert!(heads.recv().await.unwrap().contains("authorization: basic"));
+    let url = url::Url::parse(&format!("http://{}/", addr)).unwrap();
+    assert!(client
+        .auth_cache()
+        .lookup(&url, "r", AuthScheme::Basic)
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_url_credentials_answer_basic_challenge() {
+    let (addr, _heads) = auth_server(|head| {
+        if head.contains("authorization: basic dxnlcjpwyxnz") {
+            OK.to_string()
+        } else {
+            unauthorized(r#"Basic realm="r""#)
+        }
+    })
+    .await;
+
+    let resp = Client::new()
+        .get(format!("http://user:pass@{}/", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+}
+
+/// Basic auth for `user:pass` under `/private/`, everything else is public.
+fn private_area(head: &str) -> String {
+    let path = head.split_whitespace().nth(1).unwrap_or_default();
+    if !path.starts_with("/private/") || head.contains("authorization: basic dxnlcjpwyxnz") {
+        OK.to_string()
+    } else {
+        unauthorized(r#"Basic realm="private""#)
+    }
+}
+
+#[tokio::test]
+async fn test_preemptive_auth_is_scoped_to_path() {
+    let (addr, mut heads) = auth_server(private_area).await;
+    let client = Client::new();
+
+    client
+        .get(format!("http://{}/private/a", addr))
+        .credentials("user", "pass")
+        .send()
+        .await
+        .unwrap();
+    heads.recv().await.unwrap();
+    heads.recv().await.unwrap();
+
+    // Same directory: sent up front
+    client
+        .get(format!("http://{}/private/b", addr))
+        .send()
+        .await
+        .unwrap();
+    assert!(heads.recv().await.unwrap().contains("authorization"));
+
+    // Outside the protection space: not sent
+    client
+        .get(format!("http://{}/public/c", addr))
+        .send()
+        .await
+        .unwrap();
+    assert!(!heads.recv().await.unwrap().contains("authorization"));
+}
+
+#[tokio::test]
+async fn test_persisted_basic_credentials_survive_restart() {
+    let (addr, mut heads) = auth_server(private_area).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("auth.json");
+
+    let client = Client::new();
+    client
+        .get(format!("http://{}/private/a", addr))
+        .credentials("user", "pass")
+        .send()
+        .await
+        .unwrap();
+    client.auth_cache().save_to_file(&path).unwrap();
+    heads.recv().await.unwrap();
+    heads.recv().await.unwrap();
+
+    let cache = AuthCache::new();
+    assert_eq!(cache.load_from_file(&path).unwrap(), 1);
+    let restarted = Client::builder().auth_cache(cache).build();
+    let resp = restarted
+        .get(format!("http://{}/private/b", addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert!(heads.recv().await.unwrap().contains("authorization: basic"));
+}
@@ -0,0 +1,192 @@
+//! Protocol version forcing tests against local cleartext servers.
+
+use bytes::Bytes;
+use chromenet::base::neterror::NetError;
+use chromenet::http::H2cMode;
+use chromenet::socket::nextproto::NextProto;
+use chromenet::Client;
+use http::{Response, Version};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Start an HTTP/2 server that answers every request with "h2",
+/// or resets it with `reset` when given.
+async fn h2_server(reset: Option<http2::Reason>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(socket).await.unwrap();
+        while let Some(Ok((_req, mut respond))) = conn.accept().await {
+            if let Some(reason) = reset {
+                respond.send_reset(reason);
+                continue;
+            }
+            let response = Response::builder().status(200).body(()).unwrap();
+            let mut send = respond.send_response(response, false).unwrap();
+            send.send_data(Bytes::from_static(b"h2"), true).unwrap();
+        }
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn test_http2_prior_knowledge_per_client() {
+    let addr = h2_server(None).await;
+
+    let client = Client::builder().http2_prior_knowledge().build();
+    let resp = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.version(), Version::HTTP_2);
+    // Cleartext connections have no ALPN result or TLS details
+    assert_eq!(resp.negotiated_protocol(), NextProto::Unknown);
+    assert!(resp.ssl_info().is_none());
+    assert_eq!(resp.text().await.unwrap(), "h2");
+}
+
+#[tokio::test]
+async fn test_http2_prior_knowledge_per_request() {
+    let addr = h2_server(None).await;
+
+    let client = Client::new();
+    let resp = client
+        .get(format!("http://{}/", addr))
+        .http2_prior_knowledge()
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.version(), Version::HTTP_2);
+}
+
+#[tokio::test]
+async fn test_http1_only_overrides_h2c() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"GET / HTTP/1.1\r\n"));
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nh1")
+            .await
+            .unwrap();
+    });
+
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    let resp = client
+        .get(format!("http://{}/", addr))
+        .http1_only()
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.version(), Version::HTTP_11);
+    assert_eq!(resp.text().await.unwrap(), "h1");
+}
+
+#[tokio::test]
+async fn test_http11_required_reset_is_mapped() {
+    let addr = h2_server(Some(http2::Reason::HTTP_1_1_REQUIRED)).await;
+
+    let client = Client::builder().http2_prior_knowledge().build();
+    let result = client.get(format!("http://{}/", addr)).send().await;
+
+    assert!(matches!(result, Err(NetError::Http11Required)));
+}
+
+#[tokio::test]
+async fn test_http3_only_unavailable() {
+    let client = Client::builder().http3_only().build();
+    let result = client.get("https://127.0.0.1:1/").send().await;
+
+    assert!(matches!(result, Err(NetError::NotImplemented)));
+}
+
+#[tokio::test]
+async fn test_http11_required_falls_back_and_is_remembered() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let h2_requests = Arc::new(AtomicUsize::new(0));
+    let counter = h2_requests.clone();
+
+    tokio::spawn(async move {
+        // First connection speaks h2 and asks for HTTP/1.1
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(socket).await.unwrap();
+        tokio::spawn(async move {
+            while let Some(Ok((_req, mut respond))) = conn.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                respond.send_reset(http2::Reason::HTTP_1_1_REQUIRED);
+            }
+        });
+
+        // Every later connection must be HTTP/1.1
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            assert!(buf[..n].starts_with(b"GET / HTTP/1.1\r\n"));
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nh1")
+                .await
+                .unwrap();
+        }
+    });
+
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    for _ in 0..2 {
+        let resp = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.version(), Version::HTTP_11);
+        assert_eq!(resp.text().await.unwrap(), "h1");
+    }
+    // The second request went straight to HTTP/1.1
+    assert_eq!(h2_requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_http1_0_request_and_status_line() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        let _ = tx.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+        socket
+            .write_all(b"HTTP/1.0 200 Fine\xe9\r\nContent-Length: 2\r\n\r\nok")
+            .await
+            .unwrap();
+    });
+
+    let resp = Client::new()
+        .get(format!("http://{}/old", addr))
+        .http1_0()
+        .send()
+        .await
+        .unwrap();
+    let head = rx.await.unwrap();
+    assert!(head.starts_with("GET /old HTTP/1.0\r\n"), "{head}");
+
+    assert_eq!(resp.version(), Version::HTTP_10);
+    assert_eq!(resp.reason_phrase(), Some(&b"Fine\xe9"[..]));
+    assert_eq!(resp.status_line().unwrap(), "HTTP/1.0 200 Fine\u{fffd}");
+    assert_eq!(resp.text().await.unwrap(), "ok");
+}
@@ -0,0 +1,219 @@
+//! End-to-end flows against the local httpbin-like server.
+
+mod common;
+
+use chromenet::Client;
+use common::httpbin::HttpBin;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+// Every test stays within one client's six connections per host; HTTP/1.1
+// sockets are not returned to the pool yet.
+
+async fn json(client: &Client, url: String) -> (u16, Value) {
+    let resp = client.get(url).send().await.unwrap();
+    let status = resp.status().as_u16();
+    (status, resp.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_status_codes() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    for code in [200u16, 204, 404, 418, 500, 503] {
+        let resp = client
+            .get(bin.url(&format!("/status/{}", code)))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), code);
+    }
+}
+
+#[tokio::test]
+async fn test_redirect_chains_end_at_get() {
+    let bin = HttpBin::start().await;
+
+    let (status, body) = json(&Client::new(), bin.url("/redirect/3")).await;
+    assert_eq!(status, 200);
+    assert!(body["url"].as_str().unwrap().ends_with("/get"));
+    // Three hops plus the final request
+    assert_eq!(bin.requests(), 4);
+
+    let (status, body) = json(&Client::new(), bin.url("/absolute-redirect/2")).await;
+    assert_eq!(status, 200);
+    assert!(body["url"].as_str().unwrap().ends_with("/get"));
+}
+
+#[tokio::test]
+async fn test_redirect_to_with_status() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    let target = bin.url("/get?from=redirect");
+    let (status, body) = json(
+        &client,
+        bin.url(&format!("/redirect-to?url={}&status_code=307", target)),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["args"]["from"], "redirect");
+}
+
+#[tokio::test]
+async fn test_request_ids_increase_across_requests() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    let first = client.get(bin.url("/get")).send().await.unwrap();
+    let redirected = client.get(bin.url("/redirect/2")).send().await.unwrap();
+    let first = first.request_id().unwrap();
+    let redirected = redirected.request_id().unwrap();
+    // One ID for the request, however many hops it took
+    assert!(redirected > first);
+    assert_eq!(bin.requests(), 4);
+}
+
+#[tokio::test]
+async fn test_cookies_set_and_delete_through_redirects() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    let (_, body) = json(&client, bin.url("/cookies/set?session=abc&theme=dark")).await;
+    assert_eq!(body["cookies"]["session"], "abc");
+    assert_eq!(body["cookies"]["theme"], "dark");
+
+    let (_, body) = json(&client, bin.url("/cookies/delete?session")).await;
+    assert!(body["cookies"].get("session").is_none());
+    assert_eq!(body["cookies"]["theme"], "dark");
+
+    // Cookies are per client
+    let (_, body) = json(&Client::new(), bin.url("/cookies")).await;
+    assert!(body["cookies"].as_object().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_headers_are_echoed() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    let resp = client
+        .get(bin.url("/headers"))
+        .header("x-test", "value")
+        .send()
+        .await
+        .unwrap();
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["headers"]["x-test"], "value");
+    assert!(body["headers"]["host"]
+        .as_str()
+        .unwrap()
+        .starts_with(&bin.addr().ip().to_string()));
+}
+
+#[tokio::test]
+async fn test_post_body_is_echoed() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    let resp = client
+        .post(bin.url("/anything"))
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["method"], "POST");
+    assert_eq!(body["data"], "payload");
+}
+
+#[tokio::test]
+async fn test_basic_auth() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+    let url = bin.url("/basic-auth/user/passwd");
+
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let resp = client
+        .get(&url)
+        .credentials("user", "wrong")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let resp = client
+        .get(&url)
+        .credentials("user", "passwd")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["authenticated"], true);
+}
+
+#[tokio::test]
+async fn test_digest_auth() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    for qop in ["auth", "none"] {
+        let resp = client
+            .get(bin.url(&format!("/digest-auth/{}/user/passwd", qop)))
+            .credentials("user", "passwd")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200, "qop={}", qop);
+    }
+
+    let resp = client
+        .get(bin.url("/digest-auth/auth/user/passwd"))
+        .credentials("user", "wrong")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_delay() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    let start = Instant::now();
+    let resp = client.get(bin.url("/delay/0.2")).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    let slow = client.get(bin.url("/delay/5")).send();
+    assert!(tokio::time::timeout(Duration::from_millis(100), slow)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_gzip_body_is_delivered_intact() {
+    let bin = HttpBin::start().await;
+    let client = Client::new();
+
+    let resp = client.get(bin.url("/gzip")).send().await.unwrap();
+    assert_eq!(resp.headers()["content-encoding"], "gzip");
+    let body = resp.bytes().await.unwrap();
+    let expected = serde_json::json!({ "gzipped": true, "method": "GET" }).to_string();
+    assert_eq!(&body[..], &common::httpbin::gzip(expected.as_bytes())[..]);
+}
+
+#[tokio::test]
+async fn test_h2_prior_knowledge() {
+    let bin = HttpBin::start().await;
+    let client = Client::builder().http2_prior_knowledge().build();
+
+    let resp = client.get(bin.url("/bytes/1024")).send().await.unwrap();
+    assert_eq!(resp.version(), http::Version::HTTP_2);
+    assert_eq!(resp.bytes().await.unwrap().len(), 1024);
+}
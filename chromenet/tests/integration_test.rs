@@ -28,6 +28,7 @@ async fn test_real_http_get() {
 }
 
 /// Test HTTPS with browser emulation
+#[cfg(feature = "emulation-profiles")]
 #[tokio::test]
 #[ignore]
 async fn test_https_with_emulation() {
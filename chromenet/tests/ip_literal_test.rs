@@ -0,0 +1,98 @@
+//! Requests to IP literal hosts: DNS, cookies and HSTS.
+
+mod common;
+
+use chromenet::cookies::monster::CookieMonster;
+use chromenet::tls::hsts::HstsStore;
+use chromenet::Client;
+use common::server::serve;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use url::Url;
+
+fn names(store: &CookieMonster, url: &str) -> Vec<String> {
+    let mut names: Vec<_> = store
+        .get_cookies_for_url(&Url::parse(url).unwrap())
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_ipv4_cookies_are_host_only() {
+    let store = CookieMonster::new();
+    let url = Url::parse("http://10.0.0.5/").unwrap();
+    store.parse_and_save_cookie(&url, "plain=1");
+    store.parse_and_save_cookie(&url, "own=1; Domain=10.0.0.5");
+    // Trailing labels of the address are not parent domains
+    store.parse_and_save_cookie(&url, "suffix=1; Domain=0.0.5");
+    store.parse_and_save_cookie(&url, "other=1; Domain=10.0.0.6");
+
+    assert_eq!(names(&store, "http://10.0.0.5/"), ["own", "plain"]);
+    assert!(store.iter_all_cookies().all(|c| c.host_only));
+    assert!(names(&store, "http://110.0.0.5/").is_empty());
+    assert!(names(&store, "http://20.0.0.5/").is_empty());
+}
+
+#[test]
+fn test_ipv6_cookies_are_host_only() {
+    let store = CookieMonster::new();
+    let url = Url::parse("http://[2001:db8::1]/").unwrap();
+    store.parse_and_save_cookie(&url, "own=1; Domain=[2001:DB8:0::1]");
+    store.parse_and_save_cookie(&url, "other=1; Domain=[2001:db8::2]");
+
+    assert_eq!(names(&store, "http://[2001:db8::1]:8080/"), ["own"]);
+    assert!(store.iter_all_cookies().all(|c| c.host_only));
+}
+
+#[test]
+fn test_hsts_skips_ip_literals() {
+    let store = HstsStore::new();
+    store.add_from_header("10.0.0.5", "max-age=31536000; includeSubDomains");
+    store.add_from_header("[2001:db8::1]", "max-age=31536000");
+    assert!(store.is_empty());
+    assert!(!store.should_upgrade("10.0.0.5"));
+    assert!(!store.should_upgrade("[2001:db8::1]"));
+}
+
+/// Server on `addr` that sets a cookie and records request heads.
+async fn server(addr: &str) -> Option<(String, Arc<Mutex<Vec<String>>>)> {
+    // Not every host has an IPv6 loopback
+    let listener = TcpListener::bind(addr).await.ok()?;
+    let local = listener.local_addr().unwrap();
+    let url = format!("http://{}/", local);
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let seen = heads.clone();
+    serve(listener, move |request| {
+        seen.lock().unwrap().push(request.to_lowercase());
+        b"HTTP/1.1 200 OK\r\nSet-Cookie: id=1\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+            .to_vec()
+    });
+    Some((url, heads))
+}
+
+#[tokio::test]
+async fn test_requests_to_ip_literals() {
+    for addr in ["127.0.0.1:0", "[::1]:0"] {
+        let Some((url, heads)) = server(addr).await else {
+            continue;
+        };
+        let client = Client::new();
+        for _ in 0..2 {
+            let response = client.get(&url).send().await.unwrap();
+            assert_eq!(response.text().await.unwrap(), "ok");
+        }
+
+        let heads = heads.lock().unwrap();
+        let host = url.trim_start_matches("http://").trim_end_matches('/');
+        assert!(
+            heads[0].contains(&format!("host: {}\r\n", host)),
+            "{}",
+            heads[0]
+        );
+        assert!(!heads[0].contains("cookie:"));
+        assert!(heads[1].contains("cookie: id=1"), "{}", heads[1]);
+    }
+}
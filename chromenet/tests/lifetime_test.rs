@@ -0,0 +1,288 @@
+//! Retiring pooled connections by age and when their address leaves DNS.
+
+mod common;
+
+use bytes::Bytes;
+use chromenet::base::neterror::NetError;
+use chromenet::http::requestbody::RequestBody;
+use chromenet::http::streamfactory::{HttpStreamFactory, HttpVersionPref, StreamBody};
+use chromenet::http::H2cMode;
+use chromenet::socket::hooks::{ConnectHooks, ConnectOutcome};
+use chromenet::socket::lifetime::ConnectionLifetime;
+use chromenet::socket::pool::ClientSocketPool;
+use chromenet::Client;
+use common::server::read_head;
+use http::{Request, Response};
+use http_body_util::BodyExt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use url::Url;
+
+/// DNS whose answer for every host can be changed, with all connects
+/// going to a local server.
+#[derive(Clone)]
+struct RotatingDns {
+    answer: Arc<Mutex<Result<Vec<IpAddr>, NetError>>>,
+    lookups: Arc<AtomicUsize>,
+    server: SocketAddr,
+}
+
+impl RotatingDns {
+    fn new(server: SocketAddr) -> Self {
+        Self {
+            answer: Arc::new(Mutex::new(Ok(vec!["192.0.2.1".parse().unwrap()]))),
+            lookups: Arc::new(AtomicUsize::new(0)),
+            server,
+        }
+    }
+
+    fn answer(&self, answer: Result<Vec<IpAddr>, NetError>) {
+        *self.answer.lock().unwrap() = answer;
+    }
+}
+
+impl ConnectHooks for RotatingDns {
+    fn resolve(&self, _host: &str) -> Option<Result<Vec<IpAddr>, NetError>> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        Some(self.answer.lock().unwrap().clone())
+    }
+
+    fn connect(&self, _addr: SocketAddr) -> ConnectOutcome {
+        ConnectOutcome::Redirect(self.server)
+    }
+}
+
+/// Keep-alive HTTP/1.1 server answering every request with the number of
+/// the connection it arrived on.
+async fn h1_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let id = connections.fetch_add(1, Ordering::SeqCst).to_string();
+            tokio::spawn(async move {
+                while read_head(&mut socket).await.is_some() {
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        id.len(),
+                        id
+                    );
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+/// HTTP/2 server answering every request with the number of the
+/// connection it arrived on.
+async fn h2_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let id = connections.fetch_add(1, Ordering::SeqCst).to_string();
+            tokio::spawn(async move {
+                let mut conn = http2::server::handshake(socket).await.unwrap();
+                while let Some(Ok((_req, mut respond))) = conn.accept().await {
+                    let response = Response::builder().status(200).body(()).unwrap();
+                    let mut send = respond.send_response(response, false).unwrap();
+                    send.send_data(Bytes::from(id.clone()), true).unwrap();
+                }
+            });
+        }
+    });
+    addr
+}
+
+/// Send a GET over a fresh stream and return (reused, connection number).
+async fn get(factory: &HttpStreamFactory, url: &Url) -> (bool, String) {
+    let mut stream = factory
+        .create_stream(url, None, None, None, None, HttpVersionPref::Auto)
+        .await
+        .unwrap();
+    let reused = stream.is_reused();
+    let request = Request::get(url.path())
+        .header("host", url.authority())
+        .body(RequestBody::Empty.into())
+        .unwrap();
+    let response = stream.send_request(request).await.unwrap();
+    let StreamBody::H1(body) = response.into_body() else {
+        panic!("unexpected HTTP/2");
+    };
+    let body = body.collect().await.unwrap().to_bytes();
+    (reused, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// Wait until the connection task has returned its socket.
+async fn wait_idle(pool: &ClientSocketPool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while pool.idle_socket_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("socket was not returned to the pool");
+}
+
+fn h1_factory(
+    dns: &RotatingDns,
+    lifetime: ConnectionLifetime,
+) -> (Arc<ClientSocketPool>, HttpStreamFactory) {
+    let pool = ClientSocketPool::new(None)
+        .with_connect_hooks(Arc::new(dns.clone()))
+        .with_connection_lifetime(lifetime);
+    let pool = Arc::new(pool);
+    (pool.clone(), HttpStreamFactory::new(pool))
+}
+
+#[tokio::test]
+async fn test_idle_socket_retired_when_address_leaves_dns() {
+    let dns = RotatingDns::new(h1_server().await);
+    let (pool, factory) = h1_factory(&dns, ConnectionLifetime::new().with_dns_ttl(Duration::ZERO));
+    let url = Url::parse(&format!("http://cdn.test:{}/", dns.server.port())).unwrap();
+
+    assert_eq!(get(&factory, &url).await, (false, "0".to_string()));
+    wait_idle(&pool).await;
+    // Still in the answer: reused after a second lookup
+    assert_eq!(get(&factory, &url).await, (true, "0".to_string()));
+    assert_eq!(dns.lookups.load(Ordering::SeqCst), 2);
+    wait_idle(&pool).await;
+
+    // The CDN moved: the idle socket is dropped for a new connection
+    dns.answer(Ok(vec!["192.0.2.2".parse().unwrap()]));
+    assert_eq!(get(&factory, &url).await, (false, "1".to_string()));
+}
+
+#[tokio::test]
+async fn test_idle_socket_kept_when_lookup_fails() {
+    let dns = RotatingDns::new(h1_server().await);
+    let (pool, factory) = h1_factory(&dns, ConnectionLifetime::new().with_dns_ttl(Duration::ZERO));
+    let url = Url::parse(&format!("http://cdn.test:{}/", dns.server.port())).unwrap();
+
+    assert_eq!(get(&factory, &url).await, (false, "0".to_string()));
+    wait_idle(&pool).await;
+    dns.answer(Err(NetError::NameNotResolved));
+    assert_eq!(get(&factory, &url).await, (true, "0".to_string()));
+}
+
+#[tokio::test]
+async fn test_idle_socket_not_resolved_within_ttl() {
+    let dns = RotatingDns::new(h1_server().await);
+    let (pool, factory) = h1_factory(
+        &dns,
+        ConnectionLifetime::new().with_dns_ttl(Duration::from_secs(3600)),
+    );
+    let url = Url::parse(&format!("http://cdn.test:{}/", dns.server.port())).unwrap();
+
+    assert_eq!(get(&factory, &url).await, (false, "0".to_string()));
+    wait_idle(&pool).await;
+    dns.answer(Ok(vec!["192.0.2.2".parse().unwrap()]));
+    assert_eq!(get(&factory, &url).await, (true, "0".to_string()));
+    assert_eq!(dns.lookups.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_idle_socket_retired_after_max_age() {
+    let dns = RotatingDns::new(h1_server().await);
+    let (pool, factory) = h1_factory(
+        &dns,
+        ConnectionLifetime::new().with_max_age(Duration::from_millis(500)),
+    );
+    let url = Url::parse(&format!("http://cdn.test:{}/", dns.server.port())).unwrap();
+
+    assert_eq!(get(&factory, &url).await, (false, "0".to_string()));
+    wait_idle(&pool).await;
+    assert_eq!(get(&factory, &url).await, (true, "0".to_string()));
+    wait_idle(&pool).await;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    pool.cleanup_idle_sockets();
+    assert_eq!(pool.idle_socket_count(), 0);
+    assert_eq!(get(&factory, &url).await, (false, "1".to_string()));
+}
+
+#[tokio::test]
+async fn test_h2_session_retired_when_address_leaves_dns() {
+    let dns = RotatingDns::new(h2_server().await);
+    let client = Client::builder()
+        .h2c(H2cMode::PriorKnowledge)
+        .connect_hooks(dns.clone())
+        .connection_lifetime(ConnectionLifetime::new().with_dns_ttl(Duration::ZERO))
+        .build();
+    let url = format!("http://cdn.test:{}/", dns.server.port());
+    let get = || async { client.get(&url).send().await.unwrap().text().await.unwrap() };
+
+    assert_eq!(get().await, "0");
+    assert_eq!(get().await, "0");
+
+    dns.answer(Ok(vec!["192.0.2.2".parse().unwrap()]));
+    assert_eq!(get().await, "1");
+    // The new connection was made to the new address
+    assert_eq!(get().await, "1");
+}
+
+#[tokio::test]
+async fn test_socket_retired_after_max_requests() {
+    let dns = RotatingDns::new(h1_server().await);
+    let (pool, factory) = h1_factory(&dns, ConnectionLifetime::new().with_max_requests(2));
+    let url = Url::parse(&format!("http://cdn.test:{}/", dns.server.port())).unwrap();
+
+    assert_eq!(get(&factory, &url).await, (false, "0".to_string()));
+    wait_idle(&pool).await;
+    assert_eq!(get(&factory, &url).await, (true, "0".to_string()));
+    assert_eq!(get(&factory, &url).await, (false, "1".to_string()));
+}
+
+#[tokio::test]
+async fn test_idle_socket_times_out() {
+    let dns = RotatingDns::new(h1_server().await);
+    let (pool, factory) = h1_factory(
+        &dns,
+        ConnectionLifetime::new().with_idle_timeout(Duration::from_millis(100)),
+    );
+    let url = Url::parse(&format!("http://cdn.test:{}/", dns.server.port())).unwrap();
+
+    assert_eq!(get(&factory, &url).await, (false, "0".to_string()));
+    wait_idle(&pool).await;
+    assert_eq!(get(&factory, &url).await, (true, "0".to_string()));
+    wait_idle(&pool).await;
+
+    // Not reused even before the cleanup task runs
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(get(&factory, &url).await, (false, "1".to_string()));
+}
+
+#[tokio::test]
+async fn test_h2_session_retired_after_max_requests_and_idle_timeout() {
+    let dns = RotatingDns::new(h2_server().await);
+    let client = Client::builder()
+        .h2c(H2cMode::PriorKnowledge)
+        .connect_hooks(dns.clone())
+        .connection_lifetime(
+            ConnectionLifetime::new()
+                .with_max_requests(2)
+                .with_idle_timeout(Duration::from_millis(200)),
+        )
+        .build();
+    let url = format!("http://cdn.test:{}/", dns.server.port());
+    let get = || async { client.get(&url).send().await.unwrap().text().await.unwrap() };
+
+    assert_eq!(get().await, "0");
+    assert_eq!(get().await, "0");
+    assert_eq!(get().await, "1");
+
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(get().await, "2");
+}
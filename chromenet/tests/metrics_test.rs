@@ -0,0 +1,33 @@
+//! Client metrics recorded against the local httpbin-like server.
+
+mod common;
+
+use chromenet::metrics::{ClientMetrics, StatusClass};
+use chromenet::Client;
+use common::httpbin::HttpBin;
+
+#[tokio::test]
+async fn test_requests_and_connects_are_recorded() {
+    let bin = HttpBin::start().await;
+    let metrics = ClientMetrics::new();
+    let client = Client::builder().metrics(metrics.clone()).build();
+
+    for path in ["/status/200", "/status/404", "/status/503"] {
+        client.get(bin.url(path)).send().await.unwrap();
+    }
+    // Nothing listens on port 9 of the loopback address
+    assert!(client.get("http://127.0.0.1:9/").send().await.is_err());
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.requests(StatusClass::Success), 1);
+    assert_eq!(snapshot.requests(StatusClass::ClientError), 1);
+    assert_eq!(snapshot.requests(StatusClass::ServerError), 1);
+    assert_eq!(snapshot.requests(StatusClass::Error), 1);
+    assert_eq!(snapshot.request_duration.count, 4);
+
+    // Plain HTTP: connections without TLS, one pool request per response
+    assert!(snapshot.dns_duration.count >= 1);
+    assert_eq!(snapshot.connect_duration.count, snapshot.dns_duration.count);
+    assert_eq!(snapshot.tls_duration.count, 0);
+    assert_eq!(snapshot.pool_wait.count, 3);
+}
@@ -1,5 +1,11 @@
 use chromenet::base::neterror::NetError;
-use chromenet::socket::pool::ClientSocketPool;
+use chromenet::base::networkisolationkey::NetworkIsolationKey;
+use chromenet::dns::{Addrs, HostCache, Name, Resolve, Resolving};
+use chromenet::socket::pool::{ClientSocketPool, ConnectionReuse, GroupId, RequestPriority};
+use chromenet::socket::proxy::ProxySettings;
+use chromenet::socket::tls::ServerName;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use tokio::net::TcpListener;
 use url::Url;
 
@@ -45,3 +51,193 @@ async fn test_pool_limits() {
     let result = pool.request_socket(&url, None).await;
     assert!(result.is_ok(), "Should succeed after release");
 }
+
+#[tokio::test]
+async fn test_idle_sockets_partitioned_by_isolation_key() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let url = Url::parse(&format!("http://127.0.0.1:{}/", port)).unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+
+    let pool = ClientSocketPool::new(None);
+    let site_a = NetworkIsolationKey::from_top_frame_url(&Url::parse("https://a.test/").unwrap());
+    let site_b = NetworkIsolationKey::from_top_frame_url(&Url::parse("https://b.test/").unwrap());
+
+    let first = pool
+        .request_socket_isolated(&url, None, site_a.as_ref(), None)
+        .await
+        .unwrap();
+    assert!(!first.is_reused);
+    pool.release_socket_to_group(&first.group_id, first.socket, false);
+
+    // Another site must not pick up site A's idle socket
+    let other = pool
+        .request_socket_isolated(&url, None, site_b.as_ref(), None)
+        .await
+        .unwrap();
+    assert!(!other.is_reused);
+    let unpartitioned = pool.request_socket(&url, None).await.unwrap();
+    assert!(!unpartitioned.is_reused);
+
+    let again = pool
+        .request_socket_isolated(&url, None, site_a.as_ref(), None)
+        .await
+        .unwrap();
+    assert!(again.is_reused);
+}
+
+#[tokio::test]
+async fn test_fresh_socket_skips_idle_sockets() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let url = Url::parse(&format!("http://127.0.0.1:{}/", port)).unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+
+    let pool = ClientSocketPool::new(None);
+    let group_id = GroupId::new(&url, None, None).unwrap();
+    let request = |reuse| {
+        pool.request_socket_for_group(
+            &group_id,
+            &url,
+            None,
+            None,
+            RequestPriority::default(),
+            reuse,
+        )
+    };
+
+    let first = request(ConnectionReuse::Allowed).await.unwrap();
+    pool.release_socket_to_group(&group_id, first.socket, false);
+    assert_eq!(pool.idle_socket_count(), 1);
+
+    let fresh = request(ConnectionReuse::Fresh).await.unwrap();
+    assert!(!fresh.is_reused);
+    assert_eq!(pool.idle_socket_count(), 1);
+    let reused = request(ConnectionReuse::Allowed).await.unwrap();
+    assert!(reused.is_reused);
+    pool.release_socket_to_group(&group_id, reused.socket, false);
+
+    // At the group limit, an idle socket is closed to make room
+    let mut held = vec![fresh.socket];
+    for _ in 0..4 {
+        held.push(request(ConnectionReuse::Fresh).await.unwrap().socket);
+    }
+    assert_eq!(pool.total_active_count(), 5);
+    assert_eq!(pool.idle_socket_count(), 1);
+    let fresh = request(ConnectionReuse::Fresh).await.unwrap();
+    assert!(!fresh.is_reused);
+    assert_eq!(pool.idle_socket_count(), 0);
+    assert_eq!(pool.total_active_count(), 6);
+}
+
+/// Answers every name with 127.0.0.1, counting lookups.
+#[derive(Default)]
+struct Loopback(AtomicUsize);
+
+impl Resolve for Loopback {
+    fn resolve(&self, _name: Name) -> Resolving {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        let addrs: Addrs = Box::new(std::iter::once("127.0.0.1:0".parse().unwrap()));
+        Box::pin(std::future::ready(Ok(addrs)))
+    }
+}
+
+#[tokio::test]
+async fn test_flush_host() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+
+    let lookups = Arc::new(Loopback::default());
+    let cache = HostCache::new(lookups.clone());
+    let pool = ClientSocketPool::new(None).with_resolver(Arc::new(cache.clone()));
+    let a = Url::parse(&format!("http://a.test:{port}/")).unwrap();
+    let b = Url::parse(&format!("http://b.test:{port}/")).unwrap();
+
+    let idle = pool.request_socket(&a, None).await.unwrap();
+    let busy = pool.request_socket(&a, None).await.unwrap();
+    let other = pool.request_socket(&b, None).await.unwrap();
+    pool.release_socket_to_group(&idle.group_id, idle.socket, false);
+    pool.release_socket_to_group(&other.group_id, other.socket, false);
+    assert_eq!(cache.len(), 2);
+
+    assert_eq!(pool.flush_host("A.test"), 1);
+    assert_eq!(pool.idle_socket_count(), 1);
+    assert_eq!(cache.len(), 1);
+
+    // The socket in use is closed once released
+    pool.release_socket_to_group(&busy.group_id, busy.socket, false);
+    assert_eq!(pool.idle_socket_count(), 1);
+    assert_eq!(pool.total_active_count(), 0);
+
+    // Later connections look the host up again and are pooled
+    let fresh = pool.request_socket(&a, None).await.unwrap();
+    assert!(!fresh.is_reused);
+    assert_eq!(lookups.0.load(Ordering::SeqCst), 3);
+    pool.release_socket_to_group(&fresh.group_id, fresh.socket, false);
+    assert_eq!(pool.idle_socket_count(), 2);
+
+    assert_eq!(pool.flush_all(), 2);
+    assert!(cache.is_empty());
+    assert!(!pool.is_closed());
+}
+
+#[test]
+fn test_group_id_includes_proxy_and_isolation_key() {
+    let url = Url::parse("https://example.com/").unwrap();
+    let direct = GroupId::new(&url, None, None).unwrap();
+    let proxy_a = ProxySettings::new("http://proxy-a.test:8080").unwrap();
+    let proxy_b = ProxySettings::new("http://proxy-b.test:8080").unwrap();
+
+    let via_a = GroupId::new(&url, Some(&proxy_a), None).unwrap();
+    assert_eq!(via_a.proxy(), Some("http://proxy-a.test:8080"));
+    assert_ne!(direct, via_a);
+    assert_ne!(via_a, GroupId::new(&url, Some(&proxy_b), None).unwrap());
+
+    // Proxies commonly pick the exit or session from the username
+    let session_1 = proxy_a.clone().with_auth("session-1", "pw");
+    let session_2 = proxy_a.clone().with_auth("session-2", "pw");
+    assert_ne!(
+        GroupId::new(&url, Some(&session_1), None),
+        GroupId::new(&url, Some(&session_2), None)
+    );
+
+    let nik = NetworkIsolationKey::opaque("session");
+    let isolated = GroupId::new(&url, None, Some(&nik)).unwrap();
+    assert_ne!(direct, isolated);
+    assert_eq!(isolated.network_isolation_key(), Some(&nik));
+    assert_eq!(isolated.host(), "example.com");
+    assert_eq!(isolated.port(), 443);
+}
+
+#[test]
+fn test_group_id_includes_server_name() {
+    let url = Url::parse("https://example.com/").unwrap();
+    let direct = GroupId::new(&url, None, None).unwrap();
+    assert_eq!(direct.server_name(), &ServerName::Host);
+
+    let fronted = direct
+        .clone()
+        .with_server_name(ServerName::custom("front.test"));
+    assert_ne!(direct, fronted);
+    assert_ne!(
+        fronted,
+        direct.clone().with_server_name(ServerName::Disabled)
+    );
+    assert_eq!(direct, direct.clone().with_server_name(ServerName::Host));
+}
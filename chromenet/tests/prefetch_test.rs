@@ -0,0 +1,139 @@
+//! Background prefetching: registered URLs are fetched into the cache and
+//! revalidated before their entries expire.
+
+mod common;
+
+use chromenet::http::{HttpCache, PrefetchRound};
+use chromenet::Client;
+use common::server::read_head;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Server answering every path with `max-age=60` and an ETag, and `304`
+/// to a matching `If-None-Match`. Returns the address and the request
+/// heads it received.
+async fn origin() -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let seen = heads.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                while let Some(head) = read_head(&mut socket).await {
+                    let head = head.to_ascii_lowercase();
+                    let response = if head.contains("if-none-match: \"v1\"") {
+                        "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nCache-Control: max-age=60\r\n\r\n"
+                    } else {
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nCache-Control: max-age=60\r\nContent-Length: 2\r\n\r\nv1"
+                    };
+                    seen.lock().unwrap().push(head);
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    (addr, heads)
+}
+
+fn url(addr: SocketAddr, path: &str) -> Url {
+    Url::parse(&format!("http://{}{}", addr, path)).unwrap()
+}
+
+#[tokio::test]
+async fn test_round_fetches_then_skips_fresh_entries() {
+    let (addr, heads) = origin().await;
+    let client = Client::builder().cache(HttpCache::new()).build();
+    let prefetcher = client
+        .prefetcher()
+        .urls([url(addr, "/a"), url(addr, "/b")])
+        .refresh_ahead(Duration::from_secs(10))
+        .spacing(Duration::ZERO);
+
+    let round = prefetcher.run_once().await;
+    assert_eq!(
+        round,
+        PrefetchRound {
+            fetched: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(client.cache().unwrap().len(), 2);
+
+    let round = prefetcher.run_once().await;
+    assert_eq!(round.fresh, 2);
+    assert_eq!(heads.lock().unwrap().len(), 2);
+
+    // Requests are answered from the warm cache
+    let body = client
+        .get(url(addr, "/a"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, "v1");
+    assert_eq!(heads.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_entries_about_to_expire_are_revalidated() {
+    let (addr, heads) = origin().await;
+    let client = Client::builder().cache(HttpCache::new()).build();
+    // Every entry expires within the window
+    let prefetcher = client
+        .prefetcher()
+        .url(url(addr, "/a"))
+        .refresh_ahead(Duration::from_secs(120));
+
+    assert_eq!(prefetcher.run_once().await.fetched, 1);
+    assert_eq!(prefetcher.run_once().await.revalidated, 1);
+
+    let heads = heads.lock().unwrap();
+    assert_eq!(heads.len(), 2);
+    assert!(!heads[0].contains("if-none-match"));
+    assert!(heads[1].contains("if-none-match: \"v1\""));
+    let entry = client
+        .cache()
+        .unwrap()
+        .get(&url(addr, "/a"), "GET")
+        .unwrap();
+    assert_eq!(entry.body, "v1");
+}
+
+#[tokio::test]
+async fn test_background_rounds_until_dropped() {
+    let (addr, heads) = origin().await;
+    let client = Client::builder().cache(HttpCache::new()).build();
+    let prefetcher = client
+        .prefetcher()
+        .url(url(addr, "/a"))
+        .interval(Duration::from_millis(20))
+        .jitter(Duration::from_millis(10))
+        .refresh_ahead(Duration::from_secs(120))
+        .spacing(Duration::ZERO)
+        .start();
+    assert!(prefetcher.is_running());
+
+    // Registered while running, picked up by the next round
+    prefetcher.register(url(addr, "/b"));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    drop(prefetcher);
+    // Let a request sent just before the drop arrive
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let seen = heads.lock().unwrap().clone();
+    assert!(seen.len() >= 4);
+    assert!(seen.iter().any(|h| h.starts_with("get /b ")));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(heads.lock().unwrap().len(), seen.len());
+}
@@ -0,0 +1,159 @@
+//! RFC 9218 `priority` request header: sent from the request's priority on
+//! the protocols the client's mode covers, never over one set explicitly.
+
+use bytes::Bytes;
+use chromenet::emulation::{Emulation, Http2Options};
+use chromenet::http::{H2Fingerprint, H2cMode, PriorityHeader};
+use chromenet::socket::pool::RequestPriority;
+use chromenet::Client;
+use http::Response;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// HTTP/2 server answering with the request's `priority`, `-` if none.
+async fn h2_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut conn = http2::server::handshake::<_, Bytes>(socket).await.unwrap();
+                while let Some(Ok((req, mut respond))) = conn.accept().await {
+                    let priority = req
+                        .headers()
+                        .get("priority")
+                        .map_or("-".to_string(), |v| v.to_str().unwrap().to_string());
+                    let response = Response::builder().status(200).body(()).unwrap();
+                    let mut send = respond.send_response(response, false).unwrap();
+                    send.send_data(Bytes::from(priority), true).unwrap();
+                }
+            });
+        }
+    });
+    url
+}
+
+/// HTTP/1.1 server answering with the request's `priority`, `-` if none.
+async fn h1_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            let head = String::from_utf8_lossy(&buf[..n]).to_string();
+            let priority = head
+                .lines()
+                .find_map(|line| line.strip_prefix("priority: "))
+                .unwrap_or("-")
+                .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                priority.len(),
+                priority
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    url
+}
+
+async fn priority_of(
+    client: &Client,
+    url: &str,
+    priority: RequestPriority,
+    incremental: bool,
+) -> String {
+    client
+        .get(url)
+        .priority(priority)
+        .priority_incremental(incremental)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_multiplexed_sends_on_h2_only() {
+    let h2 = h2_server().await;
+    let client = Client::builder()
+        .h2c(H2cMode::PriorKnowledge)
+        .priority_header(PriorityHeader::Multiplexed)
+        .build();
+    assert_eq!(
+        priority_of(&client, &h2, RequestPriority::Highest, true).await,
+        "u=0, i"
+    );
+    assert_eq!(
+        priority_of(&client, &h2, RequestPriority::Medium, false).await,
+        "u=1"
+    );
+    // All defaults: nothing to send
+    assert_eq!(
+        priority_of(&client, &h2, RequestPriority::Lowest, false).await,
+        "-"
+    );
+
+    let h1 = h1_server().await;
+    let client = Client::builder()
+        .priority_header(PriorityHeader::Multiplexed)
+        .build();
+    assert_eq!(
+        priority_of(&client, &h1, RequestPriority::Highest, true).await,
+        "-"
+    );
+}
+
+#[tokio::test]
+async fn test_always_sends_on_h1() {
+    let h1 = h1_server().await;
+    let client = Client::builder()
+        .priority_header(PriorityHeader::Always)
+        .build();
+    assert_eq!(
+        priority_of(&client, &h1, RequestPriority::Idle, false).await,
+        "u=4"
+    );
+
+    // An explicit header is sent as is
+    let text = client
+        .get(&h1)
+        .header("priority", "u=7")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text, "u=7");
+}
+
+#[tokio::test]
+async fn test_auto_follows_rfc7540_setting() {
+    let h2 = h2_server().await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    assert_eq!(
+        priority_of(&client, &h2, RequestPriority::Highest, false).await,
+        "-"
+    );
+
+    // Firefox's HTTP/2 settings turn RFC 7540 priorities off
+    let fingerprint = H2Fingerprint::firefox();
+    assert_eq!(fingerprint.no_rfc7540_priorities, Some(true));
+    let emulation = Emulation::builder()
+        .http2_options(Http2Options::builder().fingerprint(fingerprint).build())
+        .build();
+    let client = Client::builder()
+        .emulation(emulation)
+        .h2c(H2cMode::PriorKnowledge)
+        .build();
+    assert_eq!(
+        priority_of(&client, &h2, RequestPriority::Highest, false).await,
+        "u=0"
+    );
+}
@@ -0,0 +1,70 @@
+//! Query parameter tests against a local HTTP/1.1 server.
+
+use chromenet::base::neterror::NetError;
+use chromenet::Client;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// Serve one request and hand back its request line.
+async fn capture_server() -> (String, oneshot::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        let head = String::from_utf8_lossy(&buf[..n]).into_owned();
+        let _ = tx.send(head.lines().next().unwrap_or_default().to_string());
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .unwrap();
+    });
+
+    (url, rx)
+}
+
+#[derive(Serialize)]
+struct Search {
+    q: &'static str,
+    page: Option<u32>,
+    sort: Option<&'static str>,
+}
+
+#[tokio::test]
+async fn test_query_appends_to_url() {
+    let (url, request_line) = capture_server().await;
+    let client = Client::new();
+
+    client
+        .get(format!("{}/search?lang=en", url))
+        .query(&[("tag", "a b"), ("tag", "c&d")])
+        .query(&Search {
+            q: "ünïcode",
+            page: Some(2),
+            sort: None,
+        })
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        request_line.await.unwrap(),
+        "GET /search?lang=en&tag=a+b&tag=c%26d&q=%C3%BCn%C3%AFcode&page=2 HTTP/1.1"
+    );
+}
+
+#[tokio::test]
+async fn test_unserializable_query_fails() {
+    let client = Client::new();
+    let result = client
+        .get("http://127.0.0.1:1/")
+        .query("not a map")
+        .send()
+        .await;
+    assert!(matches!(result, Err(NetError::InvalidUrl)));
+}
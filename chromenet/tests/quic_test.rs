@@ -3,6 +3,7 @@
 //! Covers:
 //! - `QuicConfig` defaults and builder
 //! - `QuicConnection` API surface check
+//! - Racing QUIC against TCP from the client
 
 use chromenet::quic::{QuicConfig, QuicConnectionBuilder};
 use std::time::Duration;
@@ -42,3 +43,25 @@ fn test_quic_connection_builder_api() {
     // Only check API compilation, as connecting requires network
     let _builder = QuicConnectionBuilder::new();
 }
+
+#[tokio::test]
+async fn test_http3_races_and_remembers_failure() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = url::Url::parse(&format!("https://{}/", listener.local_addr().unwrap())).unwrap();
+    // Accept TCP, then hang up instead of completing TLS
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            drop(socket);
+        }
+    });
+
+    assert!(chromenet::Client::new().quic_outcomes().is_none());
+    let client = chromenet::Client::builder().http3(true).build();
+    let outcomes = client.quic_outcomes().unwrap();
+    assert!(outcomes.should_race(&url));
+
+    // TCP's error is reported, and QUIC is not raced to the origin again
+    let result = client.get(url.as_str()).send().await;
+    assert!(result.is_err());
+    assert!(!outcomes.should_race(&url));
+}
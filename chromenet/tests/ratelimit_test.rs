@@ -0,0 +1,65 @@
+//! Rate limit scheduling tests against a local HTTP/1.1 server.
+
+use chromenet::http::RateLimiter;
+use chromenet::Client;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Serve `responses` in order, one per connection.
+async fn scripted_server(responses: Vec<&'static str>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        for response in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    url
+}
+
+#[tokio::test]
+async fn test_retry_after_delays_next_request() {
+    let url = scripted_server(vec![
+        "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    ])
+    .await;
+    let limiter = RateLimiter::new();
+    let client = Client::builder().rate_limiter(limiter.clone()).build();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 429);
+    let origin = url::Url::parse(&url).unwrap();
+    assert!(limiter.delay(&origin).is_some());
+    assert_eq!(limiter.limited_origins().len(), 1);
+
+    let start = Instant::now();
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(start.elapsed() >= Duration::from_millis(900));
+    assert_eq!(limiter.delay(&origin), None);
+}
+
+#[tokio::test]
+async fn test_exhausted_quota_on_success_response() {
+    let url = scripted_server(vec![
+        "HTTP/1.1 200 OK\r\nRateLimit: limit=10, remaining=0, reset=30\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    ])
+    .await;
+    let client = Client::builder().rate_limiter(RateLimiter::new()).build();
+
+    client.get(&url).send().await.unwrap();
+
+    let delay = client
+        .rate_limiter()
+        .unwrap()
+        .delay(&url::Url::parse(&url).unwrap())
+        .unwrap();
+    assert!(delay > Duration::from_secs(29));
+}
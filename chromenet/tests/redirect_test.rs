@@ -1,3 +1,4 @@
+use chromenet::urlrequest::redirect::BodyDrain;
 use chromenet::urlrequest::request::URLRequest;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -224,3 +225,123 @@ async fn test_redirect_persists_headers_same_origin() {
         "Custom header should persist on same-origin redirect"
     );
 }
+
+#[tokio::test]
+async fn test_refresh_header_opt_in() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        loop {
+            if let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]);
+
+                    let response = if request.starts_with("POST /start") {
+                        "HTTP/1.1 200 OK\r\nRefresh: 0; url=/target\r\nContent-Length: 5\r\nConnection: close\r\n\r\nstart"
+                    } else if request.starts_with("GET /slow") {
+                        "HTTP/1.1 200 OK\r\nRefresh: 5; url=/target\r\nContent-Length: 4\r\nConnection: close\r\n\r\nslow"
+                    } else if request.starts_with("GET /target") {
+                        "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\ntarget"
+                    } else {
+                        "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    };
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        }
+    });
+
+    // Off by default
+    let client = chromenet::Client::new();
+    let response = client
+        .post(&format!("{}/start", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), "start");
+
+    // Followed as a GET once enabled, but only without a delay
+    let client = chromenet::Client::builder().follow_refresh(true).build();
+    let response = client
+        .post(&format!("{}/start", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.url().unwrap().path(), "/target");
+    assert_eq!(response.text().await.unwrap(), "target");
+
+    let response = client
+        .get(&format!("{}/slow", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), "slow");
+}
+
+#[tokio::test]
+async fn test_redirect_body_drained_up_to_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+
+    tokio::spawn(async move {
+        loop {
+            if let Ok((mut socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    while let Ok(n @ 1..) = socket.read(&mut buf).await {
+                        let request = String::from_utf8_lossy(&buf[..n]);
+                        let response = if request.starts_with("GET /small") {
+                            "HTTP/1.1 302 Found\r\nLocation: /target\r\nContent-Length: 10\r\n\r\nmoved here".to_string()
+                        } else if request.starts_with("GET /large") {
+                            let body = "x".repeat(64 * 1024);
+                            format!(
+                                "HTTP/1.1 302 Found\r\nLocation: /target\r\nContent-Length: {}\r\n\r\n{}",
+                                body.len(),
+                                body
+                            )
+                        } else {
+                            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\ntarget".to_string()
+                        };
+                        if socket.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        }
+    });
+
+    // A small body is read, and the target comes over the same connection
+    let client = chromenet::Client::new();
+    let response = client
+        .get(&format!("{}/small", base_url))
+        .send()
+        .await
+        .unwrap();
+    let hops = response.redirects();
+    assert_eq!(hops.len(), 1);
+    assert_eq!(hops[0].url.path(), "/small");
+    assert_eq!(hops[0].status, 302);
+    assert_eq!(hops[0].drain, BodyDrain::Drained { bytes: 10 });
+    assert_eq!(response.text().await.unwrap(), "target");
+    assert_eq!(connections.load(Ordering::Relaxed), 1);
+
+    // A body over the limit costs the connection
+    let client = chromenet::Client::builder()
+        .max_redirect_drain(1024)
+        .build();
+    let response = client
+        .get(&format!("{}/large", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.redirects()[0].drain, BodyDrain::Discarded);
+    assert_eq!(response.text().await.unwrap(), "target");
+    assert_eq!(connections.load(Ordering::Relaxed), 3);
+}
@@ -101,3 +101,28 @@ async fn test_retry_on_reused_socket_failure() {
     let resp = trans.get_response().unwrap();
     assert_eq!(resp.status(), 200);
 }
+
+#[tokio::test]
+async fn test_connect_failure_reports_each_attempt() {
+    use chromenet::base::neterror::NetError;
+
+    // Bind then drop to get a port nobody listens on
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let result = chromenet::Client::new()
+        .get(format!("http://{}/", addr))
+        .send()
+        .await;
+
+    let Err(NetError::ConnectionAttemptsFailed { port, attempts, .. }) = result else {
+        panic!("expected aggregated connection attempts");
+    };
+    assert_eq!(port, addr.port());
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0].address, addr);
+    assert!(matches!(attempts[0].error, NetError::ConnectionRefused));
+}
@@ -0,0 +1,240 @@
+//! Request body replay tests against a local HTTP/1.1 server.
+
+use bytes::Bytes;
+use chromenet::base::neterror::NetError;
+use chromenet::http::RewindableBody;
+use chromenet::Client;
+use futures::stream;
+use std::net::SocketAddr;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// A request as seen by the server.
+#[derive(Debug)]
+struct Received {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Serve `/start` with `redirect_status` to `/next`, and `/next` with 200.
+/// Each connection carries one request.
+async fn redirect_server(redirect_status: u16) -> (SocketAddr, mpsc::UnboundedReceiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(socket);
+                let Some(received) = read_request(&mut reader).await else {
+                    return;
+                };
+                let response = if received.path == "/start" {
+                    format!(
+                        "HTTP/1.1 {} Redirect\r\nLocation: /next\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        redirect_status
+                    )
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                        .to_string()
+                };
+                let _ = tx.send(received);
+                let _ = reader.get_mut().write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    (addr, rx)
+}
+
+/// Read one request, decoding a Content-Length or chunked body.
+async fn read_request<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Option<Received> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut content_length = 0;
+    let mut chunked = false;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await.ok()?;
+        let header = line.trim_end().to_ascii_lowercase();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("content-length:") {
+            content_length = value.trim().parse().ok()?;
+        }
+        if header == "transfer-encoding: chunked" {
+            chunked = true;
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await.ok()?;
+            let size = usize::from_str_radix(line.trim_end(), 16).ok()?;
+            let mut chunk = vec![0u8; size + 2];
+            reader.read_exact(&mut chunk).await.ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body).await.ok()?;
+    }
+
+    Some(Received { method, path, body })
+}
+
+fn chunks(
+    parts: &[&'static str],
+) -> impl futures::Stream<Item = Result<Bytes, NetError>> + Send + 'static {
+    let parts: Vec<_> = parts
+        .iter()
+        .map(|p| Ok(Bytes::from_static(p.as_bytes())))
+        .collect();
+    stream::iter(parts)
+}
+
+#[tokio::test]
+async fn test_post_sends_method_and_body() {
+    let (addr, mut rx) = redirect_server(307).await;
+
+    let resp = Client::new()
+        .post(format!("http://{}/next", addr))
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let received = rx.recv().await.unwrap();
+    assert_eq!(received.method, "POST");
+    assert_eq!(received.body, b"hello");
+}
+
+#[tokio::test]
+async fn test_307_replays_buffered_stream() {
+    let (addr, mut rx) = redirect_server(307).await;
+
+    let body = RewindableBody::buffered(chunks(&["chunk-1,", "chunk-2"]), 1024);
+    let resp = Client::new()
+        .post(format!("http://{}/start", addr))
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    for path in ["/start", "/next"] {
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received.path, path);
+        assert_eq!(received.method, "POST");
+        assert_eq!(received.body, b"chunk-1,chunk-2");
+    }
+}
+
+#[tokio::test]
+async fn test_308_reopens_factory_stream() {
+    let (addr, mut rx) = redirect_server(308).await;
+
+    let body = RewindableBody::from_factory(|| chunks(&["from ", "factory"]));
+    Client::new()
+        .put(format!("http://{}/start", addr))
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received.method, "PUT");
+        assert_eq!(received.body, b"from factory");
+    }
+}
+
+#[tokio::test]
+async fn test_307_buffer_exceeded_fails() {
+    let (addr, _rx) = redirect_server(307).await;
+
+    let body = RewindableBody::buffered(chunks(&["0123456789", "0123456789"]), 8);
+    let result = Client::new()
+        .post(format!("http://{}/start", addr))
+        .body(body)
+        .send()
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(NetError::UploadStreamRewindNotSupported)
+    ));
+}
+
+#[tokio::test]
+async fn test_303_drops_body() {
+    let (addr, mut rx) = redirect_server(303).await;
+
+    Client::new()
+        .post(format!("http://{}/start", addr))
+        .header("content-type", "text/plain")
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+
+    let first = rx.recv().await.unwrap();
+    assert_eq!(first.body, b"payload");
+
+    let second = rx.recv().await.unwrap();
+    assert_eq!(second.method, "GET");
+    assert!(second.body.is_empty());
+}
+
+#[tokio::test]
+async fn test_streaming_body_over_http2() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Echo the request body back
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake(socket).await.unwrap();
+        while let Some(Ok((req, mut respond))) = conn.accept().await {
+            tokio::spawn(async move {
+                let mut body = req.into_body();
+                let mut data = Vec::new();
+                while let Some(chunk) = body.data().await {
+                    let chunk = chunk.unwrap();
+                    let _ = body.flow_control().release_capacity(chunk.len());
+                    data.extend_from_slice(&chunk);
+                }
+                let response = http::Response::builder().status(200).body(()).unwrap();
+                let mut send = respond.send_response(response, false).unwrap();
+                send.send_data(Bytes::from(data), true).unwrap();
+            });
+        }
+    });
+
+    let body = RewindableBody::buffered(chunks(&["over ", "h2"]), 1024);
+    let resp = Client::new()
+        .post(format!("http://{}/", addr))
+        .http2_prior_knowledge()
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.version(), http::Version::HTTP_2);
+    assert_eq!(resp.text().await.unwrap(), "over h2");
+}
@@ -0,0 +1,213 @@
+//! Per-request cookie jars and SameSite cookies along redirect chains,
+//! against a local HTTP/1.1 server.
+
+use chromenet::cookies::monster::CookieMonster;
+use chromenet::socket::connectto::ConnectTo;
+use chromenet::Client;
+use http::Method;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Server that records request heads. `/start` sets a cookie and redirects
+/// to `/next`; every other path sets a cookie and returns 200.
+async fn cookie_server() -> (Url, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let seen = heads.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let response = if head.starts_with("get /start") {
+                    "HTTP/1.1 302 Found\r\nLocation: /next\r\nSet-Cookie: hop=1\r\nContent-Length: 0\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\nSet-Cookie: fresh=1\r\nContent-Length: 0\r\n\r\n"
+                };
+                seen.lock().unwrap().push(head);
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    (url, heads)
+}
+
+fn cookie_names(store: &CookieMonster) -> Vec<String> {
+    let mut names: Vec<_> = store.iter_all_cookies().map(|c| c.name).collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn test_no_cookies_neither_sends_nor_saves() {
+    let (url, heads) = cookie_server().await;
+    let client = Client::new();
+    client
+        .cookie_store()
+        .parse_and_save_cookie(&url, "session=abc");
+
+    client.get(url.as_str()).no_cookies().send().await.unwrap();
+
+    assert!(!heads.lock().unwrap()[0].contains("cookie:"));
+    assert_eq!(cookie_names(client.cookie_store()), ["session"]);
+}
+
+#[tokio::test]
+async fn test_explicit_cookie_store() {
+    let (url, heads) = cookie_server().await;
+    let client = Client::new();
+    client
+        .cookie_store()
+        .parse_and_save_cookie(&url, "session=client");
+
+    let jar = Arc::new(CookieMonster::new());
+    jar.parse_and_save_cookie(&url, "session=other");
+
+    client
+        .get(url.as_str())
+        .cookie_store(jar.clone())
+        .send()
+        .await
+        .unwrap();
+
+    assert!(heads.lock().unwrap()[0].contains("cookie: session=other"));
+    assert_eq!(cookie_names(&jar), ["fresh", "session"]);
+    assert_eq!(cookie_names(client.cookie_store()), ["session"]);
+}
+
+#[tokio::test]
+async fn test_incognito_keeps_cookies_within_redirect_chain() {
+    let (url, heads) = cookie_server().await;
+    let client = Client::new();
+    client
+        .cookie_store()
+        .parse_and_save_cookie(&url, "session=abc");
+
+    client
+        .get(url.join("/start").unwrap().as_str())
+        .incognito()
+        .send()
+        .await
+        .unwrap();
+
+    let heads = heads.lock().unwrap();
+    assert_eq!(heads.len(), 2);
+    assert!(!heads[0].contains("cookie:"));
+    assert!(heads[1].contains("cookie: hop=1"));
+    assert_eq!(cookie_names(client.cookie_store()), ["session"]);
+}
+
+/// Server for `site.test` and `other.test`, reached through the
+/// `connect_to` rules returned: `/bounce` redirects to
+/// `http://site.test/echo` with `status`, every other path returns 200.
+/// Records request heads. The client holds cookies for `site.test`.
+async fn two_site_server(status: u16) -> (Client, Vec<ConnectTo>, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let seen = heads.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let response = if head.contains(" /bounce ") {
+                    format!("HTTP/1.1 {status} Redirect\r\nLocation: http://site.test/echo\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                };
+                seen.lock().unwrap().push(head);
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    let rules = ["site.test", "other.test"]
+        .map(|host| ConnectTo::parse(&format!("{host}:80:127.0.0.1:{port}")).unwrap())
+        .to_vec();
+    let client = Client::new();
+    let site = Url::parse("http://site.test/").unwrap();
+    for line in [
+        "strict=1; SameSite=Strict",
+        "lax=1; SameSite=Lax",
+        "none=1; SameSite=None",
+    ] {
+        client.cookie_store().parse_and_save_cookie(&site, line);
+    }
+    (client, rules, heads)
+}
+
+fn sent_cookies(head: &str) -> Vec<&str> {
+    let line = head
+        .lines()
+        .find_map(|line| line.strip_prefix("cookie: "))
+        .unwrap_or_default();
+    let mut names: Vec<_> = line
+        .split("; ")
+        .filter_map(|pair| pair.split('=').next())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn test_same_site_cookies_per_redirect_hop() {
+    let (client, rules, heads) = two_site_server(302).await;
+    let get = |url| {
+        rules.iter().fold(client.get(url), |request, rule| {
+            request.connect_to(rule.clone())
+        })
+    };
+
+    get("http://site.test/echo").send().await.unwrap();
+    assert_eq!(
+        sent_cookies(&heads.lock().unwrap()[0]),
+        ["lax", "none", "strict"]
+    );
+
+    // Navigated to from another site: the chain left the site
+    get("http://other.test/bounce").send().await.unwrap();
+    let heads = heads.lock().unwrap();
+    assert!(sent_cookies(&heads[1]).is_empty());
+    assert_eq!(sent_cookies(&heads[2]), ["lax", "none"]);
+}
+
+#[tokio::test]
+async fn test_same_site_cookies_cross_site_unsafe_and_subresource() {
+    let (client, rules, heads) = two_site_server(307).await;
+    let request = |method, url| {
+        rules
+            .iter()
+            .fold(client.request(method, url), |request, rule| {
+                request.connect_to(rule.clone())
+            })
+    };
+
+    request(Method::POST, "http://other.test/bounce")
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(sent_cookies(&heads.lock().unwrap()[1]), ["none"]);
+
+    // A fetch made from other.test
+    request(Method::GET, "http://site.test/echo")
+        .site_for_cookies(Url::parse("http://other.test/page").unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(sent_cookies(&heads.lock().unwrap()[2]), ["none"]);
+}
@@ -0,0 +1,495 @@
+//! Request-level header override tests against a local HTTP/1.1 server.
+
+use chromenet::base::neterror::NetError;
+use chromenet::emulation::{Emulation, UaConsistency};
+use chromenet::http::orderedheaders::{CaseSensitiveHeaders, OrderedHeaderMap};
+use chromenet::http::{BodyDescriptor, HeaderLimits};
+use chromenet::tls::HstsStore;
+use chromenet::urlrequest::urllimits::UrlLimits;
+use chromenet::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// Serve one request and hand back the raw request head.
+async fn capture_server() -> (String, oneshot::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let (tx, rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let n = socket.read(&mut buf).await.unwrap();
+        let _ = tx.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .unwrap();
+    });
+
+    (url, rx)
+}
+
+/// Header names of a raw request head, in wire order.
+fn header_names(head: &str) -> Vec<&str> {
+    head.lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split(':').next())
+        .collect()
+}
+
+fn profile() -> Emulation {
+    Emulation::builder()
+        .header("user-agent", "Profile/1.0")
+        .header("accept", "text/html")
+        .header("accept-encoding", "gzip, deflate, br")
+        .build()
+}
+
+#[tokio::test]
+async fn test_remove_header_suppresses_profile_default() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder().emulation(profile()).build();
+
+    client
+        .get(&url)
+        .remove_header("Accept-Encoding")
+        .send()
+        .await
+        .unwrap();
+
+    let head = head.await.unwrap();
+    let names = header_names(&head);
+    assert!(names.contains(&"user-agent"));
+    assert!(!names.contains(&"accept-encoding"));
+}
+
+#[tokio::test]
+async fn test_ordered_headers_replace_profile() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder().emulation(profile()).build();
+
+    let mut headers = OrderedHeaderMap::new();
+    headers.insert("x-first", "1").unwrap();
+    headers.insert("user-agent", "Override/2.0").unwrap();
+    headers.insert("x-last", "3").unwrap();
+
+    client
+        .get(&url)
+        .ordered_headers(headers)
+        .send()
+        .await
+        .unwrap();
+
+    let head = head.await.unwrap();
+    let names: Vec<_> = header_names(&head)
+        .into_iter()
+        .filter(|n| *n != "host")
+        .collect();
+    assert_eq!(names, ["x-first", "user-agent", "x-last"]);
+    assert!(head.contains("user-agent: Override/2.0"));
+}
+
+#[tokio::test]
+async fn test_title_case_headers_on_http1() {
+    let (url, head) = capture_server().await;
+    let client = Client::new();
+
+    let mut headers = CaseSensitiveHeaders::new();
+    headers.insert("user-agent", "Test/1.0");
+    headers.insert("accept-language", "en-US");
+
+    client
+        .get(&url)
+        .title_case_headers(OrderedHeaderMap::try_from(&headers).unwrap())
+        .send()
+        .await
+        .unwrap();
+
+    let head = head.await.unwrap();
+    let names = header_names(&head);
+    assert!(names.contains(&"User-Agent"));
+    assert!(names.contains(&"Accept-Language"));
+}
+
+/// Header lines of a raw request head other than `host`, in wire order.
+fn header_lines(head: &str) -> Vec<&str> {
+    head.lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter(|line| !line.to_ascii_lowercase().starts_with("host:"))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_header_layers_override_in_place() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder()
+        .emulation(profile())
+        .default_header("Accept", "application/json")
+        .default_header("x-client", "1")
+        .build();
+
+    client
+        .get(&url)
+        .header("user-agent", "Request/3.0")
+        .header("x-request", "2")
+        .send()
+        .await
+        .unwrap();
+
+    let head = head.await.unwrap();
+    assert_eq!(
+        header_lines(&head),
+        [
+            "user-agent: Request/3.0",
+            "accept: application/json",
+            "accept-encoding: gzip, deflate, br",
+            "x-client: 1",
+            "x-request: 2",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_remove_default_header() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder()
+        .emulation(profile())
+        .remove_default_header("Accept-Encoding")
+        .remove_default_header("accept")
+        .build();
+
+    // The request can bring back a header the client removed
+    client
+        .get(&url)
+        .header("accept", "*/*")
+        .send()
+        .await
+        .unwrap();
+
+    let head = head.await.unwrap();
+    assert_eq!(
+        header_lines(&head),
+        ["user-agent: Profile/1.0", "accept: */*"]
+    );
+}
+
+#[tokio::test]
+async fn test_default_headers_with_title_case_override() {
+    let (url, head) = capture_server().await;
+    let mut defaults = http::HeaderMap::new();
+    defaults.insert("x-client", "1".parse().unwrap());
+    defaults.insert("accept-language", "de-DE".parse().unwrap());
+    let client = Client::builder()
+        .emulation(profile())
+        .default_headers(defaults)
+        .remove_default_header("accept-language")
+        .build();
+
+    let mut headers = OrderedHeaderMap::new();
+    headers.insert("accept-language", "en-US").unwrap();
+    headers.insert("user-agent", "Override/2.0").unwrap();
+
+    client
+        .get(&url)
+        .title_case_headers(headers)
+        .send()
+        .await
+        .unwrap();
+
+    // The request's ordered headers win over the client's defaults and
+    // removals, and keep their order in Title-Case
+    let head = head.await.unwrap();
+    assert_eq!(
+        header_lines(&head),
+        [
+            "Accept-Language: en-US",
+            "User-Agent: Override/2.0",
+            "X-Client: 1",
+        ]
+    );
+}
+
+const CHROME_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+fn chrome_profile() -> Emulation {
+    Emulation::builder()
+        .header("user-agent", CHROME_UA)
+        .header(
+            "sec-ch-ua",
+            r#""Chromium";v="124", "Google Chrome";v="124", "Not-A.Brand";v="99""#,
+        )
+        .header("sec-ch-ua-mobile", "?0")
+        .header("sec-ch-ua-platform", "\"Windows\"")
+        .header("accept", "text/html")
+        .build()
+}
+
+#[tokio::test]
+async fn test_user_agent_override_derives_client_hints() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder()
+        .emulation(chrome_profile())
+        .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/128.0.0.0 Safari/537.36")
+        .build();
+
+    client.get(&url).send().await.unwrap();
+
+    let head = head.await.unwrap();
+    let lines = header_lines(&head);
+    assert_eq!(
+        lines[1],
+        r#"sec-ch-ua: "Chromium";v="128", "Not;A=Brand";v="24", "Google Chrome";v="128""#
+    );
+    assert_eq!(lines[3], "sec-ch-ua-platform: \"Linux\"");
+}
+
+#[tokio::test]
+async fn test_ua_consistency_derive_rewrites_hints() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder()
+        .emulation(chrome_profile())
+        .ua_consistency(UaConsistency::Derive)
+        .build();
+
+    // Only the User-Agent is replaced; the hints follow it
+    client
+        .get(&url)
+        .header("user-agent", "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36")
+        .send()
+        .await
+        .unwrap();
+
+    let head = head.await.unwrap();
+    let lines = header_lines(&head);
+    assert_eq!(lines[2], "sec-ch-ua-mobile: ?1");
+    assert_eq!(lines[3], "sec-ch-ua-platform: \"Android\"");
+}
+
+#[tokio::test]
+async fn test_ua_consistency_strict_rejects_engine_mismatch() {
+    let client = Client::builder()
+        .emulation(chrome_profile())
+        .ua_consistency(UaConsistency::Strict)
+        .build();
+
+    let result = client
+        .get("http://127.0.0.1:1/")
+        .user_agent(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:129.0) Gecko/20100101 Firefox/129.0",
+        )
+        .send()
+        .await;
+    match result {
+        Err(NetError::InconsistentIdentity { reason }) => {
+            assert!(reason.contains("Gecko"), "{}", reason)
+        }
+        other => panic!("expected InconsistentIdentity, got {:?}", other.err()),
+    }
+}
+
+#[cfg(feature = "emulation-profiles")]
+#[tokio::test]
+async fn test_impersonate_sends_consistent_identity() {
+    use chromenet::emulation::ImpersonateOs;
+    use chromenet::socket::tls::ImpersonateTarget;
+
+    let (url, head) = capture_server().await;
+    let client = Client::impersonate(ImpersonateTarget::Chrome124.with_os(ImpersonateOs::MacOS));
+
+    client.get(&url).send().await.unwrap();
+
+    let head = head.await.unwrap().to_lowercase();
+    assert!(head.contains("user-agent: mozilla/5.0 (macintosh;"));
+    assert!(head.contains("chrome/124.0.0.0"));
+    assert!(head.contains("sec-ch-ua-platform: \"macos\""));
+}
+
+#[tokio::test]
+async fn test_line_break_in_header_value_fails_request() {
+    let result = Client::new()
+        .get("http://127.0.0.1:9/")
+        .header("x-id", "1\r\nX-Injected: 1")
+        .send()
+        .await;
+    assert!(matches!(result, Err(NetError::InvalidHeader)));
+}
+
+#[tokio::test]
+async fn test_header_limits() {
+    let client = Client::builder()
+        .header_limits(HeaderLimits::new(1024, 64))
+        .build();
+
+    let (url, head) = capture_server().await;
+    client
+        .get(&url)
+        .header("x-small", "a".repeat(64))
+        .send()
+        .await
+        .unwrap();
+    assert!(head.await.unwrap().contains("x-small: aaaa"));
+
+    // Checked before connecting, so no server is needed
+    let result = client
+        .get("http://127.0.0.1:9/")
+        .header("x-large", "a".repeat(65))
+        .send()
+        .await;
+    assert!(matches!(result, Err(NetError::InvalidHeader)));
+
+    let mut request = client.get("http://127.0.0.1:9/");
+    for i in 0..20 {
+        request = request.header(
+            http::HeaderName::try_from(format!("x-{}", i)).unwrap(),
+            "a".repeat(60),
+        );
+    }
+    assert!(matches!(request.send().await, Err(NetError::InvalidHeader)));
+}
+
+#[tokio::test]
+async fn test_url_limits() {
+    let client = Client::builder()
+        .url_limits(UrlLimits::new(1024, 128))
+        .build();
+
+    let (url, head) = capture_server().await;
+    let path = "a".repeat(100);
+    client.get(format!("{url}{path}")).send().await.unwrap();
+    assert!(head
+        .await
+        .unwrap()
+        .starts_with(&format!("GET /{path} HTTP/1.1")));
+
+    // Checked before connecting, so no server is needed
+    let result = client
+        .get(format!("http://127.0.0.1:9/{}", "a".repeat(120)))
+        .send()
+        .await;
+    assert!(matches!(
+        result,
+        Err(NetError::RequestLineTooLong { limit: 128, .. })
+    ));
+
+    let result = client
+        .get(format!("http://127.0.0.1:9/#{}", "a".repeat(1024)))
+        .send()
+        .await;
+    assert!(matches!(
+        result,
+        Err(NetError::UrlTooLong { limit: 1024, .. })
+    ));
+
+    // Chromium's 2 MiB cap by default
+    let result = Client::new()
+        .get(format!(
+            "http://127.0.0.1:9/?q={}",
+            "a".repeat(2 * 1024 * 1024)
+        ))
+        .send()
+        .await;
+    assert!(matches!(result, Err(NetError::UrlTooLong { .. })));
+}
+
+#[tokio::test]
+async fn test_build_parts_matches_wire() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder()
+        .emulation(profile())
+        .default_header("x-client", "1")
+        .build();
+    client
+        .cookie_store()
+        .parse_and_save_cookie(&url::Url::parse(&url).unwrap(), "session=abc");
+
+    let request = client
+        .get(&url)
+        .query(&[("q", "1")])
+        .header("x-request", "2");
+    let parts = request.build_parts().await.unwrap();
+    assert_eq!(parts.header("cookie").unwrap(), "session=abc");
+    assert_eq!(parts.body, BodyDescriptor::Empty);
+    // Resolving again gives the same request
+    assert_eq!(
+        parts.to_string(),
+        request.build_parts().await.unwrap().to_string()
+    );
+
+    request.send().await.unwrap();
+    let head = head.await.unwrap();
+    assert_eq!(
+        head.to_ascii_lowercase(),
+        parts.to_string().to_ascii_lowercase()
+    );
+}
+
+#[tokio::test]
+async fn test_build_parts_hsts_upgrade() {
+    let hsts = HstsStore::new();
+    hsts.add_from_header("example.test", "max-age=600");
+    let client = Client::builder().hsts(hsts).build();
+
+    // Nothing is sent, so the host need not exist
+    let parts = client
+        .post("http://example.test/submit")
+        .body("a=1")
+        .build_parts()
+        .await
+        .unwrap();
+    assert_eq!(parts.url.as_str(), "https://example.test/submit");
+    assert_eq!(parts.header("host").unwrap(), "example.test");
+    assert_eq!(parts.body, BodyDescriptor::Bytes("a=1".into()));
+
+    let parts = client
+        .get("http://other.test:8080/")
+        .build_parts()
+        .await
+        .unwrap();
+    assert_eq!(parts.url.as_str(), "http://other.test:8080/");
+}
+
+#[tokio::test]
+async fn test_later_user_agent_restores_client_hints() {
+    let (url, head) = capture_server().await;
+    let client = Client::builder().emulation(chrome_profile()).build();
+
+    client
+        .get(&url)
+        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:129.0) Gecko/20100101 Firefox/129.0")
+        .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/128.0.0.0 Safari/537.36")
+        .send()
+        .await
+        .unwrap();
+
+    let head = head.await.unwrap();
+    assert!(head.contains("sec-ch-ua-platform: \"Linux\""), "{}", head);
+}
+
+#[tokio::test]
+async fn test_invalid_user_agent_fails_request() {
+    let result = Client::new()
+        .get("http://127.0.0.1:9/")
+        .user_agent("Agent\r\nX-Injected: 1")
+        .send()
+        .await;
+    assert!(matches!(result, Err(NetError::InvalidHeader)));
+
+    let client = Client::builder().user_agent("Agent\n").build();
+    let result = client.get("http://127.0.0.1:9/").send().await;
+    assert!(matches!(result, Err(NetError::InvalidHeader)));
+}
+
+#[test]
+fn test_invalid_case_sensitive_header_is_reported() {
+    let mut headers = CaseSensitiveHeaders::new();
+    headers.insert("X-Good", "1");
+    headers.insert("Bad Name", "2");
+    assert!(matches!(
+        OrderedHeaderMap::try_from(&headers),
+        Err(NetError::InvalidHeader)
+    ));
+}
@@ -0,0 +1,339 @@
+//! Consuming response bodies: limits, charsets, JSON, JSON lines and
+//! writers.
+
+mod common;
+
+use chromenet::base::neterror::NetError;
+use chromenet::http::{EntityTag, RetryAfter};
+use chromenet::Client;
+use common::server::serve;
+use futures::StreamExt;
+use std::io::Write;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Answer every request with `head` and a body of `body`.
+async fn server(head: &'static str, body: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    serve(listener, move |_| [head.as_bytes(), body].concat());
+    url
+}
+
+#[tokio::test]
+async fn test_text_uses_charset() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=ISO-8859-1\r\nContent-Length: 4\r\nConnection: close\r\n\r\n",
+        b"caf\xe9",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(response.mime_type().as_deref(), Some("text/plain"));
+    assert_eq!(response.charset().as_deref(), Some("iso-8859-1"));
+    assert_eq!(response.text().await.unwrap(), "café");
+}
+
+#[tokio::test]
+async fn test_typed_headers() {
+    let url = server(
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Type: Text/HTML; Charset=\"UTF-8\"\r\nContent-Length: 2\r\nETag: W/\"v1\"\r\nRetry-After: 30\r\nConnection: close\r\n\r\n",
+        b"hi",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(response.content_length(), Some(2));
+    let content_type = response.content_type().unwrap();
+    assert_eq!(content_type.mime_type(), "text/html");
+    assert_eq!(content_type.to_string(), "text/html; charset=UTF-8");
+    assert_eq!(response.charset().as_deref(), Some("utf-8"));
+    assert_eq!(response.etag(), Some(EntityTag::weak("v1")));
+    assert_eq!(
+        response.retry_after(),
+        Some(RetryAfter::Delay(Duration::from_secs(30)))
+    );
+}
+
+#[tokio::test]
+async fn test_encoded_body_left_as_is() {
+    let gzip_hello: &[u8] = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03\xcbH\xcd\xc9\xc9\x07\x00\x86\xa6\x106\x05\x00\x00\x00";
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 25\r\nConnection: close\r\n\r\n",
+        gzip_hello,
+    )
+    .await;
+    let response = Client::new()
+        .get(&url)
+        .accept_encoding("gzip")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.content_encodings(), ["gzip"]);
+    assert_eq!(&response.bytes().await.unwrap()[..], gzip_hello);
+}
+
+#[tokio::test]
+async fn test_declared_length_over_limit() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\n",
+        b"hello world",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let result = response.with_body_limit(5).bytes().await;
+    assert!(matches!(
+        result,
+        Err(NetError::ResponseBodyTooBig { limit: 5 })
+    ));
+}
+
+#[tokio::test]
+async fn test_streamed_body_over_limit() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        b"6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n",
+    )
+    .await;
+
+    let response = Client::new().get(&url).send().await.unwrap();
+    let result = response.with_body_limit(8).text().await;
+    assert!(matches!(
+        result,
+        Err(NetError::ResponseBodyTooBig { limit: 8 })
+    ));
+
+    let response = Client::new().get(&url).send().await.unwrap();
+    let text = response.with_body_limit(11).text().await.unwrap();
+    assert_eq!(text, "hello world");
+}
+
+#[tokio::test]
+async fn test_json() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 13\r\nConnection: close\r\n\r\n",
+        b"{\"answer\":42}",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let value: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(value["answer"], 42);
+}
+
+#[tokio::test]
+async fn test_copy_to_writer() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        b"6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let mut out = Vec::new();
+    let written = response.copy_to(&mut out).await.unwrap();
+    assert_eq!(written, 11);
+    assert_eq!(out, b"hello world");
+}
+
+#[tokio::test]
+async fn test_body_taken_before_consuming() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n",
+        b"ok",
+    )
+    .await;
+    let mut response = Client::new().get(&url).send().await.unwrap();
+    let body = response.take_body().unwrap();
+    assert_eq!(body.bytes().await.unwrap(), "ok");
+    assert!(matches!(
+        response.bytes().await,
+        Err(NetError::HttpBodyError)
+    ));
+}
+
+#[tokio::test]
+async fn test_partial_body_after_short_content_length() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n",
+        b"abcd",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let partial = response.bytes_or_partial().await.unwrap_err();
+    assert_eq!(&partial.bytes[..], b"abcd");
+    assert!(matches!(partial.error, NetError::ContentLengthMismatch));
+
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert!(matches!(
+        response.bytes().await,
+        Err(NetError::ContentLengthMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn test_partial_body_after_missing_last_chunk() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        b"4\r\nabcd\r\n3\r\nef",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let partial = response.bytes_or_partial().await.unwrap_err();
+    assert!(partial.bytes.starts_with(b"abcd"));
+    assert!(
+        matches!(partial.error, NetError::IncompleteChunkedEncoding),
+        "{:?}",
+        partial.error
+    );
+}
+
+#[tokio::test]
+async fn test_partial_body_over_limit_and_complete() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        b"6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let partial = response
+        .with_body_limit(8)
+        .bytes_or_partial()
+        .await
+        .unwrap_err();
+    assert_eq!(&partial.bytes[..], b"hello ");
+    assert!(matches!(
+        partial.error,
+        NetError::ResponseBodyTooBig { limit: 8 }
+    ));
+
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(
+        &response.bytes_or_partial().await.unwrap()[..],
+        b"hello world"
+    );
+}
+
+#[tokio::test]
+async fn test_error_for_status() {
+    let url = server(
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\nConnection: close\r\n\r\n",
+        b"not found",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(response.url().map(|u| u.as_str()), Some(url.as_str()));
+    assert!(response.error_for_status_ref().is_err());
+
+    let Err(NetError::HttpStatus(error)) = response.error_for_status() else {
+        panic!("expected a status error");
+    };
+    assert_eq!(error.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(error.url().map(|u| u.as_str()), Some(url.as_str()));
+    assert!(error.body().is_none());
+    assert_eq!(
+        error.to_string(),
+        format!("HTTP status client error (404 Not Found) for url ({url})")
+    );
+    assert_eq!(NetError::HttpStatus(error).as_i32(), -10033);
+}
+
+#[tokio::test]
+async fn test_error_for_status_with_body() {
+    let url = server(
+        "HTTP/1.1 503 Service Unavailable\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        b"5\r\nretry\r\n6\r\n later\r\n0\r\n\r\n",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let Err(NetError::HttpStatus(error)) = response.error_for_status_with_body(8).await else {
+        panic!("expected a status error");
+    };
+    assert_eq!(error.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error.body().unwrap().as_ref(), b"retry la");
+
+    let ok = server(
+        "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n",
+        b"ok",
+    )
+    .await;
+    let response = Client::new().get(&ok).send().await.unwrap();
+    let response = response.error_for_status_with_body(8).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn test_multipart_x_mixed_replace() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary=frame\r\nConnection: close\r\n\r\n",
+        b"--frame\r\nContent-Type: image/jpeg\r\nContent-Length: 5\r\n\r\nJPEG1\r\n--frame\r\nContent-Type: image/jpeg\r\n\r\nJPEG2\r\n--frame--\r\n",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let parts: Vec<_> = response
+        .multipart()
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].content_type(), Some("image/jpeg"));
+    assert_eq!(parts[0].headers()["content-length"], "5");
+    assert_eq!(parts[0].body().as_ref(), b"JPEG1");
+    assert_eq!(parts[1].body().as_ref(), b"JPEG2");
+}
+
+#[tokio::test]
+async fn test_multipart_needs_boundary() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\nConnection: close\r\n\r\n",
+        b"hi",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    assert!(matches!(
+        response.multipart().err(),
+        Some(NetError::InvalidResponse)
+    ));
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Event {
+    id: u32,
+}
+
+#[tokio::test]
+async fn test_json_lines_chunked() {
+    let url = server(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        b"d\r\n{\"id\":1}\n{\"id\r\n7\r\n\":2}\r\n\n\r\n0\r\n\r\n",
+    )
+    .await;
+    let response = Client::new().get(&url).send().await.unwrap();
+    let ids: Vec<_> = response
+        .json_lines::<Event>()
+        .unwrap()
+        .map(|event| event.unwrap().id)
+        .collect()
+        .await;
+    assert_eq!(ids, [1, 2]);
+}
+
+#[tokio::test]
+async fn test_json_lines_gzip() {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"{\"id\":1}\n{\"id\":2}\n").unwrap();
+    let body: &'static [u8] = encoder.finish().unwrap().leak();
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    let url = server(head.leak(), body).await;
+
+    let response = Client::new().get(&url).send().await.unwrap();
+    let ids: Vec<_> = response
+        .json_lines::<Event>()
+        .unwrap()
+        .map(|event| event.unwrap().id)
+        .collect()
+        .await;
+    assert_eq!(ids, [1, 2]);
+}
@@ -0,0 +1,176 @@
+//! Resumable uploads against tus and `Content-Range` servers.
+
+mod common;
+
+use chromenet::base::neterror::NetError;
+use chromenet::http::retry::RetryConfig;
+use chromenet::http::ResumeProtocol;
+use chromenet::Client;
+use common::server::read_head;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const DATA: &[u8] = b"hello, resumable world";
+
+/// A request as the server saw it: method, headers and body.
+struct Request {
+    method: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Read one request, its body framed by `Content-Length`.
+async fn read_request(socket: &mut TcpStream) -> Request {
+    let head = read_head(socket).await.unwrap();
+    let mut lines = head.lines();
+    let method = lines.next().unwrap().split(' ').next().unwrap().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+        .collect();
+    let mut request = Request {
+        method,
+        headers,
+        body: Vec::new(),
+    };
+    let len: usize = request
+        .header("content-length")
+        .map_or(0, |v| v.parse().unwrap());
+    request.body.resize(len, 0);
+    socket.read_exact(&mut request.body).await.unwrap();
+    request
+}
+
+/// A tus server that has the first `initial` bytes of the upload and drops
+/// the connection of the first `PATCH` after storing `cut` of its bytes.
+async fn tus_server(initial: usize, cut: Option<usize>) -> (String, Arc<Mutex<Vec<u8>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/files/1", listener.local_addr().unwrap());
+    let stored = Arc::new(Mutex::new(DATA[..initial].to_vec()));
+    let state = stored.clone();
+
+    tokio::spawn(async move {
+        let mut cut = cut;
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            assert_eq!(request.header("tus-resumable"), Some("1.0.0"));
+            if request.method == "PATCH" {
+                let offset: usize = request.header("upload-offset").unwrap().parse().unwrap();
+                let mut stored = state.lock().unwrap();
+                assert_eq!(offset, stored.len(), "resumed at the server's offset");
+                if let Some(n) = cut.take() {
+                    stored.extend_from_slice(&request.body[..n]);
+                    continue;
+                }
+                stored.extend_from_slice(&request.body);
+            }
+            let offset = state.lock().unwrap().len();
+            let status = match request.method.as_str() {
+                "PATCH" => "204 No Content",
+                _ => "200 OK",
+            };
+            let head = format!(
+                "HTTP/1.1 {status}\r\nTus-Resumable: 1.0.0\r\nUpload-Offset: {offset}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+            let _ = socket.write_all(head.as_bytes()).await;
+        }
+    });
+
+    (url, stored)
+}
+
+#[tokio::test]
+async fn test_tus_offset_and_send_from() {
+    let (url, stored) = tus_server(5, None).await;
+    let upload = Client::new().resumable_upload(&url, DATA.len() as u64);
+
+    assert_eq!(upload.offset().await.unwrap(), 5);
+    let offset = upload.send_from(5, &DATA[5..]).await.unwrap();
+    assert_eq!(offset, DATA.len() as u64);
+    assert_eq!(stored.lock().unwrap().as_slice(), DATA);
+}
+
+#[tokio::test]
+async fn test_tus_resumes_after_dropped_connection() {
+    let (url, stored) = tus_server(0, Some(7)).await;
+    let retry = RetryConfig {
+        base_delay_ms: 1,
+        ..RetryConfig::default()
+    };
+    let offsets = Arc::new(Mutex::new(Vec::new()));
+    let opened = offsets.clone();
+
+    let offset = Client::new()
+        .resumable_upload(&url, DATA.len() as u64)
+        .retry(retry)
+        .upload(move |offset| {
+            opened.lock().unwrap().push(offset);
+            async move { Ok::<_, NetError>(&DATA[offset as usize..]) }
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(offset, DATA.len() as u64);
+    assert_eq!(*offsets.lock().unwrap(), [0, 7]);
+    assert_eq!(stored.lock().unwrap().as_slice(), DATA);
+}
+
+#[tokio::test]
+async fn test_content_range_upload() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/upload", listener.local_addr().unwrap());
+    let stored = Arc::new(Mutex::new(DATA[..4].to_vec()));
+    let state = stored.clone();
+    let ranges = Arc::new(Mutex::new(Vec::new()));
+    let seen = ranges.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            let range = request.header("content-range").unwrap().to_string();
+            seen.lock().unwrap().push(range);
+            let mut stored = state.lock().unwrap();
+            stored.extend_from_slice(&request.body);
+            let head = if stored.len() == DATA.len() {
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            } else {
+                format!(
+                    "HTTP/1.1 308 Resume Incomplete\r\nRange: bytes=0-{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    stored.len() - 1
+                )
+            };
+            drop(stored);
+            let _ = socket.write_all(head.as_bytes()).await;
+        }
+    });
+
+    let total = DATA.len() as u64;
+    let offset = Client::new()
+        .resumable_upload(&url, total)
+        .protocol(ResumeProtocol::ContentRange)
+        .upload(|offset| async move { Ok::<_, NetError>(&DATA[offset as usize..]) })
+        .await
+        .unwrap();
+
+    assert_eq!(offset, total);
+    assert_eq!(stored.lock().unwrap().as_slice(), DATA);
+    assert_eq!(
+        *ranges.lock().unwrap(),
+        [
+            format!("bytes */{total}"),
+            format!("bytes 4-{}/{total}", total - 1)
+        ]
+    );
+}
@@ -0,0 +1,135 @@
+//! robots.txt checking against a local HTTP/1.1 server.
+
+mod common;
+
+use chromenet::base::neterror::NetError;
+use chromenet::http::RateLimiter;
+use chromenet::urlrequest::robots::{Robots, RobotsMode, RobotsTxt};
+use chromenet::Client;
+use common::server::read_head;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use url::Url;
+
+const ROBOTS: &str = "User-agent: *\nDisallow: /private\nCrawl-delay: 30\n\n\
+                      User-agent: friendlybot\nAllow: /\n";
+
+/// Keep-alive server answering `/robots.txt` with [`ROBOTS`] and every
+/// other path with "ok". Returns the base URL and the paths requested.
+async fn server() -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    let seen = paths.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                while let Some(request) = read_head(&mut socket).await {
+                    let path = request.split(' ').nth(1).unwrap().to_string();
+                    let body = if path == "/robots.txt" { ROBOTS } else { "ok" };
+                    seen.lock().unwrap().push(path);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+    (url, paths)
+}
+
+#[tokio::test]
+async fn test_blocks_disallowed_requests() {
+    let (url, paths) = server().await;
+    let client = Client::builder().robots(Robots::new()).build();
+
+    let response = client.get(format!("{url}/public")).send().await.unwrap();
+    let verdict = response.robots().unwrap();
+    assert!(verdict.allowed);
+    assert_eq!(verdict.crawl_delay, Some(Duration::from_secs(30)));
+    assert_eq!(response.text().await.unwrap(), "ok");
+
+    let blocked = client.get(format!("{url}/private/page")).send().await;
+    assert!(matches!(blocked, Err(NetError::BlockedByClient)));
+
+    // robots.txt is fetched once per origin
+    assert_eq!(*paths.lock().unwrap(), ["/robots.txt", "/public"]);
+}
+
+#[tokio::test]
+async fn test_annotates_disallowed_requests() {
+    let (url, paths) = server().await;
+    let client = Client::builder()
+        .robots(Robots::new().with_mode(RobotsMode::Annotate))
+        .build();
+
+    let response = client
+        .get(format!("{url}/private/page"))
+        .send()
+        .await
+        .unwrap();
+    assert!(!response.robots().unwrap().allowed);
+    assert_eq!(response.text().await.unwrap(), "ok");
+    assert_eq!(paths.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_user_agent_token_selects_group() {
+    let (url, _) = server().await;
+    let robots = Robots::new();
+    let client = Client::builder().robots(robots.clone()).build();
+
+    let response = client
+        .get(format!("{url}/private/page"))
+        .header("User-Agent", "Mozilla/5.0 (compatible; FriendlyBot/2.1)")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.robots().unwrap().allowed);
+
+    // A token set on the cache overrides the header
+    let client = Client::builder()
+        .robots(robots.with_user_agent("OtherBot"))
+        .build();
+    let blocked = client
+        .get(format!("{url}/private/page"))
+        .header("User-Agent", "FriendlyBot")
+        .send()
+        .await;
+    assert!(matches!(blocked, Err(NetError::BlockedByClient)));
+}
+
+#[tokio::test]
+async fn test_inserted_robots_skip_fetch() {
+    let (url, paths) = server().await;
+    let robots = Robots::new();
+    robots.insert(&Url::parse(&url).unwrap(), RobotsTxt::disallow_all());
+    let client = Client::builder().robots(robots).build();
+
+    let blocked = client.get(format!("{url}/")).send().await;
+    assert!(matches!(blocked, Err(NetError::BlockedByClient)));
+    assert!(paths.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_crawl_delay_schedules_rate_limiter() {
+    let (url, _) = server().await;
+    let limiter = RateLimiter::new().with_max_delay(Duration::from_secs(10));
+    let client = Client::builder()
+        .robots(Robots::new())
+        .rate_limiter(limiter.clone())
+        .build();
+
+    client.get(format!("{url}/")).send().await.unwrap();
+
+    // Capped by the limiter's maximum delay
+    let delay = limiter.delay(&Url::parse(&url).unwrap()).unwrap();
+    assert!(delay > Duration::from_secs(9) && delay <= Duration::from_secs(10));
+}
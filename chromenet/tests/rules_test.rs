@@ -0,0 +1,172 @@
+//! Blocking, redirecting and editing requests by declarative rules.
+
+mod common;
+
+use chromenet::base::neterror::NetError;
+use chromenet::urlrequest::rules::{ResourceType, Rule, RuleAction, RuleSet, UrlPattern};
+use chromenet::Client;
+use common::server::serve;
+use http::{HeaderName, HeaderValue, Method};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Server answering every request with `name`, or with a redirect to
+/// `/ads/banner` for `/go`. Returns the address and the requests received.
+async fn server(name: &'static str) -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    serve(listener, move |request| {
+        let request = request.to_ascii_lowercase();
+        let response = if request.contains(" /go ") {
+            "HTTP/1.1 302 Found\r\nLocation: /ads/banner\r\nContent-Length: 0\r\n\r\n".to_string()
+        } else {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                name.len(),
+                name
+            )
+        };
+        seen.lock().unwrap().push(request);
+        response.into_bytes()
+    });
+    (addr, requests)
+}
+
+fn block_ads() -> Rule {
+    Rule::new(RuleAction::Block).with_url(UrlPattern::glob("*/ads/*"))
+}
+
+#[tokio::test]
+async fn test_block() {
+    let (addr, requests) = server("origin").await;
+    let client = Client::builder()
+        .rules(RuleSet::new().with_rule(block_ads()))
+        .build();
+
+    let blocked = client
+        .get(format!("http://{}/ads/banner", addr))
+        .send()
+        .await;
+    assert!(matches!(blocked, Err(NetError::BlockedByClient)));
+    assert!(requests.lock().unwrap().is_empty());
+
+    let page = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(page.text().await.unwrap(), "origin");
+}
+
+#[tokio::test]
+async fn test_redirect_into_blocked_url() {
+    let (addr, requests) = server("origin").await;
+    let client = Client::builder()
+        .rules(RuleSet::new().with_rule(block_ads()))
+        .build();
+
+    let result = client.get(format!("http://{}/go", addr)).send().await;
+    assert!(matches!(result, Err(NetError::BlockedByClient)));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_rewrite_to_mirror() {
+    let (origin, origin_requests) = server("origin").await;
+    let (mirror, mirror_requests) = server("mirror").await;
+    let pattern = format!(r"^http://127\.0\.0\.1:{}/(.*)$", origin.port());
+    let client = Client::builder()
+        .rules(
+            RuleSet::new().with_rule(
+                Rule::new(RuleAction::RegexSubstitution(format!(
+                    "http://{}/$1",
+                    mirror
+                )))
+                .with_url(UrlPattern::regex(&pattern).unwrap()),
+            ),
+        )
+        .build();
+
+    let response = client
+        .post(format!("http://{}/upload?part=1", origin))
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), "mirror");
+    assert!(origin_requests.lock().unwrap().is_empty());
+
+    // Method, path, query and body carry over to the mirror
+    let requests = mirror_requests.lock().unwrap();
+    assert!(requests[0].starts_with("post /upload?part=1 "));
+    assert!(requests[0].ends_with("payload"));
+}
+
+#[tokio::test]
+async fn test_rule_redirect_counts_against_cycles() {
+    let (addr, _) = server("origin").await;
+    let a = Url::parse(&format!("http://{}/a", addr)).unwrap();
+    let b = Url::parse(&format!("http://{}/b", addr)).unwrap();
+    let client = Client::builder()
+        .rules(
+            RuleSet::new()
+                .with_rule(
+                    Rule::new(RuleAction::Redirect(b.clone()))
+                        .with_url(UrlPattern::glob(a.as_str())),
+                )
+                .with_rule(
+                    Rule::new(RuleAction::Redirect(a.clone()))
+                        .with_url(UrlPattern::glob(b.as_str())),
+                ),
+        )
+        .build();
+
+    let result = client.get(a.as_str()).send().await;
+    assert!(matches!(result, Err(NetError::RedirectCycleDetected)));
+}
+
+#[tokio::test]
+async fn test_header_rules_by_method_and_resource_type() {
+    let (addr, requests) = server("origin").await;
+    let client = Client::builder()
+        .rules(
+            RuleSet::new()
+                .with_rule(
+                    Rule::new(RuleAction::SetHeader(
+                        HeaderName::from_static("x-pipeline"),
+                        HeaderValue::from_static("crawler"),
+                    ))
+                    .with_methods([Method::GET]),
+                )
+                .with_rule(
+                    Rule::new(RuleAction::RemoveHeader(HeaderName::from_static("x-debug")))
+                        .with_resource_types([ResourceType::Image]),
+                ),
+        )
+        .build();
+    let url = format!("http://{}/", addr);
+
+    client
+        .get(&url)
+        .header("x-debug", "1")
+        .send()
+        .await
+        .unwrap();
+    client
+        .get(&url)
+        .header("x-debug", "1")
+        .resource_type(ResourceType::Image)
+        .send()
+        .await
+        .unwrap();
+    client.post(&url).body("x").send().await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert!(requests[0].contains("x-pipeline: crawler") && requests[0].contains("x-debug: 1"));
+    assert!(requests[1].contains("x-pipeline: crawler") && !requests[1].contains("x-debug"));
+    assert!(!requests[2].contains("x-pipeline"));
+}
@@ -0,0 +1,107 @@
+//! TLS server name overrides: opt-in and connection pooling.
+
+use chromenet::base::neterror::NetError;
+use chromenet::http::streamfactory::{HttpStreamFactory, HttpVersionPref};
+use chromenet::socket::pool::{ClientSocketPool, ConnectionReuse, GroupId, RequestPriority};
+use chromenet::socket::tls::ServerName;
+use chromenet::Client;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+#[tokio::test]
+async fn test_override_requires_opt_in() {
+    let client = Client::new();
+    for server_name in [ServerName::custom("front.test"), ServerName::Disabled] {
+        let result = client
+            .get("https://hidden.test/")
+            .server_name(server_name)
+            .send()
+            .await;
+        assert!(matches!(result, Err(NetError::SniOverrideNotAllowed)));
+    }
+    assert_eq!(NetError::SniOverrideNotAllowed.as_i32(), -10032);
+    assert!(matches!(
+        NetError::from(-10032),
+        NetError::SniOverrideNotAllowed
+    ));
+}
+
+#[tokio::test]
+async fn test_connections_are_pooled_per_server_name() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                while matches!(socket.read(&mut buf).await, Ok(n) if n > 0) {
+                    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+                    if socket.write_all(response).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+    let host = GroupId::new(&url, None, None).unwrap();
+    let fronted = host
+        .clone()
+        .with_server_name(ServerName::custom("front.test"));
+
+    let pool = Arc::new(ClientSocketPool::new(None));
+    let factory = HttpStreamFactory::new(pool.clone());
+    let get = |group_id: GroupId| {
+        let factory = &factory;
+        let url = &url;
+        async move {
+            let mut stream = factory
+                .create_stream_for_group(
+                    &group_id,
+                    url,
+                    None,
+                    None,
+                    None,
+                    HttpVersionPref::Auto,
+                    RequestPriority::default(),
+                    ConnectionReuse::Allowed,
+                )
+                .await
+                .unwrap();
+            let reused = stream.is_reused();
+            let request = http::Request::get("/")
+                .header("host", url.authority())
+                .body(chromenet::http::requestbody::RequestBody::Empty.into())
+                .unwrap();
+            let response = stream.send_request(request).await.unwrap();
+            drop(response);
+            reused
+        }
+    };
+    let wait_idle = |count: usize| {
+        let pool = pool.clone();
+        async move {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while pool.idle_socket_count() < count {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+            .await
+            .expect("socket was not returned to the pool");
+        }
+    };
+
+    assert!(!get(host.clone()).await);
+    wait_idle(1).await;
+
+    // The idle connection was opened for the URL's host
+    assert!(!get(fronted.clone()).await);
+    wait_idle(2).await;
+
+    assert!(get(fronted).await);
+    assert!(get(host).await);
+}
@@ -0,0 +1,63 @@
+//! Repeated `Set-Cookie` headers reach the response and the jar one cookie
+//! per line, never joined at the commas of their `Expires` dates.
+
+use bytes::Bytes;
+use chromenet::http::H2cMode;
+use chromenet::Client;
+use http::Response;
+use tokio::net::TcpListener;
+use url::Url;
+
+const COOKIES: [&str; 3] = [
+    "a=1; Expires=Wed, 21 Oct 2037 07:28:00 GMT; Path=/",
+    "b=2; Expires=Thu, 22 Oct 2037 07:28:00 GMT; Path=/",
+    "c=x,y",
+];
+
+/// HTTP/2 server answering every request with the three cookies.
+async fn h2_server() -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut conn = http2::server::handshake::<_, Bytes>(socket).await.unwrap();
+                while let Some(Ok((_, mut respond))) = conn.accept().await {
+                    let mut response = Response::builder().status(200);
+                    for cookie in COOKIES {
+                        response = response.header("set-cookie", cookie);
+                    }
+                    let response = response.body(()).unwrap();
+                    respond.send_response(response, true).unwrap();
+                }
+            });
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_h2_set_cookie_lines_kept_apart() {
+    let url = h2_server().await;
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    let response = client.get(url.as_str()).send().await.unwrap();
+    assert_eq!(response.version(), http::Version::HTTP_2);
+    assert_eq!(response.set_cookies(), COOKIES);
+    assert_eq!(response.headers().get_all("set-cookie").iter().count(), 3);
+
+    let mut cookies: Vec<_> = client
+        .cookie_store()
+        .iter_all_cookies()
+        .map(|c| (c.name, c.value, c.expiration_time.map(|t| t.year())))
+        .collect();
+    cookies.sort();
+    assert_eq!(
+        cookies,
+        [
+            ("a".to_string(), "1".to_string(), Some(2037)),
+            ("b".to_string(), "2".to_string(), Some(2037)),
+            ("c".to_string(), "x,y".to_string(), None),
+        ]
+    );
+}
@@ -0,0 +1,165 @@
+//! Compression dictionary transport: responses kept as dictionaries are
+//! announced on matching requests, and `dcb` bodies are decoded with them.
+
+mod common;
+
+use chromenet::http::SharedDictionaryStore;
+use chromenet::Client;
+use common::server::serve;
+use std::io::Write;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const DICTIONARY: &[u8] = b"function greet(name) { return 'hello ' + name; }";
+
+/// Serve `/dict.js` as a dictionary for `/app/*`, and `/app/*` with `dcb`
+/// when the request announces it. Reports the head of each request.
+async fn server() -> (String, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let (heads, received) = mpsc::unbounded_channel();
+
+    serve(listener, move |request| {
+        let head = request.to_ascii_lowercase();
+        let _ = heads.send(head.clone());
+
+        let (extra, body) = if head.starts_with("get /dict.js") {
+            (
+                "Use-As-Dictionary: match=\"/app/*\", id=\"v1\"\r\nCache-Control: max-age=3600\r\n",
+                DICTIONARY.to_vec(),
+            )
+        } else if head.contains("available-dictionary:") {
+            let mut body = vec![0xff, 0x44, 0x43, 0x42];
+            body.extend_from_slice(&boring::sha::sha256(DICTIONARY));
+            let mut writer = brotli::CompressorWriter::new(&mut body, 4096, 11, 22);
+            writer.write_all(b"greet('world');").unwrap();
+            drop(writer);
+            (
+                "Content-Encoding: dcb\r\nVary: Available-Dictionary\r\n",
+                body,
+            )
+        } else {
+            ("", b"greet('world');".to_vec())
+        };
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+            extra,
+            body.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(&body);
+        response
+    });
+
+    (base, received)
+}
+
+#[tokio::test]
+async fn test_dictionary_announced_and_used() {
+    let (base, mut heads) = server().await;
+    let store = SharedDictionaryStore::new();
+    let client = Client::builder()
+        .default_header("Accept-Encoding", "gzip, deflate, br, zstd")
+        .shared_dictionaries(store.clone())
+        .build();
+
+    // Nothing to announce yet
+    let text = client
+        .get(format!("{}/dict.js", base))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text.as_bytes(), DICTIONARY);
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("accept-encoding: gzip, deflate, br, zstd\r\n"));
+    assert!(!head.contains("available-dictionary"));
+    assert_eq!(store.len(), 1);
+
+    let response = client
+        .get(format!("{}/app/main.js", base))
+        .send()
+        .await
+        .unwrap();
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("accept-encoding: gzip, deflate, br, zstd, dcb, dcz\r\n"));
+    assert!(head.contains("dictionary-id: \"v1\"\r\n"));
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.content_length(), Some(15));
+    assert_eq!(response.text().await.unwrap(), "greet('world');");
+
+    // Not matched by the pattern
+    client
+        .get(format!("{}/other.js", base))
+        .send()
+        .await
+        .unwrap();
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("accept-encoding: gzip, deflate, br, zstd\r\n"));
+    assert!(!head.contains("available-dictionary"));
+}
+
+#[tokio::test]
+async fn test_explicit_accept_encoding_skips_dictionaries() {
+    let (base, mut heads) = server().await;
+    let store = SharedDictionaryStore::new();
+    let client = Client::builder()
+        .default_header("Accept-Encoding", "gzip, deflate, br, zstd")
+        .shared_dictionaries(store.clone())
+        .build();
+    client
+        .get(format!("{}/dict.js", base))
+        .send()
+        .await
+        .unwrap();
+    heads.recv().await.unwrap();
+    assert_eq!(store.len(), 1);
+
+    let text = client
+        .get(format!("{}/app/main.js", base))
+        .identity_encoding()
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text, "greet('world');");
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("accept-encoding: identity\r\n"));
+    assert!(!head.contains("available-dictionary"));
+
+    client
+        .get(format!("{}/app/main.js", base))
+        .remove_header("Accept-Encoding")
+        .send()
+        .await
+        .unwrap();
+    let head = heads.recv().await.unwrap();
+    assert!(!head.contains("accept-encoding"));
+    assert!(!head.contains("available-dictionary"));
+}
+
+#[tokio::test]
+async fn test_off_without_store() {
+    let (base, mut heads) = server().await;
+    let client = Client::builder()
+        .default_header("Accept-Encoding", "gzip, deflate, br, zstd")
+        .build();
+    client
+        .get(format!("{}/dict.js", base))
+        .send()
+        .await
+        .unwrap();
+    heads.recv().await.unwrap();
+    client
+        .get(format!("{}/app/main.js", base))
+        .send()
+        .await
+        .unwrap();
+    let head = heads.recv().await.unwrap();
+    assert!(head.contains("accept-encoding: gzip, deflate, br, zstd\r\n"));
+    assert!(!head.contains("available-dictionary"));
+}
@@ -0,0 +1,212 @@
+//! Graceful client shutdown: requests in flight finish, later ones fail,
+//! connections close and shutdown hooks run. Closing does the same without
+//! waiting.
+
+use bytes::Bytes;
+use chromenet::base::neterror::NetError;
+use chromenet::http::{H2cMode, HttpCache, ShutdownReport};
+use chromenet::tls::{HstsStore, PinStore, TransportSecurityPersister};
+use chromenet::Client;
+use http::Response;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// HTTP/1.1 server answering each request after `delay`, keeping the
+/// connection alive. Reports each connection the client closes.
+async fn h1_server(delay: Duration) -> (SocketAddr, mpsc::UnboundedReceiver<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (closed, closes) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let closed = closed.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                loop {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) => {}
+                    }
+                    tokio::time::sleep(delay).await;
+                    let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+                let _ = closed.send(());
+            });
+        }
+    });
+    (addr, closes)
+}
+
+#[tokio::test]
+async fn test_waits_for_requests_in_flight() {
+    let (addr, mut closes) = h1_server(Duration::from_millis(100)).await;
+    let client = Client::new();
+    let url = format!("http://{}/", addr);
+
+    let request = tokio::spawn(client.get(&url).send());
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let report = client.shutdown(Duration::from_secs(5)).await;
+    assert_eq!(report, ShutdownReport::default());
+    assert!(report.is_clean());
+
+    let response = request.await.unwrap().unwrap();
+    assert_eq!(response.text().await.unwrap(), "ok");
+    // The connection is not kept for reuse
+    tokio::time::timeout(Duration::from_secs(5), closes.recv())
+        .await
+        .unwrap();
+
+    // Clones share the shutdown
+    let clone = client.clone();
+    assert!(clone.is_closed());
+    let result = clone.get(&url).send().await;
+    assert!(matches!(result, Err(NetError::ContextShutDown)));
+}
+
+#[tokio::test]
+async fn test_idle_connections_closed() {
+    let (addr, mut closes) = h1_server(Duration::ZERO).await;
+    let client = Client::new();
+    let url = format!("http://{}/", addr);
+    let body = client.get(&url).send().await.unwrap().text().await.unwrap();
+    assert_eq!(body, "ok");
+
+    client.shutdown(Duration::from_secs(5)).await;
+    tokio::time::timeout(Duration::from_secs(5), closes.recv())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_deadline_abandons_slow_requests() {
+    let (addr, _closes) = h1_server(Duration::from_secs(30)).await;
+    let hooks = Arc::new(AtomicUsize::new(0));
+    let counted = hooks.clone();
+    let client = Client::builder()
+        .on_shutdown(move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })
+        .on_shutdown(|| Err(std::io::Error::other("disk full")))
+        .build();
+
+    let _request = tokio::spawn(client.get(format!("http://{}/", addr)).send());
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let report = client.shutdown(Duration::from_millis(50)).await;
+    assert_eq!(report.abandoned, 1);
+    assert_eq!(report.failed_hooks, 1);
+    assert!(!report.is_clean());
+    assert_eq!(hooks.load(Ordering::SeqCst), 1);
+
+    // Hooks run once
+    client.shutdown(Duration::ZERO).await;
+    assert_eq!(hooks.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_prefetcher_stopped() {
+    let (addr, _closes) = h1_server(Duration::ZERO).await;
+    let client = Client::builder().cache(HttpCache::new()).build();
+    let prefetcher = client
+        .prefetcher()
+        .url(format!("http://{}/", addr).parse().unwrap())
+        .interval(Duration::from_millis(10))
+        .start();
+
+    let report = client.shutdown(Duration::from_secs(5)).await;
+    assert_eq!(report.aborted_tasks, 1);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!prefetcher.is_running());
+}
+
+#[tokio::test]
+async fn test_h2_session_sends_goaway() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (closed, mut closes) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = http2::server::handshake::<_, Bytes>(socket).await.unwrap();
+        while let Some(Ok((_, mut respond))) = conn.accept().await {
+            let response = Response::builder().status(200).body(()).unwrap();
+            let mut send = respond.send_response(response, false).unwrap();
+            send.send_data(Bytes::from_static(b"h2"), true).unwrap();
+        }
+        let _ = closed.send(());
+    });
+
+    let client = Client::builder().h2c(H2cMode::PriorKnowledge).build();
+    let body = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, "h2");
+
+    client.shutdown(Duration::from_secs(5)).await;
+    tokio::time::timeout(Duration::from_secs(5), closes.recv())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_close_refuses_requests() {
+    let (addr, mut closes) = h1_server(Duration::ZERO).await;
+    let client = Client::builder().cache(HttpCache::new()).build();
+    let url = format!("http://{}/", addr);
+    let body = client.get(&url).send().await.unwrap().text().await.unwrap();
+    assert_eq!(body, "ok");
+    let prefetcher = client
+        .prefetcher()
+        .url(url.parse().unwrap())
+        .interval(Duration::from_millis(10))
+        .start();
+
+    let clone = client.clone();
+    assert!(!clone.is_closed());
+    client.close();
+    assert!(clone.is_closed());
+    let result = clone.get(&url).send().await;
+    assert!(matches!(result, Err(NetError::ContextShutDown)));
+
+    // The idle connection is closed and the prefetcher stopped
+    tokio::time::timeout(Duration::from_secs(5), closes.recv())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!prefetcher.is_running());
+}
+
+#[tokio::test]
+async fn test_transport_security_saved_on_shutdown() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("TransportSecurity");
+    let (hsts, pins) = (HstsStore::new(), PinStore::new());
+    let persister = TransportSecurityPersister::new(&path, &hsts, &pins)
+        .start()
+        .unwrap();
+    let client = Client::builder()
+        .transport_security_persister(persister)
+        .build();
+
+    // Learned long before the commit interval ends
+    hsts.add_from_header("example.com", "max-age=3600");
+    let report = client.shutdown(Duration::from_secs(5)).await;
+    assert!(report.is_clean());
+
+    let restored = HstsStore::new();
+    assert_eq!(restored.load_from_file(&path).unwrap(), 1);
+    assert!(restored.should_upgrade("example.com"));
+}
@@ -0,0 +1,157 @@
+//! Single-flight request coalescing against a local HTTP/1.1 server.
+
+use chromenet::http::{CacheMode, ContentDigest, DigestAlgorithm, HttpCache, TraceContext};
+use chromenet::Client;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Serve every request slowly, counting how many arrive.
+async fn slow_server() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/resource", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let counter = counter.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                if socket.read(&mut buf).await.unwrap_or(0) == 0 {
+                    return;
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+                    )
+                    .await;
+            });
+        }
+    });
+
+    (url, requests)
+}
+
+#[tokio::test]
+async fn test_concurrent_gets_share_one_fetch() {
+    let (url, requests) = slow_server().await;
+    let client = Client::builder().single_flight(true).build();
+
+    let sends = (0..5).map(|_| {
+        let request = client.get(&url);
+        async move { request.send().await.unwrap().text().await.unwrap() }
+    });
+    let bodies = futures::future::join_all(sends).await;
+
+    assert_eq!(bodies, vec!["hello"; 5]);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_different_requests_are_not_coalesced() {
+    let (url, requests) = slow_server().await;
+    let client = Client::builder().single_flight(true).build();
+
+    let (a, b, c) = tokio::join!(
+        client.get(&url).send(),
+        client.get(&url).header("accept", "application/json").send(),
+        client.post(&url).send(),
+    );
+    assert!(a.is_ok() && b.is_ok() && c.is_ok());
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_requests_for_different_sites_are_not_coalesced() {
+    let (url, requests) = slow_server().await;
+    let client = Client::builder().single_flight(true).build();
+    let site = |site: &str| url::Url::parse(site).unwrap();
+
+    // Each site decides which SameSite cookies go out
+    let (a, b) = tokio::join!(
+        client
+            .get(&url)
+            .site_for_cookies(site("http://127.0.0.1/"))
+            .send(),
+        client
+            .get(&url)
+            .site_for_cookies(site("https://other.example/"))
+            .send(),
+    );
+    assert!(a.is_ok() && b.is_ok());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_single_flight_is_opt_in() {
+    let (url, requests) = slow_server().await;
+    let client = Client::new();
+
+    let (a, b) = tokio::join!(client.get(&url).send(), client.get(&url).send());
+    assert!(a.is_ok() && b.is_ok());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_coalesced_responses_keep_their_own_id_and_extensions() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct Caller(u32);
+
+    let (url, requests) = slow_server().await;
+    let client = Client::builder().single_flight(true).build();
+
+    let (a, b) = tokio::join!(
+        client.get(&url).extension(Caller(1)).send(),
+        client.get(&url).extension(Caller(2)).send(),
+    );
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert_eq!(a.extensions().get(), Some(&Caller(1)));
+    assert_eq!(b.extensions().get(), Some(&Caller(2)));
+    assert!(a.request_id().is_some() && b.request_id().is_some());
+    assert_ne!(a.request_id(), b.request_id());
+}
+
+#[tokio::test]
+async fn test_requests_with_different_cache_modes_are_not_coalesced() {
+    let (url, requests) = slow_server().await;
+    let client = Client::builder()
+        .cache(HttpCache::new())
+        .single_flight(true)
+        .build();
+
+    let (a, b) = tokio::join!(
+        client.get(&url).send(),
+        client.get(&url).cache_mode(CacheMode::ForceRefresh).send(),
+    );
+    assert!(a.is_ok() && b.is_ok());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_requests_with_different_extras_are_not_coalesced() {
+    let (url, requests) = slow_server().await;
+    let client = Client::builder().single_flight(true).build();
+    let sha256 = ContentDigest::new(DigestAlgorithm::Sha256);
+
+    let (a, b, c, d, e) = tokio::join!(
+        client
+            .get(&url)
+            .trace_context(TraceContext::random(true))
+            .send(),
+        client
+            .get(&url)
+            .trace_context(TraceContext::random(true))
+            .send(),
+        client.get(&url).timeout(Duration::from_secs(5)).send(),
+        client.get(&url).body("x").send(),
+        client.get(&url).body("x").content_digest(sha256).send(),
+    );
+    assert!(a.is_ok() && b.is_ok() && c.is_ok() && d.is_ok() && e.is_ok());
+    assert_eq!(requests.load(Ordering::SeqCst), 5);
+}
@@ -3,6 +3,7 @@
 //! Covers:
 //! - Connection limits (Max 6 per host)
 //! - Queueing logic (7th request waits)
+//! - Per-host limits set on the client
 
 use chromenet::client::Client;
 use std::net::TcpListener;
@@ -84,12 +85,42 @@ async fn test_pool_connection_limit_per_host() {
     }
 }
 
-#[test]
-fn test_pool_limit_configuration() {
-    // Verify we can configure limits via ClientBuilder (if exposed, which it is partially via pool_size_per_host reserved field)
-    // The field is reserved/dead code currently in ClientBuilder, so we just check it compiles.
-    let _ = Client::builder(); // Default
+#[tokio::test]
+async fn test_group_limit_per_host_pattern() {
+    use chromenet::socket::grouplimits::GroupLimits;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-    // If we wanted to set limits, we'd need to expose it in builder.
-    // For now, checking the default behavior (max 6) via the async test above is the main goal.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let open = Arc::new(AtomicUsize::new(0));
+    let most_open = Arc::new(AtomicUsize::new(0));
+    let (open_server, most_server) = (open.clone(), most_open.clone());
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (open, most_open) = (open_server.clone(), most_server.clone());
+            tokio::spawn(async move {
+                let now_open = open.fetch_add(1, Ordering::SeqCst) + 1;
+                most_open.fetch_max(now_open, Ordering::SeqCst);
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                open.fetch_sub(1, Ordering::SeqCst);
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n")
+                    .await;
+            });
+        }
+    });
+
+    // 127.0.0.1 gets one socket, so the requests take turns
+    let client = Client::builder()
+        .group_limits(GroupLimits::default().with_host("127.0.0.1", 1))
+        .build();
+    let requests = (0..3).map(|_| client.get(&url).send());
+    for response in futures::future::join_all(requests).await {
+        assert_eq!(response.unwrap().status(), 200);
+    }
+    assert_eq!(most_open.load(Ordering::SeqCst), 1);
 }
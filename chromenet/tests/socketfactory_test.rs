@@ -0,0 +1,138 @@
+//! Socket factory tests: transports other than TCP, with connect hooks and
+//! failures on top.
+
+use chromenet::base::neterror::NetError;
+use chromenet::socket::hooks::{ConnectHooks, ConnectOutcome};
+use chromenet::socket::socketfactory::SocketFactory;
+use chromenet::socket::stream::StreamSocket;
+use chromenet::Client;
+use futures::future::BoxFuture;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+
+/// One end of an in-memory pipe.
+struct Pipe(DuplexStream);
+
+impl AsyncRead for Pipe {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Pipe {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+impl StreamSocket for Pipe {}
+
+/// Connects to an in-memory server answering with the address it was
+/// reached at, or refuses every connect.
+#[derive(Default, Clone)]
+struct Memory {
+    refuse: bool,
+    connects: Arc<Mutex<Vec<SocketAddr>>>,
+}
+
+impl SocketFactory for Memory {
+    fn connect(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn StreamSocket>>> {
+        Box::pin(async move {
+            self.connects.lock().unwrap().push(addr);
+            if self.refuse {
+                return Err(io::ErrorKind::ConnectionRefused.into());
+            }
+            let (client, mut server) = tokio::io::duplex(4096);
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let _ = server.read(&mut buf).await;
+                let body = addr.to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = server.write_all(response.as_bytes()).await;
+            });
+            Ok(Box::new(Pipe(client)) as Box<dyn StreamSocket>)
+        })
+    }
+}
+
+/// Sends every connect to one address.
+struct RedirectTo(SocketAddr);
+
+impl ConnectHooks for RedirectTo {
+    fn connect(&self, _addr: SocketAddr) -> ConnectOutcome {
+        ConnectOutcome::Redirect(self.0)
+    }
+}
+
+#[tokio::test]
+async fn test_requests_over_in_memory_transport() {
+    let memory = Memory::default();
+    let client = Client::builder().socket_factory(memory.clone()).build();
+
+    // TEST-NET-1: nothing listens there, the factory answers instead
+    let response = client.get("http://192.0.2.1:8080/").send().await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "192.0.2.1:8080");
+    assert_eq!(
+        *memory.connects.lock().unwrap(),
+        ["192.0.2.1:8080".parse::<SocketAddr>().unwrap()]
+    );
+}
+
+#[tokio::test]
+async fn test_factory_sees_hook_redirects() {
+    let target: SocketAddr = "192.0.2.7:80".parse().unwrap();
+    let client = Client::builder()
+        .socket_factory(Memory::default())
+        .connect_hooks(RedirectTo(target))
+        .build();
+
+    let response = client.get("http://192.0.2.1/").send().await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "192.0.2.7:80");
+}
+
+#[tokio::test]
+async fn test_factory_errors_fail_the_attempt() {
+    let memory = Memory {
+        refuse: true,
+        ..Default::default()
+    };
+    let client = Client::builder().socket_factory(memory.clone()).build();
+
+    let error = client.get("http://192.0.2.1/").send().await.unwrap_err();
+    match error {
+        NetError::ConnectionAttemptsFailed { attempts, .. } => {
+            assert_eq!(attempts.len(), 1);
+            assert!(
+                matches!(attempts[0].error, NetError::ConnectionRefused),
+                "{:?}",
+                attempts[0].error
+            );
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+    assert_eq!(memory.connects.lock().unwrap().len(), 1);
+}
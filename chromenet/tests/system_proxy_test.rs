@@ -0,0 +1,49 @@
+//! System Proxy Tests
+//!
+//! Covers clients picking up proxies from the environment, and turning
+//! that off. One test only: the environment is shared by all tests of a
+//! binary, and a proxy set for one would reroute the others.
+
+use chromenet::Client;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpListener;
+
+/// The first bytes a client sends to `listener` for a GET of `url`.
+async fn first_bytes(client: Client, listener: &TcpListener, url: &str) -> String {
+    let url = url.to_string();
+    let request = tokio::spawn(async move {
+        let _ = client.get(url).send().await;
+    });
+    let (mut socket, _) = listener.accept().await.unwrap();
+    let mut buf = vec![0; 1024];
+    let n = socket.read(&mut buf).await.unwrap();
+    request.abort();
+    String::from_utf8_lossy(&buf[..n]).into_owned()
+}
+
+#[tokio::test]
+async fn test_env_proxy_used_unless_disabled() {
+    let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target_url = format!("http://{}/", target.local_addr().unwrap());
+    std::env::set_var(
+        "HTTP_PROXY",
+        format!("http://{}", proxy.local_addr().unwrap()),
+    );
+    std::env::set_var("NO_PROXY", "localhost");
+
+    // Proxied by default
+    let sent = first_bytes(Client::builder().build(), &proxy, &target_url).await;
+    assert!(
+        sent.contains(&target.local_addr().unwrap().to_string()),
+        "{sent}"
+    );
+
+    // Direct once detection is off
+    let client = Client::builder().system_proxy(false).build();
+    let sent = first_bytes(client, &target, &target_url).await;
+    assert!(sent.starts_with("GET / HTTP/1.1"), "{sent}");
+
+    std::env::remove_var("HTTP_PROXY");
+    std::env::remove_var("NO_PROXY");
+}
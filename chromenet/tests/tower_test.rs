@@ -0,0 +1,109 @@
+//! Driving the client through tower middleware.
+#![cfg(feature = "tower")]
+
+use chromenet::base::neterror::NetError;
+use chromenet::Client;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tower::{ServiceBuilder, ServiceExt};
+
+/// Server answering each connection with its request line, an `x-token`
+/// header and the body, or never answering `/slow`.
+async fn echo_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 4096];
+                let mut len = 0;
+                // Head and a short body arrive together or shortly after
+                loop {
+                    let n = socket.read(&mut buf[len..]).await.unwrap();
+                    len += n;
+                    let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                    let Some((head, body)) = request.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let head = head.to_ascii_lowercase();
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .map_or(0, |len| len.trim().parse().unwrap());
+                    if body.len() < length {
+                        continue;
+                    }
+                    let request_line = head.lines().next().unwrap();
+                    if request_line.contains(" /slow ") {
+                        std::future::pending::<()>().await;
+                    }
+                    let token = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("x-token: "))
+                        .unwrap_or("-");
+                    let reply = format!("{request_line} {token} {body}");
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        reply.len(),
+                        reply
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                    break;
+                }
+            });
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_sends_http_requests() {
+    let url = echo_server().await;
+    let request = http::Request::post(format!("{url}/submit"))
+        .header("x-token", "abc")
+        .body("payload")
+        .unwrap();
+
+    let response = Client::new().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "post /submit http/1.1 abc payload"
+    );
+}
+
+#[tokio::test]
+async fn test_relative_uri_uses_base_url() {
+    let url = echo_server().await;
+    let client = Client::builder().base_url(&url).build();
+    let request = http::Request::get("/relative").body(()).unwrap();
+
+    let response = client.oneshot(request).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "get /relative http/1.1 - ");
+}
+
+#[tokio::test]
+async fn test_timeout_layer() {
+    let url = echo_server().await;
+    let service = ServiceBuilder::new()
+        .timeout(Duration::from_millis(200))
+        .service(Client::new());
+
+    let slow = http::Request::get(format!("{url}/slow")).body(()).unwrap();
+    let err = service.clone().oneshot(slow).await.map(drop).unwrap_err();
+    assert!(err.is::<tower::timeout::error::Elapsed>());
+
+    let fast = http::Request::get(format!("{url}/fast")).body(()).unwrap();
+    let response = service.clone().oneshot(fast).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Errors of the client pass through the layer
+    let invalid = http::Request::get("/no-base-url").body(()).unwrap();
+    let err = service.oneshot(invalid).await.map(drop).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<NetError>(),
+        Some(NetError::InvalidUrl)
+    ));
+}
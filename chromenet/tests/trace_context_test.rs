@@ -0,0 +1,70 @@
+//! W3C trace context headers on outgoing requests.
+
+mod common;
+
+use chromenet::http::TraceContext;
+use chromenet::Client;
+use common::httpbin::HttpBin;
+use serde_json::Value;
+
+const TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+async fn sent_headers(client: &Client, bin: &HttpBin) -> Value {
+    let resp = client.get(bin.url("/headers")).send().await.unwrap();
+    resp.json::<Value>().await.unwrap()["headers"].clone()
+}
+
+#[tokio::test]
+async fn test_propagator_context_is_sent() {
+    let bin = HttpBin::start().await;
+    let client = Client::builder()
+        .trace_propagator(|| {
+            TraceContext::parse(TRACEPARENT, None).map(|c| c.with_tracestate("vendor=abc"))
+        })
+        .build();
+
+    let headers = sent_headers(&client, &bin).await;
+    assert_eq!(headers["traceparent"], TRACEPARENT);
+    assert_eq!(headers["tracestate"], "vendor=abc");
+}
+
+#[tokio::test]
+async fn test_no_headers_without_context() {
+    let bin = HttpBin::start().await;
+
+    let headers = sent_headers(&Client::new(), &bin).await;
+    assert!(headers.get("traceparent").is_none());
+
+    let client = Client::builder().trace_propagator(|| None).build();
+    let headers = sent_headers(&client, &bin).await;
+    assert!(headers.get("traceparent").is_none());
+}
+
+#[tokio::test]
+async fn test_request_context_and_headers_take_precedence() {
+    let bin = HttpBin::start().await;
+    let client = Client::builder()
+        .trace_propagator(|| Some(TraceContext::random(true)))
+        .build();
+
+    let context = TraceContext::parse(TRACEPARENT, None).unwrap();
+    let resp = client
+        .get(bin.url("/headers"))
+        .trace_context(context.clone())
+        .send()
+        .await
+        .unwrap();
+    let headers = resp.json::<Value>().await.unwrap()["headers"].clone();
+    assert_eq!(headers["traceparent"], TRACEPARENT);
+
+    let manual = "00-11111111111111111111111111111111-2222222222222222-00";
+    let resp = client
+        .get(bin.url("/headers"))
+        .trace_context(context)
+        .header("traceparent", manual)
+        .send()
+        .await
+        .unwrap();
+    let headers = resp.json::<Value>().await.unwrap()["headers"].clone();
+    assert_eq!(headers["traceparent"], manual);
+}
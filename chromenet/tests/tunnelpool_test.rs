@@ -0,0 +1,211 @@
+//! Pooling of tunneled connections: sockets are grouped by proxy and
+//! CONNECT target, and tunnels are reused like direct connections.
+
+mod common;
+
+use bytes::Bytes;
+use chromenet::http::requestbody::RequestBody;
+use chromenet::http::streamfactory::{HttpStreamFactory, HttpVersionPref};
+use chromenet::socket::pool::ClientSocketPool;
+use chromenet::socket::proxy::ProxySettings;
+use common::server::read_head;
+use http::Request;
+use http_body_util::BodyExt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+/// Keep-alive server answering every request with the number of the
+/// connection it arrived on.
+async fn origin_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let id = connections.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                while read_head(&mut socket).await.is_some() {
+                    let body = id.to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    addr
+}
+
+/// HTTP proxy that records the target of every CONNECT it tunnels.
+async fn connect_proxy() -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let targets = Arc::new(Mutex::new(Vec::new()));
+    let seen = targets.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                let Some(head) = read_head(&mut client).await else {
+                    return;
+                };
+                let target = head.split_whitespace().nth(1).unwrap().to_string();
+                seen.lock().unwrap().push(target.clone());
+                let mut upstream = TcpStream::connect(&target).await.unwrap();
+                client
+                    .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+                    .await
+                    .unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+        }
+    });
+
+    (addr, targets)
+}
+
+/// Send a GET over a fresh stream and return (reused, connection number).
+async fn get(
+    factory: &HttpStreamFactory,
+    url: &Url,
+    proxy: Option<&ProxySettings>,
+) -> (bool, String) {
+    let mut stream = factory
+        .create_stream(url, proxy, None, None, None, HttpVersionPref::Auto)
+        .await
+        .unwrap();
+    let reused = stream.is_reused();
+    let request = Request::get(url.path())
+        .header("host", url.authority())
+        .body(RequestBody::Empty.into())
+        .unwrap();
+    let response = stream.send_request(request).await.unwrap();
+    let body: Bytes = match response.into_body() {
+        chromenet::http::streamfactory::StreamBody::H1(body) => {
+            body.collect().await.unwrap().to_bytes()
+        }
+        chromenet::http::streamfactory::StreamBody::H2(_) => panic!("unexpected HTTP/2"),
+    };
+    (reused, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// Wait until the connection tasks have returned `count` sockets.
+async fn wait_idle(pool: &ClientSocketPool, count: usize) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while pool.idle_socket_count() < count {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("socket was not returned to the pool");
+}
+
+#[tokio::test]
+async fn test_direct_and_tunneled_sockets_are_pooled_apart() {
+    let origin = origin_server().await;
+    let (proxy_addr, targets) = connect_proxy().await;
+    let proxy = ProxySettings::new(&format!("http://{}", proxy_addr)).unwrap();
+    let url = Url::parse(&format!("http://{}/", origin)).unwrap();
+
+    let pool = Arc::new(ClientSocketPool::new(None));
+    let factory = HttpStreamFactory::new(pool.clone());
+
+    let (reused, direct) = get(&factory, &url, None).await;
+    assert!(!reused);
+    wait_idle(&pool, 1).await;
+
+    // The idle direct socket must not carry proxied traffic
+    let (reused, tunneled) = get(&factory, &url, Some(&proxy)).await;
+    assert!(!reused);
+    assert_ne!(tunneled, direct);
+    assert_eq!(*targets.lock().unwrap(), [origin.to_string()]);
+    wait_idle(&pool, 2).await;
+
+    // Each kind of traffic picks up its own socket again
+    let (reused, again) = get(&factory, &url, Some(&proxy)).await;
+    assert!(reused);
+    assert_eq!(again, tunneled);
+    let (reused, again) = get(&factory, &url, None).await;
+    assert!(reused);
+    assert_eq!(again, direct);
+
+    // No second tunnel was needed
+    assert_eq!(targets.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_tunnels_are_pooled_per_connect_target() {
+    let first = origin_server().await;
+    let second = origin_server().await;
+    let (proxy_addr, targets) = connect_proxy().await;
+    let proxy = ProxySettings::new(&format!("http://{}", proxy_addr)).unwrap();
+    let first_url = Url::parse(&format!("http://{}/", first)).unwrap();
+    let second_url = Url::parse(&format!("http://{}/", second)).unwrap();
+
+    let pool = Arc::new(ClientSocketPool::new(None));
+    let factory = HttpStreamFactory::new(pool.clone());
+
+    let (reused, _) = get(&factory, &first_url, Some(&proxy)).await;
+    assert!(!reused);
+    wait_idle(&pool, 1).await;
+
+    // Same proxy, other target: the idle tunnel leads elsewhere
+    let (reused, _) = get(&factory, &second_url, Some(&proxy)).await;
+    assert!(!reused);
+    wait_idle(&pool, 2).await;
+
+    let (reused, _) = get(&factory, &first_url, Some(&proxy)).await;
+    assert!(reused);
+    let (reused, _) = get(&factory, &second_url, Some(&proxy)).await;
+    assert!(reused);
+
+    assert_eq!(
+        *targets.lock().unwrap(),
+        [first.to_string(), second.to_string()]
+    );
+}
+
+#[tokio::test]
+async fn test_closed_connection_is_not_pooled() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_head(&mut socket).await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                .await;
+        }
+    });
+    let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+
+    let pool = Arc::new(ClientSocketPool::new(None));
+    let factory = HttpStreamFactory::new(pool.clone());
+
+    let (_, body) = get(&factory, &url, None).await;
+    assert_eq!(body, "ok");
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while pool.total_active_count() > 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("closed socket still counted as active");
+    assert_eq!(pool.idle_socket_count(), 0);
+}
@@ -0,0 +1,285 @@
+//! WebSocket message limits, permessage-deflate and emulated handshakes
+//! against a local server.
+
+use chromenet::base::neterror::NetError;
+use chromenet::ws::{Message, PerMessageDeflate, WebSocketBuilder};
+use chromenet::{Client, Emulation};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut deflate = Compress::new(Compression::default(), false);
+    let mut out = Vec::with_capacity(data.len() + 1024);
+    deflate
+        .compress_vec(data, &mut out, FlushCompress::Sync)
+        .unwrap();
+    assert!(out.ends_with(&TRAILER));
+    out.truncate(out.len() - TRAILER.len());
+    out
+}
+
+fn frame(first: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![first];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Read a masked client frame, returning its first byte and payload.
+async fn read_frame(reader: &mut BufReader<TcpStream>) -> (u8, Vec<u8>) {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await.unwrap();
+    let len = match head[1] & 0x7f {
+        126 => reader.read_u16().await.unwrap() as usize,
+        127 => reader.read_u64().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask).await.unwrap();
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await.unwrap();
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    (head[0], payload)
+}
+
+/// Server accepting permessage-deflate and running `script` on the
+/// connection, with the request's extension offer.
+async fn deflate_server<F, Fut>(script: F) -> String
+where
+    F: FnOnce(BufReader<TcpStream>, String) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/feed", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut reader = BufReader::new(socket);
+        let mut key = String::new();
+        let mut offer = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':').unwrap_or((line, ""));
+            match name.to_ascii_lowercase().as_str() {
+                "sec-websocket-key" => key = value.trim().to_string(),
+                "sec-websocket-extensions" => offer = value.trim().to_string(),
+                _ => {}
+            }
+        }
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\
+             Sec-WebSocket-Extensions: permessage-deflate; client_no_context_takeover\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        );
+        reader
+            .get_mut()
+            .write_all(response.as_bytes())
+            .await
+            .unwrap();
+        script(reader, offer).await;
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_permessage_deflate_both_ways() {
+    let (offers, offer) = tokio::sync::oneshot::channel();
+    let url = deflate_server(|mut reader, offer| async move {
+        offers.send(offer).unwrap();
+        // A compressed text message in two fragments, with a ping between
+        let compressed = compress(b"tick tick tick tick");
+        let (a, b) = compressed.split_at(3);
+        let mut sent = frame(0x40 | 0x1, a);
+        sent.extend(frame(0x80 | 0x9, b""));
+        sent.extend(frame(0x80, b));
+        reader.get_mut().write_all(&sent).await.unwrap();
+
+        // Echo the client's message back, inflated and uncompressed
+        let (first, payload) = loop {
+            let (first, payload) = read_frame(&mut reader).await;
+            if first & 0x0f < 0x8 {
+                break (first, payload);
+            }
+        };
+        assert_eq!(first, 0x80 | 0x40 | 0x2);
+        let mut input = payload;
+        input.extend_from_slice(&TRAILER);
+        let mut data = Vec::with_capacity(1024);
+        Decompress::new(false)
+            .decompress_vec(&input, &mut data, FlushDecompress::Sync)
+            .unwrap();
+        reader
+            .get_mut()
+            .write_all(&frame(0x80 | 0x2, &data))
+            .await
+            .unwrap();
+    })
+    .await;
+
+    let ws = WebSocketBuilder::new()
+        .url(&url)
+        .unwrap()
+        .permessage_deflate(PerMessageDeflate::new())
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(offer.await.unwrap(), "permessage-deflate");
+    let accepted = ws.permessage_deflate().unwrap();
+    assert!(accepted.client_no_context_takeover());
+    assert!(!accepted.server_no_context_takeover());
+
+    assert!(matches!(ws.recv().await.unwrap(), Some(Message::Ping(_))));
+    assert!(matches!(
+        ws.recv().await.unwrap(),
+        Some(Message::Text(text)) if text == "tick tick tick tick"
+    ));
+    ws.send_binary(&b"tock tock tock tock"[..]).await.unwrap();
+    assert!(matches!(
+        ws.recv().await.unwrap(),
+        Some(Message::Binary(data)) if data == &b"tock tock tock tock"[..]
+    ));
+}
+
+#[tokio::test]
+async fn test_incompatible_deflate_response() {
+    let url = deflate_server(|_, _| async {}).await;
+    // The server did not echo server_max_window_bits
+    let ws = WebSocketBuilder::new()
+        .url(&url)
+        .unwrap()
+        .permessage_deflate(PerMessageDeflate::new().with_server_max_window_bits(10))
+        .connect()
+        .await;
+    assert!(matches!(ws, Err(NetError::WsProtocolError)));
+}
+
+#[tokio::test]
+async fn test_inflated_message_over_limit() {
+    let url = deflate_server(|mut reader, _| async move {
+        // 1 MiB of zeros, a few hundred bytes compressed
+        let compressed = compress(&vec![0u8; 1 << 20]);
+        assert!(compressed.len() < 4096);
+        let _ = reader
+            .get_mut()
+            .write_all(&frame(0x80 | 0x40 | 0x2, &compressed))
+            .await;
+        let mut rest = Vec::new();
+        let _ = reader.read_to_end(&mut rest).await;
+    })
+    .await;
+
+    let ws = WebSocketBuilder::new()
+        .url(&url)
+        .unwrap()
+        .max_message_size(64 * 1024)
+        .permessage_deflate(PerMessageDeflate::new())
+        .connect()
+        .await
+        .unwrap();
+    assert!(matches!(ws.recv().await, Err(NetError::MsgTooBig)));
+}
+
+#[tokio::test]
+async fn test_emulated_handshake() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/feed", listener.local_addr().unwrap());
+    let (requests, request) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut reader = BufReader::new(socket);
+        let mut lines = Vec::new();
+        let mut key = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Sec-WebSocket-Key: ") {
+                key = value.to_string();
+            }
+            lines.push(line);
+        }
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        );
+        reader
+            .get_mut()
+            .write_all(response.as_bytes())
+            .await
+            .unwrap();
+        requests.send(lines).unwrap();
+        reader
+            .get_mut()
+            .write_all(&frame(0x80 | 0x1, b"hi"))
+            .await
+            .unwrap();
+    });
+
+    let emulation = Emulation::builder()
+        .header(http::header::USER_AGENT, "TestAgent/1.0")
+        .header(http::header::ACCEPT_ENCODING, "gzip, deflate, br, zstd")
+        .header(http::header::ACCEPT_LANGUAGE, "de-DE,de;q=0.9")
+        .header(http::header::ACCEPT, "text/html")
+        .build();
+    let client = Client::builder().emulation(emulation).build();
+    let ws = client.websocket(&url).unwrap().connect().await.unwrap();
+
+    let lines = request.await.unwrap();
+    let names: Vec<&str> = lines[1..]
+        .iter()
+        .map(|line| line.split_once(':').unwrap().0)
+        .collect();
+    assert_eq!(lines[0], "GET /feed HTTP/1.1");
+    assert_eq!(
+        names,
+        [
+            "Host",
+            "Connection",
+            "Pragma",
+            "Cache-Control",
+            "User-Agent",
+            "Upgrade",
+            "Origin",
+            "Sec-WebSocket-Version",
+            "Accept-Encoding",
+            "Accept-Language",
+            "Sec-WebSocket-Key",
+        ]
+    );
+    assert!(lines.contains(&"User-Agent: TestAgent/1.0".to_string()));
+    assert!(lines.contains(&format!(
+        "Origin: {}",
+        url.replace("ws://", "http://").trim_end_matches("/feed")
+    )));
+
+    // Frames right behind the response are not lost
+    assert!(matches!(
+        ws.recv().await.unwrap(),
+        Some(Message::Text(text)) if text == "hi"
+    ));
+}
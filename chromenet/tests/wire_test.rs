@@ -0,0 +1,103 @@
+//! Counting and capturing the bytes of connections.
+
+mod common;
+
+use chromenet::socket::wire::{ByteCount, Direction, WireEvent, WireLayer};
+use chromenet::Client;
+use common::server::read_head;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+
+/// Keep-alive server answering every request with [`RESPONSE`]. Returns the
+/// address and the bytes of each request received.
+async fn server() -> (SocketAddr, Arc<Mutex<Vec<Vec<u8>>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                while let Some(head) = read_head(&mut socket).await {
+                    seen.lock().unwrap().push(head.into_bytes());
+                    socket.write_all(RESPONSE).await.unwrap();
+                }
+            });
+        }
+    });
+    (addr, requests)
+}
+
+#[tokio::test]
+async fn test_counts_bytes_per_request() {
+    let (addr, requests) = server().await;
+    let client = Client::builder().count_bytes(true).build();
+    let url = format!("http://{}/", addr);
+
+    let first = client.get(&url).send().await.unwrap();
+    let first_bytes = first.wire_bytes().unwrap();
+    assert_eq!(first.text().await.unwrap(), "hello");
+
+    // The second request reuses the connection without adding to the first
+    let second = client.get(&url).header("x-a", "1").send().await.unwrap();
+    let second_bytes = second.wire_bytes().unwrap();
+    second.text().await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        first_bytes.get(),
+        ByteCount {
+            sent: requests[0].len() as u64,
+            received: RESPONSE.len() as u64,
+        }
+    );
+    assert_eq!(
+        second_bytes.get(),
+        ByteCount {
+            sent: requests[1].len() as u64,
+            received: RESPONSE.len() as u64,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_captures_wire_traffic() {
+    let (addr, requests) = server().await;
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    let client = Client::builder()
+        .wire_capture(move |event: &WireEvent<'_>| {
+            seen.lock()
+                .unwrap()
+                .push((event.layer, event.direction, event.data.to_vec()));
+        })
+        .build();
+
+    let response = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.wire_bytes().is_none());
+    response.text().await.unwrap();
+
+    let events = events.lock().unwrap();
+    // Cleartext connections only have raw traffic
+    assert!(events.iter().all(|(layer, ..)| *layer == WireLayer::Raw));
+    let wire = |direction| {
+        events
+            .iter()
+            .filter(|(_, d, _)| *d == direction)
+            .flat_map(|(.., data)| data.clone())
+            .collect::<Vec<u8>>()
+    };
+    assert_eq!(wire(Direction::Sent), requests.lock().unwrap()[0]);
+    assert_eq!(wire(Direction::Received), RESPONSE);
+}